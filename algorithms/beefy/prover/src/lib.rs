@@ -228,7 +228,7 @@ where
 		u32: From<<<T as Config>::Header as Header>::Number>,
 		<T as subxt::Config>::Header: Decode,
 	{
-		let header_numbers = header_numbers.into_iter().map(From::from).collect();
+		let header_numbers = header_numbers.into_iter().map(u32::from).collect();
 		let FinalizedParaHeads { block_numbers, raw_finalized_heads: finalized_blocks } =
 			fetch_finalized_parachain_heads::<T>(
 				&self.relay_client,
@@ -38,7 +38,7 @@ pub async fn fetch_finalized_parachain_heads<T: light_client_common::config::Con
 	commitment_block_number: u32,
 	latest_beefy_height: u32,
 	para_id: u32,
-	header_numbers: &BTreeSet<<<T as Config>::Header as Header>::Number>,
+	header_numbers: &BTreeSet<u32>,
 ) -> Result<FinalizedParaHeads, Error>
 where
 	u32: From<<<T as Config>::Header as Header>::Number>,
@@ -90,8 +90,9 @@ where
 			Some(block_hash),
 		)
 		.await?;
-	let mut finalized_blocks = BTreeMap::new();
-	let mut block_numbers = vec![];
+	// the heads of the relay chain blocks the parachain header changed in, by the number of the
+	// parachain header
+	let mut included_heads = BTreeMap::new();
 
 	for changes in change_set {
 		let header = client.rpc().header(Some(changes.block)).await?.ok_or_else(|| {
@@ -115,12 +116,24 @@ where
 		let para_header: T::Header = Decode::decode(&mut &heads[&para_id][..])
 			.map_err(|_| Error::Custom(format!("Failed to decode header for {para_id}")))?;
 		let para_block_number = para_header.number();
-		// skip genesis header or any unknown headers
-		if para_block_number == Zero::zero() || !header_numbers.contains(&para_block_number) {
+		// skip genesis header
+		if para_block_number == Zero::zero() {
 			continue
 		}
 
 		let block_number = u32::from(header.number());
+		included_heads.insert(u32::from(para_block_number), (block_number, heads));
+	}
+
+	let headers_to_prove = light_client_common::included_headers_to_prove(
+		&included_heads.keys().copied().collect(),
+		header_numbers.iter().copied(),
+	);
+	let mut finalized_blocks = BTreeMap::new();
+	let mut block_numbers = vec![];
+	for (block_number, heads) in
+		headers_to_prove.into_iter().filter_map(|number| included_heads.remove(&number))
+	{
 		finalized_blocks.insert(block_number as u64, heads);
 		block_numbers.push(block_number);
 	}
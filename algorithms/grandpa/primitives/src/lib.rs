@@ -112,6 +112,32 @@ pub trait HostFunctions: light_client_common::HostFunctions + 'static {
 	fn contains_relay_header_hash(hash: <Self::Header as Header>::Hash) -> bool;
 }
 
+/// Para id of the light clients of standalone chains, which are finalized by their own GRANDPA
+/// authorities rather than by a relay chain. The headers of such a chain are its finalized blocks
+/// themselves, instead of the heads of a parachain in the state of the relay chain. No parachain
+/// is registered with this id.
+pub const STANDALONE_CHAIN_PARA_ID: u32 = 0;
+
+/// Returns `true` if the finality proof is a warp sync fragment: the justification of a block
+/// enacting an authority set change, with that block as its only header, and no parachain headers.
+///
+/// A light client whose authority set is several sets behind is brought up to date with a
+/// fragment per set change, as the warp sync of the nodes does, rather than with the proofs of all
+/// the relay chain headers in between. The justification by the current authority set is enough to
+/// prove the finality of the block, which descends from the latest finalized one, but the headers
+/// in between aren't known to the light client, so that misbehaviours in this range can't be
+/// proven.
+pub fn is_warp_fragment<H: Header + codec::Codec>(
+	finality_proof: &FinalityProof<H>,
+	parachain_headers: &BTreeMap<Hash, ParachainHeaderProofs>,
+) -> bool {
+	match &finality_proof.unknown_headers[..] {
+		[target] =>
+			parachain_headers.is_empty() && justification::find_scheduled_change(target).is_some(),
+		_ => false,
+	}
+}
+
 /// This returns the storage key for a parachain header on the relay chain.
 pub fn parachain_header_storage_key(para_id: u32) -> StorageKey {
 	let mut storage_key = frame_support::storage::storage_prefix(b"Paras", b"Heads").to_vec();
@@ -26,7 +26,7 @@ use jsonrpsee::{async_client::Client, tracing::log, ws_client::WsClientBuilder};
 use light_client_common::config::{AsInner, RuntimeStorage};
 use primitives::{
 	parachain_header_storage_key, ClientState, FinalityProof, ParachainHeaderProofs,
-	ParachainHeadersWithFinalityProof,
+	ParachainHeadersWithFinalityProof, STANDALONE_CHAIN_PARA_ID,
 };
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -35,10 +35,7 @@ use sp_core::H256;
 use sp_runtime::traits::{One, Zero};
 use std::{
 	collections::{BTreeMap, BTreeSet},
-	sync::{
-		atomic::{AtomicU32, Ordering},
-		Arc,
-	},
+	sync::Arc,
 	time::Duration,
 };
 use subxt::{config::Header, rpc::types::StorageChangeSet, Config, OnlineClient};
@@ -62,7 +59,8 @@ pub struct GrandpaProver<T: Config> {
 	pub para_client: OnlineClient<T>,
 	/// Parachain jsonrpsee client for typed rpc requests, which subxt lacks support for.
 	pub para_ws_client: Arc<Client>,
-	/// ParaId of the associated parachain
+	/// ParaId of the associated parachain, [`STANDALONE_CHAIN_PARA_ID`] for a standalone chain,
+	/// whose clients are then both connected to the chain
 	pub para_id: u32,
 	/// Delay between rpc calls to the RPC
 	pub rpc_call_delay: Duration,
@@ -202,7 +200,8 @@ where
 		})
 	}
 
-	/// Returns the latest finalized parachain header at the given finalized relay chain height.
+	/// Returns the latest finalized parachain header at the given finalized relay chain height, or
+	/// the finalized header itself for a standalone chain.
 	pub async fn query_latest_finalized_parachain_header(
 		&self,
 		latest_finalized_height: u32,
@@ -216,6 +215,15 @@ where
 			.block_hash(Some(latest_finalized_height.into()))
 			.await?
 			.ok_or_else(|| anyhow!("Block hash not found for number: {latest_finalized_height}"))?;
+		if self.para_id == STANDALONE_CHAIN_PARA_ID {
+			// the finalized block is the header of a standalone chain
+			return self
+				.relay_client
+				.rpc()
+				.header(Some(latest_finalized_hash))
+				.await?
+				.ok_or_else(|| anyhow!("Header not found for number: {latest_finalized_height}"))
+		}
 		let key = T::Storage::paras_heads(self.para_id);
 		let header = <T::Storage as RuntimeStorage>::HeadData::from_inner(
 			self.relay_client
@@ -332,98 +340,139 @@ where
 			}
 		}
 
-		// we are interested only in the blocks where our parachain header changes.
+		let standalone = self.para_id == STANDALONE_CHAIN_PARA_ID;
 		let para_storage_key = parachain_header_storage_key(self.para_id);
-		let keys = vec![para_storage_key.as_ref()];
+		// the parachain headers included in the relay chain, by number, with the relay chain
+		// blocks they are the head of the parachain in
+		let mut included_headers = BTreeMap::<u32, (T::Hash, T::Hash)>::new();
+		if standalone {
+			// the headers of a standalone chain are its finalized blocks
+			for header in unknown_headers.iter() {
+				let number = u32::from(header.number());
+				if number > previous_finalized_height {
+					let hash = T::Hash::from(header.hash());
+					included_headers.insert(number, (hash, hash));
+				}
+			}
+		} else {
+			// we are interested only in the blocks where our parachain header changes.
+			let keys = vec![para_storage_key.as_ref()];
 
-		let change_set = self
-			.relay_client
-			.rpc()
-			.query_storage(keys.clone(), start, Some(latest_finalized_hash))
-			.await?;
+			let change_set = self
+				.relay_client
+				.rpc()
+				.query_storage(keys.clone(), start, Some(latest_finalized_hash))
+				.await?;
+
+			let mut change_set_join_set: JoinSet<Result<Option<_>, anyhow::Error>> = JoinSet::new();
+			log::debug!(target:"hyperspace", "Got {} authority set changes", change_set.len());
+
+			fn clone_storage_change_sets<T: light_client_common::config::Config + Send + Sync>(
+				changes: &[StorageChangeSet<T::Hash>],
+			) -> Vec<StorageChangeSet<T::Hash>> {
+				changes
+					.iter()
+					.map(|change| StorageChangeSet {
+						block: change.block.clone(),
+						changes: change.changes.clone(),
+					})
+					.collect()
+			}
+			for changes in change_set.chunks(PROCESS_CHANGES_SET_BATCH_SIZE) {
+				for change in clone_storage_change_sets::<T>(changes) {
+					let client = self.clone();
+					let to = self.rpc_call_delay.as_millis();
+					let duration1 =
+						Duration::from_millis(rand::thread_rng().gen_range(1..to) as u64);
+					change_set_join_set.spawn(async move {
+						sleep(duration1).await;
+						let header = client
+							.relay_client
+							.rpc()
+							.header(Some(change.block))
+							.await?
+							.ok_or_else(|| anyhow!("block not found {:?}", change.block))?;
+
+						let parachain_header_bytes = {
+							let key = T::Storage::paras_heads(client.para_id);
+							let data = client
+								.relay_client
+								.storage()
+								.at(header.hash())
+								.fetch(&key)
+								.await?
+								.expect("Header exists in its own changeset; qed");
+							<T::Storage as RuntimeStorage>::HeadData::from_inner(data)
+						};
+
+						let para_header: T::Header =
+							Decode::decode(&mut parachain_header_bytes.as_ref())?;
+						// skip genesis header
+						if para_header.number() == Zero::zero() {
+							return Ok(None)
+						}
+						Ok(Some((
+							u32::from(para_header.number()),
+							(header.hash(), para_header.hash()),
+						)))
+					});
+				}
 
-		let mut change_set_join_set: JoinSet<Result<Option<_>, anyhow::Error>> = JoinSet::new();
-		let mut parachain_headers_with_proof = BTreeMap::<H256, ParachainHeaderProofs>::default();
-		log::debug!(target:"hyperspace", "Got {} authority set changes", change_set.len());
-
-		fn clone_storage_change_sets<T: light_client_common::config::Config + Send + Sync>(
-			changes: &[StorageChangeSet<T::Hash>],
-		) -> Vec<StorageChangeSet<T::Hash>> {
-			changes
-				.iter()
-				.map(|change| StorageChangeSet {
-					block: change.block.clone(),
-					changes: change.changes.clone(),
-				})
-				.collect()
+				while let Some(res) = change_set_join_set.join_next().await {
+					if let Some((number, hashes)) = res?? {
+						included_headers.insert(number, hashes);
+					}
+				}
+			}
 		}
-		let latest_para_height = Arc::new(AtomicU32::new(0u32));
-		for changes in change_set.chunks(PROCESS_CHANGES_SET_BATCH_SIZE) {
-			for change in clone_storage_change_sets::<T>(changes) {
-				let header_numbers = header_numbers.clone();
+
+		let headers_to_prove = light_client_common::included_headers_to_prove(
+			&included_headers.keys().copied().collect(),
+			header_numbers.into_iter().map(u32::from),
+		);
+		let latest_para_height = headers_to_prove.last().copied().unwrap_or_default();
+		let mut proofs_join_set: JoinSet<Result<_, anyhow::Error>> = JoinSet::new();
+		let mut parachain_headers_with_proof = BTreeMap::<H256, ParachainHeaderProofs>::default();
+		let headers_to_prove = headers_to_prove
+			.into_iter()
+			.filter_map(|number| included_headers.remove(&number))
+			.collect::<Vec<_>>();
+		for headers in headers_to_prove.chunks(PROCESS_CHANGES_SET_BATCH_SIZE) {
+			for (hash, para_hash) in headers.iter().copied() {
 				let keys = vec![para_storage_key.clone()];
 				let client = self.clone();
 				let to = self.rpc_call_delay.as_millis();
 				let duration1 = Duration::from_millis(rand::thread_rng().gen_range(1..to) as u64);
-				let latest_para_height = latest_para_height.clone();
-				change_set_join_set.spawn(async move {
+				proofs_join_set.spawn(async move {
 					sleep(duration1).await;
-					let header = client
-						.relay_client
-						.rpc()
-						.header(Some(change.block))
-						.await?
-						.ok_or_else(|| anyhow!("block not found {:?}", change.block))?;
-
-					let parachain_header_bytes = {
-						let key = T::Storage::paras_heads(client.para_id);
-						let data = client
+					// the header of a standalone chain is the finalized block itself
+					let state_proof = match standalone {
+						true => vec![],
+						false => client
 							.relay_client
-							.storage()
-							.at(header.hash())
-							.fetch(&key)
+							.rpc()
+							.read_proof(keys.iter().map(AsRef::as_ref), Some(hash))
 							.await?
-							.expect("Header exists in its own changeset; qed");
-						<T::Storage as RuntimeStorage>::HeadData::from_inner(data)
+							.proof
+							.into_iter()
+							.map(|p| p.0)
+							.collect(),
 					};
 
-					let para_header: T::Header =
-						Decode::decode(&mut parachain_header_bytes.as_ref())?;
-					let para_block_number = para_header.number();
-					// skip genesis header or any unknown headers
-					if para_block_number == Zero::zero() ||
-						!header_numbers.contains(&para_block_number)
-					{
-						return Ok(None)
-					}
-
-					let state_proof = client
-						.relay_client
-						.rpc()
-						.read_proof(keys.iter().map(AsRef::as_ref), Some(header.hash()))
-						.await?
-						.proof
-						.into_iter()
-						.map(|p| p.0)
-						.collect();
-
 					let TimeStampExtWithProof { ext: extrinsic, proof: extrinsic_proof } =
-						fetch_timestamp_extrinsic_with_proof(
-							&client.para_client,
-							Some(para_header.hash()),
-						)
-						.await
-						.map_err(|err| anyhow!("Error fetching timestamp with proof: {err:?}"))?;
+						fetch_timestamp_extrinsic_with_proof(&client.para_client, Some(para_hash))
+							.await
+							.map_err(|err| {
+								anyhow!("Error fetching timestamp with proof: {err:?}")
+							})?;
 					let proofs = ParachainHeaderProofs { state_proof, extrinsic, extrinsic_proof };
-					latest_para_height.fetch_max(u32::from(para_block_number), Ordering::SeqCst);
-					Ok(Some((H256::from(header.hash()), proofs)))
+					Ok((H256::from(hash), proofs))
 				});
 			}
 
-			while let Some(res) = change_set_join_set.join_next().await {
-				if let Some((hash, proofs)) = res?? {
-					parachain_headers_with_proof.insert(hash, proofs);
-				}
+			while let Some(res) = proofs_join_set.join_next().await {
+				let (hash, proofs) = res??;
+				parachain_headers_with_proof.insert(hash, proofs);
 			}
 		}
 
@@ -434,7 +483,7 @@ where
 		Ok(ParachainHeadersWithFinalityProof {
 			finality_proof,
 			parachain_headers: parachain_headers_with_proof,
-			latest_para_height: latest_para_height.load(Ordering::SeqCst),
+			latest_para_height,
 		})
 	}
 
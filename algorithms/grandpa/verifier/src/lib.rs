@@ -28,10 +28,10 @@ use finality_grandpa::Chain;
 use hash_db::Hasher;
 use light_client_common::state_machine;
 use primitives::{
-	error,
+	error, is_warp_fragment,
 	justification::{find_scheduled_change, AncestryChain, GrandpaJustification},
 	parachain_header_storage_key, ClientState, HostFunctions, ParachainHeaderProofs,
-	ParachainHeadersWithFinalityProof,
+	ParachainHeadersWithFinalityProof, STANDALONE_CHAIN_PARA_ID,
 };
 use sp_core::H256;
 use sp_runtime::traits::Header;
@@ -43,7 +43,9 @@ mod tests;
 /// This function verifies the GRANDPA finality proof for relay chain headers.
 ///
 /// Next, we prove the finality of parachain headers, by verifying patricia-merkle trie state proofs
-/// of these headers, stored at the recently finalized relay chain heights.
+/// of these headers, stored at the recently finalized relay chain heights. The headers of a
+/// standalone chain (see [`STANDALONE_CHAIN_PARA_ID`]) are the finalized blocks themselves. A
+/// warp sync fragment (see [`is_warp_fragment`]) only rotates the authorities.
 pub fn verify_parachain_headers_with_grandpa_finality_proof<H, Host>(
 	mut client_state: ClientState,
 	proof: ParachainHeadersWithFinalityProof<H>,
@@ -79,23 +81,31 @@ where
 
 	let from = client_state.latest_relay_hash;
 
-	let base = finality_proof
-		.unknown_headers
-		.iter()
-		.min_by_key(|h| *h.number())
-		.ok_or_else(|| anyhow!("Unknown headers can't be empty!"))?;
-
-	if base.number() < &client_state.latest_relay_height {
-		headers.ancestry(base.hash(), client_state.latest_relay_hash).map_err(|_| {
-			anyhow!(
-				"[verify_parachain_headers_with_grandpa_finality_proof] Invalid ancestry (base -> latest relay block)!"
-			)
-		})?;
-	}
+	let mut finalized = if is_warp_fragment(&finality_proof, &parachain_headers) {
+		// the ancestry of the block isn't proven, only its finality
+		if *target.number() <= client_state.latest_relay_height {
+			Err(anyhow!("Warp sync fragment should be above the latest relay block"))?;
+		}
+		vec![target.hash()]
+	} else {
+		let base = finality_proof
+			.unknown_headers
+			.iter()
+			.min_by_key(|h| *h.number())
+			.ok_or_else(|| anyhow!("Unknown headers can't be empty!"))?;
+
+		if base.number() < &client_state.latest_relay_height {
+			headers.ancestry(base.hash(), client_state.latest_relay_hash).map_err(|_| {
+				anyhow!(
+					"[verify_parachain_headers_with_grandpa_finality_proof] Invalid ancestry (base -> latest relay block)!"
+				)
+			})?;
+		}
 
-	let mut finalized = headers.ancestry(from, target.hash()).map_err(|_| {
-		anyhow!("[verify_parachain_headers_with_grandpa_finality_proof] Invalid ancestry!")
-	})?;
+		headers.ancestry(from, target.hash()).map_err(|_| {
+			anyhow!("[verify_parachain_headers_with_grandpa_finality_proof] Invalid ancestry!")
+		})?
+	};
 	finalized.sort();
 
 	// 2. verify justification.
@@ -112,19 +122,26 @@ where
 			headers.header(&hash).expect("Headers have been checked by AncestryChain; qed");
 
 		let ParachainHeaderProofs { extrinsic_proof, extrinsic, state_proof } = proofs;
-		let proof = StorageProof::new(state_proof);
-		let key = parachain_header_storage_key(client_state.para_id);
-		// verify patricia-merkle state proofs
-		let header = state_machine::read_proof_check::<Host::BlakeTwo256, _>(
-			relay_chain_header.state_root(),
-			proof,
-			&[key.as_ref()],
-		)
-		.map_err(|err| anyhow!("error verifying parachain header state proof: {err}"))?
-		.remove(key.as_ref())
-		.flatten()
-		.ok_or_else(|| anyhow!("Invalid proof, parachain header not found"))?;
-		let parachain_header = H::decode(&mut &header[..])?;
+		let parachain_header = if client_state.para_id == STANDALONE_CHAIN_PARA_ID {
+			if !state_proof.is_empty() {
+				Err(anyhow!("Unexpected state proof for the header of a standalone chain"))?;
+			}
+			relay_chain_header.clone()
+		} else {
+			let proof = StorageProof::new(state_proof);
+			let key = parachain_header_storage_key(client_state.para_id);
+			// verify patricia-merkle state proofs
+			let header = state_machine::read_proof_check::<Host::BlakeTwo256, _>(
+				relay_chain_header.state_root(),
+				proof,
+				&[key.as_ref()],
+			)
+			.map_err(|err| anyhow!("error verifying parachain header state proof: {err}"))?
+			.remove(key.as_ref())
+			.flatten()
+			.ok_or_else(|| anyhow!("Invalid proof, parachain header not found"))?;
+			H::decode(&mut &header[..])?
+		};
 		para_heights.push(parachain_header.number().clone().into());
 		// Timestamp extrinsic should be the first inherent and hence the first extrinsic
 		// https://github.com/paritytech/substrate/blob/d602397a0bbb24b5d627795b797259a44a5e29e9/primitives/trie/src/lib.rs#L99-L101
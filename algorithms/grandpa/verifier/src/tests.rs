@@ -15,6 +15,7 @@
 
 use crate::verify_parachain_headers_with_grandpa_finality_proof;
 use codec::{Decode, Encode};
+use finality_grandpa::{Commit, Message, Precommit, SignedPrecommit};
 use futures::StreamExt;
 use grandpa_prover::{
 	beefy_prover::helpers::unsafe_arc_cast, host_functions::HostFunctionsProvider, GrandpaProver,
@@ -22,10 +23,13 @@ use grandpa_prover::{
 use hyperspace_core::substrate::DefaultConfig as PolkadotConfig;
 use polkadot_core_primitives::Header;
 use primitives::{
-	justification::GrandpaJustification, FinalityProof, ParachainHeadersWithFinalityProof,
+	is_warp_fragment, justification::GrandpaJustification, ClientState, FinalityProof,
+	ParachainHeaderProofs, ParachainHeadersWithFinalityProof,
 };
 use serde::{Deserialize, Serialize};
-use sp_core::H256;
+use sp_consensus_grandpa::{AuthorityList, ConsensusLog, ScheduledChange, GRANDPA_ENGINE_ID};
+use sp_core::{ed25519, Pair, H256};
+use sp_runtime::{generic::DigestItem, traits::Header as _};
 use std::time::Duration;
 use subxt::{
 	config::substrate::{BlakeTwo256, SubstrateHeader},
@@ -151,3 +155,129 @@ async fn follow_grandpa_justifications() {
 		println!("========= Successfully verified grandpa justification =========");
 	}
 }
+
+/// The three voters of an authority set.
+fn voters(set: u8) -> Vec<ed25519::Pair> {
+	(0..3).map(|i| ed25519::Pair::from_seed(&[set * 3 + i + 1; 32])).collect()
+}
+
+fn authority_list(voters: &[ed25519::Pair]) -> AuthorityList {
+	voters.iter().map(|pair| (pair.public().into(), 1)).collect()
+}
+
+fn client_state() -> ClientState {
+	ClientState {
+		current_authorities: authority_list(&voters(0)),
+		current_set_id: 5,
+		latest_relay_height: 100,
+		latest_para_height: 10,
+		latest_relay_hash: H256::repeat_byte(2),
+		para_id: 2000,
+	}
+}
+
+/// A warp sync fragment of the block `number`, enacting the authority set of `next`, justified
+/// by `voters` as the authority set `set_id`.
+fn warp_fragment(
+	number: u32,
+	voters: &[ed25519::Pair],
+	set_id: u64,
+	next: &[ed25519::Pair],
+) -> ParachainHeadersWithFinalityProof<Header> {
+	// the parent isn't known to the light client
+	let mut header = Header::new(
+		number,
+		Default::default(),
+		Default::default(),
+		H256::repeat_byte(1),
+		Default::default(),
+	);
+	let change = ScheduledChange { next_authorities: authority_list(next), delay: 0 };
+	header.digest.push(DigestItem::Consensus(
+		GRANDPA_ENGINE_ID,
+		ConsensusLog::ScheduledChange(change).encode(),
+	));
+
+	let round = 1u64;
+	let precommit = Precommit { target_hash: header.hash(), target_number: number };
+	let precommits = voters
+		.iter()
+		.map(|pair| {
+			let message = Message::<H256, u32>::Precommit(precommit.clone());
+			SignedPrecommit {
+				precommit: precommit.clone(),
+				signature: pair.sign(&(message, round, set_id).encode()).into(),
+				id: pair.public().into(),
+			}
+		})
+		.collect();
+	let justification = Justification {
+		round,
+		commit: Commit { target_hash: header.hash(), target_number: number, precommits },
+		votes_ancestries: vec![],
+	};
+
+	ParachainHeadersWithFinalityProof {
+		finality_proof: FinalityProof {
+			block: header.hash(),
+			justification: justification.encode(),
+			unknown_headers: vec![header],
+		},
+		parachain_headers: Default::default(),
+		latest_para_height: 10,
+	}
+}
+
+fn verify(
+	client_state: ClientState,
+	proof: ParachainHeadersWithFinalityProof<Header>,
+) -> Result<ClientState, primitives::error::Error> {
+	verify_parachain_headers_with_grandpa_finality_proof::<Header, HostFunctionsProvider>(
+		client_state,
+		proof,
+	)
+}
+
+#[test]
+fn accepts_a_warp_sync_fragment() {
+	let proof = warp_fragment(150, &voters(0), 5, &voters(1));
+	assert!(is_warp_fragment(&proof.finality_proof, &proof.parachain_headers));
+	let block = proof.finality_proof.block;
+
+	let client_state = verify(client_state(), proof).expect("the fragment is valid");
+	assert_eq!(client_state.latest_relay_height, 150);
+	assert_eq!(client_state.latest_relay_hash, block);
+	assert_eq!(client_state.latest_para_height, 10);
+	// the authority set is rotated
+	assert_eq!(client_state.current_set_id, 6);
+	assert_eq!(client_state.current_authorities, authority_list(&voters(1)));
+}
+
+#[test]
+fn rejects_a_warp_sync_fragment_at_or_below_the_latest_relay_block() {
+	for number in [99, 100] {
+		let proof = warp_fragment(number, &voters(0), 5, &voters(1));
+		assert!(verify(client_state(), proof).is_err());
+	}
+}
+
+#[test]
+fn rejects_a_warp_sync_fragment_with_parachain_headers() {
+	let mut proof = warp_fragment(150, &voters(0), 5, &voters(1));
+	let proofs =
+		ParachainHeaderProofs { state_proof: vec![], extrinsic: vec![], extrinsic_proof: vec![] };
+	proof.parachain_headers.insert(proof.finality_proof.block, proofs);
+	// the ancestry of the block must then be proven
+	assert!(!is_warp_fragment(&proof.finality_proof, &proof.parachain_headers));
+	assert!(verify(client_state(), proof).is_err());
+}
+
+#[test]
+fn rejects_a_warp_sync_fragment_justified_by_another_authority_set() {
+	// the current authorities, signing as the previous set
+	let proof = warp_fragment(150, &voters(0), 4, &voters(1));
+	assert!(verify(client_state(), proof).is_err());
+	// the next authorities, before the client rotated to them
+	let proof = warp_fragment(150, &voters(1), 6, &voters(2));
+	assert!(verify(client_state(), proof).is_err());
+}
@@ -378,6 +378,25 @@ pub trait IbcHandler<AccountId> {
 	fn create_connection(client_id: ClientId, connection_id: ConnectionId) -> Result<(), Error>;
 }
 
+/// Hook called by the ibc pallet whenever a client is successfully updated
+pub trait OnClientUpdate<AccountId> {
+	/// Called with the updated client, the new consensus height and the account that submitted
+	/// the update
+	fn on_client_update(client_id: &ClientId, consensus_height: Height, updater: &AccountId);
+
+	/// Weight of a call to [`OnClientUpdate::on_client_update`], added to the weight of every
+	/// client update delivered to the pallet
+	fn weight() -> Weight;
+}
+
+impl<AccountId> OnClientUpdate<AccountId> for () {
+	fn on_client_update(_client_id: &ClientId, _consensus_height: Height, _updater: &AccountId) {}
+
+	fn weight() -> Weight {
+		Weight::zero()
+	}
+}
+
 /// Callback Weight
 /// This trait must be implemented by module callback handlers to be able to estimate the weight
 /// of the callback function.
@@ -726,7 +726,7 @@ benchmarks! {
 			memo: "".to_string()
 		};
 		let data = serde_json::to_vec(&packet_data).unwrap();
-		let mut ack = TransferAck::success().to_string().into_bytes();
+		let mut ack = TransferAck::success().to_canonical_bytes();
 		let (cs_state, value) = create_ack_packet::<T>(data, ack);
 		ctx.store_consensus_state(client_id, Height::new(0, 2), AnyConsensusState::Tendermint(cs_state)).unwrap();
 		let msg = Any {
@@ -1162,7 +1162,7 @@ benchmarks! {
 		 let mut handler = IbcModule::<T>::default();
 		 let mut output = HandlerOutputBuilder::new();
 		 let signer = Signer::from_str("relayer").unwrap();
-		 let ack: Acknowledgement = TransferAck::Error(ACK_ERR_STR.to_string()).to_string().into_bytes().into();
+		 let ack: Acknowledgement = TransferAck::Error(ACK_ERR_STR.to_string()).to_canonical_bytes().into();
 	}:{
 		let ctx = routing::Context::<T>::new();
 		handler.on_acknowledgement_packet(&ctx, &mut output, &mut packet, &ack, &signer).unwrap();
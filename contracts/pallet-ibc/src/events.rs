@@ -434,6 +434,12 @@ impl From<RawIbcEvent> for IbcEvent {
 			},
 			RawIbcEvent::Empty(_) => IbcEvent::Empty,
 			RawIbcEvent::ChainError(_) => IbcEvent::ChainError,
+			// the channel upgrades aren't supported by the pallet, so it never emits these
+			RawIbcEvent::UpgradeInitChannel(_) |
+			RawIbcEvent::UpgradeTryChannel(_) |
+			RawIbcEvent::UpgradeAckChannel(_) |
+			RawIbcEvent::UpgradeConfirmChannel(_) |
+			RawIbcEvent::UpgradeOpenChannel(_) => IbcEvent::Empty,
 			RawIbcEvent::AppModule(ev) => IbcEvent::AppModule {
 				kind: ev.kind.as_bytes().to_vec(),
 				module_id: ev.module_name.to_string().as_bytes().to_vec(),
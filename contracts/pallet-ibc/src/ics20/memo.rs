@@ -1,9 +1,6 @@
 use crate::{ics20::HandleMemo, Config};
-use alloc::{
-	format,
-	string::{String, ToString},
-};
-use core::{fmt::Debug, str::FromStr};
+use alloc::{format, string::ToString};
+use core::fmt::Debug;
 use ibc::{
 	applications::transfer::{
 		acknowledgement::Acknowledgement as Ics20Acknowledgement, error::Error as Ics20Error,
@@ -160,7 +157,7 @@ impl<T: Config + Send + Sync, S: Module + Clone + Default + PartialEq + Eq + Deb
 		relayer: &Signer,
 	) -> Result<Acknowledgement, Error> {
 		let ack = self.inner.on_recv_packet(ctx, output, packet, relayer)?;
-		let ics20_ack = Ics20Acknowledgement::from_str(&String::from_utf8_lossy(ack.as_ref()))
+		let ics20_ack = Ics20Acknowledgement::from_bytes(ack.as_ref())
 			.map_err(|_| Error::invalid_acknowledgement())?;
 
 		// we need to ensure that the previous hasn't failed. We do this, by ensuring that the ACK
@@ -180,8 +177,7 @@ impl<T: Config + Send + Sync, S: Module + Clone + Default + PartialEq + Eq + Deb
 					Ics20Acknowledgement::from_error(Ics20Error::implementation_specific(
 						err.to_string(),
 					))
-					.to_string()
-					.into_bytes(),
+					.to_canonical_bytes(),
 				))
 			},
 		}
@@ -242,8 +242,7 @@ where
 			Err(err) => {
 				log::trace!(target: "pallet_ibc", "Acknowledgement error: {:?}", err);
 				let ack = Ics20Acknowledgement::Error(format!("{ACK_ERR_STR}: {err:?}"))
-					.to_string()
-					.into_bytes();
+					.to_canonical_bytes();
 				Pallet::<T>::handle_message(HandlerMessage::WriteAck {
 					packet: packet.clone(),
 					ack: ack.clone(),
@@ -276,12 +275,12 @@ where
 				let packet = packet.clone();
 				Pallet::<T>::handle_message(HandlerMessage::WriteAck {
 					packet,
-					ack: Ics20Acknowledgement::success().to_string().into_bytes(),
+					ack: Ics20Acknowledgement::success().to_canonical_bytes(),
 				})
 				.map_err(|e| {
 					Ics04Error::implementation_specific(format!("[on_recv_packet] {e:#?}"))
 				})?;
-				Ics20Acknowledgement::success().to_string().into_bytes()
+				Ics20Acknowledgement::success().to_canonical_bytes()
 			},
 		};
 		Ok(Acknowledgement::from_bytes(ack))
@@ -300,12 +299,11 @@ where
 			serde_json::from_slice(packet.data.as_slice()).map_err(|e| {
 				Ics04Error::implementation_specific(format!("Failed to decode packet data {e:?}"))
 			})?;
-		let ack = serde_json::from_slice::<Ics20Acknowledgement>(acknowledgement.as_ref())
-			.map_err(|e| {
-				Ics04Error::implementation_specific(format!(
-					"Failed to decode acknowledgement data {e:?}"
-				))
-			})?;
+		let ack = Ics20Acknowledgement::from_bytes(acknowledgement.as_ref()).map_err(|e| {
+			Ics04Error::implementation_specific(format!(
+				"Failed to decode acknowledgement data {e:?}"
+			))
+		})?;
 		let sequence: u64 = packet.sequence.into();
 		process_ack_packet(&mut ctx, packet, &packet_data, &ack)
 			.map_err(|e| Ics04Error::implementation_specific(e.to_string()))?;
@@ -1,9 +1,6 @@
 use crate::{routing::Context, DenomToAssetId};
-use alloc::{
-	format,
-	string::{String, ToString},
-};
-use core::{fmt::Debug, marker::PhantomData, str::FromStr};
+use alloc::{format, string::ToString};
+use core::{fmt::Debug, marker::PhantomData};
 use ibc::{
 	applications::transfer::{
 		acknowledgement::Acknowledgement as Ics20Ack, context::BankKeeper,
@@ -358,19 +355,9 @@ where
 		}
 
 		let percent = ServiceChargeIn::<T>::get().unwrap_or(T::ServiceChargeIn::get());
-		let parsed_ack = String::from_utf8(ack.clone().into_bytes())
-			.map_err(|e| {
-				Ics04Error::implementation_specific(format!(
-					"Failed to decode acknowledgement {e:?}"
-				))
-			})
-			.and_then(|x: String| {
-				Ics20Ack::from_str(&x).map_err(|e| {
-					Ics04Error::implementation_specific(format!(
-						"Failed to decode acknowledgement {e:?}"
-					))
-				})
-			})?;
+		let parsed_ack = Ics20Ack::from_bytes(ack.as_ref()).map_err(|e| {
+			Ics04Error::implementation_specific(format!("Failed to decode acknowledgement {e:?}"))
+		})?;
 		// Send full amount to receiver using the default ics20 logic
 		// We only take the fee charge if the acknowledgement is not an error
 		if parsed_ack.is_successful() {
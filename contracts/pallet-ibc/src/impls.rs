@@ -41,6 +41,7 @@ use ibc::{
 		},
 		ics26_routing::handler::MsgReceipt,
 	},
+	events::IbcEvent,
 	handler::HandlerOutputBuilder,
 	signer::Signer,
 	timestamp::Timestamp,
@@ -51,9 +52,9 @@ use ibc_primitives::{
 	apply_prefix, channel_id_from_bytes, client_id_from_bytes, connection_id_from_bytes,
 	get_channel_escrow_address, port_id_from_bytes, runtime_interface, ConnectionHandshake,
 	Error as IbcHandlerError, HandlerMessage, IbcHandler, IdentifiedChannel, IdentifiedClientState,
-	IdentifiedConnection, PacketInfo, PacketState, QueryChannelResponse, QueryChannelsResponse,
-	QueryClientStateResponse, QueryConnectionResponse, QueryConnectionsResponse,
-	QueryConsensusStateResponse, QueryNextSequenceReceiveResponse,
+	IdentifiedConnection, OnClientUpdate, PacketInfo, PacketState, QueryChannelResponse,
+	QueryChannelsResponse, QueryClientStateResponse, QueryConnectionResponse,
+	QueryConnectionsResponse, QueryConsensusStateResponse, QueryNextSequenceReceiveResponse,
 	QueryPacketAcknowledgementResponse, QueryPacketAcknowledgementsResponse,
 	QueryPacketCommitmentResponse, QueryPacketCommitmentsResponse, QueryPacketReceiptResponse,
 	Timeout,
@@ -78,11 +79,21 @@ where
 	pub(crate) fn execute_ibc_messages(
 		ctx: &mut Context<T>,
 		messages: Vec<ibc_proto::google::protobuf::Any>,
+		sender: &<T as frame_system::Config>::AccountId,
 	) {
 		let (events, logs) =
 			messages.into_iter().fold((vec![], vec![]), |(mut events, mut logs), msg| {
 				match ibc::core::ics26_routing::handler::deliver(ctx, msg) {
 					Ok(MsgReceipt { events: temp_events, log: temp_logs }) => {
+						for event in &temp_events {
+							if let IbcEvent::UpdateClient(update) = event {
+								T::OnClientUpdate::on_client_update(
+									update.client_id(),
+									update.consensus_height(),
+									sender,
+								);
+							}
+						}
 						events.extend(temp_events.into_iter().map(Ok));
 						logs.extend(temp_logs);
 					},
@@ -278,6 +278,8 @@ pub mod pallet {
 			+ ValidateMemo;
 
 		type SubstrateMultihopXcmHandler: SubstrateMultihopXcmHandler<AccountId = Self::AccountId>;
+		/// Hook called on every successful client update, e.g. to record relayer telemetry
+		type OnClientUpdate: ibc_primitives::OnClientUpdate<Self::AccountId>;
 
 		type IsSendEnabled: Get<bool>;
 		type IsReceiveEnabled: Get<bool>;
@@ -801,7 +803,7 @@ pub mod pallet {
 					<T as frame_system::Config>::AccountId,
 				>>::reserve(&sender, reserve_amt)?;
 			}
-			Self::execute_ibc_messages(&mut ctx, messages);
+			Self::execute_ibc_messages(&mut ctx, messages, &sender);
 
 			Ok(())
 		}
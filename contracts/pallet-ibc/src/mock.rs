@@ -296,6 +296,13 @@ impl Config for Test {
 	type FlatFeeAssetId = FlatFeeAssetId;
 	type FlatFeeAmount = FlatFeeAmount;
 	type SubstrateMultihopXcmHandler = SubstrateMultihopXcmHandlerNone<Test>;
+	type OnClientUpdate = IbcTelemetry;
+}
+
+impl pallet_ibc_telemetry::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type TimeProvider = Timestamp;
+	type WeightInfo = ();
 }
 
 #[derive(Debug, Clone)]
@@ -481,6 +488,7 @@ frame_support::construct_runtime!(
 		IbcPing: pallet_ibc_ping,
 		Ics20Fee: crate::ics20_fee,
 		Ibc: pallet_ibc,
+		IbcTelemetry: pallet_ibc_telemetry,
 		Aura: pallet_aura,
 		Membership: pallet_membership::<Instance2>,
 	}
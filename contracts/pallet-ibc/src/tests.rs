@@ -1,6 +1,6 @@
 use crate::{
 	ics20_fee::FlatFeeConverter,
-	light_clients::{AnyClientState, AnyConsensusState},
+	light_clients::{AnyClientMessage, AnyClientState, AnyConsensusState},
 	mock::*,
 	routing::Context,
 	Any, Config, ConsensusHeights, DenomToAssetId, Event, MultiAddress, Pallet,
@@ -25,7 +25,10 @@ use ibc::{
 			client_state::ClientState,
 			context::{ClientKeeper, ClientReader},
 			height::Height,
-			msgs::create_client::{MsgCreateAnyClient, TYPE_URL},
+			msgs::{
+				create_client::{MsgCreateAnyClient, TYPE_URL},
+				update_client::{MsgUpdateAnyClient, TYPE_URL as UPDATE_CLIENT_TYPE_URL},
+			},
 		},
 		ics03_connection::{
 			connection::{ConnectionEnd, Counterparty, State as ConnState},
@@ -54,7 +57,7 @@ use ibc::{
 	signer::Signer,
 	tx_msg::Msg,
 };
-use ibc_primitives::{get_channel_escrow_address, HandlerMessage, IbcHandler};
+use ibc_primitives::{get_channel_escrow_address, HandlerMessage, IbcHandler, OnClientUpdate};
 use sp_core::Pair;
 use sp_runtime::{
 	traits::{Bounded, IdentifyAccount},
@@ -1310,3 +1313,72 @@ fn test_next_and_previous_consensus_state_for_other_client_types() {
 		assert!(ctx.next_consensus_state(&client_id, Height::new(0, 400)).unwrap().is_some());
 	})
 }
+
+#[test]
+fn client_updates_are_recorded_by_the_telemetry_hook() {
+	new_test_ext().execute_with(|| {
+		let mock_client_state =
+			MockClientState::new(MockClientMessage::from(MockHeader::new(Height::new(0, 1))));
+		let mock_cs_state = MockConsensusState::new(MockHeader::new(Height::new(0, 1)));
+		let client_id = ClientId::new(&mock_client_state.client_type(), 0).unwrap();
+		let msg = MsgCreateAnyClient::<Context<Test>>::new(
+			AnyClientState::Mock(mock_client_state),
+			AnyConsensusState::Mock(mock_cs_state),
+			Signer::from_str(MODULE_ID).unwrap(),
+		)
+		.unwrap()
+		.encode_vec()
+		.unwrap();
+		let msg = Any { type_url: TYPE_URL.to_string(), value: msg };
+		assert_ok!(Ibc::deliver(RuntimeOrigin::signed(AccountId32::new([0; 32])), vec![msg]));
+		// creating the client doesn't count as an update
+		assert!(IbcTelemetry::client_update(client_id.as_bytes().to_vec()).is_none());
+
+		frame_system::Pallet::<Test>::set_block_number(2u32);
+		Timestamp::set_timestamp(1_000);
+		let updater = AccountId32::new([1; 32]);
+		let msg = MsgUpdateAnyClient::<Context<Test>> {
+			client_id: client_id.clone(),
+			client_message: AnyClientMessage::Mock(MockClientMessage::from(MockHeader::new(
+				Height::new(0, 2),
+			))),
+			signer: Signer::from_str(MODULE_ID).unwrap(),
+		}
+		.encode_vec()
+		.unwrap();
+		let msg = Any { type_url: UPDATE_CLIENT_TYPE_URL.to_string(), value: msg };
+		assert_ok!(Ibc::deliver(RuntimeOrigin::signed(updater.clone()), vec![msg]));
+
+		let record = IbcTelemetry::client_update(client_id.as_bytes().to_vec()).unwrap();
+		assert_eq!(record.updater, updater);
+		assert_eq!(record.timestamp, 1_000);
+		assert_eq!(record.host_height, 2);
+		assert_eq!((record.revision_number, record.revision_height), (0, 2));
+		System::assert_has_event(
+			pallet_ibc_telemetry::Event::<Test>::ClientUpdated {
+				client_id: client_id.as_bytes().to_vec(),
+				updater,
+				revision_number: 0,
+				revision_height: 2,
+			}
+			.into(),
+		);
+	})
+}
+
+#[test]
+fn deliver_weight_includes_the_client_update_hook() {
+	new_test_ext().execute_with(|| {
+		let hook_weight = <IbcTelemetry as OnClientUpdate<AccountId32>>::weight();
+		assert_ne!(hook_weight, Weight::zero());
+
+		// the messages don't decode, only the weight of the hook is counted
+		let update = Any { type_url: UPDATE_CLIENT_TYPE_URL.to_string(), value: vec![] };
+		let create_client = Any { type_url: TYPE_URL.to_string(), value: vec![] };
+		assert_eq!(crate::weight::deliver::<Test>(&[create_client.clone()]), Weight::zero());
+		assert_eq!(
+			crate::weight::deliver::<Test>(&[update.clone(), create_client, update]),
+			hook_weight.saturating_mul(2)
+		);
+	})
+}
@@ -4,13 +4,13 @@ use core::marker::PhantomData;
 use frame_support::{pallet_prelude::Weight, weights::constants::WEIGHT_REF_TIME_PER_MILLIS};
 use grandpa_client_primitives::justification::GrandpaJustification;
 use ibc::core::{
-	ics02_client::msgs::ClientMsg,
+	ics02_client::msgs::{update_client::TYPE_URL as UPDATE_CLIENT_TYPE_URL, ClientMsg},
 	ics03_connection::{context::ConnectionReader, msgs::ConnectionMsg},
 	ics04_channel::msgs::{ChannelMsg, PacketMsg},
 	ics24_host::identifier::ClientId,
 	ics26_routing::msgs::Ics26Envelope,
 };
-use ibc_primitives::{client_id_from_bytes, CallbackWeight};
+use ibc_primitives::{client_id_from_bytes, CallbackWeight, OnClientUpdate};
 use ics10_grandpa::client_message::{ClientMessage, RelayChainHeader};
 use scale_info::prelude::string::ToString;
 
@@ -184,6 +184,10 @@ pub(crate) fn deliver<T: Config + Send + Sync>(msgs: &[Any]) -> Weight
 where
 	u32: From<<T as frame_system::Config>::BlockNumber>,
 {
+	// every client update also runs the `OnClientUpdate` hook, whatever the client type
+	let client_updates = msgs.iter().filter(|msg| msg.type_url == UPDATE_CLIENT_TYPE_URL).count();
+	let hooks = <T::OnClientUpdate as OnClientUpdate<T::AccountId>>::weight()
+		.saturating_mul(client_updates as u64);
 	msgs.iter()
 		.filter_map(|msg| {
 			let type_url = msg.type_url.clone();
@@ -572,4 +576,5 @@ where
 			};
 			acc.saturating_add(temp)
 		})
+		.saturating_add(hooks)
 }
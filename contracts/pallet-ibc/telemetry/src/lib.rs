@@ -0,0 +1,147 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Companion pallet for `pallet-ibc` that records, per client, the last time it was updated, the
+//! account that submitted the update and the consensus height it was updated to. Counterparty
+//! chains and explorers can use it to audit relayer liveness directly from chain state.
+//!
+//! To use it, set `type OnClientUpdate = IbcTelemetry;` in the `pallet_ibc::Config` of the
+//! runtime and implement [`runtime_api::IbcTelemetryRuntimeApi`].
+
+use frame_support::{
+	traits::{Get, UnixTime},
+	weights::{constants::RocksDbWeight, Weight},
+};
+use ibc::{core::ics24_host::identifier::ClientId, Height};
+use ibc_primitives::OnClientUpdate;
+use sp_std::prelude::*;
+// Re-export pallet items so that they can be accessed from the crate namespace.
+pub use pallet::*;
+
+/// Weights of the pallet
+pub trait WeightInfo {
+	/// Weight of recording an update of a client
+	fn on_client_update() -> Weight;
+}
+
+impl WeightInfo for () {
+	fn on_client_update() -> Weight {
+		// reads the timestamp, writes the record and the event
+		RocksDbWeight::get().reads_writes(1, 2)
+	}
+}
+
+/// The last update of a client
+#[derive(
+	Clone,
+	PartialEq,
+	Eq,
+	codec::Encode,
+	codec::Decode,
+	frame_support::RuntimeDebug,
+	scale_info::TypeInfo,
+)]
+pub struct ClientUpdateRecord<AccountId, BlockNumber> {
+	/// Account that submitted the update
+	pub updater: AccountId,
+	/// Host timestamp of the update in milliseconds
+	pub timestamp: u64,
+	/// Host block number of the update
+	pub host_height: BlockNumber,
+	/// Revision number of the consensus height the client was updated to
+	pub revision_number: u64,
+	/// Revision height of the consensus height the client was updated to
+	pub revision_height: u64,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Source of the host timestamp
+		type TimeProvider: UnixTime;
+
+		/// Weights of the pallet
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::pallet]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	#[pallet::storage]
+	#[pallet::getter(fn client_update)]
+	/// client_id => last update of the client
+	pub type ClientUpdates<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		Vec<u8>,
+		ClientUpdateRecord<T::AccountId, T::BlockNumber>,
+		OptionQuery,
+	>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A client was updated
+		ClientUpdated {
+			client_id: Vec<u8>,
+			updater: T::AccountId,
+			revision_number: u64,
+			revision_height: u64,
+		},
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Returns the last updates of all the clients
+	pub fn client_updates() -> Vec<(Vec<u8>, ClientUpdateRecord<T::AccountId, T::BlockNumber>)> {
+		ClientUpdates::<T>::iter().collect()
+	}
+}
+
+impl<T: Config> OnClientUpdate<T::AccountId> for Pallet<T> {
+	fn on_client_update(client_id: &ClientId, consensus_height: Height, updater: &T::AccountId) {
+		let client_id = client_id.as_bytes().to_vec();
+		log::trace!(target: "pallet_ibc_telemetry", "[on_client_update] >> client {:?} updated to {:?}", client_id, consensus_height);
+		let record = ClientUpdateRecord {
+			updater: updater.clone(),
+			timestamp: T::TimeProvider::now().as_millis() as u64,
+			host_height: frame_system::Pallet::<T>::block_number(),
+			revision_number: consensus_height.revision_number,
+			revision_height: consensus_height.revision_height,
+		};
+		ClientUpdates::<T>::insert(&client_id, record);
+		Self::deposit_event(Event::<T>::ClientUpdated {
+			client_id,
+			updater: updater.clone(),
+			revision_number: consensus_height.revision_number,
+			revision_height: consensus_height.revision_height,
+		});
+	}
+
+	fn weight() -> Weight {
+		T::WeightInfo::on_client_update()
+	}
+}
+
+pub mod runtime_api {
+	use super::ClientUpdateRecord;
+	use sp_std::prelude::*;
+
+	sp_api::decl_runtime_apis! {
+		/// IBC client telemetry Runtime Apis
+		pub trait IbcTelemetryRuntimeApi<AccountId: codec::Codec, BlockNumber: codec::Codec> {
+			/// Returns the last update of the given client
+			fn client_update(client_id: Vec<u8>) -> Option<ClientUpdateRecord<AccountId, BlockNumber>>;
+
+			/// Returns the last updates of all the clients
+			fn client_updates() -> Vec<(Vec<u8>, ClientUpdateRecord<AccountId, BlockNumber>)>;
+		}
+	}
+}
@@ -0,0 +1,273 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Periodic audit of the packet sequences of the relayed channels.
+//!
+//! Each audit queries the sequences of the packets and acknowledgements that are still
+//! undelivered on both ends of the whitelisted channels, using the next sequence to receive on
+//! ordered channels. A sequence that was already undelivered at the previous audit fell through
+//! the cracks of the relayer loop, e.g. because its submission was recorded in the state store but
+//! never landed. Such gaps are counted in the metrics, forgotten by the state store and relayed
+//! again by a packet clearing.
+
+use crate::{clearing::Clearing, protocol::MessagingProtocol};
+use anyhow::anyhow;
+use ibc::{
+	core::{
+		ics04_channel::channel::ChannelEnd,
+		ics24_host::identifier::{ChannelId, PortId},
+	},
+	Height,
+};
+use metrics::handler::MetricsHandler;
+use primitives::{query_undelivered_acks, query_undelivered_sequences, Chain, MessageKind};
+use std::{
+	collections::{BTreeSet, HashMap},
+	time::Duration,
+};
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// Default interval between two sequence audits.
+pub const DEFAULT_AUDIT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Sequences found undelivered on a channel by an audit.
+#[derive(Debug, Clone, Default)]
+struct Undelivered {
+	packets: BTreeSet<u64>,
+	acknowledgements: BTreeSet<u64>,
+}
+
+/// State of the periodic sequence audit of the relayer loop.
+pub(crate) struct SequenceAudit {
+	interval: Option<Interval>,
+	/// Sequences found undelivered by the last audit, per source chain and channel
+	last: HashMap<(String, ChannelId, PortId), Undelivered>,
+}
+
+impl SequenceAudit {
+	/// Audits the channels every `interval`, the first audit runs after one interval.
+	pub(crate) fn new(interval: Option<Duration>) -> Self {
+		let interval = interval.map(|period| {
+			let mut interval =
+				tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+			interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+			interval
+		});
+		Self { interval, last: HashMap::new() }
+	}
+
+	/// Waits for the next audit, or forever if auditing is disabled.
+	pub(crate) async fn tick(&mut self) {
+		match &mut self.interval {
+			Some(interval) => {
+				interval.tick().await;
+			},
+			None => futures::future::pending().await,
+		}
+	}
+
+	/// Audits the channels in both directions, and starts a clearing if gaps were found.
+	pub(crate) async fn run<A: Chain, B: Chain, P: MessagingProtocol>(
+		&mut self,
+		chain_a: &A,
+		chain_b: &B,
+		chain_a_metrics: Option<&MetricsHandler>,
+		chain_b_metrics: Option<&MetricsHandler>,
+		protocol: &P,
+		clearing: &mut Clearing,
+	) {
+		let mut gaps = 0;
+		match self.audit_chain(chain_a, chain_b, chain_a_metrics, protocol).await {
+			Ok(count) => gaps += count,
+			Err(e) =>
+				log::warn!(target: "hyperspace", "Failed to audit the sequences of {}: {e:?}", chain_a.name()),
+		}
+		match self.audit_chain(chain_b, chain_a, chain_b_metrics, protocol).await {
+			Ok(count) => gaps += count,
+			Err(e) =>
+				log::warn!(target: "hyperspace", "Failed to audit the sequences of {}: {e:?}", chain_b.name()),
+		}
+		if gaps > 0 {
+			log::info!(target: "hyperspace", "Clearing the {gaps} sequences found by the audit");
+			clearing.start();
+		} else {
+			log::debug!(target: "hyperspace", "No sequence gaps between {} and {}", chain_a.name(), chain_b.name());
+		}
+	}
+
+	/// Audits the channels of the source the protocol relays, returning the number of gaps found.
+	async fn audit_chain<A: Chain, B: Chain, P: MessagingProtocol>(
+		&mut self,
+		source: &A,
+		sink: &B,
+		metrics: Option<&MetricsHandler>,
+		protocol: &P,
+	) -> anyhow::Result<usize> {
+		let (source_height, _) = source.latest_height_and_timestamp().await?;
+		let (sink_height, _) = sink.latest_height_and_timestamp().await?;
+		let mut gaps = 0;
+		for (channel_id, port_id) in source.channel_whitelist() {
+			if !protocol.is_channel_allowed(&channel_id, &port_id) {
+				continue
+			}
+			match self
+				.audit_channel(
+					source,
+					sink,
+					(source_height, sink_height),
+					channel_id,
+					&port_id,
+					metrics,
+					protocol,
+				)
+				.await
+			{
+				Ok(count) => gaps += count,
+				Err(e) => log::warn!(
+					target: "hyperspace",
+					"Failed to audit the sequences of {} {channel_id}/{port_id}: {e:?}", source.name()
+				),
+			}
+		}
+		Ok(gaps)
+	}
+
+	async fn audit_channel<A: Chain, B: Chain, P: MessagingProtocol>(
+		&mut self,
+		source: &A,
+		sink: &B,
+		(source_height, sink_height): (Height, Height),
+		channel_id: ChannelId,
+		port_id: &PortId,
+		metrics: Option<&MetricsHandler>,
+		protocol: &P,
+	) -> anyhow::Result<usize> {
+		let packets = query_undelivered_sequences(
+			source_height,
+			sink_height,
+			channel_id,
+			port_id.clone(),
+			source,
+			sink,
+		)
+		.await?;
+		let acknowledgements = query_undelivered_acks(
+			source_height,
+			sink_height,
+			channel_id,
+			port_id.clone(),
+			source,
+			sink,
+		)
+		.await?;
+		if let Some(metrics) = metrics {
+			metrics.set_undelivered_packets(channel_id, port_id.clone(), packets.clone());
+			metrics.set_undelivered_acknowledgements(
+				channel_id,
+				port_id.clone(),
+				acknowledgements.clone(),
+			);
+		}
+
+		// the sequences the protocol doesn't relay stay undelivered, they aren't gaps
+		let allowed = |sequence: &u64| protocol.is_packet_allowed(&channel_id, port_id, *sequence);
+		let undelivered = Undelivered {
+			packets: packets.into_iter().filter(allowed).collect(),
+			acknowledgements: acknowledgements.into_iter().filter(allowed).collect(),
+		};
+		let key = (source.name().to_string(), channel_id, port_id.clone());
+		let Some(last) = self.last.insert(key, undelivered.clone()) else { return Ok(0) };
+		let packet_gaps =
+			undelivered.packets.intersection(&last.packets).copied().collect::<Vec<_>>();
+		let ack_gaps = undelivered
+			.acknowledgements
+			.intersection(&last.acknowledgements)
+			.copied()
+			.collect::<Vec<_>>();
+		if packet_gaps.is_empty() && ack_gaps.is_empty() {
+			return Ok(0)
+		}
+
+		log::warn!(
+			target: "hyperspace",
+			"Sequence gaps on {} {channel_id}/{port_id}: packets {packet_gaps:?} and acknowledgements {ack_gaps:?} are still undelivered since the last audit",
+			source.name()
+		);
+		if let Some(metrics) = metrics {
+			metrics.report_sequence_gaps(packet_gaps.len(), ack_gaps.len());
+		}
+		if let Err(e) = forget_delivered(
+			source,
+			sink,
+			source_height,
+			channel_id,
+			port_id,
+			&packet_gaps,
+			&ack_gaps,
+		)
+		.await
+		{
+			log::warn!(
+				target: "hyperspace",
+				"Failed to forget the delivered messages of {} {channel_id}/{port_id}: {e:?}", source.name()
+			);
+		}
+		Ok(packet_gaps.len() + ack_gaps.len())
+	}
+}
+
+/// Drops the gaps from the state stores of the chains, so that their messages aren't skipped as
+/// already delivered.
+async fn forget_delivered<A: Chain, B: Chain>(
+	source: &A,
+	sink: &B,
+	source_height: Height,
+	channel_id: ChannelId,
+	port_id: &PortId,
+	packet_gaps: &[u64],
+	ack_gaps: &[u64],
+) -> anyhow::Result<()> {
+	// the packets of the gaps are either received on the sink or timed out on the source
+	if let Some(store) = &sink.common_state().store {
+		store.forget_delivered(MessageKind::RecvPacket, port_id, &channel_id, packet_gaps)?;
+	}
+	if let Some(store) = &source.common_state().store {
+		store.forget_delivered(MessageKind::Timeout, port_id, &channel_id, packet_gaps)?;
+	}
+
+	// the acknowledgements are written on the source for the packets sent by the sink
+	if ack_gaps.is_empty() {
+		return Ok(())
+	}
+	let Some(store) = &sink.common_state().store else { return Ok(()) };
+	let channel_end = source
+		.query_channel_end(source_height, channel_id, port_id.clone())
+		.await?
+		.channel
+		.map(ChannelEnd::try_from)
+		.transpose()
+		.map_err(|e| anyhow!("{e:?}"))?
+		.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} not found"))?;
+	let counterparty = channel_end.counterparty();
+	let counterparty_channel_id = counterparty
+		.channel_id
+		.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} has no counterparty"))?;
+	store.forget_delivered(
+		MessageKind::Acknowledgement,
+		&counterparty.port_id,
+		&counterparty_channel_id,
+		ack_gaps,
+	)?;
+	Ok(())
+}
@@ -16,79 +16,117 @@
 
 use crate::{
 	chains,
+	lease::LeaderLeaseConfig,
+	packets::filter::PacketFilter,
 	substrate::{
 		default::DefaultConfig, ComposableConfig, PicassoKusamaConfig, PicassoRococoConfig,
 	},
 };
-use async_trait::async_trait;
 #[cfg(feature = "cosmos")]
 use cosmos::client::{CosmosClient, CosmosClientConfig};
-use futures::Stream;
-#[cfg(any(test, feature = "testing"))]
-use ibc::applications::transfer::msgs::transfer::MsgTransfer;
 use ibc::{
-	applications::transfer::PrefixedCoin,
 	core::{
-		ics02_client::{
-			client_state::ClientType,
-			events::{CodeId, UpdateClient},
-			msgs::{create_client::MsgCreateAnyClient, update_client::MsgUpdateAnyClient},
+		ics02_client::msgs::{
+			create_client::MsgCreateAnyClient, update_client::MsgUpdateAnyClient,
+			upgrade_client::MsgUpgradeAnyClient,
 		},
 		ics03_connection::msgs::{
 			conn_open_ack::MsgConnectionOpenAck, conn_open_try::MsgConnectionOpenTry,
 		},
-		ics23_commitment::commitment::CommitmentPrefix,
-		ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
 	},
-	downcast,
-	events::IbcEvent,
-	signer::Signer,
-	timestamp::Timestamp,
 	tx_msg::Msg,
-	Height,
 };
-use ibc_proto::{
-	google::protobuf::Any,
-	ibc::core::{
-		channel::v1::{
-			QueryChannelResponse, QueryChannelsResponse, QueryNextSequenceReceiveResponse,
-			QueryPacketAcknowledgementResponse, QueryPacketCommitmentResponse,
-			QueryPacketReceiptResponse,
-		},
-		client::v1::{QueryClientStateResponse, QueryConsensusStateResponse},
-		connection::v1::{IdentifiedConnection, QueryConnectionResponse},
-	},
-};
-use ics08_wasm::Bytes;
-use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState, AnyConsensusState};
-#[cfg(any(test, feature = "testing"))]
-use pallet_ibc::Timeout;
+use metrics::score::HealthScoreConfig;
 use parachain::{ParachainClient, ParachainClientConfig};
-use primitives::{
-	mock::LocalClientTypes, Chain, CommonClientState, IbcProvider, KeyProvider, LightClientSync,
-	MisbehaviourHandler, UpdateType,
-};
-use serde::{Deserialize, Serialize};
-use std::{pin::Pin, time::Duration};
+use primitives::{mock::LocalClientTypes, FinalityConfirmations};
 use tendermint_proto::Protobuf;
-use thiserror::Error;
 
 #[derive(Serialize, Deserialize)]
-pub struct Config {
-	pub chain_a: AnyConfig,
-	pub chain_b: AnyConfig,
+pub struct Config<C = AnyConfig> {
+	pub chain_a: C,
+	pub chain_b: C,
 	pub core: CoreConfig,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct CoreConfig {
 	pub prometheus_endpoint: Option<String>,
+	/// StatsD endpoint to mirror the metrics to, requires the `statsd` feature
+	pub statsd_endpoint: Option<String>,
+	/// Prefix of the metric names sent to the StatsD endpoint
+	pub statsd_prefix: Option<String>,
+	/// Interval between flushes to the StatsD endpoint, in seconds
+	pub statsd_flush_interval_secs: Option<u64>,
+	/// Address of the gRPC introspection service, requires the `introspection` feature
+	pub introspection_endpoint: Option<String>,
+	/// Address of the WebSocket server streaming the relay events, requires the `event-stream`
+	/// feature
+	pub event_stream_endpoint: Option<String>,
+	/// Interval between two clearings of the pending packets, in seconds. Set to 0 to disable
+	/// clearing, defaults to 10 minutes
+	pub packet_clear_interval_secs: Option<u64>,
+	/// Share of the finality notifications in the submissions while packets are cleared,
+	/// defaults to 1
+	pub finality_weight: Option<u32>,
+	/// Share of the packet clearing rounds in the submissions while packets are cleared,
+	/// defaults to 1
+	pub clearing_weight: Option<u32>,
+	/// Maximum number of rounds of a clearing, the packets still pending after them are cleared
+	/// by the next clearing. Defaults to 100
+	pub max_clearing_rounds: Option<u32>,
+	/// Interval between two audits of the channel sequences, which relay again the packets and
+	/// acknowledgements left undelivered since the previous audit, in seconds. Set to 0 to disable
+	/// the audits, defaults to 15 minutes. Requires packet clearing
+	pub sequence_audit_interval_secs: Option<u64>,
+	/// Path of the JSON file the startup reconciliation report is written to
+	pub reconciliation_report_path: Option<String>,
+	/// Interval between two checks of the config files for changes, in seconds. Set to 0 to only
+	/// reload the config on SIGHUP, defaults to 10 seconds
+	pub config_reload_interval_secs: Option<u64>,
+	/// Channels allowed to be relayed
+	#[serde(default)]
+	pub filter: PacketFilter,
+	/// Chain pairs relayed by the `relay-paths` command
+	#[serde(default)]
+	pub paths: Vec<PathConfig>,
+	/// Path of the database the processed heights and the delivered packets are persisted to, so
+	/// that a restarted relayer resumes where it stopped
+	pub state_store_path: Option<String>,
+	/// Path of the database the control commands run with an `--idempotency-key` are logged to,
+	/// see [`primitives::store::OperationLog`]
+	pub operation_log_path: Option<String>,
+	/// Health score of the relayed paths
+	#[serde(default)]
+	pub health_score: HealthScoreConfig,
+	/// Time given to the relayer to submit its in-flight messages on SIGTERM, in seconds,
+	/// defaults to a minute
+	pub shutdown_timeout_secs: Option<u64>,
+	/// Lease shared with the other relayers of a hot/standby deployment, the relayer only submits
+	/// messages while it holds it
+	pub leader_lease: Option<LeaderLeaseConfig>,
 }
 
-impl From<String> for AnyError {
-	fn from(s: String) -> Self {
-		Self::Other(s)
-	}
+/// A pair of chains relayed alongside the other paths of the core config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathConfig {
+	/// Name of the path, prefixed to the names of the chains in the metrics. Must only contain
+	/// alphanumeric characters and underscores
+	pub name: String,
+	/// Chain A config path
+	pub config_a: String,
+	/// Chain B config path
+	pub config_b: String,
+	/// Channels allowed to be relayed on this path
+	#[serde(default)]
+	pub filter: PacketFilter,
+	/// Path of the JSON file the startup reconciliation report of this path is written to
+	pub reconciliation_report_path: Option<String>,
+	/// Confirmations of the finality notifications of chain A on this path, overriding the
+	/// `finality_confirmations` of its config
+	pub finality_confirmations_a: Option<FinalityConfirmations>,
+	/// Confirmations of the finality notifications of chain B on this path, overriding the
+	/// `finality_confirmations` of its config
+	pub finality_confirmations_b: Option<FinalityConfirmations>,
 }
 
 chains! {
@@ -101,12 +139,25 @@ chains! {
 	Cosmos(CosmosClientConfig, CosmosClient<DefaultConfig>),
 }
 
-fn wrap_any_msg_into_wasm(msg: Any, code_id: Bytes) -> Result<Any, anyhow::Error> {
+/// Code hash of a client state wrapped in an `08-wasm` client state, `None` for the other clients.
+pub fn wasm_code_id_of(client_state: &Any) -> Option<Bytes> {
+	use ibc_proto::ibc::lightclients::wasm::v1::ClientState as RawWasmClientState;
+	use ics08_wasm::client_state::WASM_CLIENT_STATE_TYPE_URL;
+	use prost::Message;
+
+	if client_state.type_url != WASM_CLIENT_STATE_TYPE_URL {
+		return None
+	}
+	RawWasmClientState::decode(&*client_state.value).ok().map(|state| state.code_id)
+}
+
+pub fn wrap_any_msg_into_wasm(msg: Any, code_id: Bytes) -> Result<Any, anyhow::Error> {
 	// TODO: consider rewriting with Ics26Envelope
 	use ibc::core::{
 		ics02_client::msgs::{
 			create_client::TYPE_URL as CREATE_CLIENT_TYPE_URL,
 			update_client::TYPE_URL as UPDATE_CLIENT_TYPE_URL,
+			upgrade_client::TYPE_URL as UPGRADE_CLIENT_TYPE_URL,
 		},
 		ics03_connection::msgs::{
 			conn_open_ack::TYPE_URL as CONN_OPEN_ACK_TYPE_URL,
@@ -139,13 +190,14 @@ fn wrap_any_msg_into_wasm(msg: Any, code_id: Bytes) -> Result<Any, anyhow::Error
 
 			msg_decoded.to_any()
 		},
+		UPGRADE_CLIENT_TYPE_URL => {
+			let mut msg_decoded =
+				MsgUpgradeAnyClient::<LocalClientTypes>::decode_vec(&msg.value).unwrap();
+			msg_decoded.consensus_state = AnyConsensusState::wasm(msg_decoded.consensus_state)?;
+			msg_decoded.client_state = AnyClientState::wasm(msg_decoded.client_state, code_id)?;
+			msg_decoded.to_any()
+		},
 		_ => msg,
 	};
 	Ok(msg)
 }
-
-#[derive(Clone)]
-pub struct WasmChain {
-	pub inner: Box<AnyChain>,
-	pub code_id: Bytes,
-}
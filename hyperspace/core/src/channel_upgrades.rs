@@ -0,0 +1,240 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Relaying of the channel upgrade handshakes, see [`primitives::channel_upgrades`].
+//!
+//! Each step of the handshake emits an event on the chain it's executed on, which is answered
+//! with the next step on the counterparty:
+//! - `channel_upgrade_init` on A: `ChanUpgradeTry` on B
+//! - `channel_upgrade_try` on B: `ChanUpgradeAck` on A
+//! - `channel_upgrade_ack` on A: `ChanUpgradeConfirm` on B
+//! - `channel_upgrade_confirm` or `channel_upgrade_open` on B: `ChanUpgradeOpen` on A, once A
+//!   flushed its in-flight packets
+
+use anyhow::anyhow;
+use ibc::{
+	core::{
+		ics04_channel::events::UpgradeAttributes,
+		ics24_host::identifier::{ChannelId, ConnectionId, PortId},
+	},
+	events::IbcEvent,
+};
+use ibc_proto::{
+	google::protobuf::Any,
+	ibc::core::channel::v1::{
+		Channel, MsgChannelUpgradeAck, MsgChannelUpgradeConfirm, MsgChannelUpgradeOpen,
+		MsgChannelUpgradeTry, State as RawState,
+	},
+};
+use primitives::{
+	channel_upgrades::{
+		upgrade_ack_msg, upgrade_confirm_msg, upgrade_open_msg, upgrade_try_msg, ChannelUpgrade,
+	},
+	Chain,
+};
+use std::str::FromStr;
+
+/// Channel end of the source at the height of an upgrade event, with its proof.
+struct UpgradingChannel {
+	channel: Channel,
+	proof: Vec<u8>,
+	proof_height: ibc_proto::ibc::core::client::v1::Height,
+	counterparty_port_id: String,
+	counterparty_channel_id: String,
+}
+
+/// Builds the message answering an upgrade event of the source on the sink, or `None` if the
+/// event isn't an upgrade event or the sink isn't ready for the next step yet.
+pub async fn parse_upgrade_event(
+	source: &impl Chain,
+	sink: &impl Chain,
+	event: &IbcEvent,
+) -> Result<Option<Any>, anyhow::Error> {
+	let Some(attributes) = upgrade_attributes(event) else { return Ok(None) };
+	let msg = match event {
+		IbcEvent::UpgradeInitChannel(_) => {
+			let channel = query_upgrading_channel(source, attributes).await?;
+			let upgrade = query_upgrade(source, attributes).await?;
+			let fields = upgrade.upgrade.fields.clone().ok_or_else(|| {
+				anyhow!("Upgrade of {}/{} has no fields", attributes.channel_id, attributes.port_id)
+			})?;
+			// the upgrade proposes the connection of the source, the try proposes its counterparty
+			let connection_id = fields.connection_hops.first().ok_or_else(|| {
+				anyhow!(
+					"Upgrade of {}/{} has no connection",
+					attributes.channel_id,
+					attributes.port_id
+				)
+			})?;
+			let counterparty_connection_id = source
+				.query_connection_end(attributes.height, ConnectionId::from_str(connection_id)?)
+				.await?
+				.connection
+				.and_then(|connection| connection.counterparty)
+				.map(|counterparty| counterparty.connection_id)
+				.ok_or_else(|| anyhow!("Counterparty of connection {connection_id} not found"))?;
+			let msg = MsgChannelUpgradeTry {
+				port_id: channel.counterparty_port_id,
+				channel_id: channel.counterparty_channel_id,
+				proposed_upgrade_connection_hops: vec![counterparty_connection_id],
+				counterparty_upgrade_fields: Some(fields),
+				counterparty_upgrade_sequence: attributes.upgrade_sequence,
+				proof_channel: channel.proof,
+				proof_upgrade: upgrade.proof,
+				proof_height: Some(channel.proof_height),
+				signer: sink.account_id().to_string(),
+			};
+			upgrade_try_msg(msg)
+		},
+		IbcEvent::UpgradeTryChannel(_) => {
+			let channel = query_upgrading_channel(source, attributes).await?;
+			let upgrade = query_upgrade(source, attributes).await?;
+			let msg = MsgChannelUpgradeAck {
+				port_id: channel.counterparty_port_id,
+				channel_id: channel.counterparty_channel_id,
+				counterparty_upgrade: Some(upgrade.upgrade),
+				proof_channel: channel.proof,
+				proof_upgrade: upgrade.proof,
+				proof_height: Some(channel.proof_height),
+				signer: sink.account_id().to_string(),
+			};
+			upgrade_ack_msg(msg)
+		},
+		IbcEvent::UpgradeAckChannel(_) => {
+			let channel = query_upgrading_channel(source, attributes).await?;
+			let upgrade = query_upgrade(source, attributes).await?;
+			let msg = MsgChannelUpgradeConfirm {
+				port_id: channel.counterparty_port_id,
+				channel_id: channel.counterparty_channel_id,
+				counterparty_channel_state: channel.channel.state,
+				counterparty_upgrade: Some(upgrade.upgrade),
+				proof_channel: channel.proof,
+				proof_upgrade: upgrade.proof,
+				proof_height: Some(channel.proof_height),
+				signer: sink.account_id().to_string(),
+			};
+			upgrade_confirm_msg(msg)
+		},
+		IbcEvent::UpgradeConfirmChannel(_) | IbcEvent::UpgradeOpenChannel(_) =>
+			match open_msg(source, sink, attributes).await? {
+				Some(msg) => msg,
+				None => return Ok(None),
+			},
+		_ => return Ok(None),
+	};
+	log::info!(
+		target: "hyperspace",
+		"Relaying the {} of the upgrade of {}/{} on {} to {}",
+		event.event_type().as_str(), attributes.channel_id, attributes.port_id, source.name(), sink.name()
+	);
+	Ok(Some(msg))
+}
+
+/// Builds the `ChanUpgradeOpen` of the sink once both ends flushed their in-flight packets. The
+/// source is either done flushing or already open, and the sink has to be done flushing.
+async fn open_msg(
+	source: &impl Chain,
+	sink: &impl Chain,
+	attributes: &UpgradeAttributes,
+) -> Result<Option<Any>, anyhow::Error> {
+	let channel = query_upgrading_channel(source, attributes).await?;
+	let source_state = channel.channel.state;
+	if source_state != RawState::Flushcomplete as i32 && source_state != RawState::Open as i32 {
+		log::debug!(
+			target: "hyperspace",
+			"Channel {}/{} on {} is still flushing its packets", attributes.channel_id, attributes.port_id, source.name()
+		);
+		return Ok(None)
+	}
+	let (sink_height, _) = sink.latest_height_and_timestamp().await?;
+	let sink_state = sink
+		.query_channel_end(
+			sink_height,
+			ChannelId::from_str(&channel.counterparty_channel_id)?,
+			PortId::from_str(&channel.counterparty_port_id)?,
+		)
+		.await?
+		.channel
+		.map(|channel| channel.state)
+		.unwrap_or_default();
+	if sink_state != RawState::Flushcomplete as i32 {
+		log::debug!(
+			target: "hyperspace",
+			"Channel {}/{} on {} isn't ready to open its upgrade", channel.counterparty_channel_id, channel.counterparty_port_id, sink.name()
+		);
+		return Ok(None)
+	}
+	let msg = MsgChannelUpgradeOpen {
+		port_id: channel.counterparty_port_id,
+		channel_id: channel.counterparty_channel_id,
+		counterparty_channel_state: source_state,
+		counterparty_upgrade_sequence: attributes.upgrade_sequence,
+		proof_channel: channel.proof,
+		proof_height: Some(channel.proof_height),
+		signer: sink.account_id().to_string(),
+	};
+	Ok(Some(upgrade_open_msg(msg)))
+}
+
+async fn query_upgrading_channel(
+	source: &impl Chain,
+	attributes: &UpgradeAttributes,
+) -> Result<UpgradingChannel, anyhow::Error> {
+	let response = source
+		.query_channel_end(attributes.height, attributes.channel_id, attributes.port_id.clone())
+		.await?;
+	let channel = response.channel.ok_or_else(|| {
+		anyhow!("ChannelEnd not found for {}/{}", attributes.channel_id, attributes.port_id)
+	})?;
+	let counterparty = channel.counterparty.clone().ok_or_else(|| {
+		anyhow!("Counterparty not found for {}/{}", attributes.channel_id, attributes.port_id)
+	})?;
+	let proof_height =
+		response.proof_height.ok_or_else(|| anyhow!("Proof height should be present"))?;
+	Ok(UpgradingChannel {
+		channel,
+		proof: response.proof,
+		proof_height,
+		counterparty_port_id: counterparty.port_id,
+		counterparty_channel_id: counterparty.channel_id,
+	})
+}
+
+async fn query_upgrade(
+	source: &impl Chain,
+	attributes: &UpgradeAttributes,
+) -> Result<ChannelUpgrade, anyhow::Error> {
+	source
+		.query_channel_upgrade(attributes.height, attributes.channel_id, attributes.port_id.clone())
+		.await?
+		.ok_or_else(|| {
+			anyhow!(
+				"Upgrade of {}/{} not found on {}",
+				attributes.channel_id,
+				attributes.port_id,
+				source.name()
+			)
+		})
+}
+
+fn upgrade_attributes(event: &IbcEvent) -> Option<&UpgradeAttributes> {
+	match event {
+		IbcEvent::UpgradeInitChannel(ev) => Some(ev.attributes()),
+		IbcEvent::UpgradeTryChannel(ev) => Some(ev.attributes()),
+		IbcEvent::UpgradeAckChannel(ev) => Some(ev.attributes()),
+		IbcEvent::UpgradeConfirmChannel(ev) => Some(ev.attributes()),
+		IbcEvent::UpgradeOpenChannel(ev) => Some(ev.attributes()),
+		_ => None,
+	}
+}
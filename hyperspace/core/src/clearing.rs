@@ -0,0 +1,358 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Clearing of the packets missed by the relayer, e.g. while it was down. The packets are cleared
+//! at startup and then periodically, independently of the finality notifications.
+//!
+//! A clearing runs in rounds of at most `max_packets_to_process` packets per channel, until a
+//! round finds nothing to clear or [`ClearingConfig::max_rounds`] rounds ran. While a clearing is
+//! in progress, the rounds are interleaved with the finality notifications by a
+//! [`WorkloadScheduler`], so that the light clients are kept up to date while large backlogs are
+//! cleared, and the messages submitted since the start of the clearing aren't submitted again by
+//! its next rounds, see [`primitives::submitted`].
+//!
+//! Each chain may also be swept periodically, see
+//! [`primitives::CommonClientState::sweep_interval`]: a sweep relays the messages of the chain that
+//! are ready but were missed by the finality notifications, and starts a clearing if it found some.
+
+use crate::{
+	audit::DEFAULT_AUDIT_INTERVAL, process_finality_event, process_messages_and_timeouts,
+	protocol::MessagingProtocol, supervisor::open_stream, Mode,
+};
+use futures::StreamExt;
+use metrics::handler::MetricsHandler;
+use primitives::Chain;
+use std::time::Duration;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// Default interval between two packet clearings.
+pub const DEFAULT_CLEAR_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Default maximum number of rounds of a clearing.
+pub const DEFAULT_MAX_CLEAR_ROUNDS: u32 = 100;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClearingConfig {
+	/// Interval between two packet clearings
+	pub interval: Duration,
+	/// Share of the finality notifications in the submissions while a clearing is in progress
+	pub finality_weight: u32,
+	/// Share of the clearing rounds in the submissions while a clearing is in progress
+	pub clearing_weight: u32,
+	/// Interval between two audits of the channel sequences, see [`crate::audit`]. `None`
+	/// disables the audits
+	pub audit_interval: Option<Duration>,
+	/// Maximum number of rounds of a clearing, the packets still pending after them are cleared
+	/// by the next clearing
+	pub max_rounds: u32,
+}
+
+impl ClearingConfig {
+	pub fn new(interval: Duration) -> Self {
+		Self {
+			interval,
+			finality_weight: 1,
+			clearing_weight: 1,
+			audit_interval: Some(DEFAULT_AUDIT_INTERVAL),
+			max_rounds: DEFAULT_MAX_CLEAR_ROUNDS,
+		}
+	}
+}
+
+/// Workloads submitting messages to the chains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+	Finality,
+	Clearing,
+}
+
+/// Weighted round-robin between the finality notifications and the clearing rounds.
+#[derive(Debug, Clone)]
+pub struct WorkloadScheduler {
+	finality_weight: u64,
+	clearing_weight: u64,
+	finality_served: u64,
+	clearing_served: u64,
+}
+
+impl WorkloadScheduler {
+	pub fn new(finality_weight: u32, clearing_weight: u32) -> Self {
+		Self {
+			finality_weight: finality_weight.max(1) as u64,
+			clearing_weight: clearing_weight.max(1) as u64,
+			finality_served: 0,
+			clearing_served: 0,
+		}
+	}
+
+	/// Returns the workload that got the smallest share of its weight so far.
+	pub fn next(&self) -> Workload {
+		if self.clearing_served * self.finality_weight <=
+			self.finality_served * self.clearing_weight
+		{
+			Workload::Clearing
+		} else {
+			Workload::Finality
+		}
+	}
+
+	pub fn record(&mut self, workload: Workload) {
+		match workload {
+			Workload::Finality => self.finality_served += 1,
+			Workload::Clearing => self.clearing_served += 1,
+		}
+	}
+
+	/// Forgets the past workloads, so that the next clearing doesn't start with a burst.
+	pub fn reset(&mut self) {
+		self.finality_served = 0;
+		self.clearing_served = 0;
+	}
+}
+
+/// State of the periodic clearing of the relayer loop.
+pub(crate) struct Clearing {
+	interval: Option<Interval>,
+	scheduler: WorkloadScheduler,
+	in_progress: bool,
+	/// Rounds run by the clearing in progress
+	rounds: u32,
+	max_rounds: u32,
+}
+
+impl Clearing {
+	/// Clears the packets at startup if `clear_at_startup` is set, and then periodically.
+	pub(crate) fn new(config: Option<ClearingConfig>, clear_at_startup: bool) -> Self {
+		let interval = config.map(|config| {
+			// the first tick of `interval` completes immediately, so the packets are cleared at
+			// startup
+			let start = if clear_at_startup {
+				tokio::time::Instant::now()
+			} else {
+				tokio::time::Instant::now() + config.interval
+			};
+			let mut interval = tokio::time::interval_at(start, config.interval);
+			interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+			interval
+		});
+		let scheduler = config
+			.map(|config| WorkloadScheduler::new(config.finality_weight, config.clearing_weight))
+			.unwrap_or_else(|| WorkloadScheduler::new(1, 1));
+		let max_rounds = config.map_or(DEFAULT_MAX_CLEAR_ROUNDS, |config| config.max_rounds.max(1));
+		Self { interval, scheduler, in_progress: false, rounds: 0, max_rounds }
+	}
+
+	/// Returns `true` if a clearing round should run before waiting for the next finality
+	/// notification.
+	pub(crate) fn should_run(&self) -> bool {
+		self.in_progress && self.scheduler.next() == Workload::Clearing
+	}
+
+	/// Waits for the next clearing to start, or forever if clearing is disabled.
+	pub(crate) async fn tick(&mut self) {
+		match &mut self.interval {
+			Some(interval) => {
+				interval.tick().await;
+			},
+			None => futures::future::pending().await,
+		}
+	}
+
+	pub(crate) fn start(&mut self) {
+		if !self.in_progress {
+			self.in_progress = true;
+			self.rounds = 0;
+			self.scheduler.reset();
+		}
+	}
+
+	pub(crate) fn on_finality_event(&mut self) {
+		if self.in_progress {
+			self.scheduler.record(Workload::Finality);
+		}
+	}
+
+	/// Runs a clearing round in both directions.
+	pub(crate) async fn run_round<A: Chain, B: Chain, P: MessagingProtocol>(
+		&mut self,
+		chain_a: &mut A,
+		chain_b: &mut B,
+		chain_a_metrics: &mut Option<MetricsHandler>,
+		chain_b_metrics: &mut Option<MetricsHandler>,
+		protocol: &P,
+	) {
+		self.scheduler.record(Workload::Clearing);
+		if self.rounds == 0 {
+			chain_a.common_state().submitted_packets.start();
+			chain_b.common_state().submitted_packets.start();
+		}
+		self.rounds += 1;
+		let mut cleared = 0;
+		match clear_packets(chain_a, chain_b, chain_a_metrics, protocol).await {
+			Ok(count) => cleared += count,
+			Err(e) => log::error!("Failed to clear packets from {}: {:?}", chain_a.name(), e),
+		}
+		match clear_packets(chain_b, chain_a, chain_b_metrics, protocol).await {
+			Ok(count) => cleared += count,
+			Err(e) => log::error!("Failed to clear packets from {}: {:?}", chain_b.name(), e),
+		}
+		if cleared == 0 {
+			log::info!(target: "hyperspace", "Finished clearing packets");
+		} else if self.rounds >= self.max_rounds {
+			log::warn!(target: "hyperspace", "Packets are still pending after {} clearing rounds, leaving them to the next clearing", self.rounds);
+		} else {
+			return
+		}
+		self.in_progress = false;
+		chain_a.common_state().submitted_packets.stop();
+		chain_b.common_state().submitted_packets.stop();
+	}
+}
+
+/// State of the periodic sweep of the pending messages of a chain.
+pub(crate) struct Sweep {
+	interval: Option<Interval>,
+}
+
+impl Sweep {
+	/// Sweeps the chain every [`primitives::CommonClientState::sweep_interval`] unless `disabled`
+	/// is set, the first sweep runs after one interval.
+	pub(crate) fn new(chain: &impl Chain, disabled: bool) -> Self {
+		let interval = chain.common_state().sweep_interval.filter(|_| !disabled).map(|period| {
+			let mut interval =
+				tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+			interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+			interval
+		});
+		Self { interval }
+	}
+
+	/// Waits for the next sweep, or forever if sweeping is disabled.
+	pub(crate) async fn tick(&mut self) {
+		match &mut self.interval {
+			Some(interval) => {
+				interval.tick().await;
+			},
+			None => futures::future::pending().await,
+		}
+	}
+
+	/// Relays the messages of the source that are ready, and starts a clearing if there were any,
+	/// as there may be more than a round can relay.
+	pub(crate) async fn run<A: Chain, B: Chain, P: MessagingProtocol>(
+		&self,
+		source: &mut A,
+		sink: &mut B,
+		metrics: &mut Option<MetricsHandler>,
+		protocol: &P,
+		clearing: &mut Clearing,
+	) {
+		match clear_packets(source, sink, metrics, protocol).await {
+			Ok(0) => {
+				log::debug!(target: "hyperspace", "Sweep of {} found nothing to relay", source.name())
+			},
+			Ok(count) => {
+				log::info!(target: "hyperspace", "Sweep of {} relayed {count} messages missed by the finality notifications", source.name());
+				clearing.start();
+			},
+			Err(e) => {
+				log::error!(target: "hyperspace", "Failed to sweep the messages of {}: {e:?}", source.name())
+			},
+		}
+	}
+}
+
+/// Relays all the messages from the source to the sink that are ready to be delivered, and
+/// the timeouts back to the source, except the ones already submitted by the clearing in
+/// progress. Returns the number of messages found, including the ones already submitted, which
+/// are pending until the chains process them.
+pub async fn clear_packets<A: Chain, B: Chain, P: MessagingProtocol>(
+	source: &mut A,
+	sink: &mut B,
+	metrics: &mut Option<MetricsHandler>,
+	protocol: &P,
+) -> anyhow::Result<usize> {
+	let (messages, timeout_messages) =
+		protocol.query_ready_messages(&*source, &*sink, metrics.as_ref()).await?;
+	let count = messages.len() + timeout_messages.len();
+	let messages = sink.common_state().submitted_packets.filter(messages);
+	let timeout_messages = source.common_state().submitted_packets.filter(timeout_messages);
+	log::info!(
+		target: "hyperspace",
+		"Clearing {} messages from {} to {} and {} timeouts, {} were already submitted",
+		messages.len(), source.name(), sink.name(), timeout_messages.len(),
+		count - messages.len() - timeout_messages.len()
+	);
+	process_messages_and_timeouts(source, sink, metrics, messages, timeout_messages).await?;
+	Ok(count)
+}
+
+/// Clears the messages in both directions, given the protocols relayed from each chain, until
+/// there's nothing left to clear. The light clients are updated on the finality notifications
+/// received in between the rounds, from the same task, so that the client updates and the
+/// cleared messages aren't submitted concurrently by the same signer.
+pub async fn clear_all_packets<A: Chain, B: Chain, P: MessagingProtocol, Q: MessagingProtocol>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	protocol_a: &P,
+	protocol_b: &Q,
+) -> anyhow::Result<()> {
+	chain_a.common_state().submitted_packets.start();
+	chain_b.common_state().submitted_packets.start();
+	let result = clear_rounds(chain_a, chain_b, protocol_a, protocol_b).await;
+	chain_a.common_state().submitted_packets.stop();
+	chain_b.common_state().submitted_packets.stop();
+	result
+}
+
+async fn clear_rounds<A: Chain, B: Chain, P: MessagingProtocol, Q: MessagingProtocol>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	protocol_a: &P,
+	protocol_b: &Q,
+) -> anyhow::Result<()> {
+	let mut stream_a = open_stream(chain_a).await;
+	let mut stream_b = open_stream(chain_b).await;
+	for _ in 0..DEFAULT_MAX_CLEAR_ROUNDS {
+		let cleared = clear_packets(chain_a, chain_b, &mut None, protocol_a).await? +
+			clear_packets(chain_b, chain_a, &mut None, protocol_b).await?;
+		if cleared == 0 {
+			log::info!(target: "hyperspace", "Finished clearing packets");
+			return Ok(())
+		}
+		// nothing was submitted, so the next rounds would find the same messages
+		if chain_a.common_state().dry_run || chain_b.common_state().dry_run {
+			log::info!(target: "hyperspace", "[dry run] Finished simulating {cleared} messages");
+			return Ok(())
+		}
+		// wait for the submitted messages to be reflected in the chain state
+		let wait =
+			tokio::time::sleep(chain_a.expected_block_time().max(chain_b.expected_block_time()));
+		tokio::pin!(wait);
+		loop {
+			tokio::select! {
+				_ = &mut wait => break,
+				result = stream_a.next() => {
+					process_finality_event(chain_a, chain_b, &mut None, Some(Mode::Light), &(), result, &mut stream_a, &mut stream_b).await?;
+				}
+				result = stream_b.next() => {
+					process_finality_event(chain_b, chain_a, &mut None, Some(Mode::Light), &(), result, &mut stream_b, &mut stream_a).await?;
+				}
+			}
+		}
+	}
+	Err(anyhow::anyhow!(
+		"Packets are still pending after {DEFAULT_MAX_CLEAR_ROUNDS} clearing rounds"
+	))
+}
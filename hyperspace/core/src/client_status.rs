@@ -0,0 +1,215 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Status of the light clients of both chains, printed by `hyperspace query client status`.
+
+use anyhow::anyhow;
+use ibc::{
+	core::{
+		ics02_client::{
+			client_consensus::ConsensusState as ConsensusStateT,
+			client_state::ClientState as ClientStateT,
+		},
+		ics24_host::identifier::ClientId,
+	},
+	Height,
+};
+use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState};
+use primitives::Chain;
+use serde::Serialize;
+use std::{fmt, time::Duration};
+
+/// Status of the light client of a chain on its counterparty.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientStatus {
+	/// The chain tracked by the client
+	pub chain: String,
+	/// The chain hosting the client
+	pub host: String,
+	pub client_id: ClientId,
+	pub latest_height: Height,
+	/// Timestamp of the latest consensus state of the client, in nanoseconds
+	pub consensus_timestamp: u64,
+	/// Trusting period of the client, unknown for the clients that don't have one
+	pub trusting_period: Option<Duration>,
+	pub frozen_height: Option<Height>,
+	/// Time left before the client expires, 0 if it already expired
+	pub expires_in: Option<Duration>,
+}
+
+impl fmt::Display for ClientStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "Client {} of {} on {}:", self.client_id, self.chain, self.host)?;
+		writeln!(f, "  latest height: {}", self.latest_height)?;
+		writeln!(f, "  consensus state timestamp: {}", self.consensus_timestamp)?;
+		match self.trusting_period {
+			Some(period) => writeln!(f, "  trusting period: {}", format_duration(period))?,
+			None => writeln!(f, "  trusting period: unknown")?,
+		}
+		match self.frozen_height {
+			Some(height) => writeln!(f, "  frozen at {height}")?,
+			None => writeln!(f, "  not frozen")?,
+		}
+		match self.expires_in {
+			Some(left) if left.is_zero() => writeln!(f, "  expired"),
+			Some(left) => writeln!(f, "  expires in {}", format_duration(left)),
+			None => writeln!(f, "  expiry unknown"),
+		}
+	}
+}
+
+/// Queries the status of the clients of both chains on their counterparty.
+pub async fn query_client_statuses<A: Chain, B: Chain>(
+	chain_a: &A,
+	chain_b: &B,
+) -> anyhow::Result<Vec<ClientStatus>> {
+	Ok(vec![
+		query_client_status(chain_a, chain_b).await?,
+		query_client_status(chain_b, chain_a).await?,
+	])
+}
+
+/// Queries the status of the client of `source` on `sink`.
+async fn query_client_status<A: Chain, B: Chain>(
+	source: &A,
+	sink: &B,
+) -> anyhow::Result<ClientStatus> {
+	let (sink_height, now) = sink.latest_height_and_timestamp().await?;
+	let client_state = sink
+		.query_client_state(sink_height, source.client_id())
+		.await?
+		.client_state
+		.ok_or_else(|| {
+			anyhow!("Client state of {} not found on {}", source.client_id(), sink.name())
+		})?;
+	// the clients wrapped in a wasm client are reported with the state of their inner client
+	let client_state = AnyClientState::decode_recursive(client_state, |client_state| {
+		!matches!(client_state, AnyClientState::Wasm(_))
+	})
+	.ok_or_else(|| anyhow!("Failed to decode the client state of {}", source.client_id()))?;
+	// the client expires a trusting period after the timestamp of its latest consensus state
+	let latest_height = client_state.latest_height();
+	let consensus_state = sink
+		.query_client_consensus(sink_height, source.client_id(), latest_height)
+		.await?
+		.consensus_state
+		.ok_or_else(|| {
+			anyhow!(
+				"Consensus state of {} at {latest_height} not found on {}",
+				source.client_id(),
+				sink.name()
+			)
+		})?;
+	let consensus_state =
+		AnyConsensusState::try_from(consensus_state).map_err(|e| anyhow!("{e:?}"))?;
+	Ok(client_status(
+		source.name().to_string(),
+		sink.name().to_string(),
+		source.client_id(),
+		&client_state,
+		consensus_state.timestamp().nanoseconds(),
+		now.nanoseconds(),
+	))
+}
+
+/// Status of a client whose latest consensus state has the given timestamp, at `now`.
+fn client_status(
+	chain: String,
+	host: String,
+	client_id: ClientId,
+	client_state: &AnyClientState,
+	consensus_timestamp: u64,
+	now: u64,
+) -> ClientStatus {
+	let trusting_period = match client_state {
+		AnyClientState::Tendermint(client_state) => Some(client_state.trusting_period),
+		AnyClientState::Grandpa(client_state) => Some(client_state.relay_chain.trusting_period()),
+		_ => None,
+	};
+	let elapsed = Duration::from_nanos(now.saturating_sub(consensus_timestamp));
+	ClientStatus {
+		chain,
+		host,
+		client_id,
+		latest_height: client_state.latest_height(),
+		consensus_timestamp,
+		trusting_period,
+		frozen_height: client_state.frozen_height(),
+		expires_in: trusting_period.map(|period| period.saturating_sub(elapsed)),
+	}
+}
+
+/// Formats a duration as days, hours, minutes and seconds, e.g. `2d 3h 4m 5s`.
+fn format_duration(duration: Duration) -> String {
+	let secs = duration.as_secs();
+	let (days, hours, minutes, secs) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+	if days > 0 {
+		format!("{days}d {hours}h {minutes}m {secs}s")
+	} else if hours > 0 {
+		format!("{hours}h {minutes}m {secs}s")
+	} else {
+		format!("{minutes}m {secs}s")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use light_client_common::RelayChain;
+	use pallet_ibc::light_clients::HostFunctionsManager;
+	use std::str::FromStr;
+
+	const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+	const NOW: u64 = 100 * 24 * 60 * 60 * 1_000_000_000;
+
+	fn grandpa_client_status(consensus_age: Duration) -> ClientStatus {
+		let client_state = AnyClientState::Grandpa(ics10_grandpa::client_state::ClientState::<
+			HostFunctionsManager,
+		> {
+			relay_chain: RelayChain::Polkadot,
+			latest_para_height: 10,
+			para_id: 2000,
+			..Default::default()
+		});
+		client_status(
+			"parachain".to_string(),
+			"cosmos".to_string(),
+			ClientId::from_str("10-grandpa-0").unwrap(),
+			&client_state,
+			NOW - consensus_age.as_nanos() as u64,
+			NOW,
+		)
+	}
+
+	#[test]
+	fn live_clients_expire_a_trusting_period_after_their_latest_consensus_state() {
+		let trusting_period = RelayChain::Polkadot.trusting_period();
+		let status = grandpa_client_status(DAY);
+		assert_eq!(status.trusting_period, Some(trusting_period));
+		assert_eq!(status.expires_in, Some(trusting_period - DAY));
+		assert_eq!(status.consensus_timestamp, NOW - DAY.as_nanos() as u64);
+		assert_eq!(status.latest_height, Height::new(2000, 10));
+		assert!(status.to_string().contains("expires in"));
+	}
+
+	#[test]
+	fn clients_past_their_trusting_period_are_expired() {
+		let trusting_period = RelayChain::Polkadot.trusting_period();
+		for age in [trusting_period, trusting_period + DAY] {
+			let status = grandpa_client_status(age);
+			assert_eq!(status.expires_in, Some(Duration::ZERO));
+			assert!(status.to_string().contains("  expired"));
+		}
+	}
+}
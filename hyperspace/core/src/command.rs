@@ -13,24 +13,73 @@
 // limitations under the License.
 
 use crate::{
-	chain::{AnyConfig, Config, CoreConfig},
-	fish, relay, Mode,
+	chain::{Config, CoreConfig},
+	clearing::{clear_all_packets, ClearingConfig, DEFAULT_CLEAR_INTERVAL},
+	client_status::query_client_statuses,
+	fish,
+	handshake::{
+		find_channel_handshake, find_connection_handshake, resume_channel_handshake,
+		resume_connection_handshake,
+	},
+	lease::run_lease,
+	logging::LogFormat,
+	packets::filter::PacketFilter,
+	pending::{query_pending_packets, DEFAULT_TIMEOUT_WINDOW},
+	plugin::ChainConfig,
+	protocol::IbcPackets,
+	queue::refresh_batch_limits,
+	reconcile::reconcile,
+	relay, relay_with_protocol,
+	reload::{watch_config, ReloadablePath, DEFAULT_CONFIG_RELOAD_INTERVAL},
+	score::score_path,
+	shutdown::{self, DEFAULT_SHUTDOWN_TIMEOUT},
+	watchtower::watch,
+	Mode,
 };
 use anyhow::{anyhow, Result};
 use clap::Parser;
-use ibc::core::{ics04_channel::channel::Order, ics24_host::identifier::PortId};
-use metrics::{data::Metrics, handler::MetricsHandler, init_prometheus};
+use ibc::core::{
+	ics04_channel::channel::{ChannelEnd, Order, State},
+	ics24_host::identifier::{ChannelId, PortId},
+};
+use metrics::{
+	data::Metrics,
+	handler::{MetricsHandler, OutstandingPackets},
+	health::HealthState,
+	init_prometheus, query_status,
+	score::RouteHealthMetrics,
+};
 use primitives::{
-	utils::{create_channel, create_clients, create_connection},
+	apps::ChannelApp,
+	leadership::Leadership,
+	store::{OperationLog, RelayerStore},
+	utils::{
+		abort_handshakes, close_channel, counterparty_port, create_channel, create_clients,
+		create_connection, find_abandoned_handshakes, register_payees, upgrade_client,
+	},
 	Chain, IbcProvider,
 };
 use prometheus::Registry;
-use std::{num::NonZeroU64, path::PathBuf, str::FromStr, time::Duration};
+use serde::Serialize;
+use std::{future::Future, num::NonZeroU64, path::PathBuf, str::FromStr, time::Duration};
+use tokio::{sync::oneshot, task::JoinHandle};
+
+/// Default minimum age of the handshakes closed by `abort-handshake`, in seconds.
+const DEFAULT_HANDSHAKE_MIN_AGE: u64 = 60 * 60;
+/// Time given to the relayer to close the counterparty end of a channel closed by
+/// `close-channel`.
+const CLOSE_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[cfg(feature = "statsd")]
+const DEFAULT_STATSD_FLUSH_INTERVAL: u64 = 10;
 
 #[derive(Debug, Parser)]
 pub struct Cli {
 	#[structopt(subcommand)]
 	pub subcommand: Subcommand,
+	/// Format of the log lines
+	#[clap(long, value_enum, global = true, default_value = "text")]
+	pub log_format: LogFormat,
 }
 
 /// Possible subcommands of the main binary.
@@ -38,6 +87,11 @@ pub struct Cli {
 pub enum Subcommand {
 	#[clap(name = "relay", about = "Start relaying messages between two chains")]
 	Relay(Cmd),
+	#[clap(
+		name = "relay-paths",
+		about = "Start relaying messages between the chain pairs of the core config"
+	)]
+	RelayPaths(RelayPathsCmd),
 	#[clap(name = "upload-wasm", about = "Upload a WASM blob to the chain")]
 	UploadWasm(UploadWasmCmd),
 	#[clap(
@@ -45,12 +99,147 @@ pub enum Subcommand {
 		about = "Start the relayer in fishing mode (catching malicious transactions)"
 	)]
 	Fish(Cmd),
+	#[clap(
+		name = "watch",
+		about = "Start the relayer in watchtower mode (verifying the client updates of other relayers)"
+	)]
+	Watch(Cmd),
 	#[clap(name = "create-clients", about = "Creates light clients on both chains")]
 	CreateClients(Cmd),
 	#[clap(name = "create-connection", about = "Creates a connection between both chains")]
 	CreateConnection(Cmd),
 	#[clap(name = "create-channel", about = "Creates a channel on the specified port")]
 	CreateChannel(Cmd),
+	#[clap(
+		name = "clear-packets",
+		about = "Relays the pending packets, acknowledgements and timeouts of a channel, then exits"
+	)]
+	ClearPackets(Cmd),
+	#[clap(
+		name = "close-channel",
+		about = "Closes a channel on chain A, then relays the closing to chain B and times out the pending packets on close"
+	)]
+	CloseChannel(Cmd),
+	#[clap(
+		name = "abort-handshake",
+		about = "Closes the channel handshakes between both chains that are stuck in INIT or TRYOPEN"
+	)]
+	AbortHandshake(Cmd),
+	#[clap(
+		name = "upgrade-clients",
+		about = "Upgrades the client of chain A on chain B after the upgrade of chain A"
+	)]
+	UpgradeClients(Cmd),
+	#[clap(
+		name = "register-payees",
+		about = "Registers the payees of the ICS-29 fees earned by the relayer on both ends of a fee-enabled channel"
+	)]
+	RegisterPayees(Cmd),
+	#[clap(
+		name = "status",
+		about = "Prints the health of the chains and the health score of the paths of a running relayer"
+	)]
+	Status(StatusCmd),
+	#[clap(name = "query", about = "Queries the state of the chains")]
+	Query(QueryCmd),
+	#[cfg(feature = "cosmos")]
+	#[clap(
+		name = "multisig",
+		about = "Signs and broadcasts the transactions of multisig cosmos accounts"
+	)]
+	Multisig(MultisigCmd),
+}
+
+#[cfg(feature = "cosmos")]
+#[derive(Debug, Parser)]
+pub struct MultisigCmd {
+	#[clap(subcommand)]
+	pub subcommand: MultisigSubcommand,
+}
+
+/// Possible subcommands of `multisig`, see [`cosmos::multisig`].
+#[cfg(feature = "cosmos")]
+#[derive(Debug, Parser)]
+pub enum MultisigSubcommand {
+	#[clap(
+		name = "sign",
+		about = "Signs a sign doc exported by the relayer with the key of a member"
+	)]
+	Sign(MultisigSignCmd),
+	#[clap(
+		name = "broadcast",
+		about = "Broadcasts the transaction of a sign doc once its signatures were collected"
+	)]
+	Broadcast(MultisigBroadcastCmd),
+}
+
+#[cfg(feature = "cosmos")]
+#[derive(Debug, Parser)]
+pub struct MultisigSignCmd {
+	/// Path of the exported sign doc
+	#[clap(long)]
+	sign_doc: PathBuf,
+	/// Path of the file holding the mnemonic of the key of the member
+	#[clap(long)]
+	mnemonic_file: PathBuf,
+}
+
+#[cfg(feature = "cosmos")]
+#[derive(Debug, Parser)]
+pub struct MultisigBroadcastCmd {
+	/// Path of the exported sign doc
+	#[clap(long)]
+	sign_doc: PathBuf,
+	/// RPC endpoint of a node of the chain
+	#[clap(long)]
+	rpc_url: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct QueryCmd {
+	#[clap(subcommand)]
+	pub subcommand: QuerySubcommand,
+}
+
+/// Possible subcommands of `query`.
+#[derive(Debug, Parser)]
+pub enum QuerySubcommand {
+	#[clap(name = "packets", about = "Queries the packets of the channels between both chains")]
+	Packets(PacketsQueryCmd),
+	#[clap(name = "client", about = "Queries the light clients of both chains")]
+	Client(ClientQueryCmd),
+}
+
+#[derive(Debug, Parser)]
+pub struct ClientQueryCmd {
+	#[clap(subcommand)]
+	pub subcommand: ClientQuerySubcommand,
+}
+
+/// Possible subcommands of `query client`.
+#[derive(Debug, Parser)]
+pub enum ClientQuerySubcommand {
+	#[clap(
+		name = "status",
+		about = "Prints the latest height, the trusting period, the frozen status and the time left before the expiry of the clients of both chains"
+	)]
+	Status(Cmd),
+}
+
+#[derive(Debug, Parser)]
+pub struct PacketsQueryCmd {
+	#[clap(subcommand)]
+	pub subcommand: PacketsQuerySubcommand,
+}
+
+/// Possible subcommands of `query packets`.
+#[derive(Debug, Parser)]
+pub enum PacketsQuerySubcommand {
+	#[clap(
+		name = "pending",
+		about = "Lists the unreceived packets, the unreceived acknowledgements and the packets approaching their timeout of each channel"
+	)]
+	Pending(Cmd),
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -70,18 +259,65 @@ pub struct Cmd {
 	/// Connection delay period in seconds
 	#[clap(long)]
 	delay_period: Option<std::num::NonZeroU32>,
-	/// Channel order
+	/// Channel order, defaults to the order required by the application of the port
 	#[clap(long)]
 	order: Option<String>,
-	/// Channel version
+	/// Channel version, defaults to the version of the application of the port. The version of an
+	/// interchain accounts channel is its JSON metadata
 	#[clap(long)]
 	version: Option<String>,
+	/// Channel id on chain A, for clearing packets, closing the channel or registering its fee
+	/// payees
+	#[clap(long)]
+	channel_id: Option<String>,
+	/// First sequence of the packets to clear
+	#[clap(long)]
+	sequence_start: Option<u64>,
+	/// Last sequence of the packets to clear
+	#[clap(long)]
+	sequence_end: Option<u64>,
+	/// Minimum age in seconds of the handshakes to abort, defaults to an hour
+	#[clap(long)]
+	min_age_secs: Option<u64>,
+	/// Time left in seconds before the timeout of the pending packets reported as approaching it,
+	/// defaults to 10 minutes
+	#[clap(long)]
+	timeout_window_secs: Option<u64>,
+	/// Height of the upgrade of chain A, defaults to the upgrade scheduled on chain A
+	#[clap(long)]
+	upgrade_height: Option<u64>,
 	/// New config path for A to avoid overriding existing configuration
 	#[clap(long)]
 	pub out_config_a: Option<String>,
 	/// New config path for B to avoid overriding existing configuration
 	#[clap(long)]
 	pub out_config_b: Option<String>,
+	/// Build and simulate the messages when relaying or clearing packets, but never broadcast
+	/// them
+	#[clap(long)]
+	dry_run: bool,
+	/// Key identifying a control operation (`clear-packets`, `close-channel` or
+	/// `abort-handshake`) across its retries, so that it only runs once. Requires an
+	/// `operation_log_path` in the core config
+	#[clap(long)]
+	idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct RelayPathsCmd {
+	/// Relayer core config path, listing the paths to relay.
+	#[clap(long)]
+	config_core: String,
+	/// Build and simulate the messages, but never broadcast them
+	#[clap(long)]
+	dry_run: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct StatusCmd {
+	/// Relayer core config path, whose Prometheus endpoint serves the status.
+	#[clap(long)]
+	config_core: String,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -97,12 +333,65 @@ pub struct UploadWasmCmd {
 	wasm_path: PathBuf,
 }
 
+/// Runs the given subcommand for the chains registered in `C`, see [`crate::plugin`].
+pub async fn run_cli<C: ChainConfig>(cli: Cli) -> Result<()> {
+	match &cli.subcommand {
+		Subcommand::Relay(cmd) => cmd.run::<C>().await,
+		Subcommand::RelayPaths(cmd) => cmd.run::<C>().await,
+		Subcommand::UploadWasm(cmd) => {
+			let new_config = cmd.run::<C>().await?;
+			cmd.save_config(&new_config).await
+		},
+		Subcommand::CreateClients(cmd) => {
+			let new_config = cmd.create_clients::<C>().await?;
+			cmd.save_config(&new_config).await
+		},
+		Subcommand::CreateConnection(cmd) => {
+			let new_config = cmd.create_connection::<C>().await?;
+			cmd.save_config(&new_config).await
+		},
+		Subcommand::CreateChannel(cmd) => {
+			let new_config = cmd.create_channel::<C>().await?;
+			cmd.save_config(&new_config).await
+		},
+		Subcommand::Fish(cmd) => cmd.fish::<C>().await,
+		Subcommand::Watch(cmd) => cmd.watch::<C>().await,
+		Subcommand::ClearPackets(cmd) =>
+			cmd.run_once("clear-packets", cmd.clear_packets::<C>()).await,
+		Subcommand::CloseChannel(cmd) =>
+			cmd.run_once("close-channel", cmd.close_channel::<C>()).await,
+		Subcommand::AbortHandshake(cmd) =>
+			cmd.run_once("abort-handshake", cmd.abort_handshakes::<C>()).await,
+		Subcommand::UpgradeClients(cmd) => cmd.upgrade_clients::<C>().await,
+		Subcommand::RegisterPayees(cmd) => cmd.register_payees::<C>().await,
+		Subcommand::Status(cmd) => cmd.run().await,
+		Subcommand::Query(QueryCmd {
+			subcommand:
+				QuerySubcommand::Packets(PacketsQueryCmd {
+					subcommand: PacketsQuerySubcommand::Pending(cmd),
+				}),
+		}) => cmd.query_pending_packets::<C>().await,
+		Subcommand::Query(QueryCmd {
+			subcommand:
+				QuerySubcommand::Client(ClientQueryCmd {
+					subcommand: ClientQuerySubcommand::Status(cmd),
+				}),
+		}) => cmd.query_client_status::<C>().await,
+		#[cfg(feature = "cosmos")]
+		Subcommand::Multisig(MultisigCmd { subcommand: MultisigSubcommand::Sign(cmd) }) =>
+			cmd.run().await,
+		#[cfg(feature = "cosmos")]
+		Subcommand::Multisig(MultisigCmd { subcommand: MultisigSubcommand::Broadcast(cmd) }) =>
+			cmd.run().await,
+	}
+}
+
 impl UploadWasmCmd {
-	pub async fn run(&self) -> Result<AnyConfig> {
+	pub async fn run<C: ChainConfig>(&self) -> Result<C> {
 		use tokio::fs::read_to_string;
 		let path: PathBuf = self.config.parse()?;
 		let file_content = read_to_string(path).await?;
-		let mut config: AnyConfig = toml::from_str(&file_content)?;
+		let mut config: C = toml::from_str(&file_content)?;
 		let client = config.clone().into_client().await?;
 		let wasm = tokio::fs::read(&self.wasm_path).await?;
 		let code_id = client.upload_wasm(wasm).await?;
@@ -112,61 +401,151 @@ impl UploadWasmCmd {
 		Ok(config)
 	}
 
-	pub async fn save_config(&self, new_config: &AnyConfig) -> Result<()> {
+	pub async fn save_config<C: Serialize>(&self, new_config: &C) -> Result<()> {
 		let path = self.out_config.as_ref().cloned().unwrap_or_else(|| self.config.clone());
 		write_config(path, new_config).await
 	}
 }
 
 impl Cmd {
-	async fn parse_config(&self) -> Result<Config> {
+	async fn parse_config<C: ChainConfig>(&self) -> Result<Config<C>> {
 		use tokio::fs::read_to_string;
 		let path_a: PathBuf = self.config_a.parse()?;
 		let path_b: PathBuf = self.config_b.parse()?;
 		let path_core: PathBuf = self.config_core.parse()?;
 		let file_content = read_to_string(path_a).await?;
-		let config_a: AnyConfig = toml::from_str(&file_content)?;
+		let config_a: C = toml::from_str(&file_content)?;
 		let file_content = read_to_string(path_b).await?;
-		let config_b: AnyConfig = toml::from_str(&file_content)?;
+		let config_b: C = toml::from_str(&file_content)?;
 		let file_content = read_to_string(path_core).await?;
 		let config_core: CoreConfig = toml::from_str(&file_content)?;
 
 		Ok(Config { chain_a: config_a, chain_b: config_b, core: config_core })
 	}
 
+	/// Runs a control operation at most once per `--idempotency-key`, recording it in the
+	/// [`OperationLog`] of the core config. Operations without a key, and the dry runs, always
+	/// run.
+	async fn run_once(
+		&self,
+		command: &str,
+		operation: impl Future<Output = Result<()>>,
+	) -> Result<()> {
+		let Some(key) = self.idempotency_key.as_ref().filter(|_| !self.dry_run) else {
+			return operation.await
+		};
+		let file_content = tokio::fs::read_to_string(self.config_core.parse::<PathBuf>()?).await?;
+		let core: CoreConfig = toml::from_str(&file_content)?;
+		let path = core.operation_log_path.as_ref().ok_or_else(|| {
+			anyhow!(
+				"An operation_log_path is required in {} to run {command} with an idempotency key",
+				self.config_core
+			)
+		})?;
+		// the log is locked while another operation runs
+		let operations = OperationLog::open(path)
+			.map_err(|e| anyhow!("Failed to open the operation log at {path}: {e:?}"))?;
+		let args = format!("{:?}", Cmd { idempotency_key: None, ..self.clone() });
+		if let Some(record) = operations.begin(key, command, &args)? {
+			log::info!(target: "hyperspace", "{command} with idempotency key {key} already completed at {}, skipping it", record.finished_at.unwrap_or(record.started_at));
+			return Ok(())
+		}
+		let result = operation.await;
+		operations.finish(key, result.as_ref().map(|_| ()).map_err(|e| format!("{e:?}")))?;
+		result
+	}
+
 	// todo: IntoClient, since clients are generic, users must configure clients themselves.
 	/// Run the command
-	pub async fn run(&self) -> Result<()> {
-		let config = self.parse_config().await?;
-		let chain_a = config.chain_a.into_client().await?;
-		let chain_b = config.chain_b.into_client().await?;
+	pub async fn run<C: ChainConfig>(&self) -> Result<()> {
+		let config = self.parse_config::<C>().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		if self.dry_run {
+			enable_dry_run(&mut chain_a);
+			enable_dry_run(&mut chain_b);
+		}
+		if let Some(store) = open_store(&config.core)? {
+			chain_a.common_state_mut().store = Some(store.scoped(chain_a.name()));
+			chain_b.common_state_mut().store = Some(store.scoped(chain_b.name()));
+		}
+		if let Some(leadership) = spawn_lease(&config.core) {
+			chain_a.common_state_mut().leadership = leadership.clone();
+			chain_b.common_state_mut().leadership = leadership;
+		}
 
 		let registry =
 			Registry::new_custom(None, None).expect("this can only fail if the prefix is empty");
-		let metrics_a = Metrics::register(chain_a.name(), &registry)?;
-		let metrics_b = Metrics::register(chain_b.name(), &registry)?;
-		let mut metrics_handler_a = MetricsHandler::new(registry.clone(), metrics_a);
-		let mut metrics_handler_b = MetricsHandler::new(registry.clone(), metrics_b);
-		metrics_handler_a.link_with_counterparty(&mut metrics_handler_b);
-
-		if let Some(addr) = config.core.prometheus_endpoint.and_then(|s| s.parse().ok()) {
-			tokio::spawn(init_prometheus(addr, registry.clone()));
-		}
+		let health = HealthState::new();
+		let (metrics_handler_a, metrics_handler_b) = register_metrics(
+			&registry,
+			&health,
+			chain_a.name().to_string(),
+			chain_b.name().to_string(),
+		)?;
+		let outstanding_packets = vec![
+			(chain_a.name().to_string(), metrics_handler_a.outstanding_packets()),
+			(chain_b.name().to_string(), metrics_handler_b.outstanding_packets()),
+		];
+		spawn_score(
+			format!("{}_{}", chain_a.name(), chain_b.name()),
+			&config.core,
+			&registry,
+			&health,
+			(&chain_a, &chain_b),
+			(&metrics_handler_a, &metrics_handler_b),
+		)?;
+		let services = spawn_services(&config.core, &registry, health, outstanding_packets).await?;
+		tokio::spawn(shutdown::listen_for_signals(shutdown_timeout(&config.core)));
+
+		let protocol = IbcPackets::new(config.core.filter.clone());
+		tokio::spawn(watch_config::<C>(
+			self.config_core.clone(),
+			vec![ReloadablePath {
+				name: None,
+				config_a: self.config_a.clone(),
+				config_b: self.config_b.clone(),
+				chain_a: chain_a.clone(),
+				chain_b: chain_b.clone(),
+				protocol: protocol.clone(),
+			}],
+			config_reload_interval(&config.core),
+		));
 
-		relay(chain_a, chain_b, Some(metrics_handler_a), Some(metrics_handler_b), None).await
+		let result = relay_path(
+			chain_a,
+			chain_b,
+			metrics_handler_a,
+			metrics_handler_b,
+			&config.core,
+			&protocol,
+			config.core.reconciliation_report_path.as_deref(),
+		)
+		.await;
+		services.flush().await;
+		result
 	}
 
 	/// Run fisherman
-	pub async fn fish(&self) -> Result<()> {
-		let config = self.parse_config().await?;
+	pub async fn fish<C: ChainConfig>(&self) -> Result<()> {
+		let config = self.parse_config::<C>().await?;
 		let chain_a = config.chain_a.into_client().await?;
 		let chain_b = config.chain_b.into_client().await?;
 
 		fish(chain_a, chain_b).await
 	}
 
-	pub async fn create_clients(&self) -> Result<Config> {
-		let mut config = self.parse_config().await?;
+	/// Run watchtower
+	pub async fn watch<C: ChainConfig>(&self) -> Result<()> {
+		let config = self.parse_config::<C>().await?;
+		let chain_a = config.chain_a.into_client().await?;
+		let chain_b = config.chain_b.into_client().await?;
+
+		watch(chain_a, chain_b).await
+	}
+
+	pub async fn create_clients<C: ChainConfig>(&self) -> Result<Config<C>> {
+		let mut config = self.parse_config::<C>().await?;
 		let mut chain_a = config.chain_a.clone().into_client().await?;
 		let mut chain_b = config.chain_b.clone().into_client().await?;
 
@@ -190,15 +569,16 @@ impl Cmd {
 		Ok(config)
 	}
 
-	pub async fn create_connection(&self) -> Result<Config> {
+	pub async fn create_connection<C: ChainConfig>(&self) -> Result<Config<C>> {
 		let delay_period_seconds: NonZeroU64 = self
 			.delay_period
 			.expect("delay_period should be provided when creating a connection")
 			.into();
 		let delay = Duration::from_secs(delay_period_seconds.into());
-		let mut config = self.parse_config().await?;
+		let mut config = self.parse_config::<C>().await?;
 		let mut chain_a = config.chain_a.clone().into_client().await?;
 		let mut chain_b = config.chain_b.clone().into_client().await?;
+		let handshake = find_connection_handshake(&chain_a, &chain_b, delay).await?;
 
 		let chain_a_clone = chain_a.clone();
 		let chain_b_clone = chain_b.clone();
@@ -208,8 +588,13 @@ impl Cmd {
 				.unwrap();
 		});
 
-		let (connection_id_a, connection_id_b) =
-			create_connection(&mut chain_a, &mut chain_b, delay).await?;
+		let (connection_id_a, connection_id_b) = match handshake {
+			Some(step) => {
+				log::info!(target: "hyperspace", "Found a connection handshake left half done: {step:?}");
+				resume_connection_handshake(&mut chain_a, &mut chain_b, step).await?
+			},
+			None => create_connection(&mut chain_a, &mut chain_b, delay).await?,
+		};
 		log::info!("ConnectionId on Chain {}: {}", chain_a.name(), connection_id_a);
 		log::info!("ConnectionId on Chain {}: {}", chain_b.name(), connection_id_b);
 		handle.abort();
@@ -220,7 +605,7 @@ impl Cmd {
 		Ok(config)
 	}
 
-	pub async fn create_channel(&self) -> Result<Config> {
+	pub async fn create_channel<C: ChainConfig>(&self) -> Result<Config<C>> {
 		let port_id = PortId::from_str(
 			self.port_id
 				.as_ref()
@@ -228,15 +613,33 @@ impl Cmd {
 				.as_str(),
 		)
 		.expect("Port id was invalid");
-		let version = self
-			.version
+		let app = ChannelApp::from_port(&port_id);
+		let order = self
+			.order
 			.as_ref()
-			.expect("version must be specified when creating a channel")
-			.clone();
-		let order = self.order.as_ref().expect("order must be specified when creating a channel, expected one of 'ordered' or 'unordered'").as_str();
-		let mut config = self.parse_config().await?;
+			.map(|order| Order::from_str(order).expect("Expected one of 'ordered' or 'unordered'"));
+		let order = app.channel_order(order)?.expect(
+			"order must be specified when creating a channel, expected one of 'ordered' or 'unordered'",
+		);
+		let mut config = self.parse_config::<C>().await?;
 		let mut chain_a = config.chain_a.clone().into_client().await?;
 		let mut chain_b = config.chain_b.clone().into_client().await?;
+		let connection_id = chain_a.connection_id().expect("Connection id should be defined");
+		let counterparty_connection_id =
+			chain_b.connection_id().expect("Connection id should be defined");
+		let version = app
+			.channel_version(self.version.clone(), &connection_id, &counterparty_connection_id)?
+			.expect("version must be specified when creating a channel");
+		let handshake = find_channel_handshake(
+			&chain_a,
+			&chain_b,
+			&connection_id,
+			&counterparty_connection_id,
+			&port_id,
+			&counterparty_port(&port_id),
+			order,
+		)
+		.await?;
 
 		let chain_a_clone = chain_a.clone();
 		let chain_b_clone = chain_b.clone();
@@ -246,28 +649,331 @@ impl Cmd {
 				.unwrap();
 		});
 
-		let order = Order::from_str(order).expect("Expected one of 'ordered' or 'unordered'");
-		let connection_id = chain_a.connection_id().expect("Connection id should be defined");
-		let (channel_id_a, channel_id_b) = create_channel(
-			&mut chain_a,
-			&mut chain_b,
-			connection_id,
-			port_id.clone(),
-			version,
-			order,
-		)
-		.await?;
+		let (channel_id_a, channel_id_b) = match handshake {
+			Some(step) => {
+				log::info!(target: "hyperspace", "Found a channel handshake left half done: {step:?}");
+				resume_channel_handshake(&mut chain_a, &mut chain_b, step).await?
+			},
+			None =>
+				create_channel(
+					&mut chain_a,
+					&mut chain_b,
+					connection_id,
+					port_id.clone(),
+					version,
+					order,
+				)
+				.await?,
+		};
 		log::info!("ChannelId on Chain {}: {}", chain_a.name(), channel_id_a);
 		log::info!("ChannelId on Chain {}: {}", chain_b.name(), channel_id_b);
 		handle.abort();
 
 		config.chain_a.set_channel_whitelist(channel_id_a, port_id.clone());
-		config.chain_b.set_channel_whitelist(channel_id_b, port_id);
+		config.chain_b.set_channel_whitelist(channel_id_b, counterparty_port(&port_id));
 
 		Ok(config)
 	}
 
-	pub async fn save_config(&self, new_config: &Config) -> Result<()> {
+	/// Relay the pending packets, acknowledgements and timeouts of a channel, then exit
+	pub async fn clear_packets<C: ChainConfig>(&self) -> Result<()> {
+		let port_id = PortId::from_str(
+			self.port_id
+				.as_ref()
+				.expect("port_id must be specified when clearing packets")
+				.as_str(),
+		)
+		.expect("Port id was invalid");
+		let channel_id = ChannelId::from_str(
+			self.channel_id
+				.as_ref()
+				.expect("channel_id must be specified when clearing packets")
+				.as_str(),
+		)
+		.expect("Channel id was invalid");
+		let sequences = match (self.sequence_start, self.sequence_end) {
+			(None, None) => None,
+			(start, end) => Some(start.unwrap_or(1)..=end.unwrap_or(u64::MAX)),
+		};
+		let config = self.parse_config::<C>().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		if self.dry_run {
+			enable_dry_run(&mut chain_a);
+			enable_dry_run(&mut chain_b);
+		}
+
+		let channel_end = query_channel_end(&chain_a, channel_id, port_id.clone()).await?;
+		let counterparty_port_id = channel_end.counterparty().port_id.clone();
+		let counterparty_channel_id = channel_end
+			.counterparty()
+			.channel_id
+			.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} has no counterparty"))?;
+		chain_a.set_channel_whitelist([(channel_id, port_id.clone())].into_iter().collect());
+		chain_b.set_channel_whitelist(
+			[(counterparty_channel_id, counterparty_port_id.clone())].into_iter().collect(),
+		);
+
+		let protocol_a = IbcPackets::new(PacketFilter {
+			allow: vec![(channel_id, port_id)],
+			deny: vec![],
+			sequences: sequences.clone(),
+		});
+		let protocol_b = IbcPackets::new(PacketFilter {
+			allow: vec![(counterparty_channel_id, counterparty_port_id)],
+			deny: vec![],
+			sequences,
+		});
+		refresh_batch_limits(&mut chain_a).await;
+		refresh_batch_limits(&mut chain_b).await;
+		clear_all_packets(&mut chain_a, &mut chain_b, &protocol_a, &protocol_b).await
+	}
+
+	/// Print the pending packets of the channels between both chains, or of the channel on chain A
+	/// given with its counterparty, then exit
+	pub async fn query_pending_packets<C: ChainConfig>(&self) -> Result<()> {
+		let config = self.parse_config::<C>().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		if let (Some(channel_id), Some(port_id)) = (&self.channel_id, &self.port_id) {
+			let channel_id = ChannelId::from_str(channel_id).expect("Channel id was invalid");
+			let port_id = PortId::from_str(port_id).expect("Port id was invalid");
+			let channel_end = query_channel_end(&chain_a, channel_id, port_id.clone()).await?;
+			let counterparty = channel_end.counterparty();
+			let counterparty_channel_id = counterparty
+				.channel_id
+				.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} has no counterparty"))?;
+			chain_a.set_channel_whitelist([(channel_id, port_id)].into_iter().collect());
+			chain_b.set_channel_whitelist(
+				[(counterparty_channel_id, counterparty.port_id.clone())].into_iter().collect(),
+			);
+		}
+
+		let timeout_window = self
+			.timeout_window_secs
+			.map(Duration::from_secs)
+			.unwrap_or(DEFAULT_TIMEOUT_WINDOW);
+		for channel in query_pending_packets(&chain_a, &chain_b, timeout_window).await? {
+			print!("{channel}");
+		}
+		Ok(())
+	}
+
+	/// Print the status of the clients of both chains, then exit
+	pub async fn query_client_status<C: ChainConfig>(&self) -> Result<()> {
+		let config = self.parse_config::<C>().await?;
+		let chain_a = config.chain_a.into_client().await?;
+		let chain_b = config.chain_b.into_client().await?;
+		for status in query_client_statuses(&chain_a, &chain_b).await? {
+			print!("{status}");
+		}
+		Ok(())
+	}
+
+	/// Register the payees of the fees earned by the relayer on a fee-enabled channel, see
+	/// [`register_payees`]
+	pub async fn register_payees<C: ChainConfig>(&self) -> Result<()> {
+		let port_id = PortId::from_str(
+			self.port_id
+				.as_ref()
+				.expect("port_id must be specified when registering payees")
+				.as_str(),
+		)
+		.expect("Port id was invalid");
+		let channel_id = ChannelId::from_str(
+			self.channel_id
+				.as_ref()
+				.expect("channel_id must be specified when registering payees")
+				.as_str(),
+		)
+		.expect("Channel id was invalid");
+		let config = self.parse_config::<C>().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		if self.dry_run {
+			enable_dry_run(&mut chain_a);
+			enable_dry_run(&mut chain_b);
+		}
+		register_payees(&chain_a, &chain_b, channel_id, port_id).await
+	}
+
+	/// Close a channel on chain A, wait for the counterparty end to be closed and time out the
+	/// pending packets on close, then exit
+	pub async fn close_channel<C: ChainConfig>(&self) -> Result<()> {
+		let port_id = PortId::from_str(
+			self.port_id
+				.as_ref()
+				.expect("port_id must be specified when closing a channel")
+				.as_str(),
+		)
+		.expect("Port id was invalid");
+		let channel_id = ChannelId::from_str(
+			self.channel_id
+				.as_ref()
+				.expect("channel_id must be specified when closing a channel")
+				.as_str(),
+		)
+		.expect("Channel id was invalid");
+		let config = self.parse_config::<C>().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		if self.dry_run {
+			enable_dry_run(&mut chain_a);
+			enable_dry_run(&mut chain_b);
+		}
+
+		let channel_end = query_channel_end(&chain_a, channel_id, port_id.clone()).await?;
+		if channel_end.state != State::Open {
+			return Err(anyhow!(
+				"Channel {channel_id}/{port_id} on {} is {}, only open channels can be closed",
+				chain_a.name(),
+				channel_end.state
+			))
+		}
+		let counterparty_port_id = channel_end.counterparty().port_id.clone();
+		let counterparty_channel_id = channel_end
+			.counterparty()
+			.channel_id
+			.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} has no counterparty"))?;
+		chain_a.set_channel_whitelist([(channel_id, port_id.clone())].into_iter().collect());
+		chain_b.set_channel_whitelist(
+			[(counterparty_channel_id, counterparty_port_id.clone())].into_iter().collect(),
+		);
+
+		close_channel(&chain_a, channel_id, port_id.clone()).await?;
+		if self.dry_run {
+			log::info!(target: "hyperspace", "[dry run] Simulated the closing of {channel_id}/{port_id} on {}", chain_a.name());
+			return Ok(())
+		}
+		log::info!(target: "hyperspace", "Closed {channel_id}/{port_id} on {}, waiting for {counterparty_channel_id}/{counterparty_port_id} to be closed on {}", chain_a.name(), chain_b.name());
+
+		// the light relayer submits the ChanCloseConfirm to chain B, the light clients are then
+		// updated by the clearing itself while the packets are timed out
+		let chain_a_clone = chain_a.clone();
+		let chain_b_clone = chain_b.clone();
+		let handle = tokio::task::spawn(async move {
+			relay_with_protocol(
+				chain_a_clone,
+				chain_b_clone,
+				None,
+				None,
+				Some(Mode::Light),
+				&(),
+				None,
+			)
+			.await
+			.unwrap();
+		});
+
+		let result = async {
+			tokio::time::timeout(CLOSE_CONFIRM_TIMEOUT, async {
+				loop {
+					tokio::time::sleep(chain_b.expected_block_time()).await;
+					match query_channel_end(
+						&chain_b,
+						counterparty_channel_id,
+						counterparty_port_id.clone(),
+					)
+					.await
+					{
+						Ok(channel_end) if channel_end.state == State::Closed => break,
+						Ok(_) => (),
+						Err(e) => log::warn!(target: "hyperspace", "Failed to query the counterparty channel: {e:?}"),
+					}
+				}
+			})
+			.await
+			.map_err(|_| {
+				anyhow!(
+					"{counterparty_channel_id}/{counterparty_port_id} wasn't closed on {} within {CLOSE_CONFIRM_TIMEOUT:?}",
+					chain_b.name()
+				)
+			})?;
+			handle.abort();
+			log::info!(target: "hyperspace", "Channel closed on both chains, timing out the pending packets");
+
+			let protocol_a = IbcPackets::new(PacketFilter {
+				allow: vec![(channel_id, port_id)],
+				deny: vec![],
+				sequences: None,
+			});
+			let protocol_b = IbcPackets::new(PacketFilter {
+				allow: vec![(counterparty_channel_id, counterparty_port_id)],
+				deny: vec![],
+				sequences: None,
+			});
+			refresh_batch_limits(&mut chain_a).await;
+			refresh_batch_limits(&mut chain_b).await;
+			clear_all_packets(&mut chain_a, &mut chain_b, &protocol_a, &protocol_b).await
+		}
+		.await;
+		handle.abort();
+		result
+	}
+
+	/// Upgrade the client of chain A on chain B once chain A is upgraded, then exit
+	pub async fn upgrade_clients<C: ChainConfig>(&self) -> Result<()> {
+		let config = self.parse_config::<C>().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let chain_b = config.chain_b.into_client().await?;
+
+		let upgrade_height = match self.upgrade_height {
+			Some(height) => height,
+			None =>
+				chain_a
+					.query_scheduled_upgrade_height()
+					.await?
+					.ok_or_else(|| {
+						anyhow!("No upgrade scheduled on {}, set --upgrade-height", chain_a.name())
+					})?
+					.revision_height,
+		};
+		if self.dry_run {
+			log::info!(
+				target: "hyperspace",
+				"[dry run] {} would be upgraded on {} at {upgrade_height}",
+				chain_a.client_id(),
+				chain_b.name()
+			);
+			return Ok(())
+		}
+		log::info!(
+			target: "hyperspace",
+			"Upgrading {} on {} once {} reaches {upgrade_height}",
+			chain_a.client_id(),
+			chain_b.name(),
+			chain_a.name()
+		);
+		upgrade_client(&mut chain_a, &chain_b, upgrade_height).await
+	}
+
+	/// Close the channel handshakes stuck in INIT or TRYOPEN on both chains, then exit
+	pub async fn abort_handshakes<C: ChainConfig>(&self) -> Result<()> {
+		let min_age = Duration::from_secs(self.min_age_secs.unwrap_or(DEFAULT_HANDSHAKE_MIN_AGE));
+		let config = self.parse_config::<C>().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		if self.dry_run {
+			enable_dry_run(&mut chain_a);
+			enable_dry_run(&mut chain_b);
+		}
+
+		let abandoned_a = find_abandoned_handshakes(&chain_a, &chain_b, min_age).await?;
+		let abandoned_b = find_abandoned_handshakes(&chain_b, &chain_a, min_age).await?;
+		log::info!(
+			target: "hyperspace",
+			"Found {} abandoned handshakes on {} and {} on {}",
+			abandoned_a.len(),
+			chain_a.name(),
+			abandoned_b.len(),
+			chain_b.name()
+		);
+		abort_handshakes(&chain_a, abandoned_a).await;
+		abort_handshakes(&chain_b, abandoned_b).await;
+		Ok(())
+	}
+
+	pub async fn save_config<C: Serialize>(&self, new_config: &Config<C>) -> Result<()> {
 		let path_a = self.out_config_a.as_ref().cloned().unwrap_or_else(|| self.config_a.clone());
 		let path_b = self.out_config_b.as_ref().cloned().unwrap_or_else(|| self.config_b.clone());
 		write_config(path_a, &new_config.chain_a).await?;
@@ -275,8 +981,409 @@ impl Cmd {
 	}
 }
 
-async fn write_config(path: String, config: &AnyConfig) -> Result<()> {
+async fn write_config<C: Serialize>(path: String, config: &C) -> Result<()> {
 	tokio::fs::write(path.parse::<PathBuf>()?, toml::to_string(config)?)
 		.await
 		.map_err(|e| anyhow!(e))
 }
+
+impl RelayPathsCmd {
+	/// Relay the paths of the core config, each over its own relayer loop
+	pub async fn run<C: ChainConfig>(&self) -> Result<()> {
+		let file_content = tokio::fs::read_to_string(self.config_core.parse::<PathBuf>()?).await?;
+		let core: CoreConfig = toml::from_str(&file_content)?;
+		if core.paths.is_empty() {
+			return Err(anyhow!("No paths configured in {}", self.config_core))
+		}
+
+		let store = open_store(&core)?;
+		let leadership = spawn_lease(&core);
+		let registry =
+			Registry::new_custom(None, None).expect("this can only fail if the prefix is empty");
+		let health = HealthState::new();
+		let mut paths = vec![];
+		let mut outstanding_packets = vec![];
+		let mut reloadable_paths = vec![];
+		for path in &core.paths {
+			let config_a: C = toml::from_str(&tokio::fs::read_to_string(&path.config_a).await?)?;
+			let config_b: C = toml::from_str(&tokio::fs::read_to_string(&path.config_b).await?)?;
+			let mut chain_a = config_a.into_client().await?;
+			let mut chain_b = config_b.into_client().await?;
+			if self.dry_run {
+				enable_dry_run(&mut chain_a);
+				enable_dry_run(&mut chain_b);
+			}
+			let (name_a, name_b) = (
+				format!("{}_{}", path.name, chain_a.name()),
+				format!("{}_{}", path.name, chain_b.name()),
+			);
+			if let Some(store) = &store {
+				chain_a.common_state_mut().store = Some(store.scoped(&name_a));
+				chain_b.common_state_mut().store = Some(store.scoped(&name_b));
+			}
+			if let Some(leadership) = &leadership {
+				chain_a.common_state_mut().leadership = leadership.clone();
+				chain_b.common_state_mut().leadership = leadership.clone();
+			}
+			if let Some(confirmations) = path.finality_confirmations_a {
+				chain_a.common_state_mut().finality_confirmations = confirmations;
+			}
+			if let Some(confirmations) = path.finality_confirmations_b {
+				chain_b.common_state_mut().finality_confirmations = confirmations;
+			}
+			let (metrics_handler_a, metrics_handler_b) =
+				register_metrics(&registry, &health, name_a.clone(), name_b.clone())?;
+			outstanding_packets.push((name_a, metrics_handler_a.outstanding_packets()));
+			outstanding_packets.push((name_b, metrics_handler_b.outstanding_packets()));
+			spawn_score(
+				path.name.clone(),
+				&core,
+				&registry,
+				&health,
+				(&chain_a, &chain_b),
+				(&metrics_handler_a, &metrics_handler_b),
+			)?;
+			let protocol = IbcPackets::new(path.filter.clone());
+			reloadable_paths.push(ReloadablePath {
+				name: Some(path.name.clone()),
+				config_a: path.config_a.clone(),
+				config_b: path.config_b.clone(),
+				chain_a: chain_a.clone(),
+				chain_b: chain_b.clone(),
+				protocol: protocol.clone(),
+			});
+			paths.push((path, chain_a, chain_b, metrics_handler_a, metrics_handler_b, protocol));
+		}
+		let services = spawn_services(&core, &registry, health, outstanding_packets).await?;
+		tokio::spawn(shutdown::listen_for_signals(shutdown_timeout(&core)));
+		tokio::spawn(watch_config::<C>(
+			self.config_core.clone(),
+			reloadable_paths,
+			config_reload_interval(&core),
+		));
+
+		// the paths are relayed independently, a path that stops doesn't stop the others
+		let results = futures::future::join_all(paths.into_iter().map(
+			|(path, chain_a, chain_b, metrics_handler_a, metrics_handler_b, protocol)| {
+				let core = &core;
+				async move {
+					log::info!(target: "hyperspace", "Relaying path {}", path.name);
+					let result = relay_path(
+						chain_a,
+						chain_b,
+						metrics_handler_a,
+						metrics_handler_b,
+						core,
+						&protocol,
+						path.reconciliation_report_path.as_deref(),
+					)
+					.await;
+					if let Err(e) = &result {
+						log::error!(target: "hyperspace", "Relaying path {} failed: {e:?}", path.name);
+					}
+					result
+				}
+			},
+		))
+		.await;
+		services.flush().await;
+		results.into_iter().collect()
+	}
+}
+
+#[cfg(feature = "cosmos")]
+impl MultisigSignCmd {
+	/// Signs the sign doc, writing the signature next to it
+	pub async fn run(&self) -> Result<()> {
+		use cosmos::{client::MnemonicEntry, key_provider::KeyEntry, multisig::ExportedSignDoc};
+		let sign_doc = ExportedSignDoc::read(&self.sign_doc).await?;
+		println!("{}", sign_doc.describe());
+		let mnemonic = tokio::fs::read_to_string(&self.mnemonic_file).await?;
+		let key = KeyEntry::try_from(MnemonicEntry {
+			mnemonic: mnemonic.trim().to_string(),
+			// the account of the member isn't used
+			prefix: "cosmos".to_string(),
+			key_type: Default::default(),
+		})
+		.map_err(|e| anyhow!("Invalid mnemonic: {e}"))?;
+		let signature = sign_doc.sign(&key).await?;
+		println!("{}", signature.display());
+		Ok(())
+	}
+}
+
+#[cfg(feature = "cosmos")]
+impl MultisigBroadcastCmd {
+	/// Broadcasts the transaction of the sign doc with the collected signatures
+	pub async fn run(&self) -> Result<()> {
+		let sign_doc = cosmos::multisig::ExportedSignDoc::read(&self.sign_doc).await?;
+		let hash = sign_doc.broadcast(self.rpc_url.parse()?).await?;
+		println!("{hash}");
+		Ok(())
+	}
+}
+
+impl StatusCmd {
+	/// Print the `/status` report of the relayer
+	pub async fn run(&self) -> Result<()> {
+		let file_content = tokio::fs::read_to_string(self.config_core.parse::<PathBuf>()?).await?;
+		let core: CoreConfig = toml::from_str(&file_content)?;
+		let addr = core
+			.prometheus_endpoint
+			.as_ref()
+			.and_then(|s| s.parse().ok())
+			.ok_or_else(|| anyhow!("No valid prometheus_endpoint in {}", self.config_core))?;
+		print!("{}", query_status(addr).await?);
+		Ok(())
+	}
+}
+
+/// Makes the chain simulate the messages it would have been sent instead of submitting them.
+fn enable_dry_run(chain: &mut impl Chain) {
+	log::info!(target: "hyperspace", "[dry run] Messages to {} are simulated, not submitted", chain.name());
+	chain.common_state_mut().dry_run = true;
+}
+
+/// Queries the channel end at the latest height of the chain.
+async fn query_channel_end(
+	chain: &impl Chain,
+	channel_id: ChannelId,
+	port_id: PortId,
+) -> Result<ChannelEnd> {
+	let (height, _) = chain.latest_height_and_timestamp().await?;
+	chain
+		.query_channel_end(height, channel_id, port_id.clone())
+		.await?
+		.channel
+		.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} not found on {}", chain.name()))
+		.and_then(|channel| ChannelEnd::try_from(channel).map_err(|e| anyhow!("{e:?}")))
+}
+
+/// Opens the state store of the core config, if any.
+fn open_store(core: &CoreConfig) -> Result<Option<RelayerStore>> {
+	core.state_store_path
+		.as_ref()
+		.map(|path| {
+			log::info!(target: "hyperspace", "Using the state store at {path}");
+			RelayerStore::open(path)
+		})
+		.transpose()
+}
+
+/// Spawns the task holding the lease of the core config, if any. The chains sharing the returned
+/// leadership only submit messages while the relayer holds the lease.
+fn spawn_lease(core: &CoreConfig) -> Option<Leadership> {
+	let config = core.leader_lease.clone()?;
+	let leadership = Leadership::standby(config.ttl());
+	tokio::spawn(run_lease(config, leadership.clone()));
+	Some(leadership)
+}
+
+/// Registers the metrics of both chains of a path under the given names, and links them.
+fn register_metrics(
+	registry: &Registry,
+	health: &HealthState,
+	name_a: String,
+	name_b: String,
+) -> Result<(MetricsHandler, MetricsHandler)> {
+	let metrics_a = Metrics::register(&name_a, registry)?;
+	let metrics_b = Metrics::register(&name_b, registry)?;
+	let mut metrics_handler_a =
+		MetricsHandler::new(registry.clone(), metrics_a).with_health(&name_a, health.clone());
+	let mut metrics_handler_b =
+		MetricsHandler::new(registry.clone(), metrics_b).with_health(&name_b, health.clone());
+	metrics_handler_a.link_with_counterparty(&mut metrics_handler_b);
+	Ok((metrics_handler_a, metrics_handler_b))
+}
+
+/// Registers the health score gauges of a path, and spawns the task scoring it.
+fn spawn_score<A: Chain, B: Chain>(
+	path: String,
+	core: &CoreConfig,
+	registry: &Registry,
+	health: &HealthState,
+	(chain_a, chain_b): (&A, &B),
+	(metrics_handler_a, metrics_handler_b): (&MetricsHandler, &MetricsHandler),
+) -> Result<()> {
+	let metrics = RouteHealthMetrics::register(&path, registry)?;
+	tokio::spawn(score_path(
+		path,
+		chain_a.clone(),
+		chain_b.clone(),
+		(metrics_handler_a.score_inputs(), metrics_handler_b.score_inputs()),
+		core.health_score.clone(),
+		metrics,
+		health.clone(),
+	));
+	Ok(())
+}
+
+/// Handle to the services that flush their state when the relayer stops.
+#[derive(Default)]
+struct Services {
+	/// Stops the StatsD exporter, whose task returns after a last flush of the metrics
+	statsd: Option<(oneshot::Sender<()>, JoinHandle<Result<(), metrics::Error>>)>,
+}
+
+impl Services {
+	/// Flushes the metrics, once the relayer loops have returned.
+	async fn flush(self) {
+		if let Some((stop, task)) = self.statsd {
+			let _ = stop.send(());
+			if let Ok(Err(e)) = task.await {
+				log::warn!(target: "hyperspace", "StatsD exporter failed: {e:?}");
+			}
+		}
+	}
+}
+
+/// Spawns the services shared by all the paths relayed by the process: the runtime metrics, the
+/// Prometheus and StatsD exporters, the introspection service and the event stream.
+#[cfg_attr(not(feature = "introspection"), allow(unused_variables))]
+async fn spawn_services(
+	core: &CoreConfig,
+	registry: &Registry,
+	health: HealthState,
+	outstanding_packets: Vec<(String, OutstandingPackets)>,
+) -> Result<Services> {
+	#[cfg_attr(not(feature = "statsd"), allow(unused_mut))]
+	let mut services = Services::default();
+	#[cfg(tokio_unstable)]
+	tokio::spawn(
+		metrics::runtime::RuntimeMetrics::register(registry)?
+			.observe(metrics::runtime::RUNTIME_METRICS_INTERVAL),
+	);
+	#[cfg(not(tokio_unstable))]
+	if core.prometheus_endpoint.is_some() || core.statsd_endpoint.is_some() {
+		log::warn!("The tokio runtime metrics are not exported, the relayer was built without `RUSTFLAGS=\"--cfg tokio_unstable\"`");
+	}
+
+	if let Some(addr) = core.prometheus_endpoint.as_ref().and_then(|s| s.parse().ok()) {
+		tokio::spawn(init_prometheus(addr, registry.clone(), health));
+	}
+
+	if let Some(addr) = core.statsd_endpoint.as_ref() {
+		#[cfg(feature = "statsd")]
+		{
+			let addr = tokio::net::lookup_host(addr)
+				.await?
+				.next()
+				.ok_or_else(|| anyhow!("Invalid StatsD endpoint {addr}"))?;
+			let interval = Duration::from_secs(
+				core.statsd_flush_interval_secs.unwrap_or(DEFAULT_STATSD_FLUSH_INTERVAL),
+			);
+			let (stop, stopped) = oneshot::channel();
+			let task = tokio::spawn(metrics::statsd::init_statsd(
+				addr,
+				core.statsd_prefix.clone(),
+				registry.clone(),
+				interval,
+				async move {
+					let _ = stopped.await;
+				},
+			));
+			services.statsd = Some((stop, task));
+		}
+		#[cfg(not(feature = "statsd"))]
+		log::warn!("StatsD endpoint {addr} is configured, but the relayer was built without the `statsd` feature");
+	}
+
+	if let Some(addr) = core.introspection_endpoint.as_ref() {
+		#[cfg(feature = "introspection")]
+		{
+			let addr = addr.parse()?;
+			tokio::spawn(metrics::introspection::init_introspection(addr, outstanding_packets));
+		}
+		#[cfg(not(feature = "introspection"))]
+		log::warn!("Introspection endpoint {addr} is configured, but the relayer was built without the `introspection` feature");
+	}
+
+	if let Some(addr) = core.event_stream_endpoint.as_ref() {
+		#[cfg(feature = "event-stream")]
+		{
+			let addr = addr.parse()?;
+			tokio::spawn(async move {
+				if let Err(e) = crate::event_stream::serve(addr).await {
+					log::error!(target: "hyperspace", "Event stream server failed: {e:?}");
+				}
+			});
+		}
+		#[cfg(not(feature = "event-stream"))]
+		log::warn!("Event stream endpoint {addr} is configured, but the relayer was built without the `event-stream` feature");
+	}
+
+	Ok(services)
+}
+
+/// Reconciles the state of the chains of a path, then relays it.
+async fn relay_path<A: Chain, B: Chain>(
+	chain_a: A,
+	chain_b: B,
+	metrics_handler_a: MetricsHandler,
+	metrics_handler_b: MetricsHandler,
+	core: &CoreConfig,
+	protocol: &IbcPackets,
+	reconciliation_report_path: Option<&str>,
+) -> Result<()> {
+	let clear_interval = match core.packet_clear_interval_secs {
+		Some(0) => None,
+		Some(secs) => Some(Duration::from_secs(secs)),
+		None => Some(DEFAULT_CLEAR_INTERVAL),
+	};
+	let clearing = clear_interval.map(|interval| {
+		let mut clearing = ClearingConfig::new(interval);
+		if let Some(weight) = core.finality_weight {
+			clearing.finality_weight = weight;
+		}
+		if let Some(weight) = core.clearing_weight {
+			clearing.clearing_weight = weight;
+		}
+		if let Some(rounds) = core.max_clearing_rounds {
+			clearing.max_rounds = rounds;
+		}
+		match core.sequence_audit_interval_secs {
+			Some(0) => clearing.audit_interval = None,
+			Some(secs) => clearing.audit_interval = Some(Duration::from_secs(secs)),
+			None => (),
+		}
+		clearing
+	});
+
+	match reconcile(&chain_a, &chain_b, protocol).await {
+		Ok(report) => {
+			report.log();
+			report.apply_metrics(&metrics_handler_a, &metrics_handler_b);
+			if let Some(path) = reconciliation_report_path {
+				if let Err(e) = report.write_json(path).await {
+					log::warn!("Failed to write the reconciliation report to {path}: {e:?}");
+				}
+			}
+		},
+		Err(e) => log::warn!("Failed to reconcile the state of the chains: {e:?}"),
+	}
+
+	relay_with_protocol(
+		chain_a,
+		chain_b,
+		Some(metrics_handler_a),
+		Some(metrics_handler_b),
+		None,
+		protocol,
+		clearing,
+	)
+	.await
+}
+
+fn shutdown_timeout(core: &CoreConfig) -> Duration {
+	core.shutdown_timeout_secs
+		.map(Duration::from_secs)
+		.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT)
+}
+
+fn config_reload_interval(core: &CoreConfig) -> Option<Duration> {
+	match core.config_reload_interval_secs {
+		Some(0) => None,
+		Some(secs) => Some(Duration::from_secs(secs)),
+		None => Some(DEFAULT_CONFIG_RELOAD_INTERVAL),
+	}
+}
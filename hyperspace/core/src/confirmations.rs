@@ -0,0 +1,104 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Confirmations of the finality notifications of a chain, see
+//! [`primitives::FinalityConfirmations`].
+//!
+//! The notifications are timestamped when they're received, along with the latest height of the
+//! chain if blocks are awaited, and released in order once their confirmations are reached. A
+//! notification waits from the time it was received rather than from the release of the previous
+//! one, so the stream lags behind the chain by the confirmations instead of a growing backlog.
+
+use futures::{channel::mpsc, Stream, StreamExt};
+use primitives::{Chain, FinalityConfirmations};
+use std::{
+	pin::Pin,
+	time::{Duration, Instant},
+};
+
+/// Delays the finality notifications of the chain until their confirmations are reached, or
+/// returns the stream as is if the chain doesn't require any.
+pub(crate) fn confirmed<C: Chain>(
+	chain: &C,
+	mut stream: Pin<Box<dyn Stream<Item = C::FinalityEvent> + Send + Sync>>,
+) -> Pin<Box<dyn Stream<Item = C::FinalityEvent> + Send>> {
+	let confirmations = chain.common_state().finality_confirmations;
+	if !confirmations.is_set() {
+		return stream
+	}
+	log::info!(
+		target: "hyperspace",
+		"Relaying the finality notifications of {} after {} more blocks and {} seconds",
+		chain.name(), confirmations.blocks.unwrap_or_default(), confirmations.secs.unwrap_or_default()
+	);
+	let (tx, rx) = mpsc::unbounded();
+	let source = chain.clone();
+	tokio::spawn(async move {
+		while let Some(event) = stream.next().await {
+			let received = Instant::now();
+			let height = match confirmations.blocks {
+				Some(_) => latest_height(&source).await,
+				None => 0,
+			};
+			if tx.unbounded_send((received, height, event)).is_err() {
+				break
+			}
+		}
+	});
+	let chain = chain.clone();
+	Box::pin(rx.then(move |(received, height, event)| {
+		let chain = chain.clone();
+		async move {
+			wait_for_confirmations(&chain, confirmations, received, height).await;
+			event
+		}
+	}))
+}
+
+/// Waits until `secs` seconds went by since the notification was received, and the chain is
+/// `blocks` blocks past its height at the time.
+async fn wait_for_confirmations<C: Chain>(
+	chain: &C,
+	confirmations: FinalityConfirmations,
+	received: Instant,
+	height: u64,
+) {
+	if let Some(secs) = confirmations.secs {
+		let deadline = received + Duration::from_secs(secs);
+		tokio::time::sleep_until(deadline.into()).await;
+	}
+	if let Some(blocks) = confirmations.blocks {
+		let target = height.saturating_add(blocks);
+		while latest_height(chain).await < target {
+			tokio::time::sleep(chain.expected_block_time()).await;
+		}
+	}
+	log::debug!(
+		target: "hyperspace",
+		"Finality notification of {} confirmed after {:?}", chain.name(), received.elapsed()
+	);
+}
+
+/// Latest height of the chain, queried again every block time until it succeeds.
+async fn latest_height<C: Chain>(chain: &C) -> u64 {
+	loop {
+		match chain.latest_height_and_timestamp().await {
+			Ok((height, _)) => return height.revision_height,
+			Err(e) => {
+				log::warn!(target: "hyperspace", "Failed to query the latest height of {}: {e:?}", chain.name());
+				tokio::time::sleep(chain.expected_block_time()).await;
+			},
+		}
+	}
+}
@@ -0,0 +1,141 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stream of the relay events, for the indexers and UIs following the activity of the relayer.
+//!
+//! The submissions of the relayer are published as [`RelayEvent`]s, which the WebSocket server
+//! started with `event_stream_endpoint` (requires the `event-stream` feature) sends to its
+//! clients as JSON text messages. The events are dropped while no client is connected, and a
+//! client lagging more than [`EVENT_BUFFER`] events behind misses the oldest ones.
+
+use ibc::core::ics02_client::msgs::update_client::TYPE_URL as UPDATE_CLIENT_TYPE_URL;
+use ibc_proto::{google::protobuf::Any, ibc::core::client::v1::MsgUpdateClient};
+use once_cell::sync::Lazy;
+use primitives::{store::decode_packet, MessageKind};
+use prost::Message;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// Number of events buffered for the clients of the stream.
+pub const EVENT_BUFFER: usize = 1024;
+
+static EVENTS: Lazy<broadcast::Sender<RelayEvent>> =
+	Lazy::new(|| broadcast::channel(EVENT_BUFFER).0);
+
+/// An event of the relayer.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayEvent {
+	/// Unix timestamp of the event, in milliseconds
+	pub timestamp_ms: u64,
+	/// The chain the messages were submitted to
+	pub chain: String,
+	#[serde(flatten)]
+	pub kind: RelayEventKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RelayEventKind {
+	/// A transaction of the relayer was included
+	TxSubmitted { tx_id: String, messages: usize },
+	/// A packet message was delivered by a transaction of the relayer
+	PacketRelayed { kind: MessageKind, port_id: String, channel_id: String, sequence: u64 },
+	/// A client hosted on the chain was updated by a transaction of the relayer
+	ClientUpdated { client_id: String },
+	/// A submission of the relayer failed
+	Error { message: String },
+}
+
+/// Publishes an event to the clients of the stream, if any.
+pub fn publish(chain: &str, kind: RelayEventKind) {
+	if EVENTS.receiver_count() == 0 {
+		return
+	}
+	let timestamp_ms = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|elapsed| elapsed.as_millis() as u64)
+		.unwrap_or_default();
+	// the receivers may have disconnected since
+	let _ = EVENTS.send(RelayEvent { timestamp_ms, chain: chain.to_string(), kind });
+}
+
+/// Publishes the transaction that delivered a batch of messages, and its packet messages and
+/// client updates.
+pub fn publish_submission(chain: &str, tx_id: String, batch: &[Any]) {
+	if EVENTS.receiver_count() == 0 {
+		return
+	}
+	publish(chain, RelayEventKind::TxSubmitted { tx_id, messages: batch.len() });
+	for msg in batch {
+		if msg.type_url == UPDATE_CLIENT_TYPE_URL {
+			if let Ok(update) = MsgUpdateClient::decode(&*msg.value) {
+				publish(chain, RelayEventKind::ClientUpdated { client_id: update.client_id });
+			}
+			continue
+		}
+		let (Some(kind), Some(packet)) = (MessageKind::of(msg), decode_packet(msg)) else {
+			continue
+		};
+		publish(
+			chain,
+			RelayEventKind::PacketRelayed {
+				kind,
+				port_id: packet.source_port.to_string(),
+				channel_id: packet.source_channel.to_string(),
+				sequence: packet.sequence.into(),
+			},
+		);
+	}
+}
+
+/// Serves the stream of the relay events over WebSocket.
+#[cfg(feature = "event-stream")]
+pub async fn serve(addr: std::net::SocketAddr) -> anyhow::Result<()> {
+	use futures::SinkExt;
+	use tokio::net::TcpListener;
+
+	let listener = TcpListener::bind(addr).await?;
+	log::info!(target: "hyperspace", "Streaming the relay events on ws://{addr}");
+	loop {
+		let (stream, peer) = listener.accept().await?;
+		let mut events = EVENTS.subscribe();
+		tokio::spawn(async move {
+			let mut ws = match async_tungstenite::tokio::accept_async(stream).await {
+				Ok(ws) => ws,
+				Err(e) => {
+					log::debug!(target: "hyperspace", "Failed to accept the event stream client {peer}: {e:?}");
+					return
+				},
+			};
+			log::debug!(target: "hyperspace", "Event stream client {peer} connected");
+			loop {
+				let event = match events.recv().await {
+					Ok(event) => event,
+					Err(broadcast::error::RecvError::Lagged(missed)) => {
+						log::warn!(target: "hyperspace", "Event stream client {peer} missed {missed} events");
+						continue
+					},
+					Err(broadcast::error::RecvError::Closed) => break,
+				};
+				let json =
+					serde_json::to_string(&event).expect("the relay events are serializable; qed");
+				if ws.send(async_tungstenite::tungstenite::Message::Text(json)).await.is_err() {
+					break
+				}
+			}
+			log::debug!(target: "hyperspace", "Event stream client {peer} disconnected");
+		});
+	}
+}
@@ -14,7 +14,7 @@
 
 #[cfg(feature = "testing")]
 use crate::send_packet_relay::packet_relay_status;
-use crate::Mode;
+use crate::{channel_upgrades::parse_upgrade_event, protocol::MessagingProtocol, Mode};
 use codec::Encode;
 use ibc::{
 	core::{
@@ -44,7 +44,12 @@ use ibc::{
 };
 use ibc_proto::google::protobuf::Any;
 use pallet_ibc::light_clients::AnyClientState;
-use primitives::{error::Error, mock::LocalClientTypes, Chain};
+use primitives::{
+	correlation::{packet_id, PACKET_TAG},
+	error::Error,
+	mock::LocalClientTypes,
+	Chain,
+};
 use std::str::FromStr;
 use tendermint_proto::Protobuf;
 
@@ -512,7 +517,11 @@ pub async fn parse_events(
 				let value = msg.encode_vec()?;
 				let msg = Any { value, type_url: msg.type_url() };
 				messages.push(msg);
-				log::debug!(target: "hyperspace", "Sending packet {:?}", packet);
+				log::debug!(
+					target: "hyperspace",
+					"{PACKET_TAG}{} Built the packet message from the send packet event on {} at {}",
+					packet_id(&packet), source.name(), send_packet.height
+				);
 			},
 			IbcEvent::WriteAcknowledgement(write_ack) => {
 				let port_id = &write_ack.packet.destination_port.clone();
@@ -558,6 +567,11 @@ pub async fn parse_events(
 					.expect("Proof height should be present");
 				let proof_height =
 					Height::new(proof_height.revision_number, proof_height.revision_height);
+				log::debug!(
+					target: "hyperspace",
+					"{PACKET_TAG}{} Built the acknowledgement message from the write acknowledgement event on {} at {}",
+					packet_id(&packet), source.name(), write_ack.height
+				);
 				let msg = MsgAcknowledgement {
 					packet,
 					acknowledgement: acknowledgement.into(),
@@ -570,6 +584,14 @@ pub async fn parse_events(
 				let msg = Any { value, type_url: msg.type_url() };
 				messages.push(msg)
 			},
+			IbcEvent::UpgradeInitChannel(_) |
+			IbcEvent::UpgradeTryChannel(_) |
+			IbcEvent::UpgradeAckChannel(_) |
+			IbcEvent::UpgradeConfirmChannel(_) |
+			IbcEvent::UpgradeOpenChannel(_) =>
+				if let Some(msg) = parse_upgrade_event(&*source, &*sink, &event).await? {
+					messages.push(msg)
+				},
 			_ => continue,
 		}
 	}
@@ -599,6 +621,37 @@ async fn query_host_consensus_state_proof(
 	Ok(host_consensus_state_proof)
 }
 
+/// Drops the packet events of the channels and the sequences the protocol doesn't relay, see
+/// [`MessagingProtocol::is_packet_allowed`]. The channels are the ones of the chain that emitted
+/// the events, as for the packets queried by the protocol.
+pub fn filter_packet_events(
+	events: Vec<IbcEvent>,
+	protocol: &impl MessagingProtocol,
+) -> Vec<IbcEvent> {
+	events
+		.into_iter()
+		.filter(|event| {
+			let (packet, channel_id, port_id) = match event {
+				IbcEvent::SendPacket(send_packet) => {
+					let packet = &send_packet.packet;
+					(packet, &packet.source_channel, &packet.source_port)
+				},
+				IbcEvent::WriteAcknowledgement(write_ack) => {
+					let packet = &write_ack.packet;
+					(packet, &packet.destination_channel, &packet.destination_port)
+				},
+				_ => return true,
+			};
+			let allowed =
+				protocol.is_packet_allowed(channel_id, port_id, u64::from(packet.sequence));
+			if !allowed {
+				log::trace!(target: "hyperspace", "{PACKET_TAG}{} Skipping the {} event as the packet is not allowed by the packet filter", packet_id(packet), event.event_type().as_str());
+			}
+			allowed
+		})
+		.collect()
+}
+
 pub fn has_packet_events(event_types: &[IbcEventType]) -> bool {
 	event_types
 		.iter()
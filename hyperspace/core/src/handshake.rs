@@ -0,0 +1,333 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resumption of the connection and channel handshakes left half done.
+//!
+//! `create-connection` and `create-channel` only start a handshake with its `INIT` step, the
+//! relay loop they run relays the following steps from the events of the previous ones. If the
+//! relayer stopped in between, these events are gone, so the handshake is looked up on both
+//! chains instead, and its next step is built from the on-chain state of the last one, as if its
+//! event was just seen. The steps after it are relayed by the relay loop again.
+
+use crate::{events::parse_events, queue};
+use anyhow::anyhow;
+use futures::{future, StreamExt};
+use ibc::{
+	core::{
+		ics02_client::client_state::ClientState as ClientStateT,
+		ics03_connection::{connection, events as connection_events},
+		ics04_channel::{
+			channel::{Order, State},
+			events as channel_events,
+		},
+		ics24_host::identifier::{ChannelId, ConnectionId, PortId},
+	},
+	events::IbcEvent,
+	Height,
+};
+use pallet_ibc::light_clients::AnyClientState;
+use primitives::Chain;
+use std::{str::FromStr, time::Duration};
+
+/// Time to wait for the end of a resumed connection handshake.
+const CONNECTION_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// Time to wait for the end of a resumed channel handshake.
+const CHANNEL_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// The last step of a handshake left half done, as the event it emitted.
+#[derive(Debug, Clone)]
+pub enum HandshakeStep {
+	/// A step on chain A, the next one is relayed to chain B
+	ChainA(IbcEvent),
+	/// A step on chain B, the next one is relayed to chain A
+	ChainB(IbcEvent),
+}
+
+/// Finds a connection handshake between the clients of the chains, started on chain A with the
+/// given delay period and not completed on both chains.
+pub async fn find_connection_handshake<A: Chain, B: Chain>(
+	chain_a: &A,
+	chain_b: &B,
+	delay_period: Duration,
+) -> anyhow::Result<Option<HandshakeStep>> {
+	let (height_a, _) = chain_a.latest_height_and_timestamp().await?;
+	let (height_b, _) = chain_b.latest_height_and_timestamp().await?;
+	let (client_a, client_b) = (chain_b.client_id(), chain_a.client_id());
+	let connections_b = chain_b
+		.query_connection_using_client(height_b.revision_height as u32, client_b.to_string())
+		.await?;
+	let connections_a = chain_a
+		.query_connection_using_client(height_a.revision_height as u32, client_a.to_string())
+		.await?;
+
+	for connection_a in connections_a {
+		let counterparty = connection_a.counterparty.clone().unwrap_or_default();
+		if counterparty.client_id != client_b.as_str() ||
+			Duration::from_nanos(connection_a.delay_period) != delay_period
+		{
+			continue
+		}
+		let connection_id = ConnectionId::from_str(&connection_a.id)?;
+		// the `TRYOPEN` step of the connection on chain B, if it happened
+		let try_open = connections_b.iter().find(|connection_b| {
+			connection_b.state == connection::State::TryOpen as i32 &&
+				connection_b
+					.counterparty
+					.as_ref()
+					.map_or(false, |counterparty| counterparty.connection_id == connection_a.id)
+		});
+		let attributes =
+			|counterparty_connection_id: Option<ConnectionId>| connection_events::Attributes {
+				height: height_a,
+				connection_id: Some(connection_id.clone()),
+				client_id: client_a.clone(),
+				counterparty_connection_id,
+				counterparty_client_id: client_b.clone(),
+			};
+		match connection::State::from_i32(connection_a.state) {
+			Ok(connection::State::Init) => {
+				let step = match try_open {
+					Some(connection_b) => HandshakeStep::ChainB(IbcEvent::OpenTryConnection(
+						connection_events::OpenTry(connection_events::Attributes {
+							height: height_b,
+							connection_id: Some(ConnectionId::from_str(&connection_b.id)?),
+							client_id: client_b.clone(),
+							counterparty_connection_id: Some(connection_id.clone()),
+							counterparty_client_id: client_a.clone(),
+						}),
+					)),
+					None => HandshakeStep::ChainA(IbcEvent::OpenInitConnection(
+						connection_events::OpenInit(attributes(None)),
+					)),
+				};
+				return Ok(Some(step))
+			},
+			Ok(connection::State::Open) if try_open.is_some() => {
+				let counterparty_connection_id =
+					ConnectionId::from_str(&counterparty.connection_id)?;
+				return Ok(Some(HandshakeStep::ChainA(IbcEvent::OpenAckConnection(
+					connection_events::OpenAck(attributes(Some(counterparty_connection_id))),
+				))))
+			},
+			// the handshakes in `TRYOPEN` on chain A were started on chain B
+			_ => continue,
+		}
+	}
+	Ok(None)
+}
+
+/// Finds a handshake of a channel of the port and ordering over the connection, started on
+/// chain A and not completed on both chains.
+pub async fn find_channel_handshake<A: Chain, B: Chain>(
+	chain_a: &A,
+	chain_b: &B,
+	connection_id: &ConnectionId,
+	counterparty_connection_id: &ConnectionId,
+	port_id: &PortId,
+	counterparty_port_id: &PortId,
+	order: Order,
+) -> anyhow::Result<Option<HandshakeStep>> {
+	let (height_a, _) = chain_a.latest_height_and_timestamp().await?;
+	let (height_b, _) = chain_b.latest_height_and_timestamp().await?;
+	let channels_a = chain_a.query_connection_channels(height_a, connection_id).await?.channels;
+	let channels_b = chain_b
+		.query_connection_channels(height_b, counterparty_connection_id)
+		.await?
+		.channels;
+
+	for channel_a in channels_a {
+		let counterparty = channel_a.counterparty.clone().unwrap_or_default();
+		if channel_a.port_id != port_id.as_str() ||
+			counterparty.port_id != counterparty_port_id.as_str() ||
+			channel_a.ordering != order as i32
+		{
+			continue
+		}
+		let channel_id = ChannelId::from_str(&channel_a.channel_id)?;
+		// the `TRYOPEN` step of the channel on chain B, if it happened
+		let try_open = channels_b.iter().find(|channel_b| {
+			channel_b.state == State::TryOpen as i32 &&
+				channel_b.port_id == counterparty_port_id.as_str() &&
+				channel_b.counterparty.as_ref().map_or(false, |counterparty| {
+					counterparty.port_id == port_id.as_str() &&
+						counterparty.channel_id == channel_a.channel_id
+				})
+		});
+		let counterparty_channel_id = try_open
+			.map(|channel_b| ChannelId::from_str(&channel_b.channel_id))
+			.transpose()?;
+		match State::from_i32(channel_a.state) {
+			Ok(State::Init) => {
+				let step = match counterparty_channel_id {
+					Some(counterparty_channel_id) =>
+						HandshakeStep::ChainB(IbcEvent::OpenTryChannel(channel_events::OpenTry {
+							height: height_b,
+							port_id: counterparty_port_id.clone(),
+							channel_id: Some(counterparty_channel_id),
+							connection_id: counterparty_connection_id.clone(),
+							counterparty_port_id: port_id.clone(),
+							counterparty_channel_id: Some(channel_id),
+						})),
+					None =>
+						HandshakeStep::ChainA(IbcEvent::OpenInitChannel(channel_events::OpenInit {
+							height: height_a,
+							port_id: port_id.clone(),
+							channel_id: Some(channel_id),
+							connection_id: connection_id.clone(),
+							counterparty_port_id: counterparty_port_id.clone(),
+							counterparty_channel_id: None,
+						})),
+				};
+				return Ok(Some(step))
+			},
+			Ok(State::Open) if counterparty_channel_id.is_some() =>
+				return Ok(Some(HandshakeStep::ChainA(IbcEvent::OpenAckChannel(
+					channel_events::OpenAck {
+						height: height_a,
+						port_id: port_id.clone(),
+						channel_id: Some(channel_id),
+						counterparty_channel_id,
+						connection_id: connection_id.clone(),
+						counterparty_port_id: counterparty_port_id.clone(),
+					},
+				)))),
+			_ => continue,
+		}
+	}
+	Ok(None)
+}
+
+/// Relays the next step of a connection handshake, and waits for the relay loop to complete it.
+/// Returns the connection on chain A and on chain B.
+pub async fn resume_connection_handshake<A: Chain, B: Chain>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	step: HandshakeStep,
+) -> anyhow::Result<(ConnectionId, ConnectionId)> {
+	let confirm = chain_b
+		.ibc_events()
+		.await
+		.skip_while(|ev| future::ready(!matches!(ev, IbcEvent::OpenConfirmConnection(_))))
+		.take(1)
+		.collect::<Vec<_>>();
+	relay_handshake_step(chain_a, chain_b, step).await?;
+	let mut events = tokio::time::timeout(CONNECTION_HANDSHAKE_TIMEOUT, confirm)
+		.await
+		.map_err(|_| anyhow!("Didn't see OpenConfirmConnection on {}", chain_b.name()))?;
+	match events.pop() {
+		Some(IbcEvent::OpenConfirmConnection(conn)) => Ok((
+			conn.attributes()
+				.counterparty_connection_id
+				.clone()
+				.ok_or_else(|| anyhow!("Failed to resume the connection handshake"))?,
+			conn.connection_id()
+				.ok_or_else(|| anyhow!("Failed to resume the connection handshake"))?
+				.clone(),
+		)),
+		got => Err(anyhow!("Last event should be OpenConfirmConnection: {got:?}")),
+	}
+}
+
+/// Relays the next step of a channel handshake, and waits for the relay loop to complete it.
+/// Returns the channel on chain A and on chain B.
+pub async fn resume_channel_handshake<A: Chain, B: Chain>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	step: HandshakeStep,
+) -> anyhow::Result<(ChannelId, ChannelId)> {
+	let confirm = chain_b
+		.ibc_events()
+		.await
+		.skip_while(|ev| future::ready(!matches!(ev, IbcEvent::OpenConfirmChannel(_))))
+		.take(1)
+		.collect::<Vec<_>>();
+	relay_handshake_step(chain_a, chain_b, step).await?;
+	let mut events = tokio::time::timeout(CHANNEL_HANDSHAKE_TIMEOUT, confirm)
+		.await
+		.map_err(|_| anyhow!("Didn't see OpenConfirmChannel on {}", chain_b.name()))?;
+	match events.pop() {
+		Some(IbcEvent::OpenConfirmChannel(chan)) => Ok((
+			chan.counterparty_channel_id
+				.ok_or_else(|| anyhow!("Failed to resume the channel handshake"))?,
+			*chan
+				.channel_id()
+				.ok_or_else(|| anyhow!("Failed to resume the channel handshake"))?,
+		)),
+		got => Err(anyhow!("Last event should be OpenConfirmChannel: {got:?}")),
+	}
+}
+
+async fn relay_handshake_step<A: Chain, B: Chain>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	step: HandshakeStep,
+) -> anyhow::Result<()> {
+	match step {
+		HandshakeStep::ChainA(event) => relay_step(chain_a, chain_b, event).await,
+		HandshakeStep::ChainB(event) => relay_step(chain_b, chain_a, event).await,
+	}
+}
+
+/// Builds the message of the step following the event on the source, and submits it to the
+/// sink with an update of the client of the source, since the client may not be at a height the
+/// event happened before yet.
+async fn relay_step<A: Chain, B: Chain>(
+	source: &mut A,
+	sink: &mut B,
+	mut event: IbcEvent,
+) -> anyhow::Result<()> {
+	let finality_event = source
+		.finality_notifications()
+		.await?
+		.next()
+		.await
+		.ok_or_else(|| anyhow!("Finality notifications of {} ended", source.name()))?;
+	let mut updates = source.query_latest_ibc_events(finality_event, &*sink).await?;
+	let (mut messages, update_height) = match updates.pop() {
+		Some((update, height, ..)) => (vec![update], height),
+		None => {
+			let (sink_height, _) = sink.latest_height_and_timestamp().await?;
+			let client_state = sink
+				.query_client_state(sink_height, source.client_id())
+				.await?
+				.client_state
+				.ok_or_else(|| anyhow!("Client state of {} not found", source.client_id()))?;
+			let client_state = AnyClientState::try_from(client_state).map_err(|_| {
+				anyhow!("Invalid client state of {} on {}", source.name(), sink.name())
+			})?;
+			(vec![], client_state.latest_height())
+		},
+	};
+	// the proofs are queried at the height they're verified at by the client update, which the
+	// chain may place after (or before) the height of the update
+	let proof_height = source.get_proof_height(update_height).await.revision_height;
+	let event_height = match proof_height.checked_sub(update_height.revision_height) {
+		Some(offset) => update_height.revision_height.saturating_sub(offset),
+		None => update_height
+			.revision_height
+			.saturating_add(update_height.revision_height - proof_height),
+	};
+	event.set_height(Height::new(update_height.revision_number, event_height));
+	log::info!(
+		target: "hyperspace",
+		"Resuming the handshake after {} on {} at {}",
+		event.event_type().as_str(), source.name(), event.height()
+	);
+	let step = parse_events(source, sink, vec![event], None).await?;
+	if step.is_empty() {
+		return Err(anyhow!("Failed to build the next step of the handshake"))
+	}
+	messages.extend(step);
+	queue::flush_message_batch(messages, None, &*sink).await
+}
@@ -0,0 +1,333 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Leader election of the relayers of a hot/standby deployment.
+//!
+//! The relayers of the deployment share a lease file, e.g. on a shared volume. The relayer named
+//! in the file renews it every third of [`LeaderLeaseConfig::ttl_secs`] and submits the messages,
+//! while the others run as standbys, see [`primitives::leadership`]. A standby takes the lease over
+//! once it hasn't been renewed for its ttl, and the leader gives it up when it shuts down, so a
+//! standby takes over within a third of the ttl. The lease is only written while holding a lock
+//! file created next to it with `create_new`, so that two standbys can't both take a lapsed lease
+//! over, and a lock older than the ttl is removed as left by a relayer that stopped while writing.
+//! The lease is written to a temporary file that is then renamed over the lease file, so that it's
+//! never read half written. The hosts of the deployment must keep their clocks in sync.
+//!
+//! The election relies on `create_new` and `rename` being atomic on the volume of the lease, as
+//! they are on a local filesystem shared by the relayers of a host (e.g. a volume mounted in
+//! several containers) or on NFSv4. It's not safe on filesystems without an exclusive create,
+//! such as object storage mounts; there is no networked backend (etcd, redis...) for deployments
+//! spanning hosts without such a volume. Whatever the volume, a leader stops submitting once its
+//! lease wasn't renewed for the ttl, see [`Leadership::is_leader`].
+
+use crate::shutdown;
+use primitives::leadership::Leadership;
+use serde::{Deserialize, Serialize};
+use std::{
+	io::ErrorKind,
+	path::{Path, PathBuf},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Default time after which a lease that isn't renewed lapses.
+pub const DEFAULT_LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// Lease shared by the relayers of a hot/standby deployment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderLeaseConfig {
+	/// Path of the lease file, shared by the relayers of the deployment. Its volume must support
+	/// atomic exclusive creates and renames, e.g. a local filesystem or NFSv4
+	pub path: String,
+	/// Time after which a lease that isn't renewed lapses, in seconds. Defaults to 30 seconds
+	pub ttl_secs: Option<u64>,
+	/// Name of the relayer in the lease file, unique within the deployment. Defaults to a random
+	/// name
+	pub holder: Option<String>,
+}
+
+impl LeaderLeaseConfig {
+	pub fn ttl(&self) -> Duration {
+		self.ttl_secs.map(Duration::from_secs).unwrap_or(DEFAULT_LEASE_TTL)
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct Lease {
+	holder: String,
+	/// Unix time of the last renewal, in seconds
+	renewed_at: u64,
+}
+
+impl Lease {
+	/// Returns `true` if `holder` may write the lease at `now`.
+	fn is_available_to(&self, holder: &str, now: u64, ttl: Duration) -> bool {
+		self.holder == holder || now.saturating_sub(self.renewed_at) >= ttl.as_secs()
+	}
+}
+
+/// Renews or takes over the lease until the relayer shuts down, and records in `leadership`
+/// whether the relayer holds it.
+pub async fn run_lease(config: LeaderLeaseConfig, leadership: Leadership) {
+	let path = PathBuf::from(&config.path);
+	let ttl = config.ttl();
+	let holder = config.holder.unwrap_or_else(|| {
+		format!("hyperspace-{}-{:08x}", std::process::id(), rand::random::<u32>())
+	});
+	log::info!(target: "hyperspace", "Starting as a standby, waiting for the lease at {} as {holder}", path.display());
+	let mut interval = tokio::time::interval(ttl / 3);
+	loop {
+		tokio::select! {
+			_ = interval.tick() => (),
+			_ = shutdown::requested() => break,
+		}
+		let renewed_at = renewal_instant();
+		let is_leader = match try_acquire(&path, &holder, ttl).await {
+			Ok(is_leader) => is_leader,
+			Err(e) => {
+				log::error!(target: "hyperspace", "Failed to renew the lease at {}: {e:?}", path.display());
+				// the lease of a leader that can't renew it lapses for the standbys anyway
+				false
+			},
+		};
+		if leadership.set(is_leader, renewed_at) {
+			if is_leader {
+				log::info!(target: "hyperspace", "Acquired the lease at {}, submitting the messages", path.display());
+			} else {
+				log::warn!(target: "hyperspace", "Lost the lease at {}, running as a standby", path.display());
+			}
+		}
+	}
+
+	if leadership.is_leader() {
+		shutdown_release(&path, &holder).await;
+	}
+}
+
+/// Renews the lease if it's held by `holder`, or takes it over if it lapsed. Returns `true` if
+/// `holder` holds the lease.
+async fn try_acquire(path: &Path, holder: &str, ttl: Duration) -> anyhow::Result<bool> {
+	let now = unix_secs();
+	let lease = read_lease(path).await?;
+	if !lease.as_ref().map_or(true, |lease| lease.is_available_to(holder, now, ttl)) {
+		return Ok(false)
+	}
+	let Some(lock) = LeaseLock::try_lock(path, ttl).await? else {
+		// another relayer is writing the lease, the current one holds until it's done
+		return Ok(lease.map_or(false, |lease| {
+			lease.holder == holder && now.saturating_sub(lease.renewed_at) < ttl.as_secs()
+		}))
+	};
+	let result = async {
+		// the lease may have been taken over since it was read
+		if let Some(lease) = read_lease(path).await? {
+			if !lease.is_available_to(holder, now, ttl) {
+				return Ok(false)
+			}
+		}
+		let lease = Lease { holder: holder.to_string(), renewed_at: now };
+		let tmp = path.with_extension(format!("{holder}.tmp"));
+		tokio::fs::write(&tmp, serde_json::to_vec(&lease)?).await?;
+		tokio::fs::rename(&tmp, path).await?;
+		Ok(true)
+	}
+	.await;
+	lock.unlock().await;
+	result
+}
+
+/// Removes the lease of `holder` on shutdown, so that a standby takes over without waiting for the
+/// lease to lapse.
+async fn shutdown_release(path: &Path, holder: &str) {
+	let lock = match LeaseLock::try_lock(path, Duration::MAX).await {
+		Ok(Some(lock)) => lock,
+		// a standby is already taking the lease over
+		Ok(None) => return,
+		Err(e) => {
+			log::warn!(target: "hyperspace", "Failed to lock the lease at {}: {e:?}", path.display());
+			return
+		},
+	};
+	match read_lease(path).await {
+		Ok(Some(lease)) if lease.holder == holder =>
+			if let Err(e) = tokio::fs::remove_file(path).await {
+				log::warn!(target: "hyperspace", "Failed to release the lease at {}: {e:?}", path.display());
+			} else {
+				log::info!(target: "hyperspace", "Released the lease at {}", path.display());
+			},
+		Ok(_) => (),
+		Err(e) =>
+			log::warn!(target: "hyperspace", "Failed to read the lease at {}: {e:?}", path.display()),
+	}
+	lock.unlock().await;
+}
+
+/// Lock file held by the relayer writing the lease.
+struct LeaseLock {
+	path: PathBuf,
+}
+
+impl LeaseLock {
+	/// Creates the lock file of the lease, returning `None` if another relayer holds it. A lock
+	/// older than `ttl` is removed, so that the next attempt may take it.
+	async fn try_lock(lease: &Path, ttl: Duration) -> anyhow::Result<Option<Self>> {
+		let path = lease.with_extension("lock");
+		match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await {
+			Ok(_) => Ok(Some(Self { path })),
+			Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+				let stale = match tokio::fs::metadata(&path).await {
+					Ok(metadata) => metadata.modified()?.elapsed().unwrap_or_default() >= ttl,
+					// unlocked in the meantime
+					Err(e) if e.kind() == ErrorKind::NotFound => false,
+					Err(e) => return Err(e.into()),
+				};
+				if stale {
+					log::warn!(target: "hyperspace", "Removing the stale lock of the lease at {}", lease.display());
+					match tokio::fs::remove_file(&path).await {
+						Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+						_ => (),
+					}
+				}
+				Ok(None)
+			},
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	async fn unlock(self) {
+		if let Err(e) = tokio::fs::remove_file(&self.path).await {
+			log::warn!(target: "hyperspace", "Failed to remove the lock at {}: {e:?}", self.path.display());
+		}
+	}
+}
+
+async fn read_lease(path: &Path) -> anyhow::Result<Option<Lease>> {
+	match tokio::fs::read(path).await {
+		Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+		Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e.into()),
+	}
+}
+
+fn unix_secs() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// The instant of the renewal written now to the lease file. The file counts whole seconds, so the
+/// standbys see the lease lapse up to a second before its ttl elapsed since the write.
+fn renewal_instant() -> Instant {
+	let now = Instant::now();
+	let subsec = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+	now.checked_sub(Duration::from_nanos(subsec as u64)).unwrap_or(now)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TTL: Duration = Duration::from_secs(30);
+
+	/// Returns the path of a lease file that doesn't exist yet.
+	fn lease_path() -> PathBuf {
+		std::env::temp_dir().join(format!("hyperspace-lease-{:016x}.json", rand::random::<u64>()))
+	}
+
+	async fn write_lease(path: &Path, holder: &str, renewed_at: u64) {
+		let lease = Lease { holder: holder.to_string(), renewed_at };
+		tokio::fs::write(path, serde_json::to_vec(&lease).unwrap()).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn free_leases_are_acquired_and_renewed() {
+		let path = lease_path();
+		assert!(try_acquire(&path, "a", TTL).await.unwrap());
+		assert!(try_acquire(&path, "a", TTL).await.unwrap());
+		assert_eq!(read_lease(&path).await.unwrap().unwrap().holder, "a");
+		assert!(!path.with_extension("lock").exists());
+		tokio::fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn held_leases_are_not_taken_over() {
+		let path = lease_path();
+		assert!(try_acquire(&path, "a", TTL).await.unwrap());
+		assert!(!try_acquire(&path, "b", TTL).await.unwrap());
+		assert_eq!(read_lease(&path).await.unwrap().unwrap().holder, "a");
+		tokio::fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn lapsed_leases_are_taken_over() {
+		let path = lease_path();
+		write_lease(&path, "a", unix_secs() - 2 * TTL.as_secs()).await;
+		assert!(try_acquire(&path, "b", TTL).await.unwrap());
+		assert!(!try_acquire(&path, "a", TTL).await.unwrap());
+		tokio::fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+	async fn concurrent_takeovers_elect_a_single_leader() {
+		let path = lease_path();
+		write_lease(&path, "old", unix_secs() - 2 * TTL.as_secs()).await;
+		let attempts = (0..16).map(|i| {
+			let path = path.clone();
+			tokio::spawn(async move { try_acquire(&path, &format!("standby-{i}"), TTL).await })
+		});
+		let mut leaders = 0;
+		for attempt in futures::future::join_all(attempts).await {
+			if attempt.unwrap().unwrap() {
+				leaders += 1;
+			}
+		}
+		assert_eq!(leaders, 1);
+		assert!(!path.with_extension("lock").exists());
+		tokio::fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn locked_leases_are_not_written() {
+		let path = lease_path();
+		let lock = path.with_extension("lock");
+		tokio::fs::write(&lock, b"").await.unwrap();
+		assert!(!try_acquire(&path, "a", TTL).await.unwrap());
+		assert!(read_lease(&path).await.unwrap().is_none());
+		// the lease held by the relayer is still valid while another one holds the lock
+		tokio::fs::remove_file(&lock).await.unwrap();
+		assert!(try_acquire(&path, "a", TTL).await.unwrap());
+		tokio::fs::write(&lock, b"").await.unwrap();
+		assert!(try_acquire(&path, "a", TTL).await.unwrap());
+		tokio::fs::remove_file(&lock).await.unwrap();
+		tokio::fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn stale_locks_are_removed() {
+		let path = lease_path();
+		tokio::fs::write(path.with_extension("lock"), b"").await.unwrap();
+		// every lock is older than a ttl of 0
+		assert!(!try_acquire(&path, "a", Duration::ZERO).await.unwrap());
+		assert!(try_acquire(&path, "a", Duration::ZERO).await.unwrap());
+		tokio::fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn leases_are_only_released_by_their_holder() {
+		let path = lease_path();
+		assert!(try_acquire(&path, "a", TTL).await.unwrap());
+		shutdown_release(&path, "b").await;
+		assert!(path.exists());
+		shutdown_release(&path, "a").await;
+		assert!(!path.exists());
+		assert!(!path.with_extension("lock").exists());
+	}
+}
@@ -14,25 +14,61 @@
 
 #![warn(unused_variables)]
 
+pub mod audit;
 pub mod chain;
+pub mod channel_upgrades;
+pub mod clearing;
+pub mod client_status;
 pub mod command;
+mod confirmations;
+pub mod event_stream;
 pub mod events;
+pub mod handshake;
+pub mod lease;
 pub mod logging;
 mod macros;
+pub mod middleware;
 pub mod packets;
+pub mod pending;
+pub mod plugin;
+pub mod protocol;
 pub mod queue;
+pub mod reconcile;
+pub mod reload;
+pub mod score;
+pub mod shutdown;
 pub mod substrate;
+pub mod supervisor;
+pub mod timeouts;
 mod utils;
-
-use crate::utils::RecentStream;
+pub mod watchtower;
+
+use crate::{
+	audit::SequenceAudit,
+	clearing::{Clearing, ClearingConfig, Sweep},
+	supervisor::{open_stream, restart_stream, Side, StreamWatchdog},
+	timeouts::TimeoutWorker,
+	utils::RecentStream,
+};
 use anyhow::anyhow;
-use events::{has_packet_events, parse_events};
+use events::{filter_packet_events, has_packet_events, parse_events};
 use futures::{future::ready, StreamExt, TryFutureExt};
-use ibc::{events::IbcEvent, Height};
+use ibc::{
+	core::ics02_client::client_state::ClientState as ClientStateT, events::IbcEvent, Height,
+};
 use ibc_proto::google::protobuf::Any;
 use metrics::handler::MetricsHandler;
-use primitives::{Chain, IbcProvider, UndeliveredType, UpdateType};
-use std::collections::HashSet;
+use pallet_ibc::light_clients::AnyClientState;
+use primitives::{
+	correlation::packet_tags, retry::ErrorClass, Chain, IbcProvider, MessageKind, UndeliveredType,
+	UpdateType,
+};
+use protocol::{IbcPackets, MessagingProtocol};
+use std::{collections::HashSet, sync::atomic::Ordering, time::Duration};
+
+/// Share of the trusting period of a client after which it's updated even if there's nothing to
+/// relay, so that the skipped updates don't let it expire.
+const CLIENT_REFRESH_RATIO: f64 = 2.0 / 3.0;
 
 #[derive(Copy, Debug, Clone)]
 pub enum Mode {
@@ -43,42 +79,178 @@ pub enum Mode {
 /// Core relayer loop, waits for new finality events and forwards any new [`ibc::IbcEvents`]
 /// to the counter party chain.
 pub async fn relay<A, B>(
+	chain_a: A,
+	chain_b: B,
+	chain_a_metrics: Option<MetricsHandler>,
+	chain_b_metrics: Option<MetricsHandler>,
+	mode: Option<Mode>,
+) -> Result<(), anyhow::Error>
+where
+	A: Chain,
+	B: Chain,
+{
+	relay_with_protocol(
+		chain_a,
+		chain_b,
+		chain_a_metrics,
+		chain_b_metrics,
+		mode,
+		&IbcPackets::default(),
+		None,
+	)
+	.await
+}
+
+/// Core relayer loop for the given messaging protocol, see [`relay`]. If `clearing` is set, the
+/// pending messages are also cleared at startup and then periodically, see [`clearing`], and the
+/// sequences of the channels are audited for gaps, see [`audit`].
+pub async fn relay_with_protocol<A, B, P>(
 	mut chain_a: A,
 	mut chain_b: B,
 	mut chain_a_metrics: Option<MetricsHandler>,
 	mut chain_b_metrics: Option<MetricsHandler>,
 	mode: Option<Mode>,
+	protocol: &P,
+	clearing: Option<ClearingConfig>,
 ) -> Result<(), anyhow::Error>
 where
 	A: Chain,
 	B: Chain,
+	P: MessagingProtocol + Clone + 'static,
 {
-	let stream_a = RecentStream::new(chain_a.finality_notifications().await?);
-	let stream_b = RecentStream::new(chain_b.finality_notifications().await?);
+	let stream_a = open_stream(&mut chain_a).await;
+	let stream_b = open_stream(&mut chain_b).await;
 	let (mut chain_a_finality, mut chain_b_finality) = (stream_a, stream_b);
+	let mut watchdog = StreamWatchdog::new(&chain_a, &chain_b);
+	for metrics in chain_a_metrics.iter().chain(chain_b_metrics.iter()) {
+		metrics.report_startup_complete();
+	}
 
 	// Introduce altering between branches so that each branch gets a chance to execute first after
 	// another one
 	let mut first_executed = false;
+	// the ready packets are queried on every finality notification anyway, so a relayer resuming
+	// from its state store doesn't rescan the channels at startup
+	let resumed = resumed_from_store(&chain_a) && resumed_from_store(&chain_b);
+	let mut audit = SequenceAudit::new(clearing.and_then(|config| config.audit_interval));
+	let mut clearing = Clearing::new(clearing, !resumed);
+	// the light mode doesn't relay packets, so there's nothing to sweep
+	let light = matches!(mode, Some(Mode::Light));
+	let (mut sweep_a, mut sweep_b) = (Sweep::new(&chain_a, light), Sweep::new(&chain_b, light));
+	// stopped on shutdown, or aborted if the loop fails
+	let timeouts_a =
+		TimeoutWorker::spawn(&chain_a, &chain_b, chain_a_metrics.as_ref(), protocol, light);
+	let timeouts_b =
+		TimeoutWorker::spawn(&chain_b, &chain_a, chain_b_metrics.as_ref(), protocol, light);
+	queue::refresh_batch_limits(&mut chain_a).await;
+	queue::refresh_batch_limits(&mut chain_b).await;
+	let mut batch_limits_refresh = tokio::time::interval_at(
+		tokio::time::Instant::now() + queue::BATCH_LIMITS_REFRESH_INTERVAL,
+		queue::BATCH_LIMITS_REFRESH_INTERVAL,
+	);
 
-	// loop forever
+	// loop until a shutdown is requested
 	loop {
+		// the messages of the cycle in progress are always submitted, a shutdown only stops the
+		// loop in between two cycles
+		if shutdown::is_requested() {
+			break
+		}
 		tokio::select! {
+			// the branches are polled in order, so the clearing rounds only run when no finality
+			// event or tick is pending
+			biased;
 			// new finality event from chain A
 			result = chain_a_finality.next(), if !first_executed => {
 				first_executed = true;
-				process_finality_event(&mut chain_a, &mut chain_b, &mut chain_a_metrics, mode, result, &mut chain_a_finality, &mut chain_b_finality).await?;
+				clearing.on_finality_event();
+				watchdog.on_event(Side::A, result.is_none());
+				process_finality_event(&mut chain_a, &mut chain_b, &mut chain_a_metrics, mode, protocol, result, &mut chain_a_finality, &mut chain_b_finality).await?;
 			}
 			// new finality event from chain B
 			result = chain_b_finality.next() => {
 				first_executed = false;
-				process_finality_event(&mut chain_b, &mut chain_a, &mut chain_b_metrics, mode, result, &mut chain_b_finality, &mut chain_a_finality).await?;
+				clearing.on_finality_event();
+				watchdog.on_event(Side::B, result.is_none());
+				process_finality_event(&mut chain_b, &mut chain_a, &mut chain_b_metrics, mode, protocol, result, &mut chain_b_finality, &mut chain_a_finality).await?;
+			}
+			// time to restart the finality streams that went silent
+			_ = watchdog.tick() => {
+				for side in watchdog.stalled() {
+					watchdog.on_restart(side);
+					match side {
+						Side::A => {
+							log::warn!(target: "hyperspace", "No finality notification from {} for too long, restarting its stream", chain_a.name());
+							restart_stream(&mut chain_a, &mut chain_a_finality, chain_a_metrics.as_ref()).await;
+						},
+						Side::B => {
+							log::warn!(target: "hyperspace", "No finality notification from {} for too long, restarting its stream", chain_b.name());
+							restart_stream(&mut chain_b, &mut chain_b_finality, chain_b_metrics.as_ref()).await;
+						},
+					}
+				}
+			}
+			// time to clear the pending packets
+			_ = clearing.tick() => {
+				clearing.start();
+			}
+			// time to sweep the messages the finality notifications missed
+			_ = sweep_a.tick() => {
+				sweep_a.run(&mut chain_a, &mut chain_b, &mut chain_a_metrics, protocol, &mut clearing).await;
+			}
+			_ = sweep_b.tick() => {
+				sweep_b.run(&mut chain_b, &mut chain_a, &mut chain_b_metrics, protocol, &mut clearing).await;
+			}
+			// time to look for the sequences the relayer missed
+			_ = audit.tick() => {
+				audit.run(&chain_a, &chain_b, chain_a_metrics.as_ref(), chain_b_metrics.as_ref(), protocol, &mut clearing).await;
+			}
+			// time to query the batch limits of the chains again
+			_ = batch_limits_refresh.tick() => {
+				queue::refresh_batch_limits(&mut chain_a).await;
+				queue::refresh_batch_limits(&mut chain_b).await;
+			}
+			_ = shutdown::requested() => break,
+			// next round of the clearing in progress
+			_ = ready(()), if clearing.should_run() => {
+				clearing.run_round(&mut chain_a, &mut chain_b, &mut chain_a_metrics, &mut chain_b_metrics, protocol).await;
 			}
 			else => {
 				first_executed = false;
 			}
 		}
 	}
+
+	timeouts_a.stop().await;
+	timeouts_b.stop().await;
+	log::info!(target: "hyperspace", "Stopped relaying between {} and {}", chain_a.name(), chain_b.name());
+	flush_store(&chain_a);
+	flush_store(&chain_b);
+	Ok(())
+}
+
+/// Writes the pending changes of the state store of the chain to disk, if any.
+fn flush_store(chain: &impl Chain) {
+	let Some(store) = &chain.common_state().store else { return };
+	if let Err(e) = store.flush() {
+		log::warn!(target: "hyperspace", "Failed to flush the state store of {}: {e:?}", chain.name());
+	}
+}
+
+/// Returns `true` if the state store of the chain has a processed height, logging it.
+fn resumed_from_store(chain: &impl Chain) -> bool {
+	let Some(store) = &chain.common_state().store else { return false };
+	match store.processed_height() {
+		Ok(Some(height)) => {
+			log::info!(target: "hyperspace", "Resuming {} from the processed height {height}", chain.name());
+			true
+		},
+		Ok(None) => false,
+		Err(e) => {
+			log::warn!(target: "hyperspace", "Failed to read the processed height of {}: {e:?}", chain.name());
+			false
+		},
+	}
 }
 
 pub async fn fish<A, B>(chain_a: A, chain_b: B) -> Result<(), anyhow::Error>
@@ -141,11 +313,12 @@ where
 	Ok(())
 }
 
-async fn process_finality_event<A: Chain, B: Chain>(
+async fn process_finality_event<A: Chain, B: Chain, P: MessagingProtocol>(
 	source: &mut A,
 	sink: &mut B,
 	metrics: &mut Option<MetricsHandler>,
 	mode: Option<Mode>,
+	protocol: &P,
 	result: Option<A::FinalityEvent>,
 	stream_source: &mut RecentStream<A::FinalityEvent>,
 	stream_sink: &mut RecentStream<B::FinalityEvent>,
@@ -154,33 +327,38 @@ async fn process_finality_event<A: Chain, B: Chain>(
 		// stream closed
 		None => {
 			log::warn!("Stream closed for {}", source.name());
-			*stream_source = loop {
-				match source.finality_notifications().await {
-					Ok(stream) => break RecentStream::new(stream),
-					Err(e) => {
-						log::error!("Failed to get finality notifications for {} {:?}. Trying again in 30 seconds...", source.name(), e);
-						tokio::time::sleep(std::time::Duration::from_secs(30)).await;
-						let _ = source.reconnect().await;
-					},
-				};
-			};
-			*stream_sink = loop {
-				match sink.finality_notifications().await {
-					Ok(stream) => break RecentStream::new(stream),
-					Err(e) => {
-						log::error!("Failed to get finality notifications for {} {:?}. Trying again in 30 seconds...", sink.name(), e);
-						tokio::time::sleep(std::time::Duration::from_secs(30)).await;
-						let _ = sink.reconnect().await;
-					},
-				};
-			};
+			if let Some(metrics) = metrics.as_ref() {
+				metrics.report_stream_alive(false);
+			}
+			*stream_source = open_stream(source).await;
+			*stream_sink = open_stream(sink).await;
+			if let Some(metrics) = metrics.as_ref() {
+				metrics.report_stream_alive(true);
+			}
 		},
 		Some(finality_event) => {
 			log::info!("=======================================================");
 			log::info!("Received finality notification from {}", source.name(),);
 
 			let result =
-				process_some_finality_event(source, sink, metrics, mode, finality_event).await;
+				process_some_finality_event(source, sink, metrics, mode, protocol, finality_event)
+					.await;
+
+			if let Some(metrics) = metrics.as_ref() {
+				// the other errors come from the chain state or the relayed messages, the rpc may
+				// still be up
+				match &result {
+					Ok(()) => metrics.report_rpc_alive(true),
+					Err(e) if ErrorClass::of(e) == ErrorClass::Network =>
+						metrics.report_rpc_alive(false),
+					Err(_) => (),
+				}
+				metrics.report_light_store_size(
+					source.common_state().light_store_size.load(Ordering::Relaxed),
+				);
+				metrics.report_throttled_requests(source.common_state().throttled_requests.take());
+				metrics.report_proof_cache(source.common_state().proof_cache.take_stats());
+			}
 
 			match result {
 				Ok(()) => {
@@ -204,11 +382,12 @@ async fn process_finality_event<A: Chain, B: Chain>(
 	Ok(())
 }
 
-async fn process_some_finality_event<A: Chain, B: Chain>(
+async fn process_some_finality_event<A: Chain, B: Chain, P: MessagingProtocol>(
 	source: &mut A,
 	sink: &mut B,
 	metrics: &mut Option<MetricsHandler>,
 	mode: Option<Mode>,
+	protocol: &P,
 	finality_event: <A as IbcProvider>::FinalityEvent,
 ) -> anyhow::Result<()> {
 	let updates = source
@@ -218,10 +397,10 @@ async fn process_some_finality_event<A: Chain, B: Chain>(
 	log::trace!(target: "hyperspace", "Received updates count: {}", updates.len());
 	// query packets that can now be sent, at this sink height because of connection
 	// delay.
-	let (ready_packets, timeout_msgs) =
-		packets::query_ready_and_timed_out_packets(&*source, &*sink)
-			.await
-			.map_err(|e| anyhow!("Failed to parse events: {:?}", e))?;
+	let (ready_packets, timeout_msgs) = protocol
+		.query_ready_messages(&*source, &*sink, metrics.as_ref())
+		.await
+		.map_err(|e| anyhow!("Failed to query {} messages: {:?}", protocol.name(), e))?;
 
 	let mut msgs = Vec::new();
 
@@ -247,20 +426,27 @@ async fn process_some_finality_event<A: Chain, B: Chain>(
 		timeout_msgs.len()
 	);
 
-	process_updates(source, sink, metrics, mode, updates, &mut msgs).await?;
+	let processed_height = updates.iter().map(|(_, height, ..)| height.revision_height).max();
+	process_updates(source, sink, metrics, mode, protocol, updates, &mut msgs).await?;
 
 	msgs.extend(ready_packets);
 
-	process_messages(sink, metrics, msgs).await?;
-	process_timeouts(source, metrics, timeout_msgs).await?;
+	process_messages_and_timeouts(source, sink, metrics, msgs, timeout_msgs).await?;
+
+	if let (Some(store), Some(height)) = (&source.common_state().store, processed_height) {
+		if let Err(e) = store.set_processed_height(height) {
+			log::warn!(target: "hyperspace", "Failed to store the processed height of {}: {e:?}", source.name());
+		}
+	}
 	Ok(())
 }
 
-async fn process_updates<A: Chain, B: Chain>(
+async fn process_updates<A: Chain, B: Chain, P: MessagingProtocol>(
 	source: &mut A,
 	sink: &mut B,
 	metrics: &mut Option<MetricsHandler>,
 	mode: Option<Mode>,
+	protocol: &P,
 	updates: Vec<(Any, Height, Vec<IbcEvent>, UpdateType)>,
 	msgs: &mut Vec<Any>,
 ) -> anyhow::Result<()> {
@@ -277,13 +463,19 @@ async fn process_updates<A: Chain, B: Chain>(
 			HashSet::new()
 		};
 
-	for (msg_update_client, height, events, update_type) in updates {
+	let coalesce_client_updates = sink.common_state().message_priority.coalesce_client_updates;
+	let target_height_updates = source.common_state().target_height_updates;
+	// set when the packet messages of an update were left to the last update of the cycle
+	let mut deferred_packets = false;
+	let updates_count = updates.len();
+	for (i, (msg_update_client, height, events, update_type)) in updates.into_iter().enumerate() {
 		if let Some(metrics) = metrics.as_mut() {
 			if let Err(e) = metrics.handle_events(events.as_slice()).await {
 				log::error!("Failed to handle metrics for {} {:?}", source.name(), e);
 			}
 		}
 
+		let events = filter_packet_events(events, protocol);
 		let event_types = events.iter().map(|ev| ev.event_type()).collect::<Vec<_>>();
 		let mut messages = parse_events(source, sink, events, mode)
 			.await
@@ -300,6 +492,34 @@ async fn process_updates<A: Chain, B: Chain>(
 			mandatory_heights_for_undelivered_seqs.contains(&height.revision_height);
 		let common_state = source.common_state();
 		let skip_optional_updates = common_state.skip_optional_client_updates;
+		let skippable = (update_type.is_optional() || common_state.idle_mode) &&
+			!need_to_send_proofs_for_sequences;
+		let is_last = i + 1 == updates_count;
+		if target_height_updates &&
+			skip_optional_updates &&
+			skippable && !is_last &&
+			!messages.is_empty() &&
+			messages.iter().all(|msg| MessageKind::of(msg).is_some())
+		{
+			// the packets are relayed by the next cycle, proven at the latest height of this one
+			log::debug!(target: "hyperspace", "Deferring the packet messages at {height} to the client update of {} at the latest height of the cycle", sink.name());
+			deferred_packets = true;
+			continue
+		}
+		let force_update = deferred_packets && is_last;
+		let skip_update = skip_optional_updates && skippable && !force_update;
+		// the last skipped update of the cycle is sent anyway if the client approaches expiry
+		let refresh_client = skip_update &&
+			!has_packet_events(&event_types) &&
+			messages.is_empty() &&
+			is_last &&
+			client_needs_refresh(source, sink).await.unwrap_or_else(|e| {
+				log::warn!(target: "hyperspace", "Failed to check the expiry of the client of {} on {}: {e:?}", source.name(), sink.name());
+				false
+			});
+		if refresh_client {
+			log::info!("Sending client update for {} as the client approaches expiry", sink.name());
+		}
 
 		// We want to send client update if packet messages exist but where not sent due
 		// to a connection delay even if client update message is optional
@@ -308,9 +528,7 @@ async fn process_updates<A: Chain, B: Chain>(
 			// not when we have *any* undelivered packets. But this requires rewriting
 			// `find_suitable_proof_height_for_client` function, that uses binary
 			// search, which won't work in this case
-			skip_optional_updates &&
-				update_type.is_optional() &&
-				!need_to_send_proofs_for_sequences,
+			skip_update && !refresh_client,
 			has_packet_events(&event_types),
 			messages.is_empty(),
 		) {
@@ -319,6 +537,17 @@ async fn process_updates<A: Chain, B: Chain>(
 				log::info!("Skipping finality notification for {}", sink.name());
 				continue
 			},
+			(false, _, true) if refresh_client || force_update => (),
+			(false, _, true)
+				if coalesce_client_updates &&
+					update_type.is_optional() &&
+					!need_to_send_proofs_for_sequences &&
+					!is_last =>
+			{
+				// a later update of the cycle will bring the client up to date anyway
+				log::debug!(target: "hyperspace", "Coalescing optional client update for {} at {height}", sink.name());
+				continue
+			},
 			(false, _, true) =>
 				if update_type.is_optional() && need_to_send_proofs_for_sequences {
 					log::info!("Sending an optional update because source ({}) chain has undelivered sequences", sink.name());
@@ -333,17 +562,57 @@ async fn process_updates<A: Chain, B: Chain>(
 	Ok(())
 }
 
-async fn process_messages<B: Chain>(
+/// Returns `true` if the client of `source` on `sink` was last updated more than
+/// [`CLIENT_REFRESH_RATIO`] of its trusting period ago.
+async fn client_needs_refresh<A: Chain, B: Chain>(source: &A, sink: &B) -> anyhow::Result<bool> {
+	let (sink_height, _) = sink.latest_height_and_timestamp().await?;
+	let client_state = sink
+		.query_client_state(sink_height, source.client_id())
+		.await?
+		.client_state
+		.ok_or_else(|| {
+			anyhow!("Client state of {} not found on {}", source.client_id(), sink.name())
+		})?;
+	let client_state = AnyClientState::try_from(client_state).map_err(|e| anyhow!("{e:?}"))?;
+	let updated_at =
+		source.query_timestamp_at(client_state.latest_height().revision_height).await?;
+	let (_, now) = source.latest_height_and_timestamp().await?;
+	let elapsed = Duration::from_nanos(now.nanoseconds().saturating_sub(updated_at));
+	Ok(client_state.expired(elapsed.div_f64(CLIENT_REFRESH_RATIO)))
+}
+
+/// Submits the messages of a relay cycle to the sink and the timeouts to the source, in the order
+/// of their [`primitives::MessagePriority`].
+async fn process_messages_and_timeouts<A: Chain, B: Chain>(
+	source: &mut A,
 	sink: &mut B,
 	metrics: &mut Option<MetricsHandler>,
 	msgs: Vec<Any>,
+	timeout_msgs: Vec<Any>,
+) -> anyhow::Result<()> {
+	if source.common_state().message_priority.timeouts_first() {
+		process_timeouts(source, metrics, timeout_msgs).await?;
+		process_messages(sink, metrics, msgs).await?;
+	} else {
+		process_messages(sink, metrics, msgs).await?;
+		process_timeouts(source, metrics, timeout_msgs).await?;
+	}
+	Ok(())
+}
+
+async fn process_messages<B: Chain>(
+	sink: &mut B,
+	metrics: &mut Option<MetricsHandler>,
+	mut msgs: Vec<Any>,
 ) -> anyhow::Result<()> {
 	if !msgs.is_empty() {
+		sink.common_state().message_priority.sort(&mut msgs);
 		if let Some(metrics) = metrics.as_ref() {
 			metrics.handle_messages(msgs.as_slice()).await;
 		}
 		let type_urls = msgs.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>();
 		log::info!("Submitting messages to {}: {type_urls:#?}", sink.name());
+		log::debug!(target: "hyperspace", "Queued the packet messages to {}: {}", sink.name(), packet_tags(&msgs));
 
 		queue::flush_message_batch(msgs, metrics.as_ref(), &*sink)
 			.await
@@ -364,6 +633,7 @@ async fn process_timeouts<A: Chain>(
 		}
 		let type_urls = timeout_msgs.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>();
 		log::info!("Submitting timeout messages to {}: {type_urls:#?}", source.name());
+		log::debug!(target: "hyperspace", "Queued the timeout messages to {}: {}", source.name(), packet_tags(&timeout_msgs));
 		queue::flush_message_batch(timeout_msgs, metrics.as_ref(), &*source)
 			.await
 			.map_err(|e| anyhow!("Failed to submit timeout messages: {:?}", e))?;
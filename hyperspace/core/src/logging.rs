@@ -13,10 +13,83 @@
 // limitations under the License.
 
 use log::LevelFilter;
+use primitives::correlation::tagged_packet_ids;
+use std::io::Write;
+
+/// Format of the log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+	Text,
+	/// One JSON object per line, with the correlation IDs of the packets in its `packets` field,
+	/// see [`primitives::correlation`]
+	Json,
+}
 
 pub fn setup_logging() {
-	env_logger::builder()
-		.filter_module("hyper", LevelFilter::Info)
-		.format_module_path(false)
-		.init();
+	setup_logging_with_format(LogFormat::Text)
+}
+
+pub fn setup_logging_with_format(format: LogFormat) {
+	let mut builder = env_logger::builder();
+	builder.filter_module("hyper", LevelFilter::Info).format_module_path(false);
+	if format == LogFormat::Json {
+		builder.format(|buf, record| {
+			let line = json_line(
+				buf.timestamp_millis().to_string(),
+				record.level().as_str(),
+				record.target(),
+				&record.args().to_string(),
+			);
+			writeln!(buf, "{line}")
+		});
+	}
+	builder.init();
+}
+
+/// A log line in the JSON format, with the correlation IDs tagged in the message.
+fn json_line(timestamp: String, level: &str, target: &str, message: &str) -> serde_json::Value {
+	let mut line = serde_json::json!({
+		"timestamp": timestamp,
+		"level": level,
+		"target": target,
+		"message": message,
+	});
+	let packets = tagged_packet_ids(message);
+	if !packets.is_empty() {
+		line["packets"] = serde_json::json!(packets);
+	}
+	line
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn json_lines_carry_the_tagged_packets() {
+		let line = json_line(
+			"2022-01-01T00:00:00.000Z".to_string(),
+			"INFO",
+			"hyperspace",
+			"Delivering packet=transfer/channel-0/1->channel-3 packet=transfer/channel-0/2->channel-3",
+		);
+		assert_eq!(line["level"], "INFO");
+		assert_eq!(line["target"], "hyperspace");
+		assert_eq!(
+			line["packets"],
+			serde_json::json!([
+				"transfer/channel-0/1->channel-3",
+				"transfer/channel-0/2->channel-3"
+			])
+		);
+		let line: serde_json::Value = serde_json::from_str(&line.to_string()).unwrap();
+		assert_eq!(line["packets"][1], "transfer/channel-0/2->channel-3");
+	}
+
+	#[test]
+	fn json_lines_without_packets_have_no_packets_field() {
+		let line = json_line("0".to_string(), "DEBUG", "hyperspace", "Fetching events");
+		assert_eq!(line["message"], "Fetching events");
+		assert!(line.get("packets").is_none());
+	}
 }
@@ -18,6 +18,9 @@ macro_rules! chains {
         $(#[$($meta:meta)*])*
 		$name:ident($config:path, $client:path),
 	)*) => {
+		#[allow(unused_imports)]
+		use $crate::plugin::prelude::*;
+
 		#[derive(Debug, Serialize, Deserialize, Clone)]
 		#[serde(tag = "type", rename_all = "snake_case")]
 		#[allow(clippy::large_enum_variant)]
@@ -78,6 +81,52 @@ macro_rules! chains {
 			}
 		}
 
+		impl From<String> for AnyError {
+			fn from(s: String) -> Self {
+				Self::Other(s)
+			}
+		}
+
+		/// A chain hosting the clients of its counterparties in `08-wasm` clients.
+		#[derive(Clone)]
+		pub struct WasmChain {
+			pub inner: Box<AnyChain>,
+			/// Code hash of the wasm client, updated when the hosted client is migrated to
+			/// another code
+			pub code_id: std::sync::Arc<std::sync::RwLock<Bytes>>,
+		}
+
+		impl WasmChain {
+			pub fn new(inner: AnyChain, code_id: Bytes) -> Self {
+				Self {
+					inner: Box::new(inner),
+					code_id: std::sync::Arc::new(std::sync::RwLock::new(code_id)),
+				}
+			}
+
+			/// Code hash the created and upgraded clients are wrapped with.
+			pub fn code_id(&self) -> Bytes {
+				self.code_id.read().expect("the code id lock isn't poisoned; qed").clone()
+			}
+
+			/// Follows the code hash of the client state hosted on the chain.
+			fn track_code_id(&self, response: &QueryClientStateResponse) {
+				let Some(code_id) = response.client_state.as_ref().and_then(wasm_code_id_of) else {
+					return
+				};
+				let mut current =
+					self.code_id.write().expect("the code id lock isn't poisoned; qed");
+				if *current != code_id {
+					log::info!(
+						target: "hyperspace",
+						"Wasm client on {} migrated from code {} to {}",
+						self.inner.name(), hex::encode(&*current), hex::encode(&code_id)
+					);
+					*current = code_id;
+				}
+			}
+		}
+
 		#[async_trait]
 		impl IbcProvider for AnyChain {
 			type FinalityEvent = AnyFinalityEvent;
@@ -127,7 +176,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_client_consensus(at, client_id, consensus_height)
+							.common_state()
+							.retry_policy
+							.retry("query_client_consensus", || chain.query_client_consensus(at, client_id.clone(), consensus_height))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -136,6 +187,27 @@ macro_rules! chains {
 				}
 			}
 
+			async fn query_client_consensus_states(
+				&self,
+				at: Height,
+				client_id: ClientId,
+				heights: Vec<Height>,
+			) -> Result<Vec<(Height, QueryConsensusStateResponse)>, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_client_consensus_states", || chain.query_client_consensus_states(at, client_id.clone(), heights.clone()))
+							.await
+							.map_err(AnyError::$name),
+					)*
+					AnyChain::Wasm(c) =>
+						c.inner.query_client_consensus_states(at, client_id, heights).await,
+				}
+			}
+
 			async fn query_client_state(
 				&self,
 				at: Height,
@@ -145,11 +217,17 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_client_state(at, client_id)
+							.common_state()
+							.retry_policy
+							.retry("query_client_state", || chain.query_client_state(at, client_id.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
-					AnyChain::Wasm(c) => c.inner.query_client_state(at, client_id).await,
+					AnyChain::Wasm(c) => {
+						let response = c.inner.query_client_state(at, client_id).await?;
+						c.track_code_id(&response);
+						Ok(response)
+					},
 				}
 			}
 
@@ -162,7 +240,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_connection_end(at, connection_id)
+							.common_state()
+							.retry_policy
+							.retry("query_connection_end", || chain.query_connection_end(at, connection_id.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -180,7 +260,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_channel_end(at, channel_id, port_id)
+							.common_state()
+							.retry_policy
+							.retry("query_channel_end", || chain.query_channel_end(at, channel_id, port_id.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -193,7 +275,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_proof(at, keys)
+							.common_state()
+							.retry_policy
+							.retry("query_proof", || chain.query_proof(at, keys.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -212,7 +296,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_packet_commitment(at, port_id, channel_id, seq)
+							.common_state()
+							.retry_policy
+							.retry("query_packet_commitment", || chain.query_packet_commitment(at, port_id, channel_id, seq))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -232,7 +318,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_packet_acknowledgement(at, port_id, channel_id, seq)
+							.common_state()
+							.retry_policy
+							.retry("query_packet_acknowledgement", || chain.query_packet_acknowledgement(at, port_id, channel_id, seq))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -251,7 +339,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_next_sequence_recv(at, port_id, channel_id)
+							.common_state()
+							.retry_policy
+							.retry("query_next_sequence_recv", || chain.query_next_sequence_recv(at, port_id, channel_id))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -270,7 +360,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_packet_receipt(at, port_id, channel_id, seq)
+							.common_state()
+							.retry_policy
+							.retry("query_packet_receipt", || chain.query_packet_receipt(at, port_id, channel_id, seq))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -283,7 +375,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.latest_height_and_timestamp()
+							.common_state()
+							.retry_policy
+							.retry("latest_height_and_timestamp", || chain.latest_height_and_timestamp())
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -301,7 +395,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_packet_commitments(at, channel_id, port_id)
+							.common_state()
+							.retry_policy
+							.retry("query_packet_commitments", || chain.query_packet_commitments(at, channel_id, port_id.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -319,7 +415,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_packet_acknowledgements(at, channel_id, port_id)
+							.common_state()
+							.retry_policy
+							.retry("query_packet_acknowledgements", || chain.query_packet_acknowledgements(at, channel_id, port_id.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -338,7 +436,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_unreceived_packets(at, channel_id, port_id, seqs)
+							.common_state()
+							.retry_policy
+							.retry("query_unreceived_packets", || chain.query_unreceived_packets(at, channel_id, port_id.clone(), seqs.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -357,7 +457,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_unreceived_acknowledgements(at, channel_id, port_id, seqs)
+							.common_state()
+							.retry_policy
+							.retry("query_unreceived_acknowledgements", || chain.query_unreceived_acknowledgements(at, channel_id, port_id.clone(), seqs.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -385,7 +487,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_connection_channels(at, connection_id)
+							.common_state()
+							.retry_policy
+							.retry("query_connection_channels", || chain.query_connection_channels(at, connection_id))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -403,7 +507,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_send_packets(channel_id, port_id, seqs)
+							.common_state()
+							.retry_policy
+							.retry("query_send_packets", || chain.query_send_packets(channel_id, port_id.clone(), seqs.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -421,7 +527,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_received_packets(channel_id, port_id, seqs)
+							.common_state()
+							.retry_policy
+							.retry("query_received_packets", || chain.query_received_packets(channel_id, port_id.clone(), seqs.clone()))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -448,7 +556,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_client_update_time_and_height(client_id, client_height)
+							.common_state()
+							.retry_policy
+							.retry("query_client_update_time_and_height", || chain.query_client_update_time_and_height(client_id.clone(), client_height))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -465,7 +575,9 @@ macro_rules! chains {
 					$(
 						$(#[$($meta)*])*
 						Self::$name(chain) => chain
-							.query_host_consensus_state_proof(client_state)
+							.common_state()
+							.retry_policy
+							.retry("query_host_consensus_state_proof", || chain.query_host_consensus_state_proof(client_state))
 							.await
 							.map_err(AnyError::$name),
 					)*
@@ -542,17 +654,47 @@ macro_rules! chains {
 				match self {
 					$(
 						$(#[$($meta)*])*
-						Self::$name(chain) => chain.query_timestamp_at(block_number).await.map_err(AnyError::$name),
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_timestamp_at", || chain.query_timestamp_at(block_number))
+							.await
+							.map_err(AnyError::$name),
 					)*
 					Self::Wasm(c) => c.inner.query_timestamp_at(block_number).await,
 				}
 			}
 
+			async fn query_block_commitment_at(
+				&self,
+				block_number: u64,
+			) -> Result<primitives::BlockCommitment, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_block_commitment_at", || {
+								chain.query_block_commitment_at(block_number)
+							})
+							.await
+							.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_block_commitment_at(block_number).await,
+				}
+			}
+
 			async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error> {
 				match self {
 					$(
 						$(#[$($meta)*])*
-						Self::$name(chain) => chain.query_clients().await.map_err(AnyError::$name),
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_clients", || chain.query_clients())
+							.await
+							.map_err(AnyError::$name),
 					)*
 					Self::Wasm(c) => c.inner.query_clients().await,
 				}
@@ -562,7 +704,12 @@ macro_rules! chains {
 				match self {
 					$(
 						$(#[$($meta)*])*
-						Self::$name(chain) => chain.query_channels().await.map_err(AnyError::$name),
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_channels", || chain.query_channels())
+							.await
+							.map_err(AnyError::$name),
 					)*
 					Self::Wasm(c) => c.inner.query_channels().await,
 				}
@@ -576,8 +723,12 @@ macro_rules! chains {
 				match self {
 					$(
 						$(#[$($meta)*])*
-						Self::$name(chain) =>
-							chain.query_connection_using_client(height, client_id).await.map_err(AnyError::$name),
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_connection_using_client", || chain.query_connection_using_client(height, client_id.clone()))
+							.await
+							.map_err(AnyError::$name),
 					)*
 					Self::Wasm(c) => c.inner.query_connection_using_client(height, client_id).await,
 				}
@@ -645,6 +796,71 @@ macro_rules! chains {
 				}
 			}
 
+			async fn query_scheduled_upgrade_height(&self) -> Result<Option<Height>, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.query_scheduled_upgrade_height()
+							.await
+							.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_scheduled_upgrade_height().await,
+				}
+			}
+
+			async fn query_upgraded_client(
+				&self,
+				upgrade_height: Height,
+			) -> Result<primitives::UpgradedClient, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.query_upgraded_client(upgrade_height)
+							.await
+							.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_upgraded_client(upgrade_height).await,
+				}
+			}
+
+			async fn query_incentivized_packets(
+				&self,
+				at: Height,
+				channel_id: ChannelId,
+				port_id: PortId,
+			) -> Result<Vec<primitives::fees::IncentivizedPacket>, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.query_incentivized_packets(at, channel_id, port_id)
+							.await
+							.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_incentivized_packets(at, channel_id, port_id).await,
+				}
+			}
+
+			async fn query_channel_upgrade(
+				&self,
+				at: Height,
+				channel_id: ChannelId,
+				port_id: PortId,
+			) -> Result<Option<primitives::channel_upgrades::ChannelUpgrade>, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.query_channel_upgrade(at, channel_id, port_id)
+							.await
+							.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_channel_upgrade(at, channel_id, port_id).await,
+				}
+			}
+
 			async fn query_connection_id_from_tx_hash(
 				&self,
 				tx_id: Self::TransactionId,
@@ -776,6 +992,70 @@ macro_rules! chains {
 				}
 			}
 
+			async fn estimate_fee(&self, msg: Vec<Any>) -> Result<u128, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain.estimate_fee(msg).await.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.estimate_fee(msg).await,
+				}
+			}
+
+			async fn query_batch_limits(&self) -> Result<BatchLimits, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_batch_limits", || chain.query_batch_limits())
+							.await
+							.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_batch_limits().await,
+				}
+			}
+
+			async fn query_relayer_balance(&self) -> Result<u128, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_relayer_balance", || chain.query_relayer_balance())
+							.await
+							.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_relayer_balance().await,
+				}
+			}
+
+			async fn query_token_display(
+				&self,
+				denom: Option<&str>,
+			) -> Result<TokenDisplay, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) =>
+							chain.query_token_display(denom).await.map_err(AnyError::$name),
+					)*
+					Self::Wasm(c) => c.inner.query_token_display(denom).await,
+				}
+			}
+
+			fn format_account(&self, account: &str) -> String {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain.format_account(account),
+					)*
+					Self::Wasm(c) => c.inner.format_account(account),
+				}
+			}
+
 			async fn finality_notifications(
 				&self,
 			) -> Result<Pin<Box<dyn Stream<Item = Self::FinalityEvent> + Send + Sync>>, Self::Error> {
@@ -808,13 +1088,29 @@ macro_rules! chains {
 					Self::Wasm(chain) => {
 						let messages = messages
 							.into_iter()
-							.map(|msg| wrap_any_msg_into_wasm(msg, chain.code_id.clone()))
+							.map(|msg| wrap_any_msg_into_wasm(msg, chain.code_id()))
 							.collect::<Result<Vec<_>, _>>()?;
 						chain.inner.submit(messages).await.map_err(AnyError::into)
 					},
 				}
 			}
 
+			async fn simulate(&self, messages: Vec<Any>) -> Result<(), Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain.simulate(messages).await.map_err(AnyError::$name),
+					)*
+					Self::Wasm(chain) => {
+						let messages = messages
+							.into_iter()
+							.map(|msg| wrap_any_msg_into_wasm(msg, chain.code_id()))
+							.collect::<Result<Vec<_>, _>>()?;
+						chain.inner.simulate(messages).await
+					},
+				}
+			}
+
 			async fn query_client_message(
 				&self,
 				update: UpdateClient,
@@ -822,7 +1118,12 @@ macro_rules! chains {
 				match self {
 					$(
 						$(#[$($meta)*])*
-						Self::$name(chain) => chain.query_client_message(update).await.map_err(AnyError::$name),
+						Self::$name(chain) => chain
+							.common_state()
+							.retry_policy
+							.retry("query_client_message", || chain.query_client_message(update.clone()))
+							.await
+							.map_err(AnyError::$name),
 					)*
 					Self::Wasm(c) => c.inner.query_client_message(update).await,
 				}
@@ -997,7 +1298,7 @@ macro_rules! chains {
 					)*
 				};
 				if let Some(code_id) = maybe_wasm_code_id {
-					Ok(AnyChain::Wasm(WasmChain { inner: Box::new(chain), code_id }))
+					Ok(AnyChain::Wasm(WasmChain::new(chain, code_id)))
 				} else {
 					Ok(chain)
 				}
@@ -1059,6 +1360,50 @@ macro_rules! chains {
 					)*
 				}
 			}
+
+			pub async fn reload(self, chain: &AnyChain) -> anyhow::Result<()> {
+				let chain = match chain {
+					AnyChain::Wasm(c) => &*c.inner,
+					chain => chain,
+				};
+				match (self, chain) {
+					$(
+						$(#[$($meta)*])*
+						(AnyConfig::$name(config), AnyChain::$name(chain)) => chain.reload(config).await?,
+					)*
+					_ => return Err(anyhow::anyhow!("The chain type can't be changed without restarting")),
+				}
+				Ok(())
+			}
+		}
+
+		#[async_trait]
+		impl ChainConfig for AnyConfig {
+			type Chain = AnyChain;
+
+			async fn into_client(self) -> anyhow::Result<AnyChain> {
+				AnyConfig::into_client(self).await
+			}
+
+			fn set_client_id(&mut self, client_id: ClientId) {
+				AnyConfig::set_client_id(self, client_id)
+			}
+
+			fn set_connection_id(&mut self, connection_id: ConnectionId) {
+				AnyConfig::set_connection_id(self, connection_id)
+			}
+
+			fn set_channel_whitelist(&mut self, channel_id: ChannelId, port_id: PortId) {
+				AnyConfig::set_channel_whitelist(self, channel_id, port_id)
+			}
+
+			fn set_wasm_code_id(&mut self, code_id: String) {
+				AnyConfig::set_wasm_code_id(self, code_id)
+			}
+
+			async fn reload(self, chain: &AnyChain) -> anyhow::Result<()> {
+				AnyConfig::reload(self, chain).await
+			}
 		}
 	};
 }
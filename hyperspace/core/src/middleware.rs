@@ -0,0 +1,118 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pipeline of middlewares the messages of the relayer loop go through before they're submitted.
+//!
+//! Crates embedding the relayer can intercept the messages about to be submitted to a chain, e.g.
+//! to tag them with a memo, drop the transfers of blacklisted accounts or add fee messages,
+//! without forking the relayer loop. The middlewares are registered before the relayer is started,
+//! and run in their registration order:
+//!
+//! ```ignore
+//! struct DenyTransfers;
+//!
+//! #[async_trait::async_trait]
+//! impl hyperspace_core::middleware::MessageMiddleware for DenyTransfers {
+//!     fn name(&self) -> &str {
+//!         "deny-transfers"
+//!     }
+//!
+//!     async fn process(
+//!         &self,
+//!         _context: &SubmissionContext,
+//!         msgs: Vec<Any>,
+//!     ) -> anyhow::Result<Vec<Any>> {
+//!         Ok(msgs.into_iter().filter(|msg| !is_denied(msg)).collect())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     hyperspace_core::logging::setup_logging();
+//!     hyperspace_core::middleware::register(DenyTransfers);
+//!     let cli = <hyperspace_core::command::Cli as clap::Parser>::parse();
+//!     hyperspace_core::command::run_cli::<hyperspace_core::chain::AnyConfig>(cli).await
+//! }
+//! ```
+//!
+//! The pipeline runs in [`crate::queue::flush_message_batch`], after the messages already
+//! delivered are filtered out and before they're split into bundles, so it sees the messages of
+//! the relay cycles, the packet clearings, the sweeps and the timeout workers. The messages
+//! submitted by the one-off commands (e.g. the handshakes) don't go through it.
+
+use async_trait::async_trait;
+use ibc_proto::google::protobuf::Any;
+use once_cell::sync::Lazy;
+use primitives::Chain;
+use std::sync::{Arc, RwLock};
+
+static MIDDLEWARES: Lazy<RwLock<Vec<Arc<dyn MessageMiddleware>>>> =
+	Lazy::new(|| RwLock::new(vec![]));
+
+/// The chain the messages are about to be submitted to.
+#[derive(Debug, Clone)]
+pub struct SubmissionContext {
+	/// Name of the chain
+	pub chain: String,
+	/// Account of the relayer on the chain, signing the messages
+	pub signer: String,
+}
+
+/// A middleware of the pipeline, see the [module docs](self).
+#[async_trait]
+pub trait MessageMiddleware: Send + Sync + 'static {
+	/// Name of the middleware, used in the logs
+	fn name(&self) -> &str;
+
+	/// Returns the messages to submit in place of `msgs`, which can be modified, dropped or
+	/// extended. An error rejects all the messages: none of them is submitted, and the relayer
+	/// finds them again in its next relay cycles.
+	async fn process(
+		&self,
+		context: &SubmissionContext,
+		msgs: Vec<Any>,
+	) -> anyhow::Result<Vec<Any>>;
+}
+
+/// Appends a middleware to the pipeline.
+pub fn register(middleware: impl MessageMiddleware) {
+	MIDDLEWARES.write().expect("lock poisoned").push(Arc::new(middleware));
+}
+
+/// Runs the messages to submit to the sink through the pipeline. Returns `None` if a middleware
+/// rejected them.
+pub(crate) async fn apply(sink: &impl Chain, mut msgs: Vec<Any>) -> Option<Vec<Any>> {
+	let middlewares = MIDDLEWARES.read().expect("lock poisoned").clone();
+	if middlewares.is_empty() {
+		return Some(msgs)
+	}
+	let context =
+		SubmissionContext { chain: sink.name().to_string(), signer: sink.account_id().to_string() };
+	for middleware in middlewares {
+		msgs = match middleware.process(&context, msgs).await {
+			Ok(msgs) => msgs,
+			Err(e) => {
+				log::warn!(
+					target: "hyperspace",
+					"Middleware {} rejected the messages to {}: {e:?}", middleware.name(), context.chain
+				);
+				return None
+			},
+		};
+		if msgs.is_empty() {
+			break
+		}
+	}
+	Some(msgs)
+}
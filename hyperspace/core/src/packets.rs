@@ -17,39 +17,194 @@ use crate::send_packet_relay::packet_relay_status;
 use rand::Rng;
 use sp_runtime::Either::{Left, Right};
 use std::{
+	collections::{HashMap, HashSet},
 	sync::{
 		atomic::{AtomicUsize, Ordering},
-		Arc,
+		Arc, Mutex,
 	},
 	time::Duration,
 };
-use tokio::{task::JoinSet, time::sleep};
+use tokio::{sync::OwnedSemaphorePermit, task::JoinSet, time::sleep};
 
-use crate::packets::utils::{
-	construct_ack_message, construct_recv_message, construct_timeout_message,
-	get_timeout_proof_height, verify_delay_passed, VerifyDelayOn,
+use crate::packets::{
+	filter::PacketFilter,
+	utils::{
+		construct_ack_message, construct_recv_message, construct_timeout_message,
+		get_timeout_proof_height, verify_delay_passed, VerifyDelayOn,
+	},
 };
 use ibc::{
 	applications::transfer::packet::PacketData,
 	core::{
 		ics02_client::client_state::ClientState as ClientStateT,
 		ics03_connection::connection::ConnectionEnd,
-		ics04_channel::channel::{ChannelEnd, State},
+		ics04_channel::channel::{ChannelEnd, Order, State},
+		ics24_host::identifier::{ChannelId, PortId},
 	},
 	Height,
 };
 use ibc_proto::google::protobuf::Any;
+use metrics::handler::MetricsHandler;
 use pallet_ibc::light_clients::AnyClientState;
 use primitives::{
-	error::Error, find_suitable_proof_height_for_client, packet_info_to_packet,
-	query_undelivered_acks, query_undelivered_sequences, Chain, UndeliveredType,
+	display::describe_transfer, error::Error, fees::is_fee_enabled,
+	find_suitable_proof_height_for_client, packet_info_to_packet, query_undelivered_acks,
+	query_undelivered_sequences, Chain, UndeliveredType,
 };
 
 pub mod connection_delay;
+pub mod filter;
 pub mod utils;
 
 pub const PROCESS_PACKETS_BATCH_SIZE: usize = 100;
 
+/// Returns the number of packets on the channel of the source that have been received on the sink,
+/// but not acknowledged yet, given the number of packets that haven't been received yet.
+async fn query_packets_in_flight(
+	source: &impl Chain,
+	source_height: Height,
+	channel_id: ChannelId,
+	port_id: PortId,
+	undelivered_count: usize,
+) -> Result<usize, anyhow::Error> {
+	let commitments = source.query_packet_commitments(source_height, channel_id, port_id).await?;
+	Ok(commitments.len().saturating_sub(undelivered_count))
+}
+
+/// Takes a slot from the remaining in-flight packets budget of a channel. Returns `false` if the
+/// budget is exhausted.
+fn take_recv_packet_budget(budget: &AtomicUsize) -> bool {
+	budget
+		.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+		.is_ok()
+}
+
+/// Returns the denom and the amount of an ICS20 transfer, `None` for the packets of the other
+/// applications.
+fn transfer_value(data: &PacketData) -> Option<(String, u128)> {
+	let amount = data.token.amount.to_string().parse::<u128>().ok()?;
+	Some((data.token.denom.to_string(), amount))
+}
+
+/// Returns the remaining in-flight value budget of the channel of the source, by denom: the limits
+/// of [`primitives::CommonClientState::max_value_in_flight`] minus the amounts of the transfers
+/// received on the sink, but not acknowledged yet.
+async fn query_value_in_flight_budget(
+	source: &impl Chain,
+	source_height: Height,
+	channel_id: ChannelId,
+	port_id: PortId,
+	undelivered_seqs: &[u64],
+) -> Result<HashMap<String, u128>, anyhow::Error> {
+	let mut budget = source.common_state().max_value_in_flight.clone();
+	let undelivered = undelivered_seqs.iter().collect::<HashSet<_>>();
+	let in_flight = source
+		.query_packet_commitments(source_height, channel_id, port_id.clone())
+		.await?
+		.into_iter()
+		.filter(|seq| !undelivered.contains(seq))
+		.collect::<Vec<_>>();
+	if in_flight.is_empty() {
+		return Ok(budget)
+	}
+	for packet in source.query_send_packets(channel_id, port_id, in_flight).await? {
+		let Ok(data) = serde_json::from_slice::<PacketData>(&packet.data) else { continue };
+		let Some((denom, amount)) = transfer_value(&data) else { continue };
+		if let Some(left) = budget.get_mut(&denom) {
+			*left = left.saturating_sub(amount);
+		}
+	}
+	Ok(budget)
+}
+
+/// Takes the amount of a transfer from the remaining in-flight value budget of its denom. Returns
+/// `false` if the budget of the denom is exhausted, the denoms without a limit aren't budgeted.
+fn take_value_budget(budget: &Mutex<HashMap<String, u128>>, data: &PacketData) -> bool {
+	let Some((denom, amount)) = transfer_value(data) else { return true };
+	let mut budget = budget.lock().unwrap();
+	let Some(left) = budget.get_mut(&denom) else { return true };
+	match left.checked_sub(amount) {
+		Some(rest) => {
+			*left = rest;
+			true
+		},
+		None => false,
+	}
+}
+
+/// Returns the sequences of the pending packets of the channel that are incentivized by ICS-29
+/// fees, see [`primitives::fees`].
+async fn query_incentivized_sequences(
+	chain: &impl Chain,
+	at: Height,
+	channel_end: &ChannelEnd,
+	channel_id: ChannelId,
+	port_id: PortId,
+) -> HashSet<u64> {
+	if !is_fee_enabled(&channel_end.version.to_string()) {
+		return HashSet::new()
+	}
+	match chain.query_incentivized_packets(at, channel_id, port_id.clone()).await {
+		Ok(packets) => {
+			log::debug!(target: "hyperspace", "Found {} incentivized packets on {channel_id}/{port_id} of {}", packets.len(), chain.name());
+			packets.into_iter().map(|packet| packet.sequence).collect()
+		},
+		Err(e) => {
+			log::warn!(target: "hyperspace", "Failed to query the incentivized packets of {channel_id}/{port_id} on {}: {e:?}", chain.name());
+			HashSet::new()
+		},
+	}
+}
+
+/// Moves the incentivized sequences first, so that they're relayed first when the packets to
+/// process are limited. The order of the other sequences is kept.
+fn incentivized_first(sequences: Vec<u64>, incentivized: &HashSet<u64>) -> Vec<u64> {
+	let (mut first, others): (Vec<_>, Vec<_>) =
+		sequences.into_iter().partition(|sequence| incentivized.contains(sequence));
+	first.extend(others);
+	first
+}
+
+/// Waits for a slot among the concurrent proof queries to the chain, see
+/// [`primitives::CommonClientState::proof_permits`].
+async fn acquire_proof_permit(chain: &impl Chain) -> Result<OwnedSemaphorePermit, anyhow::Error> {
+	Ok(chain.common_state().proof_permits.clone().acquire_owned().await?)
+}
+
+/// Orders the messages by the sequence of their packets, since the proof queries complete in any
+/// order.
+fn by_sequence(mut messages: Vec<(u64, Any)>) -> Vec<Any> {
+	messages.sort_by_key(|(sequence, _)| *sequence);
+	messages.into_iter().map(|(_, message)| message).collect()
+}
+
+/// Keeps the messages of the sequences that follow `next_sequence` without a gap, since an
+/// ordered channel only accepts its packets in sequence. A sequence that isn't ready stalls the
+/// sequences after it until the next relay cycle, rather than getting skipped.
+fn in_sequence(
+	mut messages: Vec<(u64, Any)>,
+	next_sequence: u64,
+	channel_id: ChannelId,
+	port_id: &PortId,
+) -> Vec<Any> {
+	messages.sort_by_key(|(sequence, _)| *sequence);
+	let mut expected = next_sequence;
+	let mut ready = vec![];
+	let mut stalled = 0;
+	for (sequence, message) in messages {
+		if sequence == expected && stalled == 0 {
+			ready.push(message);
+			expected += 1;
+		} else {
+			stalled += 1;
+		}
+	}
+	if stalled != 0 {
+		log::debug!(target: "hyperspace", "Ordered channel {channel_id}/{port_id} is stalled at sequence {expected}, {stalled} later messages wait for it");
+	}
+	ready
+}
+
 /// Returns a tuple of messages, with the first item being packets that are ready to be sent to the
 /// sink chain. And the second item being packet timeouts that should be sent to the source.
 ///
@@ -62,6 +217,30 @@ pub const PROCESS_PACKETS_BATCH_SIZE: usize = 100;
 pub async fn query_ready_and_timed_out_packets(
 	source: &impl Chain,
 	sink: &impl Chain,
+	filter: &PacketFilter,
+	metrics: Option<&MetricsHandler>,
+) -> Result<(Vec<Any>, Vec<Any>), anyhow::Error> {
+	query_packets(source, sink, filter, metrics, false).await
+}
+
+/// Returns the timeouts of the packets of the source that timed out on the sink, to be sent to
+/// the source, without building the other messages. Used by the timeout worker, see
+/// [`crate::timeouts`].
+pub async fn query_timed_out_packets(
+	source: &impl Chain,
+	sink: &impl Chain,
+	filter: &PacketFilter,
+) -> Result<Vec<Any>, anyhow::Error> {
+	let (_, timeout_messages) = query_packets(source, sink, filter, None, true).await?;
+	Ok(timeout_messages)
+}
+
+async fn query_packets(
+	source: &impl Chain,
+	sink: &impl Chain,
+	filter: &PacketFilter,
+	metrics: Option<&MetricsHandler>,
+	timeouts_only: bool,
 ) -> Result<(Vec<Any>, Vec<Any>), anyhow::Error> {
 	let mut messages = vec![];
 	let mut timeout_messages = vec![];
@@ -71,6 +250,10 @@ pub async fn query_ready_and_timed_out_packets(
 
 	// TODO: parallelize this
 	for (channel_id, port_id) in channel_whitelist {
+		if !filter.is_allowed(&channel_id, &port_id) {
+			log::trace!(target: "hyperspace", "Skipping channel {:?}/{:?} as it is not allowed by the packet filter", channel_id, port_id);
+			continue
+		}
 		let source_channel_response = match source
 			.query_channel_end(source_height, channel_id, port_id.clone())
 			.await
@@ -89,8 +272,12 @@ pub async fn query_ready_and_timed_out_packets(
 				continue
 			},
 		};
-		// we're only interested in open or closed channels
-		if !matches!(source_channel_end.state, State::Open | State::Closed) {
+		// we're only interested in open or closed channels, and in the upgrading channels that
+		// flush their in-flight packets
+		if !matches!(
+			source_channel_end.state,
+			State::Open | State::Closed | State::Flushing | State::FlushComplete
+		) {
 			log::trace!(target: "hyperspace", "Skipping channel {:?}/{:?} because it is not open or closed", channel_id, port_id.clone());
 			continue
 		}
@@ -181,7 +368,7 @@ pub async fn query_ready_and_timed_out_packets(
 		let max_packets_to_process = source.common_state().max_packets_to_process;
 
 		// query packets that are waiting for connection delay.
-		let seqs = query_undelivered_sequences(
+		let undelivered_seqs = query_undelivered_sequences(
 			source_height,
 			sink_height,
 			channel_id,
@@ -189,7 +376,75 @@ pub async fn query_ready_and_timed_out_packets(
 			source,
 			sink,
 		)
-		.await?
+		.await?;
+		if let Some(metrics) = metrics {
+			metrics.set_undelivered_packets(channel_id, port_id.clone(), undelivered_seqs.clone());
+		}
+
+		let recv_packets_budget = match source
+			.common_state()
+			.max_packets_in_flight
+			.filter(|_| !timeouts_only)
+		{
+			Some(max_packets_in_flight) => {
+				let in_flight = query_packets_in_flight(
+					source,
+					source_height,
+					channel_id,
+					port_id.clone(),
+					undelivered_seqs.len(),
+				)
+				.await?;
+				if in_flight >= max_packets_in_flight {
+					log::warn!(target: "hyperspace", "Pausing relaying of packets on {:?}/{:?}: {in_flight} packets in flight, limit is {max_packets_in_flight}", channel_id, port_id.clone());
+				}
+				max_packets_in_flight.saturating_sub(in_flight)
+			},
+			None => usize::MAX,
+		};
+		let recv_packets_budget = Arc::new(AtomicUsize::new(recv_packets_budget));
+		let value_budget = if source.common_state().max_value_in_flight.is_empty() || timeouts_only
+		{
+			HashMap::new()
+		} else {
+			let budget = query_value_in_flight_budget(
+				source,
+				source_height,
+				channel_id,
+				port_id.clone(),
+				&undelivered_seqs,
+			)
+			.await?;
+			for (denom, left) in &budget {
+				if *left == 0 {
+					log::warn!(target: "hyperspace", "Pausing relaying of the {denom} transfers on {:?}/{:?}: the in-flight value limit is reached", channel_id, port_id.clone());
+				}
+			}
+			budget
+		};
+		let value_budget = Arc::new(Mutex::new(value_budget));
+		let ordered = sink_channel_end.ordering == Order::Ordered;
+
+		// the oldest packets are the first to time out
+		let incentivized = if timeouts_only {
+			HashSet::new()
+		} else {
+			query_incentivized_sequences(
+				source,
+				source_height,
+				&source_channel_end,
+				channel_id,
+				port_id.clone(),
+			)
+			.await
+		};
+		let seqs = incentivized_first(
+			undelivered_seqs
+				.into_iter()
+				.filter(|seq| filter.is_sequence_allowed(*seq))
+				.collect(),
+			&incentivized,
+		)
 		.into_iter()
 		.take(max_packets_to_process)
 		.collect::<Vec<_>>();
@@ -218,6 +473,9 @@ pub async fn query_ready_and_timed_out_packets(
 				);
 				let timeout_packets_count = timeout_packets_count.clone();
 				let recv_packets_count = send_packets_count.clone();
+				let packet_sequence = send_packet.sequence;
+				let recv_packets_budget = recv_packets_budget.clone();
+				let value_budget = value_budget.clone();
 				recv_packets_join_set.spawn(async move {
 					sleep(duration).await;
 					let source = &source;
@@ -230,6 +488,8 @@ pub async fn query_ready_and_timed_out_packets(
 
 					if packet.timed_out(&sink_timestamp, sink_height) {
 						timeout_packets_count.fetch_add(1, Ordering::SeqCst);
+						// the timeout is proven with the state of the sink
+						let _permit = acquire_proof_permit(&**sink).await?;
 						// so we know this packet has timed out on the sink, we need to find the maximum
 						// consensus state height at which we can generate a non-membership proof of the
 						// packet for the sink's client on the source.
@@ -266,7 +526,7 @@ pub async fn query_ready_and_timed_out_packets(
 						)
 							.await?
 						{
-							log::trace!(target: "hyperspace", "Skipping packet as connection delay has not passed {:?}", packet);
+							log::debug!(target: "hyperspace", "Deferring packet until the connection delay has passed {:?}", packet);
 							return Ok(None)
 						}
 
@@ -280,16 +540,62 @@ pub async fn query_ready_and_timed_out_packets(
 							proof_height,
 						)
 							.await?;
-						return Ok(Some(Left(msg)))
+						return Ok(Some(Left((packet_sequence, msg))))
 					} else {
 						log::trace!(target: "hyperspace", "The packet has not timed out yet: {:?}", packet);
 					}
 
-					// If packet has not timed out but channel is closed on sink we skip
-					// Since we have no reference point for when this channel was closed so we can't
-					// calculate connection delays yet
+					// If the channel is closed on the sink, the packet will never be received, so it's
+					// timed out on close with a proof of the closed channel at the latest height of
+					// the sink's client on the source
 					if sink_channel_end.state == State::Closed {
-						log::debug!(target: "hyperspace", "Skipping packet as channel is closed on sink: {:?}", packet);
+						timeout_packets_count.fetch_add(1, Ordering::SeqCst);
+						let _permit = acquire_proof_permit(&**sink).await?;
+						let proof_height = latest_sink_height_on_source;
+						let closed_at_proof_height = sink
+							.query_channel_end(
+								proof_height,
+								packet.destination_channel,
+								packet.destination_port.clone(),
+							)
+							.await
+							.ok()
+							.and_then(|response| response.channel)
+							.and_then(|channel| ChannelEnd::try_from(channel).ok())
+							.map_or(false, |channel| channel.state == State::Closed);
+						if !closed_at_proof_height {
+							log::debug!(target: "hyperspace", "Deferring timeout on close until the client on {} is updated past the closing of the channel: {:?}", source.name(), packet);
+							return Ok(None)
+						}
+						if !verify_delay_passed(
+							&**source,
+							&**sink,
+							source_timestamp,
+							source_height,
+							sink_timestamp,
+							sink_height,
+							source_connection_end.delay_period(),
+							proof_height,
+							VerifyDelayOn::Source,
+						)
+							.await?
+						{
+							log::debug!(target: "hyperspace", "Deferring timeout on close until the connection delay has passed {:?}", packet);
+							return Ok(None)
+						}
+						let msg = construct_timeout_message(
+							&**source,
+							&**sink,
+							&sink_channel_end,
+							packet,
+							next_sequence_recv.next_sequence_receive,
+							proof_height,
+						)
+							.await?;
+						return Ok(Some(Left((packet_sequence, msg))))
+					}
+
+					if timeouts_only {
 						return Ok(None)
 					}
 
@@ -310,6 +616,7 @@ pub async fn query_ready_and_timed_out_packets(
 						return Ok(None)
 					}
 
+					let _permit = acquire_proof_permit(&**source).await?;
 					let proof_height = if let Some(proof_height) = find_suitable_proof_height_for_client(
 						&**source,
 						&**sink,
@@ -340,7 +647,7 @@ pub async fn query_ready_and_timed_out_packets(
 					)
 						.await?
 					{
-						log::trace!(target: "hyperspace", "Skipping packet as connection delay has not passed {:?}", packet);
+						log::debug!(target: "hyperspace", "Deferring packet until the connection delay has passed {:?}", packet);
 						return Ok(None)
 					}
 
@@ -351,31 +658,55 @@ pub async fn query_ready_and_timed_out_packets(
 
 					let list = &source.common_state().skip_tokens_list;
 
-					let decoded_dara: PacketData = serde_json::from_str(&String::from_utf8_lossy(packet.data.as_ref())).map_err(|e| {
-						Error::Custom(format!(
-						"Failed to decode packet data for packet {:?}: {:?}",
-						packet, e
-						))
-					})?;
+					// packets of other applications than ICS20, e.g. interchain accounts, aren't
+					// filtered by denom
+					if let Ok(decoded_data) = serde_json::from_slice::<PacketData>(packet.data.as_ref()) {
+						if list.iter().any(|skipped_denom| decoded_data.token.denom.base_denom.as_str() == skipped_denom) {
+							let transfer = describe_transfer(&**source, &**sink, &decoded_data).await;
+							log::info!(target: "hyperspace", "Skipping packet {} of {transfer} as its denom is in the skip list", packet.sequence);
+							return Ok(None)
+						}
+						if !take_value_budget(&value_budget, &decoded_data) {
+							let transfer = describe_transfer(&**source, &**sink, &decoded_data).await;
+							log::debug!(target: "hyperspace", "Skipping packet {} of {transfer} as the in-flight value limit of its denom is reached", packet.sequence);
+							return Ok(None)
+						}
+					}
 
-					if list.iter().any(|skiped_denom| decoded_dara.token.denom.base_denom.as_str() == skiped_denom) {
-						log::info!(target: "hyperspace", "Skipping packet as uosmo packet: {:?}", packet);
+					// the budget of ordered channels is taken once the packets are in sequence
+					if !ordered && !take_recv_packet_budget(&recv_packets_budget) {
+						log::debug!(target: "hyperspace", "Skipping packet as the in-flight packets limit is reached: {:?}", packet);
 						return Ok(None)
 					}
 
 					let msg = construct_recv_message(&**source, &**sink, packet, proof_height).await?;
-					Ok(Some(Right(msg)))
+					Ok(Some(Right((packet_sequence, msg))))
 				});
 			}
 		}
 
+		let mut recv_messages = vec![];
+		let mut channel_timeout_messages = vec![];
 		while let Some(result) = recv_packets_join_set.join_next().await {
 			let Some(either) = result?? else { continue };
 			match either {
-				Left(msg) => timeout_messages.push(msg),
-				Right(msg) => messages.push(msg),
+				Left(msg) => channel_timeout_messages.push(msg),
+				Right(msg) => recv_messages.push(msg),
 			}
 		}
+		timeout_messages.extend(by_sequence(channel_timeout_messages));
+		if ordered {
+			let mut recv_messages = in_sequence(
+				recv_messages,
+				next_sequence_recv.next_sequence_receive,
+				channel_id,
+				&port_id,
+			);
+			recv_messages.truncate(recv_packets_budget.load(Ordering::SeqCst));
+			messages.extend(recv_messages);
+		} else {
+			messages.extend(by_sequence(recv_messages));
+		}
 
 		let timeouts_count = timeout_packets_count.load(Ordering::SeqCst);
 		log::debug!(target: "hyperspace", "Found {timeouts_count} packets that have timed out");
@@ -383,6 +714,11 @@ pub async fn query_ready_and_timed_out_packets(
 			.on_undelivered_sequences(timeouts_count != 0, UndeliveredType::Timeouts)
 			.await;
 
+		// the sent packets weren't looked at
+		if timeouts_only {
+			continue
+		}
+
 		let sends_count = send_packets_count.load(Ordering::SeqCst);
 		log::debug!(target: "hyperspace", "Found {sends_count} sent packets");
 		sink.on_undelivered_sequences(sends_count != 0, UndeliveredType::Recvs).await;
@@ -394,7 +730,7 @@ pub async fn query_ready_and_timed_out_packets(
 		}
 
 		// query acknowledgements that are waiting for connection delay.
-		let acks = query_undelivered_acks(
+		let undelivered_acks = query_undelivered_acks(
 			source_height,
 			sink_height,
 			channel_id,
@@ -402,13 +738,46 @@ pub async fn query_ready_and_timed_out_packets(
 			&*source,
 			&*sink,
 		)
-		.await?
+		.await?;
+		if let Some(metrics) = metrics {
+			metrics.set_undelivered_acknowledgements(
+				channel_id,
+				port_id.clone(),
+				undelivered_acks.clone(),
+			);
+		}
+		// the fees of the packets are escrowed on the sink, their source
+		let incentivized = query_incentivized_sequences(
+			&*sink,
+			sink_height,
+			&sink_channel_end,
+			sink_channel_id,
+			sink_port_id.clone(),
+		)
+		.await;
+		let acks = incentivized_first(
+			undelivered_acks
+				.into_iter()
+				.filter(|seq| filter.is_sequence_allowed(*seq))
+				.collect(),
+			&incentivized,
+		)
 		.into_iter()
 		.take(max_packets_to_process)
 		.collect::<Vec<_>>();
 
 		let acknowledgements =
 			source.query_received_packets(channel_id, port_id.clone(), acks).await?;
+		// an ordered channel accepts the acknowledgement of its oldest unacknowledged packet only,
+		// which is the oldest packet commitment on the sink
+		let next_sequence_ack = if source_channel_end.ordering == Order::Ordered {
+			sink.query_packet_commitments(sink_height, sink_channel_id, sink_port_id.clone())
+				.await?
+				.into_iter()
+				.min()
+		} else {
+			None
+		};
 		log::trace!(target: "hyperspace", "Got acknowledgements for channel {:?}: {:?}", channel_id, acknowledgements);
 		let mut acknowledgements_join_set: JoinSet<Result<_, anyhow::Error>> = JoinSet::new();
 		sink.on_undelivered_sequences(!acknowledgements.is_empty(), UndeliveredType::Acks)
@@ -421,6 +790,7 @@ pub async fn query_ready_and_timed_out_packets(
 				let duration1 = Duration::from_millis(
 					rand::thread_rng().gen_range(1..source.rpc_call_delay().as_millis() as u64),
 				);
+				let packet_sequence = acknowledgement.sequence;
 				acknowledgements_join_set.spawn(async move {
 					sleep(duration1).await;
 					let source = &source;
@@ -449,6 +819,8 @@ pub async fn query_ready_and_timed_out_packets(
 
 					log::trace!(target: "hyperspace", "sink_height: {:?}, latest_source_height_on_sink: {:?}, acknowledgement.height: {}", sink_height, latest_source_height_on_sink, ack_height);
 
+					let _permit = acquire_proof_permit(&**source).await?;
+
 					let proof_height = if let Some(proof_height) = find_suitable_proof_height_for_client(
 						&**source,
 						&**sink,
@@ -480,19 +852,25 @@ pub async fn query_ready_and_timed_out_packets(
 					)
 						.await?
 					{
-						log::trace!(target: "hyperspace", "Skipping acknowledgement for packet as connection delay has not passed {:?}", packet);
+						log::debug!(target: "hyperspace", "Deferring acknowledgement for packet until the connection delay has passed {:?}", packet);
 						return Ok(None)
 					}
 
 					let msg = construct_ack_message(&**source, &**sink, packet, ack, proof_height).await?;
-					Ok(Some(msg))
+					Ok(Some((packet_sequence, msg)))
 				});
 			}
 		}
 
+		let mut ack_messages = vec![];
 		while let Some(result) = acknowledgements_join_set.join_next().await {
 			let Some(msg) = result?? else { continue };
-			messages.push(msg)
+			ack_messages.push(msg)
+		}
+		match next_sequence_ack {
+			Some(next_sequence_ack) =>
+				messages.extend(in_sequence(ack_messages, next_sequence_ack, channel_id, &port_id)),
+			None => messages.extend(by_sequence(ack_messages)),
 		}
 	}
 
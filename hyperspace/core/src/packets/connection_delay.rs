@@ -42,3 +42,47 @@ pub fn has_delay_elapsed(
 
 	Ok(true)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DELAY: Duration = Duration::from_secs(60);
+	const DELAY_BLOCKS: u64 = 10;
+
+	fn update() -> (Timestamp, Height) {
+		(Timestamp::from_nanoseconds(1_000_000_000_000).unwrap(), Height::new(0, 100))
+	}
+
+	fn after(elapsed: Duration, blocks: u64) -> (Timestamp, Height) {
+		let (time, height) = update();
+		((time + elapsed).unwrap(), height.add(blocks))
+	}
+
+	fn elapsed(current: (Timestamp, Height)) -> bool {
+		let (update_time, update_height) = update();
+		has_delay_elapsed(current.0, current.1, update_time, update_height, DELAY, DELAY_BLOCKS)
+			.unwrap()
+	}
+
+	#[test]
+	fn delays_elapse_once_both_the_time_and_the_blocks_passed() {
+		assert!(elapsed(after(DELAY, DELAY_BLOCKS)));
+		assert!(elapsed(after(DELAY * 2, DELAY_BLOCKS * 2)));
+	}
+
+	#[test]
+	fn delays_dont_elapse_when_only_the_time_passed() {
+		assert!(!elapsed(after(DELAY, DELAY_BLOCKS - 1)));
+	}
+
+	#[test]
+	fn delays_dont_elapse_when_only_the_blocks_passed() {
+		assert!(!elapsed(after(DELAY - Duration::from_secs(1), DELAY_BLOCKS)));
+	}
+
+	#[test]
+	fn delays_dont_elapse_right_after_the_update() {
+		assert!(!elapsed(update()));
+	}
+}
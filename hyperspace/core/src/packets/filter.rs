@@ -0,0 +1,105 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Allow and deny lists of the channels whose packets are relayed, configured in the `[filter]`
+/// section of the core config. Channels are matched on the chain the packets are sent from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PacketFilter {
+	/// If not empty, only the packets on these channels are relayed
+	#[serde(default)]
+	pub allow: Vec<(ChannelId, PortId)>,
+	/// Packets on these channels are never relayed
+	#[serde(default)]
+	pub deny: Vec<(ChannelId, PortId)>,
+	/// If set, only the packets with sequences in this range are relayed. Only set by the CLI
+	/// commands, e.g. `clear-packets`, so it's not read from the config
+	#[serde(skip)]
+	pub sequences: Option<RangeInclusive<u64>>,
+}
+
+impl PacketFilter {
+	/// Returns `true` if the packets on the given channel should be relayed.
+	pub fn is_allowed(&self, channel_id: &ChannelId, port_id: &PortId) -> bool {
+		let matches = |(id, port): &(ChannelId, PortId)| id == channel_id && port == port_id;
+		if self.deny.iter().any(matches) {
+			return false
+		}
+		self.allow.is_empty() || self.allow.iter().any(matches)
+	}
+
+	/// Returns `true` if the packet with the given sequence should be relayed.
+	pub fn is_sequence_allowed(&self, sequence: u64) -> bool {
+		self.sequences.as_ref().map(|range| range.contains(&sequence)).unwrap_or(true)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn channel(id: u64, port: &str) -> (ChannelId, PortId) {
+		(ChannelId::new(id), port.parse().unwrap())
+	}
+
+	#[test]
+	fn empty_filter_allows_everything() {
+		let filter = PacketFilter::default();
+		let (channel_id, port_id) = channel(0, "transfer");
+		assert!(filter.is_allowed(&channel_id, &port_id));
+		assert!(filter.is_sequence_allowed(1));
+		assert!(filter.is_sequence_allowed(u64::MAX));
+	}
+
+	#[test]
+	fn allow_list_restricts_the_channels() {
+		let filter = PacketFilter { allow: vec![channel(0, "transfer")], ..Default::default() };
+		let (channel_id, port_id) = channel(0, "transfer");
+		assert!(filter.is_allowed(&channel_id, &port_id));
+		let (channel_id, port_id) = channel(1, "transfer");
+		assert!(!filter.is_allowed(&channel_id, &port_id));
+		// the port is matched too
+		let (channel_id, port_id) = channel(0, "icahost");
+		assert!(!filter.is_allowed(&channel_id, &port_id));
+	}
+
+	#[test]
+	fn deny_list_wins_over_the_allow_list() {
+		let filter = PacketFilter {
+			allow: vec![channel(0, "transfer"), channel(1, "transfer")],
+			deny: vec![channel(1, "transfer")],
+			..Default::default()
+		};
+		let (channel_id, port_id) = channel(0, "transfer");
+		assert!(filter.is_allowed(&channel_id, &port_id));
+		let (channel_id, port_id) = channel(1, "transfer");
+		assert!(!filter.is_allowed(&channel_id, &port_id));
+
+		let filter = PacketFilter { deny: vec![channel(1, "transfer")], ..Default::default() };
+		let (channel_id, port_id) = channel(2, "transfer");
+		assert!(filter.is_allowed(&channel_id, &port_id));
+	}
+
+	#[test]
+	fn sequence_range_is_inclusive() {
+		let filter = PacketFilter { sequences: Some(5..=10), ..Default::default() };
+		assert!(!filter.is_sequence_allowed(4));
+		assert!(filter.is_sequence_allowed(5));
+		assert!(filter.is_sequence_allowed(10));
+		assert!(!filter.is_sequence_allowed(11));
+	}
+}
@@ -37,7 +37,9 @@ use ibc::{
 };
 use ibc_proto::google::protobuf::Any;
 use pallet_ibc::light_clients::AnyClientState;
-use primitives::{find_suitable_proof_height_for_client, Chain};
+use primitives::{
+	client_updates::client_update_time_and_height, find_suitable_proof_height_for_client, Chain,
+};
 use std::time::Duration;
 use tendermint_proto::Protobuf;
 
@@ -164,12 +166,15 @@ pub async fn verify_delay_passed(
 	verify_delay_on: VerifyDelayOn,
 ) -> Result<bool, anyhow::Error> {
 	log::trace!(target: "hyperspace", "Verifying delay passed for source: {source_height}, {source_timestamp}, sink: {sink_height}, {sink_timestamp}, connection delay: {}, proof height: {proof_height}, verify delay on: {verify_delay_on:?}", connection_delay.as_secs());
+	// without a delay, any client update is old enough
+	if connection_delay.is_zero() {
+		return Ok(true)
+	}
 	match verify_delay_on {
 		VerifyDelayOn::Source => {
 			let actual_proof_height = sink.get_proof_height(proof_height).await;
-			if let Ok((source_client_update_height, source_client_update_time)) = source
-				.query_client_update_time_and_height(sink.client_id(), actual_proof_height)
-				.await
+			if let Ok((source_client_update_height, source_client_update_time)) =
+				client_update_time_and_height(source, sink.client_id(), actual_proof_height).await
 			{
 				let block_delay =
 					calculate_block_delay(connection_delay, source.expected_block_time());
@@ -194,21 +199,17 @@ pub async fn verify_delay_passed(
 				proof_height,
 				actual_proof_height
 			);
-			let _cs = sink
+			let consensus_state = sink
 				.query_client_consensus(sink_height, source.client_id(), actual_proof_height)
 				.await
-				.unwrap()
-				.consensus_state
-				.unwrap_or_else(|| {
-					panic!(
-						"query_client_consensus for {} at height {} is not found",
-						source.client_id(),
-						actual_proof_height
-					)
-				});
-			if let Ok((sink_client_update_height, sink_client_update_time)) = sink
-				.query_client_update_time_and_height(source.client_id(), actual_proof_height)
-				.await
+				.ok()
+				.and_then(|response| response.consensus_state);
+			if consensus_state.is_none() {
+				log::debug!(target: "hyperspace", "Consensus state of {} at height {actual_proof_height} not found on {}", source.client_id(), sink.name());
+				return Ok(false)
+			}
+			if let Ok((sink_client_update_height, sink_client_update_time)) =
+				client_update_time_and_height(sink, source.client_id(), actual_proof_height).await
 			{
 				let block_delay =
 					calculate_block_delay(connection_delay, sink.expected_block_time());
@@ -0,0 +1,202 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pending packets of the channels between two chains, printed by
+//! `hyperspace query packets pending`.
+
+use ibc::{
+	applications::transfer::packet::PacketData,
+	core::ics24_host::identifier::{ChannelId, PortId},
+	Height,
+};
+use primitives::{
+	display::describe_transfer, query_undelivered_acks, query_undelivered_sequences, Chain,
+};
+use serde::Serialize;
+use std::{fmt, time::Duration};
+
+/// Default time left before the timeout of the packets reported as approaching it.
+pub const DEFAULT_TIMEOUT_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// A packet not received yet that times out on the counterparty within the timeout window.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpiringPacket {
+	pub sequence: u64,
+	/// Estimated time left before the timeout, 0 if the packet already timed out
+	pub time_left_secs: u64,
+	/// Amount, sender and receiver of the packet if it's an ICS20 transfer, see
+	/// [`primitives::display`]
+	pub transfer: Option<String>,
+}
+
+/// Pending packets sent from a channel of the chain.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelPending {
+	pub chain: String,
+	pub counterparty: String,
+	pub channel_id: ChannelId,
+	pub port_id: PortId,
+	/// Sequences of the packets sent on the chain that haven't been received on the counterparty
+	pub unreceived_packets: Vec<u64>,
+	/// Sequences of the acknowledgements written on the chain that haven't been delivered to the
+	/// counterparty
+	pub unreceived_acks: Vec<u64>,
+	/// Unreceived packets approaching their timeout
+	pub expiring_packets: Vec<ExpiringPacket>,
+	/// Error of the queries of the channel, if any
+	pub error: Option<String>,
+}
+
+impl fmt::Display for ChannelPending {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} {}/{} -> {}: ",
+			self.chain, self.channel_id, self.port_id, self.counterparty
+		)?;
+		if let Some(error) = &self.error {
+			return writeln!(f, "query failed: {error}")
+		}
+		writeln!(
+			f,
+			"{} unreceived packets {:?}, {} unreceived acknowledgements {:?}",
+			self.unreceived_packets.len(),
+			self.unreceived_packets,
+			self.unreceived_acks.len(),
+			self.unreceived_acks
+		)?;
+		for packet in &self.expiring_packets {
+			write!(f, "  packet {}", packet.sequence)?;
+			if let Some(transfer) = &packet.transfer {
+				write!(f, " ({transfer})")?;
+			}
+			if packet.time_left_secs == 0 {
+				writeln!(f, " timed out")?;
+			} else {
+				writeln!(f, " times out in {}s", packet.time_left_secs)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Queries the pending packets of the whitelisted channels of both chains.
+pub async fn query_pending_packets<A: Chain, B: Chain>(
+	chain_a: &A,
+	chain_b: &B,
+	timeout_window: Duration,
+) -> anyhow::Result<Vec<ChannelPending>> {
+	let mut pending = query_chain_pending(chain_a, chain_b, timeout_window).await?;
+	pending.extend(query_chain_pending(chain_b, chain_a, timeout_window).await?);
+	Ok(pending)
+}
+
+async fn query_chain_pending<A: Chain, B: Chain>(
+	source: &A,
+	sink: &B,
+	timeout_window: Duration,
+) -> anyhow::Result<Vec<ChannelPending>> {
+	let (source_height, _) = source.latest_height_and_timestamp().await?;
+	let (sink_height, sink_timestamp) = sink.latest_height_and_timestamp().await?;
+	let mut channels = vec![];
+	for (channel_id, port_id) in source.channel_whitelist() {
+		let mut channel = ChannelPending {
+			chain: source.name().to_string(),
+			counterparty: sink.name().to_string(),
+			channel_id,
+			port_id: port_id.clone(),
+			unreceived_packets: vec![],
+			unreceived_acks: vec![],
+			expiring_packets: vec![],
+			error: None,
+		};
+		let result = query_channel_pending(
+			source,
+			sink,
+			(source_height, sink_height, sink_timestamp.nanoseconds()),
+			timeout_window,
+			&mut channel,
+		)
+		.await;
+		if let Err(e) = result {
+			channel.error = Some(format!("{e:?}"));
+		}
+		channels.push(channel);
+	}
+	channels.sort_by(|a, b| (a.channel_id, &a.port_id).cmp(&(b.channel_id, &b.port_id)));
+	Ok(channels)
+}
+
+async fn query_channel_pending<A: Chain, B: Chain>(
+	source: &A,
+	sink: &B,
+	(source_height, sink_height, sink_timestamp): (Height, Height, u64),
+	timeout_window: Duration,
+	channel: &mut ChannelPending,
+) -> anyhow::Result<()> {
+	let (channel_id, port_id) = (channel.channel_id, channel.port_id.clone());
+	channel.unreceived_packets = query_undelivered_sequences(
+		source_height,
+		sink_height,
+		channel_id,
+		port_id.clone(),
+		source,
+		sink,
+	)
+	.await?;
+	channel.unreceived_acks = query_undelivered_acks(
+		source_height,
+		sink_height,
+		channel_id,
+		port_id.clone(),
+		source,
+		sink,
+	)
+	.await?;
+	if channel.unreceived_packets.is_empty() {
+		return Ok(())
+	}
+
+	let packets = source
+		.query_send_packets(channel_id, port_id, channel.unreceived_packets.clone())
+		.await?;
+	for packet in packets {
+		// the packet times out at whichever of its timeouts comes first
+		let by_height = (packet.timeout_height.revision_height != 0).then(|| {
+			let blocks = packet
+				.timeout_height
+				.revision_height
+				.saturating_sub(sink_height.revision_height);
+			sink.expected_block_time() * blocks.min(u32::MAX as u64) as u32
+		});
+		let by_timestamp = (packet.timeout_timestamp != 0)
+			.then(|| Duration::from_nanos(packet.timeout_timestamp.saturating_sub(sink_timestamp)));
+		let Some(time_left) = [by_height, by_timestamp].into_iter().flatten().min() else {
+			continue
+		};
+		if time_left <= timeout_window {
+			let transfer = match serde_json::from_slice::<PacketData>(&packet.data) {
+				Ok(data) => Some(describe_transfer(source, sink, &data).await),
+				Err(_) => None,
+			};
+			channel.expiring_packets.push(ExpiringPacket {
+				sequence: packet.sequence,
+				time_left_secs: time_left.as_secs(),
+				transfer,
+			});
+		}
+	}
+	channel.expiring_packets.sort_by_key(|packet| packet.time_left_secs);
+	Ok(())
+}
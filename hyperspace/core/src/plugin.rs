@@ -0,0 +1,128 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Build-time registry of the chains supported by the relayer.
+//!
+//! Support for a new chain type can be added from a separate crate, without modifying this one.
+//! The crate implements the [`primitives::Chain`] trait (and its super traits) for its client and
+//! builds its own relayer binary, registering the chain next to the built-in ones with the
+//! [`chains!`](crate::chains) macro:
+//!
+//! ```ignore
+//! mod chains {
+//!     use hyperspace_core::substrate::DefaultConfig;
+//!     use my_rollup::{RollupClient, RollupClientConfig};
+//!     use parachain::{ParachainClient, ParachainClientConfig};
+//!
+//!     hyperspace_core::chains! {
+//!         Parachain(ParachainClientConfig, ParachainClient<DefaultConfig>),
+//!         MyRollup(RollupClientConfig, RollupClient),
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     hyperspace_core::logging::setup_logging();
+//!     let cli = <hyperspace_core::command::Cli as clap::Parser>::parse();
+//!     hyperspace_core::command::run_cli::<chains::AnyConfig>(cli).await
+//! }
+//! ```
+//!
+//! The macro should be invoked in a dedicated module, since it brings the names it depends on into
+//! scope. The invoking crate must depend on `serde` for the config derives. The config type of the
+//! chain must have `client_id`, `connection_id`, `channel_whitelist` and `wasm_code_id` fields,
+//! like the built-in configs, and the client must have an async `new(config)` constructor and an
+//! async `reload(&self, config)` method applying the settings that can be changed while relaying.
+
+use async_trait::async_trait;
+use ibc::core::ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId};
+use primitives::Chain;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Config of any of the chains registered with [`chains!`](crate::chains). Implemented by the
+/// generated `AnyConfig`, and used by the CLI to construct the clients and update the config
+/// files.
+#[async_trait]
+pub trait ChainConfig: Serialize + DeserializeOwned + Clone + Send + Sync + 'static {
+	/// The generated `AnyChain`
+	type Chain: Chain;
+
+	/// Construct the client described by this config
+	async fn into_client(self) -> anyhow::Result<Self::Chain>;
+
+	fn set_client_id(&mut self, client_id: ClientId);
+
+	fn set_connection_id(&mut self, connection_id: ConnectionId);
+
+	fn set_channel_whitelist(&mut self, channel_id: ChannelId, port_id: PortId);
+
+	fn set_wasm_code_id(&mut self, code_id: String);
+
+	/// Applies the settings of this config that can be changed while relaying to the client
+	async fn reload(self, chain: &Self::Chain) -> anyhow::Result<()>;
+}
+
+/// Names used by the code generated by [`chains!`](crate::chains).
+#[doc(hidden)]
+pub mod prelude {
+	pub use crate::{
+		chain::{wasm_code_id_of, wrap_any_msg_into_wasm},
+		plugin::ChainConfig,
+	};
+	pub use anyhow;
+	pub use async_trait::async_trait;
+	pub use futures::Stream;
+	pub use hex;
+	pub use ibc::{
+		applications::transfer::{msgs::transfer::MsgTransfer, PrefixedCoin},
+		core::{
+			ics02_client::{
+				client_state::ClientType,
+				events::{CodeId, UpdateClient},
+			},
+			ics23_commitment::commitment::CommitmentPrefix,
+			ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
+		},
+		downcast,
+		events::IbcEvent,
+		signer::Signer,
+		timestamp::Timestamp,
+		Height,
+	};
+	pub use ibc_proto::{
+		google::protobuf::Any,
+		ibc::core::{
+			channel::v1::{
+				QueryChannelResponse, QueryChannelsResponse, QueryNextSequenceReceiveResponse,
+				QueryPacketAcknowledgementResponse, QueryPacketCommitmentResponse,
+				QueryPacketReceiptResponse,
+			},
+			client::v1::{QueryClientStateResponse, QueryConsensusStateResponse},
+			connection::v1::{IdentifiedConnection, QueryConnectionResponse},
+		},
+	};
+	pub use ics08_wasm::Bytes;
+	pub use log;
+	pub use pallet_ibc::{
+		light_clients::{AnyClientMessage, AnyClientState, AnyConsensusState},
+		Timeout,
+	};
+	pub use primitives::{
+		self, display::TokenDisplay, BatchLimits, Chain, CommonClientState, IbcProvider,
+		KeyProvider, LightClientSync, MisbehaviourHandler, UpdateType,
+	};
+	pub use serde::{Deserialize, Serialize};
+	pub use std::{pin::Pin, time::Duration};
+	pub use thiserror::Error;
+}
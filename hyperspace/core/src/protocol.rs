@@ -0,0 +1,203 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Messaging protocols relayed by the core relayer loop.
+//!
+//! The relayer loop takes care of the finality notifications, the light client updates, the
+//! batching and the submission of the messages, and the metrics. A [`MessagingProtocol`] only
+//! decides which messages are ready to be delivered after each finality notification, so that
+//! protocols other than IBC packets (e.g. ISMP style requests and responses) can be relayed with
+//! the same machinery, see [`crate::relay_with_protocol`].
+
+use crate::packets::{self, filter::PacketFilter};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
+use ibc_proto::google::protobuf::Any;
+use metrics::handler::MetricsHandler;
+use primitives::Chain;
+use std::sync::{Arc, RwLock};
+
+/// A messaging protocol relayed on top of the light clients of the two chains.
+#[async_trait]
+pub trait MessagingProtocol: Send + Sync {
+	/// Name of the protocol, used in the logs
+	fn name(&self) -> &str;
+
+	/// Returns a tuple of messages, with the first item being the messages that are ready to be
+	/// sent to the sink chain, and the second item being the messages that should be sent back to
+	/// the source chain (e.g. timeouts).
+	///
+	/// Messages are submitted after the client updates of the finality notification. Protocols
+	/// whose messages need a client update should flag it with
+	/// [`Chain::on_undelivered_sequences`], so that optional updates aren't skipped.
+	async fn query_ready_messages<A: Chain, B: Chain>(
+		&self,
+		source: &A,
+		sink: &B,
+		metrics: Option<&MetricsHandler>,
+	) -> anyhow::Result<(Vec<Any>, Vec<Any>)>;
+
+	/// Returns the timeouts of the messages of the source that can no longer be delivered to the
+	/// sink, to be sent to the source. Queried by the timeout worker, see [`crate::timeouts`], on
+	/// top of the messages of [`MessagingProtocol::query_ready_messages`].
+	async fn query_timed_out_messages<A: Chain, B: Chain>(
+		&self,
+		_source: &A,
+		_sink: &B,
+	) -> anyhow::Result<Vec<Any>> {
+		Ok(vec![])
+	}
+
+	/// Returns `true` if the packets on the given channel should be relayed. Checked by the
+	/// relayer loop for the packets it doesn't query through the protocol: the packet events of
+	/// the finality notifications, and the sequences of the audits and the reconciliations.
+	fn is_channel_allowed(&self, _channel_id: &ChannelId, _port_id: &PortId) -> bool {
+		true
+	}
+
+	/// Returns `true` if the packet with the given sequence on the given channel should be
+	/// relayed, see [`MessagingProtocol::is_channel_allowed`].
+	fn is_packet_allowed(&self, channel_id: &ChannelId, port_id: &PortId, _sequence: u64) -> bool {
+		self.is_channel_allowed(channel_id, port_id)
+	}
+}
+
+/// IBC packets, acknowledgements and timeouts on the whitelisted channels.
+#[derive(Debug, Clone, Default)]
+pub struct IbcPackets {
+	/// Channels allowed to be relayed, on top of the channel whitelists of the chains. Shared by
+	/// the clones of the protocol, so that it can be reloaded while relaying.
+	pub filter: Arc<RwLock<PacketFilter>>,
+}
+
+impl IbcPackets {
+	pub fn new(filter: PacketFilter) -> Self {
+		Self { filter: Arc::new(RwLock::new(filter)) }
+	}
+
+	pub fn set_filter(&self, filter: PacketFilter) {
+		*self.filter.write().unwrap() = filter;
+	}
+}
+
+#[async_trait]
+impl MessagingProtocol for IbcPackets {
+	fn name(&self) -> &str {
+		"ibc"
+	}
+
+	async fn query_ready_messages<A: Chain, B: Chain>(
+		&self,
+		source: &A,
+		sink: &B,
+		metrics: Option<&MetricsHandler>,
+	) -> anyhow::Result<(Vec<Any>, Vec<Any>)> {
+		let filter = self.filter.read().unwrap().clone();
+		packets::query_ready_and_timed_out_packets(source, sink, &filter, metrics).await
+	}
+
+	async fn query_timed_out_messages<A: Chain, B: Chain>(
+		&self,
+		source: &A,
+		sink: &B,
+	) -> anyhow::Result<Vec<Any>> {
+		let filter = self.filter.read().unwrap().clone();
+		packets::query_timed_out_packets(source, sink, &filter).await
+	}
+
+	fn is_channel_allowed(&self, channel_id: &ChannelId, port_id: &PortId) -> bool {
+		self.filter.read().unwrap().is_allowed(channel_id, port_id)
+	}
+
+	fn is_packet_allowed(&self, channel_id: &ChannelId, port_id: &PortId, sequence: u64) -> bool {
+		let filter = self.filter.read().unwrap();
+		filter.is_allowed(channel_id, port_id) && filter.is_sequence_allowed(sequence)
+	}
+}
+
+/// No messages, the relayer loop only updates the light clients.
+#[async_trait]
+impl MessagingProtocol for () {
+	fn name(&self) -> &str {
+		"none"
+	}
+
+	async fn query_ready_messages<A: Chain, B: Chain>(
+		&self,
+		_source: &A,
+		_sink: &B,
+		_metrics: Option<&MetricsHandler>,
+	) -> anyhow::Result<(Vec<Any>, Vec<Any>)> {
+		Ok((vec![], vec![]))
+	}
+}
+
+/// Relays both protocols over the same relayer loop.
+#[async_trait]
+impl<P: MessagingProtocol, Q: MessagingProtocol> MessagingProtocol for (P, Q) {
+	fn name(&self) -> &str {
+		self.0.name()
+	}
+
+	async fn query_ready_messages<A: Chain, B: Chain>(
+		&self,
+		source: &A,
+		sink: &B,
+		metrics: Option<&MetricsHandler>,
+	) -> anyhow::Result<(Vec<Any>, Vec<Any>)> {
+		let (mut messages, mut source_messages) = self
+			.0
+			.query_ready_messages(source, sink, metrics)
+			.await
+			.map_err(|e| anyhow!("[{}] {e:?}", self.0.name()))?;
+		let (more_messages, more_source_messages) = self
+			.1
+			.query_ready_messages(source, sink, metrics)
+			.await
+			.map_err(|e| anyhow!("[{}] {e:?}", self.1.name()))?;
+		messages.extend(more_messages);
+		source_messages.extend(more_source_messages);
+		Ok((messages, source_messages))
+	}
+
+	async fn query_timed_out_messages<A: Chain, B: Chain>(
+		&self,
+		source: &A,
+		sink: &B,
+	) -> anyhow::Result<Vec<Any>> {
+		let mut messages = self
+			.0
+			.query_timed_out_messages(source, sink)
+			.await
+			.map_err(|e| anyhow!("[{}] {e:?}", self.0.name()))?;
+		messages.extend(
+			self.1
+				.query_timed_out_messages(source, sink)
+				.await
+				.map_err(|e| anyhow!("[{}] {e:?}", self.1.name()))?,
+		);
+		Ok(messages)
+	}
+
+	fn is_channel_allowed(&self, channel_id: &ChannelId, port_id: &PortId) -> bool {
+		self.0.is_channel_allowed(channel_id, port_id) &&
+			self.1.is_channel_allowed(channel_id, port_id)
+	}
+
+	fn is_packet_allowed(&self, channel_id: &ChannelId, port_id: &PortId, sequence: u64) -> bool {
+		self.0.is_packet_allowed(channel_id, port_id, sequence) &&
+			self.1.is_packet_allowed(channel_id, port_id, sequence)
+	}
+}
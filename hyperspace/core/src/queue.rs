@@ -12,49 +12,372 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::{
+	event_stream::{self, RelayEventKind},
+	middleware,
+};
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
 use ibc_proto::google::protobuf::Any;
 use metrics::handler::MetricsHandler;
-use primitives::Chain;
+use primitives::{
+	balance::BalanceTransition, correlation::packet_tags, dead_letter::DEAD_LETTER_TTL,
+	display::token_display, retry::ErrorClass, store::decode_packet, BatchLimits, Chain,
+	MessageKind,
+};
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	time::Duration,
+};
 
-/// This sends messages to the sink chain in a gas-aware manner.
+/// Interval between two queries of the batch limits of the chains.
+pub const BATCH_LIMITS_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// This sends messages to the sink chain in a gas-aware manner, respecting the
+/// [`BatchLimits`] of the sink. A rejected bundle is bisected until the failing packet messages
+/// are isolated and dead-lettered, see [`primitives::dead_letter`].
 pub async fn flush_message_batch(
 	msgs: Vec<Any>,
 	metrics: Option<&MetricsHandler>,
 	sink: &impl Chain,
 ) -> Result<(), anyhow::Error> {
-	let block_max_weight = sink.block_max_weight();
-	let batch_weight = sink.estimate_weight(msgs.clone()).await?;
-
+	let common_state = sink.common_state();
+	let limits = common_state.batch_limits().or(&common_state.queried_batch_limits);
+	let max_weight = limits.max_weight.unwrap_or_else(|| sink.block_max_weight());
+	let msgs = match &common_state.store {
+		Some(store) => store.filter_delivered(msgs)?,
+		None => msgs,
+	};
+	let msgs = common_state.dead_letters.filter(msgs);
 	if let Some(metrics) = metrics {
-		metrics.handle_transaction_costs(batch_weight, &msgs).await;
+		metrics.report_dead_letters(common_state.dead_letters.len());
 	}
 
-	log::debug!(target: "hyperspace", "Outgoing messages weight: {} block max weight: {}", batch_weight, block_max_weight);
-	let ratio = (batch_weight / block_max_weight) as usize;
-	if ratio == 0 {
-		sink.submit(msgs).await?;
+	let msgs = if common_state.skip_delivered_check {
+		msgs
+	} else {
+		filter_delivered_on_chain(sink, msgs).await
+	};
+
+	if msgs.is_empty() {
+		return Ok(())
+	}
+	if !common_state.leadership.is_leader() {
+		log::debug!(
+			target: "hyperspace",
+			"Standby relayer, dropping {} messages to {}",
+			msgs.len(), sink.name()
+		);
+		return Ok(())
+	}
+	if !common_state.dry_run && submissions_paused(sink, metrics).await {
+		log::debug!(
+			target: "hyperspace",
+			"Submissions to {} are paused by the low balance, dropping {} messages",
+			sink.name(), msgs.len()
+		);
+		return Ok(())
+	}
+	let Some(msgs) = middleware::apply(sink, msgs).await else { return Ok(()) };
+	if msgs.is_empty() {
 		return Ok(())
 	}
 
-	// whelp our batch exceeds the block max weight.
-	let chunk = if ratio == 1 {
-		// split the batch into ratio * 2
-		ratio * 2
-	} else {
-		// split the batch into ratio + 2
-		ratio + 2
-	};
+	let mut batches = VecDeque::from(split_by_size(msgs, &limits));
+	while let Some(mut batch) = batches.pop_front() {
+		let batch_weight = sink.estimate_weight(batch.clone()).await?;
+		log::debug!(target: "hyperspace", "Outgoing messages weight: {} max weight: {}", batch_weight, max_weight);
+		if batch_weight > max_weight {
+			if batch.len() > 1 {
+				// whelp our batch exceeds the max weight, split it in halves until it fits.
+				log::info!(
+					target: "hyperspace",
+					"Outgoing messages weight: {} exceeds the max weight: {}. Splitting {} messages into two batches",
+					batch_weight, max_weight, batch.len(),
+				);
+				let rest = batch.split_off(batch.len() / 2);
+				batches.push_front(rest);
+				batches.push_front(batch);
+				continue
+			}
+			log::warn!(
+				target: "hyperspace",
+				"Message {} weight: {} exceeds the max weight: {}",
+				batch[0].type_url, batch_weight, max_weight,
+			);
+		}
+
+		if common_state.fee_ceiling.is_set() {
+			match sink.estimate_fee(batch.clone()).await {
+				Ok(fee) if common_state.fee_ceiling.is_exceeded_by(fee, batch.len()) => {
+					if batch.len() > 1 {
+						log::info!(
+							target: "hyperspace",
+							"Estimated fee {fee} of {} messages to {} exceeds the fee ceiling. Splitting them into two batches",
+							batch.len(), sink.name(),
+						);
+						let rest = batch.split_off(batch.len() / 2);
+						batches.push_front(rest);
+						batches.push_front(batch);
+					} else {
+						// found again by the next relay cycles, until the fees go down
+						log::warn!(
+							target: "hyperspace",
+							"{} Deferring message {} to {}: its estimated fee {fee} exceeds the fee ceiling",
+							packet_tags(&batch), batch[0].type_url, sink.name(),
+						);
+					}
+					continue
+				},
+				Ok(_) => (),
+				Err(e) => log::warn!(
+					target: "hyperspace",
+					"Failed to estimate the fee of {} messages to {}, submitting them anyway: {e:?}",
+					batch.len(), sink.name(),
+				),
+			}
+		}
+
+		if common_state.dry_run {
+			let type_urls = batch.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>();
+			match sink.simulate(batch.clone()).await {
+				Ok(()) => log::info!(
+					target: "hyperspace",
+					"[dry run] Would have submitted {} messages to {}: {type_urls:?}",
+					batch.len(), sink.name()
+				),
+				Err(e) => log::error!(
+					target: "hyperspace",
+					"[dry run] Simulation of {} messages on {} failed: {type_urls:?}: {e:?}",
+					batch.len(), sink.name()
+				),
+			}
+			continue
+		}
 
-	log::info!(
-		"Outgoing messages weight: {} exceeds the block max weight: {}. Chunking {} messages into {} chunks",
-        batch_weight, block_max_weight, msgs.len(), chunk,
-	);
-	let chunk_size = (msgs.len() / chunk).max(1);
-	// TODO: return number of failed messages and record it to metrics
-	for batch in msgs.chunks(chunk_size) {
-		// send out batches.
-		sink.submit(batch.to_vec()).await?;
+		if let Some(metrics) = metrics {
+			metrics.handle_transaction_costs(batch_weight, &batch).await;
+		}
+		common_state
+			.submission_limiter
+			.acquire(batch_weight, sink.expected_block_time())
+			.await;
+		// the lease may have been lost while the bundles were built or waited for the rate limits
+		if !common_state.leadership.is_leader() {
+			log::warn!(
+				target: "hyperspace",
+				"Lost the lease, dropping {} messages to {}",
+				batch.len() + batches.iter().map(Vec::len).sum::<usize>(), sink.name()
+			);
+			return Ok(())
+		}
+		let result = sink.submit(batch.clone()).await;
+		if let Some(metrics) = metrics {
+			metrics.report_submission(result.is_ok());
+			metrics.report_earned_fees(common_state.earned_fees.take());
+			if let Err(e) = &result {
+				metrics.report_submission_failure(ErrorClass::of(e).as_str());
+			}
+		}
+		match &result {
+			Ok(tx_id) =>
+				event_stream::publish_submission(sink.name(), format!("{tx_id:?}"), &batch),
+			Err(e) => event_stream::publish(
+				sink.name(),
+				RelayEventKind::Error { message: format!("{e:?}") },
+			),
+		}
+		if let Err(e) = result {
+			// the submission may have failed because the fees can't be paid anymore
+			common_state.balance_guard.expire();
+			let class = ErrorClass::of(&e);
+			match class {
+				// the failing messages are isolated, a smaller bundle also needs less gas
+				ErrorClass::Other | ErrorClass::OutOfGas | ErrorClass::Redundant => (),
+				// the transient errors were already retried, and the fees or the client fail the
+				// other bundles as well
+				_ => return Err(e.into()),
+			}
+			if batch.len() > 1 {
+				log::warn!(
+					target: "hyperspace",
+					"Bundle of {} messages rejected by {}: {e:?}. Splitting it into two bundles to isolate the failing messages",
+					batch.len(), sink.name(),
+				);
+				let rest = batch.split_off(batch.len() / 2);
+				batches.push_front(rest);
+				batches.push_front(batch);
+				continue
+			}
+			if class == ErrorClass::Redundant {
+				log::info!(
+					target: "hyperspace",
+					"{} Message {} was already relayed to {}, dropping it: {e}",
+					packet_tags(&batch), batch[0].type_url, sink.name(),
+				);
+				continue
+			}
+			if !common_state.dead_letters.insert(&batch[0]) {
+				return Err(e.into())
+			}
+			log::error!(
+				target: "hyperspace",
+				"{} Message {} rejected by {}, setting it aside for {DEAD_LETTER_TTL:?}: {e:?}",
+				packet_tags(&batch), batch[0].type_url, sink.name(),
+			);
+			if let Some(metrics) = metrics {
+				metrics.report_dead_letters(common_state.dead_letters.len());
+			}
+			continue
+		}
+		common_state.submitted_packets.record(&batch);
+		if let Some(store) = &common_state.store {
+			if let Err(e) = store.mark_delivered(&batch) {
+				log::warn!(target: "hyperspace", "Failed to store the messages delivered to {}: {e:?}", sink.name());
+			}
+		}
 	}
 
 	Ok(())
 }
+
+/// Drops the packet messages the chain already processed, e.g. delivered by another relayer of
+/// the path: the receipts of the packets it received, and the packets whose commitment it deleted
+/// on their acknowledgement or timeout. The messages of a channel the chain can't be queried for
+/// are kept.
+async fn filter_delivered_on_chain(sink: &impl Chain, msgs: Vec<Any>) -> Vec<Any> {
+	// the receipts are on the destination of the packets, the commitments on their source
+	let channel_of = |msg: &Any| {
+		let kind = MessageKind::of(msg)?;
+		let packet = decode_packet(msg)?;
+		let (port_id, channel_id) = if kind == MessageKind::RecvPacket {
+			(packet.destination_port, packet.destination_channel)
+		} else {
+			(packet.source_port, packet.source_channel)
+		};
+		Some(((kind == MessageKind::RecvPacket, port_id, channel_id), u64::from(packet.sequence)))
+	};
+	let mut sequences = HashMap::<(bool, PortId, ChannelId), Vec<u64>>::new();
+	for (channel, sequence) in msgs.iter().filter_map(channel_of) {
+		sequences.entry(channel).or_default().push(sequence);
+	}
+	if sequences.is_empty() {
+		return msgs
+	}
+	let at = match sink.latest_height_and_timestamp().await {
+		Ok((height, _)) => height,
+		Err(e) => {
+			log::warn!(target: "hyperspace", "Failed to query the latest height of {}, skipping the delivered check: {e:?}", sink.name());
+			return msgs
+		},
+	};
+
+	let mut undelivered = HashSet::new();
+	for ((is_recv, port_id, channel_id), seqs) in sequences {
+		let result = if is_recv {
+			sink.query_unreceived_packets(at, channel_id.clone(), port_id.clone(), seqs.clone())
+				.await
+		} else {
+			sink.query_unreceived_acknowledgements(
+				at,
+				channel_id.clone(),
+				port_id.clone(),
+				seqs.clone(),
+			)
+			.await
+		};
+		let pending = result.unwrap_or_else(|e| {
+			log::warn!(target: "hyperspace", "Failed to check the delivered packets of {port_id}/{channel_id} on {}: {e:?}", sink.name());
+			seqs
+		});
+		undelivered.extend(
+			pending
+				.into_iter()
+				.map(|sequence| ((is_recv, port_id.clone(), channel_id.clone()), sequence)),
+		);
+	}
+
+	let (msgs, delivered): (Vec<_>, Vec<_>) = msgs
+		.into_iter()
+		.partition(|msg| channel_of(msg).map_or(true, |key| undelivered.contains(&key)));
+	if !delivered.is_empty() {
+		log::info!(
+			target: "hyperspace",
+			"Skipping {} packet messages already delivered to {}: {}",
+			delivered.len(), sink.name(), packet_tags(&delivered)
+		);
+	}
+	msgs
+}
+
+/// Returns `true` while the submissions to the chain are paused by a low balance, checking the
+/// balance when it's due, see [`primitives::balance`].
+async fn submissions_paused(sink: &impl Chain, metrics: Option<&MetricsHandler>) -> bool {
+	let guard = &sink.common_state().balance_guard;
+	if !guard.check_due() {
+		return guard.is_paused()
+	}
+	let balance = match sink.query_relayer_balance().await {
+		Ok(balance) => balance,
+		Err(e) => {
+			log::warn!(target: "hyperspace", "Failed to query the relayer balance on {}: {e:?}", sink.name());
+			guard.defer();
+			return guard.is_paused()
+		},
+	};
+	let threshold = guard.threshold().unwrap_or_default();
+	if let Some(transition) = guard.record(balance) {
+		let display = token_display(sink, None).await;
+		let (shown_balance, shown_threshold) = (display.format(balance), display.format(threshold));
+		match transition {
+			BalanceTransition::Paused => log::error!(
+				target: "hyperspace",
+				"Relayer balance on {} is {shown_balance}, below {shown_threshold}: pausing the submissions until the account is topped up",
+				sink.name()
+			),
+			BalanceTransition::Resumed => log::info!(
+				target: "hyperspace",
+				"Relayer balance on {} is {shown_balance}, resuming the submissions", sink.name()
+			),
+		}
+	}
+	if let Some(metrics) = metrics {
+		metrics.report_balance(balance, guard.is_paused());
+	}
+	guard.is_paused()
+}
+
+/// Queries the batch limits of the chain, which are used where the chain config doesn't set them.
+pub async fn refresh_batch_limits(chain: &mut impl Chain) {
+	match chain.query_batch_limits().await {
+		Ok(limits) => {
+			log::debug!(target: "hyperspace", "Batch limits of {}: {:?}", chain.name(), limits);
+			chain.common_state_mut().queried_batch_limits = limits;
+		},
+		Err(e) =>
+			log::warn!(target: "hyperspace", "Failed to query the batch limits of {}: {:?}", chain.name(), e),
+	}
+}
+
+/// Splits the messages into batches within the size and count limits, keeping their order.
+fn split_by_size(msgs: Vec<Any>, limits: &BatchLimits) -> Vec<Vec<Any>> {
+	let max_bytes = limits.max_bytes.unwrap_or(u64::MAX);
+	let max_messages = limits.max_messages.map(|max| max.max(1) as usize).unwrap_or(usize::MAX);
+
+	let mut batches = vec![];
+	let mut batch = vec![];
+	let mut batch_bytes = 0;
+	for msg in msgs {
+		let msg_bytes = (msg.type_url.len() + msg.value.len()) as u64;
+		if !batch.is_empty() && (batch.len() >= max_messages || batch_bytes + msg_bytes > max_bytes)
+		{
+			batches.push(std::mem::take(&mut batch));
+			batch_bytes = 0;
+		}
+		batch_bytes += msg_bytes;
+		batch.push(msg);
+	}
+	if !batch.is_empty() {
+		batches.push(batch);
+	}
+	batches
+}
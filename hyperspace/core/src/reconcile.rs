@@ -0,0 +1,302 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Startup reconciliation of the on-chain state with what the relayer expects, so that each
+//! restart begins with a clear picture of the outstanding work.
+
+use crate::protocol::MessagingProtocol;
+use anyhow::anyhow;
+use ibc::{
+	core::{
+		ics02_client::client_state::ClientState as ClientStateT,
+		ics03_connection::connection::ConnectionEnd,
+		ics04_channel::channel::ChannelEnd,
+		ics24_host::identifier::{ChannelId, ClientId, PortId},
+	},
+	Height,
+};
+use metrics::handler::MetricsHandler;
+use pallet_ibc::light_clients::AnyClientState;
+use primitives::{query_undelivered_acks, query_undelivered_sequences, Chain};
+use serde::Serialize;
+
+/// Outstanding work on a channel of the chain.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelReport {
+	pub channel_id: ChannelId,
+	pub port_id: PortId,
+	/// Sequences of the packets that haven't been received on the counterparty yet
+	pub pending_packets: Vec<u64>,
+	/// Sequences of the acknowledgements that haven't been relayed to the counterparty yet
+	pub unrelayed_acks: Vec<u64>,
+	/// Problems found with the channel, e.g. counterparty info that doesn't match
+	pub problems: Vec<String>,
+}
+
+/// Outstanding work from a chain to its counterparty.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainReport {
+	pub chain: String,
+	pub counterparty: String,
+	/// Id of the light client of the chain on the counterparty
+	pub client_id: ClientId,
+	/// Latest height of the chain
+	pub chain_height: Height,
+	/// Latest height of the light client of the chain on the counterparty
+	pub client_height: Option<Height>,
+	pub channels: Vec<ChannelReport>,
+}
+
+impl ChainReport {
+	pub fn pending_packets(&self) -> usize {
+		self.channels.iter().map(|channel| channel.pending_packets.len()).sum()
+	}
+
+	pub fn unrelayed_acks(&self) -> usize {
+		self.channels.iter().map(|channel| channel.unrelayed_acks.len()).sum()
+	}
+
+	fn log(&self) {
+		let client_height = self
+			.client_height
+			.map(|height| height.to_string())
+			.unwrap_or("unknown".to_string());
+		log::info!(
+			target: "hyperspace",
+			"{} at {}, client {} on {} at {}: {} pending packets, {} unrelayed acknowledgements",
+			self.chain, self.chain_height, self.client_id, self.counterparty, client_height,
+			self.pending_packets(), self.unrelayed_acks()
+		);
+		for channel in &self.channels {
+			log::info!(
+				target: "hyperspace",
+				"{} {}/{}: pending packets: {:?}, unrelayed acknowledgements: {:?}",
+				self.chain, channel.channel_id, channel.port_id, channel.pending_packets,
+				channel.unrelayed_acks
+			);
+			for problem in &channel.problems {
+				log::warn!(target: "hyperspace", "{} {}/{}: {problem}", self.chain, channel.channel_id, channel.port_id);
+			}
+		}
+	}
+
+	fn apply_metrics(&self, metrics: &MetricsHandler) {
+		for channel in &self.channels {
+			metrics.set_undelivered_packets(
+				channel.channel_id,
+				channel.port_id.clone(),
+				channel.pending_packets.clone(),
+			);
+			metrics.set_undelivered_acknowledgements(
+				channel.channel_id,
+				channel.port_id.clone(),
+				channel.unrelayed_acks.clone(),
+			);
+		}
+	}
+}
+
+/// Outstanding work of the relayer in both directions.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciliationReport {
+	pub chain_a: ChainReport,
+	pub chain_b: ChainReport,
+}
+
+impl ReconciliationReport {
+	pub fn log(&self) {
+		self.chain_a.log();
+		self.chain_b.log();
+	}
+
+	/// Seeds the undelivered metrics with the outstanding work, since they start from zero after a
+	/// restart.
+	pub fn apply_metrics(
+		&self,
+		chain_a_metrics: &MetricsHandler,
+		chain_b_metrics: &MetricsHandler,
+	) {
+		self.chain_a.apply_metrics(chain_a_metrics);
+		self.chain_b.apply_metrics(chain_b_metrics);
+	}
+
+	pub async fn write_json(&self, path: &str) -> anyhow::Result<()> {
+		tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+		Ok(())
+	}
+}
+
+/// Queries the outstanding work of the relayer between the two chains, on the channels and the
+/// sequences the protocol relays.
+pub async fn reconcile<A: Chain, B: Chain, P: MessagingProtocol>(
+	chain_a: &A,
+	chain_b: &B,
+	protocol: &P,
+) -> anyhow::Result<ReconciliationReport> {
+	Ok(ReconciliationReport {
+		chain_a: reconcile_chain(chain_a, chain_b, protocol).await?,
+		chain_b: reconcile_chain(chain_b, chain_a, protocol).await?,
+	})
+}
+
+async fn reconcile_chain<A: Chain, B: Chain, P: MessagingProtocol>(
+	source: &A,
+	sink: &B,
+	protocol: &P,
+) -> anyhow::Result<ChainReport> {
+	let (source_height, _) = source.latest_height_and_timestamp().await?;
+	let (sink_height, _) = sink.latest_height_and_timestamp().await?;
+	let client_height = sink
+		.query_client_state(sink_height, source.client_id())
+		.await
+		.ok()
+		.and_then(|response| response.client_state)
+		.and_then(|client_state| AnyClientState::try_from(client_state).ok())
+		.map(|client_state| client_state.latest_height());
+
+	let mut channels = vec![];
+	for (channel_id, port_id) in source.channel_whitelist() {
+		if !protocol.is_channel_allowed(&channel_id, &port_id) {
+			continue
+		}
+		let mut channel = ChannelReport {
+			channel_id,
+			port_id: port_id.clone(),
+			pending_packets: vec![],
+			unrelayed_acks: vec![],
+			problems: vec![],
+		};
+		if let Err(e) =
+			reconcile_channel(source, sink, source_height, sink_height, protocol, &mut channel)
+				.await
+		{
+			channel.problems.push(format!("Failed to query channel state: {e:?}"));
+		}
+		channels.push(channel);
+	}
+
+	Ok(ChainReport {
+		chain: source.name().to_string(),
+		counterparty: sink.name().to_string(),
+		client_id: source.client_id(),
+		chain_height: source_height,
+		client_height,
+		channels,
+	})
+}
+
+async fn reconcile_channel<A: Chain, B: Chain, P: MessagingProtocol>(
+	source: &A,
+	sink: &B,
+	source_height: Height,
+	sink_height: Height,
+	protocol: &P,
+	channel: &mut ChannelReport,
+) -> anyhow::Result<()> {
+	let (channel_id, port_id) = (channel.channel_id, channel.port_id.clone());
+	let source_channel_end = source
+		.query_channel_end(source_height, channel_id, port_id.clone())
+		.await?
+		.channel
+		.map(ChannelEnd::try_from)
+		.transpose()
+		.map_err(|e| anyhow!("{e:?}"))?
+		.ok_or_else(|| anyhow!("Channel end not found"))?;
+
+	if let Some(connection_id) = source_channel_end.connection_hops.get(0) {
+		if let Some(expected) = source.connection_id() {
+			if *connection_id != expected {
+				channel.problems.push(format!(
+					"Channel is on connection {connection_id}, but the relayer is configured with {expected}"
+				));
+			}
+		}
+		let connection_end = source
+			.query_connection_end(source_height, connection_id.clone())
+			.await?
+			.connection
+			.map(ConnectionEnd::try_from)
+			.transpose()
+			.map_err(|e| anyhow!("{e:?}"))?;
+		if let Some(connection_end) = connection_end {
+			if *connection_end.client_id() != sink.client_id() {
+				channel.problems.push(format!(
+					"Connection {connection_id} is on client {}, but the relayer is configured with {}",
+					connection_end.client_id(),
+					sink.client_id()
+				));
+			}
+		}
+	}
+
+	let counterparty = source_channel_end.counterparty();
+	match counterparty.channel_id {
+		Some(sink_channel_id) => {
+			let sink_channel_end = sink
+				.query_channel_end(sink_height, sink_channel_id, counterparty.port_id.clone())
+				.await
+				.ok()
+				.and_then(|response| response.channel)
+				.and_then(|channel| ChannelEnd::try_from(channel).ok());
+			match sink_channel_end {
+				Some(sink_channel_end) => {
+					let sink_counterparty = sink_channel_end.counterparty();
+					if sink_counterparty.channel_id != Some(channel_id) ||
+						sink_counterparty.port_id != port_id
+					{
+						channel.problems.push(format!(
+							"Counterparty channel {sink_channel_id}/{} points to {:?}/{}",
+							counterparty.port_id,
+							sink_counterparty.channel_id,
+							sink_counterparty.port_id
+						));
+					}
+				},
+				None => channel.problems.push(format!(
+					"Counterparty channel {sink_channel_id}/{} not found on {}",
+					counterparty.port_id,
+					sink.name()
+				)),
+			}
+		},
+		None => channel.problems.push("Channel has no counterparty channel id".to_string()),
+	}
+
+	let allowed = |sequence: &u64| protocol.is_packet_allowed(&channel_id, &port_id, *sequence);
+	channel.pending_packets = query_undelivered_sequences(
+		source_height,
+		sink_height,
+		channel_id,
+		port_id.clone(),
+		source,
+		sink,
+	)
+	.await?
+	.into_iter()
+	.filter(allowed)
+	.collect();
+	channel.unrelayed_acks = query_undelivered_acks(
+		source_height,
+		sink_height,
+		channel_id,
+		port_id.clone(),
+		source,
+		sink,
+	)
+	.await?
+	.into_iter()
+	.filter(allowed)
+	.collect();
+	Ok(())
+}
@@ -0,0 +1,121 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hot reload of the config of a running relayer, on `SIGHUP` or when a config file is modified.
+//!
+//! Only the settings the clients share between their clones are reloaded: the packet filters, the
+//! batch limits, the fee and gas settings and the endpoints of the chains. Other changes, e.g. to
+//! the keys or the light client ids, require restarting the relayer.
+
+use crate::{chain::CoreConfig, plugin::ChainConfig, protocol::IbcPackets};
+use anyhow::anyhow;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Default interval between two checks of the config files for changes.
+pub const DEFAULT_CONFIG_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A path relayed by the process, and the config files it was started from.
+pub struct ReloadablePath<C: ChainConfig> {
+	/// Name of the path in the `paths` of the core config, or `None` if the path is relayed with
+	/// the `filter` of the core config
+	pub name: Option<String>,
+	pub config_a: String,
+	pub config_b: String,
+	/// Clones of the clients relayed by the path, sharing their reloadable settings
+	pub chain_a: C::Chain,
+	pub chain_b: C::Chain,
+	/// Clone of the protocol relayed by the path, sharing its filter
+	pub protocol: IbcPackets,
+}
+
+/// Reloads the config of the paths on `SIGHUP`, and every time one of the config files is
+/// modified if `interval` is set.
+pub async fn watch_config<C: ChainConfig>(
+	config_core: String,
+	paths: Vec<ReloadablePath<C>>,
+	interval: Option<Duration>,
+) {
+	let mut hangup = match signal(SignalKind::hangup()) {
+		Ok(hangup) => hangup,
+		Err(e) => {
+			log::error!(target: "hyperspace", "Failed to listen to SIGHUP, the config won't be reloaded: {e:?}");
+			return
+		},
+	};
+	let files = std::iter::once(config_core.clone())
+		.chain(paths.iter().flat_map(|path| [path.config_a.clone(), path.config_b.clone()]))
+		.collect::<Vec<_>>();
+	let mut modified = modification_times(&files).await;
+	let mut poll = tokio::time::interval(interval.unwrap_or(DEFAULT_CONFIG_RELOAD_INTERVAL));
+
+	loop {
+		tokio::select! {
+			_ = hangup.recv() => {
+				log::info!(target: "hyperspace", "Received SIGHUP, reloading the config");
+			}
+			_ = poll.tick(), if interval.is_some() => {
+				let times = modification_times(&files).await;
+				if times == modified {
+					continue
+				}
+				modified = times;
+				log::info!(target: "hyperspace", "Config files changed, reloading the config");
+			}
+		}
+		match reload(&config_core, &paths).await {
+			Ok(()) => log::info!(target: "hyperspace", "Reloaded the config"),
+			Err(e) => log::error!(target: "hyperspace", "Failed to reload the config: {e:?}"),
+		}
+	}
+}
+
+async fn modification_times(files: &[String]) -> Vec<Option<SystemTime>> {
+	let mut times = vec![];
+	for file in files {
+		times.push(tokio::fs::metadata(file).await.ok().and_then(|m| m.modified().ok()));
+	}
+	times
+}
+
+/// Parses all the config files before applying any of them, so that a malformed file doesn't
+/// leave the paths half reloaded.
+async fn reload<C: ChainConfig>(
+	config_core: &str,
+	paths: &[ReloadablePath<C>],
+) -> anyhow::Result<()> {
+	let core: CoreConfig = toml::from_str(&tokio::fs::read_to_string(config_core).await?)?;
+	let mut configs = vec![];
+	for path in paths {
+		let config_a: C = toml::from_str(&tokio::fs::read_to_string(&path.config_a).await?)?;
+		let config_b: C = toml::from_str(&tokio::fs::read_to_string(&path.config_b).await?)?;
+		let filter = match &path.name {
+			None => core.filter.clone(),
+			Some(name) => core
+				.paths
+				.iter()
+				.find(|config| config.name == *name)
+				.map(|config| config.filter.clone())
+				.ok_or_else(|| anyhow!("Path {name} isn't in the core config anymore"))?,
+		};
+		configs.push((config_a, config_b, filter));
+	}
+
+	for (path, (config_a, config_b, filter)) in paths.iter().zip(configs) {
+		config_a.reload(&path.chain_a).await?;
+		config_b.reload(&path.chain_b).await?;
+		path.protocol.set_filter(filter);
+	}
+	Ok(())
+}
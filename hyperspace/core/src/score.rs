@@ -0,0 +1,70 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Periodic scoring of the health of a relayed path, see [`metrics::score`].
+
+use metrics::{
+	handler::ScoreInputs,
+	health::{HealthState, RouteHealth},
+	score::{balance_headroom, HealthFactors, HealthScoreConfig, RouteHealthMetrics},
+};
+use primitives::Chain;
+use std::time::Duration;
+
+/// Scores the path every [`HealthScoreConfig::interval_secs`], reporting the score to the gauges
+/// and to the `/status` endpoint.
+pub async fn score_path<A: Chain, B: Chain>(
+	path: String,
+	chain_a: A,
+	chain_b: B,
+	inputs: (ScoreInputs, ScoreInputs),
+	config: HealthScoreConfig,
+	metrics: RouteHealthMetrics,
+	health: HealthState,
+) {
+	let (inputs_a, inputs_b) = inputs;
+	let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+	loop {
+		interval.tick().await;
+		let client_update_age = inputs_a.client_update_age().max(inputs_b.client_update_age());
+		let backlog = inputs_a.backlog() + inputs_b.backlog();
+		let error_rate = match (inputs_a.error_rate(), inputs_b.error_rate()) {
+			(Some(a), Some(b)) => a.max(b),
+			(rate, None) | (None, rate) => rate.unwrap_or_default(),
+		};
+		let headroom = match (query_headroom(&chain_a).await, query_headroom(&chain_b).await) {
+			(Some(a), Some(b)) => Some(a.min(b)),
+			(headroom, None) | (None, headroom) => headroom,
+		};
+
+		let factors = HealthFactors::new(&config, client_update_age, backlog, error_rate, headroom);
+		let score = factors.score(&config.weights);
+		log::debug!(target: "hyperspace", "Health score of {path}: {score:.2} ({factors})");
+		metrics.observe(&factors, score);
+		health.set_route_health(&path, RouteHealth { score, factors });
+	}
+}
+
+/// Balance headroom of the relayer account on the chain, or `None` if the chain has no minimum
+/// balance or the balance couldn't be queried.
+async fn query_headroom(chain: &impl Chain) -> Option<f64> {
+	let min_balance = chain.common_state().min_balance?;
+	match chain.query_relayer_balance().await {
+		Ok(balance) => Some(balance_headroom(balance, min_balance)),
+		Err(e) => {
+			log::warn!(target: "hyperspace", "Failed to query the relayer balance on {}: {e:?}", chain.name());
+			None
+		},
+	}
+}
@@ -0,0 +1,80 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Graceful shutdown of the relayer on `SIGTERM` or `SIGINT`.
+//!
+//! Once a shutdown is requested the relayer loops stop consuming finality events, but the
+//! messages of the cycle being processed are still submitted, so that no proof is built against a
+//! height it's never submitted for. The process exits once the loops have returned and the
+//! metrics are flushed, or after the shutdown timeout.
+
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use tokio::{
+	signal::unix::{signal, SignalKind},
+	sync::watch,
+};
+
+/// Default time given to the relayer loops to drain their in-flight messages.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+static SHUTDOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+/// Requests the relayer loops to stop.
+pub fn request() {
+	SHUTDOWN.send_replace(true);
+}
+
+/// Returns `true` if a shutdown was requested.
+pub fn is_requested() -> bool {
+	*SHUTDOWN.borrow()
+}
+
+/// Resolves once a shutdown is requested.
+pub async fn requested() {
+	let mut receiver = SHUTDOWN.subscribe();
+	// the sender is static, so it's never dropped
+	let _ = receiver.wait_for(|requested| *requested).await;
+}
+
+/// Requests a shutdown on `SIGTERM` or `SIGINT`, then exits the process if the relayer loops
+/// haven't returned within `timeout`.
+pub async fn listen_for_signals(timeout: Duration) {
+	let (mut terminate, mut interrupt) = match (
+		signal(SignalKind::terminate()),
+		signal(SignalKind::interrupt()),
+	) {
+		(Ok(terminate), Ok(interrupt)) => (terminate, interrupt),
+		(Err(e), _) | (_, Err(e)) => {
+			log::error!(target: "hyperspace", "Failed to listen to SIGTERM and SIGINT, the relayer won't shut down gracefully: {e:?}");
+			return
+		},
+	};
+	tokio::select! {
+		_ = terminate.recv() => log::info!(target: "hyperspace", "Received SIGTERM, shutting down"),
+		_ = interrupt.recv() => log::info!(target: "hyperspace", "Received SIGINT, shutting down"),
+	}
+	request();
+
+	tokio::select! {
+		_ = tokio::time::sleep(timeout) => log::error!(
+			target: "hyperspace",
+			"The in-flight messages weren't drained within {timeout:?}, exiting"
+		),
+		// a second signal skips the draining
+		_ = terminate.recv() => log::warn!(target: "hyperspace", "Received SIGTERM again, exiting"),
+		_ = interrupt.recv() => log::warn!(target: "hyperspace", "Received SIGINT again, exiting"),
+	}
+	std::process::exit(1);
+}
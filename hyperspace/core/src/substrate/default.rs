@@ -19,9 +19,15 @@ use crate::{
 use async_trait::async_trait;
 use codec::{Compact, Decode, Encode};
 use ibc_proto::google::protobuf::Any;
-use light_client_common::config::{
-	EventRecordT, IbcEventsT, LocalAddress, ParaLifecycleT, RuntimeCall, RuntimeStorage,
-	RuntimeTransactions,
+use light_client_common::{
+	config::{
+		EventRecordT, IbcEventsT, LocalAddress, ParaLifecycleT, RuntimeCall, RuntimeStorage,
+		RuntimeTransactions,
+	},
+	extrinsic_params::{
+		MetadataExtrinsicParams as ParachainExtrinsicParams,
+		MetadataExtrinsicParamsBuilder as ParachainExtrinsicsParamsBuilder, SignedExtensionsConfig,
+	},
 };
 use pallet_ibc::{events::IbcEvent as RawIbcEvent, MultiAddress, Timeout, TransferParams};
 use pallet_ibc_ping::SendPingParams;
@@ -29,14 +35,7 @@ use parachain_subxt::api::runtime_types::ibc_primitives::Timeout as RawTimeout;
 use relaychain::api::runtime_types::polkadot_runtime_parachains::paras::ParaLifecycle;
 use sp_core::{crypto::AccountId32, H256};
 use subxt::{
-	config::{
-		extrinsic_params::Era,
-		polkadot::{
-			PlainTip as Tip, PolkadotExtrinsicParams as ParachainExtrinsicParams,
-			PolkadotExtrinsicParamsBuilder as ParachainExtrinsicsParamsBuilder,
-		},
-		ExtrinsicParams,
-	},
+	config::{extrinsic_params::Era, polkadot::PlainTip as Tip, ExtrinsicParams},
 	events::Phase,
 	storage::{
 		address::{StaticStorageMapKey, Yes},
@@ -179,7 +178,20 @@ impl light_client_common::config::Config for DefaultConfig {
 		Error,
 	> {
 		let params =
-			ParachainExtrinsicsParamsBuilder::new().era(Era::Immortal, client.genesis_hash());
+			ParachainExtrinsicsParamsBuilder::default().era(Era::Immortal, client.genesis_hash());
+		Ok(params)
+	}
+
+	async fn extrinsic_params(
+		client: &OnlineClient<Self>,
+		signed_extensions: &SignedExtensionsConfig,
+	) -> Result<
+		<Self::ExtrinsicParams as ExtrinsicParams<Self::Index, Self::Hash>>::OtherParams,
+		Error,
+	> {
+		let params =
+			ParachainExtrinsicsParamsBuilder::from_metadata(&client.metadata(), signed_extensions)?
+				.era(Era::Immortal, client.genesis_hash());
 		Ok(params)
 	}
 }
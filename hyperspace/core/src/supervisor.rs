@@ -0,0 +1,173 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Supervision of the finality streams of the relayer loop.
+//!
+//! A finality stream that ends, or that stays silent for [`STALL_TIMEOUT_BLOCKS`] block times of
+//! its chain (at least [`MIN_STALL_TIMEOUT`]), is restarted: the client reconnects and subscribes
+//! again, with an exponential backoff between the failed attempts. Without it, a broken websocket
+//! would silently stop the relaying in one direction. The stall timeout of a stream doubles after
+//! each restart that didn't bring any event, e.g. while its chain is halted for an upgrade.
+
+use crate::{confirmations::confirmed, utils::RecentStream};
+use metrics::handler::MetricsHandler;
+use primitives::{retry::RetryPolicy, Chain};
+use std::time::{Duration, Instant};
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// Number of block times without finality notification after which a stream is restarted.
+pub const STALL_TIMEOUT_BLOCKS: u32 = 20;
+/// Minimum silence after which a stream is restarted.
+pub const MIN_STALL_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+/// Maximum number of times the stall timeout of a stream is doubled.
+const MAX_STALL_DOUBLINGS: u32 = 3;
+/// Interval between two checks of the streams.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Backoff between two failed attempts to subscribe to the finality notifications of a chain.
+fn restart_backoff() -> RetryPolicy {
+	RetryPolicy {
+		max_attempts: u32::MAX,
+		base_delay_ms: 1000,
+		multiplier: 2.0,
+		max_delay_ms: 5 * 60 * 1000,
+		jitter: 0.2,
+		retryable: vec![],
+	}
+}
+
+/// Subscribes to the finality notifications of the chain, reconnecting with a backoff until it
+/// succeeds.
+pub(crate) async fn open_stream<C: Chain>(chain: &mut C) -> RecentStream<C::FinalityEvent> {
+	let backoff = restart_backoff();
+	let mut attempt = 0;
+	loop {
+		match chain.finality_notifications().await {
+			Ok(stream) => return RecentStream::new(confirmed(chain, stream)),
+			Err(e) => {
+				attempt += 1;
+				let delay = backoff.delay(attempt);
+				log::error!(target: "hyperspace", "Failed to get finality notifications for {} {:?}. Trying again in {delay:?}...", chain.name(), e);
+				tokio::time::sleep(delay).await;
+				if let Err(e) = chain.reconnect().await {
+					log::warn!(target: "hyperspace", "Failed to reconnect to {}: {e:?}", chain.name());
+				}
+			},
+		}
+	}
+}
+
+/// Reconnects the chain and replaces its finality stream.
+pub(crate) async fn restart_stream<C: Chain>(
+	chain: &mut C,
+	stream: &mut RecentStream<C::FinalityEvent>,
+	metrics: Option<&MetricsHandler>,
+) {
+	if let Some(metrics) = metrics {
+		metrics.report_stream_alive(false);
+	}
+	if let Err(e) = chain.reconnect().await {
+		log::warn!(target: "hyperspace", "Failed to reconnect to {}: {e:?}", chain.name());
+	}
+	*stream = open_stream(chain).await;
+	if let Some(metrics) = metrics {
+		metrics.report_stream_alive(true);
+	}
+}
+
+/// The chains of the relayer loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Side {
+	A,
+	B,
+}
+
+#[derive(Debug)]
+struct StreamState {
+	timeout: Duration,
+	last_event: Instant,
+	/// Number of restarts since the last event
+	stalls: u32,
+}
+
+impl StreamState {
+	fn new(chain: &impl Chain) -> Self {
+		let timeout = (chain.expected_block_time() * STALL_TIMEOUT_BLOCKS).max(MIN_STALL_TIMEOUT);
+		// the first notification of a stream is delayed by the confirmations of the chain
+		let confirmations = chain.common_state().finality_confirmations;
+		let timeout = timeout +
+			Duration::from_secs(confirmations.secs.unwrap_or_default()) +
+			chain.expected_block_time() * confirmations.blocks.unwrap_or_default() as u32;
+		Self { timeout, last_event: Instant::now(), stalls: 0 }
+	}
+
+	fn is_stalled(&self) -> bool {
+		self.last_event.elapsed() >= self.timeout * 2u32.pow(self.stalls.min(MAX_STALL_DOUBLINGS))
+	}
+}
+
+/// Detects the finality streams of the relayer loop that went silent.
+pub(crate) struct StreamWatchdog {
+	check: Interval,
+	a: StreamState,
+	b: StreamState,
+}
+
+impl StreamWatchdog {
+	pub(crate) fn new(chain_a: &impl Chain, chain_b: &impl Chain) -> Self {
+		let mut check =
+			tokio::time::interval_at(tokio::time::Instant::now() + CHECK_INTERVAL, CHECK_INTERVAL);
+		check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+		Self { check, a: StreamState::new(chain_a), b: StreamState::new(chain_b) }
+	}
+
+	fn state(&mut self, side: Side) -> &mut StreamState {
+		match side {
+			Side::A => &mut self.a,
+			Side::B => &mut self.b,
+		}
+	}
+
+	/// Records a finality notification of the stream. A closed stream restarts both streams, see
+	/// [`crate::process_finality_event`].
+	pub(crate) fn on_event(&mut self, side: Side, closed: bool) {
+		let sides: &[Side] = if closed { &[Side::A, Side::B] } else { &[side] };
+		for side in sides {
+			let state = self.state(*side);
+			state.last_event = Instant::now();
+			state.stalls = 0;
+		}
+	}
+
+	/// Records the restart of a stalled stream.
+	pub(crate) fn on_restart(&mut self, side: Side) {
+		let state = self.state(side);
+		state.last_event = Instant::now();
+		state.stalls += 1;
+	}
+
+	/// Waits for the next check of the streams.
+	pub(crate) async fn tick(&mut self) {
+		self.check.tick().await;
+	}
+
+	/// Returns the streams that have been silent for longer than their stall timeout.
+	pub(crate) fn stalled(&self) -> Vec<Side> {
+		[(Side::A, &self.a), (Side::B, &self.b)]
+			.into_iter()
+			.filter(|(_, state)| state.is_stalled())
+			.map(|(side, _)| side)
+			.collect()
+	}
+}
@@ -0,0 +1,101 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timeout worker of a chain.
+//!
+//! The relay cycles build the timeouts along with the other messages, so on a busy path they wait
+//! behind the backlog of the relay loop, while the escrowed funds of the timed out packets stay
+//! locked. With [`primitives::CommonClientState::timeout_scan_interval`] set, the packets sent
+//! from the chain are also scanned on a task of their own, and the timeouts of those that timed
+//! out on the counterparty are built and submitted independently of the relay loop. A timeout
+//! still needs an update of the client of the counterparty past the timeout, which the scan flags
+//! for the relay loop with [`primitives::Chain::on_undelivered_sequences`].
+
+use crate::{protocol::MessagingProtocol, queue, shutdown};
+use metrics::handler::MetricsHandler;
+use primitives::Chain;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+/// Timeout worker of a chain. It stops on shutdown, once its scan in progress is submitted, see
+/// [`TimeoutWorker::stop`], and is aborted if it's dropped before.
+pub(crate) struct TimeoutWorker(Option<JoinHandle<()>>);
+
+impl TimeoutWorker {
+	/// Spawns the timeout worker of the source, unless `disabled` is set or the chain doesn't
+	/// have a timeout scan interval. The first scan runs after one interval.
+	pub(crate) fn spawn<A: Chain, B: Chain, P: MessagingProtocol + Clone + 'static>(
+		source: &A,
+		sink: &B,
+		metrics: Option<&MetricsHandler>,
+		protocol: &P,
+		disabled: bool,
+	) -> Self {
+		let Some(period) = source.common_state().timeout_scan_interval.filter(|_| !disabled) else {
+			return Self(None)
+		};
+		let (source, sink, protocol) = (source.clone(), sink.clone(), protocol.clone());
+		let metrics = metrics.cloned();
+		let handle = tokio::spawn(async move {
+			let mut interval =
+				tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+			interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+			loop {
+				tokio::select! {
+					_ = interval.tick() => (),
+					_ = shutdown::requested() => break,
+				}
+				if let Err(e) = scan(&source, &sink, metrics.as_ref(), &protocol).await {
+					log::error!(target: "hyperspace", "Failed to relay the timeouts of {}: {e:?}", source.name());
+				}
+			}
+		});
+		Self(Some(handle))
+	}
+
+	/// Waits for the worker to stop once a shutdown is requested.
+	pub(crate) async fn stop(mut self) {
+		if let Some(handle) = self.0.take() {
+			if let Err(e) = handle.await {
+				log::error!(target: "hyperspace", "Timeout worker failed: {e:?}");
+			}
+		}
+	}
+}
+
+impl Drop for TimeoutWorker {
+	fn drop(&mut self) {
+		if let Some(handle) = self.0.take() {
+			handle.abort();
+		}
+	}
+}
+
+/// Submits the timeouts of the packets of the source that timed out on the sink.
+async fn scan<A: Chain, B: Chain, P: MessagingProtocol>(
+	source: &A,
+	sink: &B,
+	metrics: Option<&MetricsHandler>,
+	protocol: &P,
+) -> anyhow::Result<()> {
+	let timeouts = protocol.query_timed_out_messages(source, sink).await?;
+	if timeouts.is_empty() {
+		log::debug!(target: "hyperspace", "Timeout scan of {} found nothing to relay", source.name());
+		return Ok(())
+	}
+	log::info!(
+		target: "hyperspace",
+		"Timeout scan of {} found {} timeouts, submitting them", source.name(), timeouts.len()
+	);
+	queue::flush_message_batch(timeouts, metrics, source).await
+}
@@ -0,0 +1,219 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watchtower mode: instead of relaying, every client update submitted by third-party relayers
+//! is checked against the finality data fetched from the counterparty chain itself.
+
+use anyhow::anyhow;
+use futures::{future::ready, StreamExt};
+use ibc::{
+	core::ics02_client::{client_consensus::ConsensusState, events::UpdateClient},
+	events::IbcEvent,
+};
+use pallet_ibc::light_clients::AnyConsensusState;
+use primitives::{BlockCommitment, Chain};
+
+/// Watches the client updates on both chains, raising an alert for every update that can't be
+/// reproduced from the counterparty chain.
+pub async fn watch<A: Chain, B: Chain>(chain_a: A, chain_b: B) -> anyhow::Result<()> {
+	// we only care about events where the counterparty light client is updated.
+	let (mut chain_a_client_updates, mut chain_b_client_updates) = (
+		chain_a.ibc_events().await.filter_map(|ev| {
+			ready(match ev {
+				IbcEvent::UpdateClient(update) if chain_b.client_id() == *update.client_id() =>
+					Some(update),
+				_ => None,
+			})
+		}),
+		chain_b.ibc_events().await.filter_map(|ev| {
+			ready(match ev {
+				IbcEvent::UpdateClient(update) if chain_a.client_id() == *update.client_id() =>
+					Some(update),
+				_ => None,
+			})
+		}),
+	);
+	log::info!(target: "hyperspace", "Watching client updates on {} and {}", chain_a.name(), chain_b.name());
+
+	loop {
+		tokio::select! {
+			update = chain_a_client_updates.next() => {
+				let Some(update) = update else { break };
+				check_update(&chain_a, &chain_b, update).await;
+			}
+			update = chain_b_client_updates.next() => {
+				let Some(update) = update else { break };
+				check_update(&chain_b, &chain_a, update).await;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Checks an update of the client of `counterparty` on `host`, alerting if it can't be verified.
+async fn check_update<A: Chain, B: Chain>(host: &A, counterparty: &B, update: UpdateClient) {
+	let (client_id, consensus_height) = (update.client_id().clone(), update.consensus_height());
+	match verify_update(host, counterparty, &update).await {
+		Ok(()) => log::info!(
+			target: "hyperspace",
+			"Verified update of client {client_id} on {} to {consensus_height}",
+			host.name()
+		),
+		Err(e) => log::error!(
+			target: "hyperspace",
+			"🚨 Update of client {client_id} on {} at {} to {consensus_height} can't be reproduced from {}: {e:?}",
+			host.name(), update.height(), counterparty.name()
+		),
+	}
+}
+
+/// Compares the consensus state written by the update with the block of the counterparty at the
+/// same height.
+async fn verify_update<A: Chain, B: Chain>(
+	host: &A,
+	counterparty: &B,
+	update: &UpdateClient,
+) -> anyhow::Result<()> {
+	let consensus_height = update.consensus_height();
+	let (latest_height, _) = counterparty.latest_height_and_timestamp().await?;
+	if consensus_height > latest_height {
+		return Err(anyhow!(
+			"Consensus height is ahead of the latest height {latest_height} of {}",
+			counterparty.name()
+		))
+	}
+
+	let consensus_state = host
+		.query_client_consensus(update.height(), update.client_id().clone(), consensus_height)
+		.await?
+		.consensus_state
+		.ok_or_else(|| anyhow!("Consensus state not found"))?;
+	let consensus_state =
+		AnyConsensusState::try_from(consensus_state).map_err(|e| anyhow!("{e:?}"))?;
+
+	let block = counterparty.query_block_commitment_at(consensus_height.revision_height).await?;
+	compare_consensus_state(&consensus_state, &block)
+}
+
+/// Checks that the consensus state commits to the timestamp, the state root and the next
+/// validator set of the block.
+fn compare_consensus_state(
+	consensus_state: &AnyConsensusState,
+	block: &BlockCommitment,
+) -> anyhow::Result<()> {
+	let consensus_state = match consensus_state {
+		AnyConsensusState::Wasm(wasm) => &*wasm.inner,
+		consensus_state => consensus_state,
+	};
+	let timestamp = consensus_state.timestamp().nanoseconds();
+	if timestamp != block.timestamp {
+		return Err(anyhow!(
+			"Consensus state timestamp {timestamp} doesn't match the block timestamp {}",
+			block.timestamp
+		))
+	}
+	let root = consensus_state.root().as_bytes();
+	if root != block.root.as_slice() {
+		return Err(anyhow!(
+			"Consensus state root {} doesn't match the block state root {}",
+			hex::encode(root),
+			hex::encode(&block.root)
+		))
+	}
+	if let (AnyConsensusState::Tendermint(consensus_state), Some(next_validators_hash)) =
+		(consensus_state, &block.next_validators_hash)
+	{
+		let hash = consensus_state.next_validators_hash.as_bytes();
+		if hash != next_validators_hash.as_slice() {
+			return Err(anyhow!(
+				"Consensus state next validators hash {} doesn't match the block next validators hash {}",
+				hex::encode(hash),
+				hex::encode(next_validators_hash)
+			))
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ibc::timestamp::Timestamp;
+
+	const TIMESTAMP: u64 = 1_000_000_000;
+
+	fn tendermint_consensus_state(
+		root: &[u8],
+		next_validators_hash: [u8; 32],
+	) -> AnyConsensusState {
+		AnyConsensusState::Tendermint(ics07_tendermint::consensus_state::ConsensusState::new(
+			root.to_vec().into(),
+			Timestamp::from_nanoseconds(TIMESTAMP).unwrap().into_tm_time().unwrap(),
+			tendermint::Hash::Sha256(next_validators_hash),
+		))
+	}
+
+	fn tendermint_block(root: &[u8], next_validators_hash: [u8; 32]) -> BlockCommitment {
+		BlockCommitment {
+			timestamp: TIMESTAMP,
+			root: root.to_vec(),
+			next_validators_hash: Some(next_validators_hash.to_vec()),
+		}
+	}
+
+	#[test]
+	fn matching_consensus_states_are_verified() {
+		let consensus_state = tendermint_consensus_state(b"app hash", [1; 32]);
+		compare_consensus_state(&consensus_state, &tendermint_block(b"app hash", [1; 32])).unwrap();
+
+		let consensus_state =
+			AnyConsensusState::Grandpa(ics10_grandpa::consensus_state::ConsensusState::new(
+				b"state root".to_vec(),
+				Timestamp::from_nanoseconds(TIMESTAMP).unwrap().into_tm_time().unwrap(),
+			));
+		let block = BlockCommitment {
+			timestamp: TIMESTAMP,
+			root: b"state root".to_vec(),
+			next_validators_hash: None,
+		};
+		compare_consensus_state(&consensus_state, &block).unwrap();
+	}
+
+	#[test]
+	fn mismatched_timestamps_are_refused() {
+		let consensus_state = tendermint_consensus_state(b"app hash", [1; 32]);
+		let block =
+			BlockCommitment { timestamp: TIMESTAMP + 1, ..tendermint_block(b"app hash", [1; 32]) };
+		assert!(compare_consensus_state(&consensus_state, &block).is_err());
+	}
+
+	#[test]
+	fn mismatched_roots_are_refused() {
+		let consensus_state = tendermint_consensus_state(b"forged app hash", [1; 32]);
+		let err =
+			compare_consensus_state(&consensus_state, &tendermint_block(b"app hash", [1; 32]))
+				.unwrap_err();
+		assert!(err.to_string().contains("root"), "{err}");
+	}
+
+	#[test]
+	fn mismatched_next_validators_hashes_are_refused() {
+		let consensus_state = tendermint_consensus_state(b"app hash", [2; 32]);
+		let err =
+			compare_consensus_state(&consensus_state, &tendermint_block(b"app hash", [1; 32]))
+				.unwrap_err();
+		assert!(err.to_string().contains("next validators hash"), "{err}");
+	}
+}
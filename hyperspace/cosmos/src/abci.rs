@@ -0,0 +1,144 @@
+//! Reasons of the transactions rejected or failed with a nonzero ABCI code.
+//!
+//! The failures are decoded from their codespace and code when the Cosmos SDK defines them (see
+//! `types/errors` of the SDK), and from their log otherwise, since the codes of the IBC modules
+//! changed between the versions of ibc-go. The reason is part of the message of the error, so that
+//! the retries and the metrics classify it (see [`primitives::retry::ErrorClass`]), and the log is
+//! kept for the reasons that are matched on it, e.g. the expected sequence of a mismatch.
+
+use std::fmt;
+use tendermint::abci::Code;
+
+/// Codespace of the errors of the Cosmos SDK
+const SDK_CODESPACE: &str = "sdk";
+
+/// Reason of a failed transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureReason {
+	/// The gas limit of the transaction was exceeded
+	OutOfGas,
+	/// The fee is below the minimum gas price of the node
+	InsufficientFee,
+	/// The account can't pay the fee
+	InsufficientFunds,
+	/// The sequence of the transaction isn't the one of the account
+	SequenceMismatch,
+	/// The transaction is already in the mempool of the node
+	AlreadyInMempool,
+	/// The client the messages are verified with expired or is frozen
+	ClientExpired,
+	/// The packets were already received, acknowledged or timed out
+	PacketAlreadyRelayed,
+	/// Any other failure
+	Other,
+}
+
+impl FailureReason {
+	/// Decodes the reason of a failure from its codespace and code, or from its log.
+	pub fn decode(codespace: &str, code: u32, log: &str) -> Self {
+		if codespace == SDK_CODESPACE {
+			match code {
+				5 => return FailureReason::InsufficientFunds,
+				11 => return FailureReason::OutOfGas,
+				13 => return FailureReason::InsufficientFee,
+				19 => return FailureReason::AlreadyInMempool,
+				32 => return FailureReason::SequenceMismatch,
+				_ => (),
+			}
+		}
+		let log = log.to_lowercase();
+		let matches = |patterns: &[&str]| patterns.iter().any(|p| log.contains(p));
+		if matches(&["out of gas"]) {
+			FailureReason::OutOfGas
+		} else if matches(&["insufficient fee"]) {
+			FailureReason::InsufficientFee
+		} else if matches(&["insufficient funds"]) {
+			FailureReason::InsufficientFunds
+		} else if matches(&["account sequence mismatch", "incorrect account sequence"]) {
+			FailureReason::SequenceMismatch
+		} else if matches(&["tx already exists in cache"]) {
+			FailureReason::AlreadyInMempool
+		} else if matches(&[
+			"client is not active",
+			"client state is not active",
+			"not within trust period",
+			"client is frozen",
+			"status expired",
+			"status frozen",
+		]) {
+			FailureReason::ClientExpired
+		} else if matches(&["redundant", "packet already received", "already been received"]) {
+			FailureReason::PacketAlreadyRelayed
+		} else {
+			FailureReason::Other
+		}
+	}
+}
+
+impl fmt::Display for FailureReason {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// the messages the retry classes match on
+		let message = match self {
+			FailureReason::OutOfGas => "out of gas",
+			FailureReason::InsufficientFee => "insufficient fee",
+			FailureReason::InsufficientFunds => "insufficient funds",
+			FailureReason::SequenceMismatch => "account sequence mismatch",
+			FailureReason::AlreadyInMempool => "already in mempool",
+			FailureReason::ClientExpired => "client expired",
+			FailureReason::PacketAlreadyRelayed => "packet already relayed",
+			FailureReason::Other => "an unknown error",
+		};
+		f.write_str(message)
+	}
+}
+
+/// A transaction rejected by `CheckTx`, or failed in its block, with a nonzero ABCI code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxFailure {
+	/// Hash of the transaction
+	pub hash: String,
+	/// Height of the block the transaction failed in, if it was included
+	pub height: Option<u64>,
+	pub reason: FailureReason,
+	pub codespace: String,
+	pub code: u32,
+	pub log: String,
+}
+
+impl TxFailure {
+	/// The failure of a transaction with the given ABCI code, `None` if it succeeded.
+	pub fn new(
+		hash: impl ToString,
+		height: Option<u64>,
+		codespace: &str,
+		code: Code,
+		log: &str,
+	) -> Option<Self> {
+		if code.is_ok() {
+			return None
+		}
+		let code = code.value();
+		Some(Self {
+			hash: hash.to_string(),
+			height,
+			reason: FailureReason::decode(codespace, code, log),
+			codespace: codespace.to_string(),
+			code,
+			log: log.to_string(),
+		})
+	}
+}
+
+impl fmt::Display for TxFailure {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.height {
+			Some(height) => write!(f, "transaction {} failed at height {height}", self.hash)?,
+			None => write!(f, "transaction {} rejected by CheckTx", self.hash)?,
+		}
+		write!(
+			f,
+			" with {} (codespace {:?}, code {}): {}",
+			self.reason, self.codespace, self.code, self.log
+		)
+	}
+}
@@ -0,0 +1,35 @@
+//! Relaying on behalf of another account through the authz module.
+//!
+//! With `authz_granter` set in the config, the messages are built in the name of the granter
+//! (see [`primitives::KeyProvider::account_id`]), and each of them is wrapped in a `MsgExec` of
+//! its own, signed by the relayer key, the grantee of the authorizations. Wrapping the messages
+//! one by one keeps their indices in the transaction, so the logs, the events and the errors of
+//! the transaction are attributed to them as without authz.
+
+use ibc_proto::{cosmos::authz::v1beta1::MsgExec, google::protobuf::Any};
+use prost::Message;
+
+pub const MSG_EXEC_TYPE_URL: &str = "/cosmos.authz.v1beta1.MsgExec";
+
+/// Wraps each message in a `MsgExec` of the grantee.
+pub fn wrap_in_exec(grantee: &str, messages: Vec<Any>) -> Vec<Any> {
+	messages
+		.into_iter()
+		.map(|msg| {
+			let exec = MsgExec { grantee: grantee.to_string(), msgs: vec![msg] };
+			Any { type_url: MSG_EXEC_TYPE_URL.to_string(), value: exec.encode_to_vec() }
+		})
+		.collect()
+}
+
+/// Returns the message wrapped in a `MsgExec` by [`wrap_in_exec`], or the message itself if it
+/// isn't one.
+pub fn unwrap_exec(msg: Any) -> Any {
+	if msg.type_url != MSG_EXEC_TYPE_URL {
+		return msg
+	}
+	match MsgExec::decode(&*msg.value) {
+		Ok(MsgExec { mut msgs, .. }) if msgs.len() == 1 => msgs.remove(0),
+		_ => msg,
+	}
+}
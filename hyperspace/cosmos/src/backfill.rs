@@ -0,0 +1,125 @@
+//! Backfill of the IBC events missed while the relayer was down.
+//!
+//! The IBC events of the chain are streamed from the blocks produced while the relayer runs, see
+//! [`IbcProvider::ibc_events`](primitives::IbcProvider::ibc_events). With
+//! `backfill_from_height` set, the stream starts with the events of the blocks from that height
+//! to the latest one instead, paged through from the indexer of the node: `tx_search` for the
+//! events of the transactions, and `block_search` for the packets sent by the `BeginBlock` and
+//! `EndBlock` of the blocks. The backfilled blocks have no `NewBlock` event, and the events of
+//! the blocks produced while backfilling may be streamed twice.
+
+use crate::{
+	client::CosmosClient,
+	compat,
+	error::Error,
+	events::{
+		event_is_type_channel, event_is_type_client, event_is_type_connection,
+		ibc_event_try_from_abci_event,
+	},
+};
+use ibc::{core::ics24_host::identifier::ChainId, events::IbcEvent, Height};
+use std::collections::BTreeMap;
+use tendermint::{abci::Event as AbciEvent, block::Height as TmHeight};
+use tendermint_rpc::{endpoint::block_search, query::Query, Order};
+
+/// Number of results of each page of the searches, the most the nodes return
+const PAGE_SIZE: u8 = 100;
+
+/// Events of a block, in the order they were emitted.
+#[derive(Default)]
+struct BlockEvents {
+	begin_block: Vec<IbcEvent>,
+	txs: Vec<IbcEvent>,
+	end_block: Vec<IbcEvent>,
+}
+
+impl<H> CosmosClient<H>
+where
+	H: 'static + Clone + Send + Sync,
+{
+	/// The IBC events of the blocks from `from` to `to`, by block and in the order they were
+	/// emitted, see [`crate::backfill`].
+	pub async fn backfill_ibc_events(&self, from: u64, to: u64) -> Result<Vec<IbcEvent>, Error> {
+		let revision = ChainId::chain_version(self.chain_id.to_string().as_str());
+		let mut blocks = BTreeMap::<u64, BlockEvents>::new();
+
+		let tx_query = Query::gte("tx.height", from).and_lte("tx.height", to);
+		let mut page = 1;
+		loop {
+			let response = self
+				.tx_search(tx_query.clone(), false, page, PAGE_SIZE, Order::Ascending)
+				.await
+				.map_err(|e| {
+					Error::RpcError(format!("Failed to search the txs {tx_query}: {e:?}"))
+				})?;
+			for tx in &response.txs {
+				let height = Height::new(revision, tx.height.value());
+				blocks
+					.entry(height.revision_height)
+					.or_default()
+					.txs
+					.extend(parse_ibc_events(&tx.tx_result.events, height));
+			}
+			if response.txs.is_empty() || page * PAGE_SIZE as u32 >= response.total_count {
+				break
+			}
+			page += 1;
+		}
+
+		let block_query = Query::gte("block.height", from)
+			.and_lte("block.height", to)
+			.and_exists("send_packet.packet_sequence");
+		let mut page = 1;
+		loop {
+			let request =
+				block_search::Request::new(block_query.clone(), page, PAGE_SIZE, Order::Ascending);
+			self.rpc_limiter.acquire(1).await;
+			let response = compat::perform(&self.rpc_http_client, self.comet_version, request)
+				.await
+				.map_err(|e| {
+					Error::RpcError(format!("Failed to search the blocks {block_query}: {e:?}"))
+				})?;
+			for block in &response.blocks {
+				let tm_height: TmHeight = block.block.header.height;
+				let height = Height::new(revision, tm_height.value());
+				let results = self.block_results(tm_height).await.map_err(|e| {
+					Error::RpcError(format!(
+						"Failed to query block result for height {height}: {e:?}"
+					))
+				})?;
+				let events = blocks.entry(height.revision_height).or_default();
+				events.begin_block =
+					parse_ibc_events(&results.begin_block_events.unwrap_or_default(), height)
+						.collect();
+				events.end_block =
+					parse_ibc_events(&results.end_block_events.unwrap_or_default(), height)
+						.collect();
+			}
+			if response.blocks.is_empty() || page * PAGE_SIZE as u32 >= response.total_count {
+				break
+			}
+			page += 1;
+		}
+
+		let events = blocks
+			.into_values()
+			.flat_map(|block| block.begin_block.into_iter().chain(block.txs).chain(block.end_block))
+			.collect::<Vec<_>>();
+		log::info!(
+			target: "hyperspace_cosmos",
+			"Backfilled {} IBC events of {} from {from} to {to}", events.len(), self.name
+		);
+		Ok(events)
+	}
+}
+
+/// The IBC client, connection and channel events among the events.
+fn parse_ibc_events(events: &[AbciEvent], height: Height) -> impl Iterator<Item = IbcEvent> + '_ {
+	events.iter().filter_map(move |event| {
+		ibc_event_try_from_abci_event(event, height).ok().filter(|ibc_event| {
+			event_is_type_client(ibc_event) ||
+				event_is_type_connection(ibc_event) ||
+				event_is_type_channel(ibc_event)
+		})
+	})
+}
@@ -0,0 +1,185 @@
+//! Batched queries of the consensus states and the packet commitments.
+//!
+//! Each proven query of the IBC store is a round trip to the RPC endpoint, which dominates the
+//! packet clears on public endpoints far from the relayer. The queries of a batch are still one
+//! request each, but they're sent concurrently, up to [`MAX_CONCURRENT_QUERIES`] at a time, so that
+//! their latencies overlap. The transaction searches of the sent and received packets are batched
+//! the same way, see [`CosmosClient::tx_search_batch`].
+
+use crate::{client::CosmosClient, error::Error, provider::increment_proof_height};
+use futures::{stream, Future, StreamExt, TryStreamExt};
+use ibc::{
+	core::{
+		ics04_channel::packet::Sequence,
+		ics24_host::{
+			identifier::{ChannelId, ClientId, PortId},
+			path::{ClientConsensusStatePath, CommitmentsPath},
+			Path,
+		},
+	},
+	Height,
+};
+use ibc_proto::{
+	google::protobuf::Any,
+	ibc::core::{
+		channel::v1::QueryPacketCommitmentResponse, client::v1::QueryConsensusStateResponse,
+	},
+};
+use primitives::KeyProvider;
+use prost::Message;
+use tendermint_rpc::{endpoint::tx_search, query::Query, Order};
+
+/// Maximum number of the queries of a batch in flight at a time
+pub const MAX_CONCURRENT_QUERIES: usize = 16;
+
+impl<H> CosmosClient<H>
+where
+	Self: KeyProvider,
+	H: Clone + Send + Sync + 'static,
+{
+	/// Queries the consensus states of the client at the given heights with their proofs, in the
+	/// order of the heights. The heights the client has no consensus state at are left out, as well
+	/// as those whose query failed, like the chains that query them one by one do.
+	pub async fn query_consensus_states_batch(
+		&self,
+		at: Height,
+		client_id: &ClientId,
+		heights: Vec<Height>,
+	) -> Result<Vec<(Height, QueryConsensusStateResponse)>, Error> {
+		let responses = query_concurrently(heights, |height| async move {
+			let path = Path::ClientConsensusState(ClientConsensusStatePath {
+				client_id: client_id.clone(),
+				epoch: height.revision_number,
+				height: height.revision_height,
+			});
+			let (query_result, proof) =
+				self.query_path(path.to_string().into_bytes(), at, true).await?;
+			if query_result.value.is_empty() {
+				return Ok::<_, Error>(None)
+			}
+			let response = QueryConsensusStateResponse {
+				consensus_state: Some(Any::decode(&*query_result.value)?),
+				proof,
+				proof_height: increment_proof_height(Some(at.into())),
+			};
+			Ok(Some(response))
+		})
+		.await;
+		let mut found = vec![];
+		for (height, response) in responses {
+			match response {
+				Ok(response) => found.extend(response.map(|response| (height, response))),
+				Err(e) => log::debug!(
+					target: "hyperspace_cosmos",
+					"Failed to query the consensus state of {client_id} at {height}: {e:?}"
+				),
+			}
+		}
+		Ok(found)
+	}
+
+	/// Queries the commitments of the packets of the channel with the given sequences with their
+	/// proofs, leaving out the packets that have no commitment anymore.
+	pub async fn query_packet_commitments_batch(
+		&self,
+		at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		seqs: impl IntoIterator<Item = u64>,
+	) -> Result<Vec<(u64, QueryPacketCommitmentResponse)>, Error> {
+		let responses = stream::iter(seqs)
+			.map(|seq| async move {
+				let path = Path::Commitments(CommitmentsPath {
+					port_id: port_id.clone(),
+					channel_id: *channel_id,
+					sequence: Sequence::from(seq),
+				});
+				let (query_result, proof) =
+					self.query_path(path.to_string().into_bytes(), at, true).await?;
+				if query_result.value.is_empty() {
+					return Ok::<_, Error>(None)
+				}
+				let response = QueryPacketCommitmentResponse {
+					commitment: query_result.value,
+					proof,
+					proof_height: increment_proof_height(Some(at.into())),
+				};
+				Ok(Some((seq, response)))
+			})
+			.buffered(MAX_CONCURRENT_QUERIES)
+			.try_collect::<Vec<_>>()
+			.await?;
+		Ok(responses.into_iter().flatten().collect())
+	}
+
+	/// Searches the most recent transaction matching each query, returning the responses in
+	/// the order of the queries.
+	pub async fn tx_search_batch(
+		&self,
+		queries: Vec<Query>,
+	) -> Result<Vec<tx_search::Response>, Error> {
+		stream::iter(queries)
+			.map(|query| async move {
+				self.tx_search(query, true, 1, 1, Order::Descending)
+					.await
+					.map_err(|e| Error::RpcError(format!("{e:?}")))
+			})
+			.buffered(MAX_CONCURRENT_QUERIES)
+			.try_collect()
+			.await
+	}
+}
+
+/// Runs the query of each key, up to [`MAX_CONCURRENT_QUERIES`] at a time, returning the results in
+/// the order of the keys.
+async fn query_concurrently<K, T, F, Fut>(keys: Vec<K>, query: F) -> Vec<(K, T)>
+where
+	K: Clone,
+	F: Fn(K) -> Fut,
+	Fut: Future<Output = T>,
+{
+	stream::iter(keys)
+		.map(|key| {
+			let result = query(key.clone());
+			async move { (key, result.await) }
+		})
+		.buffered(MAX_CONCURRENT_QUERIES)
+		.collect()
+		.await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Answers the query of `key` once the queries of the higher keys were polled, so that the
+	/// queries complete in the reverse order.
+	async fn query(key: u64) -> Result<Option<u64>, Error> {
+		for _ in key..16 {
+			tokio::task::yield_now().await;
+		}
+		match key {
+			3 => Err(Error::from("query failed".to_string())),
+			5 => Ok(None),
+			key => Ok(Some(key * 10)),
+		}
+	}
+
+	#[tokio::test]
+	async fn results_are_returned_in_the_order_of_the_keys() {
+		let keys = (0..40).collect::<Vec<u64>>();
+		let results = query_concurrently(keys.clone(), query).await;
+		assert_eq!(results.iter().map(|(key, _)| *key).collect::<Vec<_>>(), keys);
+	}
+
+	#[tokio::test]
+	async fn failed_queries_dont_fail_the_others() {
+		let results = query_concurrently((0..8).collect(), query).await;
+		assert!(results[3].1.is_err());
+		let found = results
+			.into_iter()
+			.filter_map(|(key, result)| Some((key, result.ok()??)))
+			.collect::<Vec<_>>();
+		assert_eq!(found, vec![(0, 0), (1, 10), (2, 20), (4, 40), (6, 60), (7, 70)]);
+	}
+}
@@ -0,0 +1,98 @@
+//! Interchain Security consumer chains.
+//!
+//! The validators of a consumer chain are those of its provider chain, whose validator set
+//! changes reach the consumer in the VSC packets of the CCV channel. A consumer chain has no
+//! staking module, so its unbonding period is queried from the params of its consumer module
+//! instead, with `ccv_consumer` set in the config.
+//!
+//! A VSC packet can replace a large share of the voting power at once, more than the trust
+//! threshold of the clients of the consumer tolerates between a trusted header and the next one.
+//! The headers of the consumer that follow a validator set change in a batch of client updates
+//! are therefore trusted from the header of the change rather than from the latest height of the
+//! client, see [`chain_trusted_heights`]. The headers of the changes are mandatory updates, which
+//! are always submitted, so the client holds their consensus states by the time the following
+//! headers are verified.
+
+use crate::{error::Error, grpc_pool::GrpcPool};
+use ibc::Height;
+use ics07_tendermint::client_message::Header;
+use primitives::UpdateType;
+use std::time::Duration;
+use tendermint::validator::Set as ValidatorSet;
+use tendermint_proto::google::protobuf::Duration as ProtoDuration;
+use tonic::codegen::http::uri::PathAndQuery;
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryParamsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryParamsResponse {
+	#[prost(message, optional, tag = "1")]
+	params: Option<ConsumerParams>,
+}
+
+/// The params of the consumer module the relayer reads
+#[derive(Clone, PartialEq, prost::Message)]
+struct ConsumerParams {
+	#[prost(message, optional, tag = "9")]
+	unbonding_period: Option<ProtoDuration>,
+}
+
+/// Queries the unbonding period of the consumer chain from the params of its consumer module.
+pub async fn query_unbonding_period(grpc_pool: &GrpcPool) -> Result<Duration, Error> {
+	let response: tonic::Response<QueryParamsResponse> = grpc_pool
+		.query(|channel| async move {
+			let mut client = tonic::client::Grpc::new(channel);
+			client
+				.ready()
+				.await
+				.map_err(|e| tonic::Status::unavailable(format!("GRPC client error: {:?}", e)))?;
+			client
+				.unary(
+					tonic::Request::new(QueryParamsRequest {}),
+					PathAndQuery::from_static(
+						"/interchain_security.ccv.consumer.v1.Query/QueryParams",
+					),
+					tonic::codec::ProstCodec::default(),
+				)
+				.await
+		})
+		.await
+		.map_err(|e| Error::from(format!("Failed to query the consumer params: {e:?}")))?;
+	let unbonding_period = response
+		.into_inner()
+		.params
+		.and_then(|params| params.unbonding_period)
+		.ok_or_else(|| Error::from("The consumer unbonding period is missing".to_string()))?;
+	Ok(Duration::new(
+		u64::try_from(unbonding_period.seconds).unwrap_or_default(),
+		u32::try_from(unbonding_period.nanos).unwrap_or_default(),
+	))
+}
+
+/// Trusts the headers following a validator set change from the header of the change. The
+/// headers are those of consecutive heights, in order, as built by
+/// [`crate::client::CosmosClient::msg_update_client_header`].
+pub fn chain_trusted_heights(headers: &mut [(Header, UpdateType)]) {
+	let mut anchor: Option<(Height, ValidatorSet)> = None;
+	for i in 0..headers.len() {
+		let (header, update_type) = &mut headers[i];
+		if let Some((trusted_height, trusted_validator_set)) = &anchor {
+			header.trusted_height = *trusted_height;
+			header.trusted_validator_set = trusted_validator_set.clone();
+		}
+		if update_type.is_optional() {
+			continue
+		}
+		// the validators of the next height are the next validators of the change
+		let Some((next, _)) = headers.get(i + 1) else { break };
+		let (header, _) = &headers[i];
+		anchor = Some((
+			Height::new(
+				header.trusted_height.revision_number,
+				header.signed_header.header.height.value(),
+			),
+			next.validator_set.clone(),
+		));
+	}
+}
@@ -1,5 +1,12 @@
-use super::{client::CosmosClient, tx::sign_tx};
-use crate::{error::Error, events::client_extract_attributes_from_tx, provider::FinalityEvent};
+use super::{
+	client::{Connections, CosmosClient},
+	tx::{simulate_tx, unsigned_tx},
+};
+use crate::{
+	authz, compat, error::Error, events::client_extract_attributes_from_tx, memo::DEFAULT_MEMO,
+	provider::FinalityEvent,
+};
+use bech32::{FromBase32, ToBase32, Variant};
 use futures::{Stream, StreamExt};
 use ibc::{
 	core::{
@@ -19,17 +26,21 @@ use ibc_proto::{
 };
 use pallet_ibc::light_clients::AnyClientMessage;
 use primitives::{
-	mock::LocalClientTypes, Chain, CommonClientState, IbcProvider, LightClientSync,
-	MisbehaviourHandler,
+	correlation::packet_tags, display::TokenDisplay, mock::LocalClientTypes,
+	rate_limit::RequestLimiter, retry::ErrorClass, BatchLimits, Chain, CommonClientState,
+	IbcProvider, LightClientSync, MisbehaviourHandler,
 };
 use prost::Message;
 use std::{pin::Pin, time::Duration};
 use tendermint_rpc::{
 	event::{Event, EventData},
 	query::{EventType, Query},
-	SubscriptionClient, WebSocketClient,
+	Client, SubscriptionClient,
 };
 
+/// Number of blocks covered by a finality notification.
+const FINALITY_BLOCKS: usize = 6;
+
 #[async_trait::async_trait]
 impl<H> LightClientSync for CosmosClient<H>
 where
@@ -61,10 +72,11 @@ where
 	}
 
 	async fn estimate_weight(&self, messages: Vec<Any>) -> Result<u64, Self::Error> {
+		let messages = self.authz_wrap(messages);
 		let account_info = self.query_account().await?;
 		let fee = self.get_fee();
-		let (_, tx_raw, _) =
-			sign_tx(self.keybase.clone(), self.chain_id.clone(), &account_info, vec![], fee)?;
+		let memo = self.tx_memo(&messages);
+		let (_, tx_raw, _) = unsigned_tx(&*self.signer, &account_info, vec![], fee, memo)?;
 
 		let body_bytes_len = tx_raw.body_bytes.len();
 		// Full length of the transaction can then be derived from the length of the invariable
@@ -90,18 +102,84 @@ where
 		Ok(current_len as u64)
 	}
 
+	async fn estimate_fee(&self, _messages: Vec<Any>) -> Result<u128, Self::Error> {
+		// the fee at the configured gas limit, which the transactions with a gas adjustment only
+		// pay if they need that much gas, see `select_fee_for_gas`
+		let fee = self.select_fee().await?;
+		Ok(fee.amount.iter().filter_map(|coin| coin.amount.parse::<u128>().ok()).sum())
+	}
+
+	async fn query_batch_limits(&self) -> Result<BatchLimits, Self::Error> {
+		self.rpc_limiter.acquire(1).await;
+		let params = self
+			.rpc_client
+			.latest_consensus_params()
+			.await
+			.map_err(|e| Error::RpcError(format!("{e:?}")))?
+			.consensus_params;
+		*self.max_block_gas.lock().unwrap() =
+			u64::try_from(params.block.max_gas).ok().filter(|max_gas| *max_gas > 0);
+		let gas_limit = self.fees().gas_limit;
+		if params.block.max_gas > 0 && gas_limit > params.block.max_gas as u64 {
+			log::warn!(
+				target: "hyperspace_cosmos",
+				"Gas limit {} of {} exceeds the block max gas {}",
+				gas_limit, self.name, params.block.max_gas
+			);
+		}
+		// the weight of a transaction is its length, see `estimate_weight`
+		let max_bytes = params.block.max_bytes;
+		Ok(BatchLimits {
+			max_weight: Some(max_bytes.min(self.max_tx_size as u64)),
+			max_bytes: Some(max_bytes),
+			max_messages: None,
+		})
+	}
+
+	async fn query_relayer_balance(&self) -> Result<u128, Self::Error> {
+		self.query_fee_balance(&self.fees().fee_denom).await
+	}
+
+	async fn query_token_display(&self, denom: Option<&str>) -> Result<TokenDisplay, Self::Error> {
+		self.query_denom_display(denom.unwrap_or(&self.fees().fee_denom)).await
+	}
+
+	fn format_account(&self, account: &str) -> String {
+		let address = match account.strip_prefix("0x") {
+			Some(hex) => hex::decode(hex).ok(),
+			None => bech32::decode(account)
+				.ok()
+				.and_then(|(_, data, _)| Vec::<u8>::from_base32(&data).ok()),
+		};
+		address
+			.and_then(|address| {
+				bech32::encode(&self.account_prefix, address.to_base32(), Variant::Bech32).ok()
+			})
+			.unwrap_or_else(|| account.to_string())
+	}
+
 	async fn finality_notifications(
 		&self,
 	) -> Result<
 		Pin<Box<dyn Stream<Item = <Self as IbcProvider>::FinalityEvent> + Send + Sync>>,
 		Error,
 	> {
+		if !self.comet_version.is_legacy() {
+			// the NewBlock events of the newer versions can't be parsed, see [`compat`]
+			let blocks = compat::poll_new_blocks(
+				self.rpc_http_client.clone(),
+				self.expected_block_time(),
+				FINALITY_BLOCKS as u64,
+			);
+			log::info!(target: "hyperspace_cosmos", "🛰️ Polling {} for finality notifications", self.name);
+			return Ok(Box::pin(blocks.map(|(from, to)| FinalityEvent::Tendermint { from, to })))
+		}
 		let ws_client = self.rpc_client.clone();
 		let subscription = ws_client
 			.subscribe(Query::from(EventType::NewBlock))
 			.await
 			.map_err(|e| Error::from(format!("failed to subscribe to new blocks {e:?}")))?
-			.chunks(6);
+			.chunks(FINALITY_BLOCKS);
 		log::info!(target: "hyperspace_cosmos", "🛰️ Subscribed to {} listening to finality notifications", self.name);
 		let stream = subscription.filter_map(|events| {
 			let events = events
@@ -132,36 +210,60 @@ where
 	}
 
 	async fn submit(&self, messages: Vec<Any>) -> Result<Self::TransactionId, Error> {
+		let packets = packet_tags(&messages);
 		let hash = self.submit_call(messages).await?;
 		log::debug!(target: "hyperspace_cosmos", "Submitted. Tx hash: {}", hash);
+		if !packets.is_empty() {
+			log::info!(target: "hyperspace_cosmos", "Delivered to {} in tx {hash}: {packets}", self.name);
+		}
 		Ok(Self::TransactionId { hash })
 	}
 
+	async fn simulate(&self, messages: Vec<Any>) -> Result<(), Error> {
+		let account_info = self.query_account().await?;
+		let messages = self.authz_wrap(messages);
+		let memo = self.tx_memo(&messages);
+		let (tx, _, tx_bytes) =
+			unsigned_tx(&*self.signer, &account_info, messages, self.select_fee().await?, memo)?;
+		let res = simulate_tx(&self.grpc_pool, tx, tx_bytes).await?;
+		log::info!(
+			target: "hyperspace_cosmos",
+			"Simulated transaction on {}: gas used: {:?}",
+			self.name, res.gas_info.map(|info| info.gas_used)
+		);
+		Ok(())
+	}
+
 	async fn query_client_message(
 		&self,
 		update: UpdateClient,
 	) -> Result<AnyClientMessage, Self::Error> {
 		let query_str = Query::eq("update_client.client_id", update.client_id().to_string())
 			.and_eq("update_client.client_type", update.client_type())
-			.and_eq("update_client.consensus_heights", update.consensus_height().to_string());
+			.and_eq(
+				format!("update_client.{}", self.ibc_go_version.consensus_height_attribute_key()),
+				update.consensus_height().to_string(),
+			);
 		// omit this field since the first three should be enough to identify the update
 		// .and_eq("update_client.header", hex::encode(&update.header.unwrap_or_default()))
 		use tendermint::abci::Event as AbciEvent;
 
-		let mut client = ServiceClient::connect(self.grpc_url.to_string())
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
-		let mut resp = client
-			.get_txs_event(GetTxsEventRequest {
-				events: query_str
-					.conditions
-					.into_iter()
-					.map(|c| c.to_string().replace(" = ", "="))
-					.collect(),
-				order_by: OrderBy::Desc.into(),
-				page: 1,
-				limit: 1,
-				..Default::default()
+		let request = GetTxsEventRequest {
+			events: query_str
+				.conditions
+				.into_iter()
+				.map(|c| c.to_string().replace(" = ", "="))
+				.collect(),
+			order_by: OrderBy::Desc.into(),
+			page: 1,
+			limit: 1,
+			..Default::default()
+		};
+		let mut resp = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move { ServiceClient::new(channel).get_txs_event(request).await }
 			})
 			.await
 			.map_err(|e| Error::from(e.to_string()))?
@@ -212,7 +314,7 @@ where
 			})?
 			.messages
 			.remove(idx as usize);
-		let envelope = Ics26Envelope::<LocalClientTypes>::try_from(x);
+		let envelope = Ics26Envelope::<LocalClientTypes>::try_from(authz::unwrap_exec(x));
 		if let Ok(Ics26Envelope::Ics2Msg(ClientMsg::UpdateClient(update_msg))) = envelope {
 			return Ok(update_msg.client_message)
 		}
@@ -239,6 +341,16 @@ where
 		{
 			self.reconnect().await?;
 			self.common_state.rpc_call_delay *= 2;
+		} else if error.downcast_ref::<Error>().is_some() &&
+			matches!(ErrorClass::of(&err_str), ErrorClass::Network | ErrorClass::Timeout) &&
+			self.rotate_endpoints(false)
+		{
+			// closing the websocket connection makes the relayer loop subscribe to the
+			// finality notifications of the new endpoint
+			for handle in self.join_handles.lock().await.drain(..) {
+				handle.abort();
+			}
+			self.reconnect().await?;
 		}
 
 		Ok(())
@@ -261,12 +373,28 @@ where
 	}
 
 	async fn reconnect(&mut self) -> anyhow::Result<()> {
-		let (rpc_client, ws_driver) = WebSocketClient::new(self.websocket_url.clone())
-			.await
-			.map_err(|e| Error::RpcError(format!("{e:?}")))?;
-		self.join_handles.lock().await.push(tokio::spawn(ws_driver.run()));
-		self.rpc_client = rpc_client;
-		log::info!(target: "hyperspace_cosmos", "Reconnected to cosmos chain");
+		let endpoints = self.endpoints.lock().unwrap().clone();
+		let connections = match Connections::connect(&endpoints, self.rest_client.is_some()).await {
+			Ok(connections) => connections,
+			Err(e) => {
+				// the next attempt uses the next endpoint
+				self.rotate_endpoints(true);
+				return Err(e.into())
+			},
+		};
+		self.rpc_http_client = connections.rpc_http_client;
+		self.rpc_limiter = RequestLimiter::new(
+			&endpoints.rpc_url,
+			self.rpc_rate_limit,
+			self.common_state.throttled_requests.clone(),
+		);
+		self.grpc_pool
+			.set_primary(endpoints.grpc_url.clone(), connections.grpc_client.clone());
+		self.grpc_client = connections.grpc_client;
+		self.light_client = connections.light_client;
+		self.join_handles.lock().await.push(tokio::spawn(connections.rpc_driver.run()));
+		self.rpc_client = connections.rpc_client;
+		log::info!(target: "hyperspace_cosmos", "Reconnected to cosmos chain at {}", endpoints.rpc_url);
 		Ok(())
 	}
 }
@@ -275,12 +403,52 @@ impl<H> CosmosClient<H>
 where
 	H: 'static + Clone + Send + Sync,
 {
+	/// Records a failure of the current endpoints, moving to the next ones if they failed too
+	/// often, or right away if `now` is set. Returns `true` if the endpoints changed.
+	fn rotate_endpoints(&self, now: bool) -> bool {
+		let next = {
+			let mut rotation = self.endpoint_rotation.lock().unwrap();
+			if now {
+				rotation.rotate()
+			} else {
+				rotation.report_failure()
+			}
+		};
+		let Some(next) = next else { return false };
+		log::warn!(
+			target: "hyperspace_cosmos",
+			"Endpoint {} of {} keeps failing, switching to {}",
+			self.endpoints.lock().unwrap().rpc_url, self.name, next.rpc_url
+		);
+		*self.endpoints.lock().unwrap() = next;
+		true
+	}
+
+	/// Memo of a transaction of the messages, see [`crate::memo`].
+	pub fn tx_memo(&self, messages: &[Any]) -> String {
+		let Some(template) = &self.memo_template else { return DEFAULT_MEMO.to_string() };
+		let path = self
+			.connection_id
+			.lock()
+			.unwrap()
+			.as_ref()
+			.map(|connection_id| connection_id.to_string())
+			.unwrap_or_default();
+		template.render(&path, messages)
+	}
+
 	pub fn get_fee(&self) -> Fee {
+		let fees = self.fees();
+		self.get_fee_with(fees.fee_denom, fees.fee_amount, fees.gas_limit)
+	}
+
+	pub fn get_fee_with(&self, denom: String, amount: String, gas_limit: u64) -> Fee {
+		let fees = self.fees();
 		Fee {
-			amount: vec![Coin { denom: self.fee_denom.clone(), amount: self.fee_amount.clone() }],
-			gas_limit: self.gas_limit,
+			amount: vec![Coin { denom, amount }],
+			gas_limit,
 			payer: "".to_string(),
-			granter: "".to_string(),
+			granter: fees.fee_granter.unwrap_or_default(),
 		}
 	}
 
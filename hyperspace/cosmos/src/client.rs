@@ -1,25 +1,54 @@
 #![allow(clippy::all)]
 use super::{
-	key_provider::KeyEntry,
+	key_provider::{account_address, KeyEntry, KeyType},
+	ledger::{LedgerConfig, LedgerTxSigner},
 	light_client::LightClient,
-	tx::{broadcast_tx, confirm_tx, sign_tx, simulate_tx},
+	signer::{RemoteTxSigner, TxSigner},
+	tx::{broadcast_tx, confirm_tx, sign_tx, simulate_tx, unsigned_tx, BroadcastMode},
 };
-use crate::error::Error;
-use bech32::ToBase32;
+use crate::{
+	authz, ccv,
+	compat::{self, CometVersion},
+	error::Error,
+	ethermint::{EthAccount, ETH_ACCOUNT_TYPE_URLS},
+	fee_market::DynamicGasPrice,
+	grpc_pool::{GrpcPool, HEALTH_CHECK_INTERVAL},
+	ibc_go::{self, IbcGoVersion},
+	light_store::{LightBlockStore, LightStoreRetention},
+	memo::MemoTemplate,
+	multisig::{MultisigConfig, MultisigSigner},
+	rest::RestClient,
+	sequence::{sequence_manager, SequenceManager},
+	staking::{self, DEFAULT_TRUSTING_PERIOD_FRACTION},
+};
+use bech32::{FromBase32, ToBase32};
 use bip32::{DerivationPath, ExtendedPrivateKey, XPrv, XPub as ExtendedPublicKey};
 use core::convert::{From, Into, TryFrom};
-use digest::Digest;
-use ibc::core::{
-	ics02_client::height::Height,
-	ics23_commitment::commitment::{CommitmentPrefix, CommitmentProofBytes},
-	ics24_host::{
-		identifier::{ChainId, ChannelId, ClientId, ConnectionId, PortId},
-		IBC_QUERY_PATH,
+use ibc::{
+	core::{
+		ics02_client::height::Height,
+		ics23_commitment::commitment::{CommitmentPrefix, CommitmentProofBytes},
+		ics24_host::{
+			identifier::{ChainId, ChannelId, ClientId, ConnectionId, PortId},
+			IBC_QUERY_PATH,
+		},
 	},
+	signer::Signer,
 };
 use ibc_proto::{
-	cosmos::auth::v1beta1::{query_client::QueryClient, BaseAccount, QueryAccountRequest},
+	cosmos::{
+		auth::v1beta1::{query_client::QueryClient, BaseAccount, QueryAccountRequest},
+		bank::v1beta1::{
+			query_client::QueryClient as BankQueryClient, QueryAllBalancesRequest,
+			QueryBalanceRequest, QueryDenomMetadataRequest,
+		},
+		base::query::v1beta1::PageRequest,
+		tx::v1beta1::Fee,
+	},
 	google::protobuf::Any,
+	ibc::applications::transfer::v1::{
+		query_client::QueryClient as TransferQueryClient, QueryDenomTraceRequest,
+	},
 };
 use ics07_tendermint::{
 	client_message::Header, client_state::ClientState, consensus_state::ConsensusState,
@@ -27,23 +56,33 @@ use ics07_tendermint::{
 };
 use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager};
 use primitives::{
+	balance::BalanceGuard,
+	display::TokenDisplay,
+	endpoints::{EndpointRotation, PRIMARY_CHECK_INTERVAL},
+	fees::DISTRIBUTE_FEE_EVENT,
+	rate_limit::{RequestLimiter, RequestRateLimit, SubmissionLimiter, ThrottledRequests},
+	remote_signer::RemoteSignerConfig,
 	Chain, CommonClientConfig, CommonClientState, IbcProvider, KeyProvider, UpdateType,
 };
 use prost::Message;
-use quick_cache::sync::Cache;
 use rand::Rng;
-use ripemd::Ripemd160;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
-	collections::HashSet,
+	collections::{HashMap, HashSet},
+	path::PathBuf,
 	str::FromStr,
-	sync::{Arc, Mutex},
+	sync::{atomic::AtomicUsize, Arc, Mutex},
 	time::Duration,
 };
-use tendermint::{block::Height as TmHeight, Hash};
+use tendermint::{abci::Event as AbciEvent, block::Height as TmHeight, Hash};
 use tendermint_light_client::components::io::{AtHeight, Io};
 use tendermint_light_client_verifier::types::{LightBlock, ValidatorSet};
-use tendermint_rpc::{endpoint::abci_query::AbciQuery, Client, HttpClient, Url, WebSocketClient};
+use tendermint_rpc::{
+	endpoint::{abci_query::AbciQuery, block_results, tx_search},
+	query::Query,
+	Client, HttpClient, Order, Url, WebSocketClient, WebSocketClientDriver,
+};
 use tokio::{
 	sync::{Mutex as TokioMutex, Mutex as AsyncMutex},
 	task::{JoinHandle, JoinSet},
@@ -66,12 +105,103 @@ fn default_fee_amount() -> String {
 	DEFAULT_FEE_AMOUNT.to_string()
 }
 
+/// A fee token the chain accepts in place of the native fee denom, e.g. an IBC'd token
+/// accepted through a fee abstraction module.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FeeToken {
+	/// Fee denom, e.g. `ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2`
+	pub denom: String,
+	/// Fee amount in the given denom
+	pub amount: String,
+}
+
+/// Fee and gas settings of the transactions, which can be reloaded while relaying.
+#[derive(Debug, Clone)]
+pub struct FeeSettings {
+	/// Fee denom
+	pub fee_denom: String,
+	/// Fee amount
+	pub fee_amount: String,
+	/// Alternative fee tokens, in order of preference
+	pub alternative_fee_tokens: Vec<FeeToken>,
+	/// Gas limit
+	pub gas_limit: u64,
+	/// Account granting the fees of the transactions through the feegrant module
+	pub fee_granter: Option<String>,
+	/// Gas price following the base fee of the chain, in place of `fee_amount`
+	pub dynamic_gas_price: Option<DynamicGasPrice>,
+	/// Factor of the gas used by the simulation of a transaction its gas limit is set to, in
+	/// place of `gas_limit`
+	pub gas_adjustment: Option<f64>,
+}
+
+/// Endpoints of the chain, which can be reloaded while relaying.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Endpoints {
+	/// Chain rpc address
+	pub rpc_url: Url,
+	/// Chain grpc address
+	pub grpc_url: Url,
+	/// Websocket chain ws client
+	pub websocket_url: Url,
+}
+
+/// Clients connected to the [`Endpoints`] of the chain.
+pub struct Connections {
+	pub rpc_client: WebSocketClient,
+	pub rpc_driver: WebSocketClientDriver,
+	pub rpc_http_client: HttpClient,
+	pub grpc_client: tonic::transport::Channel,
+	pub light_client: LightClient,
+}
+
+impl Connections {
+	/// Connects the clients. The gRPC endpoint is connected on its first query with `lazy_grpc`,
+	/// when the queries can fall back to the LCD, see [`crate::rest`].
+	pub async fn connect(endpoints: &Endpoints, lazy_grpc: bool) -> Result<Self, Error> {
+		let (rpc_client, rpc_driver) = WebSocketClient::new(endpoints.websocket_url.clone())
+			.await
+			.map_err(|e| Error::RpcError(format!("{:?}", e)))?;
+		let rpc_http_client = HttpClient::new(endpoints.rpc_url.clone())
+			.map_err(|e| Error::RpcError(format!("{:?}", e)))?;
+		let grpc_endpoint = tonic::transport::Endpoint::new(endpoints.grpc_url.to_string())
+			.map_err(|e| Error::RpcError(format!("{:?}", e)))?;
+		let grpc_client = match lazy_grpc {
+			true => grpc_endpoint.connect_lazy(),
+			false =>
+				grpc_endpoint.connect().await.map_err(|e| Error::RpcError(format!("{:?}", e)))?,
+		};
+		let light_client =
+			LightClient::init_light_client(endpoints.rpc_url.clone(), Duration::from_secs(10))
+				.await?;
+		Ok(Self { rpc_client, rpc_driver, rpc_http_client, grpc_client, light_client })
+	}
+}
+
+/// Returns `true` if the RPC and gRPC endpoints respond, or only the RPC one without
+/// `check_grpc`.
+async fn is_healthy(endpoints: &Endpoints, check_grpc: bool) -> bool {
+	let Ok(rpc_client) = HttpClient::new(endpoints.rpc_url.clone()) else { return false };
+	if rpc_client.health().await.is_err() {
+		return false
+	}
+	if !check_grpc {
+		return true
+	}
+	match tonic::transport::Endpoint::new(endpoints.grpc_url.to_string()) {
+		Ok(endpoint) => endpoint.connect().await.is_ok(),
+		Err(_) => false,
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConfigKeyEntry {
 	pub public_key: String,
 	pub private_key: String,
 	pub account: String,
 	pub address: Vec<u8>,
+	#[serde(default)]
+	pub key_type: KeyType,
 }
 
 impl TryFrom<ConfigKeyEntry> for KeyEntry {
@@ -80,9 +210,10 @@ impl TryFrom<ConfigKeyEntry> for KeyEntry {
 	fn try_from(value: ConfigKeyEntry) -> Result<Self, Self::Error> {
 		Ok(KeyEntry {
 			public_key: ExtendedPublicKey::from_str(&value.public_key)?,
-			private_key: ExtendedPrivateKey::from_str(&value.private_key)?,
+			private_key: Some(ExtendedPrivateKey::from_str(&value.private_key)?),
 			account: value.account,
 			address: value.address,
+			key_type: value.key_type,
 		})
 	}
 }
@@ -96,11 +227,11 @@ impl TryFrom<MnemonicEntry> for KeyEntry {
 			bip39::Mnemonic::from_phrase(&mnemonic_entry.mnemonic, bip39::Language::English)
 				.unwrap();
 		let seed = bip39::Seed::new(&mnemonic, "");
-		let key_m = XPrv::derive_from_path(seed, &DerivationPath::from_str("m/44'/118'/0'/0/0")?)?;
+		let key_type = mnemonic_entry.key_type;
+		let key_m = XPrv::derive_from_path(seed, &DerivationPath::from_str(key_type.hd_path())?)?;
 
 		// From pubkey to address
-		let sha256 = sha2::Sha256::digest(key_m.public_key().to_bytes());
-		let public_key_hash: [u8; 20] = Ripemd160::digest(sha256).into();
+		let public_key_hash = account_address(&key_m.public_key(), key_type);
 		let account = bech32::encode(
 			&mnemonic_entry.prefix,
 			public_key_hash.to_base32(),
@@ -109,9 +240,10 @@ impl TryFrom<MnemonicEntry> for KeyEntry {
 		.unwrap();
 		Ok(KeyEntry {
 			public_key: key_m.public_key(),
-			private_key: key_m,
+			private_key: Some(key_m),
 			account,
 			address: public_key_hash.into(),
+			key_type,
 		})
 	}
 }
@@ -119,6 +251,8 @@ impl TryFrom<MnemonicEntry> for KeyEntry {
 pub struct MnemonicEntry {
 	pub mnemonic: String,
 	pub prefix: String,
+	#[serde(default)]
+	pub key_type: KeyType,
 }
 // Implements the [`crate::Chain`] trait for cosmos.
 /// This is responsible for:
@@ -133,14 +267,32 @@ pub struct CosmosClient<H> {
 	pub rpc_client: WebSocketClient,
 	/// Chain http rpc client
 	pub rpc_http_client: HttpClient,
+	/// Version of the RPC schema of the node, see [`compat`]
+	pub comet_version: CometVersion,
+	/// Version of ibc-go of the chain, see [`crate::ibc_go`]
+	pub ibc_go_version: IbcGoVersion,
+	/// How the transactions are broadcast
+	pub broadcast_mode: BroadcastMode,
+	/// Account the messages are relayed on behalf of, see [`crate::authz`]
+	pub authz_granter: Option<Signer>,
 	/// Reusable GRPC client
 	pub grpc_client: tonic::transport::Channel,
-	/// Chain rpc address
-	pub rpc_url: Url,
-	/// Chain grpc address
-	pub grpc_url: Url,
-	/// Websocket chain ws client
-	pub websocket_url: Url,
+	/// gRPC endpoints the queries fail over to, see [`crate::grpc_pool`]
+	pub grpc_pool: GrpcPool,
+	/// LCD the gRPC queries fall back to, see [`crate::rest`]
+	pub rest_client: Option<RestClient>,
+	/// Limit of the rate of the requests to the RPC endpoint, see [`primitives::rate_limit`]
+	pub rpc_rate_limit: Option<RequestRateLimit>,
+	/// Rate limiter of the requests to the current RPC endpoint
+	pub rpc_limiter: RequestLimiter,
+	/// Height the IBC events are backfilled from by the first event stream, see
+	/// [`crate::backfill`]
+	pub backfill_from_height: Arc<Mutex<Option<u64>>>,
+	/// Chain endpoints, shared by the clones of the client. The clients are connected to new
+	/// endpoints on [`Chain::reconnect`](primitives::Chain::reconnect)
+	pub endpoints: Arc<Mutex<Endpoints>>,
+	/// Primary and fallback endpoints of the chain, `endpoints` being the current one
+	pub endpoint_rotation: Arc<Mutex<EndpointRotation<Endpoints>>>,
 	/// Chain Id
 	pub chain_id: ChainId,
 	/// Light client id on counterparty chain
@@ -151,27 +303,36 @@ pub struct CosmosClient<H> {
 	pub channel_whitelist: Arc<Mutex<HashSet<(ChannelId, PortId)>>>,
 	/// Light Client instance
 	pub light_client: LightClient,
-	/// The key that signs transactions
+	/// The key of the relayer account
 	pub keybase: KeyEntry,
+	/// Signs the transactions of the relayer account, with `keybase` unless replaced, see
+	/// [`crate::signer`]
+	pub signer: Arc<dyn TxSigner>,
 	/// Account prefix
 	pub account_prefix: String,
 	/// Reference to commitment
 	pub commitment_prefix: CommitmentPrefix,
-	/// Fee denom
-	pub fee_denom: String,
-	/// Fee amount
-	pub fee_amount: String,
-	/// Fee amount
-	pub gas_limit: u64,
+	/// Fee and gas settings, shared by the clones of the client
+	pub fees: Arc<Mutex<FeeSettings>>,
 	/// Maximun transaction size
 	pub max_tx_size: usize,
+	/// Template of the memo of the transactions, see [`crate::memo`]
+	pub memo_template: Option<MemoTemplate>,
+	/// Block max gas of the chain, from its consensus params
+	pub max_block_gas: Arc<Mutex<Option<u64>>>,
+	/// Unbonding period of the chain, see [`crate::staking`]
+	pub unbonding_period: Duration,
+	/// Trusting period of the clients of the chain created by the relayer
+	pub trusting_period: Duration,
+	/// Whether the chain is an Interchain Security consumer chain
+	pub ccv_consumer: bool,
 	/// Finality protocol to use, eg Tenderminet
 	pub _phantom: std::marker::PhantomData<H>,
-	/// Mutex used to sequentially send transactions. This is necessary because
-	/// account sequence numbers are not updated until the transaction is processed.
-	pub tx_mutex: Arc<tokio::sync::Mutex<()>>,
-	/// Light-client blocks cache
-	pub light_block_cache: Arc<Cache<TmHeight, LightBlock>>,
+	/// Next sequence of the relayer account, shared by the clients of the paths relayed with
+	/// the same account so that their transactions don't race on it, see [`crate::sequence`]
+	pub sequences: Arc<SequenceManager>,
+	/// Light blocks fetched from the chain, see [`crate::light_store`]
+	pub light_block_store: Arc<LightBlockStore>,
 	/// Relayer data
 	pub common_state: CommonClientState,
 	/// Join handles for spawned tasks
@@ -189,6 +350,34 @@ pub struct CosmosClientConfig {
 	pub grpc_url: Url,
 	/// websocket url for cosmos
 	pub websocket_url: Url,
+	/// Endpoints used in turn when the primary ones keep failing
+	#[serde(default)]
+	pub fallback_endpoints: Vec<Endpoints>,
+	/// Version of the RPC schema of the node (`0.34`, `0.37` or `0.38`), detected at startup if
+	/// not set
+	#[serde(default)]
+	pub comet_version: Option<CometVersion>,
+	/// Version of ibc-go of the chain (`v6` for v6 and older, `v7` or `v8`), detected at startup
+	/// if not set
+	#[serde(default)]
+	pub ibc_go_version: Option<IbcGoVersion>,
+	/// Additional gRPC endpoints the queries fail over to when the current one doesn't respond
+	#[serde(default)]
+	pub grpc_pool: Vec<Url>,
+	/// LCD (REST API) the gRPC queries fall back to when they fail, see [`crate::rest`]
+	#[serde(default)]
+	pub rest_url: Option<Url>,
+	/// Limit of the rate of the requests sent to the RPC endpoint, e.g. to stay under the limits
+	/// of a public provider
+	#[serde(default)]
+	pub rpc_rate_limit: Option<RequestRateLimit>,
+	/// Limit of the rate of the requests sent to each gRPC endpoint
+	#[serde(default)]
+	pub grpc_rate_limit: Option<RequestRateLimit>,
+	/// Height the stream of the IBC events starts from, the events of the blocks produced before
+	/// the relayer started being backfilled from the indexer of the node
+	#[serde(default)]
+	pub backfill_from_height: Option<u64>,
 	/// Cosmos chain Id
 	pub chain_id: String,
 	/// Light client id on counterparty chain
@@ -203,16 +392,70 @@ pub struct CosmosClientConfig {
 	/// Fee amount
 	#[serde(default = "default_fee_amount")]
 	pub fee_amount: String,
+	/// Fee tokens accepted by the chain in addition to `fee_denom`, in order of preference.
+	/// The first token the relayer holds enough of is used to pay for transactions, falling
+	/// back to `fee_denom` if none of them can cover the fee.
+	#[serde(default)]
+	pub alternative_fee_tokens: Vec<FeeToken>,
 	/// Fee amount
 	#[serde(default = "default_gas_limit")]
 	pub gas_limit: u64,
+	/// Account paying the fees of the transactions through a feegrant allowance to the relayer.
+	/// The fee balances are then queried on this account
+	#[serde(default)]
+	pub fee_granter: Option<String>,
+	/// Gas price following the base fee of the fee market of the chain (Osmosis, Ethermint),
+	/// queried before each broadcast, see [`crate::fee_market`]. The fee of `fee_denom` is then
+	/// derived from it and `gas_limit` rather than set by `fee_amount`
+	#[serde(default)]
+	pub dynamic_gas_price: Option<DynamicGasPrice>,
+	/// Factor of the gas used by the simulation of each transaction its gas limit is set to, up to
+	/// the block max gas of the chain (e.g. 1.3). The fees are then those of `fee_amount` and the
+	/// alternative fee tokens at that gas limit rather than at `gas_limit`, which only bounds the
+	/// simulations
+	#[serde(default)]
+	pub gas_adjustment: Option<f64>,
+	/// Account the messages are relayed on behalf of, with authz grants of the message types to
+	/// the relayer account. The messages are then signed in its name and executed with `MsgExec`
+	#[serde(default)]
+	pub authz_granter: Option<String>,
+	/// Template of the memo of the transactions, with the `{version}`, `{path}` and
+	/// `{bundle_id}` placeholders, see [`crate::memo`]. The memo is `ibc` if not set
+	#[serde(default)]
+	pub memo_template: Option<MemoTemplate>,
 	/// Store prefix
 	pub store_prefix: String,
 	/// Maximun transaction size
 	pub max_tx_size: usize,
-	/// All the client states and headers will be wrapped in WASM ones using the WASM code ID.
+	/// How the transactions are broadcast: `sync` (the default) waits for them to pass `CheckTx`,
+	/// `async` doesn't, and `commit` waits for their inclusion. In the `sync` and `async` modes,
+	/// the transactions are tracked until they're included before their messages are considered
+	/// delivered
+	#[serde(default)]
+	pub broadcast_mode: BroadcastMode,
+	/// All the client states and headers will be wrapped in WASM ones using the WASM code ID,
+	/// the hex-encoded hash of the code set by `upload-wasm`
 	#[serde(default)]
 	pub wasm_code_id: Option<String>,
+	/// Trusting period of the clients of the chain created by the relayer, in seconds. It has
+	/// to be shorter than the unbonding period of the chain, see [`crate::staking`]
+	#[serde(default)]
+	pub trusting_period_secs: Option<u64>,
+	/// Share of the unbonding period of the chain the trusting period defaults to, two thirds
+	/// if not set
+	#[serde(default)]
+	pub trusting_period_fraction: Option<f64>,
+	/// Whether the chain is an Interchain Security consumer chain, whose validators are those of
+	/// its provider chain, see [`crate::ccv`]
+	#[serde(default)]
+	pub ccv_consumer: bool,
+	/// Retention limits of the light blocks fetched from the chain, see [`crate::light_store`]
+	#[serde(default)]
+	pub light_store: LightStoreRetention,
+	/// File the light blocks fetched from the chain are saved to and reloaded from at startup,
+	/// see [`crate::light_store`]
+	#[serde(default)]
+	pub light_store_snapshot: Option<PathBuf>,
 	/*
 	Here is a list of dropped configuration parameters from Hermes Config.toml
 	that could be set to default values or removed for the MVP phase:
@@ -222,7 +465,6 @@ pub struct CosmosClientConfig {
 	pub default_gas: Option<u64>,	  			// TODO: Could be set to `0` by default
 	pub max_gas: Option<u64>,                   // TODO: DEFAULT_MAX_GAS: u64 = 400_000
 	pub gas_multiplier: Option<GasMultiplier>,  // TODO: Could be set to `1.1` by default
-	pub fee_granter: Option<String>,            // TODO: DEFAULT_FEE_GRANTER: &str = ""
 	pub max_msg_num: MaxMsgNum,                 // TODO: Default is 30, Could be set usize = 1 for test
 												// TODO: Could be set to const MAX_LEN: usize = 50;
 	pub proof_specs: Option<ProofSpecs>,        // TODO: Could be set to None
@@ -235,8 +477,25 @@ pub struct CosmosClientConfig {
 	*/
 	/// Whitelisted channels
 	pub channel_whitelist: Vec<(ChannelId, PortId)>,
-	/// The key that signs transactions
+	/// The key that signs transactions, unless a `remote_signer` or a `ledger` holds it
+	#[serde(default)]
 	pub mnemonic: String,
+	/// Type of the key: `secp256k1` (the default), or the Ethereum keys of the Ethermint chains,
+	/// `eth_secp256k1` (Evmos, ...) and `injective_eth_secp256k1` (Injective)
+	#[serde(default)]
+	pub key_type: KeyType,
+	/// Multisig account the transactions are sent from, signed by its members out of band, see
+	/// [`crate::multisig`]. The key of the `mnemonic` signs its part if it's one of them
+	#[serde(default)]
+	pub multisig: Option<MultisigConfig>,
+	/// Signing service holding the key of the relayer instead of the `mnemonic`, see
+	/// [`primitives::remote_signer`]
+	#[serde(default)]
+	pub remote_signer: Option<RemoteSignerConfig>,
+	/// Ledger device holding the key of the relayer instead of the `mnemonic`, see
+	/// [`crate::ledger`]
+	#[serde(default)]
+	pub ledger: Option<LedgerConfig>,
 	/// Common client config
 	#[serde(flatten)]
 	pub common: CommonClientConfig,
@@ -251,67 +510,363 @@ where
 {
 	/// Initializes a [`CosmosClient`] given a [`CosmosClientConfig`]
 	pub async fn new(config: CosmosClientConfig) -> Result<Self, Error> {
-		let (rpc_client, rpc_driver) = WebSocketClient::new(config.websocket_url.clone())
-			.await
-			.map_err(|e| Error::RpcError(format!("{:?}", e)))?;
-		let rpc_http_client = HttpClient::new(config.rpc_url.clone())
-			.map_err(|e| Error::RpcError(format!("{:?}", e)))?;
+		let mut endpoint_rotation = EndpointRotation::new(
+			Endpoints {
+				rpc_url: config.rpc_url,
+				grpc_url: config.grpc_url,
+				websocket_url: config.websocket_url,
+			},
+			config.fallback_endpoints,
+		);
+		// the relayer starts on the first endpoint that's reachable
+		let connections = loop {
+			let endpoints = endpoint_rotation.current().clone();
+			match Connections::connect(&endpoints, config.rest_url.is_some()).await {
+				Ok(connections) => break connections,
+				Err(e) => {
+					log::warn!(target: "hyperspace_cosmos", "Failed to connect {} to {}: {e:?}", config.name, endpoints.rpc_url);
+					match endpoint_rotation.rotate() {
+						Some(next) if !endpoint_rotation.is_on_primary() => log::info!(
+							target: "hyperspace_cosmos", "Connecting {} to the fallback endpoint {}", config.name, next.rpc_url
+						),
+						_ => return Err(e),
+					}
+				},
+			}
+		};
+		let Connections { rpc_client, rpc_driver, rpc_http_client, grpc_client, light_client } =
+			connections;
 		let ws_driver_jh = tokio::spawn(rpc_driver.run());
-		let grpc_client = tonic::transport::Endpoint::new(config.grpc_url.to_string())
-			.map_err(|e| Error::RpcError(format!("{:?}", e)))?
-			.connect()
-			.await
-			.map_err(|e| Error::RpcError(format!("{:?}", e)))?;
+		let comet_version = match config.comet_version {
+			Some(version) => version,
+			None => CometVersion::detect(&rpc_http_client).await?,
+		};
+		log::info!(target: "hyperspace_cosmos", "Using the RPC schema of {comet_version:?} for {}", config.name);
+
+		let throttled_requests = ThrottledRequests::default();
+		let rpc_limiter = RequestLimiter::new(
+			&endpoint_rotation.current().rpc_url,
+			config.rpc_rate_limit,
+			throttled_requests.clone(),
+		);
+		let grpc_pool = GrpcPool::new(
+			(endpoint_rotation.current().grpc_url.clone(), grpc_client.clone()),
+			&config.grpc_pool,
+			config.grpc_rate_limit,
+			throttled_requests.clone(),
+		)?;
+		if !config.grpc_pool.is_empty() {
+			grpc_pool.spawn_health_checks(HEALTH_CHECK_INTERVAL);
+		}
+
+		let rest_client = config.rest_url.as_ref().map(RestClient::new);
+
+		let ibc_go_version = match config.ibc_go_version {
+			Some(version) => version,
+			None => IbcGoVersion::detect(&grpc_pool).await,
+		};
+		log::info!(target: "hyperspace_cosmos", "Using the behavior of ibc-go {ibc_go_version:?} for {}", config.name);
+		match ibc_go::query_allowed_clients(&grpc_pool).await {
+			Ok(allowed_clients) if !ibc_go_version.allows_client(&allowed_clients, "08-wasm") =>
+				log::warn!(
+					target: "hyperspace_cosmos",
+					"{} doesn't allow the 08-wasm clients (allowed clients: {allowed_clients:?}), the clients of its counterparties can't be created on it",
+					config.name
+				),
+			Ok(_) => (),
+			Err(e) => log::debug!(target: "hyperspace_cosmos", "{e}"),
+		}
+
+		let unbonding_period = match (config.ccv_consumer, &rest_client) {
+			(true, _) => ccv::query_unbonding_period(&grpc_pool).await?,
+			(false, None) => staking::query_unbonding_period(&grpc_pool).await?,
+			(false, Some(rest_client)) => match staking::query_unbonding_period(&grpc_pool).await {
+				Ok(unbonding_period) => unbonding_period,
+				Err(e) => {
+					log::debug!(target: "hyperspace_cosmos", "{e}, querying the staking params of {} from the LCD", config.name);
+					rest_client.unbonding_period().await?
+				},
+			},
+		};
+		let trusting_period = staking::trusting_period(
+			unbonding_period,
+			config.trusting_period_secs.map(Duration::from_secs),
+			config.trusting_period_fraction.unwrap_or(DEFAULT_TRUSTING_PERIOD_FRACTION),
+		)?;
+		log::info!(
+			target: "hyperspace_cosmos",
+			"Trusting period of the clients of {}: {trusting_period:?} (unbonding period {unbonding_period:?})",
+			config.name
+		);
 
 		let chain_id = ChainId::from(config.chain_id);
-		let light_client =
-			LightClient::init_light_client(config.rpc_url.clone(), Duration::from_secs(10)).await?;
 		let commitment_prefix = CommitmentPrefix::try_from(config.store_prefix.as_bytes().to_vec())
 			.map_err(|e| Error::from(format!("Invalid store prefix {:?}", e)))?;
 
-		let keybase: KeyEntry = KeyEntry::try_from(MnemonicEntry {
-			mnemonic: config.mnemonic,
-			prefix: config.account_prefix.clone(),
-		})
-		.map_err(|e| e.to_string())?;
+		if config.remote_signer.is_some() && config.multisig.is_some() {
+			return Err(Error::from(
+				"The multisig members can't sign with a remote signer".to_string(),
+			))
+		}
+		if config.ledger.is_some() && (config.remote_signer.is_some() || config.multisig.is_some())
+		{
+			return Err(Error::from(
+				"The Ledger can't sign with a remote signer or for a multisig".to_string(),
+			))
+		}
+		if config.ledger.is_some() && config.key_type.is_ethereum() {
+			return Err(Error::from("The Ledger only signs with secp256k1 keys".to_string()))
+		}
+		let (mut keybase, external_signer): (_, Option<Arc<dyn TxSigner>>) =
+			match (config.remote_signer, config.ledger) {
+				(Some(remote_signer), _) => {
+					let signer = RemoteTxSigner::connect(
+						remote_signer,
+						chain_id.to_string(),
+						config.key_type,
+					)
+					.await?;
+					let keybase = KeyEntry::from_public_key(
+						&signer.public_key(),
+						&config.account_prefix,
+						config.key_type,
+					)
+					.map_err(|e| Error::from(format!("Invalid remote public key: {e}")))?;
+					(keybase, Some(Arc::new(signer)))
+				},
+				(None, Some(ledger)) => {
+					let signer = LedgerTxSigner::connect(ledger, &config.account_prefix).await?;
+					let keybase = KeyEntry::from_public_key(
+						&signer.public_key(),
+						&config.account_prefix,
+						config.key_type,
+					)
+					.map_err(|e| Error::from(format!("Invalid Ledger public key: {e}")))?;
+					(keybase, Some(Arc::new(signer)))
+				},
+				(None, None) => {
+					let keybase = KeyEntry::try_from(MnemonicEntry {
+						mnemonic: config.mnemonic,
+						prefix: config.account_prefix.clone(),
+						key_type: config.key_type,
+					})
+					.map_err(|e| e.to_string())?;
+					(keybase, None)
+				},
+			};
+		// the relayer account is then the multisig, see [`crate::multisig`]
+		let signer: Arc<dyn TxSigner> = match (config.multisig, external_signer) {
+			(Some(multisig), _) => {
+				let signer = MultisigSigner::new(multisig, &keybase)?;
+				let (_, data, _) = bech32::decode(signer.address())
+					.map_err(|e| Error::from(format!("Invalid multisig address: {e}")))?;
+				keybase.address = Vec::<u8>::from_base32(&data)
+					.map_err(|e| Error::from(format!("Invalid multisig address: {e}")))?;
+				keybase.account = signer.address().to_string();
+				Arc::new(signer)
+			},
+			(None, Some(signer)) => signer,
+			(None, None) => Arc::new(keybase.clone()),
+		};
+
+		let authz_granter = config
+			.authz_granter
+			.map(|granter| parse_authz_granter(&granter, &config.account_prefix))
+			.transpose()?;
 
 		let rpc_call_delay = Duration::from_millis(1000);
-		Ok(Self {
+		let light_store_size = Arc::new(AtomicUsize::new(0));
+		let light_block_store =
+			Arc::new(LightBlockStore::new(config.light_store, light_store_size.clone()));
+		if let Some(path) = config.light_store_snapshot {
+			match light_block_store.load_snapshot(&path, chain_id.as_str()) {
+				Ok(count) => log::info!(
+					target: "hyperspace_cosmos",
+					"Loaded {count} light blocks of {} from {}", config.name, path.display()
+				),
+				Err(e) =>
+					log::warn!(target: "hyperspace_cosmos", "{e}, starting with an empty light block store"),
+			}
+			light_block_store.spawn_snapshots(path, chain_id.to_string());
+		}
+		let sequences = sequence_manager(&chain_id, &keybase.account);
+		let client = Self {
 			name: config.name,
 			chain_id,
 			rpc_client,
 			rpc_http_client,
+			comet_version,
+			ibc_go_version,
+			broadcast_mode: config.broadcast_mode,
+			authz_granter,
 			grpc_client,
-			rpc_url: config.rpc_url,
-			grpc_url: config.grpc_url,
-			websocket_url: config.websocket_url,
+			grpc_pool,
+			rest_client,
+			rpc_rate_limit: config.rpc_rate_limit,
+			rpc_limiter,
+			backfill_from_height: Arc::new(Mutex::new(config.backfill_from_height)),
+			endpoints: Arc::new(Mutex::new(endpoint_rotation.current().clone())),
+			endpoint_rotation: Arc::new(Mutex::new(endpoint_rotation)),
 			client_id: Arc::new(Mutex::new(config.client_id)),
 			connection_id: Arc::new(Mutex::new(config.connection_id)),
 			channel_whitelist: Arc::new(Mutex::new(config.channel_whitelist.into_iter().collect())),
 			light_client,
 			account_prefix: config.account_prefix,
 			commitment_prefix,
-			fee_denom: config.fee_denom,
-			fee_amount: config.fee_amount,
-			gas_limit: config.gas_limit,
+			fees: Arc::new(Mutex::new(FeeSettings {
+				fee_denom: config.fee_denom,
+				fee_amount: config.fee_amount,
+				alternative_fee_tokens: config.alternative_fee_tokens,
+				gas_limit: config.gas_limit,
+				fee_granter: config.fee_granter,
+				dynamic_gas_price: config.dynamic_gas_price,
+				gas_adjustment: config.gas_adjustment,
+			})),
 			max_tx_size: config.max_tx_size,
+			memo_template: config.memo_template,
+			max_block_gas: Default::default(),
+			unbonding_period,
+			trusting_period,
+			ccv_consumer: config.ccv_consumer,
+			signer,
 			keybase,
 			_phantom: std::marker::PhantomData,
-			tx_mutex: Default::default(),
-			light_block_cache: Arc::new(Cache::new(100000)),
+			sequences,
+			light_block_store,
 			common_state: CommonClientState {
 				skip_optional_client_updates: config.common.skip_optional_client_updates,
+				idle_mode: config.common.idle_mode,
+				target_height_updates: config.common.target_height_updates,
+				skip_delivered_check: config.common.skip_delivered_check,
+				fee_ceiling: config.common.fee_ceiling,
 				maybe_has_undelivered_packets: Default::default(),
 				rpc_call_delay,
 				initial_rpc_call_delay: rpc_call_delay,
 				misbehaviour_client_msg_queue: Arc::new(AsyncMutex::new(vec![])),
 				max_packets_to_process: config.common.max_packets_to_process as usize,
+				max_packets_in_flight: config.common.max_packets_in_flight.map(|max| max as usize),
+				max_value_in_flight: config.common.max_value_in_flight,
+				batch_limits: Arc::new(Mutex::new(config.common.batch_limits)),
+				queried_batch_limits: Default::default(),
+				message_priority: config.common.message_priority,
+				retry_policy: config.common.retry_policy,
+				dry_run: false,
+				min_balance: config.common.min_balance,
+				store: None,
+				client_update_times: Default::default(),
+				submission_limiter: SubmissionLimiter::new(config.common.submission_rate_limit),
+				sweep_interval: config.common.sweep_interval_secs.map(Duration::from_secs),
+				timeout_scan_interval: config
+					.common
+					.timeout_scan_interval_secs
+					.map(Duration::from_secs),
+				finality_confirmations: config.common.finality_confirmations,
+				balance_guard: BalanceGuard::new(config.common.pause_below_balance),
+				leadership: Default::default(),
+				proof_permits: CommonClientState::proof_permits(config.common.proof_concurrency),
+				fee_payee: config.common.fee_payee,
+				earned_fees: Default::default(),
+				dead_letters: Default::default(),
+				submitted_packets: Default::default(),
+				light_store_size,
+				throttled_requests,
+				proof_cache: Default::default(),
+				token_displays: Default::default(),
 				skip_tokens_list: config
 					.skip_tokens_list
 					.unwrap_or_else(|| vec!["uosmo".to_string()]),
 			},
 			join_handles: Arc::new(TokioMutex::new(vec![ws_driver_jh])),
-		})
+		};
+		client.spawn_primary_check();
+		Ok(client)
+	}
+
+	/// Probes the primary endpoints every [`PRIMARY_CHECK_INTERVAL`] while a fallback is used, and
+	/// returns to them once they're healthy. The clients are reconnected by closing the current
+	/// websocket connection, as on a reload.
+	fn spawn_primary_check(&self) {
+		let name = self.name.clone();
+		let rotation = Arc::downgrade(&self.endpoint_rotation);
+		let endpoints = self.endpoints.clone();
+		let join_handles = self.join_handles.clone();
+		// the gRPC endpoints may be missing when the queries fall back to the LCD
+		let check_grpc = self.rest_client.is_none();
+		tokio::spawn(async move {
+			loop {
+				sleep(PRIMARY_CHECK_INTERVAL).await;
+				// the task stops with the last clone of the client
+				let Some(rotation) = rotation.upgrade() else { return };
+				let primary = {
+					let rotation = rotation.lock().unwrap();
+					if rotation.is_on_primary() {
+						continue
+					}
+					rotation.primary().clone()
+				};
+				if !is_healthy(&primary, check_grpc).await {
+					log::debug!(target: "hyperspace_cosmos", "Primary endpoint {} of {name} is still unhealthy", primary.rpc_url);
+					continue
+				}
+				log::info!(target: "hyperspace_cosmos", "Primary endpoint {} of {name} is healthy again, reconnecting", primary.rpc_url);
+				*endpoints.lock().unwrap() = rotation.lock().unwrap().return_to_primary();
+				for handle in join_handles.lock().await.drain(..) {
+					handle.abort();
+				}
+			}
+		});
+	}
+
+	pub fn fees(&self) -> FeeSettings {
+		self.fees.lock().unwrap().clone()
+	}
+
+	pub fn grpc_url(&self) -> Url {
+		self.endpoints.lock().unwrap().grpc_url.clone()
+	}
+
+	/// Applies the settings of the config that can be changed while relaying: the batch limits,
+	/// the fee and gas settings and the endpoints. The clients are connected to new endpoints by
+	/// closing the current websocket connection, so that the relayer loop reconnects.
+	pub async fn reload(&self, config: CosmosClientConfig) -> Result<(), Error> {
+		if ChainId::from(config.chain_id.clone()) != self.chain_id {
+			return Err(Error::from(format!(
+				"Chain id of {} can't be changed from {} to {} without restarting",
+				self.name, self.chain_id, config.chain_id
+			)))
+		}
+		self.common_state.set_batch_limits(config.common.batch_limits);
+		*self.fees.lock().unwrap() = FeeSettings {
+			fee_denom: config.fee_denom,
+			fee_amount: config.fee_amount,
+			alternative_fee_tokens: config.alternative_fee_tokens,
+			gas_limit: config.gas_limit,
+			fee_granter: config.fee_granter,
+			dynamic_gas_price: config.dynamic_gas_price,
+			gas_adjustment: config.gas_adjustment,
+		};
+		let endpoints = Endpoints {
+			rpc_url: config.rpc_url,
+			grpc_url: config.grpc_url,
+			websocket_url: config.websocket_url,
+		};
+		self.endpoint_rotation
+			.lock()
+			.unwrap()
+			.set_endpoints(endpoints.clone(), config.fallback_endpoints);
+		let endpoints_changed = {
+			let mut current = self.endpoints.lock().unwrap();
+			let changed = *current != endpoints;
+			*current = endpoints;
+			changed
+		};
+		if endpoints_changed {
+			log::info!(target: "hyperspace_cosmos", "Endpoints of {} changed, reconnecting", self.name);
+			for handle in self.join_handles.lock().await.drain(..) {
+				handle.abort();
+			}
+		}
+		Ok(())
 	}
 
 	pub fn client_id(&self) -> ClientId {
@@ -354,30 +909,107 @@ where
 		}
 	}
 
+	/// Signs, simulates and broadcasts the messages, retrying with the [`RetryPolicy`] of the
+	/// client, and waits for the transaction to be included in a block. The confirmation isn't
+	/// retried, since the transaction may have been included anyway. The next transaction of the
+	/// account may be broadcast while this one is awaited.
+	///
+	/// [`RetryPolicy`]: primitives::retry::RetryPolicy
 	pub async fn submit_call(&self, messages: Vec<Any>) -> Result<Hash, Error> {
-		let _lock = self.tx_mutex.lock().await;
-		let account_info = self.query_account().await?;
+		let messages = &self.authz_wrap(messages);
+		let hash = self
+			.common_state
+			.retry_policy
+			.retry("submit_call", || async move {
+				// The next sequence is held until the transaction is broadcast, see
+				// [`crate::sequence`]
+				let mut sequence = self.sequences.lock().await;
+				let account_info = match sequence.account() {
+					Some(account_info) => account_info.clone(),
+					None => {
+						let account_info = self.query_account().await?;
+						sequence.set(account_info.clone());
+						account_info
+					},
+				};
+				let result = self.broadcast_messages(&account_info, messages).await;
+				match &result {
+					Ok(_) => sequence.advance(),
+					Err(e) => sequence.resync(e),
+				}
+				result
+			})
+			.await?;
+		log::debug!(target: "hyperspace_cosmos", "🤝 Transaction sent with hash: {:?}", hash);
 
-		// Sign transaction
-		let (tx, _, tx_bytes) = sign_tx(
-			self.keybase.clone(),
-			self.chain_id.clone(),
-			&account_info,
-			messages,
-			self.get_fee(),
-		)?;
+		// wait for confirmation
+		let response = confirm_tx(&self.rpc_client, self.comet_version, hash).await?;
+		self.record_earned_fees(&response.tx_result.events);
+		Ok(response.hash)
+	}
+
+	/// Simulates, signs and broadcasts a transaction of the messages with the given account
+	/// sequence.
+	async fn broadcast_messages(
+		&self,
+		account_info: &BaseAccount,
+		messages: &[Any],
+	) -> Result<Hash, Error> {
+		let fee = self.select_fee().await?;
+		let memo = self.tx_memo(messages);
 
-		// Simulate transaction
-		let res = simulate_tx(self.grpc_url.clone(), tx, tx_bytes.clone()).await?;
+		// Simulate transaction, before it's signed
+		let (tx, _, tx_bytes) =
+			unsigned_tx(&*self.signer, account_info, messages.to_vec(), fee.clone(), memo.clone())?;
+		let res = simulate_tx(&self.grpc_pool, tx, tx_bytes).await?;
 		res.result
 			.map(|r| log::debug!(target: "hyperspace_cosmos", "Simulated transaction: events: {:?}\nlogs: {}", r.events, r.log));
+		let adjusted_gas_limit = match res.gas_info {
+			Some(gas_info) => self.adjusted_gas_limit(gas_info.gas_used).await,
+			None => None,
+		};
+		let fee = match adjusted_gas_limit {
+			Some(gas_limit) => {
+				log::debug!(target: "hyperspace_cosmos", "Setting the gas limit of the transaction to {gas_limit}");
+				self.select_fee_for_gas(gas_limit).await?
+			},
+			None => fee,
+		};
+
+		// Sign transaction
+		let (_, _, tx_bytes) = sign_tx(
+			&*self.signer,
+			self.chain_id.clone(),
+			account_info,
+			messages.to_vec(),
+			fee,
+			memo,
+		)
+		.await?;
 
 		// Broadcast transaction
-		let hash = broadcast_tx(&self.rpc_client, tx_bytes).await?;
-		log::debug!(target: "hyperspace_cosmos", "🤝 Transaction sent with hash: {:?}", hash);
+		self.rpc_limiter.acquire(1).await;
+		broadcast_tx(&self.rpc_client, self.comet_version, self.broadcast_mode, tx_bytes).await
+	}
 
-		// wait for confirmation
-		confirm_tx(&self.rpc_client, hash).await
+	/// Records the ICS-29 fees distributed to the relayer, or to its payee, by the events of a
+	/// submitted transaction.
+	fn record_earned_fees(&self, events: &[AbciEvent]) {
+		let mut receivers = vec![self.keybase.account.clone()];
+		receivers.extend(self.authz_granter.as_ref().map(|granter| granter.to_string()));
+		receivers.extend(self.common_state.fee_payee.clone());
+		for event in events.iter().filter(|event| event.kind == DISTRIBUTE_FEE_EVENT) {
+			let attribute = |key: &str| {
+				event
+					.attributes
+					.iter()
+					.find(|attribute| attribute.key == key)
+					.map(|attribute| attribute.value.as_str())
+			};
+			if let (Some(receiver), Some(fee)) = (attribute("receiver"), attribute("fee")) {
+				self.common_state.earned_fees.record(receiver, fee, &receivers);
+			}
+		}
 	}
 
 	pub async fn fetch_light_block_with_cache(
@@ -387,6 +1019,8 @@ where
 	) -> Result<LightBlock, Error> {
 		let fut = async move {
 			sleep(sleep_duration).await;
+			// the signed header and the two validator sets of the block
+			self.rpc_limiter.acquire(3).await;
 			self.light_client.io.fetch_light_block(AtHeight::At(height)).map_err(|e| {
 				Error::from(format!(
 					"Failed to fetch light block for chain {:?} with error {:?}",
@@ -394,7 +1028,7 @@ where
 				))
 			})
 		};
-		self.light_block_cache.get_or_insert_async(&height, fut).await
+		self.light_block_store.get_or_insert_async(&height, fut).await
 	}
 
 	pub async fn msg_update_client_header(
@@ -455,19 +1089,203 @@ where
 			}
 		}
 		xs.sort_by_key(|(h, _)| h.signed_header.header.height.value());
+		if self.ccv_consumer {
+			ccv::chain_trusted_heights(&mut xs);
+		}
 		Ok(xs)
 	}
 
-	/// Uses the GRPC client to retrieve the account sequence
-	pub async fn query_account(&self) -> Result<BaseAccount, Error> {
-		let mut client = QueryClient::connect(self.grpc_url.clone().to_string())
+	/// Picks the fee to pay transactions with. The native fee denom is preferred, followed by the
+	/// alternative fee tokens in the configured order. The first one the relayer account holds
+	/// enough of is used. If none of them can cover the fee, the native fee denom is used anyway,
+	/// so that the chain reports the insufficient funds error.
+	pub async fn select_fee(&self) -> Result<Fee, Error> {
+		self.select_fee_for_gas(self.fees().gas_limit).await
+	}
+
+	/// Picks the fee of a transaction of the given gas limit, as [`Self::select_fee`]. The fee
+	/// amounts of the config, which pay for `gas_limit`, are scaled to it.
+	pub async fn select_fee_for_gas(&self, gas_limit: u64) -> Result<Fee, Error> {
+		let fees = self.fees();
+		let fee_amount = self.fee_amount(&fees, gas_limit).await;
+		if fees.alternative_fee_tokens.is_empty() {
+			return Ok(self.get_fee_with(fees.fee_denom, fee_amount, gas_limit))
+		}
+
+		let alternative_fee_tokens = fees.alternative_fee_tokens.iter().map(|token| FeeToken {
+			denom: token.denom.clone(),
+			amount: scale_fee_amount(&token.amount, gas_limit, fees.gas_limit),
+		});
+		let candidates =
+			std::iter::once(FeeToken { denom: fees.fee_denom.clone(), amount: fee_amount.clone() })
+				.chain(alternative_fee_tokens)
+				.collect();
+
+		// the balances of all the fee tokens are read with a single query
+		match self.query_fee_balances().await {
+			Ok(balances) => match select_fee_token(candidates, &balances)? {
+				Some(token) => return Ok(self.get_fee_with(token.denom, token.amount, gas_limit)),
+				None => {
+					log::warn!(target: "hyperspace_cosmos", "No configured fee token can cover the fee on {}, using {}", self.name, fees.fee_denom);
+				},
+			},
+			Err(e) => {
+				log::warn!(target: "hyperspace_cosmos", "Failed to query the fee balances on {}, using {}: {:?}", self.name, fees.fee_denom, e);
+			},
+		}
+		Ok(self.get_fee_with(fees.fee_denom, fee_amount, gas_limit))
+	}
+
+	/// Gas limit of a transaction whose simulation used `gas_used`: the gas used times the gas
+	/// adjustment of the config, up to the block max gas of the chain. `None` without a gas
+	/// adjustment.
+	async fn adjusted_gas_limit(&self, gas_used: u64) -> Option<u64> {
+		let adjustment = self.fees().gas_adjustment?;
+		let gas_limit = (gas_used as f64 * adjustment).ceil() as u64;
+		if self.max_block_gas.lock().unwrap().is_none() {
+			// cached by the query, the block max gas stays unknown if it fails
+			if let Err(e) = self.query_batch_limits().await {
+				log::warn!(target: "hyperspace_cosmos", "Failed to query the block max gas of {}: {:?}", self.name, e);
+			}
+		}
+		let max_block_gas = *self.max_block_gas.lock().unwrap();
+		Some(max_block_gas.map_or(gas_limit, |max_block_gas| gas_limit.min(max_block_gas)))
+	}
+
+	/// Fee amount of `fee_denom` for the given gas limit: the configured one, or the one at the
+	/// current base fee of the chain with a [`DynamicGasPrice`]
+	async fn fee_amount(&self, fees: &FeeSettings, gas_limit: u64) -> String {
+		let configured = scale_fee_amount(&fees.fee_amount, gas_limit, fees.gas_limit);
+		let Some(dynamic) = &fees.dynamic_gas_price else { return configured };
+		match dynamic.fee_market.query_base_fee(&self.grpc_pool).await {
+			Ok(base_fee) => {
+				let amount = dynamic.fee_amount(base_fee, gas_limit);
+				log::debug!(target: "hyperspace_cosmos", "Base fee of {} is {base_fee}{}, paying {amount}{}", self.name, fees.fee_denom, fees.fee_denom);
+				amount
+			},
+			Err(e) => {
+				log::warn!(target: "hyperspace_cosmos", "Failed to query the base fee of {}, paying {configured}{}: {:?}", self.name, fees.fee_denom, e);
+				configured
+			},
+		}
+	}
+
+	/// Uses the GRPC client to retrieve the balance of the given denom of the account paying the
+	/// fees, i.e. the fee granter if any, or the relayer
+	pub async fn query_fee_balance(&self, denom: &str) -> Result<u128, Error> {
+		let payer = self.fees().fee_granter.unwrap_or_else(|| self.keybase.account.to_string());
+		let request = QueryBalanceRequest { address: payer, denom: denom.to_string() };
+
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move { BankQueryClient::new(channel).balance(request).await }
+			})
+			.await
+			.map_err(|e| Error::from(format!("{:?}", e)))?
+			.into_inner();
+
+		match response.balance {
+			Some(coin) => coin.amount.parse::<u128>().map_err(|e| {
+				Error::from(format!("Failed to parse balance {}: {:?}", coin.amount, e))
+			}),
+			None => Ok(0),
+		}
+	}
+
+	/// Uses the GRPC client to retrieve the balances of all the denoms of the account paying the
+	/// fees, by denom
+	pub async fn query_fee_balances(&self) -> Result<HashMap<String, u128>, Error> {
+		let payer = self.fees().fee_granter.unwrap_or_else(|| self.keybase.account.to_string());
+		let request = QueryAllBalancesRequest {
+			address: payer,
+			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
+		};
+
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move { BankQueryClient::new(channel).all_balances(request).await }
+			})
+			.await
+			.map_err(|e| Error::from(format!("{:?}", e)))?
+			.into_inner();
+
+		response
+			.balances
+			.into_iter()
+			.map(|coin| {
+				let amount = coin.amount.parse::<u128>().map_err(|e| {
+					Error::from(format!("Failed to parse balance {}: {:?}", coin.amount, e))
+				})?;
+				Ok((coin.denom, amount))
+			})
+			.collect()
+	}
+
+	/// Resolves the display metadata of a denom: its path if it's an `ibc/` hash, and the symbol
+	/// and decimals of its display unit from the denom metadata of the bank module, if registered.
+	pub async fn query_denom_display(&self, denom: &str) -> Result<TokenDisplay, Error> {
+		// the tokens received over IBC are held as the `ibc/` hash of their path
+		let is_path = denom.split('/').nth(1).map_or(false, |part| part.starts_with("channel-"));
+		let (denom, path) = match denom.strip_prefix("ibc/") {
+			Some(hash) => {
+				let request = QueryDenomTraceRequest { hash: hash.to_string() };
+				let trace = self
+					.grpc_pool
+					.query(|channel| {
+						let request = request.clone();
+						async move { TransferQueryClient::new(channel).denom_trace(request).await }
+					})
+					.await
+					.map_err(|e| Error::from(format!("{:?}", e)))?
+					.into_inner()
+					.denom_trace
+					.ok_or_else(|| Error::from(format!("No denom trace for {denom}")))?;
+				(denom.to_string(), Some(format!("{}/{}", trace.path, trace.base_denom)))
+			},
+			None if is_path => {
+				let hash = hex::encode_upper(Sha256::digest(denom.as_bytes()));
+				(format!("ibc/{hash}"), Some(denom.to_string()))
+			},
+			None => (denom.to_string(), None),
+		};
+
+		// the chains return an error for the denoms without metadata
+		let request = QueryDenomMetadataRequest { denom: denom.clone() };
+		let metadata = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move { BankQueryClient::new(channel).denom_metadata(request).await }
+			})
 			.await
-			.map_err(|e| Error::from(format!("GRPC client error: {:?}", e)))?;
+			.ok()
+			.and_then(|response| response.into_inner().metadata);
+		let display = metadata.and_then(|metadata| {
+			let unit = metadata.denom_units.iter().find(|unit| unit.denom == metadata.display)?;
+			let symbol = match metadata.symbol.is_empty() {
+				true => unit.denom.to_uppercase(),
+				false => metadata.symbol.clone(),
+			};
+			Some(TokenDisplay { symbol, decimals: unit.exponent.min(u8::MAX as u32) as u8 })
+		});
+		Ok(display.unwrap_or_else(|| TokenDisplay::raw(path.as_deref().unwrap_or(&denom))))
+	}
 
-		let request =
-			tonic::Request::new(QueryAccountRequest { address: self.keybase.account.to_string() });
+	/// Uses the GRPC client to retrieve the account sequence
+	pub async fn query_account(&self) -> Result<BaseAccount, Error> {
+		let request = QueryAccountRequest { address: self.keybase.account.to_string() };
 
-		let response = client.account(request).await;
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move { QueryClient::new(channel).account(request).await }
+			})
+			.await;
 
 		// Querying for an account might fail, i.e. if the account doesn't actually exist
 		let resp_account =
@@ -476,17 +1294,68 @@ where
 				None => return Err(Error::from(format!("Account not found"))),
 			};
 
+		// the accounts of the Ethermint chains embed their base account
+		if ETH_ACCOUNT_TYPE_URLS.contains(&resp_account.type_url.as_str()) {
+			return EthAccount::decode(resp_account.value.as_slice())
+				.map_err(|e| Error::from(format!("Failed to decode account {}", e)))?
+				.base_account
+				.ok_or_else(|| Error::from(format!("Account has no base account")))
+		}
 		Ok(BaseAccount::decode(resp_account.value.as_slice())
 			.map_err(|e| Error::from(format!("Failed to decode account {}", e)))?)
 	}
 
+	/// Wraps the messages in `MsgExec`s of the relayer if it relays on behalf of an authz granter,
+	/// once those the chain no longer accepts are converted, see [`crate::ibc_go`]
+	pub fn authz_wrap(&self, messages: Vec<Any>) -> Vec<Any> {
+		let messages = self.ibc_go_version.convert_messages(messages);
+		match &self.authz_granter {
+			Some(_) => authz::wrap_in_exec(&self.keybase.account, messages),
+			None => messages,
+		}
+	}
+
+	/// Queries the results of the block at the given height, in the schema of Tendermint 0.34
+	pub async fn block_results(
+		&self,
+		height: TmHeight,
+	) -> Result<block_results::Response, tendermint_rpc::Error> {
+		let request = block_results::Request::new(height);
+		self.rpc_limiter.acquire(1).await;
+		compat::perform(&self.rpc_http_client, self.comet_version, request).await
+	}
+
+	/// Searches the transactions matching the query, in the schema of Tendermint 0.34
+	pub async fn tx_search(
+		&self,
+		query: Query,
+		prove: bool,
+		page: u32,
+		per_page: u8,
+		order: Order,
+	) -> Result<tx_search::Response, tendermint_rpc::Error> {
+		let request = tx_search::Request::new(query, prove, page, per_page, order);
+		self.rpc_limiter.acquire(1).await;
+		compat::perform(&self.rpc_http_client, self.comet_version, request).await
+	}
+
 	pub async fn query_path(
 		&self,
 		data: Vec<u8>,
 		height_query: Height,
 		prove: bool,
 	) -> Result<(AbciQuery, Vec<u8>), Error> {
-		let path = IBC_QUERY_PATH;
+		self.query_store_path(IBC_QUERY_PATH, data, height_query, prove).await
+	}
+
+	/// Queries a key of the given store, e.g. the upgrade store, see [`Self::query_path`]
+	pub async fn query_store_path(
+		&self,
+		path: &str,
+		data: Vec<u8>,
+		height_query: Height,
+		prove: bool,
+	) -> Result<(AbciQuery, Vec<u8>), Error> {
 		let height = TmHeight::try_from(height_query.revision_height)
 			.map_err(|e| Error::from(format!("Invalid height {}", e)))?;
 
@@ -496,6 +1365,7 @@ where
 		};
 
 		// Use the Tendermint-rs RPC client to do the query.
+		self.rpc_limiter.acquire(1).await;
 		let response = self
 			.rpc_http_client
 			.abci_query(Some(path.to_owned()), data.clone(), height, prove)
@@ -539,10 +1409,55 @@ fn is_validators_equal(set_a: &ValidatorSet, set_b: &ValidatorSet) -> bool {
 	set_a.hash() == set_b.hash()
 }
 
+/// Scales a fee amount paying for `from_gas` to one paying for `to_gas`, rounding up. Invalid
+/// amounts are kept so that they're reported as such.
+fn scale_fee_amount(amount: &str, to_gas: u64, from_gas: u64) -> String {
+	match amount.parse::<u128>() {
+		Ok(amount) if from_gas > 0 && to_gas != from_gas => {
+			let scaled = amount.saturating_mul(to_gas as u128);
+			(scaled / from_gas as u128 + u128::from(scaled % from_gas as u128 != 0)).to_string()
+		},
+		_ => amount.to_string(),
+	}
+}
+
+/// The first of the fee tokens, in the order of preference, whose balance covers its amount.
+fn select_fee_token(
+	candidates: Vec<FeeToken>,
+	balances: &HashMap<String, u128>,
+) -> Result<Option<FeeToken>, Error> {
+	for token in candidates {
+		let required = token.amount.parse::<u128>().map_err(|e| {
+			Error::from(format!("Invalid fee amount {} for {}: {:?}", token.amount, token.denom, e))
+		})?;
+		let balance = balances.get(&token.denom).copied().unwrap_or_default();
+		if balance >= required {
+			return Ok(Some(token))
+		}
+		log::debug!(target: "hyperspace_cosmos", "Insufficient {} balance to pay the fees: {balance} < {required}", token.denom);
+	}
+	Ok(None)
+}
+
+/// Parses the address of the authz granter, which must be an account of the chain.
+fn parse_authz_granter(granter: &str, account_prefix: &str) -> Result<Signer, Error> {
+	let (prefix, ..) = bech32::decode(granter)
+		.map_err(|e| Error::from(format!("Invalid authz granter {granter}: {e}")))?;
+	if prefix != account_prefix {
+		return Err(Error::from(format!(
+			"Invalid authz granter {granter}: expected the {account_prefix} prefix"
+		)))
+	}
+	granter
+		.parse()
+		.map_err(|e| Error::from(format!("Invalid authz granter {granter}: {e}")))
+}
+
 #[cfg(test)]
 pub mod tests {
-	use super::MnemonicEntry;
+	use super::{parse_authz_granter, scale_fee_amount, select_fee_token, FeeToken, MnemonicEntry};
 	use crate::key_provider::KeyEntry;
+	use std::collections::HashMap;
 
 	struct TestVector {
 		mnemonic: &'static str,
@@ -583,9 +1498,10 @@ pub mod tests {
 			match KeyEntry::try_from(MnemonicEntry {
 				mnemonic: vector.mnemonic.to_string(),
 				prefix: "cosmos".to_string(),
+				key_type: Default::default(),
 			}) {
 				Ok(key_entry) => {
-					assert_eq!(key_entry.private_key.to_bytes(), vector.private_key);
+					assert_eq!(key_entry.private_key.unwrap().to_bytes(), vector.private_key);
 					assert_eq!(key_entry.public_key.to_bytes(), vector.public_key);
 					assert_eq!(key_entry.account, vector.account);
 				},
@@ -593,4 +1509,67 @@ pub mod tests {
 			}
 		}
 	}
+
+	#[test]
+	fn test_scale_fee_amount() {
+		assert_eq!(scale_fee_amount("4000", 200_000, 4_000_000), "200");
+		assert_eq!(scale_fee_amount("4000", 150_001, 4_000_000), "151");
+		assert_eq!(scale_fee_amount("4000", 4_000_000, 4_000_000), "4000");
+		assert_eq!(scale_fee_amount("invalid", 200_000, 4_000_000), "invalid");
+	}
+
+	fn fee_tokens(tokens: &[(&str, &str)]) -> Vec<FeeToken> {
+		tokens
+			.iter()
+			.map(|(denom, amount)| FeeToken {
+				denom: denom.to_string(),
+				amount: amount.to_string(),
+			})
+			.collect()
+	}
+
+	fn selected(tokens: &[(&str, &str)], balances: &[(&str, u128)]) -> Option<String> {
+		let balances =
+			balances.iter().map(|(denom, amount)| (denom.to_string(), *amount)).collect();
+		select_fee_token(fee_tokens(tokens), &balances)
+			.unwrap()
+			.map(|token| token.denom)
+	}
+
+	#[test]
+	fn fee_tokens_are_selected_in_the_order_of_preference() {
+		let tokens = [("uatom", "100"), ("ibc/usdc", "10"), ("ibc/osmo", "50")];
+		let balances = [("uatom", 100), ("ibc/usdc", 10), ("ibc/osmo", 50)];
+		assert_eq!(selected(&tokens, &balances), Some("uatom".to_string()));
+		// the first alternative token that can pay, rather than the largest balance
+		let balances = [("uatom", 99), ("ibc/usdc", 10), ("ibc/osmo", 1_000)];
+		assert_eq!(selected(&tokens, &balances), Some("ibc/usdc".to_string()));
+	}
+
+	#[test]
+	fn fee_tokens_without_enough_balance_are_skipped() {
+		let tokens = [("uatom", "100"), ("ibc/usdc", "10"), ("ibc/osmo", "50")];
+		let balances = [("uatom", 1), ("ibc/osmo", 50)];
+		assert_eq!(selected(&tokens, &balances), Some("ibc/osmo".to_string()));
+		// none of the tokens can pay, the fee denom is then used by the caller
+		assert_eq!(selected(&tokens, &[("uatom", 1)]), None);
+		assert_eq!(selected(&tokens, &[]), None);
+	}
+
+	#[test]
+	fn invalid_fee_amounts_are_refused() {
+		let balances = HashMap::from([("uatom".to_string(), 100)]);
+		assert!(select_fee_token(fee_tokens(&[("uatom", "1.5")]), &balances).is_err());
+	}
+
+	#[test]
+	fn authz_granters_must_be_accounts_of_the_chain() {
+		let granter = "cosmos15hf3dgggyt4azpd693ax7fdfve8d5m6ct72z9p";
+		assert_eq!(parse_authz_granter(granter, "cosmos").unwrap().as_ref(), granter);
+		assert!(parse_authz_granter(granter, "osmo").is_err());
+		assert!(parse_authz_granter("", "cosmos").is_err());
+		assert!(
+			parse_authz_granter("cosmos15hf3dgggyt4azpd693ax7fdfve8d5m6ct72z9q", "cosmos").is_err()
+		);
+	}
 }
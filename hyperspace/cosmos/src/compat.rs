@@ -0,0 +1,202 @@
+//! Compatibility with the RPC schemas of CometBFT 0.37 and 0.38.
+//!
+//! The RPC types of `tendermint-rpc` follow the schema of Tendermint 0.34, which CometBFT changed
+//! in a way `tendermint-rpc` can't parse:
+//! - 0.37 no longer base64-encodes the keys and values of the event attributes
+//! - 0.38 replaces the events of `BeginBlock` and `EndBlock` in the block results with the events
+//!   of `FinalizeBlock`, and its websocket `NewBlock` events carry these instead. The results of
+//!   the transactions are no longer named after `DeliverTx` either
+//!
+//! The version of the node is detected at startup, see [`CometVersion::detect`]. On the newer
+//! versions, the block results and the transaction searches are requested as raw JSON and
+//! normalized to the schema of 0.34 before they're parsed, see [`perform`]. The websocket events
+//! are parsed with the same types, so the finality notifications and the IBC events are then
+//! polled from the RPC endpoint rather than subscribed to.
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt::Debug, time::Duration};
+use tendermint::block::Height as TmHeight;
+use tendermint_rpc::{Client, HttpClient, Method, Request, Response, SimpleRequest};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Version of the RPC schema of the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CometVersion {
+	/// Tendermint 0.34 and CometBFT 0.34
+	#[default]
+	#[serde(rename = "0.34")]
+	V0_34,
+	/// CometBFT 0.37
+	#[serde(rename = "0.37")]
+	V0_37,
+	/// CometBFT 0.38
+	#[serde(rename = "0.38")]
+	V0_38,
+}
+
+impl CometVersion {
+	/// Parses the version reported by the node, e.g. `0.37.2` or `v0.38.0-rc3`.
+	pub fn from_node_version(version: &str) -> Option<Self> {
+		let mut parts = version.trim_start_matches('v').split('.');
+		let (major, minor) = (parts.next()?, parts.next()?);
+		match (major, minor) {
+			("0", "34") => Some(Self::V0_34),
+			("0", "37") => Some(Self::V0_37),
+			("0", "38") => Some(Self::V0_38),
+			_ => None,
+		}
+	}
+
+	/// Queries the version of the node. The unknown versions are handled as the closest one the
+	/// relayer supports.
+	pub async fn detect(client: &impl Client) -> Result<Self, Error> {
+		let status = client
+			.status()
+			.await
+			.map_err(|e| Error::RpcError(format!("failed to query the node status {e:?}")))?;
+		let version = status.node_info.version.to_string();
+		Ok(Self::from_node_version(&version).unwrap_or_else(|| {
+			let fallback = if version.starts_with("0.3") { Self::V0_34 } else { Self::V0_38 };
+			log::warn!(
+				target: "hyperspace_cosmos",
+				"Unknown node version {version}, using the RPC schema of {fallback:?}"
+			);
+			fallback
+		}))
+	}
+
+	/// Returns `true` for the schema of Tendermint 0.34, which `tendermint-rpc` parses as is.
+	pub fn is_legacy(&self) -> bool {
+		*self == Self::V0_34
+	}
+}
+
+/// A request whose response is kept as raw JSON.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Raw<R>(R);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+struct RawResponse(Value);
+
+impl Response for RawResponse {}
+
+impl<R: Request> Request for Raw<R> {
+	type Response = RawResponse;
+
+	fn method(&self) -> Method {
+		self.0.method()
+	}
+}
+
+impl<R: SimpleRequest> SimpleRequest for Raw<R> {}
+
+/// Performs a request on a node of the given version, normalizing its response to the schema
+/// of Tendermint 0.34.
+pub async fn perform<C, R>(
+	client: &C,
+	version: CometVersion,
+	request: R,
+) -> Result<R::Response, tendermint_rpc::Error>
+where
+	C: Client + Sync,
+	R: SimpleRequest,
+{
+	if version.is_legacy() {
+		return client.perform(request).await
+	}
+	let RawResponse(mut value) = client.perform(Raw(request)).await?;
+	normalize(&mut value, version);
+	serde_json::from_value(value).map_err(tendermint_rpc::Error::serde)
+}
+
+/// Rewrites a response of a node of the given version in the schema of Tendermint 0.34.
+pub fn normalize(value: &mut Value, version: CometVersion) {
+	if version.is_legacy() {
+		return
+	}
+	if let Value::Object(object) = value {
+		if version == CometVersion::V0_38 {
+			// the relayer reads the events of FinalizeBlock as the events of EndBlock
+			if let Some(events) = object.remove("finalize_block_events") {
+				object.insert("end_block_events".to_string(), events);
+			}
+			// and the result of a broadcast commit as its DeliverTx
+			if object.contains_key("check_tx") {
+				if let Some(result) = object.remove("tx_result") {
+					object.insert("deliver_tx".to_string(), result);
+				}
+			}
+		}
+		// the schemas of the validator and consensus params updates changed, and the relayer
+		// doesn't use them
+		if object.contains_key("validator_updates") {
+			object.insert("validator_updates".to_string(), Value::Array(vec![]));
+		}
+		if object.contains_key("consensus_param_updates") {
+			object.insert("consensus_param_updates".to_string(), Value::Null);
+		}
+	}
+	encode_attributes(value);
+}
+
+/// Base64-encodes the keys and values of the attributes of the events in `value`.
+fn encode_attributes(value: &mut Value) {
+	match value {
+		Value::Object(object) => {
+			let is_event = object.get("type").map_or(false, Value::is_string);
+			match object.get_mut("attributes") {
+				Some(Value::Array(attributes)) if is_event =>
+					for attribute in attributes {
+						for field in ["key", "value"] {
+							if let Some(entry) = attribute.get_mut(field) {
+								let plain = entry.as_str().unwrap_or_default();
+								*entry = Value::String(base64::encode(plain));
+							}
+						}
+					},
+				_ => object.values_mut().for_each(encode_attributes),
+			}
+		},
+		Value::Array(values) => values.iter_mut().for_each(encode_attributes),
+		_ => (),
+	}
+}
+
+/// Polls the latest height of the node every `interval`, and yields the first and the last
+/// height of the blocks produced since the previous item, once there are at least `min_blocks`
+/// of them. The first item starts at the latest height at the time of the first poll.
+pub fn poll_new_blocks(
+	client: HttpClient,
+	interval: Duration,
+	min_blocks: u64,
+) -> ReceiverStream<(TmHeight, TmHeight)> {
+	let (tx, rx) = mpsc::channel(1);
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(interval);
+		let mut next = None;
+		loop {
+			interval.tick().await;
+			let latest = match client.status().await {
+				Ok(status) => status.sync_info.latest_block_height,
+				Err(e) => {
+					log::warn!(target: "hyperspace_cosmos", "Failed to poll the latest height: {e:?}");
+					continue
+				},
+			};
+			let from: TmHeight = *next.get_or_insert(latest);
+			if latest.value() + 1 < from.value() + min_blocks {
+				continue
+			}
+			if tx.send((from, latest)).await.is_err() {
+				break
+			}
+			next = Some(latest.increment());
+		}
+	});
+	ReceiverStream::new(rx)
+}
@@ -1,4 +1,3 @@
-use super::key_provider::KeyEntry;
 use crate::error::Error;
 use ibc::core::ics24_host::identifier::ChainId;
 use ibc_proto::{
@@ -8,18 +7,20 @@ use ibc_proto::{
 	},
 	google::protobuf::Any,
 };
-use k256::ecdsa::{signature::Signer as _, Signature, SigningKey};
 use prost::Message;
 
-pub fn encode_key_bytes(key: &KeyEntry) -> Result<Vec<u8>, Error> {
+pub fn encode_key_bytes(public_key: Vec<u8>) -> Result<Vec<u8>, Error> {
 	let mut pk_buf = Vec::new();
-	Message::encode(&key.public_key.public_key().to_bytes().as_slice().to_vec(), &mut pk_buf)
-		.map_err(|e| Error::from(e.to_string()))?;
+	Message::encode(&public_key, &mut pk_buf).map_err(|e| Error::from(e.to_string()))?;
 	Ok(pk_buf)
 }
 
-pub fn encode_signer_info(sequence: u64, key_bytes: Vec<u8>) -> Result<SignerInfo, Error> {
-	let pk_any = Any { type_url: "/cosmos.crypto.secp256k1.PubKey".to_string(), value: key_bytes };
+pub fn encode_signer_info(
+	sequence: u64,
+	key_type_url: &str,
+	key_bytes: Vec<u8>,
+) -> Result<SignerInfo, Error> {
+	let pk_any = Any { type_url: key_type_url.to_string(), value: key_bytes };
 	let single = Single { mode: 1 };
 	let sum_single = Some(Sum::Single(single));
 	let mode = Some(ModeInfo { sum: sum_single });
@@ -36,7 +37,6 @@ pub fn encode_auth_info(signer_info: SignerInfo, fee: Fee) -> Result<(AuthInfo,
 }
 
 pub fn encode_sign_doc(
-	key: KeyEntry,
 	body_bytes: Vec<u8>,
 	auth_info_bytes: Vec<u8>,
 	chain_id: ChainId,
@@ -49,20 +49,13 @@ pub fn encode_sign_doc(
 	let mut signdoc_buf = Vec::new();
 	Message::encode(&sign_doc, &mut signdoc_buf)?;
 
-	// Create signature
-	let private_key_bytes = key.private_key.private_key().to_bytes();
-	let signing_key = SigningKey::from_bytes(private_key_bytes.as_slice())
-		.map_err(|e| Error::from(e.to_string()))?;
-	let signature: Signature = signing_key.sign(&signdoc_buf);
-	let signature_bytes = signature.as_ref().to_vec();
-
-	Ok(signature_bytes)
+	Ok(signdoc_buf)
 }
 
-pub fn encode_tx_body(messages: Vec<Any>) -> Result<(TxBody, Vec<u8>), Error> {
+pub fn encode_tx_body(messages: Vec<Any>, memo: String) -> Result<(TxBody, Vec<u8>), Error> {
 	let body = TxBody {
 		messages,
-		memo: "ibc".to_string(),
+		memo,
 		timeout_height: 0_u64,
 		extension_options: Vec::<Any>::default(),
 		non_critical_extension_options: Vec::<Any>::default(),
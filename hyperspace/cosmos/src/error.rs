@@ -1,3 +1,4 @@
+use crate::abci::TxFailure;
 use ibc::timestamp::ParseTimestampError;
 use prost::DecodeError;
 
@@ -25,6 +26,9 @@ pub enum Error {
 	/// Tendermint error
 	#[error("Tendermint error: {0}")]
 	TendermintError(#[from] tendermint::Error),
+	/// A transaction failed with a nonzero ABCI code, see [`crate::abci`]
+	#[error("{0}")]
+	TxFailed(TxFailure),
 }
 
 impl From<String> for Error {
@@ -0,0 +1,40 @@
+//! Keys and accounts of the Ethermint chains (Evmos, Injective, ...).
+//!
+//! These chains use Ethereum keys, of type `ethsecp256k1`: secp256k1 keys derived with the coin
+//! type of Ethereum (60), whose addresses are the Ethereum addresses of their public keys. The
+//! sign docs are hashed with Keccak-256 rather than SHA-256 before they're signed. Their
+//! accounts are `EthAccount`s, which embed the `BaseAccount` of the account.
+
+use ibc_proto::cosmos::auth::v1beta1::BaseAccount;
+use k256::ecdsa::VerifyingKey;
+use sha3::{Digest, Keccak256};
+
+/// Derivation path of the Ethereum keys
+pub const ETH_HD_PATH: &str = "m/44'/60'/0'/0/0";
+
+/// Type of the public keys of Ethermint
+pub const ETHERMINT_PUB_KEY_TYPE_URL: &str = "/ethermint.crypto.v1.ethsecp256k1.PubKey";
+/// Type of the public keys of Injective
+pub const INJECTIVE_PUB_KEY_TYPE_URL: &str = "/injective.crypto.v1beta1.ethsecp256k1.PubKey";
+
+/// Types of the accounts of Ethermint and Injective
+pub const ETH_ACCOUNT_TYPE_URLS: &[&str] =
+	&["/ethermint.types.v1.EthAccount", "/injective.types.v1beta1.EthAccount"];
+
+/// Account of an Ethermint chain. Its code hash, a string on Ethermint and bytes on Injective,
+/// isn't decoded.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EthAccount {
+	#[prost(message, optional, tag = "1")]
+	pub base_account: Option<BaseAccount>,
+}
+
+/// Ethereum address of a public key: the last 20 bytes of the Keccak-256 hash of the key,
+/// uncompressed and without its prefix.
+pub fn eth_address(public_key: &VerifyingKey) -> [u8; 20] {
+	let point = public_key.to_encoded_point(false);
+	let hash = Keccak256::digest(&point.as_bytes()[1..]);
+	let mut address = [0; 20];
+	address.copy_from_slice(&hash[12..]);
+	address
+}
@@ -29,6 +29,16 @@ use tendermint::abci::Event as AbciEvent;
 
 pub const HEADER_ATTRIBUTE_KEY: &str = "header";
 
+/// Hex encoding of the data of a packet, emitted alongside the deprecated `packet_data`
+pub const PKT_DATA_HEX_ATTRIBUTE_KEY: &str = "packet_data_hex";
+
+/// Hex encoding of the acknowledgement of a packet, emitted alongside the deprecated `packet_ack`
+pub const PKT_ACK_HEX_ATTRIBUTE_KEY: &str = "packet_ack_hex";
+
+/// Heights of the consensus states added by a client update, comma-separated, which replaced
+/// the `consensus_height` attribute in ibc-go v7
+pub const CONSENSUS_HEIGHTS_ATTRIBUTE_KEY: &str = "consensus_heights";
+
 #[derive(Clone, Debug, Serialize)]
 pub struct IbcEventWithHeight {
 	pub event: IbcEvent,
@@ -81,6 +91,11 @@ pub fn event_is_type_channel(ev: &IbcEvent) -> bool {
 			IbcEvent::OpenConfirmChannel(_) |
 			IbcEvent::CloseInitChannel(_) |
 			IbcEvent::CloseConfirmChannel(_) |
+			IbcEvent::UpgradeInitChannel(_) |
+			IbcEvent::UpgradeTryChannel(_) |
+			IbcEvent::UpgradeAckChannel(_) |
+			IbcEvent::UpgradeConfirmChannel(_) |
+			IbcEvent::UpgradeOpenChannel(_) |
 			IbcEvent::SendPacket(_) |
 			IbcEvent::ReceivePacket(_) |
 			IbcEvent::WriteAcknowledgement(_) |
@@ -151,6 +166,31 @@ pub fn ibc_event_try_from_abci_event(
 			channel_close_confirm_try_from_abci_event(abci_event)
 				.map_err(IbcEventError::channel)?,
 		)),
+		Ok(IbcEventType::UpgradeInitChannel) =>
+			Ok(IbcEvent::UpgradeInitChannel(channel_events::UpgradeInit(
+				channel_upgrade_try_from_abci_event(abci_event, height)
+					.map_err(IbcEventError::channel)?,
+			))),
+		Ok(IbcEventType::UpgradeTryChannel) =>
+			Ok(IbcEvent::UpgradeTryChannel(channel_events::UpgradeTry(
+				channel_upgrade_try_from_abci_event(abci_event, height)
+					.map_err(IbcEventError::channel)?,
+			))),
+		Ok(IbcEventType::UpgradeAckChannel) =>
+			Ok(IbcEvent::UpgradeAckChannel(channel_events::UpgradeAck(
+				channel_upgrade_try_from_abci_event(abci_event, height)
+					.map_err(IbcEventError::channel)?,
+			))),
+		Ok(IbcEventType::UpgradeConfirmChannel) =>
+			Ok(IbcEvent::UpgradeConfirmChannel(channel_events::UpgradeConfirm(
+				channel_upgrade_try_from_abci_event(abci_event, height)
+					.map_err(IbcEventError::channel)?,
+			))),
+		Ok(IbcEventType::UpgradeOpenChannel) =>
+			Ok(IbcEvent::UpgradeOpenChannel(channel_events::UpgradeOpen(
+				channel_upgrade_try_from_abci_event(abci_event, height)
+					.map_err(IbcEventError::channel)?,
+			))),
 		Ok(IbcEventType::SendPacket) => Ok(IbcEvent::SendPacket(
 			send_packet_try_from_abci_event(abci_event, height).map_err(IbcEventError::channel)?,
 		)),
@@ -309,6 +349,14 @@ pub fn channel_close_confirm_try_from_abci_event(
 	}
 }
 
+pub fn channel_upgrade_try_from_abci_event(
+	abci_event: &AbciEvent,
+	height: Height,
+) -> Result<channel_events::UpgradeAttributes, ChannelError> {
+	channel_events::extract_upgrade_attributes_from_tx(abci_event)
+		.map(|attributes| channel_events::UpgradeAttributes { height, ..attributes })
+}
+
 pub fn send_packet_try_from_abci_event(
 	abci_event: &AbciEvent,
 	height: Height,
@@ -395,6 +443,13 @@ pub fn client_extract_attributes_from_tx(
 				attr.consensus_height = value
 					.parse()
 					.map_err(|e| ClientError::invalid_string_as_height(value.to_string(), e))?,
+			// the relayer submits the updates of one header, whose height is the first one
+			CONSENSUS_HEIGHTS_ATTRIBUTE_KEY => {
+				let first = value.split(',').next().unwrap_or_default();
+				attr.consensus_height = first
+					.parse()
+					.map_err(|e| ClientError::invalid_string_as_height(value.to_string(), e))?;
+			},
 			client_events::HEIGHT_ATTRIBUTE_KEY =>
 				attr.height = value.parse().map_err(|e| {
 					ClientError::invalid_string_as_height(
@@ -494,6 +549,7 @@ fn extract_packet_and_write_ack_from_tx(
 ) -> Result<(Packet, Vec<u8>), ChannelError> {
 	let mut packet = Packet::default();
 	let mut write_ack: Vec<u8> = Vec::new();
+	let (mut data_hex, mut ack_hex) = (None, None);
 	for tag in &event.attributes {
 		let key = tag.key.as_str();
 		let value = tag.value.as_str();
@@ -529,9 +585,22 @@ fn extract_packet_and_write_ack_from_tx(
 			channel_events::PKT_ACK_ATTRIBUTE_KEY => {
 				write_ack = Vec::from(value.as_bytes());
 			},
+			PKT_DATA_HEX_ATTRIBUTE_KEY => {
+				data_hex = Some(hex::decode(value).map_err(|_| ChannelError::invalid_packet())?);
+			},
+			PKT_ACK_HEX_ATTRIBUTE_KEY => {
+				ack_hex = Some(hex::decode(value).map_err(|_| ChannelError::invalid_packet())?);
+			},
 			_ => {},
 		}
 	}
+	// the hex-encoded attributes hold the binary data and acknowledgements as is
+	if let Some(data) = data_hex {
+		packet.data = data;
+	}
+	if let Some(ack) = ack_hex {
+		write_ack = ack;
+	}
 
 	Ok((packet, write_ack))
 }
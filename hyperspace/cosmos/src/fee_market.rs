@@ -0,0 +1,184 @@
+//! Gas prices of the chains with an EIP-1559 fee market.
+//!
+//! On these chains the minimum gas price follows a base fee, which rises while the blocks are
+//! congested. With a [`DynamicGasPrice`] configured, the base fee is queried before each
+//! broadcast, and the fee of the transaction is its gas limit at the base fee times the
+//! `multiplier`, capped to `max_gas_price`. The static `fee_amount` of the config is used
+//! whenever the base fee can't be queried.
+
+use crate::{error::Error, grpc_pool::GrpcPool};
+use serde::{Deserialize, Serialize};
+use tonic::codegen::http::uri::PathAndQuery;
+
+/// Number of decimals of the `sdk.Dec`s, which are encoded as integers
+const DEC_PRECISION: i32 = 18;
+
+fn default_multiplier() -> f64 {
+	1.1
+}
+
+/// Fee market module of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeMarket {
+	/// The `txfees` module of Osmosis
+	Osmosis,
+	/// The `feemarket` module of Ethermint (Evmos, ...)
+	Ethermint,
+}
+
+/// Settings of the gas price following the base fee of the chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DynamicGasPrice {
+	/// Fee market module of the chain
+	pub fee_market: FeeMarket,
+	/// Multiplier of the base fee, so that the transactions stay valid if it rises before
+	/// they're included
+	#[serde(default = "default_multiplier")]
+	pub multiplier: f64,
+	/// Highest gas price paid, in the fee denom
+	#[serde(default)]
+	pub max_gas_price: Option<f64>,
+}
+
+impl DynamicGasPrice {
+	/// Gas price to pay at the given base fee.
+	pub fn gas_price(&self, base_fee: f64) -> f64 {
+		let gas_price = base_fee * self.multiplier;
+		match self.max_gas_price {
+			Some(max) => gas_price.min(max),
+			None => gas_price,
+		}
+	}
+
+	/// Fee of a transaction of the given gas limit, at the given base fee.
+	pub fn fee_amount(&self, base_fee: f64, gas_limit: u64) -> String {
+		((self.gas_price(base_fee) * gas_limit as f64).ceil() as u128).to_string()
+	}
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryBaseFeeRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryBaseFeeResponse {
+	#[prost(string, tag = "1")]
+	base_fee: String,
+}
+
+impl FeeMarket {
+	/// Queries the current base fee of the chain, in the fee denom per unit of gas.
+	pub async fn query_base_fee(&self, pool: &GrpcPool) -> Result<f64, Error> {
+		let path = match self {
+			Self::Osmosis => "/osmosis.txfees.v1beta1.Query/GetEipBaseFee",
+			Self::Ethermint => "/ethermint.feemarket.v1.Query/BaseFee",
+		};
+		let response: tonic::Response<QueryBaseFeeResponse> = pool
+			.query(|channel| async move {
+				let mut client = tonic::client::Grpc::new(channel);
+				client.ready().await.map_err(|e| {
+					tonic::Status::unavailable(format!("GRPC client error: {:?}", e))
+				})?;
+				client
+					.unary(
+						tonic::Request::new(QueryBaseFeeRequest {}),
+						PathAndQuery::from_static(path),
+						tonic::codec::ProstCodec::default(),
+					)
+					.await
+			})
+			.await
+			.map_err(|e| Error::from(format!("Failed to query the base fee: {:?}", e)))?;
+		self.parse_base_fee(&response.into_inner().base_fee)
+	}
+
+	/// Parses the base fee of a query response, in the fee denom per unit of gas.
+	fn parse_base_fee(&self, base_fee: &str) -> Result<f64, Error> {
+		let value = base_fee
+			.parse::<f64>()
+			.map_err(|e| Error::from(format!("Invalid base fee {base_fee}: {:?}", e)))?;
+		Ok(match self {
+			// the base fee of Osmosis is a `sdk.Dec`, the one of Ethermint a `sdk.Int`
+			Self::Osmosis if !base_fee.contains('.') => value / 10f64.powi(DEC_PRECISION),
+			_ => value,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(value: f64, expected: f64) {
+		assert!((value - expected).abs() <= expected.abs() * 1e-12, "{value} != {expected}");
+	}
+
+	#[test]
+	fn osmosis_base_fees_are_scaled_from_their_decimals() {
+		let base_fee = FeeMarket::Osmosis.parse_base_fee("25000000000000000").unwrap();
+		assert_close(base_fee, 0.025);
+		let base_fee = FeeMarket::Osmosis.parse_base_fee("1000000000000000000").unwrap();
+		assert_close(base_fee, 1.0);
+	}
+
+	#[test]
+	fn osmosis_base_fees_with_a_decimal_point_are_not_scaled() {
+		let base_fee = FeeMarket::Osmosis.parse_base_fee("0.025000000000000000").unwrap();
+		assert_close(base_fee, 0.025);
+	}
+
+	#[test]
+	fn zero_base_fees_are_parsed() {
+		assert_eq!(FeeMarket::Osmosis.parse_base_fee("0").unwrap(), 0.0);
+		assert_eq!(FeeMarket::Osmosis.parse_base_fee("0.000000000000000000").unwrap(), 0.0);
+		assert_eq!(FeeMarket::Ethermint.parse_base_fee("0").unwrap(), 0.0);
+	}
+
+	#[test]
+	fn very_large_base_fees_are_parsed() {
+		// beyond the range of the 64-bit integers
+		let base_fee = FeeMarket::Osmosis
+			.parse_base_fee("123000000000000000000000000000000000")
+			.unwrap();
+		assert_close(base_fee, 1.23e17);
+		let base_fee = FeeMarket::Ethermint.parse_base_fee("123000000000000000000000").unwrap();
+		assert_close(base_fee, 1.23e23);
+	}
+
+	#[test]
+	fn ethermint_base_fees_are_not_scaled() {
+		assert_eq!(FeeMarket::Ethermint.parse_base_fee("1000000000").unwrap(), 1e9);
+	}
+
+	#[test]
+	fn invalid_base_fees_are_rejected() {
+		assert!(FeeMarket::Osmosis.parse_base_fee("").is_err());
+		assert!(FeeMarket::Ethermint.parse_base_fee("1uosmo").is_err());
+	}
+
+	#[test]
+	fn gas_prices_are_multiplied_and_capped() {
+		let price = DynamicGasPrice {
+			fee_market: FeeMarket::Osmosis,
+			multiplier: 1.5,
+			max_gas_price: Some(0.1),
+		};
+		assert_close(price.gas_price(0.02), 0.03);
+		assert_eq!(price.gas_price(1.0), 0.1);
+		assert_eq!(price.gas_price(0.0), 0.0);
+		assert_eq!(price.fee_amount(0.02, 100_001), "3001");
+		assert_eq!(price.fee_amount(0.0, 100_000), "0");
+	}
+
+	#[test]
+	fn uncapped_gas_prices_follow_large_base_fees() {
+		let price = DynamicGasPrice {
+			fee_market: FeeMarket::Ethermint,
+			multiplier: 1.0,
+			max_gas_price: None,
+		};
+		let base_fee = FeeMarket::Ethermint.parse_base_fee("1000000000000").unwrap();
+		assert_eq!(price.gas_price(base_fee), 1e12);
+		assert_eq!(price.fee_amount(base_fee, 200_000), "200000000000000000");
+	}
+}
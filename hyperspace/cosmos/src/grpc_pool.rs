@@ -0,0 +1,349 @@
+//! Pool of the gRPC endpoints of a chain.
+//!
+//! The gRPC queries are sent to the current gRPC endpoint of the chain, and fail over to the
+//! other endpoints of the pool (`grpc_pool` in the config) when it doesn't respond: a query
+//! failing with a transport error is sent again to the next endpoint, and the endpoint that
+//! failed is marked as degraded. The degraded endpoints are only queried once the healthy ones
+//! failed too, until a health check finds them responding again, see
+//! [`GrpcPool::spawn_health_checks`]. The requests to each endpoint are limited to
+//! `grpc_rate_limit`, see [`primitives::rate_limit`].
+
+use crate::error::Error;
+use futures::Future;
+use ibc_proto::cosmos::auth::v1beta1::{query_client::QueryClient, QueryParamsRequest};
+use primitives::rate_limit::{RequestLimiter, RequestRateLimit, ThrottledRequests};
+use std::{
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex, Weak,
+	},
+	time::Duration,
+};
+use tendermint_rpc::Url;
+use tokio::task::JoinHandle;
+use tonic::{transport::Channel, Code, Status};
+
+/// Interval of the health checks of the endpoints
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time after which an endpoint that doesn't answer its health check is degraded
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A gRPC endpoint of the pool.
+pub struct PoolEntry {
+	pub url: Url,
+	pub channel: Channel,
+	healthy: AtomicBool,
+	limiter: RequestLimiter,
+}
+
+impl PoolEntry {
+	fn new(url: Url, channel: Channel, limiter: RequestLimiter) -> Arc<Self> {
+		Arc::new(Self { url, channel, healthy: AtomicBool::new(true), limiter })
+	}
+
+	pub fn is_healthy(&self) -> bool {
+		self.healthy.load(Ordering::Relaxed)
+	}
+
+	fn set_healthy(&self, healthy: bool) {
+		if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+			match healthy {
+				true =>
+					log::info!(target: "hyperspace_cosmos", "gRPC endpoint {} recovered", self.url),
+				false =>
+					log::warn!(target: "hyperspace_cosmos", "gRPC endpoint {} degraded", self.url),
+			}
+		}
+	}
+}
+
+/// The gRPC endpoints of a chain, shared by the clones of its client. The first one is the
+/// gRPC endpoint of the current endpoints of the chain.
+#[derive(Clone)]
+pub struct GrpcPool {
+	entries: Arc<Mutex<Vec<Arc<PoolEntry>>>>,
+	rate_limit: Option<RequestRateLimit>,
+	throttled: ThrottledRequests,
+}
+
+impl GrpcPool {
+	/// Creates the pool of the current endpoint and the additional ones, which are connected to
+	/// on their first query.
+	pub fn new(
+		primary: (Url, Channel),
+		additional: &[Url],
+		rate_limit: Option<RequestRateLimit>,
+		throttled: ThrottledRequests,
+	) -> Result<Self, Error> {
+		let limiter = |url: &Url| RequestLimiter::new(url, rate_limit, throttled.clone());
+		let mut entries = vec![PoolEntry::new(primary.0.clone(), primary.1, limiter(&primary.0))];
+		for url in additional {
+			let channel = tonic::transport::Endpoint::new(url.to_string())
+				.map_err(|e| Error::from(format!("Invalid gRPC endpoint {url}: {e:?}")))?
+				.connect_lazy();
+			entries.push(PoolEntry::new(url.clone(), channel, limiter(url)));
+		}
+		Ok(Self { entries: Arc::new(Mutex::new(entries)), rate_limit, throttled })
+	}
+
+	/// Replaces the current endpoint, once the client is connected to other endpoints.
+	pub fn set_primary(&self, url: Url, channel: Channel) {
+		let limiter = RequestLimiter::new(&url, self.rate_limit, self.throttled.clone());
+		self.entries.lock().unwrap()[0] = PoolEntry::new(url, channel, limiter);
+	}
+
+	/// The endpoints in the order they're queried: the healthy ones first.
+	fn candidates(&self) -> Vec<Arc<PoolEntry>> {
+		let entries = self.entries.lock().unwrap();
+		let (mut healthy, degraded): (Vec<_>, Vec<_>) =
+			entries.iter().cloned().partition(|entry| entry.is_healthy());
+		healthy.extend(degraded);
+		healthy
+	}
+
+	/// Runs the query on the endpoints in turn, until one of them responds. The error of the
+	/// last endpoint is returned if none of them does.
+	pub async fn query<T, F, Fut>(&self, query: F) -> Result<T, Status>
+	where
+		F: Fn(Channel) -> Fut,
+		Fut: Future<Output = Result<T, Status>>,
+	{
+		let mut candidates = self.candidates().into_iter().peekable();
+		loop {
+			let entry = candidates.next().expect("the pool has at least one endpoint; qed");
+			entry.limiter.acquire(1).await;
+			match query(entry.channel.clone()).await {
+				Err(status) if is_unavailable(&status) => {
+					entry.set_healthy(false);
+					match candidates.peek() {
+						Some(next) => log::debug!(
+							target: "hyperspace_cosmos",
+							"gRPC query to {} failed, failing over to {}: {status:?}", entry.url, next.url
+						),
+						None => return Err(status),
+					}
+				},
+				result => return result,
+			}
+		}
+	}
+
+	/// Checks the health of the endpoints every `interval`, restoring the degraded endpoints
+	/// that respond again. The task stops once the clients of the pool are dropped.
+	pub fn spawn_health_checks(&self, interval: Duration) -> JoinHandle<()> {
+		let entries = Arc::downgrade(&self.entries);
+		tokio::spawn(async move {
+			loop {
+				tokio::time::sleep(interval).await;
+				let Some(entries) = Weak::upgrade(&entries) else { break };
+				let current = entries.lock().unwrap().clone();
+				drop(entries);
+				for entry in current {
+					entry.limiter.acquire(1).await;
+					let mut client = QueryClient::new(entry.channel.clone());
+					let check = tokio::time::timeout(
+						HEALTH_CHECK_TIMEOUT,
+						client.params(QueryParamsRequest {}),
+					)
+					.await;
+					entry.set_healthy(matches!(check, Ok(Ok(_))));
+				}
+			}
+		})
+	}
+}
+
+/// Returns `true` if the status is a failure of the endpoint rather than of the query.
+pub(crate) fn is_unavailable(status: &Status) -> bool {
+	matches!(status.code(), Code::Unavailable | Code::DeadlineExceeded | Code::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ibc_proto::cosmos::auth::v1beta1::{
+		query_server::{Query, QueryServer},
+		*,
+	};
+	use std::sync::atomic::AtomicUsize;
+	use tonic::{Request, Response};
+
+	/// An endpoint that answers the health checks.
+	struct Healthy;
+
+	#[tonic::async_trait]
+	impl Query for Healthy {
+		async fn accounts(
+			&self,
+			_: Request<QueryAccountsRequest>,
+		) -> Result<Response<QueryAccountsResponse>, Status> {
+			Err(Status::unimplemented("accounts"))
+		}
+
+		async fn account(
+			&self,
+			_: Request<QueryAccountRequest>,
+		) -> Result<Response<QueryAccountResponse>, Status> {
+			Err(Status::unimplemented("account"))
+		}
+
+		async fn account_address_by_id(
+			&self,
+			_: Request<QueryAccountAddressByIdRequest>,
+		) -> Result<Response<QueryAccountAddressByIdResponse>, Status> {
+			Err(Status::unimplemented("account_address_by_id"))
+		}
+
+		async fn params(
+			&self,
+			_: Request<QueryParamsRequest>,
+		) -> Result<Response<QueryParamsResponse>, Status> {
+			Ok(Response::new(QueryParamsResponse::default()))
+		}
+
+		async fn module_accounts(
+			&self,
+			_: Request<QueryModuleAccountsRequest>,
+		) -> Result<Response<QueryModuleAccountsResponse>, Status> {
+			Err(Status::unimplemented("module_accounts"))
+		}
+
+		async fn module_account_by_name(
+			&self,
+			_: Request<QueryModuleAccountByNameRequest>,
+		) -> Result<Response<QueryModuleAccountByNameResponse>, Status> {
+			Err(Status::unimplemented("module_account_by_name"))
+		}
+
+		async fn bech32_prefix(
+			&self,
+			_: Request<Bech32PrefixRequest>,
+		) -> Result<Response<Bech32PrefixResponse>, Status> {
+			Err(Status::unimplemented("bech32_prefix"))
+		}
+
+		async fn address_bytes_to_string(
+			&self,
+			_: Request<AddressBytesToStringRequest>,
+		) -> Result<Response<AddressBytesToStringResponse>, Status> {
+			Err(Status::unimplemented("address_bytes_to_string"))
+		}
+
+		async fn address_string_to_bytes(
+			&self,
+			_: Request<AddressStringToBytesRequest>,
+		) -> Result<Response<AddressStringToBytesResponse>, Status> {
+			Err(Status::unimplemented("address_string_to_bytes"))
+		}
+
+		async fn account_info(
+			&self,
+			_: Request<QueryAccountInfoRequest>,
+		) -> Result<Response<QueryAccountInfoResponse>, Status> {
+			Err(Status::unimplemented("account_info"))
+		}
+	}
+
+	/// Serves the health checks on a local port, returning its url.
+	async fn healthy_endpoint() -> Url {
+		let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		tokio::spawn(
+			tonic::transport::Server::builder()
+				.add_service(QueryServer::new(Healthy))
+				.serve(addr),
+		);
+		format!("http://{addr}").parse().unwrap()
+	}
+
+	/// A local port nothing listens on.
+	fn unreachable_endpoint() -> Url {
+		let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		format!("http://{addr}").parse().unwrap()
+	}
+
+	fn pool(urls: &[Url]) -> GrpcPool {
+		let channel = tonic::transport::Endpoint::new(urls[0].to_string()).unwrap().connect_lazy();
+		GrpcPool::new((urls[0].clone(), channel), &urls[1..], None, Default::default()).unwrap()
+	}
+
+	fn health(pool: &GrpcPool) -> Vec<bool> {
+		pool.entries.lock().unwrap().iter().map(|entry| entry.is_healthy()).collect()
+	}
+
+	fn order(pool: &GrpcPool, urls: &[Url]) -> Vec<usize> {
+		pool.candidates()
+			.iter()
+			.map(|entry| urls.iter().position(|url| *url == entry.url).unwrap())
+			.collect()
+	}
+
+	/// Runs a query failing with the given statuses, then succeeding, and returns its number of
+	/// attempts.
+	async fn query(pool: &GrpcPool, failures: Vec<Status>) -> (Result<(), Status>, usize) {
+		let calls = AtomicUsize::new(0);
+		let failures = Mutex::new(failures.into_iter());
+		let result = pool
+			.query(|_| {
+				calls.fetch_add(1, Ordering::Relaxed);
+				let failure = failures.lock().unwrap().next();
+				async move { failure.map_or(Ok(()), Err) }
+			})
+			.await;
+		(result, calls.load(Ordering::Relaxed))
+	}
+
+	#[tokio::test]
+	async fn queries_fail_over_to_the_next_endpoints() {
+		let urls: Vec<_> = (0..3).map(|_| unreachable_endpoint()).collect();
+		let pool = pool(&urls);
+		let (result, calls) =
+			query(&pool, vec![Status::unavailable("a"), Status::deadline_exceeded("b")]).await;
+		assert!(result.is_ok());
+		assert_eq!(calls, 3);
+		assert_eq!(health(&pool), vec![false, false, true]);
+		// the degraded endpoints are queried last, in the order of the pool
+		assert_eq!(order(&pool, &urls), vec![2, 0, 1]);
+	}
+
+	#[tokio::test]
+	async fn the_error_of_the_last_endpoint_is_returned() {
+		let urls: Vec<_> = (0..2).map(|_| unreachable_endpoint()).collect();
+		let pool = pool(&urls);
+		let failures = vec![Status::unavailable("a"), Status::unavailable("b")];
+		let (result, calls) = query(&pool, failures).await;
+		assert_eq!(result.unwrap_err().message(), "b");
+		assert_eq!(calls, 2);
+		assert_eq!(health(&pool), vec![false, false]);
+	}
+
+	#[tokio::test]
+	async fn failed_queries_dont_fail_over() {
+		let urls: Vec<_> = (0..2).map(|_| unreachable_endpoint()).collect();
+		let pool = pool(&urls);
+		let (result, calls) = query(&pool, vec![Status::not_found("a")]).await;
+		assert_eq!(result.unwrap_err().code(), Code::NotFound);
+		assert_eq!(calls, 1);
+		assert_eq!(health(&pool), vec![true, true]);
+	}
+
+	#[tokio::test]
+	async fn degraded_endpoints_recover_once_they_respond() {
+		let urls = vec![healthy_endpoint().await, unreachable_endpoint()];
+		let pool = pool(&urls);
+		let (result, _) = query(&pool, vec![Status::unavailable("a")]).await;
+		assert!(result.is_ok());
+		assert_eq!(order(&pool, &urls), vec![1, 0]);
+
+		let checks = pool.spawn_health_checks(Duration::from_millis(10));
+		tokio::time::timeout(Duration::from_secs(5), async {
+			while health(&pool) != vec![true, false] {
+				tokio::time::sleep(Duration::from_millis(10)).await;
+			}
+		})
+		.await
+		.expect("the endpoint recovers");
+		assert_eq!(order(&pool, &urls), vec![0, 1]);
+		checks.abort();
+	}
+}
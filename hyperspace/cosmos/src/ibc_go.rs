@@ -0,0 +1,312 @@
+//! Compatibility with the versions of ibc-go.
+//!
+//! The chains running ibc-go v7 and v8 differ from the older ones in a few ways the relayer
+//! depends on:
+//! - the `update_client` events carry the heights of the consensus states they added in a
+//!   `consensus_heights` attribute, a comma-separated list, which replaced `consensus_height`
+//!   (emitted alongside it by v7, and no longer by v8)
+//! - v7 hosts a localhost client, `09-localhost`, which is listed with the client states of the
+//!   chain but isn't one the relayer can update
+//! - v8 deprecates `MsgSubmitMisbehaviour`, the misbehaviour being submitted as the client message
+//!   of a `MsgUpdateClient` instead
+//! - the `allowed_clients` param of the client module of v8 accepts all the client types with `*`
+//!
+//! The version is detected at startup from the build dependencies of the node (see
+//! [`IbcGoVersion::detect`]), or set with `ibc_go_version` in the config for the nodes that
+//! don't report them.
+
+use crate::{error::Error, grpc_pool::GrpcPool};
+use ibc_proto::{
+	google::protobuf::Any,
+	ibc::core::client::v1::{MsgSubmitMisbehaviour, MsgUpdateClient},
+};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use tonic::codegen::http::uri::PathAndQuery;
+
+/// Identifier of the localhost client of ibc-go v7 and later
+pub const LOCALHOST_CLIENT_ID: &str = "09-localhost";
+
+const MSG_SUBMIT_MISBEHAVIOUR_TYPE_URL: &str = "/ibc.core.client.v1.MsgSubmitMisbehaviour";
+
+const MSG_UPDATE_CLIENT_TYPE_URL: &str = "/ibc.core.client.v1.MsgUpdateClient";
+
+/// Version of ibc-go of the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+pub enum IbcGoVersion {
+	/// ibc-go v6 and older
+	#[default]
+	#[serde(rename = "v6")]
+	V6,
+	/// ibc-go v7
+	#[serde(rename = "v7")]
+	V7,
+	/// ibc-go v8 and later
+	#[serde(rename = "v8")]
+	V8,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct GetNodeInfoRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct GetNodeInfoResponse {
+	#[prost(message, optional, tag = "2")]
+	application_version: Option<VersionInfo>,
+}
+
+/// The fields of the version of the application the relayer reads
+#[derive(Clone, PartialEq, prost::Message)]
+struct VersionInfo {
+	#[prost(message, repeated, tag = "7")]
+	build_deps: Vec<Module>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Module {
+	#[prost(string, tag = "1")]
+	path: String,
+	#[prost(string, tag = "2")]
+	version: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryClientParamsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct QueryClientParamsResponse {
+	#[prost(message, optional, tag = "1")]
+	params: Option<ClientParams>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ClientParams {
+	#[prost(string, repeated, tag = "1")]
+	allowed_clients: Vec<String>,
+}
+
+impl IbcGoVersion {
+	/// Parses the version of the ibc-go module the node was built with, e.g. `v7.3.1`.
+	pub fn from_module_version(version: &str) -> Option<Self> {
+		let major = version.trim_start_matches('v').split('.').next()?.parse::<u32>().ok()?;
+		Some(match major {
+			0..=6 => Self::V6,
+			7 => Self::V7,
+			_ => Self::V8,
+		})
+	}
+
+	/// Queries the version of ibc-go from the build dependencies of the node. The nodes that
+	/// don't report it are handled as running v6.
+	pub async fn detect(grpc_pool: &GrpcPool) -> Self {
+		let response: Result<tonic::Response<GetNodeInfoResponse>, _> = grpc_pool
+			.query(|channel| async move {
+				let mut client = tonic::client::Grpc::new(channel);
+				client.ready().await.map_err(|e| {
+					tonic::Status::unavailable(format!("GRPC client error: {:?}", e))
+				})?;
+				client
+					.unary(
+						tonic::Request::new(GetNodeInfoRequest {}),
+						PathAndQuery::from_static(
+							"/cosmos.base.tendermint.v1beta1.Service/GetNodeInfo",
+						),
+						tonic::codec::ProstCodec::default(),
+					)
+					.await
+			})
+			.await;
+		let version = response.ok().and_then(|response| {
+			response
+				.into_inner()
+				.application_version?
+				.build_deps
+				.into_iter()
+				.find(|module| module.path.starts_with("github.com/cosmos/ibc-go"))
+				.and_then(|module| Self::from_module_version(&module.version))
+		});
+		version.unwrap_or_else(|| {
+			log::warn!(
+				target: "hyperspace_cosmos",
+				"The node doesn't report its version of ibc-go, using the behavior of {:?}",
+				Self::default()
+			);
+			Self::default()
+		})
+	}
+
+	/// Key of the attribute of the `update_client` events with the heights of the consensus
+	/// states the update added.
+	pub fn consensus_height_attribute_key(&self) -> &'static str {
+		match self {
+			Self::V6 => "consensus_height",
+			Self::V7 | Self::V8 => "consensus_heights",
+		}
+	}
+
+	/// Returns `true` if the client is the localhost client of the chain.
+	pub fn is_localhost_client(&self, client_id: &str) -> bool {
+		*self >= Self::V7 && client_id == LOCALHOST_CLIENT_ID
+	}
+
+	/// Returns `true` if the `allowed_clients` param of the chain allows the client type.
+	pub fn allows_client(&self, allowed_clients: &[String], client_type: &str) -> bool {
+		allowed_clients
+			.iter()
+			.any(|allowed| allowed == client_type || (*self >= Self::V8 && allowed == "*"))
+	}
+
+	/// Rewrites the messages the chain no longer accepts as their replacements.
+	pub fn convert_messages(&self, messages: Vec<Any>) -> Vec<Any> {
+		if *self < Self::V8 {
+			return messages
+		}
+		messages
+			.into_iter()
+			.map(|msg| {
+				if msg.type_url != MSG_SUBMIT_MISBEHAVIOUR_TYPE_URL {
+					return msg
+				}
+				#[allow(deprecated)]
+				match MsgSubmitMisbehaviour::decode(&*msg.value) {
+					Ok(MsgSubmitMisbehaviour { client_id, misbehaviour, signer }) => {
+						let update =
+							MsgUpdateClient { client_id, client_message: misbehaviour, signer };
+						Any {
+							type_url: MSG_UPDATE_CLIENT_TYPE_URL.to_string(),
+							value: update.encode_to_vec(),
+						}
+					},
+					Err(_) => msg,
+				}
+			})
+			.collect()
+	}
+}
+
+/// Queries the client types the chain allows in the params of its client module.
+pub async fn query_allowed_clients(grpc_pool: &GrpcPool) -> Result<Vec<String>, Error> {
+	let response: tonic::Response<QueryClientParamsResponse> = grpc_pool
+		.query(|channel| async move {
+			let mut client = tonic::client::Grpc::new(channel);
+			client
+				.ready()
+				.await
+				.map_err(|e| tonic::Status::unavailable(format!("GRPC client error: {:?}", e)))?;
+			client
+				.unary(
+					tonic::Request::new(QueryClientParamsRequest {}),
+					PathAndQuery::from_static("/ibc.core.client.v1.Query/ClientParams"),
+					tonic::codec::ProstCodec::default(),
+				)
+				.await
+		})
+		.await
+		.map_err(|e| Error::from(format!("Failed to query the client params: {e:?}")))?;
+	Ok(response
+		.into_inner()
+		.params
+		.map(|params| params.allowed_clients)
+		.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const VERSIONS: [IbcGoVersion; 3] = [IbcGoVersion::V6, IbcGoVersion::V7, IbcGoVersion::V8];
+
+	fn misbehaviour() -> Any {
+		Any { type_url: "/ibc.lightclients.tendermint.v1.Misbehaviour".to_string(), value: vec![1] }
+	}
+
+	#[allow(deprecated)]
+	fn submit_misbehaviour() -> Any {
+		let msg = MsgSubmitMisbehaviour {
+			client_id: "07-tendermint-0".to_string(),
+			misbehaviour: Some(misbehaviour()),
+			signer: "cosmos1signer".to_string(),
+		};
+		Any { type_url: MSG_SUBMIT_MISBEHAVIOUR_TYPE_URL.to_string(), value: msg.encode_to_vec() }
+	}
+
+	fn other_message() -> Any {
+		Any { type_url: "/ibc.core.channel.v1.MsgRecvPacket".to_string(), value: vec![2] }
+	}
+
+	#[test]
+	fn module_versions_are_parsed() {
+		assert_eq!(IbcGoVersion::from_module_version("v4.4.2"), Some(IbcGoVersion::V6));
+		assert_eq!(IbcGoVersion::from_module_version("v6.1.0"), Some(IbcGoVersion::V6));
+		assert_eq!(IbcGoVersion::from_module_version("v7.3.1"), Some(IbcGoVersion::V7));
+		assert_eq!(IbcGoVersion::from_module_version("v8.0.0"), Some(IbcGoVersion::V8));
+		assert_eq!(IbcGoVersion::from_module_version("v10.1.0"), Some(IbcGoVersion::V8));
+		assert_eq!(IbcGoVersion::from_module_version("7.0.0"), Some(IbcGoVersion::V7));
+		assert_eq!(IbcGoVersion::from_module_version("devel"), None);
+	}
+
+	#[test]
+	fn the_consensus_heights_attribute_depends_on_the_version() {
+		assert_eq!(IbcGoVersion::V6.consensus_height_attribute_key(), "consensus_height");
+		assert_eq!(IbcGoVersion::V7.consensus_height_attribute_key(), "consensus_heights");
+		assert_eq!(IbcGoVersion::V8.consensus_height_attribute_key(), "consensus_heights");
+	}
+
+	#[test]
+	fn the_localhost_client_exists_from_v7() {
+		assert!(!IbcGoVersion::V6.is_localhost_client(LOCALHOST_CLIENT_ID));
+		assert!(IbcGoVersion::V7.is_localhost_client(LOCALHOST_CLIENT_ID));
+		assert!(IbcGoVersion::V8.is_localhost_client(LOCALHOST_CLIENT_ID));
+		assert!(!IbcGoVersion::V8.is_localhost_client("07-tendermint-0"));
+	}
+
+	#[test]
+	fn the_listed_client_types_are_allowed() {
+		let allowed = vec!["07-tendermint".to_string(), "08-wasm".to_string()];
+		for version in VERSIONS {
+			assert!(version.allows_client(&allowed, "08-wasm"), "{version:?}");
+			assert!(!version.allows_client(&allowed, "10-grandpa"), "{version:?}");
+			assert!(!version.allows_client(&[], "08-wasm"), "{version:?}");
+		}
+	}
+
+	#[test]
+	fn the_wildcard_allows_all_the_client_types_from_v8() {
+		let allowed = vec!["*".to_string()];
+		assert!(!IbcGoVersion::V6.allows_client(&allowed, "08-wasm"));
+		assert!(!IbcGoVersion::V7.allows_client(&allowed, "08-wasm"));
+		assert!(IbcGoVersion::V8.allows_client(&allowed, "08-wasm"));
+	}
+
+	#[test]
+	fn messages_are_kept_before_v8() {
+		for version in [IbcGoVersion::V6, IbcGoVersion::V7] {
+			let messages = vec![submit_misbehaviour(), other_message()];
+			assert_eq!(version.convert_messages(messages.clone()), messages, "{version:?}");
+		}
+	}
+
+	#[test]
+	fn misbehaviours_are_submitted_as_client_updates_from_v8() {
+		let messages =
+			IbcGoVersion::V8.convert_messages(vec![other_message(), submit_misbehaviour()]);
+		assert_eq!(messages.len(), 2);
+		assert_eq!(messages[0], other_message());
+		assert_eq!(messages[1].type_url, MSG_UPDATE_CLIENT_TYPE_URL);
+		let update = MsgUpdateClient::decode(&*messages[1].value).unwrap();
+		assert_eq!(
+			update,
+			MsgUpdateClient {
+				client_id: "07-tendermint-0".to_string(),
+				client_message: Some(misbehaviour()),
+				signer: "cosmos1signer".to_string(),
+			}
+		);
+	}
+
+	#[test]
+	fn undecodable_misbehaviours_are_kept() {
+		let msg = Any { type_url: MSG_SUBMIT_MISBEHAVIOUR_TYPE_URL.to_string(), value: vec![0xff] };
+		assert_eq!(IbcGoVersion::V8.convert_messages(vec![msg.clone()]), vec![msg]);
+	}
+}
@@ -1,20 +1,70 @@
 use super::client::CosmosClient;
+use crate::ethermint::{self, ETHERMINT_PUB_KEY_TYPE_URL, ETH_HD_PATH, INJECTIVE_PUB_KEY_TYPE_URL};
 use bech32::{ToBase32, Variant};
-use bip32::{XPrv as ExtendedPrivateKey, XPub as ExtendedPublicKey};
+use bip32::{
+	ChainCode, ChildNumber, Depth, ExtendedKey, ExtendedKeyAttrs, KeyFingerprint, Prefix,
+	XPrv as ExtendedPrivateKey, XPub as ExtendedPublicKey,
+};
 use primitives::{error::Error, KeyProvider};
+use ripemd::Ripemd160;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::str::FromStr;
 use tendermint::account::Id as AccountId;
 
+/// Type of the public key of the cosmos PubKey
+pub const SECP256K1_PUB_KEY_TYPE_URL: &str = "/cosmos.crypto.secp256k1.PubKey";
+
+/// Type of the keys of the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyType {
+	/// The secp256k1 keys of the cosmos SDK
+	#[default]
+	Secp256k1,
+	/// The Ethereum keys of the Ethermint chains (Evmos, ...), see [`crate::ethermint`]
+	EthSecp256k1,
+	/// The Ethereum keys of Injective, only differing from Ethermint by the type of their
+	/// public keys
+	InjectiveEthSecp256k1,
+}
+
+impl KeyType {
+	/// Derivation path of the keys
+	pub fn hd_path(&self) -> &'static str {
+		match self {
+			Self::Secp256k1 => "m/44'/118'/0'/0/0",
+			Self::EthSecp256k1 | Self::InjectiveEthSecp256k1 => ETH_HD_PATH,
+		}
+	}
+
+	/// Type of the public keys in the signer infos of the transactions
+	pub fn pub_key_type_url(&self) -> &'static str {
+		match self {
+			Self::Secp256k1 => SECP256K1_PUB_KEY_TYPE_URL,
+			Self::EthSecp256k1 => ETHERMINT_PUB_KEY_TYPE_URL,
+			Self::InjectiveEthSecp256k1 => INJECTIVE_PUB_KEY_TYPE_URL,
+		}
+	}
+
+	pub fn is_ethereum(&self) -> bool {
+		*self != Self::Secp256k1
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct KeyEntry {
 	/// Public key
 	pub public_key: ExtendedPublicKey,
-	/// Private key
-	pub private_key: ExtendedPrivateKey,
+	/// Private key, `None` when the key is held by a remote signer, see
+	/// [`primitives::remote_signer`]
+	pub private_key: Option<ExtendedPrivateKey>,
 	/// Account Bech32 format
 	pub account: String,
 	/// Address
 	pub address: Vec<u8>,
+	/// Type of the key
+	pub key_type: KeyType,
 }
 
 impl KeyEntry {
@@ -23,13 +73,51 @@ impl KeyEntry {
 		private_key: ExtendedPrivateKey,
 		account: String,
 		address: Vec<u8>,
+		key_type: KeyType,
 	) -> Self {
-		Self { public_key, private_key, account, address }
+		Self { public_key, private_key: Some(private_key), account, address, key_type }
+	}
+
+	/// The entry of a key held by a remote signer, from its compressed public key.
+	pub fn from_public_key(
+		public_key: &[u8],
+		prefix: &str,
+		key_type: KeyType,
+	) -> Result<Self, bip32::Error> {
+		let key_bytes = public_key.try_into().map_err(|_| bip32::Error::Decode)?;
+		// the chain code is only used to derive child keys, which the remote keys never are
+		let public_key = ExtendedPublicKey::try_from(ExtendedKey {
+			prefix: Prefix::XPUB,
+			attrs: ExtendedKeyAttrs {
+				depth: Depth::default(),
+				parent_fingerprint: KeyFingerprint::default(),
+				child_number: ChildNumber::default(),
+				chain_code: ChainCode::default(),
+			},
+			key_bytes,
+		})?;
+		let address = account_address(&public_key, key_type);
+		let account = bech32::encode(prefix, address.to_base32(), Variant::Bech32)
+			.map_err(|_| bip32::Error::Decode)?;
+		Ok(Self { public_key, private_key: None, account, address: address.into(), key_type })
+	}
+}
+
+/// Address of the account of a public key.
+pub fn account_address(public_key: &ExtendedPublicKey, key_type: KeyType) -> [u8; 20] {
+	if key_type.is_ethereum() {
+		ethermint::eth_address(public_key.public_key())
+	} else {
+		Ripemd160::digest(Sha256::digest(public_key.to_bytes())).into()
 	}
 }
 
 impl<H> KeyProvider for CosmosClient<H> {
 	fn account_id(&self) -> ibc::signer::Signer {
+		// the messages are signed in the name of the granter, see [`crate::authz`]
+		if let Some(granter) = &self.authz_granter {
+			return granter.clone()
+		}
 		let key_entry = self.keybase.clone();
 		let address = hex::encode(key_entry.address);
 		let account = AccountId::from_str(address.as_str())
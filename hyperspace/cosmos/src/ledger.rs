@@ -0,0 +1,596 @@
+//! Signing of the transactions with a Ledger device running the Cosmos app.
+//!
+//! The Cosmos app doesn't sign the protobuf `SignDoc` of `SIGN_MODE_DIRECT`: it shows and signs
+//! the amino JSON `StdSignDoc` of `SIGN_MODE_LEGACY_AMINO_JSON`, so the chain must register an
+//! amino name for each message the relayer submits, and for the client messages its client
+//! updates carry. The names are set in [`LedgerConfig::amino_names`], since ibc-go only registers
+//! them for `MsgTransfer`. The [`LedgerTxSigner`] builds the amino JSON of the packet messages
+//! and the client updates of the relaying from their `SignDoc`, and refuses the other messages
+//! before they reach the device.
+//!
+//! The device holds the key of the account at `m/44'/118'/account'/0/index`, and each
+//! transaction waits for its confirmation on the device. The device transport requires the
+//! `ledger` feature.
+
+use crate::{
+	encode::{encode_key_bytes, encode_signer_info},
+	error::Error,
+	key_provider::SECP256K1_PUB_KEY_TYPE_URL,
+	signer::TxSigner,
+};
+use async_trait::async_trait;
+use ibc_proto::{
+	cosmos::tx::{
+		signing::v1beta1::SignMode,
+		v1beta1::{
+			mode_info::{Single, Sum},
+			AuthInfo, ModeInfo, SignDoc, SignerInfo, TxBody,
+		},
+	},
+	google::protobuf::Any,
+	ibc::{
+		core::{
+			channel::v1::{
+				MsgAcknowledgement, MsgRecvPacket, MsgTimeout, MsgTimeoutOnClose, Packet,
+			},
+			client::v1::{Height, MsgUpdateClient},
+		},
+		lightclients::wasm::v1::Header as WasmHeader,
+	},
+};
+use k256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, sync::Arc};
+
+/// Returns the public key and the address of a path
+const INS_GET_ADDR_SECP256K1: u8 = 0x04;
+/// Signs a sign doc with the key of a path
+const INS_SIGN_SECP256K1: u8 = 0x02;
+/// Chunks of a sign doc: the first one carries the path, the last one returns the signature
+const P1_INIT: u8 = 0;
+const P1_ADD: u8 = 1;
+const P1_LAST: u8 = 2;
+/// Maximum size of the data of an APDU chunk
+const CHUNK_SIZE: usize = 250;
+
+/// Coin type of the cosmos keys
+const COIN_TYPE: u32 = 118;
+const HARDENED: u32 = 0x8000_0000;
+
+/// Ledger device holding the key of the relayer, see [`crate::ledger`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerConfig {
+	/// Account of the key, the third level of its HD path
+	#[serde(default)]
+	pub account: u32,
+	/// Index of the key, the last level of its HD path
+	#[serde(default)]
+	pub index: u32,
+	/// Amino names the chain registers for the messages, by type URL, e.g.
+	/// `"/ibc.core.channel.v1.MsgRecvPacket" = "cosmos-sdk/MsgRecvPacket"`. Must include the
+	/// client messages of the client updates, e.g. `/ibc.lightclients.wasm.v1.Header`
+	pub amino_names: HashMap<String, String>,
+}
+
+impl LedgerConfig {
+	/// HD path of the key, as the 20 bytes the device expects.
+	fn path(&self) -> [u8; 20] {
+		let levels = [44 | HARDENED, COIN_TYPE | HARDENED, self.account | HARDENED, 0, self.index];
+		let mut path = [0; 20];
+		for (chunk, level) in path.chunks_mut(4).zip(levels) {
+			chunk.copy_from_slice(&level.to_le_bytes());
+		}
+		path
+	}
+}
+
+/// Signs the transactions with the key of a Ledger device.
+pub struct LedgerTxSigner {
+	config: LedgerConfig,
+	transport: Arc<transport::Transport>,
+	/// Compressed public key of the relayer, queried from the device
+	public_key: Vec<u8>,
+}
+
+impl LedgerTxSigner {
+	/// Opens the device, querying the public key of the relayer. The address the device derives
+	/// for the `prefix` is logged, so that the operator can check it.
+	pub async fn connect(config: LedgerConfig, prefix: &str) -> Result<Self, Error> {
+		let transport = Arc::new(blocking(transport::Transport::open).await?);
+		let mut data = vec![prefix.len() as u8];
+		data.extend_from_slice(prefix.as_bytes());
+		data.extend_from_slice(&config.path());
+		let response = {
+			let transport = transport.clone();
+			blocking(move || transport.exchange(INS_GET_ADDR_SECP256K1, P1_INIT, &data)).await?
+		};
+		if response.len() < 33 {
+			return Err(Error::from("Invalid public key returned by the Ledger".to_string()))
+		}
+		let (public_key, address) = response.split_at(33);
+		log::info!(
+			target: "hyperspace_cosmos",
+			"Signing the transactions with the Ledger key m/44'/118'/{}'/0/{} of {}: {}",
+			config.account,
+			config.index,
+			String::from_utf8_lossy(address),
+			hex::encode(public_key)
+		);
+		Ok(Self { config, transport, public_key: public_key.to_vec() })
+	}
+}
+
+#[async_trait]
+impl TxSigner for LedgerTxSigner {
+	fn public_key(&self) -> Vec<u8> {
+		self.public_key.clone()
+	}
+
+	fn signer_info(&self, sequence: u64) -> Result<SignerInfo, Error> {
+		let pk_bytes = encode_key_bytes(self.public_key.clone())?;
+		let mut signer_info = encode_signer_info(sequence, SECP256K1_PUB_KEY_TYPE_URL, pk_bytes)?;
+		let single = Single { mode: SignMode::LegacyAminoJson as i32 };
+		signer_info.mode_info = Some(ModeInfo { sum: Some(Sum::Single(single)) });
+		Ok(signer_info)
+	}
+
+	async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>, Error> {
+		let sign_bytes = amino_sign_bytes(&sign_doc, &self.config.amino_names)?;
+		let mut chunks = vec![(P1_INIT, self.config.path().to_vec())];
+		let mut data = sign_bytes.chunks(CHUNK_SIZE).peekable();
+		while let Some(chunk) = data.next() {
+			let p1 = if data.peek().is_some() { P1_ADD } else { P1_LAST };
+			chunks.push((p1, chunk.to_vec()));
+		}
+		log::info!(target: "hyperspace_cosmos", "Waiting for the confirmation of the transaction on the Ledger");
+		let transport = self.transport.clone();
+		let der = blocking(move || {
+			let mut response = vec![];
+			for (p1, chunk) in chunks {
+				response = transport.exchange(INS_SIGN_SECP256K1, p1, &chunk)?;
+			}
+			Ok(response)
+		})
+		.await?;
+		let signature = der_to_compact(&der)?;
+		// the chain rejects the signatures of another key, or of other sign bytes
+		VerifyingKey::from_sec1_bytes(&self.public_key)
+			.and_then(|key| key.verify(&sign_bytes, &signature))
+			.map_err(|e| Error::from(format!("Invalid signature returned by the Ledger: {e}")))?;
+		Ok(signature.as_ref().to_vec())
+	}
+}
+
+/// Runs a blocking call to the device on the blocking threads.
+async fn blocking<T: Send + 'static>(
+	f: impl FnOnce() -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+	tokio::task::spawn_blocking(f).await.map_err(|e| Error::from(e.to_string()))?
+}
+
+/// Converts the DER signature returned by the device to the 64 bytes of its scalars, with a low
+/// `s` as the chains require.
+fn der_to_compact(der: &[u8]) -> Result<Signature, Error> {
+	let invalid = || Error::from(format!("Invalid DER signature {}", hex::encode(der)));
+	let (tag, body) = der.split_first().ok_or_else(invalid)?;
+	let (len, mut body) = body.split_first().ok_or_else(invalid)?;
+	if *tag != 0x30 || *len as usize != body.len() {
+		return Err(invalid())
+	}
+	let mut compact = [0u8; 64];
+	for scalar in compact.chunks_mut(32) {
+		let [0x02, len, rest @ ..] = body else { return Err(invalid()) };
+		let len = *len as usize;
+		if rest.len() < len {
+			return Err(invalid())
+		}
+		let (integer, rest) = rest.split_at(len);
+		// the integers are signed, so they may have a leading zero
+		let integer = match integer {
+			[0, tail @ ..] => tail,
+			integer => integer,
+		};
+		if integer.len() > 32 {
+			return Err(invalid())
+		}
+		scalar[32 - integer.len()..].copy_from_slice(integer);
+		body = rest;
+	}
+	if !body.is_empty() {
+		return Err(invalid())
+	}
+	let signature = Signature::try_from(compact.as_slice()).map_err(|_| invalid())?;
+	Ok(signature.normalize_s().unwrap_or(signature))
+}
+
+/// Builds the sorted amino JSON `StdSignDoc` of the protobuf encoding of a `SignDoc`.
+fn amino_sign_bytes(
+	sign_doc: &[u8],
+	amino_names: &HashMap<String, String>,
+) -> Result<Vec<u8>, Error> {
+	let sign_doc = SignDoc::decode(sign_doc)?;
+	let body = TxBody::decode(sign_doc.body_bytes.as_slice())?;
+	let auth_info = AuthInfo::decode(sign_doc.auth_info_bytes.as_slice())?;
+	if !body.extension_options.is_empty() || !body.non_critical_extension_options.is_empty() {
+		return Err(Error::from("The extension options have no amino encoding".to_string()))
+	}
+	let sequence = auth_info
+		.signer_infos
+		.first()
+		.map(|signer_info| signer_info.sequence)
+		.ok_or_else(|| Error::from("The transaction has no signer".to_string()))?;
+	let fee = auth_info.fee.unwrap_or_default();
+	let mut std_fee = Map::new();
+	std_fee.insert(
+		"amount".to_string(),
+		fee.amount
+			.iter()
+			.map(|coin| json!({ "amount": coin.amount, "denom": coin.denom }))
+			.collect(),
+	);
+	std_fee.insert("gas".to_string(), fee.gas_limit.to_string().into());
+	insert_string(&mut std_fee, "granter", &fee.granter);
+	insert_string(&mut std_fee, "payer", &fee.payer);
+
+	let msgs = body
+		.messages
+		.iter()
+		.map(|message| amino_any(message, amino_names))
+		.collect::<Result<Vec<_>, _>>()?;
+	let mut std_sign_doc = Map::new();
+	std_sign_doc.insert("account_number".to_string(), sign_doc.account_number.to_string().into());
+	std_sign_doc.insert("chain_id".to_string(), sign_doc.chain_id.into());
+	std_sign_doc.insert("fee".to_string(), std_fee.into());
+	std_sign_doc.insert("memo".to_string(), body.memo.into());
+	std_sign_doc.insert("msgs".to_string(), msgs.into());
+	std_sign_doc.insert("sequence".to_string(), sequence.to_string().into());
+	if body.timeout_height != 0 {
+		std_sign_doc.insert("timeout_height".to_string(), body.timeout_height.to_string().into());
+	}
+	let json = serde_json::to_string(&sorted(std_sign_doc.into()))
+		.map_err(|e| Error::from(e.to_string()))?;
+	// the chains sort the sign doc with Go's encoder, which escapes these in the strings
+	Ok(json
+		.replace('<', "\\u003c")
+		.replace('>', "\\u003e")
+		.replace('&', "\\u0026")
+		.into_bytes())
+}
+
+/// Amino JSON of a message, `{"type": <amino name>, "value": <fields>}`.
+fn amino_any(message: &Any, amino_names: &HashMap<String, String>) -> Result<Value, Error> {
+	let name = amino_names.get(&message.type_url).ok_or_else(|| {
+		Error::from(format!("No amino name is set for {} in the ledger config", message.type_url))
+	})?;
+	let bytes = message.value.as_slice();
+	let value = match message.type_url.as_str() {
+		"/ibc.core.client.v1.MsgUpdateClient" => {
+			let msg = MsgUpdateClient::decode(bytes)?;
+			let client_message = msg
+				.client_message
+				.as_ref()
+				.map(|client_message| amino_any(client_message, amino_names))
+				.transpose()?;
+			fields([
+				("client_id", string(&msg.client_id)),
+				("client_message", client_message),
+				("signer", string(&msg.signer)),
+			])
+		},
+		"/ibc.lightclients.wasm.v1.Header" | "/ibc.lightclients.wasm.v1.ClientMessage" => {
+			let header = WasmHeader::decode(bytes)?;
+			fields([("data", base64(&header.data)), ("height", header.height.map(height))])
+		},
+		"/ibc.core.channel.v1.MsgRecvPacket" => {
+			let msg = MsgRecvPacket::decode(bytes)?;
+			fields([
+				("packet", Some(packet(msg.packet))),
+				("proof_commitment", base64(&msg.proof_commitment)),
+				("proof_height", Some(height(msg.proof_height.unwrap_or_default()))),
+				("signer", string(&msg.signer)),
+			])
+		},
+		"/ibc.core.channel.v1.MsgAcknowledgement" => {
+			let msg = MsgAcknowledgement::decode(bytes)?;
+			fields([
+				("packet", Some(packet(msg.packet))),
+				("acknowledgement", base64(&msg.acknowledgement)),
+				("proof_acked", base64(&msg.proof_acked)),
+				("proof_height", Some(height(msg.proof_height.unwrap_or_default()))),
+				("signer", string(&msg.signer)),
+			])
+		},
+		"/ibc.core.channel.v1.MsgTimeout" => {
+			let msg = MsgTimeout::decode(bytes)?;
+			fields([
+				("packet", Some(packet(msg.packet))),
+				("proof_unreceived", base64(&msg.proof_unreceived)),
+				("proof_height", Some(height(msg.proof_height.unwrap_or_default()))),
+				("next_sequence_recv", uint(msg.next_sequence_recv)),
+				("signer", string(&msg.signer)),
+			])
+		},
+		"/ibc.core.channel.v1.MsgTimeoutOnClose" => {
+			let msg = MsgTimeoutOnClose::decode(bytes)?;
+			fields([
+				("packet", Some(packet(msg.packet))),
+				("proof_unreceived", base64(&msg.proof_unreceived)),
+				("proof_close", base64(&msg.proof_close)),
+				("proof_height", Some(height(msg.proof_height.unwrap_or_default()))),
+				("next_sequence_recv", uint(msg.next_sequence_recv)),
+				("signer", string(&msg.signer)),
+			])
+		},
+		type_url =>
+			return Err(Error::from(format!(
+				"The Ledger signer has no amino encoding of {type_url}"
+			))),
+	};
+	Ok(json!({ "type": name, "value": value }))
+}
+
+fn packet(packet: Option<Packet>) -> Value {
+	let packet = packet.unwrap_or_default();
+	fields([
+		("sequence", uint(packet.sequence)),
+		("source_port", string(&packet.source_port)),
+		("source_channel", string(&packet.source_channel)),
+		("destination_port", string(&packet.destination_port)),
+		("destination_channel", string(&packet.destination_channel)),
+		("data", base64(&packet.data)),
+		("timeout_height", Some(height(packet.timeout_height.unwrap_or_default()))),
+		("timeout_timestamp", uint(packet.timeout_timestamp)),
+	])
+}
+
+/// The heights are never omitted, but their zero fields are, e.g. `{}` for a packet without a
+/// timeout height.
+fn height(height: Height) -> Value {
+	fields([
+		("revision_number", uint(height.revision_number)),
+		("revision_height", uint(height.revision_height)),
+	])
+}
+
+/// Object of the fields that aren't empty, since amino omits them.
+fn fields<const N: usize>(fields: [(&str, Option<Value>); N]) -> Value {
+	fields
+		.into_iter()
+		.filter_map(|(name, value)| Some((name.to_string(), value?)))
+		.collect::<Map<_, _>>()
+		.into()
+}
+
+/// Amino encodes the 64-bit integers as strings.
+fn uint(value: u64) -> Option<Value> {
+	(value != 0).then(|| value.to_string().into())
+}
+
+fn string(value: &str) -> Option<Value> {
+	(!value.is_empty()).then(|| value.into())
+}
+
+fn base64(value: &[u8]) -> Option<Value> {
+	(!value.is_empty()).then(|| base64::encode(value).into())
+}
+
+fn insert_string(map: &mut Map<String, Value>, key: &str, value: &str) {
+	if let Some(value) = string(value) {
+		map.insert(key.to_string(), value);
+	}
+}
+
+/// Sorts the keys of the objects, as the amino JSON sign docs are.
+fn sorted(value: Value) -> Value {
+	match value {
+		Value::Object(map) => {
+			let mut entries = map.into_iter().collect::<Vec<_>>();
+			entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+			entries
+				.into_iter()
+				.map(|(key, value)| (key, sorted(value)))
+				.collect::<Map<_, _>>()
+				.into()
+		},
+		Value::Array(values) => values.into_iter().map(sorted).collect(),
+		value => value,
+	}
+}
+
+/// Transport to the device, over USB HID.
+#[cfg(feature = "ledger")]
+mod transport {
+	use crate::error::Error;
+	use ledger_transport_hid::{hidapi::HidApi, TransportNativeHID};
+	use std::sync::Mutex;
+
+	/// Class of the APDUs of the Cosmos app
+	const CLA: u8 = 0x55;
+	/// Status word of the successful APDUs
+	const SW_OK: u16 = 0x9000;
+	/// Status word of the sign docs the operator rejected on the device
+	const SW_REJECTED: u16 = 0x6986;
+
+	pub struct Transport(Mutex<TransportNativeHID>);
+
+	impl Transport {
+		pub fn open() -> Result<Self, Error> {
+			let api = HidApi::new().map_err(|e| Error::from(format!("Failed to open HID: {e}")))?;
+			let transport = TransportNativeHID::new(&api)
+				.map_err(|e| Error::from(format!("Failed to open the Ledger: {e}")))?;
+			Ok(Self(Mutex::new(transport)))
+		}
+
+		/// Sends an APDU to the Cosmos app, returning the data of its answer.
+		pub fn exchange(&self, ins: u8, p1: u8, data: &[u8]) -> Result<Vec<u8>, Error> {
+			let command = ledger_apdu::APDUCommand { cla: CLA, ins, p1, p2: 0, data };
+			let answer = self
+				.0
+				.lock()
+				.unwrap()
+				.exchange(&command)
+				.map_err(|e| Error::from(format!("Failed to reach the Ledger: {e}")))?;
+			match answer.retcode() {
+				SW_OK => Ok(answer.data().to_vec()),
+				SW_REJECTED =>
+					Err(Error::from("The transaction was rejected on the Ledger".to_string())),
+				code => Err(Error::from(format!(
+					"The Ledger failed with {code:#06x}: {}",
+					String::from_utf8_lossy(answer.data())
+				))),
+			}
+		}
+	}
+}
+
+#[cfg(not(feature = "ledger"))]
+mod transport {
+	use crate::error::Error;
+
+	pub enum Transport {}
+
+	impl Transport {
+		pub fn open() -> Result<Self, Error> {
+			Err(Error::from(
+				"The relayer was built without the `ledger` feature, it can't reach the Ledger"
+					.to_string(),
+			))
+		}
+
+		pub fn exchange(&self, _ins: u8, _p1: u8, _data: &[u8]) -> Result<Vec<u8>, Error> {
+			match *self {}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ibc_proto::cosmos::{base::v1beta1::Coin, tx::v1beta1::Fee};
+
+	fn amino_names() -> HashMap<String, String> {
+		[("/ibc.core.channel.v1.MsgRecvPacket", "cosmos-sdk/MsgRecvPacket")]
+			.into_iter()
+			.map(|(type_url, name)| (type_url.to_string(), name.to_string()))
+			.collect()
+	}
+
+	fn sign_doc(messages: Vec<Any>, memo: &str) -> Vec<u8> {
+		let body = TxBody { messages, memo: memo.to_string(), ..Default::default() };
+		let auth_info = AuthInfo {
+			signer_infos: vec![SignerInfo { sequence: 7, ..Default::default() }],
+			fee: Some(Fee {
+				amount: vec![Coin { denom: "uatom".to_string(), amount: "500".to_string() }],
+				gas_limit: 200_000,
+				..Default::default()
+			}),
+			tip: None,
+		};
+		SignDoc {
+			body_bytes: body.encode_to_vec(),
+			auth_info_bytes: auth_info.encode_to_vec(),
+			chain_id: "cosmoshub-4".to_string(),
+			account_number: 42,
+		}
+		.encode_to_vec()
+	}
+
+	fn recv_packet() -> Any {
+		let msg = MsgRecvPacket {
+			packet: Some(Packet {
+				sequence: 1,
+				source_port: "transfer".to_string(),
+				source_channel: "channel-0".to_string(),
+				destination_port: "transfer".to_string(),
+				destination_channel: "channel-1".to_string(),
+				data: vec![1, 2, 3],
+				timeout_height: None,
+				timeout_timestamp: 1_700_000_000_000_000_000,
+			}),
+			proof_commitment: vec![4, 5],
+			proof_height: Some(Height { revision_number: 0, revision_height: 100 }),
+			signer: "cosmos1relayer".to_string(),
+		};
+		Any {
+			type_url: "/ibc.core.channel.v1.MsgRecvPacket".to_string(),
+			value: msg.encode_to_vec(),
+		}
+	}
+
+	#[test]
+	fn paths_are_encoded_little_endian_with_the_hardened_levels() {
+		let config = LedgerConfig { account: 1, index: 2, amino_names: HashMap::new() };
+		assert_eq!(hex::encode(config.path()), "2c00008076000080010000800000000002000000");
+	}
+
+	#[test]
+	fn sign_docs_are_sorted_amino_json() {
+		let sign_bytes =
+			amino_sign_bytes(&sign_doc(vec![recv_packet()], "a<b"), &amino_names()).unwrap();
+		let expected = concat!(
+			r#"{"account_number":"42","chain_id":"cosmoshub-4","#,
+			r#""fee":{"amount":[{"amount":"500","denom":"uatom"}],"gas":"200000"},"#,
+			r#""memo":"a\u003cb","#,
+			r#""msgs":[{"type":"cosmos-sdk/MsgRecvPacket","value":{"#,
+			r#""packet":{"data":"AQID","destination_channel":"channel-1","destination_port":"transfer","#,
+			r#""sequence":"1","source_channel":"channel-0","source_port":"transfer","#,
+			r#""timeout_height":{},"timeout_timestamp":"1700000000000000000"},"#,
+			r#""proof_commitment":"BAU=","proof_height":{"revision_height":"100"},"#,
+			r#""signer":"cosmos1relayer"}}],"#,
+			r#""sequence":"7"}"#,
+		);
+		assert_eq!(String::from_utf8(sign_bytes).unwrap(), expected);
+	}
+
+	#[test]
+	fn messages_without_an_amino_name_are_refused() {
+		assert!(amino_sign_bytes(&sign_doc(vec![recv_packet()], ""), &HashMap::new()).is_err());
+		let transfer = Any {
+			type_url: "/ibc.applications.transfer.v1.MsgTransfer".to_string(),
+			value: vec![],
+		};
+		let names: HashMap<_, _> =
+			[(transfer.type_url.clone(), "cosmos-sdk/MsgTransfer".to_string())].into();
+		assert!(amino_sign_bytes(&sign_doc(vec![transfer], ""), &names).is_err());
+	}
+
+	#[test]
+	fn der_signatures_are_converted_to_low_s_compact_signatures() {
+		// r with a leading zero byte, s in the upper half of the order
+		let r = [0x80; 32];
+		let high_s =
+			hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140")
+				.unwrap();
+		let mut der = vec![0x30, 0x45, 0x02, 0x21, 0x00];
+		der.extend_from_slice(&r);
+		der.extend_from_slice(&[0x02, 0x20]);
+		der.extend_from_slice(&high_s);
+
+		let signature = der_to_compact(&der).unwrap();
+		let (compact_r, compact_s) = signature.as_ref().split_at(32);
+		assert_eq!(compact_r, r);
+		// n - s = 1
+		let mut low_s = [0; 32];
+		low_s[31] = 1;
+		assert_eq!(compact_s, low_s);
+	}
+
+	#[test]
+	fn short_der_integers_are_left_padded() {
+		let der = [0x30, 0x06, 0x02, 0x01, 0x05, 0x02, 0x01, 0x07];
+		let signature = der_to_compact(&der).unwrap();
+		let mut expected = [0; 64];
+		expected[31] = 5;
+		expected[63] = 7;
+		assert_eq!(signature.as_ref(), expected);
+	}
+
+	#[test]
+	fn invalid_der_signatures_are_rejected() {
+		assert!(der_to_compact(&[]).is_err());
+		assert!(der_to_compact(&[0x30, 0x03, 0x02, 0x01, 0x05]).is_err());
+		assert!(der_to_compact(&[0x31, 0x06, 0x02, 0x01, 0x05, 0x02, 0x01, 0x07]).is_err());
+	}
+}
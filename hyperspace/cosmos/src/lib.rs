@@ -14,14 +14,33 @@
 
 use ibc::core::ics02_client::height::Height;
 
+pub mod abci;
+pub mod authz;
+pub mod backfill;
+pub mod batch;
+pub mod ccv;
 pub mod chain;
 pub mod client;
+pub mod compat;
 pub mod encode;
 pub mod error;
+pub mod ethermint;
 pub mod events;
+pub mod fee_market;
+pub mod grpc_pool;
+pub mod ibc_go;
 pub mod key_provider;
+pub mod ledger;
 pub mod light_client;
+pub mod light_store;
+pub mod memo;
+pub mod multisig;
 pub mod provider;
+pub mod rest;
+pub mod resubscribe;
+pub mod sequence;
+pub mod signer;
+pub mod staking;
 #[cfg(any(test, feature = "testing"))]
 pub mod test_provider;
 pub mod tx;
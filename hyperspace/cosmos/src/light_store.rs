@@ -0,0 +1,504 @@
+//! Local store of the light blocks fetched from the chain.
+//!
+//! The light blocks are fetched to build the headers of the client updates, and kept so that the
+//! trusted blocks, which are fetched again for each header, aren't downloaded every time. A light
+//! block carries the validator sets of its height, so the store is pruned on each insertion: the
+//! blocks stored more than `max_age_secs` ago are dropped, then the lowest heights until at
+//! most `max_blocks` are left (see `light_store` in the config). The number of the blocks of the
+//! store is exposed as the `hyperspace_light_store_size` metric.
+//!
+//! With `light_store_snapshot` in the config, the store is saved to a snapshot file every
+//! [`SNAPSHOT_INTERVAL`] while it changes, and reloaded from it at startup, so that a restarted
+//! relayer doesn't fetch the blocks of its pending client updates again. The snapshot is checked
+//! before it's loaded: its checksum, its chain, the hashes of the headers, commits and validator
+//! sets of each block, and the links between the blocks of consecutive heights. A snapshot that
+//! fails a check is ignored, the store then starting empty.
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+	collections::BTreeMap,
+	future::Future,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc, Mutex, Weak,
+	},
+	time::{Duration, Instant},
+};
+use tendermint::block::Height as TmHeight;
+use tendermint_light_client_verifier::types::LightBlock;
+use tokio::task::JoinHandle;
+
+/// Interval of the saves of the snapshot of a store
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Version of the format of the snapshots
+const SNAPSHOT_VERSION: u32 = 1;
+
+fn default_max_blocks() -> usize {
+	1000
+}
+
+fn default_max_age_secs() -> u64 {
+	60 * 60
+}
+
+/// Retention limits of the light block store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightStoreRetention {
+	/// Maximum number of blocks kept, the lowest heights are pruned first
+	#[serde(default = "default_max_blocks")]
+	pub max_blocks: usize,
+	/// Time after which a stored block is pruned, in seconds
+	#[serde(default = "default_max_age_secs")]
+	pub max_age_secs: u64,
+}
+
+impl Default for LightStoreRetention {
+	fn default() -> Self {
+		Self { max_blocks: default_max_blocks(), max_age_secs: default_max_age_secs() }
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+	version: u32,
+	chain_id: String,
+	blocks: Vec<SnapshotEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+	block: LightBlock,
+	/// Time since the block was stored, in seconds
+	age_secs: u64,
+}
+
+/// The light blocks of a chain, shared by the clones of its client.
+pub struct LightBlockStore {
+	blocks: Mutex<BTreeMap<TmHeight, (LightBlock, Instant)>>,
+	retention: LightStoreRetention,
+	/// Number of the stored blocks, reported as a metric
+	size: Arc<AtomicUsize>,
+	/// Whether blocks were stored since the last snapshot
+	changed: AtomicBool,
+}
+
+impl LightBlockStore {
+	pub fn new(retention: LightStoreRetention, size: Arc<AtomicUsize>) -> Self {
+		Self { blocks: Default::default(), retention, size, changed: AtomicBool::new(false) }
+	}
+
+	/// Returns the block at the given height, fetching and storing it if it isn't stored. The
+	/// blocks of the same height fetched concurrently are stored once.
+	pub async fn get_or_insert_async<F>(
+		&self,
+		height: &TmHeight,
+		fetch: F,
+	) -> Result<LightBlock, Error>
+	where
+		F: Future<Output = Result<LightBlock, Error>>,
+	{
+		if let Some((block, _)) = self.blocks.lock().unwrap().get(height) {
+			return Ok(block.clone())
+		}
+		let block = fetch.await?;
+		self.insert(*height, block.clone());
+		Ok(block)
+	}
+
+	/// Stores a block, pruning the store.
+	pub fn insert(&self, height: TmHeight, block: LightBlock) {
+		let mut blocks = self.blocks.lock().unwrap();
+		blocks.entry(height).or_insert_with(|| (block, Instant::now()));
+		self.changed.store(true, Ordering::Relaxed);
+		self.prune(&mut blocks);
+	}
+
+	fn prune(&self, blocks: &mut BTreeMap<TmHeight, (LightBlock, Instant)>) {
+		let max_age = Duration::from_secs(self.retention.max_age_secs);
+		blocks.retain(|_, (_, stored_at)| stored_at.elapsed() <= max_age);
+		while blocks.len() > self.retention.max_blocks {
+			blocks.pop_first();
+		}
+		self.size.store(blocks.len(), Ordering::Relaxed);
+	}
+
+	/// Number of the stored blocks.
+	pub fn len(&self) -> usize {
+		self.blocks.lock().unwrap().len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Saves the blocks of the store to the snapshot file. The file is replaced at once, so that
+	/// an interrupted save leaves the previous snapshot.
+	pub fn save_snapshot(&self, path: &Path, chain_id: &str) -> Result<(), Error> {
+		let snapshot = {
+			let blocks = self.blocks.lock().unwrap();
+			self.changed.store(false, Ordering::Relaxed);
+			Snapshot {
+				version: SNAPSHOT_VERSION,
+				chain_id: chain_id.to_string(),
+				blocks: blocks
+					.values()
+					.map(|(block, stored_at)| SnapshotEntry {
+						block: block.clone(),
+						age_secs: stored_at.elapsed().as_secs(),
+					})
+					.collect(),
+			}
+		};
+		let body = serde_json::to_vec(&snapshot)
+			.map_err(|e| Error::from(format!("Failed to encode the light block snapshot: {e}")))?;
+		let mut contents = format!("{}\n", hex::encode(Sha256::digest(&body))).into_bytes();
+		contents.extend(body);
+		let tmp_path = path.with_extension("tmp");
+		std::fs::write(&tmp_path, contents)
+			.and_then(|_| std::fs::rename(&tmp_path, path))
+			.map_err(|e| {
+				Error::from(format!(
+					"Failed to write the light block snapshot {}: {e}",
+					path.display()
+				))
+			})
+	}
+
+	/// Loads the blocks of the snapshot file into the store, once the snapshot passed its checks,
+	/// and returns their number. A missing file loads no blocks.
+	pub fn load_snapshot(&self, path: &Path, chain_id: &str) -> Result<usize, Error> {
+		let contents = match std::fs::read(path) {
+			Ok(contents) => contents,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+			Err(e) =>
+				return Err(Error::from(format!(
+					"Failed to read the light block snapshot {}: {e}",
+					path.display()
+				))),
+		};
+		let invalid = |reason: String| {
+			Error::from(format!("Invalid light block snapshot {}: {reason}", path.display()))
+		};
+		let split = contents
+			.iter()
+			.position(|byte| *byte == b'\n')
+			.ok_or_else(|| invalid("missing checksum".to_string()))?;
+		let (checksum, body) = (&contents[..split], &contents[split + 1..]);
+		if checksum != hex::encode(Sha256::digest(body)).as_bytes() {
+			return Err(invalid("checksum mismatch".to_string()))
+		}
+		let snapshot: Snapshot =
+			serde_json::from_slice(body).map_err(|e| invalid(e.to_string()))?;
+		if snapshot.version != SNAPSHOT_VERSION {
+			return Err(invalid(format!("unsupported version {}", snapshot.version)))
+		}
+		if snapshot.chain_id != chain_id {
+			return Err(invalid(format!("snapshot of chain {}", snapshot.chain_id)))
+		}
+		let mut previous: Option<&LightBlock> = None;
+		for SnapshotEntry { block, .. } in &snapshot.blocks {
+			check_block(block, chain_id).map_err(invalid)?;
+			if let Some(previous) = previous {
+				check_link(previous, block).map_err(invalid)?;
+			}
+			previous = Some(block);
+		}
+
+		let now = Instant::now();
+		let mut blocks = self.blocks.lock().unwrap();
+		let count = snapshot.blocks.len();
+		for SnapshotEntry { block, age_secs } in snapshot.blocks {
+			let stored_at = now.checked_sub(Duration::from_secs(age_secs)).unwrap_or(now);
+			blocks.entry(block.height()).or_insert((block, stored_at));
+		}
+		self.prune(&mut blocks);
+		Ok(count)
+	}
+
+	/// Saves the snapshot of the store every [`SNAPSHOT_INTERVAL`] while blocks are stored. The
+	/// task stops with the last clone of the store.
+	pub fn spawn_snapshots(self: &Arc<Self>, path: PathBuf, chain_id: String) -> JoinHandle<()> {
+		let store: Weak<Self> = Arc::downgrade(self);
+		tokio::spawn(async move {
+			loop {
+				tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+				let Some(store) = store.upgrade() else { break };
+				if !store.changed.load(Ordering::Relaxed) {
+					continue
+				}
+				if let Err(e) = store.save_snapshot(&path, &chain_id) {
+					log::warn!(target: "hyperspace_cosmos", "{e}");
+				}
+			}
+		})
+	}
+}
+
+/// Checks that the header, the commit and the validator sets of the block match.
+fn check_block(block: &LightBlock, chain_id: &str) -> Result<(), String> {
+	let header = &block.signed_header.header;
+	let height = header.height;
+	if header.chain_id.as_str() != chain_id {
+		return Err(format!("block {height} of chain {}", header.chain_id))
+	}
+	let commit = &block.signed_header.commit;
+	if commit.height != height || commit.block_id.hash != header.hash() {
+		return Err(format!("the commit of block {height} doesn't match its header"))
+	}
+	if block.validators.hash() != header.validators_hash {
+		return Err(format!("the validators of block {height} don't match its header"))
+	}
+	if block.next_validators.hash() != header.next_validators_hash {
+		return Err(format!("the next validators of block {height} don't match its header"))
+	}
+	Ok(())
+}
+
+/// Checks that the block follows the previous one, if their heights are consecutive.
+fn check_link(previous: &LightBlock, block: &LightBlock) -> Result<(), String> {
+	let (previous, header) = (&previous.signed_header.header, &block.signed_header.header);
+	if previous.height.increment() != header.height {
+		return Ok(())
+	}
+	let height = header.height;
+	if header.last_block_id.map(|id| id.hash) != Some(previous.hash()) {
+		return Err(format!("block {height} doesn't follow the previous block"))
+	}
+	if header.validators_hash != previous.next_validators_hash {
+		return Err(format!("the validators of block {height} aren't those of the previous block"))
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tendermint::Time;
+	use tendermint_testgen::{Generator, LightBlock as TestgenLightBlock};
+
+	const CHAIN_ID: &str = "test-chain-1";
+
+	/// Light blocks of consecutive heights from 1, each following the previous one.
+	fn light_blocks(count: u64) -> Vec<LightBlock> {
+		light_blocks_from(1_650_000_000, count)
+	}
+
+	/// Light blocks of consecutive heights from 1, the first one at the given time.
+	fn light_blocks_from(timestamp: i64, count: u64) -> Vec<LightBlock> {
+		let time = Time::from_unix_timestamp(timestamp, 0).unwrap();
+		let mut block =
+			TestgenLightBlock::new_default_with_time_and_chain_id(CHAIN_ID.to_string(), time, 1);
+		let mut blocks = vec![];
+		for _ in 0..count {
+			let tm = block.generate().unwrap();
+			blocks.push(LightBlock::new(
+				tm.signed_header,
+				tm.validators,
+				tm.next_validators,
+				tm.provider,
+			));
+			block = block.next();
+		}
+		blocks
+	}
+
+	fn store(max_blocks: usize, max_age_secs: u64) -> LightBlockStore {
+		LightBlockStore::new(LightStoreRetention { max_blocks, max_age_secs }, Default::default())
+	}
+
+	fn insert_all(store: &LightBlockStore, blocks: &[LightBlock]) {
+		for block in blocks {
+			store.insert(block.height(), block.clone());
+		}
+	}
+
+	fn heights(store: &LightBlockStore) -> Vec<u64> {
+		store.blocks.lock().unwrap().keys().map(|height| height.value()).collect()
+	}
+
+	/// Makes the block of the given height as if it was stored `secs` ago.
+	fn age(store: &LightBlockStore, height: u64, secs: u64) {
+		let mut blocks = store.blocks.lock().unwrap();
+		let (_, stored_at) = blocks.get_mut(&TmHeight::from(height as u32)).unwrap();
+		*stored_at = Instant::now().checked_sub(Duration::from_secs(secs)).unwrap();
+	}
+
+	fn snapshot_path() -> PathBuf {
+		std::env::temp_dir()
+			.join(format!("hyperspace-light-store-{:016x}.snapshot", rand::random::<u64>()))
+	}
+
+	/// Writes a snapshot with a valid checksum.
+	fn write_snapshot(path: &Path, snapshot: &Snapshot) {
+		let body = serde_json::to_vec(snapshot).unwrap();
+		let mut contents = format!("{}\n", hex::encode(Sha256::digest(&body))).into_bytes();
+		contents.extend(body);
+		std::fs::write(path, contents).unwrap();
+	}
+
+	fn entries(blocks: Vec<LightBlock>, age_secs: u64) -> Vec<SnapshotEntry> {
+		blocks.into_iter().map(|block| SnapshotEntry { block, age_secs }).collect()
+	}
+
+	fn assert_rejected(result: Result<usize, Error>, reason: &str) {
+		let e = result.unwrap_err().to_string();
+		assert!(e.contains(reason), "{e}");
+	}
+
+	#[test]
+	fn the_lowest_heights_are_pruned_over_max_blocks() {
+		let blocks = light_blocks(5);
+		let store = store(3, 60);
+		insert_all(&store, &blocks[..3]);
+		assert_eq!(heights(&store), vec![1, 2, 3]);
+
+		insert_all(&store, &blocks[3..]);
+		assert_eq!(heights(&store), vec![3, 4, 5]);
+		assert_eq!(store.size.load(Ordering::Relaxed), 3);
+
+		// a block below the kept heights is pruned at once
+		store.insert(blocks[0].height(), blocks[0].clone());
+		assert_eq!(heights(&store), vec![3, 4, 5]);
+	}
+
+	#[test]
+	fn the_blocks_older_than_max_age_are_pruned() {
+		let blocks = light_blocks(3);
+		let store = store(10, 60);
+		insert_all(&store, &blocks[..2]);
+		age(&store, 1, 61);
+		age(&store, 2, 59);
+
+		store.insert(blocks[2].height(), blocks[2].clone());
+		assert_eq!(heights(&store), vec![2, 3]);
+		assert_eq!(store.size.load(Ordering::Relaxed), 2);
+	}
+
+	#[test]
+	fn the_aged_blocks_are_pruned_before_the_lowest_heights() {
+		let blocks = light_blocks(4);
+		let store = store(2, 60);
+		insert_all(&store, &blocks[..2]);
+		age(&store, 2, 61);
+
+		// height 2 is pruned for its age, then height 1 for the number of blocks
+		insert_all(&store, &blocks[2..]);
+		assert_eq!(heights(&store), vec![3, 4]);
+	}
+
+	#[test]
+	fn snapshots_are_loaded_back() {
+		let path = snapshot_path();
+		let blocks = light_blocks(3);
+		let saved = store(10, 60);
+		insert_all(&saved, &blocks);
+		saved.save_snapshot(&path, CHAIN_ID).unwrap();
+		assert!(!saved.changed.load(Ordering::Relaxed));
+
+		let loaded = store(10, 60);
+		assert_eq!(loaded.load_snapshot(&path, CHAIN_ID).unwrap(), 3);
+		assert_eq!(heights(&loaded), vec![1, 2, 3]);
+		for block in &blocks {
+			assert_eq!(&loaded.blocks.lock().unwrap()[&block.height()].0, block);
+		}
+		assert_eq!(loaded.size.load(Ordering::Relaxed), 3);
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn missing_snapshots_load_no_blocks() {
+		let store = store(10, 60);
+		assert_eq!(store.load_snapshot(&snapshot_path(), CHAIN_ID).unwrap(), 0);
+		assert!(store.is_empty());
+	}
+
+	#[test]
+	fn the_blocks_of_a_snapshot_keep_their_age() {
+		let path = snapshot_path();
+		let blocks = light_blocks(2);
+		let mut snapshot_entries = entries(blocks[..1].to_vec(), 61);
+		snapshot_entries.extend(entries(blocks[1..].to_vec(), 59));
+		let snapshot = Snapshot {
+			version: SNAPSHOT_VERSION,
+			chain_id: CHAIN_ID.to_string(),
+			blocks: snapshot_entries,
+		};
+		write_snapshot(&path, &snapshot);
+
+		let store = store(10, 60);
+		store.load_snapshot(&path, CHAIN_ID).unwrap();
+		assert_eq!(heights(&store), vec![2]);
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn snapshots_with_a_checksum_mismatch_are_rejected() {
+		let path = snapshot_path();
+		let saved = store(10, 60);
+		insert_all(&saved, &light_blocks(2));
+		saved.save_snapshot(&path, CHAIN_ID).unwrap();
+		let mut contents = std::fs::read(&path).unwrap();
+		*contents.last_mut().unwrap() ^= 1;
+		std::fs::write(&path, contents).unwrap();
+
+		let store = store(10, 60);
+		assert_rejected(store.load_snapshot(&path, CHAIN_ID), "checksum mismatch");
+		assert!(store.is_empty());
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn snapshots_of_another_chain_are_rejected() {
+		let path = snapshot_path();
+		let saved = store(10, 60);
+		insert_all(&saved, &light_blocks(2));
+		saved.save_snapshot(&path, "other-chain-1").unwrap();
+
+		let store = store(10, 60);
+		assert_rejected(store.load_snapshot(&path, CHAIN_ID), "snapshot of chain other-chain-1");
+		assert!(store.is_empty());
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn snapshots_of_another_version_are_rejected() {
+		let path = snapshot_path();
+		let snapshot = Snapshot {
+			version: SNAPSHOT_VERSION + 1,
+			chain_id: CHAIN_ID.to_string(),
+			blocks: entries(light_blocks(2), 0),
+		};
+		write_snapshot(&path, &snapshot);
+
+		let store = store(10, 60);
+		assert_rejected(store.load_snapshot(&path, CHAIN_ID), "unsupported version");
+		assert!(store.is_empty());
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn snapshots_of_unlinked_blocks_are_rejected() {
+		let path = snapshot_path();
+		// block 2 of another history doesn't follow block 1
+		let mut blocks = light_blocks(1);
+		blocks.push(light_blocks_from(1_660_000_000, 2).pop().unwrap());
+		let snapshot = Snapshot {
+			version: SNAPSHOT_VERSION,
+			chain_id: CHAIN_ID.to_string(),
+			blocks: entries(blocks, 0),
+		};
+		write_snapshot(&path, &snapshot);
+
+		let store = store(10, 60);
+		assert_rejected(
+			store.load_snapshot(&path, CHAIN_ID),
+			"block 2 doesn't follow the previous block",
+		);
+		assert!(store.is_empty());
+		std::fs::remove_file(path).unwrap();
+	}
+}
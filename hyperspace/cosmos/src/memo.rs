@@ -0,0 +1,40 @@
+//! Memos of the transactions of the relayer.
+//!
+//! The transactions carry the memo `ibc`, unless `memo_template` is set in the config. The
+//! template is rendered for each transaction, replacing the placeholders:
+//! - `{version}`: the version of the relayer
+//! - `{path}`: the connection relayed on the chain, empty until it's known
+//! - `{bundle_id}`: an identifier of the messages of the transaction, the first 8 bytes of the hash
+//!   of their encoding in hex, which stays the same when the transaction is retried
+
+use ibc_proto::google::protobuf::Any;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Memo of the transactions without a template
+pub const DEFAULT_MEMO: &str = "ibc";
+
+/// Template of the memo of the transactions, see [`crate::memo`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MemoTemplate(pub String);
+
+impl MemoTemplate {
+	/// Renders the memo of a transaction of the messages, relayed on the given path.
+	pub fn render(&self, path: &str, messages: &[Any]) -> String {
+		self.0
+			.replace("{version}", env!("CARGO_PKG_VERSION"))
+			.replace("{path}", path)
+			.replace("{bundle_id}", &bundle_id(messages))
+	}
+}
+
+/// Identifier of the messages of a transaction.
+pub fn bundle_id(messages: &[Any]) -> String {
+	let mut hasher = Sha256::new();
+	for message in messages {
+		hasher.update(message.encode_to_vec());
+	}
+	hex::encode(&hasher.finalize()[..8])
+}
@@ -0,0 +1,420 @@
+//! Multisig relayer accounts.
+//!
+//! With `multisig` set in the config, the transactions are sent from a multisig account (a
+//! `LegacyAminoPubKey`) whose members sign them out of band. The sign doc of each transaction is
+//! exported to the `signing_dir`, and its broadcast waits for the signatures of the `signers` of
+//! the multisig to be collected next to it: the relayer key signs its part if it's one of them,
+//! and the other members sign the exported sign doc with `hyperspace multisig sign`. The
+//! transaction is broadcast once all the signatures were collected, by the relayer, or with
+//! `hyperspace multisig broadcast` if the relayer gave up waiting for them.
+//!
+//! The signers of the transactions are fixed in advance, since they're part of the
+//! `SIGN_MODE_DIRECT` sign doc they sign: each of the `signers` has to sign every transaction.
+//! The files of a sign doc are named after its hash: `<id>.signdoc` holds the hex-encoded sign
+//! doc, and `<id>.<index>.sig` the hex-encoded signature of the member at `index`.
+
+use crate::{
+	compat::{self, CometVersion},
+	encode::{encode_key_bytes, encode_tx},
+	error::Error,
+	key_provider::{KeyEntry, SECP256K1_PUB_KEY_TYPE_URL},
+	signer::{TxSigner, PLACEHOLDER_SIGNATURE},
+};
+use async_trait::async_trait;
+use ibc_proto::{
+	cosmos::{
+		crypto::multisig::v1beta1::{CompactBitArray, MultiSignature},
+		tx::v1beta1::{
+			mode_info::{Multi, Single, Sum},
+			AuthInfo, ModeInfo, SignDoc, SignerInfo, TxBody,
+		},
+	},
+	google::protobuf::Any,
+};
+use k256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+use tendermint::Hash;
+use tendermint_rpc::{endpoint::broadcast::tx_sync, HttpClient, Url};
+
+pub const MULTISIG_PUB_KEY_TYPE_URL: &str = "/cosmos.crypto.multisig.LegacyAminoPubKey";
+
+/// Extension of the files of the exported sign docs
+const SIGN_DOC_EXTENSION: &str = "signdoc";
+
+/// Interval between two checks of the collected signatures
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn default_signing_timeout_secs() -> u64 {
+	600
+}
+
+/// Public key of a multisig account.
+#[derive(Clone, PartialEq, Message)]
+pub struct LegacyAminoPubKey {
+	#[prost(uint32, tag = "1")]
+	pub threshold: u32,
+	#[prost(message, repeated, tag = "2")]
+	pub public_keys: Vec<Any>,
+}
+
+/// Multisig account of the relayer, see [`crate::multisig`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultisigConfig {
+	/// Bech32 address of the multisig account
+	pub address: String,
+	/// Number of signatures the transactions of the account need
+	pub threshold: u32,
+	/// Hex-encoded compressed secp256k1 public keys of the members, in the order of the
+	/// multisig key
+	pub public_keys: Vec<String>,
+	/// Indices of the members signing the transactions, at least `threshold` of them
+	pub signers: Vec<usize>,
+	/// Directory the sign docs are exported to, and the signatures collected from
+	pub signing_dir: PathBuf,
+	/// How long the relayer waits for the signatures of a transaction, in seconds
+	#[serde(default = "default_signing_timeout_secs")]
+	pub signing_timeout_secs: u64,
+}
+
+/// Signs the transactions of a multisig account with the signatures of its members.
+pub struct MultisigSigner {
+	config: MultisigConfig,
+	/// Compressed public keys of the members
+	public_keys: Vec<Vec<u8>>,
+	/// Index of the relayer key in the members, and the key, if it's one of the signers
+	local: Option<(usize, KeyEntry)>,
+}
+
+impl MultisigSigner {
+	pub fn new(mut config: MultisigConfig, key: &KeyEntry) -> Result<Self, Error> {
+		let public_keys = config
+			.public_keys
+			.iter()
+			.map(|public_key| {
+				hex::decode(public_key)
+					.map_err(|e| Error::from(format!("Invalid multisig member {public_key}: {e}")))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		config.signers.sort_unstable();
+		config.signers.dedup();
+		if config.threshold == 0 || config.threshold as usize > public_keys.len() {
+			return Err(Error::from(format!(
+				"Invalid multisig threshold {} of {} members",
+				config.threshold,
+				public_keys.len()
+			)))
+		}
+		if config.signers.len() < config.threshold as usize ||
+			config.signers.iter().any(|index| *index >= public_keys.len())
+		{
+			return Err(Error::from(format!(
+				"The multisig signers {:?} don't reach the threshold {} of the members",
+				config.signers, config.threshold
+			)))
+		}
+		let own_key = key.public_key();
+		let local = public_keys
+			.iter()
+			.position(|public_key| *public_key == own_key)
+			.filter(|index| config.signers.contains(index))
+			.map(|index| (index, key.clone()));
+		Ok(Self { config, public_keys, local })
+	}
+
+	/// Address of the multisig account
+	pub fn address(&self) -> &str {
+		&self.config.address
+	}
+
+	fn multisig_key(&self) -> Result<LegacyAminoPubKey, Error> {
+		let public_keys = self
+			.public_keys
+			.iter()
+			.map(|public_key| {
+				Ok(Any {
+					type_url: SECP256K1_PUB_KEY_TYPE_URL.to_string(),
+					value: encode_key_bytes(public_key.clone())?,
+				})
+			})
+			.collect::<Result<Vec<_>, Error>>()?;
+		Ok(LegacyAminoPubKey { threshold: self.config.threshold, public_keys })
+	}
+}
+
+#[async_trait]
+impl TxSigner for MultisigSigner {
+	fn public_key(&self) -> Vec<u8> {
+		self.multisig_key().map(|key| key.encode_to_vec()).unwrap_or_default()
+	}
+
+	fn public_key_type_url(&self) -> &str {
+		MULTISIG_PUB_KEY_TYPE_URL
+	}
+
+	fn signer_info(&self, sequence: u64) -> Result<SignerInfo, Error> {
+		let direct = ModeInfo { sum: Some(Sum::Single(Single { mode: 1 })) };
+		let multi = Multi {
+			bitarray: Some(bit_array(self.public_keys.len(), &self.config.signers)),
+			mode_infos: vec![direct; self.config.signers.len()],
+		};
+		Ok(SignerInfo {
+			public_key: Some(Any {
+				type_url: MULTISIG_PUB_KEY_TYPE_URL.to_string(),
+				value: self.multisig_key()?.encode_to_vec(),
+			}),
+			mode_info: Some(ModeInfo { sum: Some(Sum::Multi(multi)) }),
+			sequence,
+		})
+	}
+
+	fn placeholder_signature(&self) -> Vec<u8> {
+		MultiSignature {
+			signatures: vec![PLACEHOLDER_SIGNATURE.to_vec(); self.config.signers.len()],
+		}
+		.encode_to_vec()
+	}
+
+	async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>, Error> {
+		let path = export_sign_doc(&self.config.signing_dir, &sign_doc).await?;
+		if let Some((index, key)) = &self.local {
+			let signature = key.sign(sign_doc.clone()).await?;
+			write_signature(&path, *index, &signature).await?;
+		}
+		log::info!(
+			target: "hyperspace_cosmos",
+			"Waiting for the signatures of the multisig members {:?} to {}",
+			self.config.signers, path.display()
+		);
+		let deadline = Instant::now() + Duration::from_secs(self.config.signing_timeout_secs);
+		loop {
+			let signatures =
+				collect_signatures(&path, &sign_doc, &self.public_keys, &self.config.signers)
+					.await?;
+			if let Some(signatures) = signatures {
+				return Ok(MultiSignature { signatures }.encode_to_vec())
+			}
+			if Instant::now() >= deadline {
+				return Err(Error::from(format!(
+					"The multisig members didn't sign {} in time",
+					path.display()
+				)))
+			}
+			tokio::time::sleep(POLL_INTERVAL).await;
+		}
+	}
+}
+
+/// Bit array of the signers of a multisig of `size` members.
+fn bit_array(size: usize, signers: &[usize]) -> CompactBitArray {
+	let mut elems = vec![0u8; (size + 7) / 8];
+	for index in signers {
+		elems[index / 8] |= 1 << (7 - index % 8);
+	}
+	CompactBitArray { extra_bits_stored: (size % 8) as u32, elems }
+}
+
+/// Indices of the signers set in a bit array.
+fn signers_of(bit_array: &CompactBitArray) -> Vec<usize> {
+	let size = match bit_array.extra_bits_stored {
+		0 => bit_array.elems.len() * 8,
+		extra => (bit_array.elems.len().saturating_sub(1)) * 8 + extra as usize,
+	};
+	(0..size)
+		.filter(|index| bit_array.elems[index / 8] & (1 << (7 - index % 8)) != 0)
+		.collect()
+}
+
+fn sign_doc_id(sign_doc: &[u8]) -> String {
+	hex::encode(&Sha256::digest(sign_doc)[..8])
+}
+
+fn signature_path(sign_doc_path: &Path, index: usize) -> PathBuf {
+	sign_doc_path.with_extension(format!("{index}.sig"))
+}
+
+async fn export_sign_doc(dir: &Path, sign_doc: &[u8]) -> Result<PathBuf, Error> {
+	let path = dir.join(format!("{}.{SIGN_DOC_EXTENSION}", sign_doc_id(sign_doc)));
+	tokio::fs::create_dir_all(dir)
+		.await
+		.map_err(|e| Error::from(format!("Failed to create {}: {e}", dir.display())))?;
+	tokio::fs::write(&path, hex::encode(sign_doc))
+		.await
+		.map_err(|e| Error::from(format!("Failed to export {}: {e}", path.display())))?;
+	Ok(path)
+}
+
+async fn read_sign_doc(path: &Path) -> Result<Vec<u8>, Error> {
+	let content = tokio::fs::read_to_string(path)
+		.await
+		.map_err(|e| Error::from(format!("Failed to read {}: {e}", path.display())))?;
+	hex::decode(content.trim())
+		.map_err(|e| Error::from(format!("Invalid sign doc {}: {e}", path.display())))
+}
+
+async fn write_signature(
+	sign_doc_path: &Path,
+	index: usize,
+	signature: &[u8],
+) -> Result<(), Error> {
+	let path = signature_path(sign_doc_path, index);
+	tokio::fs::write(&path, hex::encode(signature))
+		.await
+		.map_err(|e| Error::from(format!("Failed to write {}: {e}", path.display())))
+}
+
+/// The signatures of the signers to the sign doc, in their order, once they're all collected.
+/// The signatures that don't verify are ignored.
+async fn collect_signatures(
+	sign_doc_path: &Path,
+	sign_doc: &[u8],
+	public_keys: &[Vec<u8>],
+	signers: &[usize],
+) -> Result<Option<Vec<Vec<u8>>>, Error> {
+	let mut signatures = vec![];
+	for index in signers {
+		let path = signature_path(sign_doc_path, *index);
+		let Ok(content) = tokio::fs::read_to_string(&path).await else { return Ok(None) };
+		let signature = hex::decode(content.trim()).ok();
+		match signature {
+			Some(signature) if verify(&public_keys[*index], sign_doc, &signature) =>
+				signatures.push(signature),
+			_ => {
+				log::warn!(target: "hyperspace_cosmos", "Ignoring the invalid signature {}", path.display());
+				return Ok(None)
+			},
+		}
+	}
+	Ok(Some(signatures))
+}
+
+fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+	let (Ok(key), Ok(signature)) =
+		(VerifyingKey::from_sec1_bytes(public_key), Signature::try_from(signature))
+	else {
+		return false
+	};
+	key.verify(message, &signature).is_ok()
+}
+
+/// A sign doc exported for the members of a multisig.
+pub struct ExportedSignDoc {
+	pub path: PathBuf,
+	pub sign_doc: SignDoc,
+	pub body: TxBody,
+	pub auth_info: AuthInfo,
+	/// Compressed public keys of the members of the multisig
+	pub public_keys: Vec<Vec<u8>>,
+	/// Indices of the members signing it
+	pub signers: Vec<usize>,
+}
+
+impl ExportedSignDoc {
+	pub async fn read(path: &Path) -> Result<Self, Error> {
+		let bytes = read_sign_doc(path).await?;
+		let invalid = |e: String| Error::from(format!("Invalid sign doc {}: {e}", path.display()));
+		let sign_doc = SignDoc::decode(&*bytes).map_err(|e| invalid(e.to_string()))?;
+		let body = TxBody::decode(&*sign_doc.body_bytes).map_err(|e| invalid(e.to_string()))?;
+		let auth_info =
+			AuthInfo::decode(&*sign_doc.auth_info_bytes).map_err(|e| invalid(e.to_string()))?;
+		let signer_info =
+			auth_info.signer_infos.first().ok_or_else(|| invalid("no signer".to_string()))?;
+		let multisig_key = signer_info
+			.public_key
+			.as_ref()
+			.filter(|key| key.type_url == MULTISIG_PUB_KEY_TYPE_URL)
+			.and_then(|key| LegacyAminoPubKey::decode(&*key.value).ok())
+			.ok_or_else(|| invalid("not signed by a multisig".to_string()))?;
+		let public_keys = multisig_key
+			.public_keys
+			.iter()
+			.map(|key| Vec::<u8>::decode(&*key.value))
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|e| invalid(e.to_string()))?;
+		let signers = match signer_info.mode_info.as_ref().and_then(|mode| mode.sum.as_ref()) {
+			Some(Sum::Multi(Multi { bitarray: Some(bit_array), .. })) => signers_of(bit_array),
+			_ => return Err(invalid("no multisig signers".to_string())),
+		};
+		Ok(Self { path: path.to_path_buf(), sign_doc, body, auth_info, public_keys, signers })
+	}
+
+	/// Summary of the transaction, for its review by the members.
+	pub fn describe(&self) -> String {
+		let messages = self
+			.body
+			.messages
+			.iter()
+			.map(|message| message.type_url.as_str())
+			.collect::<Vec<_>>()
+			.join(", ");
+		let fee = self
+			.auth_info
+			.fee
+			.as_ref()
+			.map(|fee| {
+				fee.amount
+					.iter()
+					.map(|coin| format!("{}{}", coin.amount, coin.denom))
+					.collect::<Vec<_>>()
+					.join(",")
+			})
+			.unwrap_or_default();
+		format!(
+			"chain: {}, account number: {}, memo: {:?}, fee: {fee}, signers: {:?}, messages: {messages}",
+			self.sign_doc.chain_id, self.sign_doc.account_number, self.body.memo, self.signers
+		)
+	}
+
+	/// Signs the sign doc with the key of a member, and writes the signature next to it.
+	pub async fn sign(&self, key: &KeyEntry) -> Result<PathBuf, Error> {
+		let own_key = key.public_key();
+		let index = self
+			.public_keys
+			.iter()
+			.position(|public_key| *public_key == own_key)
+			.ok_or_else(|| Error::from("The key isn't a member of the multisig".to_string()))?;
+		if !self.signers.contains(&index) {
+			return Err(Error::from(format!("Member {index} isn't a signer of the transaction")))
+		}
+		let signature = key.sign(self.sign_doc.encode_to_vec()).await?;
+		write_signature(&self.path, index, &signature).await?;
+		Ok(signature_path(&self.path, index))
+	}
+
+	/// Combines the collected signatures into the signed transaction.
+	pub async fn signed_tx(&self) -> Result<Vec<u8>, Error> {
+		let sign_doc = self.sign_doc.encode_to_vec();
+		let signatures =
+			collect_signatures(&self.path, &sign_doc, &self.public_keys, &self.signers)
+				.await?
+				.ok_or_else(|| Error::from(format!("Signatures of {:?} missing", self.signers)))?;
+		let (_, tx_bytes) = encode_tx(
+			self.sign_doc.body_bytes.clone(),
+			self.sign_doc.auth_info_bytes.clone(),
+			MultiSignature { signatures }.encode_to_vec(),
+		)?;
+		Ok(tx_bytes)
+	}
+
+	/// Broadcasts the signed transaction to the node at `rpc_url`.
+	pub async fn broadcast(&self, rpc_url: Url) -> Result<Hash, Error> {
+		let tx_bytes = self.signed_tx().await?;
+		let client = HttpClient::new(rpc_url).map_err(|e| Error::RpcError(format!("{e:?}")))?;
+		let version = CometVersion::detect(&client).await?;
+		let response = compat::perform(&client, version, tx_sync::Request::new(tx_bytes))
+			.await
+			.map_err(|e| Error::from(format!("failed to broadcast transaction {e:?}")))?;
+		if response.code.is_err() {
+			return Err(Error::from(format!(
+				"transaction {} rejected by CheckTx with code {:?}: {:?}",
+				response.hash, response.code, response.log
+			)))
+		}
+		Ok(response.hash)
+	}
+}
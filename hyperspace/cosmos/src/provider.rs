@@ -5,9 +5,13 @@ use super::{
 		ibc_event_try_from_abci_event, IbcEventWithHeight,
 	},
 };
-use crate::error::Error;
+use crate::{
+	compat,
+	error::Error,
+	resubscribe::{resubscribing_events, RESUBSCRIBE_BACKOFF},
+};
 use futures::{
-	stream::{self, select_all},
+	stream::{self, select_all, BoxStream},
 	Stream, StreamExt,
 };
 use ibc::{
@@ -25,6 +29,7 @@ use ibc::{
 				AcksPath, ChannelEndsPath, ClientConsensusStatePath, ClientStatePath,
 				CommitmentsPath, ConnectionsPath, Path, ReceiptsPath, SeqRecvsPath,
 			},
+			ClientUpgradePath, SDK_UPGRADE_QUERY_PATH,
 		},
 	},
 	events::IbcEvent,
@@ -35,21 +40,34 @@ use ibc::{
 };
 use ibc_primitives::PacketInfo as IbcPacketInfo;
 use ibc_proto::{
-	cosmos::{bank::v1beta1::QueryBalanceRequest, base::query::v1beta1::PageRequest},
-	google::protobuf::Any,
-	ibc::core::{
-		channel::v1::{
-			Channel, QueryChannelResponse, QueryChannelsRequest, QueryChannelsResponse,
-			QueryConnectionChannelsRequest, QueryNextSequenceReceiveResponse,
-			QueryPacketAcknowledgementResponse, QueryPacketAcknowledgementsRequest,
-			QueryPacketCommitmentResponse, QueryPacketCommitmentsRequest,
-			QueryPacketReceiptResponse, QueryUnreceivedAcksRequest, QueryUnreceivedPacketsRequest,
+	cosmos::{
+		bank::v1beta1::QueryBalanceRequest,
+		base::query::v1beta1::PageRequest,
+		upgrade::v1beta1::{
+			query_client::QueryClient as UpgradeQueryClient, QueryCurrentPlanRequest,
 		},
-		client::v1::{
-			QueryClientStateResponse, QueryClientStatesRequest, QueryConsensusStateResponse,
+	},
+	google::protobuf::Any,
+	ibc::{
+		applications::fee::v1::{
+			query_client::QueryClient as FeeQueryClient, QueryIncentivizedPacketsForChannelRequest,
 		},
-		connection::v1::{
-			ConnectionEnd, IdentifiedConnection, QueryConnectionResponse, QueryConnectionsRequest,
+		core::{
+			channel::v1::{
+				Channel, QueryChannelResponse, QueryChannelsRequest, QueryChannelsResponse,
+				QueryConnectionChannelsRequest, QueryNextSequenceReceiveResponse,
+				QueryPacketAcknowledgementResponse, QueryPacketAcknowledgementsRequest,
+				QueryPacketCommitmentResponse, QueryPacketCommitmentsRequest,
+				QueryPacketReceiptResponse, QueryUnreceivedAcksRequest,
+				QueryUnreceivedPacketsRequest, Upgrade,
+			},
+			client::v1::{
+				QueryClientStateResponse, QueryClientStatesRequest, QueryConsensusStateResponse,
+			},
+			connection::v1::{
+				ConnectionEnd, IdentifiedConnection, QueryConnectionResponse,
+				QueryConnectionsRequest,
+			},
 		},
 	},
 };
@@ -62,7 +80,11 @@ use pallet_ibc::light_clients::{
 	AnyClientMessage, AnyClientState, AnyConsensusState, HostFunctionsManager,
 };
 use primitives::{
-	filter_events_by_ids, mock::LocalClientTypes, Chain, IbcProvider, KeyProvider, UpdateType,
+	channel_upgrades::{channel_upgrade_path, ChannelUpgrade},
+	fees::IncentivizedPacket,
+	filter_events_by_ids,
+	mock::LocalClientTypes,
+	BlockCommitment, Chain, IbcProvider, KeyProvider, UpdateType, UpgradedClient,
 };
 use prost::Message;
 use rand::Rng;
@@ -78,7 +100,7 @@ use tendermint_rpc::{
 	endpoint::tx::Response,
 	event::{Event, EventData},
 	query::{EventType, Query},
-	Client, Error as RpcError, Order, SubscriptionClient,
+	Client, Error as RpcError, Order, SubscriptionClient, WebSocketClient,
 };
 use tokio::{task::JoinSet, time::sleep};
 
@@ -202,84 +224,21 @@ where
 	// TODO: Changed result: `Item =` from `IbcEvent` to `IbcEventWithHeight` to include the
 	// necessary height field, as `height` is removed from `Attribute` from ibc-rs v0.22.0
 	async fn ibc_events(&self) -> Pin<Box<dyn Stream<Item = IbcEvent> + Send + 'static>> {
-		// Create websocket client. Like what `EventMonitor::subscribe()` does in `hermes`
-		let ws_client = self.rpc_client.clone();
-
-		let query_all = vec![
-			Query::from(EventType::NewBlock),
-			Query::eq("message.module", "ibc_client"),
-			Query::eq("message.module", "ibc_connection"),
-			Query::eq("message.module", "ibc_channel"),
-		];
-		let mut subscriptions = vec![];
-		for query in &query_all {
-			let subscription = ws_client
-				.subscribe(query.clone())
-				.await
-				.map_err(|e| Error::from(format!("Web Socket Client Error {e:?}")))
-				.unwrap();
-			subscriptions.push(subscription);
+		let events = self.live_ibc_events().await;
+		// the first stream starts with the events missed while the relayer was down, see
+		// [`crate::backfill`]
+		let Some(from) = self.backfill_from_height.lock().unwrap().take() else { return events };
+		let backfilled = match self.latest_height_and_timestamp().await {
+			Ok((latest, _)) => self.backfill_ibc_events(from, latest.revision_height).await,
+			Err(e) => Err(e),
+		};
+		match backfilled {
+			Ok(backfilled) => stream::iter(backfilled).chain(events).boxed(),
+			Err(e) => {
+				log::error!(target: "hyperspace_cosmos", "Failed to backfill the IBC events of {} from {from}: {e:?}", self.name);
+				events
+			},
 		}
-		// Collect IBC events from each RPC event, Like what `stream_batches()` does in `hermes`
-		let all_subs: Box<dyn Stream<Item = Result<Event, RpcError>> + Send + Sync + Unpin> =
-			Box::new(select_all(subscriptions));
-		let chain_id = self.chain_id.clone();
-		let events = all_subs
-			.map(move |event| {
-				// Like what `get_all_events()` does in `hermes`
-				let mut events_with_height: Vec<IbcEventWithHeight> = vec![];
-				let Event { data, events: _, query } = event.unwrap();
-				match data {
-					EventData::NewBlock { block, .. }
-						if query == Query::from(EventType::NewBlock).to_string() =>
-					{
-						let height = Height::new(
-							ChainId::chain_version(chain_id.to_string().as_str()),
-							u64::from(block.as_ref().ok_or("tx.height").unwrap().header.height),
-						);
-						events_with_height.push(IbcEventWithHeight::new(
-							ClientEvents::NewBlock::new(height).into(),
-							height,
-						));
-					},
-					EventData::Tx { tx_result } => {
-						let height = Height::new(
-							ChainId::chain_version(chain_id.to_string().as_str()),
-							tx_result.height as u64,
-						);
-						for abci_event in &tx_result.result.events {
-							if let Ok(ibc_event) = ibc_event_try_from_abci_event(abci_event, height)
-							{
-								log::debug!(target: "hyperspace_cosmos", "Retrieved event: {}, query: {}, parsed: {:?}", abci_event.kind, query, ibc_event);
-								let is_client_event = query == Query::eq("message.module", "ibc_client").to_string() &&
-									event_is_type_client(&ibc_event);
-								let is_connection_event = (query ==
-									Query::eq("message.module", "ibc_connection").to_string() ||
-									query ==
-										Query::eq("message.module", "ibc_client").to_string()) &&
-									event_is_type_connection(&ibc_event);
-								let is_channel_event = query ==
-									Query::eq("message.module", "ibc_channel").to_string() &&
-									event_is_type_channel(&ibc_event);
-								if is_client_event || is_connection_event || is_channel_event {
-									events_with_height
-										.push(IbcEventWithHeight::new(ibc_event, height));
-								} else {
-									log::debug!(target: "hyperspace_cosmos", "The event is unknown");
-								}
-							} else {
-								log::debug!(target: "hyperspace_cosmos", "Failed to parse event {:?}", abci_event);
-							}
-						}
-					},
-					_ => {},
-				}
-				stream::iter(events_with_height)
-			})
-			.flatten()
-			.map(|e| e.event)
-			.boxed();
-		events
 	}
 
 	async fn query_client_consensus(
@@ -304,6 +263,15 @@ where
 		})
 	}
 
+	async fn query_client_consensus_states(
+		&self,
+		at: Height,
+		client_id: ClientId,
+		heights: Vec<Height>,
+	) -> Result<Vec<(Height, QueryConsensusStateResponse)>, Self::Error> {
+		self.query_consensus_states_batch(at, &client_id, heights).await
+	}
+
 	async fn query_client_state(
 		&self,
 		at: Height,
@@ -454,6 +422,7 @@ where
 		// We cannot rely on `/status` endpoint to provide details about the latest block.
 		// Instead, we need to pull block height via `/abci_info` and then fetch block
 		// metadata at the given height via `/blockchain` endpoint.
+		self.rpc_limiter.acquire(2).await;
 		let abci_info = self
 			.rpc_client
 			.abci_info()
@@ -492,29 +461,30 @@ where
 		channel_id: ChannelId,
 		port_id: PortId,
 	) -> Result<Vec<u64>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url.clone().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
-
 		let request = QueryPacketCommitmentsRequest {
 			port_id: port_id.to_string(),
 			channel_id: channel_id.to_string(),
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
 		};
-		let request = tonic::Request::new(request);
-		let response = grpc_client
-			.packet_commitments(request)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?
-			.into_inner();
-
-		let commitment_sequences: Vec<u64> =
-			response.commitments.into_iter().map(|v| v.sequence).collect();
-
-		Ok(commitment_sequences)
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(channel)
+						.packet_commitments(request)
+						.await
+				}
+			})
+			.await;
+
+		match response {
+			Ok(response) =>
+				Ok(response.into_inner().commitments.into_iter().map(|v| v.sequence).collect()),
+			Err(status) =>
+				self.rest_fallback(status, |rest| rest.packet_commitments(&channel_id, &port_id))
+					.await,
+		}
 	}
 
 	async fn query_packet_acknowledgements(
@@ -529,30 +499,33 @@ where
 			channel_id,
 			port_id
 		);
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url.clone().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
-
 		let request = QueryPacketAcknowledgementsRequest {
 			port_id: port_id.to_string(),
 			channel_id: channel_id.to_string(),
 			packet_commitment_sequences: vec![],
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
 		};
-		let request = tonic::Request::new(request);
-		let response = grpc_client
-			.packet_acknowledgements(request)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?
-			.into_inner();
-
-		let commitment_sequences: Vec<u64> =
-			response.acknowledgements.into_iter().map(|v| v.sequence).collect();
-
-		Ok(commitment_sequences)
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(channel)
+						.packet_acknowledgements(request)
+						.await
+				}
+			})
+			.await;
+
+		match response {
+			Ok(response) =>
+				Ok(response.into_inner().acknowledgements.into_iter().map(|v| v.sequence).collect()),
+			Err(status) =>
+				self.rest_fallback(status, |rest| {
+					rest.packet_acknowledgements(&channel_id, &port_id)
+				})
+				.await,
+		}
 	}
 
 	async fn query_unreceived_packets(
@@ -562,28 +535,31 @@ where
 		port_id: PortId,
 		seqs: Vec<u64>,
 	) -> Result<Vec<u64>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url.clone().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
-
 		let request = QueryUnreceivedPacketsRequest {
 			port_id: port_id.to_string(),
 			channel_id: channel_id.to_string(),
-			packet_commitment_sequences: seqs,
+			packet_commitment_sequences: seqs.clone(),
 		};
-		let request = tonic::Request::new(request);
-		let response = grpc_client
-			.unreceived_packets(request)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?
-			.into_inner();
-
-		let commitment_sequences: Vec<u64> = response.sequences.into_iter().collect();
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(channel)
+						.unreceived_packets(request)
+						.await
+				}
+			})
+			.await;
 
-		Ok(commitment_sequences)
+		match response {
+			Ok(response) => Ok(response.into_inner().sequences),
+			Err(status) =>
+				self.rest_fallback(status, |rest| {
+					rest.unreceived_packets(&channel_id, &port_id, &seqs)
+				})
+				.await,
+		}
 	}
 
 	async fn query_unreceived_acknowledgements(
@@ -593,28 +569,31 @@ where
 		port_id: PortId,
 		seqs: Vec<u64>,
 	) -> Result<Vec<u64>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url.clone().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
-
 		let request = QueryUnreceivedAcksRequest {
 			port_id: port_id.to_string(),
 			channel_id: channel_id.to_string(),
-			packet_ack_sequences: seqs,
+			packet_ack_sequences: seqs.clone(),
 		};
-		let request = tonic::Request::new(request);
-		let response = grpc_client
-			.unreceived_acks(request)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?
-			.into_inner();
-
-		let commitment_sequences: Vec<u64> = response.sequences.into_iter().collect();
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(channel)
+						.unreceived_acks(request)
+						.await
+				}
+			})
+			.await;
 
-		Ok(commitment_sequences)
+		match response {
+			Ok(response) => Ok(response.into_inner().sequences),
+			Err(status) =>
+				self.rest_fallback(status, |rest| {
+					rest.unreceived_acks(&channel_id, &port_id, &seqs)
+				})
+				.await,
+		}
 	}
 
 	fn channel_whitelist(&self) -> HashSet<(ChannelId, PortId)> {
@@ -626,26 +605,39 @@ where
 		_at: Height,
 		connection_id: &ConnectionId,
 	) -> Result<QueryChannelsResponse, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url.clone().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?;
-		let request = tonic::Request::new(QueryConnectionChannelsRequest {
+		let request = QueryConnectionChannelsRequest {
 			connection: connection_id.to_string(),
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
-		});
+		};
 
-		let response = grpc_client
-			.connection_channels(request)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?
-			.into_inner();
-		let channels = QueryChannelsResponse {
-			channels: response.channels,
-			pagination: response.pagination,
-			height: response.height,
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(channel)
+						.connection_channels(request)
+						.await
+				}
+			})
+			.await;
+
+		let channels = match response {
+			Ok(response) => {
+				let response = response.into_inner();
+				QueryChannelsResponse {
+					channels: response.channels,
+					pagination: response.pagination,
+					height: response.height,
+				}
+			},
+			Err(status) => QueryChannelsResponse {
+				channels: self
+					.rest_fallback(status, |rest| rest.connection_channels(connection_id))
+					.await?,
+				pagination: None,
+				height: None,
+			},
 		};
 
 		Ok(channels)
@@ -663,28 +655,16 @@ where
 		);
 		let mut block_events = HashMap::<u64, PacketInfo>::new();
 
-		for seq in seqs.iter() {
-			if block_events.contains_key(seq) {
-				continue
-			}
-			let query_str = Query::eq("send_packet.packet_src_channel", channel_id.to_string())
-				.and_eq("send_packet.packet_src_port", port_id.to_string())
-				.and_eq("send_packet.packet_sequence", seq.to_string());
-
-			let response = self
-				.rpc_http_client
-				.tx_search(
-					query_str,
-					true,
-					1,
-					1, // get only the first Tx matching the query
-					Order::Descending, /* query the most recent event, there is possibility that the same
-					    * sequence number is used twice in send_packet event (in case of an
-					    * error during the message processing) */
-				)
-				.await
-				.map_err(|e| Error::RpcError(format!("{e:?}")))?;
-
+		// the most recent event is queried, there is possibility that the same sequence number is
+		// used twice in send_packet event (in case of an error during the message processing)
+		let queries = unique(&seqs)
+			.map(|seq| {
+				Query::eq("send_packet.packet_src_channel", channel_id.to_string())
+					.and_eq("send_packet.packet_src_port", port_id.to_string())
+					.and_eq("send_packet.packet_sequence", seq.to_string())
+			})
+			.collect();
+		for response in self.tx_search_batch(queries).await? {
 			for tx in response.txs {
 				for ev in &tx.tx_result.events {
 					let height = tx.height.value();
@@ -694,8 +674,8 @@ where
 					match ev {
 						Ok(IbcEvent::SendPacket(p))
 							if seqs.contains(&p.packet.sequence.0) &&
-								p.packet.source_port == port_id && p.packet.source_channel ==
-								channel_id =>
+								p.packet.source_port == port_id &&
+								p.packet.source_channel == channel_id =>
 						{
 							let seq = p.packet.sequence.0;
 							let mut info = PacketInfo::try_from(IbcPacketInfo::from(p.packet))
@@ -739,30 +719,17 @@ where
 
 		let mut block_events = HashMap::<u64, PacketInfo>::new();
 
-		for seq in seqs.iter() {
-			if block_events.contains_key(seq) {
-				continue
-			}
-
-			let query_str =
+		// the most recent event is queried, there is possibility that the same sequence number is
+		// used twice in write_acknowledgement event (in case of an error during the message
+		// processing)
+		let queries = unique(&seqs)
+			.map(|seq| {
 				Query::eq("write_acknowledgement.packet_dst_channel", channel_id.to_string())
 					.and_eq("write_acknowledgement.packet_dst_port", port_id.to_string())
-					.and_eq("write_acknowledgement.packet_sequence", seq.to_string());
-
-			let response = self
-				.rpc_http_client
-				.tx_search(
-					query_str,
-					true,
-					1,
-					1, // get only the first Tx matching the query
-					Order::Descending, /* query the most recent event, there is possibility that the same
-					    * sequence number is used twice in write_acknowledgement event (in case
-					    * of an error during the message processing) */
-				)
-				.await
-				.map_err(|e| Error::RpcError(format!("{e:?}")))?;
-
+					.and_eq("write_acknowledgement.packet_sequence", seq.to_string())
+			})
+			.collect();
+		for response in self.tx_search_batch(queries).await? {
 			for tx in response.txs {
 				for ev in &tx.tx_result.events {
 					let height = tx.height.value();
@@ -825,7 +792,6 @@ where
 			.and_eq("update_client.consensus_height", client_height.to_string());
 
 		let response = self
-			.rpc_http_client
 			.tx_search(
 				query_str,
 				true,
@@ -868,19 +834,19 @@ where
 		asset_id: Self::AssetId,
 	) -> Result<Vec<PrefixedCoin>, Self::Error> {
 		let denom = &asset_id;
-		let mut grpc_client = ibc_proto::cosmos::bank::v1beta1::query_client::QueryClient::connect(
-			self.grpc_url.clone().to_string(),
-		)
-		.await
-		.map_err(|e| Error::from(format!("{e:?}")))?;
+		let request =
+			QueryBalanceRequest { address: self.keybase.clone().account, denom: denom.to_string() };
 
-		let request = tonic::Request::new(QueryBalanceRequest {
-			address: self.keybase.clone().account,
-			denom: denom.to_string(),
-		});
-
-		let response = grpc_client
-			.balance(request)
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::cosmos::bank::v1beta1::query_client::QueryClient::new(channel)
+						.balance(request)
+						.await
+				}
+			})
 			.await
 			.map(|r| r.into_inner())
 			.map_err(|e| Error::from(format!("{e:?}")))?;
@@ -935,6 +901,7 @@ where
 	async fn query_timestamp_at(&self, block_number: u64) -> Result<u64, Self::Error> {
 		let height = TmHeight::try_from(block_number)
 			.map_err(|e| Error::from(format!("Invalid block number: {e}")))?;
+		self.rpc_limiter.acquire(1).await;
 		let response = self
 			.rpc_client
 			.block(height)
@@ -944,28 +911,58 @@ where
 		Ok(time.nanoseconds())
 	}
 
+	async fn query_block_commitment_at(
+		&self,
+		block_number: u64,
+	) -> Result<BlockCommitment, Self::Error> {
+		let height = TmHeight::try_from(block_number)
+			.map_err(|e| Error::from(format!("Invalid block number: {e}")))?;
+		self.rpc_limiter.acquire(1).await;
+		let header = self
+			.rpc_client
+			.block(height)
+			.await
+			.map_err(|e| Error::RpcError(e.to_string()))?
+			.block
+			.header;
+		let time: Timestamp = header.time.into();
+		Ok(BlockCommitment {
+			timestamp: time.nanoseconds(),
+			root: header.app_hash.as_bytes().to_vec(),
+			next_validators_hash: Some(header.next_validators_hash.as_bytes().to_vec()),
+		})
+	}
+
 	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error> {
-		let request = tonic::Request::new(QueryClientStatesRequest {
+		let request = QueryClientStatesRequest {
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
-		});
-		let grpc_client = ibc_proto::ibc::core::client::v1::query_client::QueryClient::new(
-			self.grpc_client.clone(),
-		);
-		let response = grpc_client
-			.clone()
-			.client_states(request)
-			.await
-			.map_err(|e| {
-				Error::from(format!("Failed to query client states from grpc client: {e:?}"))
-			})?
-			.into_inner();
+		};
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::client::v1::query_client::QueryClient::new(channel)
+						.client_states(request)
+						.await
+				}
+			})
+			.await;
+		let client_ids = match response {
+			Ok(response) =>
+				response.into_inner().client_states.into_iter().map(|cs| cs.client_id).collect(),
+			Err(status) =>
+				self.rest_fallback(status, |rest| rest.client_ids()).await.map_err(|e| {
+					Error::from(format!("Failed to query client states from grpc client: {e}"))
+				})?,
+		};
 
 		// Deserialize into domain type
-		let clients: Vec<ClientId> = response
-			.client_states
+		let clients: Vec<ClientId> = client_ids
 			.into_iter()
-			.filter_map(|cs| {
-				let id = ClientId::from_str(&cs.client_id).ok()?;
+			.filter(|client_id| !self.ibc_go_version.is_localhost_client(client_id))
+			.filter_map(|client_id| {
+				let id = ClientId::from_str(&client_id).ok()?;
 				Some(id)
 			})
 			.collect();
@@ -973,21 +970,25 @@ where
 	}
 
 	async fn query_channels(&self) -> Result<Vec<(ChannelId, PortId)>, Self::Error> {
-		let request = tonic::Request::new(QueryChannelsRequest {
+		let request = QueryChannelsRequest {
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
-		});
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url.clone().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?;
-		let response = grpc_client
-			.channels(request)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?
-			.into_inner()
-			.channels
+		};
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(channel)
+						.channels(request)
+						.await
+				}
+			})
+			.await;
+		let channels = match response {
+			Ok(response) => response.into_inner().channels,
+			Err(status) => self.rest_fallback(status, |rest| rest.channels()).await?,
+		};
+		let response = channels
 			.into_iter()
 			.filter_map(|c| {
 				let id = ChannelId::from_str(&c.channel_id).ok()?;
@@ -1003,19 +1004,20 @@ where
 		_height: u32,
 		client_id: String,
 	) -> Result<Vec<IdentifiedConnection>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::connection::v1::query_client::QueryClient::connect(
-				self.grpc_url.clone().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?;
-
-		let request = tonic::Request::new(QueryConnectionsRequest {
+		let request = QueryConnectionsRequest {
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
-		});
+		};
 
-		let response = grpc_client
-			.connections(request)
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					ibc_proto::ibc::core::connection::v1::query_client::QueryClient::new(channel)
+						.connections(request)
+						.await
+				}
+			})
 			.await
 			.map_err(|e| Error::from(format!("{e:?}")))?
 			.into_inner();
@@ -1048,8 +1050,8 @@ where
 		let client_state = ClientState::new(
 			self.chain_id.clone(),
 			TrustThreshold::default(),
-			Duration::from_secs(64000),
-			Duration::from_secs(1814400),
+			self.trusting_period,
+			self.unbonding_period,
 			Duration::new(15, 0),
 			latest_height_timestamp.0,
 			ProofSpecs::default(),
@@ -1078,7 +1080,6 @@ where
 
 		let response: Response = loop {
 			let response = self
-				.rpc_client
 				.tx_search(
 					Query::eq("tx.hash", tx_id.hash.to_string()),
 					false,
@@ -1145,7 +1146,6 @@ where
 
 		let response: Response = loop {
 			let response = self
-				.rpc_client
 				.tx_search(
 					Query::eq("tx.hash", tx_id.hash.to_string()),
 					false,
@@ -1213,7 +1213,6 @@ where
 
 		let response: Response = loop {
 			let response = self
-				.rpc_client
 				.tx_search(
 					Query::eq("tx.hash", tx_id.hash.to_string()),
 					false,
@@ -1311,12 +1310,281 @@ where
 
 		Ok(code_id)
 	}
+
+	async fn query_scheduled_upgrade_height(&self) -> Result<Option<Height>, Self::Error> {
+		let response = self
+			.grpc_pool
+			.query(|channel| async move {
+				UpgradeQueryClient::new(channel).current_plan(QueryCurrentPlanRequest {}).await
+			})
+			.await
+			.map_err(|e| Error::from(format!("Failed to query the upgrade plan: {e:?}")))?
+			.into_inner();
+		Ok(response.plan.filter(|plan| plan.height > 0).map(|plan| {
+			Height::new(
+				ChainId::chain_version(self.chain_id.to_string().as_str()),
+				plan.height as u64,
+			)
+		}))
+	}
+
+	async fn query_upgraded_client(
+		&self,
+		upgrade_height: Height,
+	) -> Result<UpgradedClient, Self::Error> {
+		// the states are committed before the upgrade height, their proofs are verified against
+		// the consensus state at the upgrade height
+		let at = upgrade_height
+			.decrement()
+			.map_err(|e| Error::from(format!("Invalid upgrade height {upgrade_height}: {e}")))?;
+		let client_path =
+			ClientUpgradePath::UpgradedClientState(upgrade_height.revision_height).to_string();
+		let (client_state, proof_upgrade_client) = self
+			.query_store_path(SDK_UPGRADE_QUERY_PATH, client_path.into_bytes(), at, true)
+			.await?;
+		let consensus_path =
+			ClientUpgradePath::UpgradedClientConsensusState(upgrade_height.revision_height)
+				.to_string();
+		let (consensus_state, proof_upgrade_consensus_state) = self
+			.query_store_path(SDK_UPGRADE_QUERY_PATH, consensus_path.into_bytes(), at, true)
+			.await?;
+		if client_state.value.is_empty() || consensus_state.value.is_empty() {
+			return Err(Error::from(format!(
+				"No upgraded client committed for the upgrade at {upgrade_height}"
+			)))
+		}
+
+		let client_state = AnyClientState::try_from(Any::decode(&*client_state.value)?)
+			.map_err(|e| Error::from(format!("Invalid upgraded client state: {e:?}")))?;
+		let consensus_state = AnyConsensusState::try_from(Any::decode(&*consensus_state.value)?)
+			.map_err(|e| Error::from(format!("Invalid upgraded consensus state: {e:?}")))?;
+		Ok(UpgradedClient {
+			client_state,
+			consensus_state,
+			proof_upgrade_client,
+			proof_upgrade_consensus_state,
+		})
+	}
+
+	async fn query_incentivized_packets(
+		&self,
+		at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Vec<IncentivizedPacket>, Self::Error> {
+		let request = QueryIncentivizedPacketsForChannelRequest {
+			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
+			port_id: port_id.to_string(),
+			channel_id: channel_id.to_string(),
+			query_height: at.revision_height,
+		};
+		let response = self
+			.grpc_pool
+			.query(|channel| {
+				let request = request.clone();
+				async move {
+					FeeQueryClient::new(channel).incentivized_packets_for_channel(request).await
+				}
+			})
+			.await;
+		let response = match response {
+			Ok(response) => response.into_inner(),
+			// the chain has no fee middleware
+			Err(status) if status.code() == tonic::Code::Unimplemented => return Ok(vec![]),
+			Err(e) =>
+				return Err(Error::from(format!("Failed to query the incentivized packets: {e:?}"))),
+		};
+		Ok(response
+			.incentivized_packets
+			.into_iter()
+			.filter_map(|packet| {
+				let sequence = packet.packet_id?.sequence;
+				let mut incentivized = IncentivizedPacket { sequence, ..Default::default() };
+				for fee in packet.packet_fees.into_iter().filter_map(|packet_fee| packet_fee.fee) {
+					incentivized.recv_fee.extend(fee.recv_fee);
+					incentivized.ack_fee.extend(fee.ack_fee);
+					incentivized.timeout_fee.extend(fee.timeout_fee);
+				}
+				Some(incentivized)
+			})
+			.collect())
+	}
+
+	async fn query_channel_upgrade(
+		&self,
+		at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Option<ChannelUpgrade>, Self::Error> {
+		let path_bytes = channel_upgrade_path(&port_id, &channel_id).into_bytes();
+		let (q, proof) = self.query_path(path_bytes, at, true).await?;
+		if q.value.is_empty() {
+			return Ok(None)
+		}
+		Ok(Some(ChannelUpgrade {
+			upgrade: Upgrade::decode(&*q.value)?,
+			proof,
+			proof_height: at.increment(),
+		}))
+	}
 }
 
 impl<H> CosmosClient<H>
 where
 	H: 'static + Clone + Send + Sync,
 {
+	/// The IBC events of the blocks produced from now on, see [`IbcProvider::ibc_events`].
+	async fn live_ibc_events(&self) -> Pin<Box<dyn Stream<Item = IbcEvent> + Send + 'static>> {
+		if !self.comet_version.is_legacy() {
+			// the Tx events of the newer versions can't be parsed, see [`compat`]
+			let client = self.clone();
+			return compat::poll_new_blocks(
+				self.rpc_http_client.clone(),
+				self.expected_block_time(),
+				1,
+			)
+			.then(move |(from, to)| {
+				let client = client.clone();
+				async move { client.polled_ibc_events(from.value(), to.value()).await }
+			})
+			.map(stream::iter)
+			.flatten()
+			.boxed()
+		}
+		// the subscriptions are renewed when the websocket connection drops, see
+		// [`crate::resubscribe`]
+		let subscription = match self.subscribe_ibc_events(self.rpc_client.clone()).await {
+			Ok(subscription) => subscription,
+			Err(e) => stream::iter(vec![Err(e)]).boxed(),
+		};
+		resubscribing_events(self.clone(), subscription, RESUBSCRIBE_BACKOFF).boxed()
+	}
+
+	/// Subscribes to the IBC events of the chain over `ws_client`. The stream yields an error
+	/// and ends when the subscriptions are lost.
+	pub(crate) async fn subscribe_ibc_events(
+		&self,
+		ws_client: WebSocketClient,
+	) -> Result<BoxStream<'static, Result<IbcEvent, Error>>, Error> {
+		// Like what `EventMonitor::subscribe()` does in `hermes`
+		let query_all = vec![
+			Query::from(EventType::NewBlock),
+			Query::eq("message.module", "ibc_client"),
+			Query::eq("message.module", "ibc_connection"),
+			Query::eq("message.module", "ibc_channel"),
+		];
+		let mut subscriptions = vec![];
+		for query in &query_all {
+			let subscription = ws_client
+				.subscribe(query.clone())
+				.await
+				.map_err(|e| Error::from(format!("Web Socket Client Error {e:?}")))?;
+			subscriptions.push(subscription);
+		}
+		// Collect IBC events from each RPC event, Like what `stream_batches()` does in `hermes`
+		let all_subs: Box<dyn Stream<Item = Result<Event, RpcError>> + Send + Sync + Unpin> =
+			Box::new(select_all(subscriptions));
+		let chain_id = self.chain_id.clone();
+		let events = all_subs
+			.map(move |event| {
+				// Like what `get_all_events()` does in `hermes`
+				let mut events_with_height: Vec<IbcEventWithHeight> = vec![];
+				let Event { data, events: _, query } = match event {
+					Ok(event) => event,
+					Err(e) => {
+						let e = Error::from(format!("Web Socket Client Error {e:?}"));
+						return stream::iter(vec![Err(e)])
+					},
+				};
+				match data {
+					EventData::NewBlock { block, .. }
+						if query == Query::from(EventType::NewBlock).to_string() =>
+					{
+						let height = Height::new(
+							ChainId::chain_version(chain_id.to_string().as_str()),
+							u64::from(block.as_ref().ok_or("tx.height").unwrap().header.height),
+						);
+						events_with_height.push(IbcEventWithHeight::new(
+							ClientEvents::NewBlock::new(height).into(),
+							height,
+						));
+					},
+					EventData::Tx { tx_result } => {
+						let height = Height::new(
+							ChainId::chain_version(chain_id.to_string().as_str()),
+							tx_result.height as u64,
+						);
+						for abci_event in &tx_result.result.events {
+							if let Ok(ibc_event) = ibc_event_try_from_abci_event(abci_event, height)
+							{
+								log::debug!(target: "hyperspace_cosmos", "Retrieved event: {}, query: {}, parsed: {:?}", abci_event.kind, query, ibc_event);
+								let is_client_event = query == Query::eq("message.module", "ibc_client").to_string() &&
+									event_is_type_client(&ibc_event);
+								let is_connection_event = (query ==
+									Query::eq("message.module", "ibc_connection").to_string() ||
+									query ==
+										Query::eq("message.module", "ibc_client").to_string()) &&
+									event_is_type_connection(&ibc_event);
+								let is_channel_event = query ==
+									Query::eq("message.module", "ibc_channel").to_string() &&
+									event_is_type_channel(&ibc_event);
+								if is_client_event || is_connection_event || is_channel_event {
+									events_with_height
+										.push(IbcEventWithHeight::new(ibc_event, height));
+								} else {
+									log::debug!(target: "hyperspace_cosmos", "The event is unknown");
+								}
+							} else {
+								log::debug!(target: "hyperspace_cosmos", "Failed to parse event {:?}", abci_event);
+							}
+						}
+					},
+					_ => {},
+				}
+				stream::iter(events_with_height.into_iter().map(|e| Ok(e.event)).collect::<Vec<_>>())
+			})
+			.flatten()
+			.boxed();
+		Ok(events)
+	}
+
+	/// The IBC events of the blocks from `from` to `to`, each block starting with a `NewBlock`
+	/// event, see [`IbcProvider::ibc_events`]. The blocks whose results can't be queried are
+	/// skipped.
+	async fn polled_ibc_events(&self, from: u64, to: u64) -> Vec<IbcEvent> {
+		let revision = ChainId::chain_version(self.chain_id.to_string().as_str());
+		let mut events = vec![];
+		for height in from..=to {
+			let tm_height = TmHeight::try_from(height).expect("the polled heights are valid; qed");
+			let block_results = match self.block_results(tm_height).await {
+				Ok(block_results) => block_results,
+				Err(e) => {
+					log::warn!(target: "hyperspace_cosmos", "Failed to query block result for height {height}: {e:?}");
+					continue
+				},
+			};
+			let ibc_height = Height::new(revision, height);
+			events.push(ClientEvents::NewBlock::new(ibc_height).into());
+			let tx_events = block_results
+				.txs_results
+				.unwrap_or_default()
+				.into_iter()
+				.flat_map(|tx| tx.events);
+			for abci_event in tx_events {
+				match ibc_event_try_from_abci_event(&abci_event, ibc_height) {
+					Ok(ibc_event)
+						if event_is_type_client(&ibc_event) ||
+							event_is_type_connection(&ibc_event) ||
+							event_is_type_channel(&ibc_event) =>
+						events.push(ibc_event),
+					_ =>
+						log::debug!(target: "hyperspace_cosmos", "Failed to parse event {:?}", abci_event),
+				}
+			}
+		}
+		events
+	}
+
 	async fn parse_ibc_events_at<C: Chain>(
 		&self,
 		counterparty: &C,
@@ -1325,11 +1593,7 @@ where
 	) -> Result<Vec<IbcEvent>, <Self as IbcProvider>::Error> {
 		let mut ibc_events = Vec::new();
 
-		let block_results = self
-			.rpc_http_client
-			.block_results(TmHeight::try_from(height)?)
-			.await
-			.map_err(|e| {
+		let block_results = self.block_results(TmHeight::try_from(height)?).await.map_err(|e| {
 			Error::from(format!("Failed to query block result for height {height:?}: {e:?}"))
 		})?;
 
@@ -1406,7 +1670,6 @@ impl<H: Clone + Send + Sync + 'static> CosmosClient<H> {
 
 		let response: Response = loop {
 			let response = self
-				.rpc_http_client
 				.tx_search(
 					Query::eq("tx.hash", tx_id.hash.to_string()),
 					false,
@@ -1444,7 +1707,13 @@ impl<H: Clone + Send + Sync + 'static> CosmosClient<H> {
 	}
 }
 
-fn increment_proof_height(
+/// The sequences in the order they first appear in.
+fn unique(seqs: &[u64]) -> impl Iterator<Item = u64> + '_ {
+	let mut seen = HashSet::new();
+	seqs.iter().copied().filter(move |seq| seen.insert(*seq))
+}
+
+pub(crate) fn increment_proof_height(
 	height: Option<ibc_proto::ibc::core::client::v1::Height>,
 ) -> Option<ibc_proto::ibc::core::client::v1::Height> {
 	height.map(|height| ibc_proto::ibc::core::client::v1::Height {
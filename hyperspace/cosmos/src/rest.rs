@@ -0,0 +1,314 @@
+//! Fallback of the gRPC queries to the LCD REST API.
+//!
+//! Many public endpoints of the cosmos chains only expose the LCD, the REST gateway of the gRPC
+//! services of the node. With `rest_url` in the config, the queries of the IBC module the client
+//! sends over gRPC (the listings of the client states, the channels, the packet commitments and
+//! acknowledgements, and the unreceived packets and acknowledgements) are sent to the LCD once
+//! they failed on the gRPC endpoints of the pool, see [`CosmosClient::rest_fallback`]. The gRPC
+//! endpoint is then connected lazily, so that the client starts without it.
+//!
+//! The client and consensus states, and the commitments and acknowledgements of the packets, are
+//! queried with their proofs through the ABCI queries of the RPC endpoint, which the LCD can't
+//! prove, so they need no fallback.
+
+use crate::{client::CosmosClient, error::Error, grpc_pool::is_unavailable};
+use futures::Future;
+use hyper::{client::HttpConnector, Body, Client, Uri};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use ibc::core::ics24_host::identifier::{ChannelId, ConnectionId, PortId};
+use ibc_proto::ibc::core::channel::v1::{
+	Counterparty, IdentifiedChannel, Order as ChannelOrder, State as ChannelState,
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use std::time::Duration;
+use tendermint_rpc::Url;
+use tonic::{Code, Status};
+
+/// Time after which a query to the LCD fails
+const REST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pagination of the listings, as large as the one of the gRPC queries
+const PAGINATION: &str = "pagination.limit=4294967295";
+
+/// Deserializes the 64-bit integers of the proto3 JSON encoding, which are strings.
+fn from_str_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+	String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+}
+
+fn from_str_u64s<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+	Vec::<String>::deserialize(deserializer)?
+		.into_iter()
+		.map(|s| s.parse().map_err(serde::de::Error::custom))
+		.collect()
+}
+
+#[derive(Deserialize)]
+struct PacketState {
+	#[serde(deserialize_with = "from_str_u64")]
+	sequence: u64,
+}
+
+#[derive(Deserialize)]
+struct PacketCommitmentsResponse {
+	#[serde(default)]
+	commitments: Vec<PacketState>,
+}
+
+#[derive(Deserialize)]
+struct PacketAcknowledgementsResponse {
+	#[serde(default)]
+	acknowledgements: Vec<PacketState>,
+}
+
+#[derive(Deserialize)]
+struct SequencesResponse {
+	#[serde(default, deserialize_with = "from_str_u64s")]
+	sequences: Vec<u64>,
+}
+
+#[derive(Deserialize)]
+struct IdentifiedClientState {
+	client_id: String,
+}
+
+#[derive(Deserialize)]
+struct ClientStatesResponse {
+	#[serde(default)]
+	client_states: Vec<IdentifiedClientState>,
+}
+
+#[derive(Deserialize)]
+struct ChannelCounterparty {
+	port_id: String,
+	#[serde(default)]
+	channel_id: String,
+}
+
+#[derive(Deserialize)]
+struct Channel {
+	state: String,
+	ordering: String,
+	counterparty: ChannelCounterparty,
+	#[serde(default)]
+	connection_hops: Vec<String>,
+	#[serde(default)]
+	version: String,
+	port_id: String,
+	channel_id: String,
+}
+
+impl From<Channel> for IdentifiedChannel {
+	fn from(channel: Channel) -> Self {
+		IdentifiedChannel {
+			state: ChannelState::from_str_name(&channel.state).map_or(0, |state| state as i32),
+			ordering: ChannelOrder::from_str_name(&channel.ordering)
+				.map_or(0, |ordering| ordering as i32),
+			counterparty: Some(Counterparty {
+				port_id: channel.counterparty.port_id,
+				channel_id: channel.counterparty.channel_id,
+			}),
+			connection_hops: channel.connection_hops,
+			version: channel.version,
+			port_id: channel.port_id,
+			channel_id: channel.channel_id,
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct ChannelsResponse {
+	#[serde(default)]
+	channels: Vec<Channel>,
+}
+
+#[derive(Deserialize)]
+struct StakingParams {
+	/// Duration in seconds with a `s` suffix, e.g. `1814400s`
+	unbonding_time: String,
+}
+
+#[derive(Deserialize)]
+struct StakingParamsResponse {
+	params: StakingParams,
+}
+
+/// Client of the LCD of the chain.
+#[derive(Clone)]
+pub struct RestClient {
+	url: String,
+	client: Client<HttpsConnector<HttpConnector>, Body>,
+}
+
+impl RestClient {
+	pub fn new(url: &Url) -> Self {
+		let connector = HttpsConnectorBuilder::new()
+			.with_webpki_roots()
+			.https_or_http()
+			.enable_http1()
+			.build();
+		Self {
+			url: url.to_string().trim_end_matches('/').to_string(),
+			client: Client::builder().build(connector),
+		}
+	}
+
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+		let uri: Uri = format!("{}{path}", self.url)
+			.parse()
+			.map_err(|e| Error::from(format!("Invalid LCD query {path}: {e}")))?;
+		let response = tokio::time::timeout(REST_TIMEOUT, self.client.get(uri))
+			.await
+			.map_err(|_| Error::RpcError(format!("LCD query {path} timed out")))?
+			.map_err(|e| Error::RpcError(format!("LCD query {path} failed: {e}")))?;
+		let status = response.status();
+		let body = hyper::body::to_bytes(response.into_body())
+			.await
+			.map_err(|e| Error::RpcError(format!("LCD query {path} failed: {e}")))?;
+		if !status.is_success() {
+			return Err(Error::RpcError(format!(
+				"LCD query {path} failed with {status}: {}",
+				String::from_utf8_lossy(&body)
+			)))
+		}
+		serde_json::from_slice(&body)
+			.map_err(|e| Error::from(format!("Invalid response to the LCD query {path}: {e}")))
+	}
+
+	/// Sequences of the packets of the channel that have a commitment.
+	pub async fn packet_commitments(
+		&self,
+		channel_id: &ChannelId,
+		port_id: &PortId,
+	) -> Result<Vec<u64>, Error> {
+		let response: PacketCommitmentsResponse = self
+			.get(&format!(
+				"/ibc/core/channel/v1/channels/{channel_id}/ports/{port_id}/packet_commitments?{PAGINATION}"
+			))
+			.await?;
+		Ok(response.commitments.into_iter().map(|state| state.sequence).collect())
+	}
+
+	/// Sequences of the packets of the channel that have an acknowledgement.
+	pub async fn packet_acknowledgements(
+		&self,
+		channel_id: &ChannelId,
+		port_id: &PortId,
+	) -> Result<Vec<u64>, Error> {
+		let response: PacketAcknowledgementsResponse = self
+			.get(&format!(
+				"/ibc/core/channel/v1/channels/{channel_id}/ports/{port_id}/packet_acknowledgements?{PAGINATION}"
+			))
+			.await?;
+		Ok(response.acknowledgements.into_iter().map(|state| state.sequence).collect())
+	}
+
+	/// The sequences of the given packets sent by the counterparty that weren't received.
+	pub async fn unreceived_packets(
+		&self,
+		channel_id: &ChannelId,
+		port_id: &PortId,
+		seqs: &[u64],
+	) -> Result<Vec<u64>, Error> {
+		if seqs.is_empty() {
+			return Ok(vec![])
+		}
+		let response: SequencesResponse = self
+			.get(&format!(
+				"/ibc/core/channel/v1/channels/{channel_id}/ports/{port_id}/packet_commitments/{}/unreceived_packets",
+				join(seqs)
+			))
+			.await?;
+		Ok(response.sequences)
+	}
+
+	/// The sequences of the given packets whose acknowledgements weren't received.
+	pub async fn unreceived_acks(
+		&self,
+		channel_id: &ChannelId,
+		port_id: &PortId,
+		seqs: &[u64],
+	) -> Result<Vec<u64>, Error> {
+		if seqs.is_empty() {
+			return Ok(vec![])
+		}
+		let response: SequencesResponse = self
+			.get(&format!(
+				"/ibc/core/channel/v1/channels/{channel_id}/ports/{port_id}/packet_commitments/{}/unreceived_acks",
+				join(seqs)
+			))
+			.await?;
+		Ok(response.sequences)
+	}
+
+	/// Identifiers of the clients hosted by the chain.
+	pub async fn client_ids(&self) -> Result<Vec<String>, Error> {
+		let response: ClientStatesResponse =
+			self.get(&format!("/ibc/core/client/v1/client_states?{PAGINATION}")).await?;
+		Ok(response.client_states.into_iter().map(|state| state.client_id).collect())
+	}
+
+	/// The channels of the chain.
+	pub async fn channels(&self) -> Result<Vec<IdentifiedChannel>, Error> {
+		let response: ChannelsResponse =
+			self.get(&format!("/ibc/core/channel/v1/channels?{PAGINATION}")).await?;
+		Ok(response.channels.into_iter().map(Into::into).collect())
+	}
+
+	/// Unbonding period of the chain, from its staking params.
+	pub async fn unbonding_period(&self) -> Result<Duration, Error> {
+		let response: StakingParamsResponse = self.get("/cosmos/staking/v1beta1/params").await?;
+		let unbonding_time = response.params.unbonding_time;
+		unbonding_time
+			.strip_suffix('s')
+			.and_then(|secs| secs.parse::<f64>().ok())
+			.and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+			.ok_or_else(|| Error::from(format!("Invalid unbonding time {unbonding_time}")))
+	}
+
+	/// The channels of the given connection.
+	pub async fn connection_channels(
+		&self,
+		connection_id: &ConnectionId,
+	) -> Result<Vec<IdentifiedChannel>, Error> {
+		let response: ChannelsResponse = self
+			.get(&format!("/ibc/core/channel/v1/connections/{connection_id}/channels?{PAGINATION}"))
+			.await?;
+		Ok(response.channels.into_iter().map(Into::into).collect())
+	}
+}
+
+fn join(seqs: &[u64]) -> String {
+	seqs.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+}
+
+impl<H> CosmosClient<H> {
+	/// Sends a query that failed on the gRPC endpoints to the LCD, if the client has one and the
+	/// failure is one of the endpoints rather than of the query.
+	pub(crate) async fn rest_fallback<'a, T, F, Fut>(
+		&'a self,
+		status: Status,
+		query: F,
+	) -> Result<T, Error>
+	where
+		F: FnOnce(&'a RestClient) -> Fut,
+		Fut: Future<Output = Result<T, Error>>,
+	{
+		match &self.rest_client {
+			Some(rest_client)
+				if is_unavailable(&status) || status.code() == Code::Unimplemented =>
+			{
+				log::debug!(
+					target: "hyperspace_cosmos",
+					"gRPC query to {} failed, falling back to the LCD {}: {status:?}",
+					self.name, rest_client.url()
+				);
+				query(rest_client).await
+			},
+			_ => Err(Error::from(format!("{status:?}"))),
+		}
+	}
+}
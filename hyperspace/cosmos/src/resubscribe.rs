@@ -0,0 +1,291 @@
+//! Resubscription of the websocket event stream after a disconnect.
+//!
+//! The IBC events of the chain are subscribed to over the websocket of the node, whose
+//! subscriptions end when the connection drops. The event stream then reconnects and subscribes
+//! again, retrying every [`RESUBSCRIBE_BACKOFF`] until it succeeds, and backfills the events of the
+//! blocks produced while it was disconnected from the indexer of the node (see
+//! [`crate::backfill`]) before it resumes with the new subscriptions. The events of the blocks
+//! already backfilled are dropped from the new subscriptions, so that no event is streamed twice.
+
+use crate::{client::CosmosClient, error::Error};
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use ibc::events::IbcEvent;
+use primitives::IbcProvider;
+use std::time::Duration;
+use tendermint_rpc::WebSocketClient;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Delay between two attempts to subscribe again
+pub const RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Source of the events of a chain, see [`resubscribing_events`].
+#[async_trait]
+pub trait EventSource: Send + Sync + 'static {
+	/// Subscribes to the events of the chain, on a new connection if `reconnect` is set. The
+	/// stream ends, or yields an error, when the subscriptions are lost.
+	async fn subscribe(
+		&self,
+		reconnect: bool,
+	) -> Result<BoxStream<'static, Result<IbcEvent, Error>>, Error>;
+
+	/// Latest height of the chain.
+	async fn latest_height(&self) -> Result<u64, Error>;
+
+	/// The events of the blocks from `from` to `to`.
+	async fn backfill(&self, from: u64, to: u64) -> Result<Vec<IbcEvent>, Error>;
+}
+
+#[async_trait]
+impl<H> EventSource for CosmosClient<H>
+where
+	H: 'static + Clone + Send + Sync,
+{
+	async fn subscribe(
+		&self,
+		reconnect: bool,
+	) -> Result<BoxStream<'static, Result<IbcEvent, Error>>, Error> {
+		if !reconnect {
+			return self.subscribe_ibc_events(self.rpc_client.clone()).await
+		}
+		let websocket_url = self.endpoints.lock().unwrap().websocket_url.clone();
+		let (ws_client, ws_driver) = WebSocketClient::new(websocket_url)
+			.await
+			.map_err(|e| Error::RpcError(format!("{:?}", e)))?;
+		self.join_handles.lock().await.push(tokio::spawn(ws_driver.run()));
+		self.subscribe_ibc_events(ws_client).await
+	}
+
+	async fn latest_height(&self) -> Result<u64, Error> {
+		let (height, _) = self.latest_height_and_timestamp().await?;
+		Ok(height.revision_height)
+	}
+
+	async fn backfill(&self, from: u64, to: u64) -> Result<Vec<IbcEvent>, Error> {
+		self.backfill_ibc_events(from, to).await
+	}
+}
+
+/// Streams the events of `source`, subscribing to them again and backfilling the missed blocks
+/// whenever its subscriptions are lost. The stream starts with the given subscription.
+pub fn resubscribing_events<S: EventSource>(
+	source: S,
+	subscription: BoxStream<'static, Result<IbcEvent, Error>>,
+	backoff: Duration,
+) -> ReceiverStream<IbcEvent> {
+	let (tx, rx) = mpsc::channel(64);
+	tokio::spawn(async move {
+		let mut subscription = Some(subscription);
+		// latest height streamed, and latest height backfilled after a disconnect
+		let mut last_height = None::<u64>;
+		let mut backfilled_to = None::<u64>;
+		loop {
+			let mut events = match subscription.take() {
+				Some(events) => events,
+				None => match source.subscribe(true).await {
+					Ok(events) => events,
+					Err(e) => {
+						log::warn!(target: "hyperspace_cosmos", "Failed to subscribe to the events again: {e:?}");
+						tokio::time::sleep(backoff).await;
+						continue
+					},
+				},
+			};
+			if let Some(last) = last_height {
+				match backfill_gap(&source, last).await {
+					Ok((to, backfilled)) => {
+						for event in backfilled {
+							if tx.send(event).await.is_err() {
+								return
+							}
+						}
+						last_height = Some(to);
+						backfilled_to = Some(to);
+					},
+					Err(e) => log::error!(
+						target: "hyperspace_cosmos",
+						"Failed to backfill the events of the blocks after {last}: {e:?}"
+					),
+				}
+			}
+			loop {
+				// the task stops with the stream
+				let event = tokio::select! {
+					event = events.next() => event,
+					_ = tx.closed() => return,
+				};
+				let Some(event) = event else { break };
+				let event = match event {
+					Ok(event) => event,
+					Err(e) => {
+						log::warn!(target: "hyperspace_cosmos", "Event subscription failed: {e:?}");
+						break
+					},
+				};
+				let height = event.height().revision_height;
+				if backfilled_to.map_or(false, |to| height <= to) {
+					continue
+				}
+				last_height = Some(last_height.map_or(height, |last| last.max(height)));
+				if tx.send(event).await.is_err() {
+					return
+				}
+			}
+			log::warn!(target: "hyperspace_cosmos", "Event subscription lost, subscribing again");
+		}
+	});
+	ReceiverStream::new(rx)
+}
+
+/// The events of the blocks produced after `last`, with the latest height.
+async fn backfill_gap<S: EventSource>(
+	source: &S,
+	last: u64,
+) -> Result<(u64, Vec<IbcEvent>), Error> {
+	let latest = source.latest_height().await?;
+	if latest <= last {
+		return Ok((last, vec![]))
+	}
+	log::info!(target: "hyperspace_cosmos", "Backfilling the events of the blocks {} to {latest}", last + 1);
+	Ok((latest, source.backfill(last + 1, latest).await?))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::stream;
+	use ibc::{core::ics02_client::events::NewBlock, events::IbcEvent, Height};
+	use std::sync::{Arc, Mutex};
+
+	fn new_block(height: u64) -> IbcEvent {
+		IbcEvent::NewBlock(NewBlock::new(Height::new(0, height)))
+	}
+
+	fn heights(events: &[IbcEvent]) -> Vec<u64> {
+		events.iter().map(|event| event.height().revision_height).collect()
+	}
+
+	/// A chain whose subscriptions are scripted, each ending with a disconnect.
+	#[derive(Clone)]
+	struct MockSource {
+		latest_height: Arc<Mutex<u64>>,
+		/// Latest heights returned before `latest_height`, one per query
+		latest_heights: Arc<Mutex<Vec<u64>>>,
+		subscriptions: Arc<Mutex<Vec<Vec<Result<IbcEvent, Error>>>>>,
+		backfills: Arc<Mutex<Vec<(u64, u64)>>>,
+		failed_subscriptions: Arc<Mutex<u32>>,
+	}
+
+	impl MockSource {
+		fn new(latest_height: u64, subscriptions: Vec<Vec<Result<IbcEvent, Error>>>) -> Self {
+			Self {
+				latest_height: Arc::new(Mutex::new(latest_height)),
+				latest_heights: Default::default(),
+				subscriptions: Arc::new(Mutex::new(subscriptions)),
+				backfills: Default::default(),
+				failed_subscriptions: Default::default(),
+			}
+		}
+	}
+
+	#[async_trait]
+	impl EventSource for MockSource {
+		async fn subscribe(
+			&self,
+			reconnect: bool,
+		) -> Result<BoxStream<'static, Result<IbcEvent, Error>>, Error> {
+			assert!(reconnect);
+			let mut failed = self.failed_subscriptions.lock().unwrap();
+			if *failed > 0 {
+				*failed -= 1;
+				return Err(Error::from("connection refused".to_string()))
+			}
+			let mut subscriptions = self.subscriptions.lock().unwrap();
+			if subscriptions.is_empty() {
+				// the last subscription stays open
+				return Ok(stream::pending().boxed())
+			}
+			Ok(stream::iter(subscriptions.remove(0)).boxed())
+		}
+
+		async fn latest_height(&self) -> Result<u64, Error> {
+			let mut latest_heights = self.latest_heights.lock().unwrap();
+			if !latest_heights.is_empty() {
+				return Ok(latest_heights.remove(0))
+			}
+			Ok(*self.latest_height.lock().unwrap())
+		}
+
+		async fn backfill(&self, from: u64, to: u64) -> Result<Vec<IbcEvent>, Error> {
+			self.backfills.lock().unwrap().push((from, to));
+			Ok((from..=to).map(new_block).collect())
+		}
+	}
+
+	async fn collect(
+		source: MockSource,
+		first: Vec<Result<IbcEvent, Error>>,
+		count: usize,
+	) -> Vec<IbcEvent> {
+		let events =
+			resubscribing_events(source, stream::iter(first).boxed(), Duration::from_millis(1));
+		tokio::time::timeout(Duration::from_secs(5), events.take(count).collect())
+			.await
+			.expect("the events are streamed")
+	}
+
+	#[tokio::test]
+	async fn backfills_the_blocks_missed_during_a_disconnect() {
+		let source = MockSource::new(5, vec![vec![Ok(new_block(6)), Ok(new_block(7))]]);
+		let events = collect(source.clone(), vec![Ok(new_block(1)), Ok(new_block(2))], 7).await;
+		assert_eq!(heights(&events), vec![1, 2, 3, 4, 5, 6, 7]);
+		assert_eq!(*source.backfills.lock().unwrap(), vec![(3, 5)]);
+	}
+
+	#[tokio::test]
+	async fn resubscribes_and_backfills_each_time_the_subscription_is_cut() {
+		let source = MockSource::new(
+			8,
+			vec![
+				vec![
+					Ok(new_block(5)),
+					Ok(new_block(6)),
+					Err(Error::from("websocket closed".to_string())),
+					Ok(new_block(99)),
+				],
+				vec![Ok(new_block(9))],
+			],
+		);
+		*source.latest_heights.lock().unwrap() = vec![4];
+		let events = collect(source.clone(), vec![Ok(new_block(1)), Ok(new_block(2))], 9).await;
+		assert_eq!(heights(&events), vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+		// both cuts were followed by a new subscription and the backfill of the gap
+		assert!(source.subscriptions.lock().unwrap().is_empty());
+		assert_eq!(*source.backfills.lock().unwrap(), vec![(3, 4), (7, 8)]);
+	}
+
+	#[tokio::test]
+	async fn drops_the_backfilled_events_of_the_new_subscription() {
+		let source =
+			MockSource::new(4, vec![vec![Ok(new_block(3)), Ok(new_block(4)), Ok(new_block(5))]]);
+		let events = collect(source, vec![Ok(new_block(1)), Ok(new_block(2))], 5).await;
+		assert_eq!(heights(&events), vec![1, 2, 3, 4, 5]);
+	}
+
+	#[tokio::test]
+	async fn resubscribes_after_a_failed_subscription() {
+		let source = MockSource::new(2, vec![vec![], vec![Ok(new_block(3))]]);
+		*source.failed_subscriptions.lock().unwrap() = 2;
+		let first = vec![
+			Ok(new_block(1)),
+			Err(Error::from("websocket closed".to_string())),
+			Ok(new_block(9)),
+		];
+		let events = collect(source.clone(), first, 3).await;
+		// the events after the error are lost with the subscription, and backfilled
+		assert_eq!(heights(&events), vec![1, 2, 3]);
+		assert_eq!(*source.backfills.lock().unwrap(), vec![(2, 2)]);
+		assert_eq!(*source.failed_subscriptions.lock().unwrap(), 0);
+	}
+}
@@ -0,0 +1,158 @@
+//! Allocation of the sequences of the accounts signing in this process.
+//!
+//! The sequence of an account only advances on the chain once its transaction is included, so
+//! querying it before each transaction serializes them. The [`SequenceManager`] of an account
+//! keeps its next sequence instead: it's held while a transaction is simulated, signed and
+//! broadcast, advanced once the transaction passed `CheckTx`, and released before the inclusion
+//! of the transaction is awaited, so that the next one is broadcast meanwhile. The managers are
+//! shared by the clients relaying with the same account, see [`sequence_manager`].
+//!
+//! When a transaction is rejected with an `account sequence mismatch`, e.g. because a previous
+//! one was dropped from the mempool or the account was used by another process, the next
+//! sequence is resynchronized to the one the chain expects.
+
+use crate::error::Error;
+use ibc::core::ics24_host::identifier::ChainId;
+use ibc_proto::cosmos::auth::v1beta1::BaseAccount;
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, OnceLock},
+};
+use tokio::sync::{Mutex as TokioMutex, MutexGuard};
+
+const SEQUENCE_MISMATCH: &str = "account sequence mismatch, expected ";
+
+static SEQUENCE_MANAGERS: OnceLock<Mutex<HashMap<(ChainId, String), Arc<SequenceManager>>>> =
+	OnceLock::new();
+
+/// Sequence manager of an account, shared by the clients signing with it.
+pub fn sequence_manager(chain_id: &ChainId, account: &str) -> Arc<SequenceManager> {
+	SEQUENCE_MANAGERS
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap()
+		.entry((chain_id.clone(), account.to_string()))
+		.or_default()
+		.clone()
+}
+
+/// Next sequence of an account, with its account number.
+#[derive(Debug, Default)]
+pub struct SequenceManager {
+	account: TokioMutex<Option<BaseAccount>>,
+}
+
+impl SequenceManager {
+	/// Waits for the next sequence, held until the guard is dropped.
+	pub async fn lock(&self) -> SequenceGuard<'_> {
+		SequenceGuard(self.account.lock().await)
+	}
+}
+
+pub struct SequenceGuard<'a>(MutexGuard<'a, Option<BaseAccount>>);
+
+impl SequenceGuard<'_> {
+	/// The account with its next sequence, if it's known.
+	pub fn account(&self) -> Option<&BaseAccount> {
+		self.0.as_ref()
+	}
+
+	/// Sets the account queried from the chain.
+	pub fn set(&mut self, account: BaseAccount) {
+		*self.0 = Some(account);
+	}
+
+	/// Advances the sequence, once a transaction was accepted with it.
+	pub fn advance(&mut self) {
+		if let Some(account) = self.0.as_mut() {
+			account.sequence += 1;
+		}
+	}
+
+	/// Resynchronizes the sequence with the one the chain expects if the transaction was
+	/// rejected because of a sequence mismatch. The other errors leave it as is, since the
+	/// rejected transactions don't use up their sequence.
+	pub fn resync(&mut self, error: &Error) {
+		let Some(expected) = expected_sequence(&error.to_string()) else { return };
+		if let Some(account) = self.0.as_mut() {
+			log::info!(
+				target: "hyperspace_cosmos",
+				"Resynchronizing the sequence of {} from {} to {expected}", account.address, account.sequence
+			);
+			account.sequence = expected;
+		}
+	}
+}
+
+/// Parses the sequence the chain expected from an `account sequence mismatch` error, e.g.
+/// `account sequence mismatch, expected 12, got 11: incorrect account sequence`.
+fn expected_sequence(error: &str) -> Option<u64> {
+	let (_, rest) = error.split_once(SEQUENCE_MISMATCH)?;
+	let digits = rest.split(|c: char| !c.is_ascii_digit()).next()?;
+	digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn account(sequence: u64) -> BaseAccount {
+		BaseAccount { address: "cosmos1relayer".to_string(), sequence, ..Default::default() }
+	}
+
+	#[test]
+	fn parses_the_expected_sequence() {
+		assert_eq!(
+			expected_sequence(
+				"account sequence mismatch, expected 12, got 11: incorrect account sequence"
+			),
+			Some(12)
+		);
+		assert_eq!(
+			expected_sequence(
+				"Rpc client error: check tx failed: account sequence mismatch, expected 7, got 9"
+			),
+			Some(7)
+		);
+		assert_eq!(expected_sequence("account sequence mismatch, expected 3"), Some(3));
+	}
+
+	#[test]
+	fn ignores_the_other_errors() {
+		assert_eq!(expected_sequence("insufficient fees; got: 10stake required: 20stake"), None);
+		assert_eq!(expected_sequence("account sequence mismatch, expected , got 11"), None);
+		assert_eq!(expected_sequence(""), None);
+	}
+
+	#[tokio::test]
+	async fn resyncs_on_a_sequence_mismatch() {
+		let manager = SequenceManager::default();
+		let mut guard = manager.lock().await;
+		guard.set(account(11));
+		guard.resync(&Error::from(
+			"account sequence mismatch, expected 14, got 11: incorrect account sequence"
+				.to_string(),
+		));
+		assert_eq!(guard.account().unwrap().sequence, 14);
+		// the sequence can also move back, e.g. once a transaction was dropped from the mempool
+		guard.resync(&Error::from("account sequence mismatch, expected 12, got 14".to_string()));
+		assert_eq!(guard.account().unwrap().sequence, 12);
+	}
+
+	#[tokio::test]
+	async fn keeps_the_sequence_on_the_other_errors() {
+		let manager = SequenceManager::default();
+		let mut guard = manager.lock().await;
+		guard.set(account(11));
+		guard.resync(&Error::from("out of gas in location: WriteFlat".to_string()));
+		assert_eq!(guard.account().unwrap().sequence, 11);
+	}
+
+	#[tokio::test]
+	async fn resync_without_an_account_is_a_no_op() {
+		let manager = SequenceManager::default();
+		let mut guard = manager.lock().await;
+		guard.resync(&Error::from("account sequence mismatch, expected 3, got 2".to_string()));
+		assert!(guard.account().is_none());
+	}
+}
@@ -0,0 +1,142 @@
+//! Signers of the cosmos transactions.
+//!
+//! The transactions are signed in `SIGN_MODE_DIRECT`, over the protobuf encoding of their
+//! `SignDoc`. A [`TxSigner`] signs asynchronously, so that it can wait on a party outside of the
+//! relayer, e.g. a device waiting for the confirmation of its operator. Since a signature may
+//! then take a while, only the broadcast transactions are signed: the simulations and the size
+//! estimations, which the chains don't verify the signatures of, carry a
+//! [`PLACEHOLDER_SIGNATURE`] instead.
+//!
+//! With a `remote_signer` in the config, the key of the relayer isn't in the config at all: the
+//! transactions are signed by a [`RemoteTxSigner`], which requests the signatures from the
+//! signing service holding the key (see [`primitives::remote_signer`]).
+
+use crate::{
+	encode::{encode_key_bytes, encode_signer_info},
+	error::Error,
+	key_provider::{KeyEntry, KeyType, SECP256K1_PUB_KEY_TYPE_URL},
+};
+use async_trait::async_trait;
+use ibc_proto::cosmos::tx::v1beta1::SignerInfo;
+use k256::ecdsa::{
+	signature::{DigestSigner as _, Signer as _},
+	Signature, SigningKey,
+};
+use primitives::remote_signer::{RemoteSigner, RemoteSignerConfig, SignatureScheme};
+use sha3::{Digest, Keccak256};
+use std::sync::Arc;
+
+/// Signature of the transactions that are only simulated or measured, as long as a secp256k1
+/// signature.
+pub const PLACEHOLDER_SIGNATURE: [u8; 64] = [0; 64];
+
+#[async_trait]
+pub trait TxSigner: Send + Sync + 'static {
+	/// Compressed secp256k1 public key of the signer
+	fn public_key(&self) -> Vec<u8>;
+
+	/// Type of the public key in the signer infos of the transactions
+	fn public_key_type_url(&self) -> &str {
+		SECP256K1_PUB_KEY_TYPE_URL
+	}
+
+	/// Signer info of the transactions with the given account sequence
+	fn signer_info(&self, sequence: u64) -> Result<SignerInfo, Error> {
+		let pk_bytes = encode_key_bytes(self.public_key())?;
+		encode_signer_info(sequence, self.public_key_type_url(), pk_bytes)
+	}
+
+	/// Signature of the transactions that are simulated or measured
+	fn placeholder_signature(&self) -> Vec<u8> {
+		PLACEHOLDER_SIGNATURE.to_vec()
+	}
+
+	/// Signs the protobuf encoding of a `SignDoc`, returning the 64 bytes of the signature
+	async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>, Error>;
+}
+
+#[async_trait]
+impl TxSigner for KeyEntry {
+	fn public_key(&self) -> Vec<u8> {
+		self.public_key.public_key().to_bytes().as_slice().to_vec()
+	}
+
+	fn public_key_type_url(&self) -> &str {
+		self.key_type.pub_key_type_url()
+	}
+
+	async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>, Error> {
+		let private_key = self.private_key.as_ref().ok_or_else(|| {
+			Error::from(format!("The key of {} is held by a remote signer", self.account))
+		})?;
+		let private_key_bytes = private_key.private_key().to_bytes();
+		let signing_key = SigningKey::from_bytes(private_key_bytes.as_slice())
+			.map_err(|e| Error::from(e.to_string()))?;
+		// the Ethereum keys sign the Keccak-256 hash of the sign doc, see [`crate::ethermint`]
+		let signature: Signature = if self.key_type.is_ethereum() {
+			signing_key.sign_digest(Keccak256::new_with_prefix(&sign_doc))
+		} else {
+			signing_key.sign(&sign_doc)
+		};
+		Ok(signature.as_ref().to_vec())
+	}
+}
+
+/// Signs the transactions with a key held by a remote signing service.
+pub struct RemoteTxSigner {
+	signer: Arc<RemoteSigner>,
+	chain_id: String,
+	/// Compressed public key of the relayer, queried from the service
+	public_key: Vec<u8>,
+	key_type: KeyType,
+}
+
+impl RemoteTxSigner {
+	/// Connects to the service, querying the public key of the relayer.
+	pub async fn connect(
+		config: RemoteSignerConfig,
+		chain_id: String,
+		key_type: KeyType,
+	) -> Result<Self, Error> {
+		let scheme = if key_type.is_ethereum() {
+			SignatureScheme::EthSecp256k1
+		} else {
+			SignatureScheme::Secp256k1
+		};
+		let signer = Arc::new(RemoteSigner::new(config, scheme));
+		let public_key = {
+			let signer = signer.clone();
+			tokio::task::spawn_blocking(move || signer.public_key())
+				.await
+				.map_err(|e| Error::from(e.to_string()))?
+				.map_err(|e| Error::from(e.to_string()))?
+		};
+		log::info!(
+			target: "hyperspace_cosmos",
+			"Signing the transactions of {chain_id} with the remote key {}: {}",
+			signer.key_id(),
+			hex::encode(&public_key)
+		);
+		Ok(Self { signer, chain_id, public_key, key_type })
+	}
+}
+
+#[async_trait]
+impl TxSigner for RemoteTxSigner {
+	fn public_key(&self) -> Vec<u8> {
+		self.public_key.clone()
+	}
+
+	fn public_key_type_url(&self) -> &str {
+		self.key_type.pub_key_type_url()
+	}
+
+	async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>, Error> {
+		let signer = self.signer.clone();
+		let chain_id = self.chain_id.clone();
+		tokio::task::spawn_blocking(move || signer.sign(&chain_id, &sign_doc))
+			.await
+			.map_err(|e| Error::from(e.to_string()))?
+			.map_err(|e| Error::from(e.to_string()))
+	}
+}
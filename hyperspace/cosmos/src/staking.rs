@@ -0,0 +1,133 @@
+//! Trusting period of the tendermint clients of the chain.
+//!
+//! A tendermint client only trusts the validators of its latest header while they're still
+//! bonded, so its trusting period has to be shorter than the unbonding period of the chain. The
+//! unbonding period is queried from the staking params at startup, and the trusting period of
+//! the created clients is `trusting_period_fraction` of it (two thirds by default), unless
+//! `trusting_period_secs` sets it, which is then checked against the unbonding period.
+//!
+//! The consumer chains of Interchain Security have no staking module, see [`crate::ccv`].
+
+use crate::{error::Error, grpc_pool::GrpcPool};
+use ibc_proto::cosmos::staking::v1beta1::{query_client::QueryClient, QueryParamsRequest};
+use std::time::Duration;
+
+/// Share of the unbonding period of the chain the trusting period of its clients defaults to
+pub const DEFAULT_TRUSTING_PERIOD_FRACTION: f64 = 2.0 / 3.0;
+
+/// Queries the unbonding period of the chain from its staking params.
+pub async fn query_unbonding_period(grpc_pool: &GrpcPool) -> Result<Duration, Error> {
+	let params = grpc_pool
+		.query(
+			|channel| async move { QueryClient::new(channel).params(QueryParamsRequest {}).await },
+		)
+		.await
+		.map_err(|e| Error::from(format!("Failed to query the staking params: {e:?}")))?
+		.into_inner()
+		.params
+		.ok_or_else(|| Error::from("The staking params are missing".to_string()))?;
+	let unbonding_time = params
+		.unbonding_time
+		.ok_or_else(|| Error::from("The unbonding time is missing".to_string()))?;
+	Ok(Duration::new(
+		u64::try_from(unbonding_time.seconds).unwrap_or_default(),
+		u32::try_from(unbonding_time.nanos).unwrap_or_default(),
+	))
+}
+
+/// Returns the trusting period of the clients of a chain with the given unbonding period: the
+/// configured one if it's shorter than the unbonding period, `fraction` of it otherwise.
+pub fn trusting_period(
+	unbonding_period: Duration,
+	configured: Option<Duration>,
+	fraction: f64,
+) -> Result<Duration, Error> {
+	if let Some(trusting_period) = configured {
+		if trusting_period >= unbonding_period {
+			return Err(Error::from(format!(
+				"The trusting period {trusting_period:?} isn't shorter than the unbonding period {unbonding_period:?} of the chain"
+			)))
+		}
+		return Ok(trusting_period)
+	}
+	if !(fraction > 0.0 && fraction < 1.0) {
+		return Err(Error::from(format!(
+			"The trusting period fraction {fraction} isn't between 0 and 1"
+		)))
+	}
+	// the product is rounded to nanoseconds, which reaches the bounds for the tiniest periods
+	let trusting_period = unbonding_period.mul_f64(fraction);
+	if trusting_period.is_zero() || trusting_period >= unbonding_period {
+		return Err(Error::from(format!(
+			"The trusting period fraction {fraction} of the unbonding period {unbonding_period:?} rounds to {trusting_period:?}"
+		)))
+	}
+	Ok(trusting_period)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+	#[test]
+	fn the_trusting_period_defaults_to_two_thirds_of_the_unbonding_period() {
+		let trusting_period =
+			trusting_period(DAY * 21, None, DEFAULT_TRUSTING_PERIOD_FRACTION).unwrap();
+		assert_eq!(trusting_period, DAY * 14);
+	}
+
+	#[test]
+	fn the_trusting_period_is_the_fraction_of_the_unbonding_period() {
+		assert_eq!(trusting_period(DAY * 7, None, 0.5).unwrap(), DAY * 7 / 2);
+		assert_eq!(trusting_period(DAY * 10, None, 0.9).unwrap(), DAY * 9);
+		// the unbonding periods of the chains aren't always whole seconds
+		let unbonding_period = Duration::new(1_209_600, 500_000_000);
+		assert_eq!(
+			trusting_period(unbonding_period, None, 0.5).unwrap(),
+			Duration::new(604_800, 250_000_000)
+		);
+	}
+
+	#[test]
+	fn the_trusting_period_is_rounded_to_nanoseconds() {
+		let unbonding_period = Duration::from_secs(1);
+		let trusting_period = trusting_period(unbonding_period, None, 1.0 / 3.0).unwrap();
+		let exact = 1e9 / 3.0;
+		assert!((trusting_period.as_nanos() as f64 - exact).abs() < 1.0, "{trusting_period:?}");
+		assert!(trusting_period < unbonding_period);
+	}
+
+	#[test]
+	fn fractions_rounding_to_the_bounds_are_rejected() {
+		let unbonding_period = Duration::from_nanos(1);
+		assert!(trusting_period(unbonding_period, None, 0.4).is_err());
+		assert!(trusting_period(unbonding_period, None, 0.6).is_err());
+	}
+
+	#[test]
+	fn fractions_out_of_bounds_are_rejected() {
+		for fraction in [0.0, 1.0, -0.5, 1.5, f64::NAN] {
+			assert!(trusting_period(DAY * 21, None, fraction).is_err(), "{fraction}");
+		}
+	}
+
+	#[test]
+	fn configured_trusting_periods_override_the_fraction() {
+		let configured = Some(DAY * 10);
+		assert_eq!(trusting_period(DAY * 21, configured, 0.5).unwrap(), DAY * 10);
+		// the fraction isn't checked
+		assert_eq!(trusting_period(DAY * 21, configured, 2.0).unwrap(), DAY * 10);
+	}
+
+	#[test]
+	fn configured_trusting_periods_must_be_shorter_than_the_unbonding_period() {
+		assert!(
+			trusting_period(DAY * 21, Some(DAY * 21), DEFAULT_TRUSTING_PERIOD_FRACTION).is_err()
+		);
+		assert!(
+			trusting_period(DAY * 21, Some(DAY * 22), DEFAULT_TRUSTING_PERIOD_FRACTION).is_err()
+		);
+	}
+}
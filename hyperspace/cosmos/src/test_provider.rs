@@ -7,7 +7,7 @@ use ibc::{
 	core::ics24_host::identifier::ChannelId,
 	tx_msg::Msg,
 };
-use primitives::TestProvider;
+use primitives::{memo::Memo, TestProvider};
 use tendermint_rpc::{
 	event::{Event, EventData},
 	query::{EventType, Query},
@@ -21,6 +21,7 @@ where
 {
 	/// Initiate an ibc transfer on chain.
 	async fn send_transfer(&self, msg: MsgTransfer<PrefixedCoin>) -> Result<(), Self::Error> {
+		Memo::parse(&msg.memo).map_err(|e| Error::Custom(e.to_string()))?;
 		let hash = self.submit_call(vec![msg.to_any()]).await?;
 		log::info!(target: "hyperspace_cosmos", "🤝 Transfer transaction confirmed with hash: {:?}", hash);
 		Ok(())
@@ -1,64 +1,118 @@
 use super::{
-	encode::{
-		encode_auth_info, encode_key_bytes, encode_sign_doc, encode_signer_info, encode_tx,
-		encode_tx_body,
-	},
-	key_provider::KeyEntry,
+	encode::{encode_auth_info, encode_sign_doc, encode_tx, encode_tx_body},
+	signer::TxSigner,
+};
+use crate::{
+	abci::TxFailure,
+	compat::{self, CometVersion},
+	error::Error,
+	grpc_pool::GrpcPool,
 };
-use crate::error::Error;
 use core::time::Duration;
-use futures::TryFutureExt;
 use ibc::core::ics24_host::identifier::ChainId;
 use ibc_proto::{
 	cosmos::{
 		auth::v1beta1::BaseAccount,
 		tx::v1beta1::{
-			service_client::ServiceClient, Fee, SimulateRequest, SimulateResponse, Tx, TxRaw,
+			service_client::ServiceClient, AuthInfo, Fee, SimulateRequest, SimulateResponse, Tx,
+			TxBody, TxRaw,
 		},
 	},
 	google::protobuf::Any,
 };
 use prost::Message;
+use serde::{Deserialize, Serialize};
 use tendermint::Hash;
 use tendermint_rpc::{
-	endpoint::tx::Response as TxResponse, query::Query, Client, Order, Url, WebSocketClient,
+	endpoint::{
+		broadcast::{tx_async, tx_commit, tx_sync},
+		tx::Response as TxResponse,
+		tx_search,
+	},
+	query::Query,
+	Order, WebSocketClient,
 };
 
-pub fn sign_tx(
-	key: KeyEntry,
+/// Body and auth info of a transaction, with their encodings.
+struct UnsignedTx {
+	body: TxBody,
+	body_bytes: Vec<u8>,
+	auth_info: AuthInfo,
+	auth_info_bytes: Vec<u8>,
+}
+
+impl UnsignedTx {
+	fn new(
+		signer: &dyn TxSigner,
+		sequence: u64,
+		messages: Vec<Any>,
+		fee: Fee,
+		memo: String,
+	) -> Result<Self, Error> {
+		let signer_info = signer.signer_info(sequence)?;
+
+		// Create and Encode AuthInfo
+		let (auth_info, auth_info_bytes) = encode_auth_info(signer_info, fee)?;
+
+		// Create and Encode TxBody
+		let (body, body_bytes) = encode_tx_body(messages, memo)?;
+
+		Ok(Self { body, body_bytes, auth_info, auth_info_bytes })
+	}
+
+	fn with_signature(self, signature_bytes: Vec<u8>) -> Result<(Tx, TxRaw, Vec<u8>), Error> {
+		// Create and Encode TxRaw
+		let (tx_raw, tx_bytes) =
+			encode_tx(self.body_bytes, self.auth_info_bytes, signature_bytes.clone())?;
+
+		let tx = Tx {
+			body: Some(self.body),
+			auth_info: Some(self.auth_info),
+			signatures: vec![signature_bytes],
+		};
+
+		Ok((tx, tx_raw, tx_bytes))
+	}
+}
+
+/// Builds the transaction of the messages, signed by the signer.
+pub async fn sign_tx(
+	signer: &dyn TxSigner,
 	chain_id: ChainId,
 	account_info: &BaseAccount,
 	messages: Vec<Any>,
 	fee: Fee,
+	memo: String,
 ) -> Result<(Tx, TxRaw, Vec<u8>), Error> {
-	let pk_bytes = encode_key_bytes(&key)?;
-	let signer_info = encode_signer_info(account_info.sequence, pk_bytes)?;
-
-	// Create and Encode AuthInfo
-	let (auth_info, auth_info_bytes) = encode_auth_info(signer_info, fee)?;
+	let unsigned = UnsignedTx::new(signer, account_info.sequence, messages, fee, memo)?;
 
-	// Create and Encode TxBody
-	let (body, body_bytes) = encode_tx_body(messages)?;
-
-	// Create and Encode TxRaw
-	let signature_bytes = encode_sign_doc(
-		key,
-		body_bytes.clone(),
-		auth_info_bytes.clone(),
+	// Encode SignDoc and Create Signature
+	let sign_doc = encode_sign_doc(
+		unsigned.body_bytes.clone(),
+		unsigned.auth_info_bytes.clone(),
 		chain_id,
 		account_info.account_number,
 	)?;
+	let signature_bytes = signer.sign(sign_doc).await?;
 
-	// Encode SignDoc and Create Signature
-	let (tx_raw, tx_bytes) = encode_tx(body_bytes, auth_info_bytes, signature_bytes.clone())?;
-
-	let tx = Tx { body: Some(body), auth_info: Some(auth_info), signatures: vec![signature_bytes] };
+	unsigned.with_signature(signature_bytes)
+}
 
-	Ok((tx, tx_raw, tx_bytes))
+/// Builds the transaction of the messages with a placeholder signature, to be simulated or
+/// measured but not broadcast, see [`crate::signer`].
+pub fn unsigned_tx(
+	signer: &dyn TxSigner,
+	account_info: &BaseAccount,
+	messages: Vec<Any>,
+	fee: Fee,
+	memo: String,
+) -> Result<(Tx, TxRaw, Vec<u8>), Error> {
+	UnsignedTx::new(signer, account_info.sequence, messages, fee, memo)?
+		.with_signature(signer.placeholder_signature())
 }
 
 pub async fn simulate_tx(
-	grpc_url: Url,
+	grpc_pool: &GrpcPool,
 	tx: Tx,
 	tx_bytes: Vec<u8>,
 ) -> Result<SimulateResponse, Error> {
@@ -67,50 +121,127 @@ pub async fn simulate_tx(
 		tx: Some(tx), // needed for simulation to go through with Cosmos SDK <  0.43
 		tx_bytes,     // needed for simulation to go through with Cosmos SDk >= 0.43
 	};
-	let mut client = ServiceClient::connect(grpc_url.clone().to_string())
+
+	let response = grpc_pool
+		.query(|channel| {
+			let request = req.clone();
+			async move {
+				tokio::time::timeout(
+					Duration::from_secs(15),
+					ServiceClient::new(channel).simulate(request),
+				)
+				.await
+				.map_err(|_| tonic::Status::deadline_exceeded("simulation timeout"))?
+			}
+		})
 		.await
-		.map_err(|e| Error::from(e.to_string()))?;
-	let request = tonic::Request::new(req);
-
-	let response = tokio::time::timeout(
-		Duration::from_secs(15),
-		client.simulate(request).map_err(|e| Error::from(e.to_string())),
-	)
-	.await
-	.map_err(|_| Error::from("simulation timeout".to_string()))??
-	.into_inner();
+		.map_err(|e| Error::from(e.to_string()))?
+		.into_inner();
 	Ok(response)
 }
 
-pub async fn broadcast_tx(rpc_client: &WebSocketClient, tx_bytes: Vec<u8>) -> Result<Hash, Error> {
-	let response = rpc_client
-		.broadcast_tx_sync(tx_bytes)
-		.await
-		.map_err(|e| Error::from(format!("failed to broadcast transaction {e:?}")))?;
-	Ok(response.hash)
+/// How the transactions are broadcast to the node.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BroadcastMode {
+	/// Wait for the transaction to pass `CheckTx`, then track its inclusion
+	#[default]
+	Sync,
+	/// Don't wait for `CheckTx`, the transaction is only tracked until its inclusion
+	Async,
+	/// Wait for the transaction to be included in a block. The connection is held until then, so
+	/// most public nodes disable it
+	Commit,
 }
 
-pub async fn confirm_tx(rpc_client: &WebSocketClient, tx_hash: Hash) -> Result<Hash, Error> {
+/// Broadcasts a transaction in the given mode. The transactions rejected by `CheckTx` (or, in the
+/// commit mode, that failed in their block) are returned as errors.
+pub async fn broadcast_tx(
+	rpc_client: &WebSocketClient,
+	comet_version: CometVersion,
+	mode: BroadcastMode,
+	tx_bytes: Vec<u8>,
+) -> Result<Hash, Error> {
+	let failed =
+		|e: tendermint_rpc::Error| Error::from(format!("failed to broadcast transaction {e:?}"));
+	match mode {
+		BroadcastMode::Sync => {
+			let request = tx_sync::Request::new(tx_bytes);
+			let response =
+				compat::perform(rpc_client, comet_version, request).await.map_err(failed)?;
+			if let Some(failure) = TxFailure::new(
+				response.hash,
+				None,
+				&response.codespace,
+				response.code,
+				&response.log,
+			) {
+				return Err(Error::TxFailed(failure))
+			}
+			Ok(response.hash)
+		},
+		BroadcastMode::Async => {
+			let request = tx_async::Request::new(tx_bytes);
+			let response =
+				compat::perform(rpc_client, comet_version, request).await.map_err(failed)?;
+			Ok(response.hash)
+		},
+		BroadcastMode::Commit => {
+			let request = tx_commit::Request::new(tx_bytes);
+			let response =
+				compat::perform(rpc_client, comet_version, request).await.map_err(failed)?;
+			let check_tx = &response.check_tx;
+			if let Some(failure) = TxFailure::new(
+				response.hash,
+				None,
+				&check_tx.codespace,
+				check_tx.code,
+				&check_tx.log,
+			) {
+				return Err(Error::TxFailed(failure))
+			}
+			let deliver_tx = &response.deliver_tx;
+			if let Some(failure) = TxFailure::new(
+				response.hash,
+				Some(response.height.value()),
+				&deliver_tx.codespace,
+				deliver_tx.code,
+				&deliver_tx.log,
+			) {
+				return Err(Error::TxFailed(failure))
+			}
+			Ok(response.hash)
+		},
+	}
+}
+
+pub async fn confirm_tx(
+	rpc_client: &WebSocketClient,
+	comet_version: CometVersion,
+	tx_hash: Hash,
+) -> Result<TxResponse, Error> {
 	let start_time = tokio::time::Instant::now();
 	let timeout = Duration::from_millis(30000);
 	const WAIT_BACKOFF: Duration = Duration::from_millis(300);
 	let response: TxResponse = loop {
-		let response = rpc_client
-			.tx_search(
-				Query::eq("tx.hash", tx_hash.to_string()),
-				false,
-				1,
-				1, // get only the first Tx matching the query
-				Order::Ascending,
-			)
+		let request = tx_search::Request::new(
+			Query::eq("tx.hash", tx_hash.to_string()),
+			false,
+			1,
+			1, // get only the first Tx matching the query
+			Order::Ascending,
+		);
+		let response = compat::perform(rpc_client, comet_version, request)
 			.await
 			.map_err(|e| Error::from(format!("failed to search for transaction {e:?}")))?;
 		match response.txs.into_iter().next() {
 			None => {
 				let elapsed = start_time.elapsed();
 				if elapsed > timeout {
+					// the transaction was dropped from the mempool, e.g. by the CheckTx of an
+					// async broadcast or a recheck after a block, or it's still pending
 					return Err(Error::from(format!(
-						"transaction {} not found after {} seconds",
+						"transaction {} not included after {} seconds",
 						tx_hash,
 						elapsed.as_secs()
 					)))
@@ -122,20 +253,26 @@ pub async fn confirm_tx(rpc_client: &WebSocketClient, tx_hash: Hash) -> Result<H
 		}
 	};
 
-	let response_code = response.tx_result.code;
-	if response_code.is_err() {
-		return Err(Error::from(format!("transaction {tx_hash} failed with code {response_code:?}")))
+	let result = &response.tx_result;
+	if let Some(failure) = TxFailure::new(
+		tx_hash,
+		Some(response.height.value()),
+		&result.codespace,
+		result.code,
+		&result.log,
+	) {
+		return Err(Error::TxFailed(failure))
 	}
-	Ok(response.hash)
+	Ok(response)
 }
 
 pub fn encoded_tx_metrics(
-	key: KeyEntry,
-	chain_id: ChainId,
+	signer: &dyn TxSigner,
 	account_info: &BaseAccount,
 	fee: Fee,
+	memo: String,
 ) -> Result<(usize, usize), Error> {
-	let (_, tx_raw, _) = sign_tx(key, chain_id, account_info, vec![], fee)?;
+	let (_, tx_raw, _) = unsigned_tx(signer, account_info, vec![], fee, memo)?;
 
 	let total_len = tx_raw.encoded_len();
 	let body_bytes_len = tx_raw.body_bytes.len();
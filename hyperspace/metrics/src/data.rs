@@ -102,14 +102,10 @@ pub struct Metrics {
 	/// Total number of "timeout packet" events received.
 	pub number_of_received_timeouts: Counter<U64>,
 
-	/// Total number of received packets on the counterparty's side.
-	pub counterparty_number_of_received_packets: Option<Counter<U64>>,
-	/// Total number of received acknowledgments on the counterparty's side.
-	pub counterparty_number_of_received_acknowledgments: Option<Counter<U64>>,
-
-	/// Total number of sent packets.
+	/// Total number of sent packets, confirmed by a "receive packet" event on the counterparty.
 	pub number_of_sent_packets: Counter<U64>,
-	/// Total number of sent acknowledgments.
+	/// Total number of sent acknowledgments, confirmed by an "acknowledge packet" event on the
+	/// counterparty.
 	pub number_of_sent_acknowledgments: Counter<U64>,
 	/// Total number of timed out packets.
 	pub number_of_sent_timeout_packets: Counter<U64>,
@@ -118,6 +114,14 @@ pub struct Metrics {
 	pub number_of_undelivered_packets: Gauge<U64>,
 	/// Number of undelivered acknowledgements over time.
 	pub number_of_undelivered_acknowledgements: Gauge<U64>,
+	/// Number of packets submitted to the counterparty but not confirmed by an event yet.
+	pub number_of_unconfirmed_packets: Gauge<U64>,
+	/// Number of acknowledgements submitted to the counterparty but not confirmed by an event yet.
+	pub number_of_unconfirmed_acknowledgements: Gauge<U64>,
+	/// Total number of packets found undelivered by two consecutive sequence audits.
+	pub number_of_packet_sequence_gaps: Counter<U64>,
+	/// Total number of acknowledgements found undelivered by two consecutive sequence audits.
+	pub number_of_acknowledgement_sequence_gaps: Counter<U64>,
 	/// Gas cost for every sent tx bundle.
 	pub gas_cost_for_sent_tx_bundle: Histogram,
 	/// Transaction length (in bytes) for every sent tx bundle.
@@ -143,6 +147,24 @@ pub struct Metrics {
 
 	/// Latest processed height - helpful to prevent pushing the same event twice
 	pub latest_processed_height: Gauge<U64>,
+	/// 1 while the submissions are paused because the relayer balance is too low, 0 otherwise.
+	pub submissions_paused: Gauge<U64>,
+	/// Latest known balance of the relayer account, in the smallest unit of the fee token.
+	pub relayer_balance: Gauge<U64>,
+	/// Total ICS-29 fees earned by the relayer on the chain, per denom.
+	pub earned_fees: CounterVec<U64>,
+	/// Number of packet messages set aside after being rejected on their own.
+	pub dead_letter_messages: Gauge<U64>,
+	/// Number of failed submissions to the chain, per class of error.
+	pub submission_failures: CounterVec<U64>,
+	/// Number of the blocks in the local light block store of the chain.
+	pub light_store_size: Gauge<U64>,
+	/// Number of the requests delayed by the rate limits of the endpoints of the chain.
+	pub throttled_requests: CounterVec<U64>,
+	/// Number of the proof queries served by the proof cache of the chain.
+	pub proof_cache_hits: Counter<U64>,
+	/// Number of the proof queries that missed the proof cache of the chain.
+	pub proof_cache_misses: Counter<U64>,
 
 	/// Metrics prefix.
 	pub prefix: String,
@@ -191,13 +213,11 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
-			counterparty_number_of_received_packets: None,
-			counterparty_number_of_received_acknowledgments: None,
 			number_of_sent_packets: register(
 				Counter::with_opts(
 					Opts::new(
 						format!("hyperspace_number_of_sent_packets"),
-						"Total number of sent packets",
+						"Total number of sent packets, confirmed by the counterparty",
 					)
 					.const_label("name", prefix.to_string()),
 				)?,
@@ -207,7 +227,7 @@ impl Metrics {
 				Counter::with_opts(
 					Opts::new(
 						format!("hyperspace_number_of_sent_acknowledgments"),
-						"Total number of sent acknowledgments",
+						"Total number of sent acknowledgments, confirmed by the counterparty",
 					)
 					.const_label("name", prefix.to_string()),
 				)?,
@@ -243,6 +263,46 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			number_of_unconfirmed_packets: register(
+				Gauge::with_opts(
+					Opts::new(
+						format!("hyperspace_number_of_unconfirmed_packets"),
+						"Number of packets submitted but not confirmed by the counterparty yet",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			number_of_unconfirmed_acknowledgements: register(
+				Gauge::with_opts(
+					Opts::new(
+						format!("hyperspace_number_of_unconfirmed_acknowledgements"),
+						"Number of acknowledgements submitted but not confirmed by the counterparty yet",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			number_of_packet_sequence_gaps: register(
+				Counter::with_opts(
+					Opts::new(
+						format!("hyperspace_number_of_packet_sequence_gaps"),
+						"Total number of packets left undelivered between two sequence audits",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			number_of_acknowledgement_sequence_gaps: register(
+				Counter::with_opts(
+					Opts::new(
+						format!("hyperspace_number_of_acknowledgement_sequence_gaps"),
+						"Total number of acknowledgements left undelivered between two sequence audits",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
 			gas_cost_for_sent_tx_bundle: register(
 				Histogram::with_opts(
 					HistogramOpts::new(
@@ -353,34 +413,103 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			submissions_paused: register(
+				Gauge::with_opts(
+					Opts::new(
+						format!("hyperspace_submissions_paused"),
+						"Whether the submissions are paused because the relayer balance is too low",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			relayer_balance: register(
+				Gauge::with_opts(
+					Opts::new(
+						format!("hyperspace_relayer_balance"),
+						"Latest known balance of the relayer account",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			earned_fees: register(
+				CounterVec::new(
+					Opts::new(
+						format!("hyperspace_earned_fees"),
+						"Total ICS-29 fees earned by the relayer",
+					)
+					.const_label("name", prefix.to_string()),
+					&["denom"],
+				)?,
+				registry,
+			)?,
+			dead_letter_messages: register(
+				Gauge::with_opts(
+					Opts::new(
+						format!("hyperspace_dead_letter_messages"),
+						"Number of packet messages set aside after being rejected on their own",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			submission_failures: register(
+				CounterVec::new(
+					Opts::new(
+						format!("hyperspace_submission_failures"),
+						"Number of failed submissions to the chain, per class of error",
+					)
+					.const_label("name", prefix.to_string()),
+					&["class"],
+				)?,
+				registry,
+			)?,
+			light_store_size: register(
+				Gauge::with_opts(
+					Opts::new(
+						format!("hyperspace_light_store_size"),
+						"Number of the blocks in the local light block store of the chain",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			throttled_requests: register(
+				CounterVec::new(
+					Opts::new(
+						format!("hyperspace_throttled_requests"),
+						"Number of the requests delayed by the rate limits of the endpoints of the chain, per endpoint",
+					)
+					.const_label("name", prefix.to_string()),
+					&["endpoint"],
+				)?,
+				registry,
+			)?,
+			proof_cache_hits: register(
+				Counter::with_opts(
+					Opts::new(
+						format!("hyperspace_proof_cache_hits"),
+						"Number of the proof queries served by the proof cache of the chain",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			proof_cache_misses: register(
+				Counter::with_opts(
+					Opts::new(
+						format!("hyperspace_proof_cache_misses"),
+						"Number of the proof queries that missed the proof cache of the chain",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
 			prefix: prefix.to_string(),
 		})
 	}
 
-	pub fn link_with_counterparty_metrics(&mut self, other: &mut Metrics) {
-		self.counterparty_number_of_received_packets =
-			Some(other.number_of_received_receive_packets.clone());
-		self.counterparty_number_of_received_acknowledgments =
-			Some(other.number_of_received_acknowledge_packets.clone());
-
-		other.counterparty_number_of_received_packets =
-			Some(self.number_of_received_receive_packets.clone());
-		other.counterparty_number_of_received_acknowledgments =
-			Some(self.number_of_received_acknowledge_packets.clone());
-	}
-
-	pub fn counterparty_number_of_received_packets(&self) -> &Counter<U64> {
-		self.counterparty_number_of_received_packets
-            .as_ref()
-            .expect("counterparty_number_of_received_packets is not set. Perhaps you forgot to call `link_with_counterparty_metrics`?")
-	}
-
-	pub fn counterparty_number_of_received_acknowledgments(&self) -> &Counter<U64> {
-		self.counterparty_number_of_received_acknowledgments
-            .as_ref()
-            .expect("counterparty_number_of_received_acknowledgments is not set. Perhaps you forgot to call `link_with_counterparty_metrics`?")
-	}
-
 	pub fn update_light_client_height(
 		&mut self,
 		client_id: &ClientId,
@@ -12,11 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::data::Metrics;
+use crate::{data::Metrics, health::HealthState, Counter, Gauge, U64};
 use ibc::{
 	core::{
 		ics04_channel::{
 			events::{TimeoutOnClosePacket, TimeoutPacket},
+			msgs::{acknowledgement::MsgAcknowledgement, recv_packet::MsgRecvPacket},
 			packet::{Packet, Sequence},
 		},
 		ics24_host::identifier::{ChannelId, PortId},
@@ -26,11 +27,18 @@ use ibc::{
 use ibc_proto::google::protobuf::Any;
 use prometheus::{Histogram, Registry};
 use std::{
-	collections::HashMap,
+	collections::{HashMap, VecDeque},
 	ops::DerefMut,
 	sync::{Arc, Mutex},
-	time::Instant,
+	time::{Duration, Instant},
 };
+use tendermint_proto::Protobuf;
+
+/// Time after which a submitted message that hasn't been confirmed by the counterparty is
+/// forgotten, as the transaction most likely failed.
+pub const UNCONFIRMED_TTL: Duration = Duration::from_secs(60 * 60);
+/// Number of recent submissions the error rate of the handler is computed over.
+pub const SUBMISSIONS_WINDOW: usize = 100;
 
 #[derive(Eq, PartialEq, Hash)]
 pub struct PacketId {
@@ -51,6 +59,99 @@ impl From<Packet> for PacketId {
 
 pub type PacketMap = Arc<Mutex<HashMap<PacketId, Instant>>>;
 
+/// Sequences on a channel that haven't been delivered to the counterparty yet.
+#[derive(Debug, Clone, Default)]
+pub struct UndeliveredSequences {
+	pub packets: Vec<u64>,
+	pub acknowledgements: Vec<u64>,
+}
+
+pub type UndeliveredMap = Arc<Mutex<HashMap<(ChannelId, PortId), UndeliveredSequences>>>;
+
+/// Messages submitted to the counterparty that haven't been confirmed by an event on the
+/// counterparty yet. The `confirmed` counter is only incremented once the event is seen, so that
+/// failed transactions aren't counted as sent.
+#[derive(Clone)]
+struct Unconfirmed {
+	messages: PacketMap,
+	confirmed: Counter<U64>,
+	gauge: Gauge<U64>,
+}
+
+impl Unconfirmed {
+	fn new(confirmed: Counter<U64>, gauge: Gauge<U64>) -> Self {
+		Self { messages: Arc::new(Mutex::new(HashMap::new())), confirmed, gauge }
+	}
+
+	fn submit(&self, packet_id: PacketId) {
+		let now = Instant::now();
+		let mut messages = self.messages.lock().unwrap();
+		messages.retain(|_, submitted_at| now.duration_since(*submitted_at) < UNCONFIRMED_TTL);
+		messages.insert(packet_id, now);
+		self.gauge.set(messages.len() as u64);
+	}
+
+	fn confirm(&self, packet: &Packet) {
+		let mut messages = self.messages.lock().unwrap();
+		if messages.remove(&packet.clone().into()).is_some() {
+			self.confirmed.inc();
+		}
+		self.gauge.set(messages.len() as u64);
+	}
+}
+
+/// Shared view of the packets that the handler believes are outstanding.
+#[derive(Clone)]
+pub struct OutstandingPackets {
+	pub last_sent_packet_time: PacketMap,
+	pub last_sent_acknowledgment_time: PacketMap,
+	pub last_sent_timeout_packet_time: PacketMap,
+	pub undelivered_sequences: UndeliveredMap,
+}
+
+/// Shared view of the state the health score of a path is computed from, see [`crate::score`].
+#[derive(Clone)]
+pub struct ScoreInputs {
+	started_at: Instant,
+	last_update_client_time: Arc<Mutex<Option<Instant>>>,
+	undelivered_sequences: UndeliveredMap,
+	submissions: Arc<Mutex<VecDeque<bool>>>,
+}
+
+impl ScoreInputs {
+	/// Time since the last update of the client on the chain, or since the relayer started if the
+	/// client wasn't updated yet.
+	pub fn client_update_age(&self) -> Duration {
+		self.last_update_client_time
+			.lock()
+			.unwrap()
+			.unwrap_or(self.started_at)
+			.elapsed()
+	}
+
+	/// Number of undelivered packets and acknowledgements.
+	pub fn backlog(&self) -> u64 {
+		self.undelivered_sequences
+			.lock()
+			.unwrap()
+			.values()
+			.map(|seqs| (seqs.packets.len() + seqs.acknowledgements.len()) as u64)
+			.sum()
+	}
+
+	/// Share of the recent submissions that failed, or `None` if nothing was submitted yet.
+	pub fn error_rate(&self) -> Option<f64> {
+		let submissions = self.submissions.lock().unwrap();
+		if submissions.is_empty() {
+			return None
+		}
+		let failures = submissions.iter().filter(|ok| !**ok).count();
+		Some(failures as f64 / submissions.len() as f64)
+	}
+}
+
+/// Metrics of a chain, the clones report to the same metrics.
+#[derive(Clone)]
 pub struct MetricsHandler {
 	registry: Registry,
 	metrics: Metrics,
@@ -59,14 +160,31 @@ pub struct MetricsHandler {
 	last_sent_acknowledgment_time: PacketMap,
 	last_sent_timeout_packet_time: PacketMap,
 	last_update_client_time: Arc<Mutex<Option<Instant>>>,
+	undelivered_sequences: UndeliveredMap,
+	unconfirmed_packets: Unconfirmed,
+	unconfirmed_acknowledgements: Unconfirmed,
+	submissions: Arc<Mutex<VecDeque<bool>>>,
+	started_at: Instant,
 
 	counterparty_last_sent_packet_time: Option<PacketMap>,
 	counterparty_last_sent_acknowledgment_time: Option<PacketMap>,
 	counterparty_last_sent_timeout_packet_time: Option<PacketMap>,
+	counterparty_unconfirmed_packets: Option<Unconfirmed>,
+	counterparty_unconfirmed_acknowledgements: Option<Unconfirmed>,
+
+	health: Option<(String, HealthState)>,
 }
 
 impl MetricsHandler {
 	pub fn new(registry: Registry, metrics: Metrics) -> Self {
+		let unconfirmed_packets = Unconfirmed::new(
+			metrics.number_of_sent_packets.clone(),
+			metrics.number_of_unconfirmed_packets.clone(),
+		);
+		let unconfirmed_acknowledgements = Unconfirmed::new(
+			metrics.number_of_sent_acknowledgments.clone(),
+			metrics.number_of_unconfirmed_acknowledgements.clone(),
+		);
 		Self {
 			registry,
 			metrics,
@@ -74,9 +192,142 @@ impl MetricsHandler {
 			last_sent_acknowledgment_time: Arc::new(Mutex::new(HashMap::new())),
 			last_sent_timeout_packet_time: Arc::new(Mutex::new(HashMap::new())),
 			last_update_client_time: Arc::new(Mutex::new(None)),
+			undelivered_sequences: Arc::new(Mutex::new(HashMap::new())),
+			unconfirmed_packets,
+			unconfirmed_acknowledgements,
+			submissions: Arc::new(Mutex::new(VecDeque::with_capacity(SUBMISSIONS_WINDOW))),
+			started_at: Instant::now(),
 			counterparty_last_sent_packet_time: None,
 			counterparty_last_sent_acknowledgment_time: None,
 			counterparty_last_sent_timeout_packet_time: None,
+			counterparty_unconfirmed_packets: None,
+			counterparty_unconfirmed_acknowledgements: None,
+			health: None,
+		}
+	}
+
+	/// Reports the liveness of the chain with the given name to the shared health state.
+	pub fn with_health(mut self, name: &str, health: HealthState) -> Self {
+		health.register_chain(name);
+		self.health = Some((name.to_string(), health));
+		self
+	}
+
+	pub fn report_stream_alive(&self, alive: bool) {
+		if let Some((name, health)) = &self.health {
+			health.set_stream_alive(name, alive);
+		}
+	}
+
+	pub fn report_rpc_alive(&self, alive: bool) {
+		if let Some((name, health)) = &self.health {
+			health.set_rpc_alive(name, alive);
+		}
+	}
+
+	pub fn report_startup_complete(&self) {
+		if let Some((_, health)) = &self.health {
+			health.set_startup_complete();
+		}
+	}
+
+	/// Returns a handle to the outstanding packets tracked by the handler.
+	pub fn outstanding_packets(&self) -> OutstandingPackets {
+		OutstandingPackets {
+			last_sent_packet_time: self.last_sent_packet_time.clone(),
+			last_sent_acknowledgment_time: self.last_sent_acknowledgment_time.clone(),
+			last_sent_timeout_packet_time: self.last_sent_timeout_packet_time.clone(),
+			undelivered_sequences: self.undelivered_sequences.clone(),
+		}
+	}
+
+	/// Returns a handle to the state the health score of the path is computed from.
+	pub fn score_inputs(&self) -> ScoreInputs {
+		ScoreInputs {
+			started_at: self.started_at,
+			last_update_client_time: self.last_update_client_time.clone(),
+			undelivered_sequences: self.undelivered_sequences.clone(),
+			submissions: self.submissions.clone(),
+		}
+	}
+
+	/// Records the outcome of a submission, for the error rate of the health score.
+	pub fn report_submission(&self, ok: bool) {
+		let mut submissions = self.submissions.lock().unwrap();
+		if submissions.len() >= SUBMISSIONS_WINDOW {
+			submissions.pop_front();
+		}
+		submissions.push_back(ok);
+	}
+
+	/// Sets the packets of the channel that haven't been received on the counterparty, as queried
+	/// from chain state.
+	pub fn set_undelivered_packets(&self, channel_id: ChannelId, port_id: PortId, seqs: Vec<u64>) {
+		let mut undelivered = self.undelivered_sequences.lock().unwrap();
+		undelivered.entry((channel_id, port_id)).or_default().packets = seqs;
+		self.metrics
+			.number_of_undelivered_packets
+			.set(undelivered.values().map(|seqs| seqs.packets.len() as u64).sum());
+	}
+
+	pub fn set_undelivered_acknowledgements(
+		&self,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) {
+		let mut undelivered = self.undelivered_sequences.lock().unwrap();
+		undelivered.entry((channel_id, port_id)).or_default().acknowledgements = seqs;
+		self.metrics
+			.number_of_undelivered_acknowledgements
+			.set(undelivered.values().map(|seqs| seqs.acknowledgements.len() as u64).sum());
+	}
+
+	/// Counts the packets and acknowledgements found undelivered by a sequence audit.
+	pub fn report_sequence_gaps(&self, packets: usize, acknowledgements: usize) {
+		self.metrics.number_of_packet_sequence_gaps.inc_by(packets as u64);
+		self.metrics
+			.number_of_acknowledgement_sequence_gaps
+			.inc_by(acknowledgements as u64);
+	}
+
+	/// Reports the balance of the relayer account, and whether the submissions are paused
+	/// because it's too low.
+	pub fn report_balance(&self, balance: u128, paused: bool) {
+		self.metrics.relayer_balance.set(balance.min(u64::MAX as u128) as u64);
+		self.metrics.submissions_paused.set(paused as u64);
+	}
+
+	/// Records a failed submission, by the class of its error (see `primitives::retry`).
+	pub fn report_submission_failure(&self, class: &str) {
+		self.metrics.submission_failures.with_label_values(&[class]).inc();
+	}
+
+	pub fn report_dead_letters(&self, count: usize) {
+		self.metrics.dead_letter_messages.set(count as u64);
+	}
+
+	pub fn report_light_store_size(&self, size: usize) {
+		self.metrics.light_store_size.set(size as u64);
+	}
+
+	pub fn report_throttled_requests(&self, throttled: Vec<(String, u64)>) {
+		for (endpoint, count) in throttled {
+			self.metrics.throttled_requests.with_label_values(&[&endpoint]).inc_by(count);
+		}
+	}
+
+	pub fn report_proof_cache(&self, (hits, misses): (u64, u64)) {
+		self.metrics.proof_cache_hits.inc_by(hits);
+		self.metrics.proof_cache_misses.inc_by(misses);
+	}
+
+	pub fn report_earned_fees(&self, fees: Vec<(String, u128)>) {
+		for (denom, amount) in fees {
+			self.metrics
+				.earned_fees
+				.with_label_values(&[&denom])
+				.inc_by(amount.min(u64::MAX as u128) as u64);
 		}
 	}
 
@@ -116,6 +367,7 @@ impl MetricsHandler {
 				},
 				IbcEvent::ReceivePacket(packet) => {
 					self.metrics.number_of_received_receive_packets.inc();
+					self.counterparty_unconfirmed_packets().confirm(&packet.packet);
 					self.observe_last_packet_time(
 						&packet.packet,
 						&self.counterparty_last_sent_packet_time,
@@ -131,6 +383,7 @@ impl MetricsHandler {
 				},
 				IbcEvent::AcknowledgePacket(packet) => {
 					self.metrics.number_of_received_acknowledge_packets.inc();
+					self.counterparty_unconfirmed_acknowledgements().confirm(&packet.packet);
 					self.observe_last_packet_time(
 						&packet.packet,
 						&self.counterparty_last_sent_acknowledgment_time,
@@ -163,48 +416,58 @@ impl MetricsHandler {
 		Ok(())
 	}
 
+	/// Records the submitted packets and acknowledgements as unconfirmed. They are counted as sent
+	/// once the counterparty handler sees the matching event.
 	pub async fn handle_messages(&self, messages: &[Any]) {
 		for message in messages {
 			match message.type_url.as_str() {
-				"/ibc.core.channel.v1.MsgAcknowledgement" => {
-					self.metrics.number_of_sent_acknowledgments.inc();
-					// The counters may be out of sync (e.g. when relayer was restarted), so we use
-					// saturating sub
-					let number_of_undelivered_acknowledgements =
-						self.metrics.number_of_sent_acknowledgments.get().saturating_sub(
-							self.metrics.counterparty_number_of_received_acknowledgments().get(),
-						);
-					self.metrics
-						.number_of_undelivered_acknowledgements
-						.set(number_of_undelivered_acknowledgements);
-				},
-				"/ibc.core.channel.v1.MsgRecvPacket" => {
-					self.metrics.number_of_undelivered_packets.set(
-						self.metrics.number_of_sent_packets.get().saturating_sub(
-							self.metrics.counterparty_number_of_received_packets().get(),
-						),
-					);
-					self.metrics.number_of_sent_packets.inc();
-				},
+				"/ibc.core.channel.v1.MsgAcknowledgement" =>
+					match MsgAcknowledgement::decode_vec(&message.value) {
+						Ok(msg) => self.unconfirmed_acknowledgements.submit(msg.packet.into()),
+						Err(e) =>
+							log::warn!(target: "hyperspace", "Failed to decode MsgAcknowledgement: {e:?}"),
+					},
+				"/ibc.core.channel.v1.MsgRecvPacket" =>
+					match MsgRecvPacket::decode_vec(&message.value) {
+						Ok(msg) => self.unconfirmed_packets.submit(msg.packet.into()),
+						Err(e) =>
+							log::warn!(target: "hyperspace", "Failed to decode MsgRecvPacket: {e:?}"),
+					},
 				_ => (),
 			}
 		}
 	}
 
 	pub fn link_with_counterparty(&mut self, counterparty: &mut Self) {
-		self.metrics.link_with_counterparty_metrics(&mut counterparty.metrics);
-
 		self.counterparty_last_sent_packet_time = Some(counterparty.last_sent_packet_time.clone());
 		self.counterparty_last_sent_acknowledgment_time =
 			Some(counterparty.last_sent_acknowledgment_time.clone());
 		self.counterparty_last_sent_timeout_packet_time =
 			Some(counterparty.last_sent_timeout_packet_time.clone());
+		self.counterparty_unconfirmed_packets = Some(counterparty.unconfirmed_packets.clone());
+		self.counterparty_unconfirmed_acknowledgements =
+			Some(counterparty.unconfirmed_acknowledgements.clone());
 
 		counterparty.counterparty_last_sent_packet_time = Some(self.last_sent_packet_time.clone());
 		counterparty.counterparty_last_sent_acknowledgment_time =
 			Some(self.last_sent_acknowledgment_time.clone());
 		counterparty.counterparty_last_sent_timeout_packet_time =
 			Some(self.last_sent_timeout_packet_time.clone());
+		counterparty.counterparty_unconfirmed_packets = Some(self.unconfirmed_packets.clone());
+		counterparty.counterparty_unconfirmed_acknowledgements =
+			Some(self.unconfirmed_acknowledgements.clone());
+	}
+
+	fn counterparty_unconfirmed_packets(&self) -> &Unconfirmed {
+		self.counterparty_unconfirmed_packets
+            .as_ref()
+            .expect("counterparty_unconfirmed_packets is not set. Perhaps you forgot to call `link_with_counterparty`?")
+	}
+
+	fn counterparty_unconfirmed_acknowledgements(&self) -> &Unconfirmed {
+		self.counterparty_unconfirmed_acknowledgements
+            .as_ref()
+            .expect("counterparty_unconfirmed_acknowledgements is not set. Perhaps you forgot to call `link_with_counterparty`?")
 	}
 
 	pub async fn handle_timeouts(&self, timeouts: &[Any]) {
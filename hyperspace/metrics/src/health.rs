@@ -0,0 +1,134 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::score::HealthFactors;
+use std::{
+	collections::BTreeMap,
+	fmt::Write,
+	sync::{Arc, RwLock},
+};
+
+/// Liveness of a single chain, as observed by the relayer loop.
+#[derive(Debug, Clone, Copy)]
+pub struct ChainHealth {
+	/// Whether the finality event stream is open.
+	pub stream_alive: bool,
+	/// Whether the last round of RPC calls to the chain succeeded.
+	pub rpc_alive: bool,
+}
+
+impl Default for ChainHealth {
+	fn default() -> Self {
+		Self { stream_alive: true, rpc_alive: true }
+	}
+}
+
+impl ChainHealth {
+	pub fn is_healthy(&self) -> bool {
+		self.stream_alive && self.rpc_alive
+	}
+}
+
+/// Health score of a relayed path, see [`crate::score`].
+#[derive(Debug, Clone, Copy)]
+pub struct RouteHealth {
+	pub score: f64,
+	pub factors: HealthFactors,
+}
+
+#[derive(Debug, Default)]
+struct HealthInner {
+	startup_complete: bool,
+	chains: BTreeMap<String, ChainHealth>,
+	routes: BTreeMap<String, RouteHealth>,
+}
+
+/// Shared health state served on `/healthz`, `/readyz` and `/status`.
+#[derive(Debug, Clone, Default)]
+pub struct HealthState {
+	inner: Arc<RwLock<HealthInner>>,
+}
+
+impl HealthState {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	fn update_chain(&self, name: &str, f: impl FnOnce(&mut ChainHealth)) {
+		let mut inner = self.inner.write().unwrap();
+		f(inner.chains.entry(name.to_string()).or_default());
+	}
+
+	/// Registers a chain, so that it's reported on the health endpoints.
+	pub fn register_chain(&self, name: &str) {
+		self.update_chain(name, |_| ());
+	}
+
+	pub fn set_stream_alive(&self, name: &str, alive: bool) {
+		self.update_chain(name, |health| health.stream_alive = alive);
+	}
+
+	pub fn set_rpc_alive(&self, name: &str, alive: bool) {
+		self.update_chain(name, |health| health.rpc_alive = alive);
+	}
+
+	pub fn set_route_health(&self, path: &str, health: RouteHealth) {
+		self.inner.write().unwrap().routes.insert(path.to_string(), health);
+	}
+
+	/// Marks the relayer startup as complete (clients are created and finality streams are open).
+	pub fn set_startup_complete(&self) {
+		self.inner.write().unwrap().startup_complete = true;
+	}
+
+	pub fn is_startup_complete(&self) -> bool {
+		self.inner.read().unwrap().startup_complete
+	}
+
+	/// Returns `true` if all the registered chains have live streams and RPC connections.
+	pub fn is_live(&self) -> bool {
+		self.inner.read().unwrap().chains.values().all(ChainHealth::is_healthy)
+	}
+
+	/// Returns `true` if the relayer has completed startup and is live.
+	pub fn is_ready(&self) -> bool {
+		self.is_startup_complete() && self.is_live()
+	}
+
+	/// Human-readable report of the health of each chain.
+	pub fn report(&self) -> String {
+		let inner = self.inner.read().unwrap();
+		let mut report = String::new();
+		let _ = writeln!(report, "startup_complete: {}", inner.startup_complete);
+		for (name, health) in &inner.chains {
+			let _ = writeln!(
+				report,
+				"{name}: stream_alive: {}, rpc_alive: {}",
+				health.stream_alive, health.rpc_alive
+			);
+		}
+		report
+	}
+
+	/// Human-readable report of the health of each chain, followed by the health score of each
+	/// path.
+	pub fn status_report(&self) -> String {
+		let mut report = self.report();
+		let inner = self.inner.read().unwrap();
+		for (path, health) in &inner.routes {
+			let _ = writeln!(report, "{path}: score: {:.2} ({})", health.score, health.factors);
+		}
+		report
+	}
+}
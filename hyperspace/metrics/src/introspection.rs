@@ -0,0 +1,120 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! gRPC service exposing the packets that the metrics handlers believe are outstanding.
+
+use crate::{
+	handler::{OutstandingPackets, PacketMap},
+	Error,
+};
+use proto::{
+	introspection_server::{IntrospectionServer, IntrospectionService},
+	ChainOutstandingPackets, QueryOutstandingPacketsRequest, QueryOutstandingPacketsResponse,
+	SentPacket, UndeliveredSequences,
+};
+use std::{net::SocketAddr, time::Instant};
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+	tonic::include_proto!("hyperspace.introspection.v1");
+
+	pub use introspection_server::Introspection as IntrospectionService;
+}
+
+struct Introspection {
+	chains: Vec<(String, OutstandingPackets)>,
+}
+
+fn sent_packets(map: &PacketMap, now: Instant) -> Vec<SentPacket> {
+	let mut packets = map
+		.lock()
+		.unwrap()
+		.iter()
+		.map(|(id, sent_at)| SentPacket {
+			sequence: id.sequence.0,
+			destination_channel: id.destination_channel.to_string(),
+			destination_port: id.destination_port.to_string(),
+			age_ms: now.duration_since(*sent_at).as_millis() as u64,
+		})
+		.collect::<Vec<_>>();
+	packets.sort_by(|a, b| {
+		(&a.destination_channel, &a.destination_port, a.sequence).cmp(&(
+			&b.destination_channel,
+			&b.destination_port,
+			b.sequence,
+		))
+	});
+	packets
+}
+
+fn chain_outstanding_packets(
+	name: &str,
+	outstanding: &OutstandingPackets,
+) -> ChainOutstandingPackets {
+	let now = Instant::now();
+	let mut undelivered_sequences = outstanding
+		.undelivered_sequences
+		.lock()
+		.unwrap()
+		.iter()
+		.map(|((channel_id, port_id), seqs)| UndeliveredSequences {
+			channel_id: channel_id.to_string(),
+			port_id: port_id.to_string(),
+			packets: seqs.packets.clone(),
+			acknowledgements: seqs.acknowledgements.clone(),
+		})
+		.collect::<Vec<_>>();
+	undelivered_sequences
+		.sort_by(|a, b| (&a.channel_id, &a.port_id).cmp(&(&b.channel_id, &b.port_id)));
+
+	ChainOutstandingPackets {
+		chain: name.to_string(),
+		last_sent_packets: sent_packets(&outstanding.last_sent_packet_time, now),
+		last_sent_acknowledgements: sent_packets(&outstanding.last_sent_acknowledgment_time, now),
+		last_sent_timeouts: sent_packets(&outstanding.last_sent_timeout_packet_time, now),
+		undelivered_sequences,
+	}
+}
+
+#[tonic::async_trait]
+impl IntrospectionService for Introspection {
+	async fn outstanding_packets(
+		&self,
+		request: Request<QueryOutstandingPacketsRequest>,
+	) -> Result<Response<QueryOutstandingPacketsResponse>, Status> {
+		let chain = request.into_inner().chain;
+		let chains = self
+			.chains
+			.iter()
+			.filter(|(name, _)| chain.is_empty() || *name == chain)
+			.map(|(name, outstanding)| chain_outstanding_packets(name, outstanding))
+			.collect::<Vec<_>>();
+		if chains.is_empty() && !chain.is_empty() {
+			return Err(Status::not_found(format!("Unknown chain {chain}")))
+		}
+		Ok(Response::new(QueryOutstandingPacketsResponse { chains }))
+	}
+}
+
+/// Starts a gRPC server exposing the outstanding packets of the given chains.
+pub async fn init_introspection(
+	addr: SocketAddr,
+	chains: Vec<(String, OutstandingPackets)>,
+) -> Result<(), Error> {
+	tonic::transport::Server::builder()
+		.add_service(IntrospectionServer::new(Introspection { chains }))
+		.serve(addr)
+		.await
+		.map_err(Into::into)
+}
@@ -14,7 +14,16 @@
 
 pub mod data;
 pub mod handler;
-
+pub mod health;
+#[cfg(feature = "introspection")]
+pub mod introspection;
+#[cfg(tokio_unstable)]
+pub mod runtime;
+pub mod score;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+
+use health::HealthState;
 use hyper::{
 	http::StatusCode,
 	server::Server,
@@ -55,53 +64,85 @@ pub enum Error {
 	#[error(transparent)]
 	Io(#[from] std::io::Error),
 
+	/// gRPC transport error.
+	#[cfg(feature = "introspection")]
+	#[error(transparent)]
+	Transport(#[from] tonic::transport::Error),
+
 	#[error("Prometheus port {0} already in use.")]
 	PortInUse(SocketAddr),
 }
 
-async fn request_metrics(req: Request<Body>, registry: Registry) -> Result<Response<Body>, Error> {
-	if req.uri().path() == "/metrics" {
-		let metric_families = registry.gather();
-		let mut buffer = vec![];
-		let encoder = TextEncoder::new();
-		encoder.encode(&metric_families, &mut buffer).unwrap();
+fn health_response(ok: bool, health: &HealthState) -> Result<Response<Body>, Error> {
+	let status = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+	Response::builder()
+		.status(status)
+		.header("Content-Type", "text/plain")
+		.body(Body::from(health.report()))
+		.map_err(Error::Http)
+}
 
-		Response::builder()
+async fn request_metrics(
+	req: Request<Body>,
+	registry: Registry,
+	health: HealthState,
+) -> Result<Response<Body>, Error> {
+	match req.uri().path() {
+		"/metrics" => {
+			let metric_families = registry.gather();
+			let mut buffer = vec![];
+			let encoder = TextEncoder::new();
+			encoder.encode(&metric_families, &mut buffer).unwrap();
+
+			Response::builder()
+				.status(StatusCode::OK)
+				.header("Content-Type", encoder.format_type())
+				.body(Body::from(buffer))
+				.map_err(Error::Http)
+		},
+		"/healthz" => health_response(health.is_live(), &health),
+		"/readyz" => health_response(health.is_ready(), &health),
+		"/status" => Response::builder()
 			.status(StatusCode::OK)
-			.header("Content-Type", encoder.format_type())
-			.body(Body::from(buffer))
-			.map_err(Error::Http)
-	} else {
-		Response::builder()
+			.header("Content-Type", "text/plain")
+			.body(Body::from(health.status_report()))
+			.map_err(Error::Http),
+		_ => Response::builder()
 			.status(StatusCode::NOT_FOUND)
 			.body(Body::from("Not found."))
-			.map_err(Error::Http)
+			.map_err(Error::Http),
 	}
 }
 
 /// Initializes the metrics context, and starts an HTTP server
-/// to serve metrics.
-pub async fn init_prometheus(prometheus_addr: SocketAddr, registry: Registry) -> Result<(), Error> {
+/// to serve metrics and the `/healthz`, `/readyz` and `/status` endpoints.
+pub async fn init_prometheus(
+	prometheus_addr: SocketAddr,
+	registry: Registry,
+	health: HealthState,
+) -> Result<(), Error> {
 	let listener = tokio::net::TcpListener::bind(&prometheus_addr)
 		.await
 		.map_err(|_| Error::PortInUse(prometheus_addr))?;
 
-	init_prometheus_with_listener(listener, registry).await
+	init_prometheus_with_listener(listener, registry, health).await
 }
 
 /// Init prometheus using the given listener.
 async fn init_prometheus_with_listener(
 	listener: tokio::net::TcpListener,
 	registry: Registry,
+	health: HealthState,
 ) -> Result<(), Error> {
 	let listener = hyper::server::conn::AddrIncoming::from_listener(listener)?;
 
 	let service = make_service_fn(move |_| {
 		let registry = registry.clone();
+		let health = health.clone();
 
 		async move {
 			Ok::<_, hyper::Error>(service_fn(move |req: Request<Body>| {
-				request_metrics(req, registry.clone())
+				request_metrics(req, registry.clone(), health.clone())
 			}))
 		}
 	});
@@ -110,3 +151,13 @@ async fn init_prometheus_with_listener(
 
 	server.await.map_err(Into::into)
 }
+
+/// Fetches the `/status` report of a relayer running with its Prometheus server on `addr`.
+pub async fn query_status(addr: SocketAddr) -> Result<String, Error> {
+	let uri = format!("http://{addr}/status")
+		.parse::<hyper::Uri>()
+		.map_err(hyper::http::Error::from)?;
+	let response = hyper::Client::new().get(uri).await?;
+	let body = hyper::body::to_bytes(response.into_body()).await?;
+	Ok(String::from_utf8_lossy(&body).into_owned())
+}
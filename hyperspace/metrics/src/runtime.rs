@@ -0,0 +1,174 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metrics of the tokio runtime. The runtime metrics are only available when building with
+//! `RUSTFLAGS="--cfg tokio_unstable"`.
+
+use super::*;
+use crate::register;
+use std::time::Duration;
+use tokio::runtime::Handle;
+
+/// Interval between two samples of the runtime metrics.
+pub const RUNTIME_METRICS_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct RuntimeMetrics {
+	/// Number of worker threads of the runtime.
+	pub workers: Gauge<U64>,
+	/// Number of tasks alive in the runtime.
+	pub active_tasks: Gauge<U64>,
+	/// Number of threads of the blocking pool.
+	pub blocking_threads: Gauge<U64>,
+	/// Number of idle threads of the blocking pool.
+	pub idle_blocking_threads: Gauge<U64>,
+	/// Number of tasks in the global queue of the runtime.
+	pub injection_queue_depth: Gauge<U64>,
+	/// Number of tasks waiting for a thread of the blocking pool.
+	pub blocking_queue_depth: Gauge<U64>,
+	/// Number of tasks in the local queue of each worker.
+	pub worker_local_queue_depth: GaugeVec<U64>,
+	/// Mean duration of the task polls of each worker, in microseconds.
+	pub worker_mean_poll_time: GaugeVec<F64>,
+	/// Total number of task polls of each worker.
+	pub worker_polls: CounterVec<U64>,
+	/// Total time each worker has been busy, in milliseconds.
+	pub worker_busy_duration: CounterVec<U64>,
+}
+
+impl RuntimeMetrics {
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			workers: register(
+				Gauge::new(
+					"hyperspace_runtime_workers",
+					"Number of worker threads of the runtime",
+				)?,
+				registry,
+			)?,
+			active_tasks: register(
+				Gauge::new(
+					"hyperspace_runtime_active_tasks",
+					"Number of tasks alive in the runtime",
+				)?,
+				registry,
+			)?,
+			blocking_threads: register(
+				Gauge::new(
+					"hyperspace_runtime_blocking_threads",
+					"Number of threads of the blocking pool",
+				)?,
+				registry,
+			)?,
+			idle_blocking_threads: register(
+				Gauge::new(
+					"hyperspace_runtime_idle_blocking_threads",
+					"Number of idle threads of the blocking pool",
+				)?,
+				registry,
+			)?,
+			injection_queue_depth: register(
+				Gauge::new(
+					"hyperspace_runtime_injection_queue_depth",
+					"Number of tasks in the global queue of the runtime",
+				)?,
+				registry,
+			)?,
+			blocking_queue_depth: register(
+				Gauge::new(
+					"hyperspace_runtime_blocking_queue_depth",
+					"Number of tasks waiting for a thread of the blocking pool",
+				)?,
+				registry,
+			)?,
+			worker_local_queue_depth: register(
+				GaugeVec::new(
+					Opts::new(
+						"hyperspace_runtime_worker_local_queue_depth",
+						"Number of tasks in the local queue of the worker",
+					),
+					&["worker"],
+				)?,
+				registry,
+			)?,
+			worker_mean_poll_time: register(
+				GaugeVec::new(
+					Opts::new(
+						"hyperspace_runtime_worker_mean_poll_time",
+						"Mean duration of the task polls of the worker, in microseconds",
+					),
+					&["worker"],
+				)?,
+				registry,
+			)?,
+			worker_polls: register(
+				CounterVec::new(
+					Opts::new(
+						"hyperspace_runtime_worker_polls",
+						"Total number of task polls of the worker",
+					),
+					&["worker"],
+				)?,
+				registry,
+			)?,
+			worker_busy_duration: register(
+				CounterVec::new(
+					Opts::new(
+						"hyperspace_runtime_worker_busy_duration",
+						"Total time the worker has been busy, in milliseconds",
+					),
+					&["worker"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	/// Updates the metrics with the current state of the runtime.
+	pub fn update(&self, metrics: &tokio::runtime::RuntimeMetrics) {
+		self.workers.set(metrics.num_workers() as u64);
+		self.active_tasks.set(metrics.active_tasks_count() as u64);
+		self.blocking_threads.set(metrics.num_blocking_threads() as u64);
+		self.idle_blocking_threads.set(metrics.num_idle_blocking_threads() as u64);
+		self.injection_queue_depth.set(metrics.injection_queue_depth() as u64);
+		self.blocking_queue_depth.set(metrics.blocking_queue_depth() as u64);
+		for worker in 0..metrics.num_workers() {
+			let label = worker.to_string();
+			self.worker_local_queue_depth
+				.with_label_values(&[&label])
+				.set(metrics.worker_local_queue_depth(worker) as u64);
+			self.worker_mean_poll_time
+				.with_label_values(&[&label])
+				.set(metrics.worker_mean_poll_time(worker).as_micros() as f64);
+			// the runtime reports totals, so only the difference since the last update is added
+			let polls = self.worker_polls.with_label_values(&[&label]);
+			polls.inc_by(metrics.worker_poll_count(worker).saturating_sub(polls.get()));
+			let busy_duration = self.worker_busy_duration.with_label_values(&[&label]);
+			busy_duration.inc_by(
+				(metrics.worker_total_busy_duration(worker).as_millis() as u64)
+					.saturating_sub(busy_duration.get()),
+			);
+		}
+	}
+
+	/// Periodically samples the metrics of the current runtime.
+	pub async fn observe(self, interval: Duration) {
+		let metrics = Handle::current().metrics();
+		let mut interval = tokio::time::interval(interval);
+		loop {
+			interval.tick().await;
+			self.update(&metrics);
+		}
+	}
+}
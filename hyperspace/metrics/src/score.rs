@@ -0,0 +1,223 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Composite health score of the relayed paths.
+//!
+//! Each path is scored from a few factors between `0` (unhealthy) and `1` (healthy): the freshness
+//! of the light clients, the backlog of undelivered packets, the error rate of the submissions
+//! and the balance headroom of the relayer accounts. The score is the weighted average of the
+//! factors, so that a dashboard can show one number per path.
+
+use crate::{register, Gauge, GaugeVec, Opts, PrometheusError, Registry, F64};
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+fn default_weight() -> f64 {
+	1.0
+}
+
+fn default_stale_client_secs() -> u64 {
+	60 * 60
+}
+
+fn default_max_backlog() -> u64 {
+	100
+}
+
+fn default_interval_secs() -> u64 {
+	60
+}
+
+/// Weights of the factors in the health score. A weight of `0` ignores the factor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthScoreWeights {
+	#[serde(default = "default_weight")]
+	pub client_freshness: f64,
+	#[serde(default = "default_weight")]
+	pub backlog: f64,
+	#[serde(default = "default_weight")]
+	pub error_rate: f64,
+	#[serde(default = "default_weight")]
+	pub balance_headroom: f64,
+}
+
+impl Default for HealthScoreWeights {
+	fn default() -> Self {
+		Self {
+			client_freshness: default_weight(),
+			backlog: default_weight(),
+			error_rate: default_weight(),
+			balance_headroom: default_weight(),
+		}
+	}
+}
+
+/// Settings of the health score of the paths.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthScoreConfig {
+	#[serde(default)]
+	pub weights: HealthScoreWeights,
+	/// Age of the last client update at which the client freshness drops to 0, in seconds
+	#[serde(default = "default_stale_client_secs")]
+	pub stale_client_secs: u64,
+	/// Number of undelivered packets and acknowledgements at which the backlog factor drops to 0
+	#[serde(default = "default_max_backlog")]
+	pub max_backlog: u64,
+	/// Interval between two updates of the scores, in seconds
+	#[serde(default = "default_interval_secs")]
+	pub interval_secs: u64,
+}
+
+impl Default for HealthScoreConfig {
+	fn default() -> Self {
+		Self {
+			weights: Default::default(),
+			stale_client_secs: default_stale_client_secs(),
+			max_backlog: default_max_backlog(),
+			interval_secs: default_interval_secs(),
+		}
+	}
+}
+
+/// Factors of the health score of a path, each between `0` and `1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthFactors {
+	/// Freshness of the least recently updated light client of the path
+	pub client_freshness: f64,
+	/// Headroom before the backlog of undelivered packets and acknowledgements reaches the maximum
+	pub backlog: f64,
+	/// Share of the recent submissions that succeeded, `1` if nothing was submitted
+	pub error_rate: f64,
+	/// Headroom of the relayer account with the lowest balance, over its minimum balance. `None`
+	/// if no minimum balance is configured or the balances couldn't be queried
+	pub balance_headroom: Option<f64>,
+}
+
+impl HealthFactors {
+	pub fn new(
+		config: &HealthScoreConfig,
+		client_update_age: Duration,
+		backlog: u64,
+		error_rate: f64,
+		balance_headroom: Option<f64>,
+	) -> Self {
+		Self {
+			client_freshness: 1.0 -
+				ratio(client_update_age.as_secs_f64(), config.stale_client_secs as f64),
+			backlog: 1.0 - ratio(backlog as f64, config.max_backlog as f64),
+			error_rate: 1.0 - error_rate.clamp(0.0, 1.0),
+			balance_headroom: balance_headroom.map(|headroom| headroom.clamp(0.0, 1.0)),
+		}
+	}
+
+	/// Weighted average of the factors. Factors that are unknown are left out.
+	pub fn score(&self, weights: &HealthScoreWeights) -> f64 {
+		let factors = [
+			(Some(self.client_freshness), weights.client_freshness),
+			(Some(self.backlog), weights.backlog),
+			(Some(self.error_rate), weights.error_rate),
+			(self.balance_headroom, weights.balance_headroom),
+		];
+		let (sum, total_weight) = factors
+			.into_iter()
+			.filter_map(|(factor, weight)| Some((factor?, weight.max(0.0))))
+			.fold((0.0, 0.0), |(sum, total), (factor, weight)| {
+				(sum + factor * weight, total + weight)
+			});
+		if total_weight == 0.0 {
+			return 1.0
+		}
+		sum / total_weight
+	}
+}
+
+impl fmt::Display for HealthFactors {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"client_freshness: {:.2}, backlog: {:.2}, error_rate: {:.2}, balance_headroom: ",
+			self.client_freshness, self.backlog, self.error_rate
+		)?;
+		match self.balance_headroom {
+			Some(headroom) => write!(f, "{headroom:.2}"),
+			None => write!(f, "n/a"),
+		}
+	}
+}
+
+/// Headroom of a balance over its minimum: `0` at or below the minimum, `1` at twice the minimum
+/// or above.
+pub fn balance_headroom(balance: u128, min_balance: u128) -> f64 {
+	if min_balance == 0 {
+		return 1.0
+	}
+	ratio(balance.saturating_sub(min_balance) as f64, min_balance as f64)
+}
+
+/// `value / max`, clamped between `0` and `1`.
+fn ratio(value: f64, max: f64) -> f64 {
+	if max <= 0.0 {
+		return if value > 0.0 { 1.0 } else { 0.0 }
+	}
+	(value / max).clamp(0.0, 1.0)
+}
+
+/// Gauges of the health score of a path.
+#[derive(Clone)]
+pub struct RouteHealthMetrics {
+	/// Health score of the path.
+	pub score: Gauge<F64>,
+	/// Factors of the health score of the path.
+	pub factors: GaugeVec<F64>,
+}
+
+impl RouteHealthMetrics {
+	pub fn register(path: &str, registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			score: register(
+				Gauge::with_opts(
+					Opts::new(
+						"hyperspace_route_health_score",
+						"Composite health score of the path, between 0 and 1",
+					)
+					.const_label("path", path.to_string()),
+				)?,
+				registry,
+			)?,
+			factors: register(
+				GaugeVec::new(
+					Opts::new(
+						"hyperspace_route_health_factor",
+						"Factors of the health score of the path, between 0 and 1",
+					)
+					.const_label("path", path.to_string()),
+					&["factor"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	pub fn observe(&self, factors: &HealthFactors, score: f64) {
+		self.score.set(score);
+		self.factors
+			.with_label_values(&["client_freshness"])
+			.set(factors.client_freshness);
+		self.factors.with_label_values(&["backlog"]).set(factors.backlog);
+		self.factors.with_label_values(&["error_rate"]).set(factors.error_rate);
+		if let Some(headroom) = factors.balance_headroom {
+			self.factors.with_label_values(&["balance_headroom"]).set(headroom);
+		}
+	}
+}
@@ -0,0 +1,145 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! StatsD exporter, mirroring the metrics of a prometheus [`Registry`] to a StatsD endpoint.
+
+use crate::Error;
+use prometheus::{
+	proto::{Metric, MetricFamily, MetricType},
+	Registry,
+};
+use std::{collections::HashMap, future::Future, net::SocketAddr, time::Duration};
+use tokio::net::UdpSocket;
+
+/// Maximum size of a single UDP datagram sent to the StatsD endpoint.
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+/// Periodically gathers the metrics from the registry and sends them to the StatsD endpoint.
+///
+/// Gauges are sent as StatsD gauges. Counters, and the count and sum of histograms, are sent as
+/// StatsD counters with the increment since the previous flush. Labels are appended to the metric
+/// name, graphite style.
+///
+/// The metrics are flushed a last time once `shutdown` resolves, then the exporter returns.
+pub async fn init_statsd(
+	statsd_addr: SocketAddr,
+	prefix: Option<String>,
+	registry: Registry,
+	flush_interval: Duration,
+	shutdown: impl Future<Output = ()>,
+) -> Result<(), Error> {
+	let bind_addr: SocketAddr = if statsd_addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }
+		.parse()
+		.expect("valid address");
+	let socket = UdpSocket::bind(bind_addr).await?;
+	socket.connect(statsd_addr).await?;
+
+	let mut previous = HashMap::new();
+	let mut interval = tokio::time::interval(flush_interval);
+	tokio::pin!(shutdown);
+	loop {
+		let stop = tokio::select! {
+			_ = interval.tick() => false,
+			_ = &mut shutdown => true,
+		};
+		let lines = encode(&registry.gather(), prefix.as_deref(), &mut previous);
+		for datagram in into_datagrams(lines) {
+			if let Err(e) = socket.send(datagram.as_bytes()).await {
+				log::warn!(target: "hyperspace", "Failed to send metrics to StatsD endpoint {statsd_addr}: {e}");
+			}
+		}
+		if stop {
+			return Ok(())
+		}
+	}
+}
+
+fn series_name(prefix: Option<&str>, name: &str, metric: &Metric) -> String {
+	let mut series = match prefix {
+		Some(prefix) => format!("{prefix}.{name}"),
+		None => name.to_string(),
+	};
+	for label in metric.get_label() {
+		series.push('.');
+		series.extend(label.get_value().chars().map(|c| match c {
+			'.' | ':' | '|' | '@' | ' ' => '_',
+			c => c,
+		}));
+	}
+	series
+}
+
+fn counter_delta(previous: &mut HashMap<String, f64>, series: String, value: f64) -> f64 {
+	let last = previous.insert(series, value).unwrap_or_default();
+	// the counter may have been reset
+	if value >= last {
+		value - last
+	} else {
+		value
+	}
+}
+
+fn encode(
+	families: &[MetricFamily],
+	prefix: Option<&str>,
+	previous: &mut HashMap<String, f64>,
+) -> Vec<String> {
+	let mut lines = vec![];
+	for family in families {
+		for metric in family.get_metric() {
+			let series = series_name(prefix, family.get_name(), metric);
+			match family.get_field_type() {
+				MetricType::COUNTER => {
+					let value = metric.get_counter().get_value();
+					let delta = counter_delta(previous, series.clone(), value);
+					lines.push(format!("{series}:{delta}|c"));
+				},
+				MetricType::GAUGE => {
+					lines.push(format!("{series}:{}|g", metric.get_gauge().get_value()));
+				},
+				MetricType::HISTOGRAM => {
+					let histogram = metric.get_histogram();
+					let count_series = format!("{series}.count");
+					let count = histogram.get_sample_count() as f64;
+					let delta = counter_delta(previous, count_series.clone(), count);
+					lines.push(format!("{count_series}:{delta}|c"));
+					let sum_series = format!("{series}.sum");
+					let sum = histogram.get_sample_sum();
+					let delta = counter_delta(previous, sum_series.clone(), sum);
+					lines.push(format!("{sum_series}:{delta}|c"));
+				},
+				_ => (),
+			}
+		}
+	}
+	lines
+}
+
+fn into_datagrams(lines: Vec<String>) -> Vec<String> {
+	let mut datagrams = vec![];
+	let mut current = String::new();
+	for line in lines {
+		if !current.is_empty() && current.len() + line.len() + 1 > MAX_DATAGRAM_SIZE {
+			datagrams.push(std::mem::take(&mut current));
+		}
+		if !current.is_empty() {
+			current.push('\n');
+		}
+		current.push_str(&line);
+	}
+	if !current.is_empty() {
+		datagrams.push(current);
+	}
+	datagrams
+}
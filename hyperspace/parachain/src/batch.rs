@@ -0,0 +1,113 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Submission of the message bundles in `utility` batches.
+//!
+//! By default the messages of a bundle are delivered with a single `ibc.deliver` call. With a
+//! [`BatchMode`] in the config, each message is delivered with its own `ibc.deliver` call, and the
+//! calls are wrapped in a `utility.batch_all` or `utility.force_batch` call:
+//! - `batch_all` dispatches the bundle atomically: the extrinsic fails and none of the messages are
+//!   delivered if one of them fails, the bundle then being bisected by the relayer to isolate the
+//!   failing messages
+//! - `force_batch` dispatches all the messages, the ones that failed being reported by the
+//!   `utility.ItemFailed` events of the extrinsic, see [`failed_items`]
+//!
+//! The batch calls are encoded with the indices of the metadata of the chain, so any runtime with
+//! the `utility` pallet is supported.
+
+use crate::dynamic::IbcCall;
+use codec::{Compact, Encode};
+use serde::{Deserialize, Serialize};
+use subxt::{
+	blocks::ExtrinsicEvents, error::MetadataError, metadata::Metadata, tx::TxPayload, Error,
+};
+
+/// Pallet of the batch calls
+const UTILITY_PALLET: &str = "Utility";
+
+/// Call of the `utility` pallet the messages are batched with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchMode {
+	/// `utility.batch_all`, reverting the whole bundle if a message fails
+	#[serde(rename = "batch_all")]
+	BatchAll,
+	/// `utility.force_batch`, dispatching all the messages of the bundle
+	#[serde(rename = "force_batch")]
+	ForceBatch,
+}
+
+impl BatchMode {
+	fn call_name(&self) -> &'static str {
+		match self {
+			Self::BatchAll => "batch_all",
+			Self::ForceBatch => "force_batch",
+		}
+	}
+}
+
+/// The call delivering a bundle of messages.
+pub enum DeliverCall<C> {
+	/// One `ibc.deliver` call with all the messages
+	Single(IbcCall<C>),
+	/// A `utility` batch of `ibc.deliver` calls, one per message
+	Batch(BatchMode, Vec<IbcCall<C>>),
+}
+
+impl<C> TxPayload for DeliverCall<C>
+where
+	IbcCall<C>: TxPayload,
+{
+	fn encode_call_data_to(&self, metadata: &Metadata, out: &mut Vec<u8>) -> Result<(), Error> {
+		let (mode, calls) = match self {
+			Self::Single(call) => return call.encode_call_data_to(metadata, out),
+			Self::Batch(mode, calls) => (mode, calls),
+		};
+		let pallet = metadata.pallet_by_name_err(UTILITY_PALLET)?;
+		let call = pallet
+			.call_variant_by_name(mode.call_name())
+			.ok_or_else(|| MetadataError::CallNameNotFound(mode.call_name().to_string()))?;
+		pallet.index().encode_to(out);
+		call.index.encode_to(out);
+		// the call data of the inner calls are their encodings as runtime calls
+		Compact(calls.len() as u32).encode_to(out);
+		for call in calls {
+			call.encode_call_data_to(metadata, out)?;
+		}
+		Ok(())
+	}
+}
+
+/// Returns the indices of the calls of a `force_batch` that failed, with their errors, from the
+/// `utility.ItemCompleted` and `utility.ItemFailed` events the batch emits for each of its calls.
+pub fn failed_items<T: subxt::Config>(
+	events: &ExtrinsicEvents<T>,
+) -> Result<Vec<(usize, String)>, Error> {
+	let mut item = 0;
+	let mut failed = vec![];
+	for event in events.iter() {
+		let event = event?;
+		if event.pallet_name() != UTILITY_PALLET {
+			continue
+		}
+		match event.variant_name() {
+			"ItemCompleted" => item += 1,
+			"ItemFailed" => {
+				failed.push((item, format!("{:?}", event.field_values()?)));
+				item += 1;
+			},
+			_ => (),
+		}
+	}
+	Ok(failed)
+}
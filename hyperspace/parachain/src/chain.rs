@@ -12,12 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{error::Error, signer::ExtrinsicSigner, ParachainClient};
-use crate::{parachain::UncheckedExtrinsic, provider::TransactionId, FinalityProtocol};
+use super::{error::Error, ParachainClient, SecondaryRelayChain};
+use crate::{
+	batch::{failed_items, BatchMode},
+	dynamic,
+	provider::TransactionId,
+	relay_chain::{Justifications, RelayChainConnection},
+	runtime_upgrade::RuntimeUpgrades,
+	utils::{fetch_max_extrinsic_weight, fold_proof_size},
+	FinalityProtocol,
+};
 use anyhow::anyhow;
 use codec::{Decode, Encode};
 use finality_grandpa::BlockNumberOps;
-use finality_grandpa_rpc::GrandpaApiClient;
 use futures::{Stream, StreamExt, TryFutureExt};
 use grandpa_light_client_primitives::{FinalityProof, ParachainHeaderProofs};
 use ibc::{
@@ -39,21 +46,22 @@ use jsonrpsee_ws_client::WsClientBuilder;
 use light_client_common::config::{EventRecordT, RuntimeCall, RuntimeTransactions};
 use pallet_ibc::light_clients::AnyClientMessage;
 use primitives::{
-	mock::LocalClientTypes, Chain, CommonClientState, IbcProvider, MisbehaviourHandler,
+	correlation::packet_tags, display::TokenDisplay, mock::LocalClientTypes, BatchLimits, Chain,
+	CommonClientState, IbcProvider, MisbehaviourHandler,
+};
+use sp_core::{
+	crypto::{AccountId32, Ss58Codec},
+	twox_128, H256,
 };
-use sc_consensus_beefy_rpc::BeefyApiClient;
-use sp_core::{twox_128, H256};
 use sp_runtime::{
 	traits::{IdentifyAccount, One, Verify},
 	MultiSignature, MultiSigner,
 };
 use std::{collections::BTreeMap, fmt::Display, pin::Pin, sync::Arc, time::Duration};
 use subxt::{
-	config::{
-		extrinsic_params::{BaseExtrinsicParamsBuilder, Era},
-		ExtrinsicParams, Header as HeaderT, Header,
-	},
+	config::{ExtrinsicParams, Header as HeaderT, Header},
 	events::Phase,
+	rpc::types::DryRunResult,
 };
 use tokio::time::sleep;
 use transaction_payment_rpc::TransactionPaymentApiClient;
@@ -66,10 +74,6 @@ type GrandpaJustification = grandpa_light_client_primitives::justification::Gran
 type BeefyJustification =
 	beefy_primitives::SignedCommitment<u32, beefy_primitives::crypto::Signature>;
 
-/// An encoded justification proving that the given header has been finalized
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-struct JustificationNotification(sp_core::Bytes);
-
 #[async_trait::async_trait]
 impl<T: light_client_common::config::Config + Send + Sync + Clone + 'static> Chain
 	for ParachainClient<T>
@@ -88,8 +92,7 @@ where
 	BTreeMap<sp_core::H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
 	sp_core::H256: From<T::Hash>,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	<T as subxt::Config>::AccountId: Send + Sync,
 	<T as subxt::Config>::Address: Send + Sync,
 	<T as light_client_common::config::Config>::AssetId: Clone,
@@ -99,41 +102,80 @@ where
 	}
 
 	fn block_max_weight(&self) -> u64 {
-		self.max_extrinsic_weight * 100 / 80
+		self.max_extrinsic_weight.ref_time() * 100 / 80
 	}
 
 	async fn estimate_weight(&self, messages: Vec<Any>) -> Result<u64, Self::Error> {
-		let extrinsic = {
-			// todo: put this in utils
-			let signer = ExtrinsicSigner::<T, Self>::new(
-				self.key_store.clone(),
-				self.key_type_id.clone(),
-				self.public_key.clone(),
-			);
+		let weight = self.query_dispatch_info(messages).await?.weight;
+		Ok(fold_proof_size(weight, self.max_extrinsic_weight))
+	}
 
-			let messages = messages
-				.into_iter()
-				.map(|msg| Any { type_url: msg.type_url.clone(), value: msg.value })
-				.collect::<Vec<_>>();
+	async fn estimate_fee(&self, messages: Vec<Any>) -> Result<u128, Self::Error> {
+		Ok(self.query_dispatch_info(messages).await?.partial_fee)
+	}
 
-			let tx_params = BaseExtrinsicParamsBuilder::new()
-				.tip(T::Tip::from(100_000u128))
-				.era(Era::Immortal, self.para_client.genesis_hash());
-			let call = T::Tx::ibc_deliver(messages);
-			self.para_client
-				.tx()
-				.create_signed(&call, &signer, tx_params.into())
-				.await?
-				.encoded()
-				.to_vec()
+	async fn query_batch_limits(&self) -> Result<BatchLimits, Self::Error> {
+		let max_extrinsic_weight = fetch_max_extrinsic_weight(&self.para_client).await?;
+		Ok(BatchLimits {
+			max_weight: Some(max_extrinsic_weight.ref_time().saturating_mul(100) / 80),
+			..Default::default()
+		})
+	}
+
+	async fn query_relayer_balance(&self) -> Result<u128, Self::Error> {
+		use subxt::ext::scale_value::At;
+
+		// the account layout is the same on all the runtimes, so it's queried dynamically rather
+		// than through the storage items of the config
+		let account = self.public_key.clone().into_account();
+		let account_addr = subxt::dynamic::storage(
+			"System",
+			"Account",
+			vec![subxt::dynamic::Value::from_bytes(<[u8; 32]>::from(account))],
+		);
+		let account_info =
+			self.para_client.storage().at_latest().await?.fetch(&account_addr).await?;
+		let Some(account_info) = account_info else { return Ok(0) };
+		account_info
+			.to_value()?
+			.at("data")
+			.at("free")
+			.and_then(|free| free.as_u128())
+			.ok_or_else(|| {
+				Error::from("Failed to decode the free balance of the relayer".to_string())
+			})
+	}
+
+	async fn query_token_display(&self, denom: Option<&str>) -> Result<TokenDisplay, Self::Error> {
+		// the properties of the chain only describe the native token, the tokens received over IBC
+		// are displayed with their path
+		let properties = self.para_client.rpc().system_properties().await?;
+		let first = |key: &str| match properties.get(key) {
+			Some(serde_json::Value::Array(values)) => values.first().cloned(),
+			value => value.cloned(),
 		};
-		let dispatch_info = TransactionPaymentApiClient::<
-			H256,
-			RuntimeDispatchInfo<u128, sp_weights::Weight>,
-		>::query_info(&*self.para_ws_client, extrinsic.into(), None)
-		.await
-		.map_err(|e| Error::from(format!("Rpc Error From Estimating weight {:?}", e)))?;
-		Ok(dispatch_info.weight.ref_time())
+		let symbol = first("tokenSymbol").and_then(|symbol| symbol.as_str().map(str::to_string));
+		let decimals = first("tokenDecimals").and_then(|decimals| decimals.as_u64());
+		match (symbol, decimals) {
+			(Some(symbol), Some(decimals))
+				if denom.map_or(true, |denom| denom.eq_ignore_ascii_case(&symbol)) =>
+				Ok(TokenDisplay { symbol, decimals: decimals.min(u8::MAX as u64) as u8 }),
+			_ => Ok(TokenDisplay::raw(denom.unwrap_or_default())),
+		}
+	}
+
+	fn format_account(&self, account: &str) -> String {
+		let account_id = match account.strip_prefix("0x") {
+			Some(hex) => hex::decode(hex)
+				.ok()
+				.and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+				.map(AccountId32::from),
+			None => AccountId32::from_ss58check_with_version(account).ok().map(|(id, _)| id),
+		};
+		account_id.map_or_else(
+			|| account.to_string(),
+			|id| id.to_ss58check_with_version(self.ss58_version),
+		)
 	}
 
 	async fn finality_notifications(
@@ -142,26 +184,21 @@ where
 		Pin<Box<dyn Stream<Item = <Self as IbcProvider>::FinalityEvent> + Send + Sync>>,
 		Error,
 	> {
+		// the notifications end when the client can return to the primary relay chain rpc, see
+		// [`ParachainClient::relay_chain_endpoints`]
+		let restored = self.primary_relay_chain_restored.clone();
+		let primary_restored = async move { restored.notified().await };
 		match self.finality_protocol {
 			FinalityProtocol::Grandpa => {
-				let subscription =
-					GrandpaApiClient::<JustificationNotification, sp_core::H256, u32>::subscribe_justifications(
-						&*self.relay_ws_client,
-					)
-						.await?
-						.chunks(3)
-						.map(|mut notifs| notifs.remove(notifs.len() - 1)); // skip every 3 finality notifications
-
-				let stream = subscription.filter_map(|justification_notif| {
-					let encoded_justification = match justification_notif {
-						Ok(JustificationNotification(sp_core::Bytes(justification))) =>
-							justification,
-						Err(err) => {
-							log::error!("Failed to fetch Justification: {}", err);
-							return futures::future::ready(None)
-						},
-					};
+				let subscription = self
+					.relay_chain
+					.justifications(Justifications::Grandpa)
+					.await?
+					.take_until(primary_restored)
+					.chunks(3)
+					.map(|mut notifs| notifs.remove(notifs.len() - 1)); // skip every 3 finality notifications
 
+				let stream = subscription.filter_map(|sp_core::Bytes(encoded_justification)| {
 					let justification =
 						match GrandpaJustification::decode(&mut &*encoded_justification) {
 							Ok(j) => j,
@@ -176,22 +213,14 @@ where
 				Ok(Box::pin(Box::new(stream)))
 			},
 			FinalityProtocol::Beefy => {
-				let subscription =
-					BeefyApiClient::<JustificationNotification, sp_core::H256>::subscribe_justifications(
-						&*self.relay_ws_client,
-					)
-						.await
-						.expect("Failed to subscribe to beefy justifications");
-
-				let stream = subscription.filter_map(|commitment_notification| {
-					let encoded_commitment = match commitment_notification {
-						Ok(JustificationNotification(sp_core::Bytes(commitment))) => commitment,
-						Err(err) => {
-							log::error!("Failed to fetch Commitment: {}", err);
-							return futures::future::ready(None)
-						},
-					};
-
+				let subscription = self
+					.relay_chain
+					.justifications(Justifications::Beefy)
+					.await
+					.expect("Failed to subscribe to beefy justifications")
+					.take_until(primary_restored);
+
+				let stream = subscription.filter_map(|sp_core::Bytes(encoded_commitment)| {
 					let signed_commitment =
 						match BeefyJustification::decode(&mut &*encoded_commitment) {
 							Ok(c) => c,
@@ -217,14 +246,48 @@ where
 		let messages_urls_c = messages_urls.clone();
 		log::debug!(target: "hyperspace_parachain", "Sending message: {messages_urls_c}");
 
-		let call = T::Tx::ibc_deliver(messages.clone());
-		let (ext_hash, block_hash) = self.submit_call(call).await?;
+		let call = self.deliver_call(messages.clone());
+		let (ext_hash, block_hash, events) = self.submit_call_with_events(call).await?;
 
 		log::debug!(target: "hyperspace_parachain", "Submitted extrinsic (hash: {:?}) to block {:?}", ext_hash, block_hash);
+		if self.batch_mode == Some(BatchMode::ForceBatch) {
+			for (index, error) in failed_items(&events)? {
+				let Some(message) = messages.get(index) else { continue };
+				log::warn!(
+					target: "hyperspace_parachain",
+					"Message {} {} of extrinsic {ext_hash:?} failed on {}: {error}",
+					message.type_url, packet_tags(std::slice::from_ref(message)), self.name
+				);
+			}
+		}
+		let packets = packet_tags(&messages);
+		if !packets.is_empty() {
+			log::info!(target: "hyperspace_parachain", "Delivered to {} in extrinsic {ext_hash:?} of block {block_hash:?}: {packets}", self.name);
+		}
 
 		Ok(TransactionId { ext_hash, block_hash })
 	}
 
+	async fn simulate(&self, messages: Vec<Any>) -> Result<(), Error> {
+		let messages = messages
+			.into_iter()
+			.map(|msg| Any { type_url: msg.type_url.clone(), value: msg.value })
+			.collect::<Vec<_>>();
+		let call = self.deliver_call(messages);
+		let extrinsic = self.create_signed(&call, None).await?;
+		match extrinsic.dry_run(None).await? {
+			DryRunResult::Success => {
+				log::info!(target: "hyperspace_parachain", "Dry-ran extrinsic on {}", self.name);
+				Ok(())
+			},
+			DryRunResult::DispatchError(e) =>
+				Err(Error::Custom(format!("Dry run of the extrinsic failed: {e:?}"))),
+			DryRunResult::TransactionValidityError => Err(Error::Custom(
+				"Dry run of the extrinsic failed: invalid transaction".to_string(),
+			)),
+		}
+	}
+
 	async fn query_client_message(&self, update: UpdateClient) -> Result<AnyClientMessage, Error> {
 		let host_height = update.height();
 
@@ -246,63 +309,60 @@ where
 			}
 		};
 
-		let mut storage_key = twox_128(b"System").to_vec();
-		storage_key.extend(twox_128(b"Events").to_vec());
+		let update_client = if self.dynamic_metadata {
+			let events = self.para_client.events().at(block_hash).await?;
+			find_update_client(
+				dynamic::ibc_events(&events)?
+					.into_iter()
+					.map(|(phase, events)| (phase, Some(events))),
+				&update,
+			)
+		} else {
+			let mut storage_key = twox_128(b"System").to_vec();
+			storage_key.extend(twox_128(b"Events").to_vec());
 
-		let event_bytes = self
-			.para_client
-			.rpc()
-			.storage(&*storage_key, Some(block_hash))
-			.await?
-			.map(|e| e.0)
-			.ok_or_else(|| Error::from("No events found".to_owned()))?;
-		let events: Vec<T::EventRecord> = Decode::decode(&mut &*event_bytes)
-			.map_err(|e| Error::from(format!("Failed to decode events: {:?}", e)))?;
-		let (transaction_index, event_index) = events
-			.into_iter()
-			.find_map(|pallet_event| {
-				let tx_index = match pallet_event.phase() {
-					Phase::ApplyExtrinsic(i) => i as usize,
-					other => {
-						log::error!("Unexpected event phase: {:?}", other);
-						return None
-					},
-				};
-				if let Some(events) = pallet_event.ibc_events() {
-					events.into_iter().enumerate().find_map(|(i, event)| {
-						TryInto::<IbcEvent>::try_into(event)
-							.map(|event| match event {
-								IbcEvent::UpdateClient(ev_update) if ev_update == update =>
-									Some((tx_index, i)),
-								_ => None,
-							})
-							.ok()
-							.flatten()
-					})
-				} else {
-					None
-				}
-			})
-			.ok_or_else(|| Error::from("No update client event found".to_owned()))?;
+			let event_bytes = self
+				.para_client
+				.rpc()
+				.storage(&*storage_key, Some(block_hash))
+				.await?
+				.map(|e| e.0)
+				.ok_or_else(|| Error::from("No events found".to_owned()))?;
+			let events: Vec<T::EventRecord> = Decode::decode(&mut &*event_bytes)
+				.map_err(|e| Error::from(format!("Failed to decode events: {:?}", e)))?;
+			find_update_client(
+				events
+					.into_iter()
+					.map(|pallet_event| (pallet_event.phase(), pallet_event.ibc_events())),
+				&update,
+			)
+		};
+		let (transaction_index, event_index) =
+			update_client.ok_or_else(|| Error::from("No update client event found".to_owned()))?;
 
-		let block = self
+		// the signed extensions of the extrinsic are skipped as the metadata lists them
+		let extrinsic = self
 			.para_client
-			.rpc()
-			.block(Some(block_hash.into()))
+			.blocks()
+			.at(block_hash)
 			.await?
-			.ok_or_else(|| Error::from(format!("Block not found for hash {:?}", block_hash)))?;
-
-		let extrinsic_opaque =
-			block.block.extrinsics.get(transaction_index).expect("Extrinsic not found");
-
-		let unchecked_extrinsic =
-			UncheckedExtrinsic::<T>::decode(&mut &*extrinsic_opaque.0.encode())
-				.map_err(|e| Error::from(format!("Extrinsic decode error: {}", e)))?;
-
-		let messages = unchecked_extrinsic
-			.function
-			.extract_ibc_deliver_messages()
-			.ok_or_else(|| Error::Custom("failed to extract deliver messages".to_string()))?;
+			.body()
+			.await?
+			.extrinsics()
+			.iter()
+			.nth(transaction_index)
+			.ok_or_else(|| {
+				Error::from(format!("Extrinsic not found in block {:?}", block_hash))
+			})??;
+
+		let messages = if self.dynamic_metadata {
+			dynamic::deliver_messages(&extrinsic)
+		} else {
+			T::ParaRuntimeCall::decode(&mut extrinsic.call_bytes())
+				.map_err(|e| Error::from(format!("Extrinsic decode error: {}", e)))?
+				.extract_ibc_deliver_messages()
+		}
+		.ok_or_else(|| Error::Custom("failed to extract deliver messages".to_string()))?;
 		let message = messages
 			.get(event_index)
 			.ok_or_else(|| Error::from(format!("Message index {} out of bounds", event_index)))?;
@@ -345,23 +405,50 @@ where
 	}
 
 	async fn reconnect(&mut self) -> anyhow::Result<()> {
-		let relay_ws_client = Arc::new(
-			WsClientBuilder::default()
-				.build(&self.relay_chain_rpc_url)
-				.await
-				.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?,
-		);
+		let parachain_rpc_url = self.parachain_rpc_url.lock().unwrap().clone();
+		// a relay chain rpc whose connection keeps dropping is replaced by the next one
+		if !self.relay_chain.ws_client.is_connected() {
+			let mut endpoints = self.relay_chain_endpoints.lock().unwrap();
+			if endpoints.current() == &self.relay_chain.rpc_url {
+				if let Some(next) = endpoints.report_failure() {
+					log::warn!(
+						target: "hyperspace_parachain",
+						"Relay chain rpc {} of {} keeps dropping, switching to {next}",
+						self.relay_chain.rpc_url, self.name
+					);
+				}
+			}
+		}
+		let relay_chain = RelayChainConnection::connect_with_failover(
+			&self.relay_chain_endpoints,
+			self.share_relay_chain_connection,
+		)
+		.await?;
 		let para_ws_client = Arc::new(
 			WsClientBuilder::default()
-				.build(&self.parachain_rpc_url)
+				.build(&parachain_rpc_url)
 				.await
 				.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?,
 		);
 
 		let para_client = subxt::OnlineClient::from_rpc_client(para_ws_client.clone()).await?;
-		let relay_client = subxt::OnlineClient::from_rpc_client(relay_ws_client.clone()).await?;
+		if self.dynamic_metadata {
+			dynamic::validate(&para_client.metadata())?;
+		}
+		let relay_client = relay_chain.client::<T>().await?;
 
-		self.relay_ws_client = relay_ws_client;
+		if let Some(secondary) = &self.secondary_relay_chain {
+			self.secondary_relay_chain =
+				Some(SecondaryRelayChain::connect(secondary.rpc_url.clone()).await?);
+		}
+
+		self.runtime_upgrades = RuntimeUpgrades::spawn(vec![
+			(self.name.clone(), para_client.clone()),
+			(format!("the relay chain of {}", self.name), relay_client.clone()),
+		]);
+		self.relay_ws_client = relay_chain.ws_client.clone();
+		*self.relay_chain_rpc_url.lock().unwrap() = relay_chain.rpc_url.clone();
+		self.relay_chain = relay_chain;
 		self.para_ws_client = para_ws_client;
 		self.relay_client = relay_client;
 		self.para_client = para_client;
@@ -380,6 +467,51 @@ where
 	}
 }
 
+impl<T: light_client_common::config::Config + Send + Sync + Clone + 'static> ParachainClient<T>
+where
+	u32: From<<<T as subxt::Config>::Header as HeaderT>::Number>,
+	u32: From<<<T as subxt::Config>::Header as Header>::Number>,
+	<<T as light_client_common::config::Config>::Signature as Verify>::Signer:
+		From<MultiSigner> + IdentifyAccount<AccountId = T::AccountId>,
+	MultiSigner: From<MultiSigner>,
+	<T as subxt::Config>::Address: From<<T as subxt::Config>::AccountId>,
+	<T as subxt::Config>::Signature: From<MultiSignature> + Send + Sync,
+	<<T as subxt::Config>::Header as Header>::Number:
+		BlockNumberOps + From<u32> + Display + Ord + sp_runtime::traits::Zero + One + Send + Sync,
+	<T as subxt::Config>::Header: Decode + Send + Sync + Clone,
+	T::Hash: From<sp_core::H256> + From<[u8; 32]>,
+	BTreeMap<sp_core::H256, ParachainHeaderProofs>:
+		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
+	sp_core::H256: From<T::Hash>,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
+	<T as subxt::Config>::AccountId: Send + Sync,
+	<T as subxt::Config>::Address: Send + Sync,
+	<T as light_client_common::config::Config>::AssetId: Clone,
+{
+	/// Queries the weight and the fee of the extrinsic delivering the messages.
+	async fn query_dispatch_info(
+		&self,
+		messages: Vec<Any>,
+	) -> Result<RuntimeDispatchInfo<u128, sp_weights::Weight>, Error> {
+		let extrinsic = {
+			let messages = messages
+				.into_iter()
+				.map(|msg| Any { type_url: msg.type_url.clone(), value: msg.value })
+				.collect::<Vec<_>>();
+
+			let call = self.deliver_call(messages);
+			self.create_signed(&call, None).await?.encoded().to_vec()
+		};
+		let dispatch_info = TransactionPaymentApiClient::<
+			H256,
+			RuntimeDispatchInfo<u128, sp_weights::Weight>,
+		>::query_info(&*self.para_ws_client, extrinsic.into(), None)
+		.await
+		.map_err(|e| Error::from(format!("Rpc Error From Estimating weight {:?}", e)))?;
+		Ok(dispatch_info)
+	}
+}
+
 #[async_trait::async_trait]
 impl<T: light_client_common::config::Config + Send + Sync> MisbehaviourHandler
 	for ParachainClient<T>
@@ -397,8 +529,7 @@ where
 	BTreeMap<sp_core::H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
 	sp_core::H256: From<T::Hash>,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	<T as subxt::Config>::AccountId: Send + Sync,
 	<T as subxt::Config>::Address: Send + Sync,
 {
@@ -427,11 +558,9 @@ where
 					})?;
 
 				let common_ancestor_block_number = u32::from(common_ancestor_header.number());
-				let encoded =
-					GrandpaApiClient::<JustificationNotification, H256, u32>::prove_finality(
-						&*self.relay_ws_client,
-						common_ancestor_block_number + 1,
-					)
+				let encoded = self
+					.relay_chain
+					.prove_finality(common_ancestor_block_number + 1)
 					.await?
 					.ok_or_else(|| {
 						anyhow!(
@@ -501,15 +630,24 @@ where
 						second_finality_proof: trusted_finality_proof,
 					});
 
-					counterparty
-						.submit(vec![MsgUpdateAnyClient::<LocalClientTypes>::new(
-							self.client_id(),
-							AnyClientMessage::Grandpa(misbehaviour.clone()),
-							counterparty.account_id(),
-						)
-						.to_any()])
-						.map_err(|e| anyhow!("Failed to submit misbehaviour report: {:?}", e))
-						.await?;
+					let msg = MsgUpdateAnyClient::<LocalClientTypes>::new(
+						self.client_id(),
+						AnyClientMessage::Grandpa(misbehaviour.clone()),
+						counterparty.account_id(),
+					)
+					.to_any();
+					if counterparty.common_state().dry_run {
+						counterparty
+							.simulate(vec![msg])
+							.map_err(|e| anyhow!("Failed to simulate misbehaviour report: {:?}", e))
+							.await?;
+						log::info!(target: "hyperspace_parachain", "[dry run] Would have submitted a misbehaviour report to {}", counterparty.name());
+					} else {
+						counterparty
+							.submit(vec![msg])
+							.map_err(|e| anyhow!("Failed to submit misbehaviour report: {:?}", e))
+							.await?;
+					}
 				}
 			},
 			_ => {},
@@ -517,3 +655,30 @@ where
 		Ok(())
 	}
 }
+
+/// The index of the extrinsic that updated the client, and of the update among the IBC events of
+/// the extrinsic, from the IBC events of the block by the phase they were emitted in.
+fn find_update_client<E: TryInto<IbcEvent>>(
+	events: impl IntoIterator<Item = (Phase, Option<Vec<E>>)>,
+	update: &UpdateClient,
+) -> Option<(usize, usize)> {
+	events.into_iter().find_map(|(phase, events)| {
+		let tx_index = match phase {
+			Phase::ApplyExtrinsic(i) => i as usize,
+			other => {
+				log::error!("Unexpected event phase: {:?}", other);
+				return None
+			},
+		};
+		events?.into_iter().enumerate().find_map(|(i, event)| {
+			TryInto::<IbcEvent>::try_into(event)
+				.map(|event| match event {
+					IbcEvent::UpdateClient(ev_update) if &ev_update == update =>
+						Some((tx_index, i)),
+					_ => None,
+				})
+				.ok()
+				.flatten()
+		})
+	})
+}
@@ -0,0 +1,238 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dynamic-metadata mode of the parachain client.
+//!
+//! The calls, storage items and events of `pallet-ibc` are otherwise encoded and decoded with
+//! the types `subxt` generates from the metadata of each supported runtime, so relaying a new
+//! parachain requires generating its types. With `dynamic_metadata` in the config, the client
+//! instead builds them from the metadata of the chain at runtime:
+//! - the `ibc.deliver` calls are encoded from the names of the pallet and the call, see [`deliver`]
+//! - `timestamp.now` is queried as a dynamic storage item, see [`timestamp_now`]
+//! - the `ibc.Events` events and the messages of the `ibc.deliver` extrinsics are decoded with the
+//!   types of `pallet-ibc`, see [`ibc_events`] and [`deliver_messages`]
+//!
+//! The metadata is checked for the shapes of these items the relayer expects when the client is
+//! created, see [`validate`], so that a runtime with a different `pallet-ibc` is rejected up
+//! front rather than failing on the first message.
+
+use crate::error::Error;
+use codec::Decode;
+use ibc_proto::google::protobuf::Any;
+use subxt::{
+	blocks::ExtrinsicDetails,
+	client::OfflineClientT,
+	dynamic::{DecodedValueThunk, Value},
+	events::{Events, Phase},
+	ext::sp_runtime::scale_info::{form::PortableForm, Field, TypeDef, TypeDefPrimitive},
+	metadata::{types::StorageEntryType, Metadata},
+	storage::DynamicAddress,
+	tx::{DynamicPayload, Payload, TxPayload},
+};
+
+const IBC_PALLET: &str = "Ibc";
+const DELIVER_CALL: &str = "deliver";
+const EVENTS_EVENT: &str = "Events";
+const TIMESTAMP_PALLET: &str = "Timestamp";
+const NOW_STORAGE: &str = "Now";
+
+/// Shape of a type the relayer encodes or decodes.
+enum Shape {
+	U8,
+	U64,
+	Str,
+	/// `Vec<u8>`
+	Bytes,
+	Sequence(Box<Shape>),
+	Composite(Vec<(&'static str, Shape)>),
+	/// `Result<T, E>`, whose variants aren't checked any further
+	Result,
+}
+
+impl Shape {
+	/// `pallet_ibc::Any`
+	fn any() -> Self {
+		Self::Composite(vec![("type_url", Self::Str), ("value", Self::Bytes)])
+	}
+
+	/// Checks that the type has the shape.
+	fn check(&self, metadata: &Metadata, type_id: u32) -> Result<(), String> {
+		let ty = metadata
+			.types()
+			.resolve(type_id)
+			.ok_or_else(|| format!("type {type_id} not found"))?;
+		let matches = match (self, &ty.type_def) {
+			(Self::U8, TypeDef::Primitive(TypeDefPrimitive::U8)) => true,
+			(Self::U64, TypeDef::Primitive(TypeDefPrimitive::U64)) => true,
+			(Self::Str, TypeDef::Primitive(TypeDefPrimitive::Str)) => true,
+			(Self::Bytes, TypeDef::Sequence(sequence)) =>
+				return Self::U8.check(metadata, sequence.type_param.id),
+			(Self::Sequence(item), TypeDef::Sequence(sequence)) =>
+				return item.check(metadata, sequence.type_param.id),
+			(Self::Composite(fields), TypeDef::Composite(composite)) =>
+				return check_fields(metadata, fields, &composite.fields),
+			(Self::Result, TypeDef::Variant(variant)) =>
+				variant.variants.iter().map(|variant| variant.name.as_str()).eq(["Ok", "Err"]),
+			_ => false,
+		};
+		match matches {
+			true => Ok(()),
+			false => Err(format!("unexpected type {:?}", ty.path.segments)),
+		}
+	}
+}
+
+/// Checks the names and shapes of the fields of a call, an event or a struct.
+fn check_fields(
+	metadata: &Metadata,
+	expected: &[(&'static str, Shape)],
+	fields: &[Field<PortableForm>],
+) -> Result<(), String> {
+	if fields.len() != expected.len() {
+		return Err(format!("expected {} fields, found {}", expected.len(), fields.len()))
+	}
+	for ((name, shape), field) in expected.iter().zip(fields) {
+		if field.name.as_deref().map_or(false, |field_name| field_name != *name) {
+			return Err(format!("expected field {name}, found {:?}", field.name))
+		}
+		shape.check(metadata, field.ty.id).map_err(|e| format!("field {name}: {e}"))?;
+	}
+	Ok(())
+}
+
+/// Checks that the metadata has the items of `pallet-ibc` the dynamic mode relies on, with the
+/// shapes it expects.
+pub fn validate(metadata: &Metadata) -> Result<(), Error> {
+	let ibc = metadata
+		.pallet_by_name(IBC_PALLET)
+		.ok_or_else(|| Error::Custom(format!("The metadata has no {IBC_PALLET} pallet")))?;
+
+	let deliver = ibc.call_variant_by_name(DELIVER_CALL).ok_or_else(|| {
+		Error::Custom(format!("The metadata has no {IBC_PALLET}.{DELIVER_CALL} call"))
+	})?;
+	check_fields(
+		metadata,
+		&[("messages", Shape::Sequence(Box::new(Shape::any())))],
+		&deliver.fields,
+	)
+	.map_err(|e| Error::Custom(format!("Unexpected {IBC_PALLET}.{DELIVER_CALL} call: {e}")))?;
+
+	let events = ibc
+		.event_variants()
+		.and_then(|variants| variants.iter().find(|variant| variant.name == EVENTS_EVENT))
+		.ok_or_else(|| {
+			Error::Custom(format!("The metadata has no {IBC_PALLET}.{EVENTS_EVENT} event"))
+		})?;
+	check_fields(metadata, &[("events", Shape::Sequence(Box::new(Shape::Result)))], &events.fields)
+		.map_err(|e| Error::Custom(format!("Unexpected {IBC_PALLET}.{EVENTS_EVENT} event: {e}")))?;
+
+	let now = metadata
+		.pallet_by_name(TIMESTAMP_PALLET)
+		.and_then(|pallet| pallet.storage())
+		.and_then(|storage| storage.entry_by_name(NOW_STORAGE))
+		.ok_or_else(|| {
+			Error::Custom(format!("The metadata has no {TIMESTAMP_PALLET}.{NOW_STORAGE} storage"))
+		})?;
+	match now.entry_type() {
+		StorageEntryType::Plain(ty) => Shape::U64.check(metadata, *ty),
+		StorageEntryType::Map { .. } => Err("expected a plain storage item".to_string()),
+	}
+	.map_err(|e| {
+		Error::Custom(format!("Unexpected {TIMESTAMP_PALLET}.{NOW_STORAGE} storage: {e}"))
+	})?;
+	Ok(())
+}
+
+/// The `ibc.deliver` call of the messages.
+pub fn deliver(messages: Vec<Any>) -> DynamicPayload {
+	let messages = messages.into_iter().map(|message| {
+		Value::named_composite([
+			("type_url", Value::string(message.type_url)),
+			("value", Value::from_bytes(message.value)),
+		])
+	});
+	subxt::dynamic::tx(IBC_PALLET, DELIVER_CALL, vec![Value::unnamed_composite(messages)])
+}
+
+/// The `timestamp.now` storage item.
+pub fn timestamp_now() -> DynamicAddress<Value> {
+	subxt::dynamic::storage(TIMESTAMP_PALLET, NOW_STORAGE, Vec::<Value>::new())
+}
+
+/// Decodes the `timestamp.now` storage item, in milliseconds.
+pub fn decode_timestamp(value: DecodedValueThunk) -> Result<u64, Error> {
+	value
+		.to_value()?
+		.as_u128()
+		.map(|now| now as u64)
+		.ok_or_else(|| Error::from("Failed to decode the timestamp".to_string()))
+}
+
+/// The IBC events of the `ibc.Events` events, with the phase they were emitted in. The events
+/// that are errors are skipped.
+pub fn ibc_events<T: subxt::Config>(
+	events: &Events<T>,
+) -> Result<Vec<(Phase, Vec<pallet_ibc::events::IbcEvent>)>, Error> {
+	let mut ibc_events = vec![];
+	for event in events.iter() {
+		let event = event?;
+		if event.pallet_name() != IBC_PALLET || event.variant_name() != EVENTS_EVENT {
+			continue
+		}
+		let results =
+			Vec::<Result<pallet_ibc::events::IbcEvent, pallet_ibc::errors::IbcError>>::decode(
+				&mut event.field_bytes(),
+			)
+			.map_err(|e| Error::from(format!("Failed to decode the IBC events: {e:?}")))?;
+		ibc_events.push((event.phase(), results.into_iter().filter_map(Result::ok).collect()));
+	}
+	Ok(ibc_events)
+}
+
+/// The messages of an `ibc.deliver` extrinsic, or `None` for the other extrinsics.
+pub fn deliver_messages<T, C>(extrinsic: &ExtrinsicDetails<T, C>) -> Option<Vec<Any>>
+where
+	T: subxt::Config,
+	C: OfflineClientT<T>,
+{
+	if extrinsic.pallet_name().ok()? != IBC_PALLET || extrinsic.variant_name().ok()? != DELIVER_CALL
+	{
+		return None
+	}
+	let messages = Vec::<pallet_ibc::Any>::decode(&mut extrinsic.field_bytes()).ok()?;
+	Some(messages.into_iter().map(Any::from).collect())
+}
+
+/// An IBC call, encoded with the types generated for the runtime or from the metadata of the
+/// chain.
+pub enum IbcCall<C> {
+	Static(Payload<C>),
+	Dynamic(DynamicPayload),
+}
+
+impl<C> TxPayload for IbcCall<C>
+where
+	Payload<C>: TxPayload,
+{
+	fn encode_call_data_to(
+		&self,
+		metadata: &Metadata,
+		out: &mut Vec<u8>,
+	) -> Result<(), subxt::Error> {
+		match self {
+			Self::Static(call) => call.encode_call_data_to(metadata, out),
+			Self::Dynamic(call) => call.encode_call_data_to(metadata, out),
+		}
+	}
+}
@@ -19,7 +19,6 @@ use anyhow::anyhow;
 use beefy_light_client_primitives::{ClientState as BeefyPrimitivesClientState, NodesUtils};
 use codec::{Decode, Encode};
 use finality_grandpa::BlockNumberOps;
-use finality_grandpa_rpc::GrandpaApiClient;
 use grandpa_light_client_primitives::{
 	justification::find_scheduled_change, FinalityProof, ParachainHeaderProofs,
 	ParachainHeadersWithFinalityProof,
@@ -36,6 +35,7 @@ use ics10_grandpa::client_message::{ClientMessage, Header as GrandpaHeader};
 use ics11_beefy::client_message::{
 	BeefyHeader, ClientMessage as BeefyClientMessage, ParachainHeadersWithProof,
 };
+use light_client_common::config::RuntimeStorage;
 use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState};
 use primitives::{
 	filter_events_by_ids, mock::LocalClientTypes, query_maximum_height_for_timeout_proofs, Chain,
@@ -56,12 +56,8 @@ use std::{
 };
 
 use beefy_prover::helpers::unsafe_arc_cast;
-use grandpa_prover::{
-	GrandpaJustification, GrandpaProver, JustificationNotification, PROCESS_BLOCKS_BATCH_SIZE,
-};
-use subxt::config::{
-	extrinsic_params::BaseExtrinsicParamsBuilder, ExtrinsicParams, Header as HeaderT, Header,
-};
+use grandpa_prover::{GrandpaJustification, GrandpaProver, PROCESS_BLOCKS_BATCH_SIZE};
+use subxt::config::{ExtrinsicParams, Header as HeaderT, Header};
 use tendermint_proto::Protobuf;
 use tokio::task::JoinSet;
 
@@ -114,8 +110,7 @@ impl FinalityProtocol {
 		sp_core::H256: From<T::Hash>,
 		BTreeMap<H256, ParachainHeaderProofs>:
 			From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
-		<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-			From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+		<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 		<T as subxt::Config>::AccountId: Send + Sync,
 		<T as subxt::Config>::Address: Send + Sync,
 	{
@@ -149,8 +144,7 @@ where
 	<<T as subxt::Config>::Header as Header>::Number:
 		From<u32> + Debug + Display + Ord + sp_runtime::traits::Zero + One,
 	<T as subxt::Config>::Header: Decode,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	T::Hash: From<sp_core::H256>,
 	sp_core::H256: From<T::Hash>,
 	<T as subxt::Config>::AccountId: Send + Sync,
@@ -215,20 +209,21 @@ where
 	// Get finalized parachain block numbers, but only those higher than the latest para
 	// height recorded in the on-chain client state, because in some cases a parachain
 	// block that was already finalized in a former beefy block might still be part of
-	// the parachain headers in a later beefy block, discovered this from previous logs
-	let finalized_blocks =
-		headers.iter().map(|header| u32::from(header.number())).collect::<Vec<_>>();
+	// the parachain headers in a later beefy block, discovered this from previous logs.
+	// With asynchronous backing, the parachain blocks aren't all the head of the parachain in
+	// some relay chain block, so the events are queried for all the blocks up to the latest head.
+	let latest_finalized_block = headers
+		.iter()
+		.map(|header| u32::from(header.number()))
+		.max()
+		.unwrap_or_default();
+	let finalized_blocks = ((client_state.latest_height().revision_height as u32 + 1)..=
+		latest_finalized_block)
+		.collect::<Vec<_>>();
 
 	let finalized_block_numbers = finalized_blocks
 		.iter()
-		.filter_map(|block_number| {
-			if (client_state.latest_height().revision_height as u32) < *block_number {
-				Some(*block_number)
-			} else {
-				None
-			}
-		})
-		.map(|h| BlockNumberOrHash::Number(h))
+		.map(|h| BlockNumberOrHash::Number(*h))
 		.collect::<Vec<_>>();
 
 	// 1. we should query the sink chain for any outgoing packets to the source chain
@@ -243,8 +238,6 @@ where
 		false
 	};
 
-	let latest_finalized_block = finalized_blocks.into_iter().max().unwrap_or_default();
-
 	let authority_set_changed =
 		signed_commitment.commitment.validator_set_id == beefy_client_state.next_authorities.id;
 
@@ -359,6 +352,47 @@ where
 	Ok(vec![(update_header, Height::new(0, 0), events, update_type)])
 }
 
+/// Finds the first block in `from + 1..=to` whose grandpa authority set isn't `set_id`, i.e. the
+/// block enacting the next set change. Returns `None` if the set of `to` is still `set_id`.
+async fn find_set_change<T>(
+	prover: &GrandpaProver<T>,
+	set_id: u64,
+	from: u32,
+	to: u32,
+) -> anyhow::Result<Option<u32>>
+where
+	T: light_client_common::config::Config + Send + Sync,
+{
+	let set_id_at =
+		|height: u32| async move {
+			let hash =
+				prover.relay_client.rpc().block_hash(Some(height.into())).await?.ok_or_else(
+					|| anyhow!("Failed to fetch block hash for block number {height}"),
+				)?;
+			prover
+				.relay_client
+				.storage()
+				.at(hash)
+				.fetch(&T::Storage::grandpa_current_set_id())
+				.await?
+				.ok_or_else(|| anyhow!("Failed to fetch the current set id at block {height}"))
+		};
+	if to <= from || set_id_at(to).await? == set_id {
+		return Ok(None)
+	}
+	// the set of `from` is the one of the client and the set of `to` is a later one
+	let (mut low, mut high) = (from, to);
+	while high - low > 1 {
+		let mid = low + (high - low) / 2;
+		if set_id_at(mid).await? == set_id {
+			low = mid;
+		} else {
+			high = mid;
+		}
+	}
+	Ok(Some(high))
+}
+
 async fn find_next_justification<T>(
 	prover: &GrandpaProver<T>,
 	from: u32,
@@ -379,8 +413,7 @@ where
 	sp_core::H256: From<T::Hash>,
 	BTreeMap<H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	<T as subxt::Config>::Header: Decode + Send + Sync + Clone,
 	<T as subxt::Config>::AccountId: Send + Sync,
 	<T as subxt::Config>::Address: Send + Sync,
@@ -450,8 +483,7 @@ where
 	sp_core::H256: From<T::Hash>,
 	BTreeMap<H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	<T as subxt::Config>::Header: Decode + Send + Sync + Clone,
 	<T as subxt::Config>::AccountId: Send + Sync,
 	<T as subxt::Config>::Address: Send + Sync,
@@ -478,17 +510,14 @@ where
 
 	let prover = source.grandpa_prover();
 	// prove_finality will always give us the highest block finalized by the authority set for the
-	// block number passed, so we can't miss any authority set change since the session change block
-	// will always be finalized.
+	// block number passed, so we shouldn't miss any authority set change since the session change
+	// block will always be finalized. The target is still checked against the set of the client
+	// below, since a cached or closer justification may cross the change.
 	let next_relay_height = client_state.latest_relay_height + 1;
 
-	let encoded = GrandpaApiClient::<JustificationNotification, H256, u32>::prove_finality(
-		// we cast between the same type but different crate versions.
-		&*prover.relay_ws_client.clone(),
-		next_relay_height,
-	)
-	.await
-	.map_err(|_| {
+	// the proof is shared with the clients of the other parachains of the relay chain proving the
+	// same block, see [`crate::relay_chain`]
+	let encoded = source.relay_chain.prove_finality(next_relay_height).await.map_err(|_| {
 		Error::Custom(
 		format!("Next relay block {} has not been finalized, previous finalized height on counterparty {}",
 				next_relay_height, client_state.latest_relay_height
@@ -520,6 +549,39 @@ where
 		}
 	}
 
+	// The counterparty only accepts justifications of the authority set of the client, so the
+	// update is split at the block enacting the next set change, if the target is past it. The
+	// next update is then proven by the new set.
+	if let Some(set_change_block) = find_set_change(
+		&prover,
+		client_state.current_set_id,
+		client_state.latest_relay_height,
+		justification.commit.target_number,
+	)
+	.await?
+	{
+		if set_change_block < justification.commit.target_number {
+			log::info!(
+				target: "hyperspace_parachain",
+				"Authority set changed at #{set_change_block}, splitting the finality proof of #{} for {}",
+				justification.commit.target_number, source.name()
+			);
+			let encoded =
+				source.relay_chain.prove_finality(set_change_block).await?.ok_or_else(|| {
+					anyhow!("No justification found for block: {:?}", set_change_block)
+				})?;
+			let finality_proof = FinalityProof::<T::Header>::decode(&mut &encoded[..])?;
+			justification =
+				GrandpaJustification::<T::Header>::decode(&mut &finality_proof.justification[..])?;
+			if justification.commit.target_number != set_change_block {
+				Err(anyhow!(
+					"Expected the justification of the set change block #{set_change_block}, found #{}",
+					justification.commit.target_number
+				))?
+			}
+		}
+	}
+
 	// Sometimes the returned justification doesn't contain the header for the target block
 	// in the votes ancestry, so we need to fetch it manually
 	if !justification.votes_ancestries.is_empty() &&
@@ -653,6 +715,7 @@ where
 			headers_with_events.into_iter().collect(),
 		)
 		.await?;
+	source.cross_check_finality_proof(&finality_proof).await?;
 
 	let target = source
 		.relay_client
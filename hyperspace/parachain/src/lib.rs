@@ -23,11 +23,16 @@ use std::{
 	time::Duration,
 };
 
+pub mod batch;
 pub mod chain;
+pub mod dynamic;
 pub mod error;
 pub mod key_provider;
+pub mod nonce;
 pub mod parachain;
 pub mod provider;
+pub mod relay_chain;
+pub mod runtime_upgrade;
 pub mod signer;
 pub mod utils;
 
@@ -41,17 +46,28 @@ use frame_support::Serialize;
 use serde::Deserialize;
 
 use crate::{
-	finality_protocol::FinalityProtocol, signer::ExtrinsicSigner, utils::fetch_max_extrinsic_weight,
+	batch::{BatchMode, DeliverCall},
+	dynamic::IbcCall,
+	finality_protocol::FinalityProtocol,
+	nonce::{nonce_manager, NonceManager},
+	relay_chain::RelayChainConnection,
+	runtime_upgrade::RuntimeUpgrades,
+	signer::ExtrinsicSigner,
+	utils::fetch_max_extrinsic_weight,
 };
 use beefy_light_client_primitives::{ClientState, MmrUpdateProof};
 use beefy_prover::Prover;
-use codec::Decode;
-use grandpa_light_client_primitives::ParachainHeaderProofs;
-use grandpa_prover::GrandpaProver;
+use codec::{Decode, Encode};
+use finality_grandpa_rpc::GrandpaApiClient;
+use grandpa_light_client_primitives::{
+	FinalityProof, ParachainHeaderProofs, STANDALONE_CHAIN_PARA_ID,
+};
+use grandpa_prover::{GrandpaJustification, GrandpaProver, JustificationNotification};
 use ibc::{
 	core::ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
 	timestamp::Timestamp,
 };
+use ibc_proto::google::protobuf::Any;
 use ics10_grandpa::{
 	client_state::ClientState as GrandpaClientState,
 	consensus_state::ConsensusState as GrandpaConsensusState,
@@ -60,13 +76,23 @@ use ics11_beefy::{
 	client_message::ParachainHeader, client_state::ClientState as BeefyClientState,
 	consensus_state::ConsensusState as BeefyConsensusState,
 };
+use jsonrpsee::{core::client::ClientT, rpc_params};
 use jsonrpsee_ws_client::WsClientBuilder;
-use light_client_common::config::{AsInner, RuntimeStorage};
+use light_client_common::{
+	config::{AsInner, RuntimeStorage, RuntimeTransactions},
+	extrinsic_params::SignedExtensionsConfig,
+};
 use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager};
 use pallet_mmr_primitives::Proof;
-use primitives::{CommonClientState, KeyProvider};
+use primitives::{
+	balance::BalanceGuard,
+	endpoints::{EndpointRotation, PRIMARY_CHECK_INTERVAL},
+	rate_limit::SubmissionLimiter,
+	remote_signer::{RemoteSigner, RemoteSignerConfig, SignatureScheme},
+	CommonClientConfig, CommonClientState, KeyProvider,
+};
 use sc_keystore::LocalKeystore;
-use sp_core::{ecdsa, ed25519, sr25519, Bytes, Pair, H256};
+use sp_core::{crypto::Ss58Codec, ecdsa, ed25519, sr25519, Bytes, Pair, H256};
 use sp_keystore::KeystorePtr;
 use sp_runtime::{
 	traits::{IdentifyAccount, One, Verify},
@@ -74,10 +100,11 @@ use sp_runtime::{
 };
 use ss58_registry::Ss58AddressFormat;
 use subxt::{
+	blocks::ExtrinsicEvents,
 	config::{Header as HeaderT, Header},
-	tx::{Signer, TxPayload},
+	tx::{Signer, SubmittableExtrinsic, TxPayload},
 };
-use tokio::sync::Mutex as AsyncMutex;
+use tokio::sync::{Mutex as AsyncMutex, Notify};
 
 /// Implements the [`crate::Chain`] trait for parachains.
 /// This is responsible for:
@@ -88,16 +115,32 @@ use tokio::sync::Mutex as AsyncMutex;
 pub struct ParachainClient<T: light_client_common::config::Config> {
 	/// Chain name
 	pub name: String,
-	/// rpc url for parachain
-	pub parachain_rpc_url: String,
-	/// rpc url for relay chain
-	pub relay_chain_rpc_url: String,
+	/// rpc url for parachain, shared by the clones of the client. The clients are connected to a
+	/// new url on [`Chain::reconnect`](primitives::Chain::reconnect)
+	pub parachain_rpc_url: Arc<Mutex<String>>,
+	/// rpc url of the relay chain the client is connected to, shared by the clones of the client
+	pub relay_chain_rpc_url: Arc<Mutex<String>>,
+	/// rpc urls of the relay chain, the primary one and its fallbacks, shared by the clones of the
+	/// client. The clients move to the next one when the current one is unreachable or keeps
+	/// dropping, see [`RelayChainConnection::connect_with_failover`]
+	pub relay_chain_endpoints: Arc<Mutex<EndpointRotation<String>>>,
+	/// Notified when the primary relay chain rpc is reachable again while a fallback is used,
+	/// which ends the finality notifications so that the relayer loop reconnects to it
+	pub primary_relay_chain_restored: Arc<Notify>,
 	/// Relay chain rpc client
 	pub relay_client: subxt::OnlineClient<T>,
+	/// Connection to the relay chain rpc, shared with the clients of other parachains of the relay
+	/// chain if `share_relay_chain_connection` is set, see [`relay_chain`]
+	pub relay_chain: Arc<RelayChainConnection>,
+	/// Whether the relay chain connection is shared
+	pub share_relay_chain_connection: bool,
 	/// Parachain rpc client
 	pub para_client: subxt::OnlineClient<T>,
 	/// Relay chain ws client
 	pub relay_ws_client: Arc<jsonrpsee_ws_client::WsClient>,
+	/// Independent relay chain rpc the GRANDPA justifications are cross-checked against, see
+	/// [`ParachainClient::cross_check_finality_proof`]
+	pub secondary_relay_chain: Option<SecondaryRelayChain<T>>,
 	/// Parachain ws client
 	pub para_ws_client: Arc<jsonrpsee_ws_client::WsClient>,
 	/// Parachain Id
@@ -114,24 +157,72 @@ pub struct ParachainClient<T: light_client_common::config::Config> {
 	pub public_key: MultiSigner,
 	/// Reference to keystore
 	pub key_store: KeystorePtr,
+	/// Signing service holding the relayer key instead of the keystore, see
+	/// [`primitives::remote_signer`]
+	pub remote_signer: Option<Arc<RemoteSigner>>,
 	/// Key type Id
 	pub key_type_id: KeyTypeId,
+	/// Nonces of the relayer account, reserved for the concurrent submissions, see [`nonce`]
+	pub nonces: Arc<NonceManager>,
 	/// used for encoding relayer address.
 	pub ss58_version: Ss58AddressFormat,
-	/// the maximum extrinsic weight allowed by this client
-	pub max_extrinsic_weight: u64,
+	/// the maximum extrinsic weight allowed by this client, ref time and proof size
+	pub max_extrinsic_weight: sp_weights::Weight,
 	/// Finality protocol to use, eg Beefy, Grandpa
 	pub finality_protocol: FinalityProtocol,
 	/// Common relayer data
 	pub common_state: CommonClientState,
+	/// Refreshes the metadata of the clients on the runtime upgrades of their chains, see
+	/// [`runtime_upgrade`]
+	pub runtime_upgrades: Arc<RuntimeUpgrades>,
+	/// Data of the signed extensions of the extrinsics
+	pub signed_extensions: SignedExtensionsConfig,
+	/// Call of the `utility` pallet the message bundles are batched with, see [`batch`]
+	pub batch_mode: Option<BatchMode>,
+	/// Bring the GRANDPA light client up to date with warp sync fragments, see
+	/// [`grandpa_light_client_primitives::is_warp_fragment`]
+	pub warp_sync: bool,
+	/// Build the `pallet-ibc` calls, storage queries and events from the metadata of the chain
+	/// rather than from the generated types of the config, see [`dynamic`]
+	pub dynamic_metadata: bool,
 }
 
-enum KeyType {
+/// Signature scheme of the relayer key. The account of the relayer is derived from the public key
+/// as the runtimes do with a `MultiSigner`: the sr25519 and ed25519 public keys are the account
+/// ids, and the ecdsa ones are hashed with blake2-256.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
 	Sr25519,
 	Ed25519,
 	Ecdsa,
 }
 
+/// Signature scheme of the relayer key if the config doesn't set one
+fn default_key_type() -> String {
+	"sr25519".to_string()
+}
+
+/// Clients of a secondary relay chain rpc, operated independently from the main one.
+#[derive(Clone)]
+pub struct SecondaryRelayChain<T: light_client_common::config::Config> {
+	pub rpc_url: String,
+	pub client: subxt::OnlineClient<T>,
+	pub ws_client: Arc<jsonrpsee_ws_client::WsClient>,
+}
+
+impl<T: light_client_common::config::Config> SecondaryRelayChain<T> {
+	pub async fn connect(rpc_url: String) -> Result<Self, Error> {
+		let ws_client = Arc::new(
+			WsClientBuilder::default()
+				.build(&rpc_url)
+				.await
+				.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?,
+		);
+		let client = subxt::OnlineClient::from_rpc_client(ws_client.clone()).await?;
+		Ok(Self { rpc_url, client, ws_client })
+	}
+}
+
 pub const DEFAULT_RPC_CALL_DELAY: Duration = Duration::from_millis(10);
 pub const WAIT_FOR_IN_BLOCK_TIMEOUT: Duration = Duration::from_secs(60 * 1);
 
@@ -143,17 +234,60 @@ impl KeyType {
 			KeyType::Ecdsa => KeyTypeId(ecdsa::CRYPTO_ID.0),
 		}
 	}
+
+	pub fn signature_scheme(&self) -> SignatureScheme {
+		match self {
+			KeyType::Sr25519 => SignatureScheme::Sr25519,
+			KeyType::Ed25519 => SignatureScheme::Ed25519,
+			KeyType::Ecdsa => SignatureScheme::Ecdsa,
+		}
+	}
+
+	/// Derives the public key of this type from the secret URI of the key, e.g. a mnemonic, a hex
+	/// seed or a dev key like `//Alice`.
+	pub fn public_key_from_suri(&self, suri: &str) -> Result<MultiSigner, Error> {
+		let invalid = |e| Error::Custom(format!("Invalid {self:?} private key: {e:?}"));
+		Ok(match self {
+			KeyType::Sr25519 => sr25519::Pair::from_string_with_seed(suri, None)
+				.map_err(invalid)?
+				.0
+				.public()
+				.into(),
+			KeyType::Ed25519 => ed25519::Pair::from_string_with_seed(suri, None)
+				.map_err(invalid)?
+				.0
+				.public()
+				.into(),
+			KeyType::Ecdsa => ecdsa::Pair::from_string_with_seed(suri, None)
+				.map_err(invalid)?
+				.0
+				.public()
+				.into(),
+		})
+	}
+
+	/// Decodes a public key of this type
+	pub fn public_key(&self, bytes: &[u8]) -> Result<MultiSigner, Error> {
+		let invalid = |_| Error::Custom(format!("Invalid public key {}", hex::encode(bytes)));
+		Ok(match self {
+			KeyType::Sr25519 => sr25519::Public::try_from(bytes).map_err(invalid)?.into(),
+			KeyType::Ed25519 => ed25519::Public::try_from(bytes).map_err(invalid)?.into(),
+			KeyType::Ecdsa => ecdsa::Public::try_from(bytes).map_err(invalid)?.into(),
+		})
+	}
 }
 
 impl FromStr for KeyType {
 	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		match s {
+		match s.to_lowercase().as_str() {
 			"sr25519" => Ok(KeyType::Sr25519),
 			"ed25519" => Ok(KeyType::Ed25519),
 			"ecdsa" => Ok(KeyType::Ecdsa),
-			_ => Err(Error::Custom("Invalid key type".to_string())),
+			_ => Err(Error::Custom(format!(
+				"Invalid key type {s}, expected one of sr25519, ed25519 or ecdsa"
+			))),
 		}
 	}
 }
@@ -164,18 +298,40 @@ pub struct ParachainClientConfig {
 	/// Chain name
 	pub name: String,
 	/// Parachain Id
+	#[serde(default)]
 	pub para_id: u32,
 	/// rpc url for parachain
 	pub parachain_rpc_url: String,
 	/// rpc url for relay chain
+	#[serde(default)]
 	pub relay_chain_rpc_url: String,
+	/// rpc urls of other relay chain nodes, used in order when the relay chain rpc is unreachable
+	/// or its connection keeps dropping
+	#[serde(default)]
+	pub relay_chain_fallback_rpc_urls: Vec<String>,
+	/// Relay a standalone chain, finalized by its own GRANDPA authorities rather than by a relay
+	/// chain. The para id and the relay chain rpc url are then ignored, the headers of the chain
+	/// being its finalized blocks
+	#[serde(default)]
+	pub standalone: bool,
+	/// rpc url of a second relay chain node, operated independently from the first one. When set,
+	/// the GRANDPA justifications are cross-checked against both nodes, and client updates aren't
+	/// submitted if they diverge.
+	#[serde(default)]
+	pub secondary_relay_chain_rpc_url: Option<String>,
+	/// Share the connection to the relay chain, its subscription to the justifications and the
+	/// finality proofs it serves with the clients of the other parachains of the same relay chain
+	/// rpc url, see [`relay_chain`]
+	#[serde(default)]
+	pub share_relay_chain_connection: bool,
 	/// Light client id on counterparty chain
 	pub client_id: Option<ClientId>,
 	/// Connection Id
 	pub connection_id: Option<ConnectionId>,
 	/// Commitment prefix
 	pub commitment_prefix: Bytes,
-	/// Raw private key for signing transactions
+	/// Raw private key for signing transactions, unless a `remote_signer` holds it
+	#[serde(default)]
 	pub private_key: String,
 	/// used for encoding relayer address.
 	pub ss58_version: u8,
@@ -183,11 +339,62 @@ pub struct ParachainClientConfig {
 	pub channel_whitelist: Vec<(ChannelId, PortId)>,
 	/// Finality protocol
 	pub finality_protocol: FinalityProtocol,
-	/// Digital signature scheme
+	/// Signature scheme of the relayer key, `sr25519` (the default), `ed25519` or `ecdsa`, see
+	/// [`KeyType`]
+	#[serde(default = "default_key_type")]
 	pub key_type: String,
+	/// Signing service holding the relayer key instead of the `private_key`, see
+	/// [`primitives::remote_signer`]
+	#[serde(default)]
+	pub remote_signer: Option<RemoteSignerConfig>,
 	/// All the client states and headers will be wrapped in WASM ones using the WASM code ID.
 	#[serde(default)]
 	pub wasm_code_id: Option<String>,
+	/// Common client config
+	#[serde(flatten)]
+	pub common: CommonClientConfig,
+	/// Data of the signed extensions of the extrinsics, for the ones the relayer can't derive
+	/// from the metadata, see [`light_client_common::extrinsic_params`]
+	#[serde(default)]
+	pub signed_extensions: SignedExtensionsConfig,
+	/// Submit the message bundles in a `utility.batch_all` or `utility.force_batch` call, see
+	/// [`batch`]
+	#[serde(default)]
+	pub batch_mode: Option<BatchMode>,
+	/// Catch up with the authority set changes the light client missed with warp sync fragments,
+	/// see [`grandpa_light_client_primitives::is_warp_fragment`]
+	#[serde(default)]
+	pub warp_sync: bool,
+	/// Build the `pallet-ibc` calls, storage queries and events from the metadata of the chain at
+	/// runtime, for the runtimes the relayer has no generated types for, see [`dynamic`]
+	#[serde(default)]
+	pub dynamic_metadata: bool,
+}
+
+impl ParachainClientConfig {
+	/// Checks the chain the config is for, pointing the relay chain of a standalone chain to the
+	/// chain itself.
+	fn resolve_chain(mut self) -> Result<Self, Error> {
+		if !self.standalone {
+			if self.para_id == STANDALONE_CHAIN_PARA_ID || self.relay_chain_rpc_url.is_empty() {
+				return Err(Error::Custom(format!(
+					"{} needs a para id and a relay chain rpc url, or `standalone = true` for a standalone chain",
+					self.name
+				)))
+			}
+			return Ok(self)
+		}
+		if !matches!(self.finality_protocol, FinalityProtocol::Grandpa) {
+			return Err(Error::Custom(format!(
+				"{} is a standalone chain, which is only relayed with the GRANDPA finality protocol",
+				self.name
+			)))
+		}
+		self.para_id = STANDALONE_CHAIN_PARA_ID;
+		self.relay_chain_rpc_url = self.parachain_rpc_url.clone();
+		self.relay_chain_fallback_rpc_urls.clear();
+		Ok(self)
+	}
 }
 
 impl<T> ParachainClient<T>
@@ -195,13 +402,21 @@ where
 	T: light_client_common::config::Config,
 {
 	/// Initializes a [`ParachainClient`] given a [`ParachainConfig`]
-	pub async fn new(config: ParachainClientConfig) -> Result<Self, Error> {
-		let relay_ws_client = Arc::new(
-			WsClientBuilder::default()
-				.build(&config.relay_chain_rpc_url)
-				.await
-				.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?,
-		);
+	pub async fn new(config: ParachainClientConfig) -> Result<Self, Error>
+	where
+		T: 'static,
+	{
+		let config = config.resolve_chain()?;
+		let relay_chain_endpoints = Mutex::new(EndpointRotation::new(
+			config.relay_chain_rpc_url.clone(),
+			config.relay_chain_fallback_rpc_urls.clone(),
+		));
+		let relay_chain = RelayChainConnection::connect_with_failover(
+			&relay_chain_endpoints,
+			config.share_relay_chain_connection,
+		)
+		.await?;
+		let relay_ws_client = relay_chain.ws_client.clone();
 		let para_ws_client = Arc::new(
 			WsClientBuilder::default()
 				.build(&config.parachain_rpc_url)
@@ -210,67 +425,255 @@ where
 		);
 
 		let para_client = subxt::OnlineClient::from_rpc_client(para_ws_client.clone()).await?;
+		if config.dynamic_metadata {
+			dynamic::validate(&para_client.metadata())?;
+			log::info!(target: "hyperspace_parachain", "Relaying {} with the dynamic metadata of the chain", config.name);
+		}
+
+		let relay_client = relay_chain.client::<T>().await?;
 
-		let relay_client = subxt::OnlineClient::from_rpc_client(relay_ws_client.clone()).await?;
+		let secondary_relay_chain = match config.secondary_relay_chain_rpc_url {
+			Some(rpc_url) => Some(SecondaryRelayChain::connect(rpc_url).await?),
+			None => None,
+		};
 
 		let max_extrinsic_weight = fetch_max_extrinsic_weight(&para_client).await?;
+		let runtime_upgrades = RuntimeUpgrades::spawn(vec![
+			(config.name.clone(), para_client.clone()),
+			(format!("the relay chain of {}", config.name), relay_client.clone()),
+		]);
 
 		let temp_dir = PathBuf::from("/tmp/keystore");
 		let key_store: KeystorePtr = Arc::new(LocalKeystore::open(temp_dir, None).unwrap());
 		let key_type = KeyType::from_str(&config.key_type)?;
 		let key_type_id = key_type.to_key_type_id();
 
-		let public_key: MultiSigner = match key_type {
-			KeyType::Sr25519 => sr25519::Pair::from_string_with_seed(&config.private_key, None)
-				.map_err(|_| Error::Custom("invalid key".to_owned()))?
-				.0
-				.public()
-				.into(),
-			KeyType::Ed25519 => ed25519::Pair::from_string_with_seed(&config.private_key, None)
-				.map_err(|_| Error::Custom("invalid key".to_owned()))?
-				.0
-				.public()
-				.into(),
-			KeyType::Ecdsa => ecdsa::Pair::from_string_with_seed(&config.private_key, None)
-				.map_err(|_| Error::Custom("invalid key".to_owned()))?
-				.0
-				.public()
-				.into(),
+		let remote_signer = config.remote_signer.map(|remote_signer| {
+			Arc::new(RemoteSigner::new(remote_signer, key_type.signature_scheme()))
+		});
+		let public_key: MultiSigner = match &remote_signer {
+			Some(remote_signer) => {
+				let signer = remote_signer.clone();
+				let public_key = tokio::task::spawn_blocking(move || signer.public_key())
+					.await
+					.map_err(|e| Error::Custom(e.to_string()))?
+					.map_err(|e| Error::Custom(e.to_string()))?;
+				log::info!(
+					target: "hyperspace_parachain",
+					"Signing the extrinsics of {} with the remote key {}: {}",
+					config.name,
+					remote_signer.key_id(),
+					hex::encode(&public_key)
+				);
+				key_type.public_key(&public_key)?
+			},
+			None => key_type.public_key_from_suri(&config.private_key)?,
 		};
+		log::info!(
+			target: "hyperspace_parachain",
+			"Relayer account of {} ({key_type:?}): {}",
+			config.name,
+			public_key
+				.clone()
+				.into_account()
+				.to_ss58check_with_version(Ss58AddressFormat::from(config.ss58_version))
+		);
 
-		key_store
-			.insert(key_type_id, &*config.private_key, public_key.as_ref())
-			.unwrap();
+		// the keys of a remote signer never are in the keystore
+		if remote_signer.is_none() {
+			key_store
+				.insert(key_type_id, &*config.private_key, public_key.as_ref())
+				.unwrap();
 
-		assert!(key_store.has_keys(&[(public_key.as_ref().to_vec(), key_type_id)]));
-		Ok(Self {
+			assert!(key_store.has_keys(&[(public_key.as_ref().to_vec(), key_type_id)]));
+		}
+		let nonces = nonce_manager(
+			&para_client.genesis_hash().encode(),
+			public_key.clone().into_account().as_ref(),
+		);
+		let client = Self {
 			name: config.name,
-			parachain_rpc_url: config.parachain_rpc_url,
-			relay_chain_rpc_url: config.relay_chain_rpc_url,
+			parachain_rpc_url: Arc::new(Mutex::new(config.parachain_rpc_url)),
+			relay_chain_rpc_url: Arc::new(Mutex::new(relay_chain.rpc_url.clone())),
+			relay_chain_endpoints: Arc::new(relay_chain_endpoints),
+			primary_relay_chain_restored: Arc::new(Notify::new()),
 			para_client,
 			relay_client,
+			relay_chain,
+			share_relay_chain_connection: config.share_relay_chain_connection,
 			para_id: config.para_id,
 			client_id: Arc::new(Mutex::new(config.client_id)),
 			commitment_prefix: config.commitment_prefix.0,
 			connection_id: Arc::new(Mutex::new(config.connection_id)),
 			public_key,
 			key_store,
+			remote_signer,
 			key_type_id,
+			nonces,
 			max_extrinsic_weight,
 			para_ws_client,
 			relay_ws_client,
+			secondary_relay_chain,
 			ss58_version: Ss58AddressFormat::from(config.ss58_version),
 			channel_whitelist: Arc::new(Mutex::new(config.channel_whitelist.into_iter().collect())),
 			finality_protocol: config.finality_protocol,
+			runtime_upgrades,
+			signed_extensions: config.signed_extensions,
+			batch_mode: config.batch_mode,
+			warp_sync: config.warp_sync,
+			dynamic_metadata: config.dynamic_metadata,
 			common_state: CommonClientState {
-				skip_optional_client_updates: true,
+				skip_optional_client_updates: config.common.skip_optional_client_updates,
+				idle_mode: config.common.idle_mode,
+				target_height_updates: config.common.target_height_updates,
+				skip_delivered_check: config.common.skip_delivered_check,
+				fee_ceiling: config.common.fee_ceiling,
 				maybe_has_undelivered_packets: Arc::new(Mutex::new(Default::default())),
 				rpc_call_delay: DEFAULT_RPC_CALL_DELAY,
 				initial_rpc_call_delay: DEFAULT_RPC_CALL_DELAY,
 				misbehaviour_client_msg_queue: Arc::new(AsyncMutex::new(vec![])),
+				max_packets_to_process: config.common.max_packets_to_process as usize,
+				max_packets_in_flight: config.common.max_packets_in_flight.map(|max| max as usize),
+				max_value_in_flight: config.common.max_value_in_flight,
+				batch_limits: Arc::new(Mutex::new(config.common.batch_limits)),
+				queried_batch_limits: Default::default(),
+				message_priority: config.common.message_priority,
+				retry_policy: config.common.retry_policy,
+				min_balance: config.common.min_balance,
+				submission_limiter: SubmissionLimiter::new(config.common.submission_rate_limit),
+				sweep_interval: config.common.sweep_interval_secs.map(Duration::from_secs),
+				timeout_scan_interval: config
+					.common
+					.timeout_scan_interval_secs
+					.map(Duration::from_secs),
+				finality_confirmations: config.common.finality_confirmations,
+				balance_guard: BalanceGuard::new(config.common.pause_below_balance),
+				proof_permits: CommonClientState::proof_permits(config.common.proof_concurrency),
+				fee_payee: config.common.fee_payee,
 				..Default::default()
 			},
-		})
+		};
+		client.spawn_primary_relay_chain_check();
+		Ok(client)
+	}
+
+	/// Probes the primary relay chain rpc every [`PRIMARY_CHECK_INTERVAL`] while a fallback is
+	/// used, and returns to it once it's reachable, by ending the finality notifications of the
+	/// client so that the relayer loop reconnects.
+	fn spawn_primary_relay_chain_check(&self) {
+		let name = self.name.clone();
+		let endpoints = Arc::downgrade(&self.relay_chain_endpoints);
+		let restored = self.primary_relay_chain_restored.clone();
+		tokio::spawn(async move {
+			loop {
+				tokio::time::sleep(PRIMARY_CHECK_INTERVAL).await;
+				// the task stops with the last clone of the client
+				let Some(endpoints) = endpoints.upgrade() else { return };
+				let primary = {
+					let endpoints = endpoints.lock().unwrap();
+					if endpoints.is_on_primary() {
+						continue
+					}
+					endpoints.primary().clone()
+				};
+				if let Err(e) = WsClientBuilder::default().build(&primary).await {
+					log::debug!(target: "hyperspace_parachain", "Primary relay chain rpc {primary} of {name} is still unreachable: {e:?}");
+					continue
+				}
+				log::info!(target: "hyperspace_parachain", "Primary relay chain rpc {primary} of {name} is reachable again, reconnecting");
+				endpoints.lock().unwrap().return_to_primary();
+				restored.notify_waiters();
+			}
+		});
+	}
+
+	/// Applies the settings of the config that can be changed while relaying: the batch limits
+	/// and the rpc urls. New rpc urls are used from the next reconnection of the relayer loop,
+	/// starting with the primary relay chain rpc.
+	pub async fn reload(&self, config: ParachainClientConfig) -> Result<(), Error> {
+		let config = config.resolve_chain()?;
+		if config.para_id != self.para_id {
+			return Err(Error::Custom(format!(
+				"Para id of {} can't be changed from {} to {} without restarting",
+				self.name, self.para_id, config.para_id
+			)))
+		}
+		self.common_state.set_batch_limits(config.common.batch_limits);
+		*self.parachain_rpc_url.lock().unwrap() = config.parachain_rpc_url;
+		self.relay_chain_endpoints
+			.lock()
+			.unwrap()
+			.set_endpoints(config.relay_chain_rpc_url, config.relay_chain_fallback_rpc_urls);
+		Ok(())
+	}
+
+	/// Cross-checks a GRANDPA finality proof fetched from the relay chain rpc against the
+	/// [`SecondaryRelayChain`], if one is configured, so that a single compromised rpc can't feed
+	/// crafted justifications to the light client on the counterparty. Fails if the secondary rpc
+	/// hasn't finalized the target block of the justification, or finalized a different block at
+	/// the same height.
+	pub async fn cross_check_finality_proof(
+		&self,
+		finality_proof: &FinalityProof<T::Header>,
+	) -> Result<(), Error>
+	where
+		<T as subxt::Config>::Header: Decode,
+		H256: From<T::Hash>,
+	{
+		let Some(secondary) = &self.secondary_relay_chain else { return Ok(()) };
+		let justification =
+			GrandpaJustification::<T::Header>::decode(&mut &*finality_proof.justification)?;
+		let (target_number, target_hash) =
+			(justification.commit.target_number, justification.commit.target_hash);
+		if target_hash != finality_proof.block {
+			return Err(Error::Custom(format!(
+				"Justification targets {target_hash:?}, but the finality proof is for {:?}",
+				finality_proof.block
+			)))
+		}
+
+		let encoded = GrandpaApiClient::<JustificationNotification, H256, u32>::prove_finality(
+			&*secondary.ws_client,
+			target_number,
+		)
+		.await
+		.map_err(|e| Error::from(format!("Failed to query the secondary relay chain rpc: {e:?}")))?
+		.ok_or_else(|| {
+			Error::Custom(format!(
+				"Relay block {target_number} isn't finalized according to the secondary relay chain rpc"
+			))
+		})?
+		.0;
+		let secondary_proof = FinalityProof::<T::Header>::decode(&mut &encoded[..])?;
+		let secondary_justification =
+			GrandpaJustification::<T::Header>::decode(&mut &*secondary_proof.justification)?;
+
+		// the secondary rpc proves the highest block finalized by the authority set, which may be
+		// a descendant of the target
+		let secondary_hash = if secondary_justification.commit.target_number == target_number {
+			secondary_justification.commit.target_hash
+		} else {
+			secondary
+				.client
+				.rpc()
+				.block_hash(Some(target_number.into()))
+				.await?
+				.map(H256::from)
+				.ok_or_else(|| {
+					Error::Custom(format!(
+						"Relay block {target_number} not found on the secondary relay chain rpc"
+					))
+				})?
+		};
+		if secondary_hash != target_hash {
+			return Err(Error::Custom(format!(
+				"Justifications of relay block {target_number} diverge: {target_hash:?} on {}, \
+				{secondary_hash:?} on {}",
+				self.relay_chain_rpc_url.lock().unwrap(),
+				secondary.rpc_url
+			)))
+		}
+		Ok(())
 	}
 }
 
@@ -405,38 +808,37 @@ where
 	/// Submits the given transaction to the parachain node, waits for it to be included in a block
 	/// and asserts that it was successfully dispatched on-chain.
 	///
-	/// We retry sending the transaction up to 5 times in the case where the transaction pool might
-	/// reject the transaction because of conflicting nonces.
+	/// The extrinsic is signed with a nonce reserved from the [`NonceManager`] of the relayer
+	/// account, so that concurrent submissions don't wait for each other. The submission is retried
+	/// with the [`RetryPolicy`](primitives::retry::RetryPolicy) of the client, e.g. in the case
+	/// where the transaction pool rejects the transaction because of conflicting nonces, which are
+	/// then resynchronized.
 	pub async fn submit_call<C: TxPayload>(&self, call: C) -> Result<(T::Hash, T::Hash), Error> {
-		// Try extrinsic submission five times in case of failures
-		let mut count = 0;
-		let progress = loop {
-			if count == 10 {
-				Err(Error::Custom("Failed to submit extrinsic after 5 tries".to_string()))?
-			}
-
-			let other_params = T::custom_extrinsic_params(&self.para_client).await?;
+		let (ext_hash, block_hash, _) = self.submit_call_with_events(call).await?;
+		Ok((ext_hash, block_hash))
+	}
 
-			let res = {
-				let signer = ExtrinsicSigner::<T, Self>::new(
-					self.key_store.clone(),
-					self.key_type_id.clone(),
-					self.public_key.clone(),
-				);
-				self.para_client
-					.tx()
-					.sign_and_submit_then_watch(&call, &signer, other_params)
-					.await
-			};
-			match res {
-				Ok(progress) => break progress,
-				Err(e) => {
-					log::warn!("Failed to submit extrinsic: {:?}. Retrying...", e);
-					count += 1;
-					tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-				},
-			}
-		};
+	/// Like [`Self::submit_call`], also returning the events of the extrinsic.
+	pub async fn submit_call_with_events<C: TxPayload>(
+		&self,
+		call: C,
+	) -> Result<(T::Hash, T::Hash, ExtrinsicEvents<T>), Error> {
+		let call = &call;
+		let progress = self
+			.common_state
+			.retry_policy
+			.retry("submit_call", || async move {
+				let nonce = self.nonces.reserve(|| self.query_account_nonce()).await?;
+				let progress = match self.create_signed(call, Some(nonce)).await {
+					Ok(extrinsic) => extrinsic.submit_and_watch().await.map_err(Error::from),
+					Err(e) => Err(e),
+				};
+				if let Err(e) = &progress {
+					self.nonces.release(nonce, e).await;
+				}
+				progress
+			})
+			.await?;
 
 		let tx_in_block =
 			tokio::time::timeout(WAIT_FOR_IN_BLOCK_TIMEOUT, progress.wait_for_in_block())
@@ -444,8 +846,88 @@ where
 				.map_err(|e| {
 					Error::from(format!("[submit_call] Failed to wait for in block due to {:?}", e))
 				})??;
-		tx_in_block.wait_for_success().await?;
-		Ok((tx_in_block.extrinsic_hash(), tx_in_block.block_hash()))
+		let events = tx_in_block.wait_for_success().await?;
+		Ok((tx_in_block.extrinsic_hash(), tx_in_block.block_hash(), events))
+	}
+
+	/// Signs the extrinsic of the call, with the given nonce or the next one of the relayer
+	/// account. The signature of a remote signer is requested before the extrinsic is built, so
+	/// that the extrinsics it refuses to sign fail here.
+	pub async fn create_signed<C: TxPayload>(
+		&self,
+		call: &C,
+		nonce: Option<u32>,
+	) -> Result<SubmittableExtrinsic<T, subxt::OnlineClient<T>>, Error> {
+		let other_params = T::extrinsic_params(&self.para_client, &self.signed_extensions).await?;
+		let signer = ExtrinsicSigner::<T, Self>::new(
+			self.key_store.clone(),
+			self.key_type_id.clone(),
+			self.public_key.clone(),
+		)
+		.with_remote_signer(self.remote_signer.clone(), &self.name);
+		let tx = self.para_client.tx();
+		if self.remote_signer.is_none() {
+			return Ok(match nonce {
+				Some(nonce) => tx.create_signed_with_nonce(call, &signer, nonce, other_params)?,
+				None => tx.create_signed(call, &signer, other_params).await?,
+			})
+		}
+		let partial = match nonce {
+			Some(nonce) => tx.create_partial_signed_with_nonce(call, nonce, other_params)?,
+			None => tx.create_partial_signed(call, signer.account_id(), other_params).await?,
+		};
+		let signature = signer
+			.sign_remotely(partial.signer_payload())
+			.await?
+			.expect("the signer has a remote signer; qed");
+		Ok(partial.sign_with_address_and_signature(&signer.address(), &signature))
+	}
+
+	/// The next nonce of the relayer account on the parachain, as `system_accountNextIndex`
+	/// returns it: unlike the nonce stored in `system.account` at the latest block, it counts the
+	/// extrinsics of the account that are still in the transaction pool.
+	pub async fn query_account_nonce(&self) -> Result<u32, Error> {
+		let account = self.public_key.clone().into_account().to_ss58check();
+		let nonce: u64 = self
+			.para_ws_client
+			.request("system_accountNextIndex", rpc_params![account])
+			.await?;
+		u32::try_from(nonce)
+			.map_err(|_| Error::from(format!("Nonce of the relayer out of range: {nonce}")))
+	}
+
+	/// The call delivering the messages, batched with the [`BatchMode`] of the client.
+	pub fn deliver_call(
+		&self,
+		messages: Vec<Any>,
+	) -> DeliverCall<<T::Tx as RuntimeTransactions>::Deliver> {
+		let ibc_deliver = |messages| match self.dynamic_metadata {
+			true => IbcCall::Dynamic(dynamic::deliver(messages)),
+			false => IbcCall::Static(T::Tx::ibc_deliver(messages)),
+		};
+		match self.batch_mode {
+			Some(mode) => DeliverCall::Batch(
+				mode,
+				messages.into_iter().map(|msg| ibc_deliver(vec![msg])).collect(),
+			),
+			None => DeliverCall::Single(ibc_deliver(messages)),
+		}
+	}
+
+	/// The timestamp of the parachain block, in milliseconds, as `timestamp.now` stores it.
+	pub async fn query_timestamp_millis(&self, block_hash: T::Hash) -> Result<u64, Error> {
+		let storage = self.para_client.storage().at(block_hash);
+		let timestamp = match self.dynamic_metadata {
+			true => storage
+				.fetch(&dynamic::timestamp_now())
+				.await?
+				.map(dynamic::decode_timestamp)
+				.transpose()?,
+			false => storage.fetch(&T::Storage::timestamp_now()).await?,
+		};
+		timestamp.ok_or_else(|| {
+			Error::from(format!("Timestamp not found at the parachain block {block_hash:?}"))
+		})
 	}
 
 	pub fn client_id(&self) -> ClientId {
@@ -491,7 +973,6 @@ where
 	{
 		use ibc::core::ics24_host::identifier::ChainId;
 		let api = self.relay_client.storage();
-		let para_client_api = self.para_client.storage();
 		let client_wrapper = Prover {
 			relay_client: self.relay_client.clone(),
 			para_client: self.para_client.clone(),
@@ -543,12 +1024,7 @@ where
 				self.para_client.rpc().block_hash(Some(subxt_block_number)).await?.ok_or_else(
 					|| Error::Custom(format!("Couldn't find block hash for para block",)),
 				)?;
-			let timestamp_addr = T::Storage::timestamp_now();
-			let unix_timestamp_millis = para_client_api
-				.at(block_hash)
-				.fetch(&timestamp_addr)
-				.await?
-				.expect("Timestamp should exist");
+			let unix_timestamp_millis = self.query_timestamp_millis(block_hash).await?;
 			let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
 
 			let consensus_state = AnyConsensusState::Beefy(BeefyConsensusState {
@@ -586,31 +1062,14 @@ where
 			para_id: self.para_id,
 			rpc_call_delay: self.common_state.rpc_call_delay,
 		};
-		let api = self.relay_client.storage();
-		let para_client_api = self.para_client.storage();
 		loop {
 			let light_client_state = prover
 				.initialize_client_state()
 				.await
 				.map_err(|e| Error::from(format!("Error constructing client state: {e}")))?;
 
-			let heads_addr = T::Storage::paras_heads(self.para_id);
-			let head_data = <T::Storage as RuntimeStorage>::HeadData::from_inner(
-				api.at(light_client_state.latest_relay_hash.into())
-					.fetch(&heads_addr)
-					.await?
-					.ok_or_else(|| {
-						Error::Custom(format!(
-							"Couldn't find header for ParaId({}) at relay block {:?}",
-							self.para_id, light_client_state.latest_relay_hash
-						))
-					})?,
-			);
-			let decoded_para_head = sp_runtime::generic::Header::<
-				u32,
-				sp_runtime::traits::BlakeTwo256,
-			>::decode(&mut &*head_data.as_ref())?;
-			let block_number = decoded_para_head.number;
+			// the head of the parachain, or the finalized block of a standalone chain
+			let block_number = light_client_state.latest_para_height;
 			// we can't use the genesis block to construct the initial state.
 			if block_number == 0 {
 				continue
@@ -637,12 +1096,7 @@ where
 						))
 					},
 				)?;
-			let timestamp_addr = T::Storage::timestamp_now();
-			let unix_timestamp_millis = para_client_api
-				.at(block_hash)
-				.fetch(&timestamp_addr)
-				.await?
-				.expect("Timestamp should exist");
+			let unix_timestamp_millis = self.query_timestamp_millis(block_hash).await?;
 			let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
 
 			let consensus_state = AnyConsensusState::Grandpa(GrandpaConsensusState {
@@ -5,19 +5,18 @@ use std::{
 };
 
 use finality_grandpa::BlockNumberOps;
-use grandpa_light_client_primitives::{ParachainHeaderProofs, ParachainHeadersWithFinalityProof};
+use grandpa_light_client_primitives::{
+	is_warp_fragment, FinalityProof, ParachainHeaderProofs, ParachainHeadersWithFinalityProof,
+};
 use ibc_proto::google::protobuf::Any;
 use sp_core::H256;
 use sp_runtime::{
 	traits::{IdentifyAccount, One, Verify},
 	MultiSignature, MultiSigner,
 };
-use subxt::config::{
-	extrinsic_params::{BaseExtrinsicParamsBuilder, ExtrinsicParams},
-	Header as HeaderT, Header,
-};
+use subxt::config::{extrinsic_params::ExtrinsicParams, Header as HeaderT, Header};
 
-use grandpa_prover::GrandpaProver;
+use grandpa_prover::{GrandpaJustification, GrandpaProver};
 use ibc::core::ics02_client::msgs::update_client::MsgUpdateAnyClient;
 use tendermint_proto::Protobuf;
 
@@ -25,7 +24,7 @@ use ibc::{
 	core::ics24_host::identifier::ClientId, events::IbcEvent, signer::Signer, tx_msg::Msg, Height,
 };
 use ibc_rpc::{BlockNumberOrHash, IbcApiClient};
-use ics10_grandpa::client_message::{ClientMessage, Header as GrandpaHeader};
+use ics10_grandpa::client_message::{ClientMessage, Header as GrandpaHeader, RelayChainHeader};
 use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState};
 
 use primitives::{
@@ -56,8 +55,7 @@ where
 	sp_core::H256: From<T::Hash>,
 	BTreeMap<sp_core::H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	<T as light_client_common::config::Config>::AssetId: Clone,
 	<T as subxt::Config>::AccountId: Send + Sync,
 	<T as subxt::Config>::Address: Send + Sync,
@@ -182,9 +180,7 @@ where
 		<<T as subxt::Config>::ExtrinsicParams as ExtrinsicParams<
 			<T as subxt::Config>::Index,
 			<T as subxt::Config>::Hash,
-		>>::OtherParams: Sync
-			+ Send
-			+ From<BaseExtrinsicParamsBuilder<T, <T as light_client_common::config::Config>::Tip>>,
+		>>::OtherParams: Sync + Send,
 		<T as subxt::Config>::Hash: From<H256>,
 		<T as subxt::Config>::Hash: From<[u8; 32]>,
 		<T as light_client_common::config::Config>::AssetId: Clone,
@@ -213,21 +209,46 @@ where
 				"Getting message for session end block: #{} (finalized #{}) ({}/{})",
 				session_end_block, latest_finalized_height, count + 1, limit
 			);
-			let (msg, evs, previous_para_height, ..) = get_message(
-				self,
-				counterparty,
-				&prover,
-				previous_finalized_para_height,
-				previous_finalized_height,
-				session_end_block,
-				client_id.clone(),
-				signer.clone(),
-				&self.name,
-				self.para_id,
-			)
-			.await?;
-			messages.push(msg);
-			events.extend(evs);
+			// the last update of the iteration proves the parachain headers
+			let is_last =
+				session_end_block + session_length > latest_finalized_height || count + 1 == limit;
+			let warp_fragment = if self.warp_sync && !is_last {
+				get_warp_fragment(
+					self,
+					&prover,
+					previous_finalized_para_height,
+					session_end_block,
+					client_id.clone(),
+					signer.clone(),
+				)
+				.await?
+			} else {
+				None
+			};
+			let previous_para_height = match warp_fragment {
+				Some((msg, finalized_para_height)) => {
+					messages.push(msg);
+					finalized_para_height
+				},
+				None => {
+					let (msg, evs, previous_para_height, ..) = get_message(
+						self,
+						counterparty,
+						&prover,
+						previous_finalized_para_height,
+						previous_finalized_height,
+						session_end_block,
+						client_id.clone(),
+						signer.clone(),
+						&self.name,
+						self.para_id,
+					)
+					.await?;
+					messages.push(msg);
+					events.extend(evs);
+					previous_para_height
+				},
+			};
 			previous_finalized_height = session_end_block;
 			previous_finalized_para_height = previous_para_height;
 			session_end_block += session_length;
@@ -237,9 +258,78 @@ where
 	}
 }
 
+/// Return a warp sync fragment: the client update to the block enacting the authority set change
+/// at `set_change_block`, proven by its justification alone (see
+/// [`grandpa_light_client_primitives::is_warp_fragment`]), and the parachain height finalized at
+/// this block. The events of the parachain blocks the fragment skips aren't queried. Returns `None`
+/// if the justification doesn't target a block enacting a set change.
+async fn get_warp_fragment<T: light_client_common::config::Config + Send + Sync>(
+	source: &ParachainClient<T>,
+	prover: &GrandpaProver<T>,
+	previous_finalized_para_height: u32,
+	set_change_block: u32,
+	client_id: ClientId,
+	signer: Signer,
+) -> Result<Option<(Any, u32)>, anyhow::Error>
+where
+	u32: From<<<T as subxt::Config>::Header as Header>::Number>,
+	<<T as subxt::Config>::Header as Header>::Number: Ord + sp_runtime::traits::Zero,
+	<T as subxt::Config>::Header: Decode,
+	T::Hash: From<H256>,
+	H256: From<T::Hash>,
+{
+	let encoded = source.relay_chain.prove_finality(set_change_block).await?.ok_or_else(|| {
+		Error::Custom(format!("No justification found for block: {set_change_block}"))
+	})?;
+	let mut finality_proof = FinalityProof::<T::Header>::decode(&mut &*encoded)?;
+	let justification =
+		GrandpaJustification::<T::Header>::decode(&mut &*finality_proof.justification)?;
+	let (target_number, target_hash) =
+		(justification.commit.target_number, justification.commit.target_hash);
+	finality_proof.block = target_hash;
+	let target = source
+		.relay_client
+		.rpc()
+		.header(Some(target_hash.into()))
+		.await?
+		.ok_or_else(|| Error::Custom(format!("Header not found for hash: {target_hash:?}")))?;
+	finality_proof.unknown_headers = vec![target];
+	source.cross_check_finality_proof(&finality_proof).await?;
+
+	let finality_proof: FinalityProof<RelayChainHeader> =
+		codec::Decode::decode(&mut &*finality_proof.encode())
+			.expect("Same struct from different crates,decode should not fail");
+	if !is_warp_fragment(&finality_proof, &Default::default()) {
+		log::debug!(
+			target: "hyperspace_parachain",
+			"Block #{target_number} doesn't enact a set change, proving its ancestry"
+		);
+		return Ok(None)
+	}
+
+	let finalized_para_height =
+		u32::from(prover.query_latest_finalized_parachain_header(target_number).await?.number());
+	log::info!(
+		target: "hyperspace_parachain",
+		"Warp syncing {} to the authority set change at #{target_number}", source.name
+	);
+	let grandpa_header = GrandpaHeader {
+		finality_proof,
+		parachain_headers: Default::default(),
+		height: Height::new(source.para_id as u64, previous_finalized_para_height as u64),
+	};
+	let msg = MsgUpdateAnyClient::<LocalClientTypes> {
+		client_id,
+		client_message: AnyClientMessage::Grandpa(ClientMessage::Header(grandpa_header)),
+		signer,
+	};
+	let value = msg.encode_vec()?;
+	Ok(Some((Any { value, type_url: msg.type_url() }, finalized_para_height)))
+}
+
 /// Return a single client update message
 async fn get_message<T: light_client_common::config::Config + Send + Sync>(
-	source: &impl Chain,
+	source: &ParachainClient<T>,
 	counterparty: &impl Chain,
 	prover: &GrandpaProver<T>,
 	previous_finalized_para_height: u32,
@@ -259,6 +349,7 @@ where
 	H256: From<T::Hash>,
 	BTreeMap<sp_core::H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
+	ParachainClient<T>: Chain,
 {
 	// fetch the latest finalized parachain header
 	let finalized_para_header =
@@ -338,6 +429,7 @@ where
 			headers_with_events.into_iter().collect(),
 		)
 		.await?;
+	source.cross_check_finality_proof(&finality_proof).await?;
 
 	let grandpa_header = GrandpaHeader {
 		finality_proof: codec::Decode::decode(&mut &*finality_proof.encode())
@@ -0,0 +1,323 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allocation of the nonces of the relayer accounts.
+//!
+//! Reading the nonce of the account before signing each extrinsic serializes the submissions,
+//! since the nonce only advances once the previous extrinsic reached the transaction pool. The
+//! [`NonceManager`] of an account instead reserves its next nonce for each submission, so that
+//! concurrent extrinsics are signed with consecutive nonces. The managers are shared by the
+//! clients relaying with the same account on the same chain, see [`nonce_manager`].
+//!
+//! The next nonce is read with `system_accountNextIndex`, which counts the extrinsics of the
+//! account in the transaction pool, and read again when the transaction pool rejects an extrinsic
+//! as `Stale` (its nonce was used on the chain, e.g. by another process) or `Future` (an earlier
+//! nonce was never used), or when a reserved nonce is released while later ones are in use, which
+//! would otherwise leave a gap.
+
+use crate::error::Error;
+use jsonrpsee::types::{error::CallError, ErrorObjectOwned};
+use std::{
+	collections::HashMap,
+	future::Future,
+	sync::{Arc, Mutex, OnceLock},
+};
+use tokio::sync::Mutex as AsyncMutex;
+
+/// Code of the rpc errors of the extrinsics the transaction pool finds invalid, with the reason in
+/// their data
+const POOL_INVALID_TX: i32 = 1010;
+
+/// Code of the rpc errors of the extrinsics replacing one of the transaction pool with a lower
+/// priority
+const POOL_TOO_LOW_PRIORITY: i32 = 1014;
+
+static NONCE_MANAGERS: OnceLock<Mutex<HashMap<(Vec<u8>, Vec<u8>), Arc<NonceManager>>>> =
+	OnceLock::new();
+
+/// Nonce manager of an account, by the genesis hash of the chain, shared by the clients signing
+/// with it.
+pub fn nonce_manager(genesis_hash: &[u8], account: &[u8]) -> Arc<NonceManager> {
+	NONCE_MANAGERS
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap()
+		.entry((genesis_hash.to_vec(), account.to_vec()))
+		.or_default()
+		.clone()
+}
+
+/// Rejections of the transaction pool caused by the nonce of the extrinsic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceError {
+	/// The nonce is lower than the one of the account on the chain
+	Stale,
+	/// The nonce is higher than the one of the account on the chain
+	Future,
+	/// An extrinsic with the same nonce is in the transaction pool
+	InPool,
+}
+
+impl NonceError {
+	/// Classifies the rejection of an extrinsic by the code of its rpc error, or by its message
+	/// for the rejections that don't come with one, e.g. those of the watched extrinsics.
+	pub fn of(error: &Error) -> Option<Self> {
+		if let Some(object) = rpc_error_object(error) {
+			return match object.code() {
+				POOL_INVALID_TX => {
+					let reason = object.data().map(|data| data.get().to_lowercase());
+					match reason.as_deref() {
+						Some(reason) if reason.contains("outdated") => Some(Self::Stale),
+						Some(reason) if reason.contains("future") => Some(Self::Future),
+						_ => None,
+					}
+				},
+				POOL_TOO_LOW_PRIORITY => Some(Self::InPool),
+				_ => None,
+			}
+		}
+		let message = error.to_string().to_lowercase();
+		if message.contains("transaction is outdated") || message.contains("stale") {
+			Some(Self::Stale)
+		} else if message.contains("will be valid in the future") {
+			Some(Self::Future)
+		} else if message.contains("priority is too low") {
+			Some(Self::InPool)
+		} else {
+			None
+		}
+	}
+}
+
+/// The error object of the rpc call that failed with the error, if any.
+fn rpc_error_object(error: &Error) -> Option<&ErrorObjectOwned> {
+	let rpc_error = match error {
+		Error::Subxt(subxt::Error::Rpc(subxt::error::RpcError::ClientError(e))) |
+		Error::SubxtRRpc(subxt::error::RpcError::ClientError(e)) =>
+			e.downcast_ref::<jsonrpsee::core::Error>()?,
+		Error::JosnrpseeError(e) => e,
+		_ => return None,
+	};
+	match rpc_error {
+		jsonrpsee::core::Error::Call(CallError::Custom(object)) => Some(object),
+		_ => None,
+	}
+}
+
+/// Next nonce of an account, unknown until read from the chain.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+	next: AsyncMutex<Option<u32>>,
+}
+
+impl NonceManager {
+	/// Reserves the next nonce, reading it with `fetch` if it's unknown. The concurrent
+	/// reservations wait while the nonce is read.
+	pub async fn reserve<F, Fut>(&self, fetch: F) -> Result<u32, Error>
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<u32, Error>>,
+	{
+		let mut next = self.next.lock().await;
+		let nonce = match *next {
+			Some(nonce) => nonce,
+			None => fetch().await?,
+		};
+		*next = Some(nonce + 1);
+		Ok(nonce)
+	}
+
+	/// Releases a nonce whose extrinsic was rejected. The last reserved nonce is reused by the
+	/// next extrinsic, and the nonce is read from the chain again if it can't be.
+	pub async fn release(&self, nonce: u32, error: &Error) {
+		let mut next = self.next.lock().await;
+		match NonceError::of(error) {
+			Some(NonceError::InPool) => {
+				// the extrinsics of the pool may have been submitted by another process
+				*next = next.map(|next| next.max(nonce + 1));
+			},
+			Some(kind) => {
+				log::info!(target: "hyperspace_parachain", "Nonce {nonce} was rejected as {kind:?}, resynchronizing from the chain");
+				*next = None;
+			},
+			None if *next == Some(nonce + 1) => *next = Some(nonce),
+			None => *next = None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpsee::types::ErrorObject;
+
+	fn pool_error(code: i32, message: &str, reason: &str) -> jsonrpsee::core::Error {
+		jsonrpsee::core::Error::Call(CallError::Custom(ErrorObject::owned(
+			code,
+			message,
+			Some(reason),
+		)))
+	}
+
+	fn stale() -> Error {
+		Error::JosnrpseeError(pool_error(
+			POOL_INVALID_TX,
+			"Invalid Transaction",
+			"Transaction is outdated",
+		))
+	}
+
+	fn future() -> Error {
+		Error::JosnrpseeError(pool_error(
+			POOL_INVALID_TX,
+			"Invalid Transaction",
+			"Transaction will be valid in the future",
+		))
+	}
+
+	fn in_pool() -> Error {
+		Error::JosnrpseeError(pool_error(
+			POOL_TOO_LOW_PRIORITY,
+			"Priority is too low: (140 vs 140)",
+			"The transaction has too low priority to replace another transaction already in the pool.",
+		))
+	}
+
+	fn other() -> Error {
+		Error::Custom("The remote signer refused the request".to_string())
+	}
+
+	/// Fetches of the nonce of the chain, counted.
+	struct Chain {
+		nonce: Mutex<u32>,
+		fetches: Mutex<u32>,
+	}
+
+	impl Chain {
+		fn new(nonce: u32) -> Self {
+			Self { nonce: Mutex::new(nonce), fetches: Mutex::new(0) }
+		}
+
+		async fn fetch(&self) -> Result<u32, Error> {
+			*self.fetches.lock().unwrap() += 1;
+			Ok(*self.nonce.lock().unwrap())
+		}
+
+		fn fetches(&self) -> u32 {
+			*self.fetches.lock().unwrap()
+		}
+	}
+
+	#[test]
+	fn pool_errors_are_classified_by_their_code() {
+		assert_eq!(NonceError::of(&stale()), Some(NonceError::Stale));
+		assert_eq!(NonceError::of(&future()), Some(NonceError::Future));
+		assert_eq!(NonceError::of(&in_pool()), Some(NonceError::InPool));
+		let banned = pool_error(1012, "Transaction is temporarily banned", "");
+		assert_eq!(NonceError::of(&Error::JosnrpseeError(banned)), None);
+		let payment =
+			pool_error(POOL_INVALID_TX, "Invalid Transaction", "Inability to pay some fees");
+		assert_eq!(NonceError::of(&Error::JosnrpseeError(payment)), None);
+		assert_eq!(NonceError::of(&other()), None);
+	}
+
+	#[test]
+	fn the_pool_errors_of_subxt_are_classified() {
+		let error = subxt::error::RpcError::ClientError(Box::new(pool_error(
+			POOL_INVALID_TX,
+			"Invalid Transaction",
+			"Transaction is outdated",
+		)));
+		assert_eq!(
+			NonceError::of(&Error::Subxt(subxt::Error::Rpc(error))),
+			Some(NonceError::Stale)
+		);
+		let error = subxt::error::RpcError::ClientError(Box::new(pool_error(
+			POOL_TOO_LOW_PRIORITY,
+			"Priority is too low: (140 vs 140)",
+			"",
+		)));
+		assert_eq!(NonceError::of(&Error::SubxtRRpc(error)), Some(NonceError::InPool));
+	}
+
+	#[test]
+	fn the_messages_of_unstructured_errors_are_classified() {
+		let error = Error::Custom("Transaction is outdated".to_string());
+		assert_eq!(NonceError::of(&error), Some(NonceError::Stale));
+		let error = Error::Custom("Transaction will be valid in the future".to_string());
+		assert_eq!(NonceError::of(&error), Some(NonceError::Future));
+	}
+
+	#[tokio::test]
+	async fn nonces_are_reserved_in_sequence() {
+		let chain = Chain::new(5);
+		let nonces = NonceManager::default();
+		for expected in 5..8 {
+			assert_eq!(nonces.reserve(|| chain.fetch()).await.unwrap(), expected);
+		}
+		assert_eq!(chain.fetches(), 1);
+	}
+
+	#[tokio::test]
+	async fn the_last_released_nonce_is_reused() {
+		let chain = Chain::new(5);
+		let nonces = NonceManager::default();
+		nonces.reserve(|| chain.fetch()).await.unwrap();
+		let nonce = nonces.reserve(|| chain.fetch()).await.unwrap();
+		nonces.release(nonce, &other()).await;
+		assert_eq!(nonces.reserve(|| chain.fetch()).await.unwrap(), 6);
+		assert_eq!(chain.fetches(), 1);
+	}
+
+	#[tokio::test]
+	async fn releasing_an_earlier_nonce_resynchronizes() {
+		let chain = Chain::new(5);
+		let nonces = NonceManager::default();
+		let nonce = nonces.reserve(|| chain.fetch()).await.unwrap();
+		nonces.reserve(|| chain.fetch()).await.unwrap();
+		// nonce 6 is reserved, so 5 is read from the chain again rather than assumed free
+		nonces.release(nonce, &other()).await;
+		assert_eq!(nonces.reserve(|| chain.fetch()).await.unwrap(), 5);
+		assert_eq!(chain.fetches(), 2);
+	}
+
+	#[tokio::test]
+	async fn stale_and_future_nonces_resynchronize() {
+		for error in [stale(), future()] {
+			let chain = Chain::new(5);
+			let nonces = NonceManager::default();
+			let nonce = nonces.reserve(|| chain.fetch()).await.unwrap();
+			// another process used the nonce, or an earlier one was never used
+			*chain.nonce.lock().unwrap() = 9;
+			nonces.release(nonce, &error).await;
+			assert_eq!(nonces.reserve(|| chain.fetch()).await.unwrap(), 9);
+			assert_eq!(chain.fetches(), 2);
+		}
+	}
+
+	#[tokio::test]
+	async fn nonces_in_the_pool_are_skipped() {
+		let chain = Chain::new(5);
+		let nonces = NonceManager::default();
+		let nonce = nonces.reserve(|| chain.fetch()).await.unwrap();
+		nonces.release(nonce, &in_pool()).await;
+		assert_eq!(nonces.reserve(|| chain.fetch()).await.unwrap(), 6);
+
+		// the next nonce isn't rewound by an earlier nonce in the pool
+		nonces.reserve(|| chain.fetch()).await.unwrap();
+		nonces.release(6, &in_pool()).await;
+		assert_eq!(nonces.reserve(|| chain.fetch()).await.unwrap(), 8);
+		assert_eq!(chain.fetches(), 1);
+	}
+}
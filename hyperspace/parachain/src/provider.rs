@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use super::{error::Error, ParachainClient};
-use crate::{finality_protocol::FinalityEvent, FinalityProtocol, GrandpaClientState};
+use crate::{dynamic, finality_protocol::FinalityEvent, FinalityProtocol, GrandpaClientState};
 use beefy_prover::helpers::fetch_timestamp_extrinsic_with_proof;
 use codec::{Decode, Encode};
 use finality_grandpa::BlockNumberOps;
@@ -51,7 +51,10 @@ use pallet_ibc::{
 	light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager},
 	HostConsensusProof,
 };
-use primitives::{apply_prefix, Chain, IbcProvider, KeyProvider, UpdateType};
+use primitives::{
+	apply_prefix, channel_upgrades::ChannelUpgrade, fees::IncentivizedPacket, BlockCommitment,
+	Chain, IbcProvider, KeyProvider, UpdateType, UpgradedClient,
+};
 use sp_core::H256;
 use sp_runtime::{
 	traits::{IdentifyAccount, One, Verify},
@@ -64,9 +67,7 @@ use std::{
 	str::FromStr,
 	time::Duration,
 };
-use subxt::config::{
-	extrinsic_params::BaseExtrinsicParamsBuilder, ExtrinsicParams, Header as HeaderT, Header,
-};
+use subxt::config::{ExtrinsicParams, Header as HeaderT, Header};
 use tokio_stream::wrappers::ReceiverStream;
 
 #[derive(Debug)]
@@ -101,8 +102,7 @@ where
 	sp_core::H256: From<T::Hash>,
 	BTreeMap<sp_core::H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	<T as subxt::Config>::AccountId: Send + Sync,
 	<T as subxt::Config>::Address: Send + Sync,
 	<T as light_client_common::config::Config>::AssetId: Clone,
@@ -132,6 +132,7 @@ where
 		let (tx, rx) = tokio::sync::mpsc::channel(32);
 		let event = self.para_client.events();
 		let para_client = self.para_client.clone();
+		let dynamic_metadata = self.dynamic_metadata;
 		tokio::spawn(async move {
 			let stream = para_client
 				.blocks()
@@ -142,6 +143,25 @@ where
 					let block = block.ok()?;
 					let hash = block.hash();
 					let events = event.at(hash).await.ok()?;
+					if dynamic_metadata {
+						let ibc_events = dynamic::ibc_events(&events)
+							.map_err(|e| {
+								log::error!(target: "hyperspace_parachain", "Error event at block {hash:?}: {:?}", e);
+							})
+							.ok()?;
+						let result = ibc_events
+							.into_iter()
+							.filter_map(|(_, events)| {
+								events
+									.into_iter()
+									.map(|ev| TryInto::<IbcEvent>::try_into(ev))
+									.collect::<Result<Vec<_>, _>>()
+									.ok()
+							})
+							.flatten()
+							.collect::<Vec<_>>();
+						return Some(result)
+					}
 					let result = events
 						.find::<<T::Events as AsInnerEvent>::Inner>()
 						.filter_map(|ev| {
@@ -199,6 +219,22 @@ where
 		Ok(res)
 	}
 
+	async fn query_client_consensus_states(
+		&self,
+		at: Height,
+		client_id: ClientId,
+		heights: Vec<Height>,
+	) -> Result<Vec<(Height, QueryConsensusStateResponse)>, Self::Error> {
+		// the runtime API has no batched query, the consensus states are queried one by one
+		let mut responses = vec![];
+		for height in heights {
+			if let Ok(response) = self.query_client_consensus(at, client_id.clone(), height).await {
+				responses.push((height, response));
+			}
+		}
+		Ok(responses)
+	}
+
 	async fn query_client_state(
 		&self,
 		at: Height,
@@ -247,21 +283,37 @@ where
 		Ok(response)
 	}
 
-	/// Query the proof of the given keys at the given height.
+	/// Query the proof of the given keys at the given height, from the proof cache if it was
+	/// queried already, see [`primitives::proof_cache`].
 	///
 	/// Note: all the keys will be prefixed with the connection prefix.
 	async fn query_proof(&self, at: Height, keys: Vec<Vec<u8>>) -> Result<Vec<u8>, Self::Error> {
 		let prefix = self.connection_prefix().into_vec();
-		let prefixed_keys =
+		let prefixed_keys: Vec<_> =
 			keys.into_iter().map(|path| apply_prefix(prefix.clone(), path)).collect();
 
+		let proof_cache = &self.common_state.proof_cache;
+		let block_hash = self
+			.para_client
+			.rpc()
+			.block_hash(Some(at.revision_height.into()))
+			.await?
+			.map(|hash| hash.encode());
+		if let Some(proof) =
+			block_hash.as_ref().and_then(|hash| proof_cache.get(hash, &prefixed_keys))
+		{
+			return Ok(proof)
+		}
 		let proof = IbcApiClient::<u32, H256, <T as light_client_common::config::Config>::AssetId>::query_proof(
 			&*self.para_ws_client,
 			at.revision_height as u32,
-			prefixed_keys,
+			prefixed_keys.clone(),
 		)
 		.await
 		.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		if let Some(hash) = block_hash {
+			proof_cache.insert(hash, prefixed_keys, proof.proof.clone());
+		}
 		Ok(proof.proof)
 	}
 
@@ -355,14 +407,7 @@ where
 			self.para_client.rpc().block_hash(Some(subxt_block_number)).await?.ok_or_else(
 				|| Error::Custom("Latest block hash query returned None".to_string()),
 			)?;
-		let timestamp_addr = T::Storage::timestamp_now();
-		let unix_timestamp_millis = self
-			.para_client
-			.storage()
-			.at(block_hash)
-			.fetch(&timestamp_addr)
-			.await?
-			.ok_or_else(|| Error::from("Timestamp should exist".to_string()))?;
+		let unix_timestamp_millis = self.query_timestamp_millis(block_hash).await?;
 		let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
 
 		Ok((height, Timestamp::from_nanoseconds(timestamp_nanos)?))
@@ -629,19 +674,39 @@ where
 			self.para_client.rpc().block_hash(Some(subxt_block_number)).await?.ok_or_else(
 				|| Error::Custom("Block hash not found for block number".to_string()),
 			)?;
-		let timestamp_addr = T::Storage::timestamp_now();
-		let unix_timestamp_millis = self
-			.para_client
-			.storage()
-			.at(block_hash)
-			.fetch(&timestamp_addr)
-			.await?
-			.expect("Timestamp should exist");
+		let unix_timestamp_millis = self.query_timestamp_millis(block_hash).await?;
 		let timestamp_nanos = Duration::from_millis(unix_timestamp_millis).as_nanos() as u64;
 
 		Ok(timestamp_nanos)
 	}
 
+	async fn query_block_commitment_at(
+		&self,
+		block_number: u64,
+	) -> Result<BlockCommitment, Self::Error> {
+		let subxt_block_number: subxt::rpc::types::BlockNumber = block_number.into();
+		let block_hash =
+			self.para_client.rpc().block_hash(Some(subxt_block_number)).await?.ok_or_else(
+				|| Error::Custom("Block hash not found for block number".to_string()),
+			)?;
+		let header = self
+			.para_client
+			.rpc()
+			.header(Some(block_hash))
+			.await?
+			.ok_or_else(|| Error::Custom(format!("Header not found for block {block_hash:?}")))?
+			.encode();
+		let header = sp_runtime::generic::Header::<u32, sp_runtime::traits::BlakeTwo256>::decode(
+			&mut &*header,
+		)?;
+		let unix_timestamp_millis = self.query_timestamp_millis(block_hash).await?;
+		Ok(BlockCommitment {
+			timestamp: Duration::from_millis(unix_timestamp_millis).as_nanos() as u64,
+			root: header.state_root.as_bytes().to_vec(),
+			next_validators_hash: None,
+		})
+	}
+
 	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error> {
 		let response: Vec<IdentifiedClientState> = IbcApiClient::<
 			u32,
@@ -814,4 +879,36 @@ where
 	async fn upload_wasm(&self, _wasm: Vec<u8>) -> Result<Vec<u8>, Self::Error> {
 		Err(Error::Custom("Uploading WASM to parachain is not supported".to_string()))
 	}
+
+	async fn query_scheduled_upgrade_height(&self) -> Result<Option<Height>, Self::Error> {
+		// runtime upgrades don't halt the chain, the clients keep working across them
+		Ok(None)
+	}
+
+	async fn query_upgraded_client(
+		&self,
+		_upgrade_height: Height,
+	) -> Result<UpgradedClient, Self::Error> {
+		Err(Error::Custom("Upgrading the clients of a parachain is not supported".to_string()))
+	}
+
+	async fn query_incentivized_packets(
+		&self,
+		_at: Height,
+		_channel_id: ChannelId,
+		_port_id: PortId,
+	) -> Result<Vec<IncentivizedPacket>, Self::Error> {
+		// pallet-ibc has no fee middleware
+		Ok(vec![])
+	}
+
+	async fn query_channel_upgrade(
+		&self,
+		_at: Height,
+		_channel_id: ChannelId,
+		_port_id: PortId,
+	) -> Result<Option<ChannelUpgrade>, Self::Error> {
+		// pallet-ibc doesn't support the channel upgrades
+		Ok(None)
+	}
 }
@@ -0,0 +1,268 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Relay chain connections shared by the parachain clients.
+//!
+//! The parachains of a relay chain are finalized by the same GRANDPA (or BEEFY) justifications,
+//! so the clients of several parachains of one relay chain don't need a connection each. With
+//! `share_relay_chain_connection` in their configs, the clients of the same relay chain rpc url
+//! share a [`RelayChainConnection`]: one websocket connection, one subscription to the
+//! justifications, whose notifications are broadcast to all the clients, and the finality proofs
+//! they query, which are fetched once for the clients proving the same block.
+//!
+//! The subscription is opened by the first client that streams the justifications, and closed
+//! once the streams of all the clients are dropped. A client reconnecting to a relay chain whose
+//! connection was lost replaces the shared connection, which the other clients then reconnect to.
+//!
+//! The clients connect to the relay chain rpc with [`RelayChainConnection::connect_with_failover`],
+//! which moves to the `relay_chain_fallback_rpc_urls` of their configs while the current rpc is
+//! unreachable.
+
+use crate::error::Error;
+use finality_grandpa_rpc::GrandpaApiClient;
+use futures::{Stream, StreamExt};
+use grandpa_prover::JustificationNotification;
+use jsonrpsee_ws_client::{WsClient, WsClientBuilder};
+use primitives::endpoints::EndpointRotation;
+use sc_consensus_beefy_rpc::BeefyApiClient;
+use sp_core::{Bytes, H256};
+use std::{
+	any::{Any, TypeId},
+	collections::HashMap,
+	sync::{Arc, Mutex, OnceLock, Weak},
+	time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, Mutex as AsyncMutex};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+
+/// Capacity of the broadcast of the justifications, in notifications. A client that lags
+/// further behind misses the oldest ones
+const NOTIFICATION_CAPACITY: usize = 64;
+
+/// Time a finality proof is reused for, about a relay chain block
+const FINALITY_PROOF_TTL: Duration = Duration::from_secs(6);
+
+/// Justifications the relay chain is subscribed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Justifications {
+	Grandpa,
+	Beefy,
+}
+
+/// The shared connections, by relay chain rpc url.
+fn registry() -> &'static Mutex<HashMap<String, Weak<RelayChainConnection>>> {
+	static REGISTRY: OnceLock<Mutex<HashMap<String, Weak<RelayChainConnection>>>> = OnceLock::new();
+	REGISTRY.get_or_init(Default::default)
+}
+
+/// A connection to a relay chain rpc.
+pub struct RelayChainConnection {
+	pub rpc_url: String,
+	pub ws_client: Arc<WsClient>,
+	/// The `subxt` clients of the connection, by their config
+	clients: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+	/// Broadcasts of the open subscriptions to the justifications
+	subscriptions: Mutex<HashMap<Justifications, broadcast::Sender<Bytes>>>,
+	/// Recent finality proofs, by the block they were queried for. Locked while a proof is
+	/// fetched, so that the clients proving the same block fetch it once
+	finality_proofs: AsyncMutex<HashMap<u32, (Instant, Option<Bytes>)>>,
+}
+
+impl RelayChainConnection {
+	/// Connects to the relay chain rpc, for a single client.
+	pub async fn connect(rpc_url: &str) -> Result<Arc<Self>, Error> {
+		let ws_client = WsClientBuilder::default()
+			.build(rpc_url)
+			.await
+			.map_err(|e| Error::from(format!("Rpc Error {:?}", e)))?;
+		Ok(Arc::new(Self {
+			rpc_url: rpc_url.to_string(),
+			ws_client: Arc::new(ws_client),
+			clients: Default::default(),
+			subscriptions: Default::default(),
+			finality_proofs: Default::default(),
+		}))
+	}
+
+	/// Returns the connection to the relay chain rpc shared by the clients, connecting to it if
+	/// no client is connected or its connection was lost.
+	pub async fn shared(rpc_url: &str) -> Result<Arc<Self>, Error> {
+		let existing = registry().lock().unwrap().get(rpc_url).and_then(Weak::upgrade);
+		if let Some(connection) = existing.filter(|connection| connection.ws_client.is_connected())
+		{
+			return Ok(connection)
+		}
+		let connection = Self::connect(rpc_url).await?;
+		let mut connections = registry().lock().unwrap();
+		// another client may have connected in the meantime
+		if let Some(existing) = connections.get(rpc_url).and_then(Weak::upgrade) {
+			if existing.ws_client.is_connected() {
+				return Ok(existing)
+			}
+		}
+		connections.retain(|_, connection| connection.strong_count() > 0);
+		connections.insert(rpc_url.to_string(), Arc::downgrade(&connection));
+		log::info!(target: "hyperspace_parachain", "Connected to the shared relay chain rpc {rpc_url}");
+		Ok(connection)
+	}
+
+	/// Connects to the current rpc url of the endpoints, shared or not, moving to the next ones
+	/// while it's unreachable. Fails if none of the rpc urls is reachable.
+	pub async fn connect_with_failover(
+		endpoints: &Mutex<EndpointRotation<String>>,
+		shared: bool,
+	) -> Result<Arc<Self>, Error> {
+		let attempts = endpoints.lock().unwrap().endpoints().len();
+		let mut last_error = None;
+		for _ in 0..attempts {
+			let rpc_url = endpoints.lock().unwrap().current().clone();
+			let connection = match shared {
+				true => Self::shared(&rpc_url).await,
+				false => Self::connect(&rpc_url).await,
+			};
+			match connection {
+				Ok(connection) => return Ok(connection),
+				Err(e) => {
+					log::warn!(target: "hyperspace_parachain", "Failed to connect to the relay chain rpc {rpc_url}: {e:?}");
+					last_error = Some(e);
+					let mut endpoints = endpoints.lock().unwrap();
+					match endpoints.rotate() {
+						Some(next) if !endpoints.is_on_primary() => log::info!(
+							target: "hyperspace_parachain", "Connecting to the fallback relay chain rpc {next}"
+						),
+						Some(next) => log::info!(
+							target: "hyperspace_parachain", "Connecting to the primary relay chain rpc {next}"
+						),
+						None => break,
+					}
+				},
+			}
+		}
+		Err(last_error.expect("the endpoints contain at least the primary one; qed"))
+	}
+
+	/// The `subxt` client of the connection, created on the first call for each config.
+	pub async fn client<T>(&self) -> Result<subxt::OnlineClient<T>, Error>
+	where
+		T: light_client_common::config::Config + 'static,
+	{
+		let cached = self
+			.clients
+			.lock()
+			.unwrap()
+			.get(&TypeId::of::<T>())
+			.and_then(|client| client.downcast_ref::<subxt::OnlineClient<T>>().cloned());
+		if let Some(client) = cached {
+			return Ok(client)
+		}
+		let client = subxt::OnlineClient::<T>::from_rpc_client(self.ws_client.clone()).await?;
+		self.clients
+			.lock()
+			.unwrap()
+			.entry(TypeId::of::<T>())
+			.or_insert_with(|| Box::new(client.clone()));
+		Ok(client)
+	}
+
+	/// Streams the encoded justifications of the relay chain, subscribing to them if no client
+	/// streams them yet.
+	pub async fn justifications(
+		self: &Arc<Self>,
+		kind: Justifications,
+	) -> Result<impl Stream<Item = Bytes> + Send + Sync, Error> {
+		if let Some(sender) = self.subscriptions.lock().unwrap().get(&kind) {
+			return Ok(receive(&self.rpc_url, sender.subscribe()))
+		}
+		let mut subscription = match kind {
+			Justifications::Grandpa =>
+				GrandpaApiClient::<JustificationNotification, H256, u32>::subscribe_justifications(
+					&*self.ws_client,
+				)
+				.await?,
+			Justifications::Beefy =>
+				BeefyApiClient::<JustificationNotification, H256>::subscribe_justifications(
+					&*self.ws_client,
+				)
+				.await?,
+		};
+		let (sender, receiver) = {
+			let mut subscriptions = self.subscriptions.lock().unwrap();
+			// another client may have subscribed in the meantime, its subscription is used
+			if let Some(sender) = subscriptions.get(&kind) {
+				return Ok(receive(&self.rpc_url, sender.subscribe()))
+			}
+			let (sender, receiver) = broadcast::channel(NOTIFICATION_CAPACITY);
+			subscriptions.insert(kind, sender.clone());
+			(sender, receiver)
+		};
+
+		let connection = Arc::downgrade(self);
+		tokio::spawn(async move {
+			while let Some(notification) = subscription.next().await {
+				let justification = match notification {
+					Ok(JustificationNotification(justification)) => justification,
+					Err(err) => {
+						log::error!("Failed to fetch Justification: {}", err);
+						continue
+					},
+				};
+				// the subscription is closed once no client streams it
+				if sender.send(justification).is_err() {
+					break
+				}
+			}
+			if let Some(connection) = connection.upgrade() {
+				connection.subscriptions.lock().unwrap().remove(&kind);
+			}
+		});
+		Ok(receive(&self.rpc_url, receiver))
+	}
+
+	/// The GRANDPA finality proof of the block, as `grandpa_proveFinality` returns it. The proofs
+	/// are reused for [`FINALITY_PROOF_TTL`].
+	pub async fn prove_finality(&self, block_number: u32) -> Result<Option<Bytes>, Error> {
+		let mut proofs = self.finality_proofs.lock().await;
+		proofs.retain(|_, (fetched_at, _)| fetched_at.elapsed() < FINALITY_PROOF_TTL);
+		if let Some((_, proof)) = proofs.get(&block_number) {
+			return Ok(proof.clone())
+		}
+		let proof = GrandpaApiClient::<JustificationNotification, H256, u32>::prove_finality(
+			&*self.ws_client,
+			block_number,
+		)
+		.await?;
+		proofs.insert(block_number, (Instant::now(), proof.clone()));
+		Ok(proof)
+	}
+}
+
+fn receive(
+	rpc_url: &str,
+	receiver: broadcast::Receiver<Bytes>,
+) -> impl Stream<Item = Bytes> + Send + Sync {
+	let rpc_url = rpc_url.to_string();
+	BroadcastStream::new(receiver).filter_map(move |notification| {
+		let justification = match notification {
+			Ok(justification) => Some(justification),
+			Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+				log::warn!(
+					target: "hyperspace_parachain",
+					"Missed {skipped} justifications of the relay chain {rpc_url}"
+				);
+				None
+			},
+		};
+		futures::future::ready(justification)
+	})
+}
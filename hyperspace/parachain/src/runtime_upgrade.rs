@@ -0,0 +1,90 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime upgrades of the parachain and of its relay chain.
+//!
+//! The `subxt` clients encode the extrinsics and decode the storage and the events with the
+//! metadata and the runtime version they fetched when they connected. Once the runtime of the
+//! chain is upgraded (`system.CodeUpdated`), the extrinsics signed with the previous spec
+//! version are rejected, and the storage and events whose types changed no longer decode. The
+//! clients therefore follow the runtime versions of their chains, and re-fetch the metadata on
+//! each new spec version, see [`RuntimeUpgrades`]. The clones of a client share its metadata, so
+//! the following calls and queries of the relayer use the new metadata without a restart.
+
+use std::sync::Arc;
+use subxt::OnlineClient;
+use tokio::task::JoinHandle;
+
+/// Tasks applying the runtime upgrades of the chains of a client, stopped once the client is
+/// dropped.
+pub struct RuntimeUpgrades(Vec<JoinHandle<()>>);
+
+impl RuntimeUpgrades {
+	/// Follows the runtime versions of the given chains, named for the logs.
+	pub fn spawn<T>(clients: Vec<(String, OnlineClient<T>)>) -> Arc<Self>
+	where
+		T: light_client_common::config::Config + Send + Sync + 'static,
+	{
+		Arc::new(Self(
+			clients
+				.into_iter()
+				.map(|(name, client)| tokio::spawn(apply_runtime_upgrades(name, client)))
+				.collect(),
+		))
+	}
+}
+
+impl Drop for RuntimeUpgrades {
+	fn drop(&mut self) {
+		for handle in &self.0 {
+			handle.abort();
+		}
+	}
+}
+
+/// Applies the runtime upgrades of the chain to its client, until its connection is closed. The
+/// client then reconnects with the latest metadata.
+async fn apply_runtime_upgrades<T>(name: String, client: OnlineClient<T>)
+where
+	T: light_client_common::config::Config + Send + Sync,
+{
+	let updater = client.updater();
+	let mut updates = match updater.runtime_updates().await {
+		Ok(updates) => updates,
+		Err(e) => {
+			log::warn!(target: "hyperspace_parachain", "Failed to follow the runtime upgrades of {name}: {e:?}");
+			return
+		},
+	};
+	while let Some(update) = updates.next().await {
+		let update = match update {
+			Ok(update) => update,
+			Err(e) => {
+				log::warn!(target: "hyperspace_parachain", "Failed to fetch the runtime upgrade of {name}: {e:?}");
+				continue
+			},
+		};
+		let spec_version = update.runtime_version().spec_version;
+		match updater.apply_update(update) {
+			Ok(()) => log::info!(
+				target: "hyperspace_parachain",
+				"Runtime of {name} upgraded to spec version {spec_version}, refreshed its metadata"
+			),
+			// the spec version the client already has
+			Err(e) =>
+				log::trace!(target: "hyperspace_parachain", "Runtime of {name} unchanged: {e:?}"),
+		}
+	}
+	log::debug!(target: "hyperspace_parachain", "Stopped following the runtime upgrades of {name}");
+}
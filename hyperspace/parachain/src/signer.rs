@@ -12,13 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::error::Error;
 use codec::Decode;
-use primitives::KeyProvider;
+use primitives::{remote_signer::RemoteSigner, KeyProvider};
 use sp_keystore::{Keystore, KeystorePtr};
 use sp_runtime::{
 	traits::{IdentifyAccount, Verify},
 	KeyTypeId, MultiSignature, MultiSigner,
 };
+use std::sync::Arc;
 use subxt::tx::Signer;
 
 /// A [`Signer`] implementation.
@@ -28,6 +30,8 @@ pub struct ExtrinsicSigner<T: light_client_common::config::Config, Provider: Key
 	signer: MultiSigner,
 	key_store: KeystorePtr,
 	key_type_id: KeyTypeId,
+	/// Signing service holding the key instead of the keystore, with the name of the chain
+	remote_signer: Option<(Arc<RemoteSigner>, String)>,
 	_phantom: std::marker::PhantomData<Provider>,
 }
 
@@ -54,9 +58,57 @@ where
 			key_store,
 			key_type_id,
 			signer: MultiSigner::from(public_key),
+			remote_signer: None,
 			_phantom: Default::default(),
 		}
 	}
+
+	/// Signs with the remote signer, if any, instead of the keystore, see
+	/// [`primitives::remote_signer`]. The extrinsics are then signed with
+	/// [`Self::sign_remotely`], see `ParachainClient::create_signed`.
+	pub fn with_remote_signer(
+		mut self,
+		remote_signer: Option<Arc<RemoteSigner>>,
+		chain: &str,
+	) -> Self {
+		self.remote_signer = remote_signer.map(|signer| (signer, chain.to_string()));
+		self
+	}
+
+	/// Signs the payload of an extrinsic with the remote signer, returning `None` without one.
+	/// The request blocks until the signer responds, so it's sent off the async runtime.
+	pub async fn sign_remotely(
+		&self,
+		signer_payload: Vec<u8>,
+	) -> Result<Option<<T as subxt::Config>::Signature>, Error>
+	where
+		<T as subxt::Config>::Signature: From<MultiSignature>,
+	{
+		let Some((remote_signer, chain)) = self.remote_signer.clone() else { return Ok(None) };
+		let encoded_sig =
+			tokio::task::spawn_blocking(move || remote_signer.sign(&chain, &signer_payload))
+				.await
+				.map_err(|e| Error::Custom(e.to_string()))?
+				.map_err(|e| Error::Custom(format!("Failed to sign an extrinsic: {e}")))?;
+		let signature = decode_signature(&self.signer, &encoded_sig)
+			.map_err(|e| Error::Custom(format!("Invalid signature of the remote signer: {e}")))?;
+		Ok(Some(signature.into()))
+	}
+}
+
+/// Decodes a signature of the type of the public key.
+fn decode_signature(
+	signer: &MultiSigner,
+	encoded_sig: &[u8],
+) -> Result<MultiSignature, codec::Error> {
+	let signature: MultiSignature = match signer {
+		MultiSigner::Ed25519(_) =>
+			sp_core::ed25519::Signature::decode(&mut &encoded_sig[..])?.into(),
+		MultiSigner::Sr25519(_) =>
+			sp_core::sr25519::Signature::decode(&mut &encoded_sig[..])?.into(),
+		MultiSigner::Ecdsa(_) => sp_core::ecdsa::Signature::decode(&mut &encoded_sig[..])?.into(),
+	};
+	Ok(signature)
 }
 
 impl<T, P> Signer<T> for ExtrinsicSigner<T, P>
@@ -82,6 +134,11 @@ where
 			MultiSigner::Sr25519(key) => (sp_core::sr25519::CRYPTO_ID, key.0.to_vec()),
 			MultiSigner::Ecdsa(key) => (sp_core::ecdsa::CRYPTO_ID, key.0.to_vec()),
 		};
+		// the signing can't fail here, the extrinsics of a remote signer are signed beforehand
+		assert!(
+			self.remote_signer.is_none(),
+			"The extrinsics of a remote signer are signed with `ExtrinsicSigner::sign_remotely`"
+		);
 		let encoded_sig = Keystore::sign_with(
 			&*self.key_store,
 			self.key_type_id,
@@ -92,17 +149,8 @@ where
 		.ok()
 		.flatten()
 		.expect("Signing should not fail");
-		let signature: MultiSignature = match self.signer {
-			MultiSigner::Ed25519(_) => sp_core::ed25519::Signature::decode(&mut &encoded_sig[..])
-				.expect("Should decode same signature type as public key; qed")
-				.into(),
-			MultiSigner::Sr25519(_) => sp_core::sr25519::Signature::decode(&mut &encoded_sig[..])
-				.expect("Should decode same signature type as public key; qed")
-				.into(),
-			MultiSigner::Ecdsa(_) => sp_core::ecdsa::Signature::decode(&mut &encoded_sig[..])
-				.expect("Should decode same signature type as public key; qed")
-				.into(),
-		};
-		signature.into()
+		decode_signature(&self.signer, &encoded_sig)
+			.expect("Should decode same signature type as public key; qed")
+			.into()
 	}
 }
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{signer::ExtrinsicSigner, Error, ParachainClient};
+use crate::{Error, ParachainClient};
 use codec::Decode;
 use finality_grandpa::BlockNumberOps;
 use futures::{Stream, StreamExt};
@@ -37,9 +37,7 @@ use sp_runtime::{
 	MultiSignature, MultiSigner,
 };
 use std::{collections::BTreeMap, fmt::Display, pin::Pin, str::FromStr};
-use subxt::config::{
-	extrinsic_params::BaseExtrinsicParamsBuilder, ExtrinsicParams, Header as HeaderT, Header,
-};
+use subxt::config::{ExtrinsicParams, Header as HeaderT, Header};
 
 impl<T: light_client_common::config::Config + Send + Sync> ParachainClient<T>
 where
@@ -101,21 +99,12 @@ where
 	}
 
 	pub async fn submit_sudo_call(&self, call: T::ParaRuntimeCall) -> Result<(), Error> {
-		let signer = ExtrinsicSigner::<T, Self>::new(
-			self.key_store.clone(),
-			self.key_type_id.clone(),
-			self.public_key.clone(),
-		);
-
 		let ext = T::Tx::sudo_sudo(call);
 		// Submit extrinsic to parachain node
-
-		let other_params = T::custom_extrinsic_params(&self.para_client).await?;
-
 		let _progress = self
-			.para_client
-			.tx()
-			.sign_and_submit_then_watch(&ext, &signer, other_params)
+			.create_signed(&ext, None)
+			.await?
+			.submit_and_watch()
 			.await?
 			.wait_for_in_block()
 			.await?
@@ -144,8 +133,7 @@ where
 	H256: From<T::Hash>,
 	BTreeMap<H256, ParachainHeaderProofs>:
 		From<BTreeMap<<T as subxt::Config>::Hash, ParachainHeaderProofs>>,
-	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
-		From<BaseExtrinsicParamsBuilder<T, T::Tip>> + Send + Sync,
+	<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams: Send + Sync,
 	<T as subxt::Config>::AccountId: Send + Sync,
 	<T as subxt::Config>::Address: Send + Sync,
 	<T as light_client_common::config::Config>::AssetId: Clone,
@@ -43,10 +43,11 @@ pub fn get_updated_client_state(
 	client_state
 }
 
-/// Fetch the maximum allowed extrinsic weight from a substrate node with the given client.
+/// Fetch the maximum allowed extrinsic weight from a substrate node with the given client, both its
+/// ref time and its proof size.
 pub async fn fetch_max_extrinsic_weight<T: light_client_common::config::Config>(
 	client: &subxt::OnlineClient<T>,
-) -> Result<u64, Error> {
+) -> Result<Weight, Error> {
 	let metadata = client.rpc().metadata().await?;
 	let block_weights = metadata
 		.pallet_by_name("System")
@@ -59,6 +60,21 @@ pub async fn fetch_max_extrinsic_weight<T: light_client_common::config::Config>(
 	let max_extrinsic_weight = extrinsic_weights
 		.max_extrinsic
 		.or(extrinsic_weights.max_total)
-		.unwrap_or(Weight::from_ref_time(u64::MAX));
-	Ok(max_extrinsic_weight.ref_time())
+		.unwrap_or(Weight::MAX);
+	Ok(max_extrinsic_weight)
+}
+
+/// Folds the proof size of the weight into its ref time, for the limits that only compare the ref
+/// time: the proof size is scaled to the ref time with the ratio of their maximums, and the weight
+/// is the largest of the two, so that the extrinsics fitting the maximum ref time also fit the
+/// maximum proof size (the PoV limit of the parachain blocks). The proof size is ignored on the
+/// chains that don't limit it.
+pub fn fold_proof_size(weight: Weight, max_weight: Weight) -> u64 {
+	let max_proof_size = max_weight.proof_size();
+	if max_proof_size == 0 || max_proof_size == u64::MAX {
+		return weight.ref_time()
+	}
+	let scaled_proof_size =
+		weight.proof_size() as u128 * max_weight.ref_time() as u128 / max_proof_size as u128;
+	weight.ref_time().max(scaled_proof_size.min(u64::MAX as u128) as u64)
 }
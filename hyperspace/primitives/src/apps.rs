@@ -0,0 +1,370 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Channel requirements of the IBC applications.
+//!
+//! The applications bound to a port negotiate the version of their channels and may only accept
+//! one ordering: ICS-20 transfer channels are unordered, ICS-27 interchain accounts channels are
+//! ordered and carry JSON metadata naming the connections of the channel. The channels opened by
+//! the relayer are checked against these requirements before the handshake is started, since the
+//! application would reject them when the handshake is already half done.
+
+use crate::fees::FeeVersion;
+use ibc::core::{
+	ics04_channel::channel::Order,
+	ics24_host::identifier::{ConnectionId, PortId},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Version of the ICS-20 transfer channels.
+pub const ICS20_VERSION: &str = "ics20-1";
+/// Version of the ICS-27 interchain accounts channels.
+pub const ICS27_VERSION: &str = "ics27-1";
+/// Prefix of the ports of the ICS-27 controller accounts, followed by the owner of the account.
+pub const ICA_CONTROLLER_PORT_PREFIX: &str = "icacontroller-";
+/// Port of the ICS-27 host.
+pub const ICA_HOST_PORT: &str = "icahost";
+/// Encoding of the interchain accounts transactions.
+pub const ICA_ENCODING: &str = "proto3";
+/// Type of the interchain accounts transactions.
+pub const ICA_TX_TYPE: &str = "sdk_multi_msg";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AppError {
+	#[error("The {app} application only accepts {expected:?} channels, got {got:?}")]
+	InvalidOrder { app: &'static str, expected: Order, got: Order },
+	#[error("Invalid version for the {app} application: {reason}")]
+	InvalidVersion { app: &'static str, reason: String },
+}
+
+/// Version metadata of an ICS-27 channel.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct IcaMetadata {
+	pub version: String,
+	pub controller_connection_id: String,
+	pub host_connection_id: String,
+	/// Address of the interchain account, set by the host in the `TRYOPEN` step
+	#[serde(default)]
+	pub address: String,
+	pub encoding: String,
+	pub tx_type: String,
+}
+
+impl IcaMetadata {
+	pub fn new(controller_connection_id: &ConnectionId, host_connection_id: &ConnectionId) -> Self {
+		Self {
+			version: ICS27_VERSION.to_string(),
+			controller_connection_id: controller_connection_id.to_string(),
+			host_connection_id: host_connection_id.to_string(),
+			address: String::new(),
+			encoding: ICA_ENCODING.to_string(),
+			tx_type: ICA_TX_TYPE.to_string(),
+		}
+	}
+}
+
+/// Application bound to the port of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelApp {
+	Transfer,
+	InterchainAccounts,
+	/// An application the relayer doesn't know the requirements of
+	Other,
+}
+
+impl ChannelApp {
+	/// The application bound to the port of the channel initiating the handshake.
+	pub fn from_port(port_id: &PortId) -> Self {
+		let port_id = port_id.as_str();
+		if port_id == PortId::transfer().as_str() {
+			Self::Transfer
+		} else if port_id.starts_with(ICA_CONTROLLER_PORT_PREFIX) {
+			Self::InterchainAccounts
+		} else {
+			Self::Other
+		}
+	}
+
+	fn name(&self) -> &'static str {
+		match self {
+			Self::Transfer => "ICS-20 transfer",
+			Self::InterchainAccounts => "ICS-27 interchain accounts",
+			Self::Other => "unknown",
+		}
+	}
+
+	/// The port of the application on the counterparty, if it's fixed.
+	pub fn counterparty_port(&self) -> Option<PortId> {
+		match self {
+			Self::Transfer => Some(PortId::transfer()),
+			Self::InterchainAccounts =>
+				Some(ICA_HOST_PORT.parse().expect("the ICS-27 host port is valid; qed")),
+			Self::Other => None,
+		}
+	}
+
+	/// The ordering of the channels of the application, if it only accepts one.
+	pub fn order(&self) -> Option<Order> {
+		match self {
+			Self::Transfer => Some(Order::Unordered),
+			Self::InterchainAccounts => Some(Order::Ordered),
+			Self::Other => None,
+		}
+	}
+
+	/// Returns the ordering of a new channel of the application, the required one if no ordering
+	/// is given.
+	pub fn channel_order(&self, order: Option<Order>) -> Result<Option<Order>, AppError> {
+		match (self.order(), order) {
+			(Some(expected), Some(got)) if expected != got =>
+				Err(AppError::InvalidOrder { app: self.name(), expected, got }),
+			(expected, order) => Ok(order.or(expected)),
+		}
+	}
+
+	/// Returns the version a new channel of the application proposes, given the connection of the
+	/// channel on both chains. The default version of the application is used if no version is
+	/// given. A version given for an ICS-27 channel may be its bare version or its full metadata,
+	/// which must name the connections of the channel. The version of the application may be
+	/// wrapped in the version of the fee middleware, see [`FeeVersion`].
+	pub fn channel_version(
+		&self,
+		version: Option<String>,
+		connection_id: &ConnectionId,
+		counterparty_connection_id: &ConnectionId,
+	) -> Result<Option<String>, AppError> {
+		if let Some(fee_version) = version.as_deref().and_then(FeeVersion::parse) {
+			let app_version = self.channel_version(
+				Some(fee_version.app_version.clone()),
+				connection_id,
+				counterparty_connection_id,
+			)?;
+			return Ok(app_version
+				.map(|app_version| FeeVersion { app_version, ..fee_version }.to_version_string()))
+		}
+		let invalid = |reason: String| AppError::InvalidVersion { app: self.name(), reason };
+		match self {
+			Self::Transfer => match version {
+				Some(version) if version != ICS20_VERSION =>
+					Err(invalid(format!("expected {ICS20_VERSION}, got {version}"))),
+				_ => Ok(Some(ICS20_VERSION.to_string())),
+			},
+			Self::InterchainAccounts => {
+				let metadata = match version {
+					None => IcaMetadata::new(connection_id, counterparty_connection_id),
+					Some(version) if version == ICS27_VERSION =>
+						IcaMetadata::new(connection_id, counterparty_connection_id),
+					Some(version) => serde_json::from_str::<IcaMetadata>(&version)
+						.map_err(|e| invalid(format!("the metadata isn't valid JSON: {e}")))?,
+				};
+				if metadata.version != ICS27_VERSION {
+					return Err(invalid(format!(
+						"expected {ICS27_VERSION}, got {}",
+						metadata.version
+					)))
+				}
+				if metadata.controller_connection_id != connection_id.as_str() ||
+					metadata.host_connection_id != counterparty_connection_id.as_str()
+				{
+					return Err(invalid(format!(
+						"the metadata names the connections {}/{}, the channel is opened on {connection_id}/{counterparty_connection_id}",
+						metadata.controller_connection_id, metadata.host_connection_id
+					)))
+				}
+				if !metadata.address.is_empty() {
+					return Err(invalid("the address is set by the host".to_string()))
+				}
+				Ok(Some(
+					serde_json::to_string(&metadata).expect("the metadata is serializable; qed"),
+				))
+			},
+			Self::Other => Ok(version),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fees::FEE_VERSION;
+
+	fn connections() -> (ConnectionId, ConnectionId) {
+		(ConnectionId::new(0), ConnectionId::new(1))
+	}
+
+	fn ica_version(metadata: &IcaMetadata) -> Option<String> {
+		let (connection_id, counterparty_connection_id) = connections();
+		ChannelApp::InterchainAccounts
+			.channel_version(
+				Some(serde_json::to_string(metadata).unwrap()),
+				&connection_id,
+				&counterparty_connection_id,
+			)
+			.unwrap()
+	}
+
+	#[test]
+	fn apps_are_found_from_the_port() {
+		assert_eq!(ChannelApp::from_port(&PortId::transfer()), ChannelApp::Transfer);
+		assert_eq!(
+			ChannelApp::from_port(&"icacontroller-owner".parse().unwrap()),
+			ChannelApp::InterchainAccounts
+		);
+		assert_eq!(ChannelApp::from_port(&"wasm.contract".parse().unwrap()), ChannelApp::Other);
+	}
+
+	#[test]
+	fn channel_orders_must_match_the_app() {
+		assert_eq!(ChannelApp::Transfer.channel_order(None), Ok(Some(Order::Unordered)));
+		assert_eq!(
+			ChannelApp::InterchainAccounts.channel_order(Some(Order::Ordered)),
+			Ok(Some(Order::Ordered))
+		);
+		assert_eq!(
+			ChannelApp::InterchainAccounts.channel_order(Some(Order::Unordered)),
+			Err(AppError::InvalidOrder {
+				app: "ICS-27 interchain accounts",
+				expected: Order::Ordered,
+				got: Order::Unordered,
+			})
+		);
+		assert_eq!(ChannelApp::Other.channel_order(None), Ok(None));
+		assert_eq!(ChannelApp::Other.channel_order(Some(Order::Ordered)), Ok(Some(Order::Ordered)));
+	}
+
+	#[test]
+	fn bare_ica_versions_are_expanded_to_the_metadata() {
+		let (connection_id, counterparty_connection_id) = connections();
+		let expected = IcaMetadata::new(&connection_id, &counterparty_connection_id);
+		for version in [None, Some(ICS27_VERSION.to_string())] {
+			let version = ChannelApp::InterchainAccounts
+				.channel_version(version, &connection_id, &counterparty_connection_id)
+				.unwrap()
+				.unwrap();
+			assert_eq!(serde_json::from_str::<IcaMetadata>(&version).unwrap(), expected);
+		}
+	}
+
+	#[test]
+	fn full_ica_metadata_is_kept() {
+		let (connection_id, counterparty_connection_id) = connections();
+		let metadata = IcaMetadata {
+			tx_type: "custom".to_string(),
+			..IcaMetadata::new(&connection_id, &counterparty_connection_id)
+		};
+		let version = ica_version(&metadata).unwrap();
+		assert_eq!(serde_json::from_str::<IcaMetadata>(&version).unwrap(), metadata);
+	}
+
+	#[test]
+	fn ica_metadata_must_name_the_connections_of_the_channel() {
+		let (connection_id, counterparty_connection_id) = connections();
+		for metadata in [
+			IcaMetadata::new(&counterparty_connection_id, &connection_id),
+			IcaMetadata::new(&connection_id, &ConnectionId::new(2)),
+		] {
+			let err = ChannelApp::InterchainAccounts
+				.channel_version(
+					Some(serde_json::to_string(&metadata).unwrap()),
+					&connection_id,
+					&counterparty_connection_id,
+				)
+				.unwrap_err();
+			assert!(matches!(err, AppError::InvalidVersion { .. }), "{err:?}");
+		}
+	}
+
+	#[test]
+	fn ica_metadata_with_an_address_is_refused() {
+		let (connection_id, counterparty_connection_id) = connections();
+		let metadata = IcaMetadata {
+			address: "cosmos1account".to_string(),
+			..IcaMetadata::new(&connection_id, &counterparty_connection_id)
+		};
+		let err = ChannelApp::InterchainAccounts
+			.channel_version(
+				Some(serde_json::to_string(&metadata).unwrap()),
+				&connection_id,
+				&counterparty_connection_id,
+			)
+			.unwrap_err();
+		assert_eq!(
+			err,
+			AppError::InvalidVersion {
+				app: "ICS-27 interchain accounts",
+				reason: "the address is set by the host".to_string(),
+			}
+		);
+	}
+
+	#[test]
+	fn invalid_versions_are_refused() {
+		let (connection_id, counterparty_connection_id) = connections();
+		for (app, version) in [
+			(ChannelApp::Transfer, "ics20-2"),
+			(ChannelApp::InterchainAccounts, "ics27-2"),
+			(ChannelApp::InterchainAccounts, "{not json"),
+		] {
+			assert!(app
+				.channel_version(
+					Some(version.to_string()),
+					&connection_id,
+					&counterparty_connection_id
+				)
+				.is_err());
+		}
+		assert_eq!(
+			ChannelApp::Other.channel_version(
+				Some("custom-1".to_string()),
+				&connection_id,
+				&counterparty_connection_id
+			),
+			Ok(Some("custom-1".to_string()))
+		);
+	}
+
+	#[test]
+	fn fee_wrapped_versions_check_the_app_version() {
+		let (connection_id, counterparty_connection_id) = connections();
+		let wrap = |app_version: &str| {
+			FeeVersion {
+				fee_version: FEE_VERSION.to_string(),
+				app_version: app_version.to_string(),
+			}
+			.to_version_string()
+		};
+		assert_eq!(
+			ChannelApp::Transfer.channel_version(
+				Some(wrap(ICS20_VERSION)),
+				&connection_id,
+				&counterparty_connection_id
+			),
+			Ok(Some(wrap(ICS20_VERSION)))
+		);
+		assert!(ChannelApp::Transfer
+			.channel_version(Some(wrap("ics20-2")), &connection_id, &counterparty_connection_id)
+			.is_err());
+
+		let version = ChannelApp::InterchainAccounts
+			.channel_version(Some(wrap(ICS27_VERSION)), &connection_id, &counterparty_connection_id)
+			.unwrap()
+			.unwrap();
+		let fee_version = FeeVersion::parse(&version).unwrap();
+		assert_eq!(
+			serde_json::from_str::<IcaMetadata>(&fee_version.app_version).unwrap(),
+			IcaMetadata::new(&connection_id, &counterparty_connection_id)
+		);
+	}
+}
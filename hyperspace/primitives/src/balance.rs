@@ -0,0 +1,150 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pausing of the submissions to a chain while the balance of the relayer account is too low.
+//!
+//! The balance is checked at most every [`BALANCE_CHECK_INTERVAL`], and right after a failed
+//! submission. While it's below the threshold, the relayer keeps processing the finality
+//! notifications but drops the outgoing messages, which are found again once the account is
+//! topped up, instead of submitting transactions that can't pay their fees.
+
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// Interval between two checks of the relayer balance.
+pub const BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Change of the state of a [`BalanceGuard`] after a balance check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceTransition {
+	Paused,
+	Resumed,
+}
+
+#[derive(Debug, Default)]
+struct GuardState {
+	paused: bool,
+	checked_at: Option<Instant>,
+}
+
+/// Pauses the submissions while the relayer balance is below a threshold, shared by the clones of
+/// the client.
+#[derive(Debug, Clone, Default)]
+pub struct BalanceGuard {
+	threshold: Option<u128>,
+	state: Arc<Mutex<GuardState>>,
+}
+
+impl BalanceGuard {
+	pub fn new(threshold: Option<u128>) -> Self {
+		Self { threshold, state: Default::default() }
+	}
+
+	pub fn threshold(&self) -> Option<u128> {
+		self.threshold
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.state.lock().unwrap().paused
+	}
+
+	/// Returns `true` if a threshold is set and the balance should be checked again.
+	pub fn check_due(&self) -> bool {
+		self.threshold.is_some() &&
+			self.state
+				.lock()
+				.unwrap()
+				.checked_at
+				.map_or(true, |at| at.elapsed() >= BALANCE_CHECK_INTERVAL)
+	}
+
+	/// Makes the next submission check the balance, e.g. after a failed submission.
+	pub fn expire(&self) {
+		self.state.lock().unwrap().checked_at = None;
+	}
+
+	/// Postpones the next check without changing the state, e.g. when the balance couldn't be
+	/// queried.
+	pub fn defer(&self) {
+		self.state.lock().unwrap().checked_at = Some(Instant::now());
+	}
+
+	/// Records the latest balance, returning the transition it caused, if any.
+	pub fn record(&self, balance: u128) -> Option<BalanceTransition> {
+		let threshold = self.threshold?;
+		let mut state = self.state.lock().unwrap();
+		state.checked_at = Some(Instant::now());
+		let paused = balance < threshold;
+		if paused == state.paused {
+			return None
+		}
+		state.paused = paused;
+		Some(if paused { BalanceTransition::Paused } else { BalanceTransition::Resumed })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn submissions_pause_below_the_threshold_and_resume_at_it() {
+		let guard = BalanceGuard::new(Some(100));
+		assert_eq!(guard.record(100), None);
+		assert!(!guard.is_paused());
+
+		assert_eq!(guard.record(99), Some(BalanceTransition::Paused));
+		assert!(guard.is_paused());
+		// the transition is only reported once
+		assert_eq!(guard.record(50), None);
+		assert!(guard.is_paused());
+
+		assert_eq!(guard.record(100), Some(BalanceTransition::Resumed));
+		assert!(!guard.is_paused());
+		assert_eq!(guard.record(1_000), None);
+	}
+
+	#[test]
+	fn guards_without_a_threshold_never_pause() {
+		let guard = BalanceGuard::new(None);
+		assert!(!guard.check_due());
+		assert_eq!(guard.record(0), None);
+		assert!(!guard.is_paused());
+	}
+
+	#[test]
+	fn balances_are_checked_again_once_expired() {
+		let guard = BalanceGuard::new(Some(100));
+		assert!(guard.check_due());
+		guard.record(1_000);
+		assert!(!guard.check_due());
+		guard.expire();
+		assert!(guard.check_due());
+		guard.defer();
+		assert!(!guard.check_due());
+		assert!(!guard.is_paused());
+	}
+
+	#[test]
+	fn clones_share_the_state() {
+		let guard = BalanceGuard::new(Some(100));
+		let clone = guard.clone();
+		assert_eq!(guard.record(0), Some(BalanceTransition::Paused));
+		assert!(clone.is_paused());
+		assert_eq!(clone.record(100), Some(BalanceTransition::Resumed));
+		assert!(!guard.is_paused());
+	}
+}
@@ -0,0 +1,68 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ICS-04 channel upgrades.
+//!
+//! An open channel is upgraded (e.g. to wrap its application in the fee middleware) with a
+//! handshake of its own, started on one end by `ChanUpgradeInit` and relayed like the opening
+//! handshake: `ChanUpgradeTry` on the counterparty, then `ChanUpgradeAck`, `ChanUpgradeConfirm`
+//! and `ChanUpgradeOpen`. The channel keeps relaying its in-flight packets while it's flushing,
+//! and only moves to the new version once both ends flushed them.
+
+use ibc::{
+	core::ics24_host::identifier::{ChannelId, PortId},
+	Height,
+};
+use ibc_proto::{
+	google::protobuf::Any,
+	ibc::core::channel::v1::{
+		MsgChannelUpgradeAck, MsgChannelUpgradeConfirm, MsgChannelUpgradeOpen,
+		MsgChannelUpgradeTry, Upgrade,
+	},
+};
+use prost::Message;
+
+pub const CHANNEL_UPGRADE_TRY_TYPE_URL: &str = "/ibc.core.channel.v1.MsgChannelUpgradeTry";
+pub const CHANNEL_UPGRADE_ACK_TYPE_URL: &str = "/ibc.core.channel.v1.MsgChannelUpgradeAck";
+pub const CHANNEL_UPGRADE_CONFIRM_TYPE_URL: &str = "/ibc.core.channel.v1.MsgChannelUpgradeConfirm";
+pub const CHANNEL_UPGRADE_OPEN_TYPE_URL: &str = "/ibc.core.channel.v1.MsgChannelUpgradeOpen";
+
+/// Upgrade of a channel stored on its chain, with its proof.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelUpgrade {
+	pub upgrade: Upgrade,
+	pub proof: Vec<u8>,
+	pub proof_height: Height,
+}
+
+/// Path of the upgrade of a channel in the store of ibc-go.
+pub fn channel_upgrade_path(port_id: &PortId, channel_id: &ChannelId) -> String {
+	format!("channelUpgrades/upgrades/ports/{port_id}/channels/{channel_id}")
+}
+
+pub fn upgrade_try_msg(msg: MsgChannelUpgradeTry) -> Any {
+	Any { type_url: CHANNEL_UPGRADE_TRY_TYPE_URL.to_string(), value: msg.encode_to_vec() }
+}
+
+pub fn upgrade_ack_msg(msg: MsgChannelUpgradeAck) -> Any {
+	Any { type_url: CHANNEL_UPGRADE_ACK_TYPE_URL.to_string(), value: msg.encode_to_vec() }
+}
+
+pub fn upgrade_confirm_msg(msg: MsgChannelUpgradeConfirm) -> Any {
+	Any { type_url: CHANNEL_UPGRADE_CONFIRM_TYPE_URL.to_string(), value: msg.encode_to_vec() }
+}
+
+pub fn upgrade_open_msg(msg: MsgChannelUpgradeOpen) -> Any {
+	Any { type_url: CHANNEL_UPGRADE_OPEN_TYPE_URL.to_string(), value: msg.encode_to_vec() }
+}
@@ -0,0 +1,117 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Times and heights at which the light clients hosted by a chain were updated.
+//!
+//! The connection delay of a packet is counted from the update of the client to the consensus
+//! height the packet is proven at. Finding that update means searching the transactions of the
+//! chain, so the updates found are kept here, per client and consensus height, for the packets
+//! proven at the same height and for the next relay cycles.
+
+use crate::Chain;
+use ibc::{core::ics24_host::identifier::ClientId, timestamp::Timestamp, Height};
+use std::{
+	collections::BTreeMap,
+	sync::{Arc, Mutex},
+};
+
+/// Maximum number of updates kept per client. The updates to the lowest consensus heights are
+/// dropped first, since packets are proven at the most recent heights.
+const MAX_UPDATES_PER_CLIENT: usize = 1000;
+
+/// Client updates shared by the clones of a client.
+#[derive(Debug, Clone, Default)]
+pub struct ClientUpdateTimes {
+	updates: Arc<Mutex<BTreeMap<ClientId, BTreeMap<Height, (Height, Timestamp)>>>>,
+}
+
+impl ClientUpdateTimes {
+	/// Host height and time of the update of the client to the given consensus height, if known.
+	pub fn get(
+		&self,
+		client_id: &ClientId,
+		consensus_height: Height,
+	) -> Option<(Height, Timestamp)> {
+		self.updates.lock().unwrap().get(client_id)?.get(&consensus_height).copied()
+	}
+
+	/// Records the update of the client to the given consensus height.
+	pub fn record(
+		&self,
+		client_id: ClientId,
+		consensus_height: Height,
+		update: (Height, Timestamp),
+	) {
+		let mut updates = self.updates.lock().unwrap();
+		let client_updates = updates.entry(client_id).or_default();
+		client_updates.insert(consensus_height, update);
+		while client_updates.len() > MAX_UPDATES_PER_CLIENT {
+			client_updates.pop_first();
+		}
+	}
+}
+
+/// Height and time at which `client_id` was updated to `consensus_height` on `chain`, from the
+/// updates already known, or else from [`crate::IbcProvider::query_client_update_time_and_height`].
+pub async fn client_update_time_and_height(
+	chain: &impl Chain,
+	client_id: ClientId,
+	consensus_height: Height,
+) -> Result<(Height, Timestamp), anyhow::Error> {
+	let times = &chain.common_state().client_update_times;
+	if let Some(update) = times.get(&client_id, consensus_height) {
+		return Ok(update)
+	}
+	let update = chain
+		.query_client_update_time_and_height(client_id.clone(), consensus_height)
+		.await?;
+	times.record(client_id, consensus_height, update);
+	Ok(update)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	fn update(height: u64) -> (Height, Timestamp) {
+		(Height::new(0, height), Timestamp::from_nanoseconds(height * 1_000_000_000).unwrap())
+	}
+
+	#[test]
+	fn updates_are_kept_per_client_and_consensus_height() {
+		let times = ClientUpdateTimes::default();
+		let (client_a, client_b) = (
+			ClientId::from_str("07-tendermint-0").unwrap(),
+			ClientId::from_str("07-tendermint-1").unwrap(),
+		);
+		times.record(client_a.clone(), Height::new(1, 10), update(100));
+		times.record(client_b.clone(), Height::new(1, 10), update(200));
+
+		assert_eq!(times.get(&client_a, Height::new(1, 10)), Some(update(100)));
+		assert_eq!(times.get(&client_b, Height::new(1, 10)), Some(update(200)));
+		assert_eq!(times.get(&client_a, Height::new(1, 11)), None);
+	}
+
+	#[test]
+	fn the_updates_to_the_lowest_heights_are_dropped_first() {
+		let times = ClientUpdateTimes::default();
+		let client_id = ClientId::from_str("07-tendermint-0").unwrap();
+		for height in 1..=MAX_UPDATES_PER_CLIENT as u64 + 1 {
+			times.record(client_id.clone(), Height::new(1, height), update(height));
+		}
+		assert_eq!(times.get(&client_id, Height::new(1, 1)), None);
+		assert_eq!(times.get(&client_id, Height::new(1, 2)), Some(update(2)));
+	}
+}
@@ -0,0 +1,100 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Correlation IDs of the packets, so that the journey of a packet from its event to the
+//! transaction delivering it can be followed in the logs.
+//!
+//! A packet is identified by its source port, channel and sequence, followed by its destination
+//! channel, which all the messages of the packet carry: e.g. `transfer/channel-0/42->channel-3`.
+//! The log lines about a packet tag it with [`PACKET_TAG`], which the JSON log format extracts into
+//! their `packets` field.
+
+use crate::store::decode_packet;
+use ibc::core::ics04_channel::packet::Packet;
+use ibc_proto::google::protobuf::Any;
+
+/// Prefix of the correlation IDs in the log lines.
+pub const PACKET_TAG: &str = "packet=";
+
+/// The correlation ID of the packet.
+pub fn packet_id(packet: &Packet) -> String {
+	format!(
+		"{}/{}/{}->{}",
+		packet.source_port,
+		packet.source_channel,
+		u64::from(packet.sequence),
+		packet.destination_channel
+	)
+}
+
+/// The tagged correlation IDs of the packet messages, e.g.
+/// `packet=transfer/channel-0/1->channel-3`, separated by spaces.
+pub fn packet_tags(messages: &[Any]) -> String {
+	messages
+		.iter()
+		.filter_map(decode_packet)
+		.map(|packet| format!("{PACKET_TAG}{}", packet_id(&packet)))
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// The correlation IDs tagged in a log line.
+pub fn tagged_packet_ids(line: &str) -> Vec<&str> {
+	line.split(|c: char| c.is_whitespace() || c == ',')
+		.filter_map(|word| word.strip_prefix(PACKET_TAG))
+		.filter(|id| !id.is_empty())
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dead_letter::tests::recv_packet;
+	use ibc::core::{
+		ics04_channel::packet::Sequence,
+		ics24_host::identifier::{ChannelId, PortId},
+	};
+
+	#[test]
+	fn packet_ids_name_the_source_and_the_destination_channel() {
+		let packet = Packet {
+			sequence: Sequence::from(42),
+			source_port: PortId::transfer(),
+			source_channel: ChannelId::new(0),
+			destination_port: PortId::transfer(),
+			destination_channel: ChannelId::new(3),
+			..Default::default()
+		};
+		assert_eq!(packet_id(&packet), "transfer/channel-0/42->channel-3");
+	}
+
+	#[test]
+	fn packet_messages_are_tagged() {
+		let update =
+			Any { type_url: "/ibc.core.client.v1.MsgUpdateClient".to_string(), value: vec![] };
+		assert_eq!(
+			packet_tags(&[update, recv_packet(1), recv_packet(2)]),
+			"packet=transfer/channel-0/1->channel-1 packet=transfer/channel-0/2->channel-1"
+		);
+		assert_eq!(packet_tags(&[]), "");
+	}
+
+	#[test]
+	fn tagged_ids_are_found_in_the_log_lines() {
+		let line = format!("Submitted a message: {}, packet=", packet_tags(&[recv_packet(1)]));
+		assert_eq!(tagged_packet_ids(&line), vec!["transfer/channel-0/1->channel-1"]);
+		assert_eq!(tagged_packet_ids("packets packet=a,packet=b\tpacket=c"), vec!["a", "b", "c"]);
+		assert!(tagged_packet_ids("no packet here").is_empty());
+	}
+}
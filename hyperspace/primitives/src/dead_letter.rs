@@ -0,0 +1,150 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Packet messages isolated as failing by the bisection of the rejected bundles.
+//!
+//! A rejected bundle is split in halves that are submitted again, until the packet messages that
+//! fail on their own are isolated. These are set aside for [`DEAD_LETTER_TTL`] instead of being
+//! built and submitted again on each relay cycle, so that one bad message doesn't block the
+//! others. The messages are identified by their packet, since they're built with new proofs on
+//! each cycle.
+
+use crate::{store::decode_packet, MessageKind};
+use ibc_proto::google::protobuf::Any;
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// Time during which a dead-lettered message isn't submitted again.
+pub const DEAD_LETTER_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Dead-lettered packet messages of a chain, shared by the clones of the client.
+#[derive(Debug, Clone, Default)]
+pub struct DeadLetters(Arc<Mutex<HashMap<String, Instant>>>);
+
+impl DeadLetters {
+	/// Sets aside a packet message. Returns `false` if it isn't a packet message, since the other
+	/// messages (e.g. client updates) are needed by the messages that follow them.
+	pub fn insert(&self, message: &Any) -> bool {
+		let Some(key) = key(message) else { return false };
+		self.0.lock().unwrap().insert(key, Instant::now());
+		true
+	}
+
+	/// Drops the messages that were dead-lettered less than [`DEAD_LETTER_TTL`] ago.
+	pub fn filter(&self, messages: Vec<Any>) -> Vec<Any> {
+		let mut dead_letters = self.0.lock().unwrap();
+		dead_letters.retain(|_, at| at.elapsed() < DEAD_LETTER_TTL);
+		if dead_letters.is_empty() {
+			return messages
+		}
+		messages
+			.into_iter()
+			.filter(|message| match key(message) {
+				Some(key) if dead_letters.contains_key(&key) => {
+					log::debug!(target: "hyperspace", "Skipping dead-lettered message {key}");
+					false
+				},
+				_ => true,
+			})
+			.collect()
+	}
+
+	/// Number of messages set aside.
+	pub fn len(&self) -> usize {
+		self.0.lock().unwrap().len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// Key of a packet message, identifying it by its kind and its packet.
+pub(crate) fn key(message: &Any) -> Option<String> {
+	let kind = MessageKind::of(message)?;
+	let packet = decode_packet(message)?;
+	Some(format!("{kind:?}/{}/{}/{}", packet.source_port, packet.source_channel, packet.sequence))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use ibc_proto::ibc::core::{
+		channel::v1::{MsgRecvPacket, Packet},
+		client::v1::Height,
+	};
+	use prost::Message;
+
+	/// Returns a `MsgRecvPacket` of the packet with the sequence on `transfer/channel-0`.
+	pub(crate) fn recv_packet(sequence: u64) -> Any {
+		let packet = Packet {
+			sequence,
+			source_port: "transfer".to_string(),
+			source_channel: "channel-0".to_string(),
+			destination_port: "transfer".to_string(),
+			destination_channel: "channel-1".to_string(),
+			data: vec![1],
+			timeout_height: Some(Height { revision_number: 0, revision_height: 100 }),
+			timeout_timestamp: 0,
+		};
+		let message = MsgRecvPacket {
+			packet: Some(packet),
+			proof_commitment: vec![1],
+			proof_height: Some(Height { revision_number: 0, revision_height: 10 }),
+			signer: "relayer".to_string(),
+		};
+		Any {
+			type_url: "/ibc.core.channel.v1.MsgRecvPacket".to_string(),
+			value: message.encode_to_vec(),
+		}
+	}
+
+	#[test]
+	fn dead_letters_are_skipped() {
+		let dead_letters = DeadLetters::default();
+		assert!(dead_letters.insert(&recv_packet(1)));
+		assert_eq!(dead_letters.len(), 1);
+		let messages = dead_letters.filter(vec![recv_packet(1), recv_packet(2)]);
+		assert_eq!(messages, vec![recv_packet(2)]);
+	}
+
+	#[test]
+	fn only_packet_messages_are_dead_lettered() {
+		let dead_letters = DeadLetters::default();
+		let update =
+			Any { type_url: "/ibc.core.client.v1.MsgUpdateClient".to_string(), value: vec![] };
+		assert!(!dead_letters.insert(&update));
+		assert!(dead_letters.is_empty());
+		assert_eq!(dead_letters.filter(vec![update.clone()]), vec![update]);
+	}
+
+	#[test]
+	fn dead_letters_expire_after_the_ttl() {
+		let dead_letters = DeadLetters::default();
+		dead_letters.insert(&recv_packet(1));
+		let key = key(&recv_packet(1)).unwrap();
+		*dead_letters.0.lock().unwrap().get_mut(&key).unwrap() -= DEAD_LETTER_TTL;
+		assert_eq!(dead_letters.filter(vec![recv_packet(1)]).len(), 1);
+		assert!(dead_letters.is_empty());
+	}
+
+	#[test]
+	fn messages_are_keyed_by_kind_and_packet() {
+		assert_eq!(key(&recv_packet(1)).unwrap(), "RecvPacket/transfer/channel-0/1");
+		assert_ne!(key(&recv_packet(1)), key(&recv_packet(2)));
+	}
+}
@@ -0,0 +1,148 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Formatting of the accounts, denoms and amounts in the logs and the CLI output.
+//!
+//! Each chain renders the accounts in its own format, e.g. bech32 or SS58, see
+//! [`Chain::format_account`], and resolves the display metadata of its denoms from its on-chain
+//! registry, see [`Chain::query_token_display`]: the `ibc/` hashes are resolved to the path of the
+//! token, and the symbol and decimals are read from the denom metadata of the bank module or the
+//! properties of the substrate chain. The metadata is cached by denom in
+//! [`CommonClientState::token_displays`](crate::CommonClientState::token_displays), and the denoms
+//! whose metadata can't be resolved are rendered as is, in their smallest unit.
+
+use crate::Chain;
+use ibc::applications::transfer::packet::PacketData;
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+};
+
+/// Symbol and decimals a token is displayed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenDisplay {
+	pub symbol: String,
+	/// Number of decimals of the displayed unit, 0 to display the smallest unit
+	pub decimals: u8,
+}
+
+impl TokenDisplay {
+	/// Displays the denom as is, in its smallest unit.
+	pub fn raw(denom: &str) -> Self {
+		Self { symbol: denom.to_string(), decimals: 0 }
+	}
+
+	/// Formats an amount of the token given in its smallest unit, e.g. `1.5 ATOM`.
+	pub fn format(&self, amount: u128) -> String {
+		let amount = format_amount(amount, self.decimals);
+		match self.symbol.is_empty() {
+			true => amount,
+			false => format!("{amount} {}", self.symbol),
+		}
+	}
+}
+
+/// Formats an amount given in the smallest unit with the decimals of the displayed unit, without
+/// the trailing zeros.
+pub fn format_amount(amount: u128, decimals: u8) -> String {
+	// 10^38 is the largest power of ten fitting in a u128
+	let decimals = decimals.min(38) as usize;
+	if decimals == 0 {
+		return amount.to_string()
+	}
+	let unit = 10u128.pow(decimals as u32);
+	let (whole, fraction) = (amount / unit, amount % unit);
+	if fraction == 0 {
+		return whole.to_string()
+	}
+	let fraction = format!("{fraction:0decimals$}");
+	format!("{whole}.{}", fraction.trim_end_matches('0'))
+}
+
+/// Display metadata resolved by denom, `None` being the fee token, shared by the clones of the
+/// client.
+#[derive(Debug, Clone, Default)]
+pub struct TokenDisplays(Arc<Mutex<HashMap<Option<String>, TokenDisplay>>>);
+
+/// Returns the display metadata of a denom of the chain, or of its fee token if `None`, querying
+/// it on the first use.
+pub async fn token_display(chain: &impl Chain, denom: Option<&str>) -> TokenDisplay {
+	let key = denom.map(str::to_string);
+	let displays = &chain.common_state().token_displays;
+	if let Some(display) = displays.0.lock().unwrap().get(&key) {
+		return display.clone()
+	}
+	match chain.query_token_display(denom).await {
+		Ok(display) => {
+			displays.0.lock().unwrap().insert(key, display.clone());
+			display
+		},
+		Err(e) => {
+			log::debug!(
+				target: "hyperspace",
+				"Failed to query the display metadata of {} on {}: {e:?}",
+				denom.unwrap_or("the fee token"), chain.name()
+			);
+			TokenDisplay::raw(denom.unwrap_or_default())
+		},
+	}
+}
+
+/// Describes an ICS20 transfer sent from `source` to `sink`, e.g.
+/// `1.5 ATOM from cosmos1... to 5Grw...`.
+pub async fn describe_transfer(
+	source: &impl Chain,
+	sink: &impl Chain,
+	data: &PacketData,
+) -> String {
+	let denom = data.token.denom.to_string();
+	let display = token_display(source, Some(&denom)).await;
+	let amount = match data.token.amount.to_string().parse::<u128>() {
+		Ok(amount) => display.format(amount),
+		Err(_) => format!("{} {}", data.token.amount, display.symbol),
+	};
+	format!(
+		"{amount} from {} to {}",
+		source.format_account(data.sender.as_ref()),
+		sink.format_account(data.receiver.as_ref())
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn amounts_are_formatted_with_the_decimals() {
+		assert_eq!(format_amount(1_500_000, 6), "1.5");
+		assert_eq!(format_amount(1_000_000, 6), "1");
+		assert_eq!(format_amount(1, 6), "0.000001");
+		assert_eq!(format_amount(0, 6), "0");
+		assert_eq!(format_amount(1_234_567, 0), "1234567");
+	}
+
+	#[test]
+	fn decimals_are_capped_to_the_largest_power_of_ten() {
+		assert_eq!(format_amount(u128::MAX, 255), format_amount(u128::MAX, 38));
+		assert_eq!(format_amount(10u128.pow(38), 38), "1");
+	}
+
+	#[test]
+	fn tokens_are_formatted_with_their_symbol() {
+		let display = TokenDisplay { symbol: "ATOM".to_string(), decimals: 6 };
+		assert_eq!(display.format(2_500_000), "2.5 ATOM");
+		assert_eq!(TokenDisplay::raw("uatom").format(42), "42 uatom");
+		assert_eq!(TokenDisplay::raw("").format(42), "42");
+	}
+}
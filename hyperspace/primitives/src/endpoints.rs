@@ -0,0 +1,177 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rotation between the primary endpoints of a chain and its fallbacks.
+//!
+//! The clients report the network errors of their endpoints, and move to the next endpoint once
+//! the current one failed [`FAILURE_THRESHOLD`] times within [`FAILURE_WINDOW`]. While on a
+//! fallback, the clients probe the primary endpoint every [`PRIMARY_CHECK_INTERVAL`] and return to
+//! it once it's healthy again.
+
+use std::{
+	collections::VecDeque,
+	time::{Duration, Instant},
+};
+
+/// Number of failures of an endpoint after which the next one is used.
+pub const FAILURE_THRESHOLD: usize = 3;
+/// Period over which the failures of an endpoint are counted.
+pub const FAILURE_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// Interval between two probes of the primary endpoint, while a fallback is used.
+pub const PRIMARY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Endpoints of a chain, the first one being the primary.
+#[derive(Debug, Clone)]
+pub struct EndpointRotation<E> {
+	endpoints: Vec<E>,
+	current: usize,
+	failures: VecDeque<Instant>,
+}
+
+impl<E: Clone> EndpointRotation<E> {
+	pub fn new(primary: E, fallbacks: Vec<E>) -> Self {
+		Self {
+			endpoints: std::iter::once(primary).chain(fallbacks).collect(),
+			current: 0,
+			failures: VecDeque::new(),
+		}
+	}
+
+	/// All the endpoints, starting with the primary.
+	pub fn endpoints(&self) -> &[E] {
+		&self.endpoints
+	}
+
+	pub fn current(&self) -> &E {
+		&self.endpoints[self.current]
+	}
+
+	pub fn primary(&self) -> &E {
+		&self.endpoints[0]
+	}
+
+	pub fn is_on_primary(&self) -> bool {
+		self.current == 0
+	}
+
+	/// Records a failure of the current endpoint. Returns the next endpoint, which becomes the
+	/// current one, if the current endpoint failed too often.
+	pub fn report_failure(&mut self) -> Option<E> {
+		let now = Instant::now();
+		while self
+			.failures
+			.front()
+			.map_or(false, |at| now.duration_since(*at) > FAILURE_WINDOW)
+		{
+			self.failures.pop_front();
+		}
+		self.failures.push_back(now);
+		if self.failures.len() < FAILURE_THRESHOLD {
+			return None
+		}
+		self.rotate()
+	}
+
+	/// Moves to the next endpoint, or `None` if there are no fallbacks.
+	pub fn rotate(&mut self) -> Option<E> {
+		if self.endpoints.len() < 2 {
+			return None
+		}
+		self.failures.clear();
+		self.current = (self.current + 1) % self.endpoints.len();
+		Some(self.current().clone())
+	}
+
+	/// Moves back to the primary endpoint.
+	pub fn return_to_primary(&mut self) -> E {
+		self.failures.clear();
+		self.current = 0;
+		self.primary().clone()
+	}
+
+	/// Replaces the endpoints, e.g. on a config reload, moving back to the primary.
+	pub fn set_endpoints(&mut self, primary: E, fallbacks: Vec<E>) {
+		*self = Self::new(primary, fallbacks);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rotation() -> EndpointRotation<&'static str> {
+		EndpointRotation::new("primary", vec!["fallback-1", "fallback-2"])
+	}
+
+	#[test]
+	fn endpoints_rotate_once_the_failure_threshold_is_reached() {
+		let mut rotation = rotation();
+		for _ in 1..FAILURE_THRESHOLD {
+			assert_eq!(rotation.report_failure(), None);
+		}
+		assert_eq!(rotation.report_failure(), Some("fallback-1"));
+		assert_eq!(*rotation.current(), "fallback-1");
+		assert!(!rotation.is_on_primary());
+
+		// the failures of the previous endpoint don't count against the next one
+		assert_eq!(rotation.report_failure(), None);
+	}
+
+	#[test]
+	fn failures_outside_of_the_window_are_forgotten() {
+		let mut rotation = rotation();
+		let Some(expired) = Instant::now().checked_sub(FAILURE_WINDOW + Duration::from_secs(1))
+		else {
+			return
+		};
+		rotation.failures.extend(std::iter::repeat(expired).take(FAILURE_THRESHOLD - 1));
+		assert_eq!(rotation.report_failure(), None);
+		assert_eq!(rotation.failures.len(), 1);
+		assert!(rotation.is_on_primary());
+	}
+
+	#[test]
+	fn rotation_wraps_around_to_the_primary() {
+		let mut rotation = rotation();
+		assert_eq!(rotation.rotate(), Some("fallback-1"));
+		assert_eq!(rotation.rotate(), Some("fallback-2"));
+		assert_eq!(rotation.rotate(), Some("primary"));
+		assert!(rotation.is_on_primary());
+	}
+
+	#[test]
+	fn endpoints_without_fallbacks_dont_rotate() {
+		let mut rotation = EndpointRotation::new("primary", vec![]);
+		for _ in 0..FAILURE_THRESHOLD {
+			assert_eq!(rotation.report_failure(), None);
+		}
+		assert_eq!(rotation.rotate(), None);
+		assert_eq!(*rotation.current(), "primary");
+	}
+
+	#[test]
+	fn failures_are_reset_once_the_primary_is_healthy_again() {
+		let mut rotation = rotation();
+		rotation.rotate();
+		for _ in 1..FAILURE_THRESHOLD {
+			rotation.report_failure();
+		}
+		assert_eq!(rotation.return_to_primary(), "primary");
+		assert!(rotation.is_on_primary());
+		for _ in 1..FAILURE_THRESHOLD {
+			assert_eq!(rotation.report_failure(), None);
+		}
+		assert_eq!(rotation.report_failure(), Some("fallback-1"));
+	}
+}
@@ -0,0 +1,234 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ICS-29 fee middleware.
+//!
+//! The channels of the fee middleware wrap the version of their application in a
+//! [`FeeVersion`]. Their packets may be incentivized: the fees escrowed for a packet on its source
+//! are paid, once its acknowledgement or its timeout is delivered there, to the relayers that
+//! delivered the packet and its acknowledgement (or its timeout), or to the payees they
+//! registered. The relayer delivers the incentivized packets of a channel first, and records the
+//! fees distributed to it.
+
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
+use ibc_proto::{
+	cosmos::base::v1beta1::Coin,
+	google::protobuf::Any,
+	ibc::applications::fee::v1::{MsgRegisterCounterpartyPayee, MsgRegisterPayee},
+};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+};
+
+/// Version of the fee middleware.
+pub const FEE_VERSION: &str = "ics29-1";
+pub const REGISTER_PAYEE_TYPE_URL: &str = "/ibc.applications.fee.v1.MsgRegisterPayee";
+pub const REGISTER_COUNTERPARTY_PAYEE_TYPE_URL: &str =
+	"/ibc.applications.fee.v1.MsgRegisterCounterpartyPayee";
+/// Type of the events emitted for each fee distributed to a relayer.
+pub const DISTRIBUTE_FEE_EVENT: &str = "distribute_fee";
+
+/// Version of a channel of the fee middleware.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FeeVersion {
+	pub fee_version: String,
+	pub app_version: String,
+}
+
+impl FeeVersion {
+	/// Parses the version of a channel, returning `None` if the channel isn't fee-enabled.
+	pub fn parse(version: &str) -> Option<Self> {
+		serde_json::from_str::<Self>(version)
+			.ok()
+			.filter(|version| version.fee_version == FEE_VERSION)
+	}
+
+	pub fn to_version_string(&self) -> String {
+		serde_json::to_string(self).expect("the fee version is serializable; qed")
+	}
+}
+
+/// Returns `true` if the channel of the given version is fee-enabled.
+pub fn is_fee_enabled(version: &str) -> bool {
+	FeeVersion::parse(version).is_some()
+}
+
+/// Fees escrowed for a packet, summed over its payers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncentivizedPacket {
+	pub sequence: u64,
+	pub recv_fee: Vec<Coin>,
+	pub ack_fee: Vec<Coin>,
+	pub timeout_fee: Vec<Coin>,
+}
+
+/// Registers the address the acknowledgement and timeout fees of the relayer are paid to, on the
+/// source chain of the packets.
+pub fn register_payee_msg(
+	port_id: &PortId,
+	channel_id: &ChannelId,
+	relayer: String,
+	payee: String,
+) -> Any {
+	let msg = MsgRegisterPayee {
+		port_id: port_id.to_string(),
+		channel_id: channel_id.to_string(),
+		relayer,
+		payee,
+	};
+	Any { type_url: REGISTER_PAYEE_TYPE_URL.to_string(), value: msg.encode_to_vec() }
+}
+
+/// Registers the address on the counterparty the receive fees of the relayer are paid to, on the
+/// destination chain of the packets.
+pub fn register_counterparty_payee_msg(
+	port_id: &PortId,
+	channel_id: &ChannelId,
+	relayer: String,
+	counterparty_payee: String,
+) -> Any {
+	let msg = MsgRegisterCounterpartyPayee {
+		port_id: port_id.to_string(),
+		channel_id: channel_id.to_string(),
+		relayer,
+		counterparty_payee,
+	};
+	Any { type_url: REGISTER_COUNTERPARTY_PAYEE_TYPE_URL.to_string(), value: msg.encode_to_vec() }
+}
+
+/// Parses an amount of coins, e.g. `100stake,20uatom`, skipping the malformed ones.
+pub fn parse_coins(coins: &str) -> Vec<(String, u128)> {
+	coins
+		.split(',')
+		.filter_map(|coin| {
+			let coin = coin.trim();
+			let split = coin.find(|c: char| !c.is_ascii_digit())?;
+			let (amount, denom) = coin.split_at(split);
+			Some((denom.to_string(), amount.parse().ok()?))
+		})
+		.collect()
+}
+
+/// Fees distributed to the relayer and not reported to the metrics yet, per denom. Shared by the
+/// clones of the client.
+#[derive(Debug, Clone, Default)]
+pub struct EarnedFees(Arc<Mutex<HashMap<String, u128>>>);
+
+impl EarnedFees {
+	/// Records a fee distributed by a `distribute_fee` event, if it's paid to one of the receivers.
+	pub fn record(&self, receiver: &str, fee: &str, receivers: &[String]) {
+		if !receivers.iter().any(|address| address == receiver) {
+			return
+		}
+		let mut earned = self.0.lock().unwrap();
+		for (denom, amount) in parse_coins(fee) {
+			let total = earned.entry(denom).or_default();
+			*total = total.saturating_add(amount);
+		}
+	}
+
+	/// Takes the fees recorded since the last call.
+	pub fn take(&self) -> Vec<(String, u128)> {
+		self.0.lock().unwrap().drain().collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fee_version(app_version: &str) -> String {
+		FeeVersion { fee_version: FEE_VERSION.to_string(), app_version: app_version.to_string() }
+			.to_version_string()
+	}
+
+	#[test]
+	fn coins_are_parsed() {
+		assert_eq!(
+			parse_coins(
+				"100stake, 20ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+			),
+			vec![
+				("stake".to_string(), 100),
+				(
+					"ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+						.to_string(),
+					20
+				),
+			]
+		);
+		assert!(parse_coins("").is_empty());
+	}
+
+	#[test]
+	fn malformed_coins_are_skipped() {
+		// no denom, no amount, amount after the denom, amount overflowing
+		assert_eq!(
+			parse_coins("100,stake,uatom5,,1000000000000000000000000000000000000000stake,7uosmo"),
+			vec![("uosmo".to_string(), 7)]
+		);
+	}
+
+	#[test]
+	fn fee_versions_are_parsed() {
+		let version = fee_version("ics20-1");
+		assert_eq!(
+			FeeVersion::parse(&version),
+			Some(FeeVersion {
+				fee_version: FEE_VERSION.to_string(),
+				app_version: "ics20-1".to_string()
+			})
+		);
+		assert!(is_fee_enabled(&version));
+	}
+
+	#[test]
+	fn versions_without_the_fee_middleware_arent_fee_versions() {
+		for version in [
+			"ics20-1",
+			"",
+			r#"{"fee_version":"ics29-2","app_version":"ics20-1"}"#,
+			r#"{"app_version":"ics20-1"}"#,
+		] {
+			assert_eq!(FeeVersion::parse(version), None, "{version}");
+			assert!(!is_fee_enabled(version));
+		}
+	}
+
+	#[test]
+	fn nested_fee_versions_are_unwrapped_one_level_at_a_time() {
+		let nested = fee_version(&fee_version("ics20-1"));
+		let outer = FeeVersion::parse(&nested).unwrap();
+		assert_eq!(outer.app_version, fee_version("ics20-1"));
+		let inner = FeeVersion::parse(&outer.app_version).unwrap();
+		assert_eq!(inner.app_version, "ics20-1");
+	}
+
+	#[test]
+	fn only_the_fees_paid_to_the_receivers_are_earned() {
+		let earned = EarnedFees::default();
+		let receivers = vec!["relayer".to_string(), "payee".to_string()];
+		earned.record("relayer", "100stake", &receivers);
+		earned.record("payee", "20stake,5uatom", &receivers);
+		earned.record("other", "1000stake", &receivers);
+
+		let mut fees = earned.take();
+		fees.sort();
+		assert_eq!(fees, vec![("stake".to_string(), 120), ("uatom".to_string(), 5)]);
+		assert!(earned.take().is_empty());
+	}
+}
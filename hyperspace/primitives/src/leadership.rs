@@ -0,0 +1,115 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Role of the relayer in a hot/standby deployment.
+//!
+//! A standby relayer processes the finality notifications and tracks the state of the chains like
+//! the leader, but drops its outgoing messages instead of submitting them, so that it can take
+//! over as soon as the lease of the leader lapses. A relayer that isn't part of such a deployment
+//! is always the leader.
+
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+struct Role {
+	is_leader: bool,
+	/// Time at which the lease was last renewed, and time after which it lapses if it isn't
+	/// renewed again. `None` outside of a hot/standby deployment
+	lease: Option<(Instant, Duration)>,
+}
+
+/// Whether the relayer is the leader of its deployment, shared by the clones of the client and by
+/// the chains of the relayer.
+#[derive(Debug, Clone)]
+pub struct Leadership(Arc<Mutex<Role>>);
+
+impl Default for Leadership {
+	fn default() -> Self {
+		Self(Arc::new(Mutex::new(Role { is_leader: true, lease: None })))
+	}
+}
+
+impl Leadership {
+	/// A relayer that doesn't hold the lease yet, whose lease lapses once it isn't renewed for
+	/// `ttl`.
+	pub fn standby(ttl: Duration) -> Self {
+		Self(Arc::new(Mutex::new(Role { is_leader: false, lease: Some((Instant::now(), ttl)) })))
+	}
+
+	/// Returns `true` if the relayer is the leader. The leader of a hot/standby deployment stops
+	/// being one once its lease wasn't renewed for the ttl, even if the task renewing it is stuck,
+	/// since a standby may take it over from then on.
+	pub fn is_leader(&self) -> bool {
+		let role = self.0.lock().unwrap();
+		role.is_leader && role.lease.map_or(true, |(renewed_at, ttl)| renewed_at.elapsed() < ttl)
+	}
+
+	/// Records whether the relayer holds the lease, renewed at `renewed_at`, returning `true` if
+	/// the role changed.
+	pub fn set(&self, is_leader: bool, renewed_at: Instant) -> bool {
+		let was_leader = self.is_leader();
+		let mut role = self.0.lock().unwrap();
+		role.is_leader = is_leader;
+		if let Some((last_renewal, _)) = role.lease.as_mut() {
+			if is_leader {
+				*last_renewal = renewed_at;
+			}
+		}
+		was_leader != is_leader
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TTL: Duration = Duration::from_millis(100);
+
+	#[test]
+	fn relayers_outside_of_a_deployment_are_leaders() {
+		let leadership = Leadership::default();
+		assert!(leadership.is_leader());
+		assert!(!leadership.set(true, Instant::now()));
+		assert!(leadership.is_leader());
+	}
+
+	#[test]
+	fn standbys_lead_while_they_hold_the_lease() {
+		let leadership = Leadership::standby(TTL);
+		assert!(!leadership.is_leader());
+		assert!(leadership.set(true, Instant::now()));
+		assert!(leadership.clone().is_leader());
+		assert!(!leadership.set(true, Instant::now()));
+		assert!(leadership.set(false, Instant::now()));
+		assert!(!leadership.is_leader());
+	}
+
+	#[test]
+	fn leaders_step_down_once_the_lease_lapses() {
+		let leadership = Leadership::standby(TTL);
+		leadership.set(true, Instant::now());
+		std::thread::sleep(TTL);
+		assert!(!leadership.is_leader());
+		// renewing the lease makes the relayer the leader again
+		assert!(leadership.set(true, Instant::now()));
+		assert!(leadership.is_leader());
+
+		let Some(lapsed) = Instant::now().checked_sub(TTL) else { return };
+		leadership.set(true, lapsed);
+		assert!(!leadership.is_leader());
+	}
+}
@@ -34,12 +34,30 @@ use std::{
 	fmt::Debug,
 	pin::Pin,
 	str::FromStr,
-	sync::{Arc, Mutex},
+	sync::{atomic::AtomicUsize, Arc, Mutex},
 	time::Duration,
 };
-use tokio::{sync::Mutex as AsyncMutex, task::JoinSet, time::sleep};
+use tokio::{
+	sync::{Mutex as AsyncMutex, Semaphore},
+	task::JoinSet,
+	time::sleep,
+};
 
-use crate::error::Error;
+use crate::{
+	balance::BalanceGuard,
+	channel_upgrades::ChannelUpgrade,
+	client_updates::ClientUpdateTimes,
+	dead_letter::DeadLetters,
+	display::{TokenDisplay, TokenDisplays},
+	error::Error,
+	fees::{EarnedFees, IncentivizedPacket},
+	leadership::Leadership,
+	proof_cache::ProofCache,
+	rate_limit::{SubmissionLimiter, SubmissionRateLimit, ThrottledRequests},
+	retry::RetryPolicy,
+	store::RelayerStore,
+	submitted::SubmittedPackets,
+};
 #[cfg(any(feature = "testing", test))]
 use ibc::applications::transfer::msgs::transfer::MsgTransfer;
 use ibc::{
@@ -69,8 +87,25 @@ use ibc_proto::ibc::core::{
 use ibc_rpc::PacketInfo;
 use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState, AnyConsensusState};
 
+pub mod apps;
+pub mod balance;
+pub mod channel_upgrades;
+pub mod client_updates;
+pub mod correlation;
+pub mod dead_letter;
+pub mod display;
+pub mod endpoints;
 pub mod error;
+pub mod fees;
+pub mod leadership;
+pub mod memo;
 pub mod mock;
+pub mod proof_cache;
+pub mod rate_limit;
+pub mod remote_signer;
+pub mod retry;
+pub mod store;
+pub mod submitted;
 pub mod utils;
 
 pub enum UpdateMessage {
@@ -103,6 +138,10 @@ fn max_packets_to_process() -> u32 {
 	50
 }
 
+/// Default number of proofs queried concurrently from a chain, see
+/// [`CommonClientConfig::proof_concurrency`].
+pub const DEFAULT_PROOF_CONCURRENCY: usize = 10;
+
 // TODO: move other fields like `client_id`, `connection_id`, etc. here
 /// Common relayer parameters
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -110,8 +149,223 @@ pub struct CommonClientConfig {
 	/// Skip optional client updates
 	#[serde(default = "default_skip_optional_client_updates")]
 	pub skip_optional_client_updates: bool,
+	/// Skip the mandatory client updates too when there's nothing to relay, see
+	/// [`CommonClientState::idle_mode`]. Only safe for chains whose light clients may skip headers
+	/// with validator set changes, e.g. tendermint chains
+	#[serde(default)]
+	pub idle_mode: bool,
 	#[serde(default = "max_packets_to_process")]
 	pub max_packets_to_process: u32,
+	/// Maximum number of packets per channel that may be in flight (received on the counterparty,
+	/// but not acknowledged yet) at once. Relaying of new packets on the channel is paused while
+	/// the limit is reached.
+	#[serde(default)]
+	pub max_packets_in_flight: Option<u32>,
+	/// Maximum total amount per denom of the ICS20 transfers per channel that may be in flight at
+	/// once, in the smallest unit of the denom, e.g. `"transfer/channel-0/uatom" = 1000000`. The
+	/// denoms are written with their trace path, as in the packet data. Relaying of the transfers
+	/// of a denom is paused while its limit would be exceeded, the denoms without a limit and the
+	/// packets of the other applications aren't limited.
+	#[serde(default)]
+	pub max_value_in_flight: HashMap<String, u128>,
+	/// Limits of the transaction bundles submitted to the chain, on top of the limits queried from
+	/// the chain, see [`Chain::query_batch_limits`]
+	#[serde(default)]
+	pub batch_limits: BatchLimits,
+	/// Order of the messages submitted to the chain within a relay cycle
+	#[serde(default)]
+	pub message_priority: MessagePriority,
+	/// Retries of the failed queries and transaction submissions
+	#[serde(default)]
+	pub retry_policy: RetryPolicy,
+	/// Balance of the relayer account below which the balance headroom of the health score of the
+	/// paths drops to 0, in the smallest unit of the fee token, see
+	/// [`Chain::query_relayer_balance`]
+	#[serde(default)]
+	pub min_balance: Option<u128>,
+	/// Limits of the rate at which transaction bundles are submitted to the chain
+	#[serde(default)]
+	pub submission_rate_limit: SubmissionRateLimit,
+	/// Interval in seconds between two sweeps of the pending messages of the chain, see
+	/// [`CommonClientState::sweep_interval`]. Sweeps are disabled if not set
+	#[serde(default)]
+	pub sweep_interval_secs: Option<u64>,
+	/// Balance of the relayer account below which the submissions to the chain are paused until
+	/// the account is topped up, in the smallest unit of the fee token. Unlike
+	/// [`Self::min_balance`], which only lowers the health score so that the account is topped up
+	/// in time, it stops the relaying, so it's meant to be set well below `min_balance`, to what
+	/// the fees of a few transactions cost
+	#[serde(default)]
+	pub pause_below_balance: Option<u128>,
+	/// Maximum number of packet proofs and commitments queried concurrently from the chain when
+	/// constructing the packet messages, [`DEFAULT_PROOF_CONCURRENCY`] if not set
+	#[serde(default)]
+	pub proof_concurrency: Option<u32>,
+	/// Address the ICS-29 fees earned by the relayer on the chain are paid to, the relayer account
+	/// if not set, see the `register-payees` command
+	#[serde(default)]
+	pub fee_payee: Option<String>,
+	/// Update the client of the chain only at the latest height of a relay cycle, see
+	/// [`CommonClientState::target_height_updates`]
+	#[serde(default)]
+	pub target_height_updates: bool,
+	/// Submit the packet messages to the chain without checking whether they were already
+	/// delivered, see [`CommonClientState::skip_delivered_check`]
+	#[serde(default)]
+	pub skip_delivered_check: bool,
+	/// Ceilings of the estimated fees of the bundles submitted to the chain
+	#[serde(default)]
+	pub fee_ceiling: FeeCeiling,
+	/// Interval in seconds between two scans of the timed out packets of the chain, see
+	/// [`CommonClientState::timeout_scan_interval`]. The timeouts are only relayed by the relay
+	/// cycles if not set
+	#[serde(default)]
+	pub timeout_scan_interval_secs: Option<u64>,
+	/// Confirmations required on top of the finality of the chain before its proofs are relayed,
+	/// see [`FinalityConfirmations`]
+	#[serde(default)]
+	pub finality_confirmations: FinalityConfirmations,
+}
+
+/// Safety margin on top of the finality of a chain, for the chains whose light clients have edge
+/// cases around the tip of their finalized chain. A finality notification of the chain is only
+/// relayed once `blocks` more blocks were finalized after it was received, and `secs` seconds
+/// went by since. The relay loop waits for both if both are set.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FinalityConfirmations {
+	/// Number of blocks finalized after the notification
+	#[serde(default)]
+	pub blocks: Option<u64>,
+	/// Number of seconds elapsed since the notification
+	#[serde(default)]
+	pub secs: Option<u64>,
+}
+
+impl FinalityConfirmations {
+	pub fn is_set(&self) -> bool {
+		self.blocks.is_some() || self.secs.is_some()
+	}
+}
+
+/// Ceilings of the fees paid to submit messages to the chain, in the smallest unit of the fee
+/// token. The fee of each bundle is estimated before it's submitted, see [`Chain::estimate_fee`].
+/// A bundle over a ceiling is split in halves, and a message that is still over it on its own is
+/// deferred: it's dropped for this relay cycle, and found again by the next ones until the fees go
+/// down.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeCeiling {
+	/// Maximum fee of a message, averaged over the messages of the bundle
+	#[serde(default)]
+	pub max_per_message: Option<u128>,
+	/// Maximum fee of a bundle
+	#[serde(default)]
+	pub max_per_bundle: Option<u128>,
+}
+
+impl FeeCeiling {
+	pub fn is_set(&self) -> bool {
+		self.max_per_message.is_some() || self.max_per_bundle.is_some()
+	}
+
+	/// Returns `true` if the estimated fee of a bundle of `messages` messages is over a ceiling.
+	pub fn is_exceeded_by(&self, fee: u128, messages: usize) -> bool {
+		let per_message = fee / messages.max(1) as u128;
+		self.max_per_bundle.map_or(false, |max| fee > max) ||
+			self.max_per_message.map_or(false, |max| per_message > max)
+	}
+}
+
+/// Limits of a single transaction bundle submitted to the chain. Outgoing messages are split into
+/// as many bundles as needed to respect them.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BatchLimits {
+	/// Maximum weight (or gas) of a bundle, defaults to [`Chain::block_max_weight`]
+	#[serde(default)]
+	pub max_weight: Option<u64>,
+	/// Maximum size of the messages of a bundle, in bytes
+	#[serde(default)]
+	pub max_bytes: Option<u64>,
+	/// Maximum number of messages in a bundle
+	#[serde(default)]
+	pub max_messages: Option<u32>,
+}
+
+impl BatchLimits {
+	/// Fills the limits that aren't set with the given ones.
+	pub fn or(&self, other: &BatchLimits) -> BatchLimits {
+		BatchLimits {
+			max_weight: self.max_weight.or(other.max_weight),
+			max_bytes: self.max_bytes.or(other.max_bytes),
+			max_messages: self.max_messages.or(other.max_messages),
+		}
+	}
+}
+
+/// Kinds of packet messages, ordered by [`MessagePriority`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+	Timeout,
+	RecvPacket,
+	Acknowledgement,
+}
+
+impl MessageKind {
+	pub fn of(msg: &Any) -> Option<Self> {
+		match msg.type_url.as_str() {
+			"/ibc.core.channel.v1.MsgTimeout" | "/ibc.core.channel.v1.MsgTimeoutOnClose" =>
+				Some(Self::Timeout),
+			"/ibc.core.channel.v1.MsgRecvPacket" => Some(Self::RecvPacket),
+			"/ibc.core.channel.v1.MsgAcknowledgement" => Some(Self::Acknowledgement),
+			_ => None,
+		}
+	}
+}
+
+fn default_message_order() -> Vec<MessageKind> {
+	vec![MessageKind::Timeout, MessageKind::RecvPacket, MessageKind::Acknowledgement]
+}
+
+/// Order of the messages submitted to the chain within a relay cycle, so that e.g. near-expiry
+/// timeouts aren't starved behind a large backlog of acknowledgements.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessagePriority {
+	/// Packet messages from the highest to the lowest priority. Client updates and other messages
+	/// always come first, and kinds that aren't listed come last.
+	#[serde(default = "default_message_order")]
+	pub order: Vec<MessageKind>,
+	/// Only submit the latest of the optional client updates of a cycle that no message depends
+	/// on
+	#[serde(default)]
+	pub coalesce_client_updates: bool,
+}
+
+impl Default for MessagePriority {
+	fn default() -> Self {
+		Self { order: default_message_order(), coalesce_client_updates: false }
+	}
+}
+
+impl MessagePriority {
+	fn rank(&self, msg: &Any) -> usize {
+		match MessageKind::of(msg) {
+			None => 0,
+			Some(kind) =>
+				1 + self.order.iter().position(|k| *k == kind).unwrap_or(self.order.len()),
+		}
+	}
+
+	/// Sorts the messages by priority, keeping the order of the messages of the same kind.
+	pub fn sort(&self, msgs: &mut [Any]) {
+		msgs.sort_by_key(|msg| self.rank(msg));
+	}
+
+	/// Whether the timeouts of a cycle are submitted before the other packet messages.
+	pub fn timeouts_first(&self) -> bool {
+		let position = |kind| self.order.iter().position(|k| *k == kind).unwrap_or(usize::MAX);
+		position(MessageKind::Timeout) <
+			position(MessageKind::RecvPacket).min(position(MessageKind::Acknowledgement))
+	}
 }
 
 /// A common data that all clients should keep.
@@ -119,6 +373,26 @@ pub struct CommonClientConfig {
 pub struct CommonClientState {
 	/// Enable skipping client updates when possible.
 	pub skip_optional_client_updates: bool,
+	/// Skip the client updates of the chain, optional or mandatory, unless there are messages to
+	/// relay or undelivered sequences. The client is still updated once it approaches expiry.
+	pub idle_mode: bool,
+	/// Skip the skippable client updates of a relay cycle whose messages are all packet messages,
+	/// and update the client at the latest height of the cycle instead. The packets are then
+	/// relayed with proofs at that height by the next cycle, with one client update for all of
+	/// them instead of one per height with packet events.
+	pub target_height_updates: bool,
+	/// Don't query the chain for the receipts and acknowledgements of the packets before
+	/// submitting their messages. The check avoids paying for the messages another relayer of the
+	/// path already delivered, at the cost of a query per channel before each submission.
+	pub skip_delivered_check: bool,
+	/// Ceilings of the estimated fees of the bundles, see [`CommonClientConfig::fee_ceiling`]
+	pub fee_ceiling: FeeCeiling,
+	/// Interval between two scans of the packets sent from the chain for the ones that timed out,
+	/// by a worker of its own rather than the relay loop
+	pub timeout_scan_interval: Option<Duration>,
+	/// Confirmations awaited before relaying the finality notifications of the chain, see
+	/// [`CommonClientConfig::finality_confirmations`]
+	pub finality_confirmations: FinalityConfirmations,
 	/// Used to determine whether client updates should be forced to send
 	/// even if it's optional. It's required, because some timeout packets
 	/// should use proof of the client states.
@@ -132,6 +406,64 @@ pub struct CommonClientState {
 	pub initial_rpc_call_delay: Duration,
 	pub misbehaviour_client_msg_queue: Arc<AsyncMutex<Vec<AnyClientMessage>>>,
 	pub max_packets_to_process: usize,
+	/// Maximum number of in-flight packets per channel, see
+	/// [`CommonClientConfig::max_packets_in_flight`].
+	pub max_packets_in_flight: Option<usize>,
+	/// Maximum value of the in-flight transfers per channel and denom, see
+	/// [`CommonClientConfig::max_value_in_flight`].
+	pub max_value_in_flight: HashMap<String, u128>,
+	/// Limits of the transaction bundles, see [`CommonClientConfig::batch_limits`]. Shared by the
+	/// clones of the client, so that they can be reloaded while relaying.
+	pub batch_limits: Arc<Mutex<BatchLimits>>,
+	/// Limits of the transaction bundles queried from the chain, see
+	/// [`Chain::query_batch_limits`].
+	pub queried_batch_limits: BatchLimits,
+	/// Order of the messages, see [`CommonClientConfig::message_priority`].
+	pub message_priority: MessagePriority,
+	/// Retries of the failed queries and transaction submissions, see
+	/// [`CommonClientConfig::retry_policy`].
+	pub retry_policy: RetryPolicy,
+	/// Simulate the outgoing messages instead of submitting them, see [`Chain::simulate`]
+	pub dry_run: bool,
+	/// Minimum balance of the relayer account, see [`CommonClientConfig::min_balance`].
+	pub min_balance: Option<u128>,
+	/// Persistent state of the relayer for this chain, set by the CLI when a state store is
+	/// configured
+	pub store: Option<RelayerStore>,
+	/// Updates of the clients hosted by the chain, to check the connection delays
+	pub client_update_times: ClientUpdateTimes,
+	/// Rate limiter of the submissions, see [`CommonClientConfig::submission_rate_limit`]
+	pub submission_limiter: SubmissionLimiter,
+	/// Interval between two sweeps of the messages ready to be relayed from the chain, on top of
+	/// the finality notifications, to catch the events the relayer missed (e.g. dropped websocket
+	/// messages)
+	pub sweep_interval: Option<Duration>,
+	/// Pauses the submissions while the relayer balance is low, see
+	/// [`CommonClientConfig::pause_below_balance`]
+	pub balance_guard: BalanceGuard,
+	/// Drops the outgoing messages while the relayer is a standby, see [`leadership`]
+	pub leadership: Leadership,
+	/// Permits of the concurrent proof queries to the chain, see
+	/// [`CommonClientConfig::proof_concurrency`]. Shared by the clones of the client
+	pub proof_permits: Arc<Semaphore>,
+	/// Payee of the fees earned on the chain, see [`CommonClientConfig::fee_payee`]
+	pub fee_payee: Option<String>,
+	/// ICS-29 fees distributed to the relayer (or its payee) by the submitted transactions
+	pub earned_fees: EarnedFees,
+	/// Packet messages isolated as failing by the bisection of the rejected bundles, see
+	/// [`dead_letter`]
+	pub dead_letters: DeadLetters,
+	/// Packet messages submitted during the clearing in progress, see [`submitted`]
+	pub submitted_packets: SubmittedPackets,
+	/// Number of the blocks in the local light block store of the chain, for the chains that keep
+	/// one, reported as a metric
+	pub light_store_size: Arc<AtomicUsize>,
+	/// Requests delayed by the rate limits of the endpoints of the chain, reported as a metric
+	pub throttled_requests: ThrottledRequests,
+	/// State proofs queried from the chain, for the chains that cache them, see [`proof_cache`]
+	pub proof_cache: ProofCache,
+	/// Display metadata of the denoms of the chain, see [`display`]
+	pub token_displays: TokenDisplays,
 
 	pub skip_tokens_list: Vec<String>,
 }
@@ -141,17 +473,61 @@ impl Default for CommonClientState {
 		let rpc_call_delay = Duration::from_millis(100);
 		Self {
 			skip_optional_client_updates: true,
+			idle_mode: false,
+			target_height_updates: false,
+			skip_delivered_check: false,
+			fee_ceiling: Default::default(),
+			timeout_scan_interval: None,
+			finality_confirmations: Default::default(),
 			maybe_has_undelivered_packets: Default::default(),
 			rpc_call_delay,
 			initial_rpc_call_delay: rpc_call_delay,
 			misbehaviour_client_msg_queue: Arc::new(Default::default()),
 			max_packets_to_process: 100,
+			max_packets_in_flight: None,
+			max_value_in_flight: Default::default(),
+			batch_limits: Default::default(),
+			queried_batch_limits: BatchLimits::default(),
+			message_priority: MessagePriority::default(),
+			retry_policy: RetryPolicy::default(),
+			dry_run: false,
+			min_balance: None,
+			store: None,
+			client_update_times: Default::default(),
+			submission_limiter: Default::default(),
+			sweep_interval: None,
+			balance_guard: Default::default(),
+			leadership: Default::default(),
+			proof_permits: Arc::new(Semaphore::new(DEFAULT_PROOF_CONCURRENCY)),
+			fee_payee: None,
+			earned_fees: Default::default(),
+			dead_letters: Default::default(),
+			submitted_packets: Default::default(),
+			light_store_size: Default::default(),
+			throttled_requests: Default::default(),
+			proof_cache: Default::default(),
+			token_displays: Default::default(),
 			skip_tokens_list: vec!["uosmo".to_string()],
 		}
 	}
 }
 
 impl CommonClientState {
+	pub fn batch_limits(&self) -> BatchLimits {
+		self.batch_limits.lock().unwrap().clone()
+	}
+
+	pub fn set_batch_limits(&self, limits: BatchLimits) {
+		*self.batch_limits.lock().unwrap() = limits;
+	}
+
+	/// Permits of the concurrent proof queries, given the configured limit.
+	pub fn proof_permits(concurrency: Option<u32>) -> Arc<Semaphore> {
+		let concurrency = concurrency
+			.map_or(DEFAULT_PROOF_CONCURRENCY, |concurrency| concurrency.max(1) as usize);
+		Arc::new(Semaphore::new(concurrency))
+	}
+
 	pub async fn on_undelivered_sequences(&self, has: bool, kind: UndeliveredType) {
 		log::trace!(
 			target: "hyperspace",
@@ -197,6 +573,27 @@ pub enum UndeliveredType {
 	Timeouts,
 }
 
+/// The client and consensus states committed by a chain before an upgrade, for its counterparties
+/// to upgrade its clients with a `MsgUpgradeClient`.
+#[derive(Debug, Clone)]
+pub struct UpgradedClient {
+	pub client_state: AnyClientState,
+	pub consensus_state: AnyConsensusState,
+	pub proof_upgrade_client: Vec<u8>,
+	pub proof_upgrade_consensus_state: Vec<u8>,
+}
+
+/// The fields of a block that the consensus states of the clients of the chain commit to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockCommitment {
+	/// Timestamp of the block in nanoseconds
+	pub timestamp: u64,
+	/// Commitment root of the state of the chain: the app hash or the state root
+	pub root: Vec<u8>,
+	/// Hash of the validator set of the next block, for the chains with a validator set
+	pub next_validators_hash: Option<Vec<u8>>,
+}
+
 /// Provides an interface for accessing new events and Ibc data on the chain which must be
 /// relayed to the counterparty chain.
 #[async_trait::async_trait]
@@ -234,6 +631,17 @@ pub trait IbcProvider {
 		consensus_height: Height,
 	) -> Result<QueryConsensusStateResponse, Self::Error>;
 
+	/// Query the client consensus states at the given heights with proof, in the order of the
+	/// heights, leaving out the heights the client has no consensus state at or whose query
+	/// failed. The chains that can send the queries concurrently should, see
+	/// [`find_suitable_proof_height_for_client`].
+	async fn query_client_consensus_states(
+		&self,
+		at: Height,
+		client_id: ClientId,
+		heights: Vec<Height>,
+	) -> Result<Vec<(Height, QueryConsensusStateResponse)>, Self::Error>;
+
 	/// Query client state with proof
 	async fn query_client_state(
 		&self,
@@ -419,6 +827,13 @@ pub trait IbcProvider {
 	/// Should return timestamp in nanoseconds of chain at a given block height
 	async fn query_timestamp_at(&self, block_number: u64) -> Result<u64, Self::Error>;
 
+	/// Should return the fields of the block at the given height that a consensus state of the
+	/// client of this chain at that height commits to
+	async fn query_block_commitment_at(
+		&self,
+		block_number: u64,
+	) -> Result<BlockCommitment, Self::Error>;
+
 	/// Should return a list of all clients on the chain
 	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error>;
 
@@ -464,6 +879,35 @@ pub trait IbcProvider {
 	) -> Result<(ChannelId, PortId), Self::Error>;
 
 	async fn upload_wasm(&self, wasm: Vec<u8>) -> Result<Vec<u8>, Self::Error>;
+
+	/// Should return the height of the upgrade scheduled on the chain, if any. The chain halts at
+	/// this height until it's upgraded.
+	async fn query_scheduled_upgrade_height(&self) -> Result<Option<Height>, Self::Error>;
+
+	/// Should return the client and consensus states committed by the chain for the upgrade at
+	/// the given height, with their proofs at the upgrade height.
+	async fn query_upgraded_client(
+		&self,
+		upgrade_height: Height,
+	) -> Result<UpgradedClient, Self::Error>;
+
+	/// Should return the ICS-29 fees escrowed for the packets of the channel that are still
+	/// pending, or an empty list if the chain has no fee middleware.
+	async fn query_incentivized_packets(
+		&self,
+		at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Vec<IncentivizedPacket>, Self::Error>;
+
+	/// Should return the upgrade of the channel stored on the chain with its proof, or `None` if
+	/// the channel isn't being upgraded.
+	async fn query_channel_upgrade(
+		&self,
+		at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Option<ChannelUpgrade>, Self::Error>;
 }
 
 /// Provides an interface that allows us run the hyperspace-testsuite
@@ -534,6 +978,32 @@ pub trait Chain:
 	/// Should return an estimate of the weight of a batch of messages.
 	async fn estimate_weight(&self, msg: Vec<Any>) -> Result<u64, Self::Error>;
 
+	/// Should return an estimate of the fee paid to submit a batch of messages, in the smallest
+	/// unit of the fee token, like [`Chain::query_relayer_balance`].
+	async fn estimate_fee(&self, msg: Vec<Any>) -> Result<u128, Self::Error>;
+
+	/// Should query the limits of a batch of messages from the on-chain parameters (e.g. the
+	/// consensus params), which change with governance proposals and runtime upgrades.
+	async fn query_batch_limits(&self) -> Result<BatchLimits, Self::Error>;
+
+	/// Should return the balance of the relayer account in the token the transaction fees are paid
+	/// with, in its smallest unit.
+	async fn query_relayer_balance(&self) -> Result<u128, Self::Error>;
+
+	/// Should resolve the symbol and decimals of a denom of the chain, or of the fee token if
+	/// `None`, from the on-chain registry of the chain, see [`display`]. Displays the denom as is
+	/// by default.
+	async fn query_token_display(&self, denom: Option<&str>) -> Result<TokenDisplay, Self::Error> {
+		Ok(TokenDisplay::raw(denom.unwrap_or_default()))
+	}
+
+	/// Should render an account given in any format the chain accepts (e.g. hex) in the format of
+	/// the chain, e.g. bech32 or SS58. Used in the logs and the CLI output, see [`display`].
+	/// Renders the account as is by default.
+	fn format_account(&self, account: &str) -> String {
+		account.to_string()
+	}
+
 	/// Return a stream that yields when new [`IbcEvents`] are ready to be queried.
 	async fn finality_notifications(
 		&self,
@@ -544,6 +1014,11 @@ pub trait Chain:
 	/// Should return the transaction id
 	async fn submit(&self, messages: Vec<Any>) -> Result<Self::TransactionId, Self::Error>;
 
+	/// Simulates the submission of the messages without broadcasting them, e.g. with a `Simulate`
+	/// query on tendermint chains or a runtime dry-run on substrate chains. Used by the dry-run
+	/// mode of the relayer, see [`CommonClientState::dry_run`].
+	async fn simulate(&self, messages: Vec<Any>) -> Result<(), Self::Error>;
+
 	/// Returns an [`AnyClientMessage`] for an [`UpdateClient`] event
 	async fn query_client_message(
 		&self,
@@ -701,6 +1176,9 @@ pub fn packet_info_to_packet(packet_info: &PacketInfo) -> Packet {
 	}
 }
 
+/// Number of the consensus states queried at once by [`find_suitable_proof_height_for_client`]
+pub const CONSENSUS_STATES_BATCH_SIZE: usize = 16;
+
 /// Should return the first client consensus height with a consensus state timestamp that
 /// is equal to or greater than the values provided
 pub async fn find_suitable_proof_height_for_client(
@@ -722,27 +1200,44 @@ pub async fn find_suitable_proof_height_for_client(
 	if timestamp_to_match.is_none() {
 		// try to find latest states first, because relayer's strategy is to submit the most
 		// recent ones
-		for height in start_height.revision_height..=latest_client_height.revision_height {
-			let temp_height = Height::new(start_height.revision_number, height);
-			let consensus_state =
-				sink.query_client_consensus(at, client_id.clone(), temp_height).await.ok();
-			let decoded = consensus_state
-				.map(|x| x.consensus_state.map(AnyConsensusState::try_from))
-				.flatten();
-			if !matches!(decoded, Some(Ok(_))) {
-				continue
-			}
-			let proof_height = source.get_proof_height(temp_height).await;
-			let has_client_state = sink
-				.query_client_update_time_and_height(client_id.clone(), proof_height)
+		let heights = (start_height.revision_height..=latest_client_height.revision_height)
+			.map(|height| Height::new(start_height.revision_number, height))
+			.collect::<Vec<_>>();
+		// the consensus states are queried in batches, concurrently on the chains that support it.
+		// The heights whose query failed are left out of a batch
+		for heights in heights.chunks(CONSENSUS_STATES_BATCH_SIZE) {
+			let consensus_states = match sink
+				.query_client_consensus_states(at, client_id.clone(), heights.to_vec())
 				.await
-				.ok()
-				.is_some();
-			if !has_client_state {
-				continue
+			{
+				Ok(consensus_states) => consensus_states,
+				Err(e) => {
+					log::debug!(target: "hyperspace", "Failed to query the consensus states of {client_id} on {}: {e:?}", sink.name());
+					continue
+				},
+			};
+			for (temp_height, consensus_state) in consensus_states {
+				let decoded = consensus_state.consensus_state.map(AnyConsensusState::try_from);
+				if !matches!(decoded, Some(Ok(_))) {
+					continue
+				}
+				let proof_height = source.get_proof_height(temp_height).await;
+				let has_client_state = sink
+					.query_client_update_time_and_height(client_id.clone(), proof_height)
+					.await
+					.ok()
+					.is_some();
+				if !has_client_state {
+					continue
+				}
+				log::info!(
+					"Found proof height on {} as {}:{}",
+					sink.name(),
+					temp_height,
+					proof_height
+				);
+				return Some(temp_height)
 			}
-			log::info!("Found proof height on {} as {}:{}", sink.name(), temp_height, proof_height);
-			return Some(temp_height)
 		}
 	} else {
 		let timestamp_to_match = timestamp_to_match.unwrap();
@@ -894,7 +1389,7 @@ pub fn filter_events_by_ids(
 	use ibc::core::{
 		ics02_client::events::Attributes as ClientAttributes,
 		ics03_connection::events::Attributes as ConnectionAttributes,
-		ics04_channel::events::Attributes as ChannelAttributes,
+		ics04_channel::events::{Attributes as ChannelAttributes, UpgradeAttributes},
 	};
 	let channel_ids = channel_and_port_ids
 		.iter()
@@ -928,6 +1423,14 @@ pub fn filter_events_by_ids(
 				.map(|id| channel_ids.contains(&id))
 				.unwrap_or(false)
 	};
+	let filter_upgrade_attributes = |attributes: &UpgradeAttributes| {
+		channel_ids.contains(&&attributes.channel_id) ||
+			attributes
+				.counterparty_channel_id
+				.as_ref()
+				.map(|id| channel_ids.contains(&id))
+				.unwrap_or(false)
+	};
 
 	let v = match ev {
 		IbcEvent::SendPacket(e) => filter_packet(&e.packet),
@@ -956,6 +1459,11 @@ pub fn filter_events_by_ids(
 			filter_channel_attributes(&ChannelAttributes::from(e.clone())),
 		IbcEvent::CloseConfirmChannel(e) =>
 			filter_channel_attributes(&ChannelAttributes::from(e.clone())),
+		IbcEvent::UpgradeInitChannel(e) => filter_upgrade_attributes(e.attributes()),
+		IbcEvent::UpgradeTryChannel(e) => filter_upgrade_attributes(e.attributes()),
+		IbcEvent::UpgradeAckChannel(e) => filter_upgrade_attributes(e.attributes()),
+		IbcEvent::UpgradeConfirmChannel(e) => filter_upgrade_attributes(e.attributes()),
+		IbcEvent::UpgradeOpenChannel(e) => filter_upgrade_attributes(e.attributes()),
 		IbcEvent::PushWasmCode(_) => true,
 		IbcEvent::NewBlock(_) |
 		IbcEvent::AppModule(_) |
@@ -0,0 +1,261 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Memos of outgoing ICS-20 transfers.
+//!
+//! Memos are built and validated locally against the schemas of the middlewares that consume
+//! them, so that a malformed memo is rejected before the transfer is sent instead of failing on
+//! the destination chain (or on an intermediate hop of a multi-hop transfer).
+
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+use thiserror::Error;
+
+/// Maximum size of a memo, in bytes. Larger memos are rejected by ibc-go.
+pub const MAX_MEMO_SIZE: usize = 32 * 1024;
+/// Maximum number of hops of a packet-forward memo.
+pub const MAX_FORWARD_HOPS: usize = 8;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MemoError {
+	#[error("Memo is {0} bytes long, the maximum is {MAX_MEMO_SIZE} bytes")]
+	TooLarge(usize),
+	#[error("Memo forwards over {0} hops, the maximum is {MAX_FORWARD_HOPS}")]
+	TooManyHops(usize),
+	#[error("Invalid JSON memo: {0}")]
+	InvalidJson(String),
+	#[error("Invalid forward memo: {0}")]
+	InvalidForward(String),
+	#[error("Invalid wasm hook memo: {0}")]
+	InvalidWasmHook(String),
+}
+
+/// Memo of an outgoing transfer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Memo {
+	/// Plain text, ignored by the middlewares
+	Text(String),
+	/// Forwarded by the packet-forward middleware of the receiving chain
+	Forward(Forward),
+	/// Executed by the wasm hooks middleware of the receiving chain
+	Wasm(WasmHook),
+}
+
+/// JSON schemas of the memos consumed by a middleware.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MemoJson {
+	Forward(Forward),
+	Wasm(WasmHook),
+}
+
+/// Packet-forward memo, see <https://github.com/strangelove-ventures/packet-forward-middleware>
+/// and [`pallet_ibc::ics20::Forward`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Forward {
+	/// Receiver on the next chain
+	pub receiver: String,
+	/// Port on the receiving chain to forward the transfer over
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub port: Option<String>,
+	/// Channel on the receiving chain to forward the transfer over
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub channel: Option<String>,
+	/// Timeout of the forwarded transfer, in nanoseconds
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub timeout: Option<u64>,
+	/// Number of retries of the forwarded transfer on timeout
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub retries: Option<u64>,
+	/// Parachain to forward the transfer to over XCM, or the relay chain if none
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub para_id: Option<u32>,
+	/// Forwards the transfer over XCM instead of IBC
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub substrate: Option<bool>,
+	/// Memo of the forwarded transfer
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub next: Option<Box<serde_json::Value>>,
+}
+
+/// Wasm hooks memo, see <https://github.com/osmosis-labs/osmosis/tree/main/x/ibc-hooks>.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasmHook {
+	/// Address of the contract to execute, which must also be the receiver of the transfer
+	pub contract: String,
+	/// Execute message of the contract
+	pub msg: serde_json::Value,
+}
+
+impl Forward {
+	/// Forwards the transfer over the given channel of the receiving chain.
+	pub fn new(receiver: impl Into<String>, port: PortId, channel: ChannelId) -> Self {
+		Self {
+			receiver: receiver.into(),
+			port: Some(port.to_string()),
+			channel: Some(channel.to_string()),
+			timeout: None,
+			retries: None,
+			para_id: None,
+			substrate: None,
+			next: None,
+		}
+	}
+
+	/// Forwards the transfer over XCM to the given parachain, or to the relay chain.
+	pub fn xcm(receiver: impl Into<String>, para_id: Option<u32>) -> Self {
+		Self {
+			receiver: receiver.into(),
+			port: None,
+			channel: None,
+			timeout: None,
+			retries: None,
+			para_id,
+			substrate: Some(true),
+			next: None,
+		}
+	}
+
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout.as_nanos() as u64);
+		self
+	}
+
+	pub fn retries(mut self, retries: u64) -> Self {
+		self.retries = Some(retries);
+		self
+	}
+
+	/// Sets the memo of the forwarded transfer. A text memo is passed as is, other memos are
+	/// nested as JSON objects.
+	pub fn next(mut self, memo: Memo) -> Self {
+		self.next = Some(Box::new(memo.to_json()));
+		self
+	}
+
+	fn validate(&self, hops: usize) -> Result<(), MemoError> {
+		if hops > MAX_FORWARD_HOPS {
+			return Err(MemoError::TooManyHops(hops))
+		}
+		if self.receiver.is_empty() {
+			return Err(MemoError::InvalidForward("empty receiver".to_string()))
+		}
+		if self.substrate.unwrap_or_default() {
+			if self.next.is_some() {
+				return Err(MemoError::InvalidForward(
+					"transfers forwarded over XCM can't be forwarded further".to_string(),
+				))
+			}
+			return Ok(())
+		}
+		let port = self
+			.port
+			.as_deref()
+			.ok_or_else(|| MemoError::InvalidForward("missing port".to_string()))?;
+		PortId::from_str(port)
+			.map_err(|e| MemoError::InvalidForward(format!("invalid port {port}: {e}")))?;
+		let channel = self
+			.channel
+			.as_deref()
+			.ok_or_else(|| MemoError::InvalidForward("missing channel".to_string()))?;
+		ChannelId::from_str(channel)
+			.map_err(|e| MemoError::InvalidForward(format!("invalid channel {channel}: {e}")))?;
+		match self.next.as_deref() {
+			None | Some(serde_json::Value::String(_)) => Ok(()),
+			Some(next @ serde_json::Value::Object(_)) =>
+				match serde_json::from_value::<MemoJson>(next.clone())
+					.map_err(|e| MemoError::InvalidJson(e.to_string()))?
+				{
+					MemoJson::Forward(forward) => forward.validate(hops + 1),
+					MemoJson::Wasm(wasm) => wasm.validate(),
+				},
+			Some(next) => Err(MemoError::InvalidForward(format!("invalid next memo {next}"))),
+		}
+	}
+}
+
+impl WasmHook {
+	pub fn new(contract: impl Into<String>, msg: serde_json::Value) -> Self {
+		Self { contract: contract.into(), msg }
+	}
+
+	fn validate(&self) -> Result<(), MemoError> {
+		if self.contract.is_empty() {
+			return Err(MemoError::InvalidWasmHook("empty contract address".to_string()))
+		}
+		if !self.msg.is_object() {
+			return Err(MemoError::InvalidWasmHook("msg must be a JSON object".to_string()))
+		}
+		Ok(())
+	}
+}
+
+impl Memo {
+	/// Parses and validates a raw memo. Memos that look like a JSON object must match one of the
+	/// supported schemas.
+	pub fn parse(memo: &str) -> Result<Self, MemoError> {
+		if memo.len() > MAX_MEMO_SIZE {
+			return Err(MemoError::TooLarge(memo.len()))
+		}
+		if !memo.trim_start().starts_with('{') {
+			return Ok(Memo::Text(memo.to_string()))
+		}
+		let memo = match serde_json::from_str::<MemoJson>(memo)
+			.map_err(|e| MemoError::InvalidJson(e.to_string()))?
+		{
+			MemoJson::Forward(forward) => Memo::Forward(forward),
+			MemoJson::Wasm(wasm) => Memo::Wasm(wasm),
+		};
+		memo.validate()?;
+		Ok(memo)
+	}
+
+	pub fn validate(&self) -> Result<(), MemoError> {
+		match self {
+			Memo::Text(text) if text.trim_start().starts_with('{') => Err(MemoError::InvalidJson(
+				"text memos can't be JSON objects, use a typed memo instead".to_string(),
+			)),
+			Memo::Text(_) => Ok(()),
+			Memo::Forward(forward) => forward.validate(1),
+			Memo::Wasm(wasm) => wasm.validate(),
+		}
+	}
+
+	/// Validates the memo and encodes it for [`MsgTransfer::memo`].
+	///
+	/// [`MsgTransfer::memo`]: ibc::applications::transfer::msgs::transfer::MsgTransfer::memo
+	pub fn encode(&self) -> Result<String, MemoError> {
+		self.validate()?;
+		let memo = match self {
+			Memo::Text(text) => text.clone(),
+			memo => memo.to_json().to_string(),
+		};
+		if memo.len() > MAX_MEMO_SIZE {
+			return Err(MemoError::TooLarge(memo.len()))
+		}
+		Ok(memo)
+	}
+
+	fn to_json(&self) -> serde_json::Value {
+		match self {
+			Memo::Text(text) => Ok(serde_json::Value::String(text.clone())),
+			Memo::Forward(forward) => serde_json::to_value(MemoJson::Forward(forward.clone())),
+			Memo::Wasm(wasm) => serde_json::to_value(MemoJson::Wasm(wasm.clone())),
+		}
+		.expect("memos are serializable; qed")
+	}
+}
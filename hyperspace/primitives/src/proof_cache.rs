@@ -0,0 +1,159 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cache of the state proofs queried from a chain.
+//!
+//! The messages of a relay cycle are proven at the same height: the proofs of a connection, of its
+//! channels, and of the absence of the receipts of the timed out packets together with the proof
+//! of their closed channel. The proofs are cached by the hash of their block and their storage
+//! keys, so that the repeated queries are served without a round trip to the rpc. The hash, unlike
+//! the height, identifies the block even while the chain reorganizes, and the keys are sorted, the
+//! proof of a set of keys not depending on their order. The proofs of the last
+//! [`MAX_CACHED_BLOCKS`] blocks are kept.
+//!
+//! The hits and misses of the cache are reported as the `hyperspace_proof_cache_hits` and
+//! `hyperspace_proof_cache_misses` metrics.
+
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+};
+
+/// Number of blocks whose proofs are kept.
+pub const MAX_CACHED_BLOCKS: usize = 8;
+
+/// Proofs of the storage keys of blocks.
+type BlockProofs = HashMap<Vec<Vec<u8>>, Vec<u8>>;
+
+/// Proofs by block hash and storage keys, shared by the clones of the client.
+#[derive(Debug, Clone, Default)]
+pub struct ProofCache {
+	/// The proofs of each block, the most recently cached blocks last
+	proofs: Arc<Mutex<VecDeque<(Vec<u8>, BlockProofs)>>>,
+	hits: Arc<AtomicU64>,
+	misses: Arc<AtomicU64>,
+}
+
+fn sorted(keys: &[Vec<u8>]) -> Vec<Vec<u8>> {
+	let mut keys = keys.to_vec();
+	keys.sort();
+	keys.dedup();
+	keys
+}
+
+impl ProofCache {
+	/// The cached proof of the keys at the block, counting the hit or miss.
+	pub fn get(&self, block_hash: &[u8], keys: &[Vec<u8>]) -> Option<Vec<u8>> {
+		let keys = sorted(keys);
+		let proof = self
+			.proofs
+			.lock()
+			.unwrap()
+			.iter()
+			.find(|(hash, _)| hash == block_hash)
+			.and_then(|(_, proofs)| proofs.get(&keys).cloned());
+		match proof {
+			Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+			None => self.misses.fetch_add(1, Ordering::Relaxed),
+		};
+		proof
+	}
+
+	/// Caches the proof of the keys at the block, evicting the proofs of the least recently
+	/// cached blocks.
+	pub fn insert(&self, block_hash: Vec<u8>, keys: Vec<Vec<u8>>, proof: Vec<u8>) {
+		let keys = sorted(&keys);
+		let mut proofs = self.proofs.lock().unwrap();
+		match proofs.iter_mut().find(|(hash, _)| *hash == block_hash) {
+			Some((_, block_proofs)) => {
+				block_proofs.insert(keys, proof);
+			},
+			None => {
+				proofs.push_back((block_hash, HashMap::from([(keys, proof)])));
+				while proofs.len() > MAX_CACHED_BLOCKS {
+					proofs.pop_front();
+				}
+			},
+		}
+	}
+
+	/// Returns the numbers of hits and misses, resetting them.
+	pub fn take_stats(&self) -> (u64, u64) {
+		(self.hits.swap(0, Ordering::Relaxed), self.misses.swap(0, Ordering::Relaxed))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key(byte: u8) -> Vec<u8> {
+		vec![byte; 4]
+	}
+
+	fn hash(byte: u8) -> Vec<u8> {
+		vec![byte; 32]
+	}
+
+	#[test]
+	fn cached_proofs_are_hits() {
+		let cache = ProofCache::default();
+		cache.insert(hash(1), vec![key(1), key(2)], vec![10]);
+		assert_eq!(cache.get(&hash(1), &[key(1), key(2)]), Some(vec![10]));
+		// the keys are in any order
+		assert_eq!(cache.get(&hash(1), &[key(2), key(1)]), Some(vec![10]));
+		assert_eq!(cache.take_stats(), (2, 0));
+		assert_eq!(cache.take_stats(), (0, 0));
+	}
+
+	#[test]
+	fn other_blocks_and_keys_are_misses() {
+		let cache = ProofCache::default();
+		cache.insert(hash(1), vec![key(1), key(2)], vec![10]);
+		// a block of the same height on another fork
+		assert_eq!(cache.get(&hash(2), &[key(1), key(2)]), None);
+		assert_eq!(cache.get(&hash(1), &[key(1)]), None);
+		assert_eq!(cache.get(&hash(1), &[key(1), key(2), key(3)]), None);
+		assert_eq!(cache.take_stats(), (0, 3));
+	}
+
+	#[test]
+	fn the_proofs_of_a_block_are_cached_together() {
+		let cache = ProofCache::default();
+		cache.insert(hash(1), vec![key(1)], vec![10]);
+		cache.insert(hash(1), vec![key(2)], vec![20]);
+		assert_eq!(cache.get(&hash(1), &[key(1)]), Some(vec![10]));
+		assert_eq!(cache.get(&hash(1), &[key(2)]), Some(vec![20]));
+		assert_eq!(cache.proofs.lock().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn the_least_recently_cached_blocks_are_evicted() {
+		let cache = ProofCache::default();
+		for byte in 0..=MAX_CACHED_BLOCKS as u8 {
+			cache.insert(hash(byte), vec![key(1)], vec![byte]);
+		}
+		assert_eq!(cache.get(&hash(0), &[key(1)]), None);
+		for byte in 1..=MAX_CACHED_BLOCKS as u8 {
+			assert_eq!(cache.get(&hash(byte), &[key(1)]), Some(vec![byte]));
+		}
+		// the proofs of a cached block don't evict
+		cache.insert(hash(1), vec![key(2)], vec![20]);
+		assert_eq!(cache.get(&hash(1), &[key(1)]), Some(vec![1]));
+		assert_eq!(cache.proofs.lock().unwrap().len(), MAX_CACHED_BLOCKS);
+	}
+}
@@ -0,0 +1,289 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rate limiting of the transaction bundles submitted to a chain, and of the requests sent to its
+//! endpoints.
+//!
+//! Each limit is a token bucket: the number of transactions refills over a window of blocks, the
+//! gas (or weight) refills over a minute. A submission waits until both buckets hold enough
+//! tokens, so that large packet clearings don't flood the mempool of the chain. A bundle that
+//! costs more than a whole bucket is submitted once the bucket is full.
+//!
+//! The requests to an endpoint refill at `requests_per_second`, so that the public endpoints
+//! that throttle the clients sending too many requests don't ban the relayer. The requests over
+//! the limit wait in turn for their tokens, and are counted, per endpoint, in
+//! [`ThrottledRequests`].
+
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+use tokio::sync::Mutex as AsyncMutex;
+
+fn default_block_window() -> u32 {
+	1
+}
+
+/// Limits of the rate at which transaction bundles are submitted to the chain.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct SubmissionRateLimit {
+	/// Maximum number of transactions submitted over `block_window` blocks
+	#[serde(default)]
+	pub max_txs: Option<u32>,
+	/// Number of blocks over which `max_txs` applies
+	#[serde(default = "default_block_window")]
+	pub block_window: u32,
+	/// Maximum gas (or weight) of the transactions submitted per minute
+	#[serde(default)]
+	pub max_gas_per_minute: Option<u64>,
+}
+
+impl Default for SubmissionRateLimit {
+	fn default() -> Self {
+		Self { max_txs: None, block_window: default_block_window(), max_gas_per_minute: None }
+	}
+}
+
+#[derive(Debug)]
+struct Bucket {
+	capacity: f64,
+	tokens: f64,
+	refill_per_sec: f64,
+	refilled_at: Instant,
+}
+
+impl Bucket {
+	/// A full bucket of `capacity` tokens, refilled over `period`.
+	fn new(capacity: u64, period: Duration) -> Self {
+		let capacity = capacity.max(1) as f64;
+		Self {
+			capacity,
+			tokens: capacity,
+			refill_per_sec: capacity / period.as_secs_f64().max(f64::EPSILON),
+			refilled_at: Instant::now(),
+		}
+	}
+
+	fn refill(&mut self) {
+		let now = Instant::now();
+		let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+		self.refilled_at = now;
+	}
+
+	/// Time until the bucket holds `cost` tokens, at most the time until it's full.
+	fn wait_time(&mut self, cost: u64) -> Duration {
+		self.refill();
+		let missing = (cost as f64).min(self.capacity) - self.tokens;
+		if missing <= 0.0 {
+			return Duration::ZERO
+		}
+		Duration::from_secs_f64(missing / self.refill_per_sec)
+	}
+
+	fn take(&mut self, cost: u64) {
+		self.refill();
+		self.tokens -= (cost as f64).min(self.capacity);
+	}
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+	txs: Option<Bucket>,
+	gas: Option<Bucket>,
+}
+
+/// Rate limiter of the submissions to a chain, shared by the clones of the client.
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionLimiter {
+	limit: SubmissionRateLimit,
+	/// Created on the first submission, once the block time is known. Held while a submission
+	/// waits, so that the submissions are served in order
+	buckets: Arc<AsyncMutex<Option<Buckets>>>,
+}
+
+impl SubmissionLimiter {
+	pub fn new(limit: SubmissionRateLimit) -> Self {
+		Self { limit, buckets: Default::default() }
+	}
+
+	/// Waits until a transaction of the given gas may be submitted to a chain with the given block
+	/// time, and counts it against the limits.
+	pub async fn acquire(&self, gas: u64, block_time: Duration) {
+		if self.limit.max_txs.is_none() && self.limit.max_gas_per_minute.is_none() {
+			return
+		}
+		let mut buckets = self.buckets.lock().await;
+		let buckets = buckets.get_or_insert_with(|| Buckets {
+			txs: self.limit.max_txs.map(|max_txs| {
+				Bucket::new(max_txs as u64, block_time * self.limit.block_window.max(1))
+			}),
+			gas: self
+				.limit
+				.max_gas_per_minute
+				.map(|max_gas| Bucket::new(max_gas, Duration::from_secs(60))),
+		});
+		loop {
+			let wait = [
+				buckets.txs.as_mut().map(|bucket| bucket.wait_time(1)),
+				buckets.gas.as_mut().map(|bucket| bucket.wait_time(gas)),
+			]
+			.into_iter()
+			.flatten()
+			.max()
+			.unwrap_or_default();
+			if wait.is_zero() {
+				break
+			}
+			log::debug!(target: "hyperspace", "Submission rate limit reached, waiting {wait:?}");
+			tokio::time::sleep(wait).await;
+		}
+		if let Some(bucket) = buckets.txs.as_mut() {
+			bucket.take(1);
+		}
+		if let Some(bucket) = buckets.gas.as_mut() {
+			bucket.take(gas);
+		}
+	}
+}
+
+/// Limit of the rate of the requests sent to an endpoint of a chain.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RequestRateLimit {
+	/// Maximum number of requests per second
+	pub requests_per_second: f64,
+	/// Number of requests that may be sent at once after the endpoint was idle,
+	/// `requests_per_second` if not set
+	#[serde(default)]
+	pub burst: Option<u32>,
+}
+
+/// Number of the requests delayed by the rate limits of the endpoints of a chain, per endpoint,
+/// since they were last reported. Shared by the limiters of the endpoints.
+#[derive(Debug, Clone, Default)]
+pub struct ThrottledRequests(Arc<Mutex<HashMap<String, u64>>>);
+
+impl ThrottledRequests {
+	pub fn record(&self, endpoint: &str) {
+		*self.0.lock().unwrap().entry(endpoint.to_string()).or_default() += 1;
+	}
+
+	/// Returns the counts, resetting them.
+	pub fn take(&self) -> Vec<(String, u64)> {
+		self.0.lock().unwrap().drain().collect()
+	}
+}
+
+/// Rate limiter of the requests to an endpoint, shared by the clones of the client.
+#[derive(Debug, Clone)]
+pub struct RequestLimiter {
+	endpoint: String,
+	/// Held while a request waits, so that the requests are served in order
+	bucket: Option<Arc<AsyncMutex<Bucket>>>,
+	throttled: ThrottledRequests,
+}
+
+impl RequestLimiter {
+	pub fn new(
+		endpoint: impl ToString,
+		limit: Option<RequestRateLimit>,
+		throttled: ThrottledRequests,
+	) -> Self {
+		let bucket = limit.filter(|limit| limit.requests_per_second > 0.0).map(|limit| {
+			let burst = limit.burst.map_or(limit.requests_per_second.ceil(), |burst| burst as f64);
+			let period = Duration::from_secs_f64(burst.max(1.0) / limit.requests_per_second);
+			Arc::new(AsyncMutex::new(Bucket::new(burst as u64, period)))
+		});
+		Self { endpoint: endpoint.to_string(), bucket, throttled }
+	}
+
+	/// Waits until `cost` requests may be sent to the endpoint, and counts them against the
+	/// limit.
+	pub async fn acquire(&self, cost: u64) {
+		let Some(bucket) = &self.bucket else { return };
+		let mut bucket = bucket.lock().await;
+		let wait = bucket.wait_time(cost);
+		if !wait.is_zero() {
+			self.throttled.record(&self.endpoint);
+			log::trace!(target: "hyperspace", "Request rate limit of {} reached, waiting {wait:?}", self.endpoint);
+			tokio::time::sleep(wait).await;
+		}
+		bucket.take(cost);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn full_buckets_dont_wait() {
+		let mut bucket = Bucket::new(10, Duration::from_secs(1));
+		assert_eq!(bucket.wait_time(10), Duration::ZERO);
+	}
+
+	#[test]
+	fn empty_buckets_wait_for_the_refill() {
+		let mut bucket = Bucket::new(10, Duration::from_secs(1));
+		bucket.take(10);
+		// a token refills every 100ms
+		let wait = bucket.wait_time(1);
+		assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100), "{wait:?}");
+		let wait = bucket.wait_time(5);
+		assert!(
+			wait > Duration::from_millis(490) && wait <= Duration::from_millis(500),
+			"{wait:?}"
+		);
+	}
+
+	#[test]
+	fn buckets_refill_up_to_their_capacity() {
+		let mut bucket = Bucket::new(10, Duration::from_millis(10));
+		bucket.take(10);
+		std::thread::sleep(Duration::from_millis(50));
+		bucket.refill();
+		assert_eq!(bucket.tokens, 10.0);
+	}
+
+	#[test]
+	fn costs_over_the_capacity_wait_for_a_full_bucket() {
+		let mut bucket = Bucket::new(10, Duration::from_secs(1));
+		assert_eq!(bucket.wait_time(100), Duration::ZERO);
+		bucket.take(100);
+		assert!(bucket.tokens < 1.0);
+		assert!(bucket.wait_time(100) > Duration::from_millis(900));
+	}
+
+	#[test]
+	fn zero_capacities_hold_one_token() {
+		let mut bucket = Bucket::new(0, Duration::from_secs(1));
+		assert_eq!(bucket.wait_time(1), Duration::ZERO);
+		bucket.take(1);
+		assert!(!bucket.wait_time(1).is_zero());
+	}
+
+	#[test]
+	fn throttled_requests_are_reset_when_taken() {
+		let throttled = ThrottledRequests::default();
+		throttled.record("a");
+		throttled.record("a");
+		throttled.record("b");
+		let mut counts = throttled.take();
+		counts.sort();
+		assert_eq!(counts, vec![("a".to_string(), 2), ("b".to_string(), 1)]);
+		assert!(throttled.take().is_empty());
+	}
+}
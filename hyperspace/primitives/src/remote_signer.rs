@@ -0,0 +1,342 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing service holding the keys of the relayer outside of its process.
+//!
+//! With a `remote_signer` in the config of a chain, the transactions of the relayer are signed by
+//! a service reached over a socket, in the manner of tmkms or horcrux: the relayer never holds
+//! the key, and the service can refuse the requests its own policy doesn't allow, e.g. for the
+//! chains or the messages it doesn't sign for. The service is asked for the public key of the
+//! relayer when the client starts, to derive the account of the relayer.
+//!
+//! The requests and the responses are JSON objects of a line each, one request per connection:
+//!
+//! ```text
+//! {"id":1,"key_id":"relayer","scheme":"secp256k1","method":"public_key"}
+//! {"id":2,"key_id":"relayer","scheme":"secp256k1","method":"sign","chain_id":"osmosis-1","payload":"0a9c..."}
+//! ```
+//!
+//! The service responds with `{"id":2,"result":"<hex>"}`, the public key or the signature, or with
+//! `{"id":2,"error":"<reason>"}` when it refuses the request. The payload is the encoded cosmos
+//! `SignDoc`, or the signer payload of an extrinsic, which the service signs as the keys of the
+//! [`SignatureScheme`] do.
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{TcpStream, ToSocketAddrs},
+	sync::atomic::{AtomicU64, Ordering},
+	time::Duration,
+};
+
+fn default_timeout_secs() -> u64 {
+	10
+}
+
+/// Remote signing service of the relayer key of a chain, see [`crate::remote_signer`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteSignerConfig {
+	/// Address of the service, `tcp://<host>:<port>` or `unix://<path>`
+	pub address: String,
+	/// Identifier of the relayer key in the service
+	pub key_id: String,
+	/// How long a request waits for the response of the service, in seconds
+	#[serde(default = "default_timeout_secs")]
+	pub timeout_secs: u64,
+}
+
+/// Scheme of the signatures requested from the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureScheme {
+	/// secp256k1 over the SHA-256 hash of the payload, the keys of the cosmos SDK. The signature
+	/// is the 64 bytes of `r` and `s`
+	Secp256k1,
+	/// secp256k1 over the Keccak-256 hash of the payload, the keys of the Ethermint chains
+	EthSecp256k1,
+	/// The sr25519 keys of substrate
+	Sr25519,
+	/// The ed25519 keys of substrate
+	Ed25519,
+	/// secp256k1 over the BLAKE2b-256 hash of the payload, the ECDSA keys of substrate. The
+	/// signature is the 65 bytes of the recoverable signature
+	Ecdsa,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum Method<'a> {
+	PublicKey,
+	Sign { chain_id: &'a str, payload: String },
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+	id: u64,
+	key_id: &'a str,
+	scheme: SignatureScheme,
+	#[serde(flatten)]
+	method: Method<'a>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+	id: u64,
+	#[serde(default)]
+	result: Option<String>,
+	#[serde(default)]
+	error: Option<String>,
+}
+
+/// Client of the remote signing service. The requests block until the service responds, or
+/// `timeout_secs` passed.
+#[derive(Debug)]
+pub struct RemoteSigner {
+	config: RemoteSignerConfig,
+	scheme: SignatureScheme,
+	next_id: AtomicU64,
+}
+
+impl RemoteSigner {
+	pub fn new(config: RemoteSignerConfig, scheme: SignatureScheme) -> Self {
+		Self { config, scheme, next_id: AtomicU64::new(0) }
+	}
+
+	pub fn key_id(&self) -> &str {
+		&self.config.key_id
+	}
+
+	pub fn scheme(&self) -> SignatureScheme {
+		self.scheme
+	}
+
+	/// Public key of the relayer, in the encoding of the scheme (compressed for secp256k1)
+	pub fn public_key(&self) -> Result<Vec<u8>, Error> {
+		self.request(Method::PublicKey)
+	}
+
+	/// Signs the payload of a transaction of the given chain.
+	pub fn sign(&self, chain_id: &str, payload: &[u8]) -> Result<Vec<u8>, Error> {
+		self.request(Method::Sign { chain_id, payload: hex::encode(payload) })
+	}
+
+	fn request(&self, method: Method) -> Result<Vec<u8>, Error> {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		let request = Request { id, key_id: &self.config.key_id, scheme: self.scheme, method };
+		let mut line = serde_json::to_vec(&request)
+			.map_err(|e| Error::Custom(format!("Failed to encode the signing request: {e}")))?;
+		line.push(b'\n');
+		let response = self.exchange(&line).map_err(|e| {
+			Error::Custom(format!("Remote signer {} unreachable: {e}", self.config.address))
+		})?;
+		let response: Response = serde_json::from_str(&response).map_err(|e| {
+			Error::Custom(format!(
+				"Invalid response of the remote signer {}: {e}",
+				self.config.address
+			))
+		})?;
+		if response.id != id {
+			return Err(Error::Custom(format!(
+				"The remote signer {} responded to request {} instead of {id}",
+				self.config.address, response.id
+			)))
+		}
+		match (response.result, response.error) {
+			(_, Some(error)) => Err(Error::Custom(format!(
+				"The remote signer refused the request for key {}: {error}",
+				self.config.key_id
+			))),
+			(Some(result), None) => Ok(hex::decode(result)?),
+			(None, None) => Err(Error::Custom(format!(
+				"Empty response of the remote signer {}",
+				self.config.address
+			))),
+		}
+	}
+
+	fn exchange(&self, request: &[u8]) -> io::Result<String> {
+		let timeout = Duration::from_secs(self.config.timeout_secs);
+		if let Some(path) = self.config.address.strip_prefix("unix://") {
+			#[cfg(unix)]
+			{
+				let stream = std::os::unix::net::UnixStream::connect(path)?;
+				stream.set_read_timeout(Some(timeout))?;
+				stream.set_write_timeout(Some(timeout))?;
+				return round_trip(stream, request)
+			}
+			#[cfg(not(unix))]
+			return Err(io::Error::new(
+				io::ErrorKind::Unsupported,
+				format!("unix sockets aren't supported on this platform: {path}"),
+			))
+		}
+		let address = self.config.address.strip_prefix("tcp://").unwrap_or(&self.config.address);
+		let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+			io::Error::new(io::ErrorKind::InvalidInput, format!("no address for {address}"))
+		})?;
+		let stream = TcpStream::connect_timeout(&address, timeout)?;
+		stream.set_read_timeout(Some(timeout))?;
+		stream.set_write_timeout(Some(timeout))?;
+		round_trip(stream, request)
+	}
+}
+
+fn round_trip<S: Read + Write>(mut stream: S, request: &[u8]) -> io::Result<String> {
+	stream.write_all(request)?;
+	stream.flush()?;
+	let mut response = String::new();
+	BufReader::new(stream).read_line(&mut response)?;
+	Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::{json, Value};
+	use std::{net::TcpListener, sync::mpsc, thread};
+
+	fn signer(address: String, timeout_secs: u64) -> RemoteSigner {
+		let config = RemoteSignerConfig { address, key_id: "relayer".to_string(), timeout_secs };
+		RemoteSigner::new(config, SignatureScheme::Secp256k1)
+	}
+
+	/// Serves the requests on a local port with the responses `respond` returns for them, if
+	/// any, sending the requests to the receiver.
+	fn serve(
+		respond: impl Fn(&Value) -> Option<Value> + Send + 'static,
+	) -> (String, mpsc::Receiver<Value>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = format!("tcp://{}", listener.local_addr().unwrap());
+		let (sender, receiver) = mpsc::channel();
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let mut stream = stream.unwrap();
+				let mut line = String::new();
+				BufReader::new(&stream).read_line(&mut line).unwrap();
+				let request: Value = serde_json::from_str(&line).unwrap();
+				match respond(&request) {
+					Some(response) => writeln!(stream, "{response}").unwrap(),
+					// keep the connection open without responding
+					None => thread::sleep(Duration::from_secs(5)),
+				}
+				let _ = sender.send(request);
+			}
+		});
+		(address, receiver)
+	}
+
+	fn assert_error<T: std::fmt::Debug>(result: Result<T, Error>, reason: &str) {
+		let e = result.unwrap_err().to_string();
+		assert!(e.contains(reason), "{e}");
+	}
+
+	#[test]
+	fn signatures_are_requested_from_the_service() {
+		let (address, requests) =
+			serve(|request| Some(json!({ "id": request["id"], "result": "0a0b" })));
+		let signature = signer(address, 10).sign("osmosis-1", &[1, 2]).unwrap();
+		assert_eq!(signature, vec![0x0a, 0x0b]);
+		assert_eq!(
+			requests.recv().unwrap(),
+			json!({
+				"id": 0,
+				"key_id": "relayer",
+				"scheme": "secp256k1",
+				"method": "sign",
+				"chain_id": "osmosis-1",
+				"payload": "0102",
+			})
+		);
+	}
+
+	#[test]
+	fn the_requests_have_increasing_ids() {
+		let (address, requests) =
+			serve(|request| Some(json!({ "id": request["id"], "result": "02" })));
+		let signer = signer(address, 10);
+		for id in 0..3 {
+			assert_eq!(signer.public_key().unwrap(), vec![2]);
+			assert_eq!(requests.recv().unwrap()["id"], id);
+		}
+	}
+
+	#[test]
+	fn responses_to_other_requests_are_rejected() {
+		let (address, _requests) = serve(|_| Some(json!({ "id": 7, "result": "0a0b" })));
+		assert_error(
+			signer(address, 10).sign("osmosis-1", &[1]),
+			"responded to request 7 instead of 0",
+		);
+	}
+
+	#[test]
+	fn refusals_of_the_service_are_returned() {
+		let (address, _requests) =
+			serve(|request| Some(json!({ "id": request["id"], "error": "chain not allowed" })));
+		assert_error(
+			signer(address, 10).sign("osmosis-1", &[1]),
+			"refused the request for key relayer: chain not allowed",
+		);
+	}
+
+	#[test]
+	fn empty_and_invalid_responses_are_rejected() {
+		let (address, _requests) = serve(|request| Some(json!({ "id": request["id"] })));
+		assert_error(signer(address, 10).sign("osmosis-1", &[1]), "Empty response");
+
+		let (address, _requests) =
+			serve(|request| Some(json!({ "id": request["id"], "result": "not hex" })));
+		assert!(signer(address, 10).sign("osmosis-1", &[1]).is_err());
+
+		let (address, _requests) = serve(|_| Some(json!("signed")));
+		assert_error(signer(address, 10).sign("osmosis-1", &[1]), "Invalid response");
+	}
+
+	#[test]
+	fn requests_time_out_without_a_response() {
+		let (address, _requests) = serve(|_| None);
+		let started = std::time::Instant::now();
+		assert_error(signer(address, 1).sign("osmosis-1", &[1]), "unreachable");
+		let elapsed = started.elapsed();
+		assert!(
+			elapsed >= Duration::from_secs(1) && elapsed < Duration::from_secs(4),
+			"{elapsed:?}"
+		);
+	}
+
+	#[test]
+	fn unreachable_services_are_reported() {
+		let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		assert_error(signer(format!("tcp://{address}"), 1).public_key(), "unreachable");
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn services_are_reached_over_unix_sockets() {
+		let path = std::env::temp_dir()
+			.join(format!("hyperspace-signer-{:016x}.sock", rand::random::<u64>()));
+		let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+		thread::spawn(move || {
+			let (mut stream, _) = listener.accept().unwrap();
+			let mut line = String::new();
+			BufReader::new(&stream).read_line(&mut line).unwrap();
+			let request: Value = serde_json::from_str(&line).unwrap();
+			writeln!(stream, "{}", json!({ "id": request["id"], "result": "03" })).unwrap();
+		});
+		let signer = signer(format!("unix://{}", path.display()), 10);
+		assert_eq!(signer.public_key().unwrap(), vec![3]);
+		std::fs::remove_file(path).unwrap();
+	}
+}
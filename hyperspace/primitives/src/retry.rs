@@ -0,0 +1,287 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retries of the failed queries and transaction submissions.
+//!
+//! The errors of the clients are mostly strings, so they are classified by their message: only
+//! the classes listed in [`RetryPolicy::retryable`] are retried, other errors are returned
+//! immediately.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, future::Future, time::Duration};
+
+/// Classes of errors that may be retried.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+	/// The connection to the node failed or was dropped
+	Network,
+	/// The request timed out
+	Timeout,
+	/// The node rejected the request because of too many concurrent requests
+	RateLimit,
+	/// The transaction was rejected because of a conflicting nonce (or account sequence)
+	Nonce,
+	/// The transaction ran out of gas (or weight)
+	OutOfGas,
+	/// The fee of the transaction is too low, or the account can't pay it
+	InsufficientFee,
+	/// The client of the counterparty the messages are verified with expired or is frozen
+	ClientExpired,
+	/// The packets of the transaction were already relayed
+	Redundant,
+	/// Any other error
+	Other,
+}
+
+impl ErrorClass {
+	/// Classifies an error by its message.
+	pub fn of(error: &impl Display) -> Self {
+		let message = error.to_string().to_lowercase();
+		let matches = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+		if matches(&["maxslotsexceeded", "too many requests", "rate limit", "resource exhausted"]) {
+			ErrorClass::RateLimit
+		} else if matches(&[
+			"account sequence mismatch",
+			"incorrect account sequence",
+			"priority is too low",
+			"transaction is outdated",
+			"will be valid in the future",
+			"temporarily banned",
+			"invalid nonce",
+		]) {
+			ErrorClass::Nonce
+		} else if matches(&["out of gas"]) {
+			ErrorClass::OutOfGas
+		} else if matches(&["insufficient fee", "insufficient funds"]) {
+			ErrorClass::InsufficientFee
+		} else if matches(&["client expired", "client is not active", "client state is not active"])
+		{
+			ErrorClass::ClientExpired
+		} else if matches(&["packet already relayed", "packet messages are redundant"]) {
+			ErrorClass::Redundant
+		} else if matches(&["timeout", "timed out", "deadline exceeded"]) {
+			ErrorClass::Timeout
+		} else if matches(&[
+			"connection refused",
+			"connection reset",
+			"connection closed",
+			"broken pipe",
+			"transport error",
+			"error trying to connect",
+			"networking or low-level protocol error",
+			"restart required",
+			"status: unavailable",
+			"websocket",
+		]) {
+			ErrorClass::Network
+		} else {
+			ErrorClass::Other
+		}
+	}
+
+	/// Name of the class, as in the config.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ErrorClass::Network => "network",
+			ErrorClass::Timeout => "timeout",
+			ErrorClass::RateLimit => "rate_limit",
+			ErrorClass::Nonce => "nonce",
+			ErrorClass::OutOfGas => "out_of_gas",
+			ErrorClass::InsufficientFee => "insufficient_fee",
+			ErrorClass::ClientExpired => "client_expired",
+			ErrorClass::Redundant => "redundant",
+			ErrorClass::Other => "other",
+		}
+	}
+}
+
+fn default_max_attempts() -> u32 {
+	5
+}
+
+fn default_base_delay_ms() -> u64 {
+	500
+}
+
+fn default_multiplier() -> f64 {
+	2.0
+}
+
+fn default_max_delay_ms() -> u64 {
+	30_000
+}
+
+fn default_jitter() -> f64 {
+	0.2
+}
+
+fn default_retryable() -> Vec<ErrorClass> {
+	vec![ErrorClass::Network, ErrorClass::Timeout, ErrorClass::RateLimit, ErrorClass::Nonce]
+}
+
+/// Retries of the queries and transaction submissions of a chain, with an exponential backoff.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryPolicy {
+	/// Maximum number of attempts, including the first one. `1` disables the retries.
+	#[serde(default = "default_max_attempts")]
+	pub max_attempts: u32,
+	/// Delay before the first retry, in milliseconds
+	#[serde(default = "default_base_delay_ms")]
+	pub base_delay_ms: u64,
+	/// Factor the delay is multiplied by after each retry
+	#[serde(default = "default_multiplier")]
+	pub multiplier: f64,
+	/// Maximum delay between two attempts, in milliseconds
+	#[serde(default = "default_max_delay_ms")]
+	pub max_delay_ms: u64,
+	/// Fraction of the delay that is randomized, so that the clones of a client don't retry in
+	/// lockstep. `0.2` waits between 80% and 120% of the delay.
+	#[serde(default = "default_jitter")]
+	pub jitter: f64,
+	/// Classes of errors that are retried
+	#[serde(default = "default_retryable")]
+	pub retryable: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: default_max_attempts(),
+			base_delay_ms: default_base_delay_ms(),
+			multiplier: default_multiplier(),
+			max_delay_ms: default_max_delay_ms(),
+			jitter: default_jitter(),
+			retryable: default_retryable(),
+		}
+	}
+}
+
+impl RetryPolicy {
+	pub fn is_retryable(&self, error: &impl Display) -> bool {
+		self.retryable.contains(&ErrorClass::of(error))
+	}
+
+	/// Delay before the given retry, starting from `1`.
+	pub fn delay(&self, retry: u32) -> Duration {
+		let delay = (self.base_delay_ms as f64 * self.multiplier.max(1.0).powi(retry as i32 - 1))
+			.min(self.max_delay_ms as f64);
+		let jitter = self.jitter.clamp(0.0, 1.0);
+		let factor = if jitter > 0.0 {
+			rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter)
+		} else {
+			1.0
+		};
+		Duration::from_millis((delay * factor) as u64)
+	}
+
+	/// Runs `f` until it succeeds, fails with an error that isn't retryable or the maximum number
+	/// of attempts is reached.
+	pub async fn retry<T, E, F, Fut>(&self, name: &str, mut f: F) -> Result<T, E>
+	where
+		E: Display,
+		F: FnMut() -> Fut,
+		Fut: Future<Output = Result<T, E>>,
+	{
+		let mut attempt = 1;
+		loop {
+			match f().await {
+				Ok(value) => return Ok(value),
+				Err(e) if attempt < self.max_attempts && self.is_retryable(&e) => {
+					let delay = self.delay(attempt);
+					log::warn!(
+						target: "hyperspace",
+						"{name} failed ({:?}): {e}, retrying in {delay:?} ({attempt}/{})",
+						ErrorClass::of(&e), self.max_attempts
+					);
+					tokio::time::sleep(delay).await;
+					attempt += 1;
+				},
+				Err(e) => return Err(e),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn errors_are_classified_by_their_message() {
+		let cases = [
+			("RPC error: MaxSlotsExceeded", ErrorClass::RateLimit),
+			("status: ResourceExhausted, message: \"resource exhausted\"", ErrorClass::RateLimit),
+			("account sequence mismatch, expected 5, got 4", ErrorClass::Nonce),
+			("Transaction is temporarily banned", ErrorClass::Nonce),
+			("out of gas in location: ReadFlat", ErrorClass::OutOfGas),
+			("insufficient fees; got: 10uatom required: 20uatom", ErrorClass::InsufficientFee),
+			("client state is not active", ErrorClass::ClientExpired),
+			("packet messages are redundant", ErrorClass::Redundant),
+			("request timed out", ErrorClass::Timeout),
+			("status: DeadlineExceeded, message: \"deadline exceeded\"", ErrorClass::Timeout),
+			("error trying to connect: Connection refused", ErrorClass::Network),
+			("status: Unavailable, message: \"transport error\"", ErrorClass::Network),
+			("invalid proof", ErrorClass::Other),
+		];
+		for (message, class) in cases {
+			assert_eq!(ErrorClass::of(&message), class, "{message}");
+		}
+	}
+
+	#[test]
+	fn rate_limits_take_precedence_over_timeouts() {
+		assert_eq!(ErrorClass::of(&"rate limit reached, request timed out"), ErrorClass::RateLimit);
+	}
+
+	#[test]
+	fn only_the_retryable_classes_are_retried() {
+		let policy = RetryPolicy::default();
+		assert!(policy.is_retryable(&"connection reset by peer"));
+		assert!(!policy.is_retryable(&"invalid proof"));
+		let policy = RetryPolicy { retryable: vec![ErrorClass::Other], ..Default::default() };
+		assert!(policy.is_retryable(&"invalid proof"));
+		assert!(!policy.is_retryable(&"connection reset by peer"));
+	}
+
+	#[test]
+	fn delays_grow_exponentially_up_to_the_maximum() {
+		let policy = RetryPolicy {
+			base_delay_ms: 100,
+			multiplier: 2.0,
+			max_delay_ms: 1000,
+			jitter: 0.0,
+			..Default::default()
+		};
+		let delays = (1..=6).map(|retry| policy.delay(retry).as_millis()).collect::<Vec<_>>();
+		assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+	}
+
+	#[test]
+	fn delays_are_jittered_within_the_fraction() {
+		let policy = RetryPolicy { base_delay_ms: 1000, jitter: 0.2, ..Default::default() };
+		for _ in 0..100 {
+			let delay = policy.delay(1).as_millis();
+			assert!((800..=1200).contains(&delay), "{delay}");
+		}
+	}
+
+	#[test]
+	fn multipliers_below_one_dont_shrink_the_delay() {
+		let policy =
+			RetryPolicy { base_delay_ms: 100, multiplier: 0.5, jitter: 0.0, ..Default::default() };
+		assert_eq!(policy.delay(3), Duration::from_millis(100));
+	}
+}
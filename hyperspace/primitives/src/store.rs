@@ -0,0 +1,358 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent state of the relayer, so that a restarted relayer resumes where it stopped.
+//!
+//! The store records the last finality height processed from each chain, and the packet
+//! messages delivered to each chain. Delivered messages are skipped for [`DELIVERED_TTL`] after
+//! their submission, so that a restart doesn't submit them again while the counterparty state
+//! the relayer queries still lags behind.
+//!
+//! The [`OperationLog`] is a separate database, recording the control operations of the CLI by
+//! idempotency key, so that the automation retrying them doesn't run them twice.
+
+use crate::MessageKind;
+use ibc::{
+	core::{
+		ics04_channel::{
+			msgs::{
+				acknowledgement::MsgAcknowledgement, recv_packet::MsgRecvPacket,
+				timeout::MsgTimeout, timeout_on_close::MsgTimeoutOnClose,
+			},
+			packet::Packet,
+		},
+		ics24_host::identifier::{ChannelId, PortId},
+	},
+	protobuf::Protobuf,
+};
+use ibc_proto::google::protobuf::Any;
+use serde::{Deserialize, Serialize};
+use std::{
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Time during which a delivered message isn't submitted again.
+pub const DELIVERED_TTL: Duration = Duration::from_secs(60 * 60);
+
+const HEIGHTS_TREE: &str = "processed_heights";
+const DELIVERED_TREE: &str = "delivered_sequences";
+
+/// Handle to the store of a single chain. Clones share the same database.
+#[derive(Clone)]
+pub struct RelayerStore {
+	db: sled::Db,
+	/// Prefix of the keys of the chain, so that several chains (or several paths relaying the same
+	/// chain) can share a database
+	scope: String,
+}
+
+impl std::fmt::Debug for RelayerStore {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("RelayerStore").field("scope", &self.scope).finish()
+	}
+}
+
+impl RelayerStore {
+	/// Opens the database at the given path, creating it if needed, and prunes the delivered
+	/// messages older than [`DELIVERED_TTL`].
+	pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+		let store = Self { db: sled::open(path)?, scope: String::new() };
+		store.prune()?;
+		Ok(store)
+	}
+
+	/// Returns a handle to the keys of the given chain.
+	pub fn scoped(&self, scope: &str) -> Self {
+		Self { db: self.db.clone(), scope: scope.to_string() }
+	}
+
+	/// Last finality height processed from the chain.
+	pub fn processed_height(&self) -> anyhow::Result<Option<u64>> {
+		Ok(self
+			.db
+			.open_tree(HEIGHTS_TREE)?
+			.get(&self.scope)?
+			.map(|bytes| decode_u64(&bytes)))
+	}
+
+	/// Records a processed finality height, unless a higher one is recorded already.
+	pub fn set_processed_height(&self, height: u64) -> anyhow::Result<()> {
+		self.db.open_tree(HEIGHTS_TREE)?.fetch_and_update(&self.scope, |old| {
+			let old = old.map(decode_u64).unwrap_or_default();
+			Some(old.max(height).to_be_bytes().to_vec())
+		})?;
+		Ok(())
+	}
+
+	/// Drops the messages that were delivered to the chain less than [`DELIVERED_TTL`] ago.
+	pub fn filter_delivered(&self, messages: Vec<Any>) -> anyhow::Result<Vec<Any>> {
+		let tree = self.db.open_tree(DELIVERED_TREE)?;
+		let now = unix_secs();
+		let mut undelivered = vec![];
+		for message in messages {
+			let delivered_at = match self.key(&message) {
+				Some(key) => tree.get(key)?.map(|bytes| decode_u64(&bytes)),
+				None => None,
+			};
+			match delivered_at {
+				Some(at) if now.saturating_sub(at) < DELIVERED_TTL.as_secs() => log::debug!(
+					target: "hyperspace",
+					"Skipping {} already delivered to {}", message.type_url, self.scope
+				),
+				_ => undelivered.push(message),
+			}
+		}
+		Ok(undelivered)
+	}
+
+	/// Records the packet messages of a submitted batch as delivered.
+	pub fn mark_delivered(&self, messages: &[Any]) -> anyhow::Result<()> {
+		let tree = self.db.open_tree(DELIVERED_TREE)?;
+		let now = unix_secs().to_be_bytes();
+		for key in messages.iter().filter_map(|message| self.key(message)) {
+			tree.insert(key, &now[..])?;
+		}
+		tree.flush()?;
+		Ok(())
+	}
+
+	/// Forgets that the messages of the given kind were delivered for the packets sent with these
+	/// sequences on the given channel, so that they're submitted again.
+	pub fn forget_delivered(
+		&self,
+		kind: MessageKind,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		sequences: &[u64],
+	) -> anyhow::Result<()> {
+		let tree = self.db.open_tree(DELIVERED_TREE)?;
+		for sequence in sequences {
+			tree.remove(self.packet_key(kind, port_id, channel_id, *sequence))?;
+		}
+		Ok(())
+	}
+
+	/// Writes the pending changes to disk.
+	pub fn flush(&self) -> anyhow::Result<()> {
+		self.db.flush()?;
+		Ok(())
+	}
+
+	fn prune(&self) -> anyhow::Result<()> {
+		let tree = self.db.open_tree(DELIVERED_TREE)?;
+		let now = unix_secs();
+		for entry in tree.iter() {
+			let (key, value) = entry?;
+			if now.saturating_sub(decode_u64(&value)) >= DELIVERED_TTL.as_secs() {
+				tree.remove(key)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Key of a packet message, `None` if it isn't a packet message.
+	fn key(&self, message: &Any) -> Option<Vec<u8>> {
+		let kind = MessageKind::of(message)?;
+		let packet = decode_packet(message)?;
+		Some(self.packet_key(
+			kind,
+			&packet.source_port,
+			&packet.source_channel,
+			packet.sequence.into(),
+		))
+	}
+
+	fn packet_key(
+		&self,
+		kind: MessageKind,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		sequence: u64,
+	) -> Vec<u8> {
+		let kind = match kind {
+			MessageKind::Timeout => "timeout",
+			MessageKind::RecvPacket => "recv_packet",
+			MessageKind::Acknowledgement => "acknowledgement",
+		};
+		format!("{}/{kind}/{port_id}/{channel_id}/{sequence}", self.scope).into_bytes()
+	}
+}
+
+/// Status of a control operation in the [`OperationLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+	/// The operation is running, or its process stopped before it completed
+	Started,
+	Completed,
+	Failed,
+}
+
+/// A control operation recorded in the [`OperationLog`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationRecord {
+	/// Name of the command, e.g. `clear-packets`
+	pub command: String,
+	/// Arguments of the command, the retries of the operation must pass the same ones
+	pub args: String,
+	pub status: OperationStatus,
+	/// Unix time in seconds at which the last attempt of the operation started
+	pub started_at: u64,
+	/// Unix time in seconds at which the last attempt of the operation completed or failed
+	pub finished_at: Option<u64>,
+	/// Error of the last attempt, if it failed
+	pub error: Option<String>,
+}
+
+/// Log of the control operations of the CLI (e.g. `clear-packets`), by idempotency key. An
+/// operation that completed isn't run again under the same key, while the ones that failed or
+/// were interrupted are attempted again. The database is locked by the process that opened it,
+/// so that two retries of an operation can't run at once.
+pub struct OperationLog {
+	db: sled::Db,
+}
+
+impl OperationLog {
+	/// Opens the database at the given path, creating it if needed.
+	pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+		Ok(Self { db: sled::open(path)? })
+	}
+
+	/// Records the start of an operation under its idempotency key. Returns the record of the
+	/// operation if it already completed under this key, in which case it mustn't run again.
+	/// Fails if the key was used for another operation.
+	pub fn begin(
+		&self,
+		key: &str,
+		command: &str,
+		args: &str,
+	) -> anyhow::Result<Option<OperationRecord>> {
+		if let Some(record) = self.get(key)? {
+			if record.command != command || record.args != args {
+				anyhow::bail!(
+					"Idempotency key {key} was used for another operation: {} {}",
+					record.command,
+					record.args
+				)
+			}
+			if record.status == OperationStatus::Completed {
+				return Ok(Some(record))
+			}
+		}
+		let record = OperationRecord {
+			command: command.to_string(),
+			args: args.to_string(),
+			status: OperationStatus::Started,
+			started_at: unix_secs(),
+			finished_at: None,
+			error: None,
+		};
+		self.put(key, &record)?;
+		Ok(None)
+	}
+
+	/// Records the outcome of an operation started with [`OperationLog::begin`].
+	pub fn finish(&self, key: &str, result: Result<(), String>) -> anyhow::Result<()> {
+		let mut record = self
+			.get(key)?
+			.ok_or_else(|| anyhow::anyhow!("Operation {key} was never started"))?;
+		record.finished_at = Some(unix_secs());
+		(record.status, record.error) = match result {
+			Ok(()) => (OperationStatus::Completed, None),
+			Err(e) => (OperationStatus::Failed, Some(e)),
+		};
+		self.put(key, &record)
+	}
+
+	/// Record of the operation of the given idempotency key, if any.
+	pub fn get(&self, key: &str) -> anyhow::Result<Option<OperationRecord>> {
+		self.db.get(key)?.map(|bytes| Ok(serde_json::from_slice(&bytes)?)).transpose()
+	}
+
+	fn put(&self, key: &str, record: &OperationRecord) -> anyhow::Result<()> {
+		self.db.insert(key, serde_json::to_vec(record)?)?;
+		self.db.flush()?;
+		Ok(())
+	}
+}
+
+/// Decodes the packet of a packet message.
+pub fn decode_packet(message: &Any) -> Option<Packet> {
+	let packet = match message.type_url.as_str() {
+		"/ibc.core.channel.v1.MsgRecvPacket" =>
+			MsgRecvPacket::decode_vec(&message.value).ok()?.packet,
+		"/ibc.core.channel.v1.MsgAcknowledgement" =>
+			MsgAcknowledgement::decode_vec(&message.value).ok()?.packet,
+		"/ibc.core.channel.v1.MsgTimeout" => MsgTimeout::decode_vec(&message.value).ok()?.packet,
+		"/ibc.core.channel.v1.MsgTimeoutOnClose" =>
+			MsgTimeoutOnClose::decode_vec(&message.value).ok()?.packet,
+		_ => return None,
+	};
+	Some(packet)
+}
+
+fn decode_u64(bytes: &[u8]) -> u64 {
+	bytes.try_into().map(u64::from_be_bytes).unwrap_or_default()
+}
+
+fn unix_secs() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn operation_log() -> OperationLog {
+		OperationLog { db: sled::Config::new().temporary(true).open().unwrap() }
+	}
+
+	#[test]
+	fn completed_operations_are_not_run_again() {
+		let log = operation_log();
+		assert_eq!(log.begin("key", "clear-packets", "channel-0").unwrap(), None);
+		log.finish("key", Ok(())).unwrap();
+
+		let record = log.begin("key", "clear-packets", "channel-0").unwrap().unwrap();
+		assert_eq!(record.status, OperationStatus::Completed);
+		assert!(record.finished_at.is_some());
+		// other keys are independent
+		assert_eq!(log.begin("other", "clear-packets", "channel-0").unwrap(), None);
+	}
+
+	#[test]
+	fn failed_and_interrupted_operations_are_run_again() {
+		let log = operation_log();
+		assert_eq!(log.begin("key", "close-channel", "channel-0").unwrap(), None);
+		// the process stopped before the operation completed
+		assert_eq!(log.begin("key", "close-channel", "channel-0").unwrap(), None);
+		log.finish("key", Err("timed out".to_string())).unwrap();
+		let record = log.get("key").unwrap().unwrap();
+		assert_eq!(record.status, OperationStatus::Failed);
+		assert_eq!(record.error.as_deref(), Some("timed out"));
+
+		assert_eq!(log.begin("key", "close-channel", "channel-0").unwrap(), None);
+		assert_eq!(log.get("key").unwrap().unwrap().status, OperationStatus::Started);
+	}
+
+	#[test]
+	fn keys_are_not_reused_for_other_operations() {
+		let log = operation_log();
+		log.begin("key", "clear-packets", "channel-0").unwrap();
+		assert!(log.begin("key", "clear-packets", "channel-1").is_err());
+		assert!(log.begin("key", "abort-handshake", "channel-0").is_err());
+		assert!(log.finish("unknown", Ok(())).is_err());
+	}
+}
@@ -0,0 +1,110 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Packet messages submitted to a chain while a packet clearing is in progress.
+//!
+//! A submitted message is only dropped from the pending messages queried on the chains once it's
+//! included in a block and the nodes serve the new state, so the clearing round that follows the
+//! submission may find it again. The packet messages submitted during a clearing, by its rounds
+//! or by the finality notifications, are recorded so that the next rounds of the clearing skip
+//! them. The packets whose messages failed on chain are cleared by the next clearing.
+
+use crate::dead_letter::key;
+use ibc_proto::google::protobuf::Any;
+use std::{
+	collections::HashSet,
+	sync::{Arc, Mutex},
+};
+
+/// Packet messages submitted to a chain since the start of the clearing in progress, shared by the
+/// clones of the client. `None` while no clearing is in progress.
+#[derive(Debug, Clone, Default)]
+pub struct SubmittedPackets(Arc<Mutex<Option<HashSet<String>>>>);
+
+impl SubmittedPackets {
+	/// Starts recording the submitted messages, forgetting the ones of the previous clearing.
+	pub fn start(&self) {
+		*self.0.lock().unwrap() = Some(HashSet::new());
+	}
+
+	/// Stops recording the submitted messages.
+	pub fn stop(&self) {
+		*self.0.lock().unwrap() = None;
+	}
+
+	/// Records the packet messages of a submitted bundle, if a clearing is in progress.
+	pub fn record(&self, messages: &[Any]) {
+		if let Some(submitted) = self.0.lock().unwrap().as_mut() {
+			submitted.extend(messages.iter().filter_map(key));
+		}
+	}
+
+	/// Drops the messages submitted since the start of the clearing in progress.
+	pub fn filter(&self, messages: Vec<Any>) -> Vec<Any> {
+		let submitted = self.0.lock().unwrap();
+		let Some(submitted) = submitted.as_ref().filter(|submitted| !submitted.is_empty()) else {
+			return messages
+		};
+		messages
+			.into_iter()
+			.filter(|message| match key(message) {
+				Some(key) if submitted.contains(&key) => {
+					log::debug!(target: "hyperspace", "Skipping message {key} already submitted by this clearing");
+					false
+				},
+				_ => true,
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dead_letter::tests::recv_packet;
+
+	#[test]
+	fn messages_submitted_during_a_clearing_are_skipped() {
+		let submitted = SubmittedPackets::default();
+		submitted.start();
+		submitted.record(&[recv_packet(1)]);
+		let messages = submitted.filter(vec![recv_packet(1), recv_packet(2)]);
+		assert_eq!(messages, vec![recv_packet(2)]);
+	}
+
+	#[test]
+	fn messages_are_only_recorded_while_a_clearing_is_in_progress() {
+		let submitted = SubmittedPackets::default();
+		submitted.record(&[recv_packet(1)]);
+		submitted.start();
+		assert_eq!(submitted.filter(vec![recv_packet(1)]).len(), 1);
+
+		submitted.record(&[recv_packet(1)]);
+		submitted.stop();
+		assert_eq!(submitted.filter(vec![recv_packet(1)]).len(), 1);
+		// the next clearing starts with no submitted messages
+		submitted.start();
+		assert_eq!(submitted.filter(vec![recv_packet(1)]).len(), 1);
+	}
+
+	#[test]
+	fn other_messages_are_kept() {
+		let submitted = SubmittedPackets::default();
+		submitted.start();
+		let update =
+			Any { type_url: "/ibc.core.client.v1.MsgUpdateClient".to_string(), value: vec![] };
+		submitted.record(&[update.clone(), recv_packet(1)]);
+		assert_eq!(submitted.filter(vec![update.clone()]), vec![update]);
+	}
+}
@@ -14,26 +14,39 @@
 
 #[cfg(any(test, feature = "testing"))]
 use crate::TestProvider;
-use crate::{mock::LocalClientTypes, Chain};
+use crate::{
+	apps::ChannelApp,
+	fees::{is_fee_enabled, register_counterparty_payee_msg, register_payee_msg},
+	mock::LocalClientTypes,
+	Chain,
+};
+use anyhow::anyhow;
 use futures::{future, StreamExt};
 use ibc::{
 	core::{
-		ics02_client::msgs::create_client::MsgCreateAnyClient,
-		ics03_connection::{connection::Counterparty, msgs::conn_open_init::MsgConnectionOpenInit},
+		ics02_client::{
+			client_state::ClientState as ClientStateT,
+			msgs::{create_client::MsgCreateAnyClient, upgrade_client::MsgUpgradeAnyClient},
+		},
+		ics03_connection::{
+			connection, connection::Counterparty, msgs::conn_open_init::MsgConnectionOpenInit,
+		},
 		ics04_channel,
 		ics04_channel::{
 			channel,
 			channel::{ChannelEnd, Order, State},
-			msgs::chan_open_init::MsgChannelOpenInit,
+			msgs::{chan_close_init::MsgChannelCloseInit, chan_open_init::MsgChannelOpenInit},
 		},
 		ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
 	},
 	events::IbcEvent,
 	protobuf::Protobuf,
 	tx_msg::Msg,
+	Height,
 };
 use ibc_proto::google::protobuf::Any;
-use std::{future::Future, time::Duration};
+use pallet_ibc::light_clients::AnyClientState;
+use std::{fmt, future::Future, str::FromStr, time::Duration};
 
 pub async fn timeout_future<T: Future>(future: T, secs: u64, reason: String) -> T::Output {
 	let duration = Duration::from_secs(secs);
@@ -150,7 +163,9 @@ pub async fn create_connection(
 }
 
 /// Completes the chanel handshake process
-/// The relayer process must be running before this function is executed
+/// The relayer process must be running before this function is executed. The counterparty port of
+/// the channel is the port of the application on chain B, see [`ChannelApp::counterparty_port`],
+/// or the same port as on chain A.
 pub async fn create_channel(
 	chain_a: &mut impl Chain,
 	chain_b: &mut impl Chain,
@@ -162,7 +177,7 @@ pub async fn create_channel(
 	let channel = ChannelEnd::new(
 		State::Init,
 		order,
-		channel::Counterparty::new(port_id.clone(), None),
+		channel::Counterparty::new(counterparty_port(&port_id), None),
 		vec![connection_id],
 		ics04_channel::Version::new(version),
 	);
@@ -199,3 +214,297 @@ pub async fn create_channel(
 
 	Ok((channel_id_a, channel_id_b))
 }
+
+/// The port of the channel on the counterparty of a channel opened on the given port.
+pub fn counterparty_port(port_id: &PortId) -> PortId {
+	ChannelApp::from_port(port_id)
+		.counterparty_port()
+		.unwrap_or_else(|| port_id.clone())
+}
+
+/// A channel or connection handshake that didn't progress past `INIT` or `TRYOPEN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbandonedHandshake {
+	Channel { channel_id: ChannelId, port_id: PortId, state: State },
+	Connection { connection_id: ConnectionId, state: connection::State },
+}
+
+impl fmt::Display for AbandonedHandshake {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			AbandonedHandshake::Channel { channel_id, port_id, state } =>
+				write!(f, "channel {channel_id}/{port_id} ({state})"),
+			AbandonedHandshake::Connection { connection_id, state } =>
+				write!(f, "connection {connection_id} ({state})"),
+		}
+	}
+}
+
+/// Finds the channels and connections to `counterparty` that are in `INIT` or `TRYOPEN` on
+/// `chain`, and already were `min_age` ago.
+pub async fn find_abandoned_handshakes(
+	chain: &impl Chain,
+	counterparty: &impl Chain,
+	min_age: Duration,
+) -> Result<Vec<AbandonedHandshake>, anyhow::Error> {
+	let (latest_height, _) = chain.latest_height_and_timestamp().await?;
+	let blocks = (min_age.as_millis() / chain.expected_block_time().as_millis().max(1)) as u64;
+	let past_height = latest_height.sub(blocks).ok();
+	let is_handshake =
+		|state: i32| matches!(State::from_i32(state), Ok(State::Init | State::TryOpen));
+
+	let connections = chain
+		.query_connection_using_client(
+			latest_height.revision_height as u32,
+			counterparty.client_id().to_string(),
+		)
+		.await?;
+	let connection_ids =
+		connections.iter().map(|connection| connection.id.clone()).collect::<Vec<_>>();
+
+	let mut abandoned = vec![];
+	for connection in connections {
+		let state = match connection::State::from_i32(connection.state) {
+			Ok(state @ (connection::State::Init | connection::State::TryOpen)) => state,
+			_ => continue,
+		};
+		let connection_id = ConnectionId::from_str(&connection.id)?;
+		let was_pending = match past_height {
+			Some(height) if blocks > 0 => chain
+				.query_connection_end(height, connection_id.clone())
+				.await
+				.ok()
+				.and_then(|response| response.connection)
+				.map_or(false, |end| end.state == state as i32),
+			Some(_) => true,
+			None => false,
+		};
+		if was_pending {
+			abandoned.push(AbandonedHandshake::Connection { connection_id, state });
+		}
+	}
+
+	for (channel_id, port_id) in chain.query_channels().await? {
+		let channel = match chain
+			.query_channel_end(latest_height, channel_id, port_id.clone())
+			.await?
+			.channel
+		{
+			Some(channel) if is_handshake(channel.state) => channel,
+			_ => continue,
+		};
+		if !channel.connection_hops.iter().any(|hop| connection_ids.contains(hop)) {
+			continue
+		}
+		let was_pending = match past_height {
+			Some(height) if blocks > 0 => chain
+				.query_channel_end(height, channel_id, port_id.clone())
+				.await
+				.ok()
+				.and_then(|response| response.channel)
+				.map_or(false, |end| end.state == channel.state),
+			Some(_) => true,
+			None => false,
+		};
+		if was_pending {
+			let state = State::from_i32(channel.state)?;
+			abandoned.push(AbandonedHandshake::Channel { channel_id, port_id, state });
+		}
+	}
+
+	Ok(abandoned)
+}
+
+/// Starts the closing handshake of a channel with a `MsgChannelCloseInit`, which is only
+/// simulated in dry-run mode.
+pub async fn close_channel(
+	chain: &impl Chain,
+	channel_id: ChannelId,
+	port_id: PortId,
+) -> Result<(), anyhow::Error> {
+	let msg = MsgChannelCloseInit { port_id, channel_id, signer: chain.account_id() };
+	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec()? };
+	if chain.common_state().dry_run {
+		chain.simulate(vec![msg]).await?;
+	} else {
+		chain.submit(vec![msg]).await?;
+	}
+	Ok(())
+}
+
+/// Registers the payees of the ICS-29 fees earned by the relayer on both ends of a fee-enabled
+/// channel of chain A. On each chain, the payee of the acknowledgement and timeout fees is
+/// registered if it's not the relayer account, and the payee of the receive fees on the
+/// counterparty is always registered, since it defaults to no payee.
+pub async fn register_payees(
+	chain_a: &impl Chain,
+	chain_b: &impl Chain,
+	channel_id: ChannelId,
+	port_id: PortId,
+) -> Result<(), anyhow::Error> {
+	let (height, _) = chain_a.latest_height_and_timestamp().await?;
+	let channel_end = chain_a
+		.query_channel_end(height, channel_id, port_id.clone())
+		.await?
+		.channel
+		.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} not found on {}", chain_a.name()))
+		.and_then(|channel| ChannelEnd::try_from(channel).map_err(|e| anyhow!("{e:?}")))?;
+	if !is_fee_enabled(&channel_end.version.to_string()) {
+		return Err(anyhow!(
+			"Channel {channel_id}/{port_id} on {} isn't fee-enabled, its version is {}",
+			chain_a.name(),
+			channel_end.version
+		))
+	}
+	let counterparty_port_id = channel_end.counterparty().port_id.clone();
+	let counterparty_channel_id = channel_end
+		.counterparty()
+		.channel_id
+		.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} has no counterparty"))?;
+
+	register_chain_payees(chain_a, chain_b, channel_id, port_id).await?;
+	register_chain_payees(chain_b, chain_a, counterparty_channel_id, counterparty_port_id).await
+}
+
+async fn register_chain_payees(
+	chain: &impl Chain,
+	counterparty: &impl Chain,
+	channel_id: ChannelId,
+	port_id: PortId,
+) -> Result<(), anyhow::Error> {
+	let relayer = chain.account_id().to_string();
+	let mut messages = vec![];
+	if let Some(payee) = chain.common_state().fee_payee.clone().filter(|payee| *payee != relayer) {
+		log::info!(target: "hyperspace", "Registering {payee} as the payee of {relayer} on {channel_id}/{port_id} of {}", chain.name());
+		messages.push(register_payee_msg(&port_id, &channel_id, relayer.clone(), payee));
+	}
+	let counterparty_payee = counterparty
+		.common_state()
+		.fee_payee
+		.clone()
+		.unwrap_or_else(|| counterparty.account_id().to_string());
+	log::info!(target: "hyperspace", "Registering {counterparty_payee} on {} as the counterparty payee of {relayer} on {channel_id}/{port_id} of {}", counterparty.name(), chain.name());
+	messages.push(register_counterparty_payee_msg(
+		&port_id,
+		&channel_id,
+		relayer,
+		counterparty_payee,
+	));
+	if chain.common_state().dry_run {
+		chain.simulate(messages).await?;
+	} else {
+		chain.submit(messages).await?;
+	}
+	Ok(())
+}
+
+/// Upgrades the client of the source hosted on the sink after the upgrade of the source at
+/// `upgrade_height`, the block height in the revision of the client. The client is first updated
+/// to the upgrade height with the finality of the source, which resumes once the source is
+/// upgraded, then the upgraded client and consensus states are submitted with a
+/// `MsgUpgradeClient`.
+pub async fn upgrade_client(
+	source: &mut impl Chain,
+	sink: &impl Chain,
+	upgrade_height: u64,
+) -> Result<(), anyhow::Error> {
+	let client_id = source.client_id();
+	let mut finality = source.finality_notifications().await?;
+	loop {
+		let (sink_height, _) = sink.latest_height_and_timestamp().await?;
+		let client_state = sink
+			.query_client_state(sink_height, client_id.clone())
+			.await?
+			.client_state
+			.ok_or_else(|| anyhow!("Client {client_id} not found on {}", sink.name()))?;
+		let client_height = AnyClientState::try_from(client_state)
+			.map_err(|e| anyhow!("Invalid client state of {client_id}: {e:?}"))?
+			.latest_height();
+		let upgrade_height = Height::new(client_height.revision_number, upgrade_height);
+		if client_height > upgrade_height {
+			return Err(anyhow!(
+				"Client {client_id} on {} is already at {client_height}, past the upgrade at {upgrade_height}",
+				sink.name()
+			))
+		}
+
+		let mut messages = vec![];
+		if client_height < upgrade_height {
+			let finality_event = finality
+				.next()
+				.await
+				.ok_or_else(|| anyhow!("Finality stream of {} ended", source.name()))?;
+			let mut updates = source
+				.query_latest_ibc_events(finality_event, sink)
+				.await?
+				.into_iter()
+				.map(|(update, height, ..)| (update, height))
+				.filter(|(_, height)| *height <= upgrade_height)
+				.collect::<Vec<_>>();
+			match updates.pop() {
+				Some((update, height)) if height == upgrade_height => messages.push(update),
+				Some((update, height)) => {
+					// the upgrade height isn't finalized yet, move the client closer to it
+					log::info!(
+						target: "hyperspace",
+						"Updating {client_id} on {} to {height}, waiting for the upgrade at {upgrade_height}",
+						sink.name()
+					);
+					sink.submit(vec![update]).await?;
+					continue
+				},
+				None => continue,
+			}
+		}
+
+		let upgraded = source.query_upgraded_client(upgrade_height).await?;
+		let msg = MsgUpgradeAnyClient::<LocalClientTypes> {
+			client_id: client_id.clone(),
+			client_state: upgraded.client_state,
+			consensus_state: upgraded.consensus_state,
+			proof_upgrade_client: upgraded.proof_upgrade_client,
+			proof_upgrade_consensus_state: upgraded.proof_upgrade_consensus_state,
+			signer: sink.account_id(),
+		};
+		messages.push(Any { type_url: msg.type_url(), value: msg.encode_vec()? });
+		sink.submit(messages).await?;
+		log::info!(target: "hyperspace", "Upgraded {client_id} on {} at {upgrade_height}", sink.name());
+		return Ok(())
+	}
+}
+
+/// Closes the abandoned channel handshakes with a `MsgChannelCloseInit`, one message per channel
+/// so that a channel the application refuses to close doesn't block the others. IBC has no way
+/// of closing a connection, so the abandoned connections are only reported.
+///
+/// Returns the handshakes that were closed, or that would be closed in dry-run mode.
+pub async fn abort_handshakes(
+	chain: &impl Chain,
+	handshakes: Vec<AbandonedHandshake>,
+) -> Vec<AbandonedHandshake> {
+	let mut closed = vec![];
+	for handshake in handshakes {
+		let (channel_id, port_id) = match &handshake {
+			AbandonedHandshake::Channel { channel_id, port_id, .. } =>
+				(*channel_id, port_id.clone()),
+			AbandonedHandshake::Connection { .. } => {
+				log::warn!(
+					target: "hyperspace",
+					"Abandoned {handshake} on {} can't be closed, ignore it", chain.name()
+				);
+				continue
+			},
+		};
+		match close_channel(chain, channel_id, port_id).await {
+			Ok(()) => {
+				log::info!(target: "hyperspace", "Closed abandoned {handshake} on {}", chain.name());
+				closed.push(handshake);
+			},
+			Err(e) => log::error!(
+				target: "hyperspace",
+				"Failed to close abandoned {handshake} on {}: {e:?}", chain.name()
+			),
+		}
+	}
+	closed
+}
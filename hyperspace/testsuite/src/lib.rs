@@ -18,15 +18,19 @@ use crate::utils::assert_timeout_packet;
 use futures::{future, StreamExt};
 use hyperspace_core::send_packet_relay::set_relay_status;
 use hyperspace_primitives::{
-	utils::{create_channel, create_connection, timeout_after, timeout_future},
+	utils::{
+		abort_handshakes, create_channel, create_connection, find_abandoned_handshakes,
+		timeout_after, timeout_future, AbandonedHandshake,
+	},
 	TestProvider,
 };
 use ibc::{
 	applications::transfer::{msgs::transfer::MsgTransfer, Amount, PrefixedCoin, VERSION},
 	core::{
 		ics04_channel::{
-			channel::{ChannelEnd, Order, State},
-			msgs::chan_close_init::MsgChannelCloseInit,
+			channel::{ChannelEnd, Counterparty, Order, State},
+			msgs::{chan_close_init::MsgChannelCloseInit, chan_open_init::MsgChannelOpenInit},
+			Version,
 		},
 		ics24_host::identifier::{ChannelId, ConnectionId, PortId},
 	},
@@ -618,6 +622,54 @@ pub async fn ibc_messaging_packet_timeout_on_channel_close<A, B>(
 	handle.abort()
 }
 
+/// Leave a channel handshake in INIT, then assert it's found and closed by the abandoned
+/// handshakes cleanup.
+pub async fn ibc_abort_abandoned_handshake<A, B>(chain_a: &mut A, chain_b: &mut B)
+where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	let (handle, _, _, connection_id_a, _) =
+		setup_connection_and_channel(chain_a, chain_b, Duration::from_secs(0)).await;
+	// Without a relayer the handshake never progresses past INIT
+	handle.abort();
+
+	let channel = ChannelEnd::new(
+		State::Init,
+		Order::Unordered,
+		Counterparty::new(PortId::transfer(), None),
+		vec![connection_id_a],
+		Version::new(VERSION.to_string()),
+	);
+	let msg = MsgChannelOpenInit::new(PortId::transfer(), channel, chain_a.account_id());
+	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec().unwrap() };
+	let tx_id = chain_a.submit(vec![msg]).await.unwrap();
+	let channel_id = chain_a.query_channel_id_from_tx_hash(tx_id).await.unwrap();
+
+	let abandoned = find_abandoned_handshakes(chain_a, chain_b, Duration::ZERO).await.unwrap();
+	let handshake =
+		AbandonedHandshake::Channel { channel_id, port_id: PortId::transfer(), state: State::Init };
+	assert!(abandoned.contains(&handshake), "{handshake} wasn't found in {abandoned:?}");
+
+	let closed = abort_handshakes(chain_a, vec![handshake.clone()]).await;
+	assert_eq!(closed, vec![handshake]);
+
+	let (latest_height, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
+	let channel_end = chain_a
+		.query_channel_end(latest_height, channel_id, PortId::transfer())
+		.await
+		.unwrap()
+		.channel
+		.unwrap();
+	let channel_end = ChannelEnd::try_from(channel_end).unwrap();
+	assert_eq!(channel_end.state, State::Closed);
+	log::info!(target: "hyperspace", "🚀🚀 Abandoned channel handshake successfully closed");
+}
+
 pub async fn client_synchronization_test<A, B>(chain_a: &mut A, chain_b: &mut B)
 where
 	A: TestProvider,
@@ -78,6 +78,9 @@ async fn setup_clients() -> (AnyChain, AnyChain) {
 		para_id: args.para_id,
 		parachain_rpc_url: args.chain_a,
 		relay_chain_rpc_url: args.relay_chain.clone(),
+		secondary_relay_chain_rpc_url: None,
+		share_relay_chain_connection: false,
+		standalone: false,
 		client_id: None,
 		connection_id: None,
 		commitment_prefix: args.connection_prefix_a.as_bytes().to_vec().into(),
@@ -86,7 +89,34 @@ async fn setup_clients() -> (AnyChain, AnyChain) {
 		finality_protocol: FinalityProtocol::Grandpa,
 		private_key: "//Alice".to_string(),
 		key_type: "sr25519".to_string(),
+		remote_signer: None,
 		wasm_code_id: None,
+		common: CommonClientConfig {
+			skip_optional_client_updates: true,
+			idle_mode: false,
+			max_packets_to_process: 100,
+			max_packets_in_flight: None,
+			max_value_in_flight: Default::default(),
+			batch_limits: Default::default(),
+			message_priority: Default::default(),
+			retry_policy: Default::default(),
+			min_balance: None,
+			submission_rate_limit: Default::default(),
+			sweep_interval_secs: None,
+			pause_below_balance: None,
+			proof_concurrency: None,
+			target_height_updates: false,
+			skip_delivered_check: false,
+			fee_ceiling: Default::default(),
+			timeout_scan_interval_secs: None,
+			finality_confirmations: Default::default(),
+			fee_payee: None,
+		},
+		signed_extensions: Default::default(),
+		batch_mode: None,
+		warp_sync: false,
+		relay_chain_fallback_rpc_urls: vec![],
+		dynamic_metadata: false,
 	};
 
 	let mut config_b = CosmosClientConfig {
@@ -94,23 +124,64 @@ async fn setup_clients() -> (AnyChain, AnyChain) {
 		rpc_url: args.chain_b.clone().parse().unwrap(),
 		grpc_url: args.cosmos_grpc.clone().parse().unwrap(),
 		websocket_url: args.cosmos_ws.clone().parse().unwrap(),
+		fallback_endpoints: vec![],
+		comet_version: None,
+		ibc_go_version: None,
+		grpc_pool: vec![],
+		rest_url: None,
+		rpc_rate_limit: None,
+		grpc_rate_limit: None,
+		backfill_from_height: None,
 		chain_id: "ibcgo-1".to_string(),
 		client_id: None,
 		connection_id: None,
 		account_prefix: "cosmos".to_string(),
 		fee_denom: "stake".to_string(),
 		fee_amount: "4000".to_string(),
+		alternative_fee_tokens: vec![],
 		gas_limit: (i64::MAX - 1) as u64,
+		fee_granter: None,
+		dynamic_gas_price: None,
+		gas_adjustment: None,
+		authz_granter: None,
 		store_prefix: args.connection_prefix_b,
 		max_tx_size: 200000,
+		broadcast_mode: Default::default(),
 		mnemonic:
 			"oxygen fall sure lava energy veteran enroll frown question detail include maximum"
 				.to_string(),
+		key_type: Default::default(),
+		multisig: None,
+		remote_signer: None,
+		ledger: None,
 		wasm_code_id: None,
+		trusting_period_secs: None,
+		trusting_period_fraction: None,
+		memo_template: None,
+		ccv_consumer: false,
+		light_store: Default::default(),
+		light_store_snapshot: None,
 		channel_whitelist: vec![],
 		common: CommonClientConfig {
 			skip_optional_client_updates: true,
+			idle_mode: false,
 			max_packets_to_process: 200,
+			max_packets_in_flight: None,
+			max_value_in_flight: Default::default(),
+			batch_limits: Default::default(),
+			message_priority: Default::default(),
+			retry_policy: Default::default(),
+			min_balance: None,
+			submission_rate_limit: Default::default(),
+			sweep_interval_secs: None,
+			pause_below_balance: None,
+			proof_concurrency: None,
+			target_height_updates: false,
+			skip_delivered_check: false,
+			fee_ceiling: Default::default(),
+			timeout_scan_interval_secs: None,
+			finality_confirmations: Default::default(),
+			fee_payee: None,
 		},
 		skip_tokens_list: Some(vec!["uosmo".to_string()]),
 	};
@@ -17,9 +17,9 @@ use hyperspace_core::{logging, substrate::DefaultConfig};
 use hyperspace_parachain::{
 	finality_protocol::FinalityProtocol, ParachainClient, ParachainClientConfig,
 };
-use hyperspace_primitives::{utils::create_clients, IbcProvider, TestProvider};
+use hyperspace_primitives::{utils::create_clients, CommonClientConfig, IbcProvider, TestProvider};
 use hyperspace_testsuite::{
-	client_synchronization_test, ibc_channel_close,
+	client_synchronization_test, ibc_abort_abandoned_handshake, ibc_channel_close,
 	ibc_messaging_packet_height_timeout_with_connection_delay,
 	ibc_messaging_packet_timeout_on_channel_close,
 	ibc_messaging_packet_timestamp_timeout_with_connection_delay,
@@ -65,6 +65,9 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 		para_id: args.para_id_a,
 		parachain_rpc_url: args.chain_a,
 		relay_chain_rpc_url: args.relay_chain.clone(),
+		secondary_relay_chain_rpc_url: None,
+		share_relay_chain_connection: false,
+		standalone: false,
 		client_id: None,
 		connection_id: None,
 		commitment_prefix: args.connection_prefix_b.as_bytes().to_vec().into(),
@@ -73,13 +76,43 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 		finality_protocol: FinalityProtocol::Grandpa,
 		private_key: "//Alice".to_string(),
 		key_type: "sr25519".to_string(),
+		remote_signer: None,
 		wasm_code_id: None,
+		common: CommonClientConfig {
+			skip_optional_client_updates: true,
+			idle_mode: false,
+			max_packets_to_process: 100,
+			max_packets_in_flight: None,
+			max_value_in_flight: Default::default(),
+			batch_limits: Default::default(),
+			message_priority: Default::default(),
+			retry_policy: Default::default(),
+			min_balance: None,
+			submission_rate_limit: Default::default(),
+			sweep_interval_secs: None,
+			pause_below_balance: None,
+			proof_concurrency: None,
+			target_height_updates: false,
+			skip_delivered_check: false,
+			fee_ceiling: Default::default(),
+			timeout_scan_interval_secs: None,
+			finality_confirmations: Default::default(),
+			fee_payee: None,
+		},
+		signed_extensions: Default::default(),
+		batch_mode: None,
+		warp_sync: false,
+		relay_chain_fallback_rpc_urls: vec![],
+		dynamic_metadata: false,
 	};
 	let config_b = ParachainClientConfig {
 		name: "9188".to_string(),
 		para_id: args.para_id_b,
 		parachain_rpc_url: args.chain_b,
 		relay_chain_rpc_url: args.relay_chain,
+		secondary_relay_chain_rpc_url: None,
+		share_relay_chain_connection: false,
+		standalone: false,
 		client_id: None,
 		connection_id: None,
 		commitment_prefix: args.connection_prefix_b.as_bytes().to_vec().into(),
@@ -88,7 +121,34 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 		channel_whitelist: vec![],
 		finality_protocol: FinalityProtocol::Grandpa,
 		key_type: "sr25519".to_string(),
+		remote_signer: None,
 		wasm_code_id: None,
+		common: CommonClientConfig {
+			skip_optional_client_updates: true,
+			idle_mode: false,
+			max_packets_to_process: 100,
+			max_packets_in_flight: None,
+			max_value_in_flight: Default::default(),
+			batch_limits: Default::default(),
+			message_priority: Default::default(),
+			retry_policy: Default::default(),
+			min_balance: None,
+			submission_rate_limit: Default::default(),
+			sweep_interval_secs: None,
+			pause_below_balance: None,
+			proof_concurrency: None,
+			target_height_updates: false,
+			skip_delivered_check: false,
+			fee_ceiling: Default::default(),
+			timeout_scan_interval_secs: None,
+			finality_confirmations: Default::default(),
+			fee_payee: None,
+		},
+		signed_extensions: Default::default(),
+		batch_mode: None,
+		warp_sync: false,
+		relay_chain_fallback_rpc_urls: vec![],
+		dynamic_metadata: false,
 	};
 
 	let mut chain_a = ParachainClient::<DefaultConfig>::new(config_a).await.unwrap();
@@ -204,6 +264,9 @@ async fn parachain_to_parachain_ibc_messaging_full_integration_test() {
 		res.unwrap();
 	}
 
+	// cleanup of the handshakes left in INIT
+	ibc_abort_abandoned_handshake(&mut chain_a, &mut chain_b).await;
+
 	// Test sync abilities, run this before misbehaviour test
 	client_synchronization_test(&mut chain_a, &mut chain_b).await;
 
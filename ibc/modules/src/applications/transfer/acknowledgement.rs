@@ -15,7 +15,7 @@
 use super::error::Error;
 use crate::prelude::*;
 use core::{
-	fmt::{Display, Formatter},
+	fmt::{Display, Formatter, Write},
 	str::FromStr,
 };
 
@@ -26,7 +26,13 @@ use serde::{Deserialize, Serialize};
 pub const ACK_ERR_STR: &str = "error handling packet on destination chain: see events for details";
 pub const ACK_SUCCESS_B64: &str = "AQ==";
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Acknowledgement of an ICS20 packet, encoded as the JSON of the `Acknowledgement` message of
+/// ibc-go's channel module.
+///
+/// The commitment of an acknowledgement is the hash of its encoded bytes, so acknowledgements must
+/// always be encoded with [`Acknowledgement::to_canonical_bytes`] to hash identically on every
+/// implementation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum Acknowledgement {
 	Result(String),
@@ -52,13 +58,56 @@ impl Acknowledgement {
 			Self::Error(e) => Err(e),
 		}
 	}
+
+	/// Encodes the acknowledgement the way ibc-go does, i.e. as the output of Go's
+	/// `json.Marshal`, which escapes `<`, `>`, `&`, U+2028 and U+2029 on top of the characters
+	/// escaped by `serde_json`.
+	pub fn to_canonical_bytes(&self) -> Vec<u8> {
+		self.to_string().into_bytes()
+	}
+
+	/// Decodes an acknowledgement, which may have been encoded by any implementation.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		serde_json::from_slice(bytes)
+			.map_err(|_e| Error::implementation_specific("could not parse acknowledgement".into()))
+	}
+
+	/// Returns `true` if the bytes are the canonical encoding of an acknowledgement, i.e. they
+	/// hash to the same commitment as the acknowledgement written by ibc-go.
+	pub fn is_canonical(bytes: &[u8]) -> bool {
+		Self::from_bytes(bytes)
+			.map(|ack| ack.to_canonical_bytes() == bytes)
+			.unwrap_or(false)
+	}
+}
+
+/// Writes a JSON string like Go's `json.Marshal` does.
+fn write_json_string(f: &mut Formatter<'_>, s: &str) -> core::fmt::Result {
+	f.write_char('"')?;
+	for c in s.chars() {
+		match c {
+			'"' => f.write_str("\\\"")?,
+			'\\' => f.write_str("\\\\")?,
+			'\n' => f.write_str("\\n")?,
+			'\r' => f.write_str("\\r")?,
+			'\t' => f.write_str("\\t")?,
+			'<' | '>' | '&' | '\u{2028}' | '\u{2029}' => write!(f, "\\u{:04x}", c as u32)?,
+			c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+			c => f.write_char(c)?,
+		}
+	}
+	f.write_char('"')
 }
 
 impl Display for Acknowledgement {
 	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-		serde_json::to_string(self)
-			.map_err(|_| core::fmt::Error)
-			.and_then(|s| write!(f, "{}", s))
+		let (key, value) = match self {
+			Self::Result(r) => ("result", r),
+			Self::Error(e) => ("error", e),
+		};
+		write!(f, "{{\"{key}\":")?;
+		write_json_string(f, value)?;
+		f.write_char('}')
 	}
 }
 
@@ -66,7 +115,91 @@ impl FromStr for Acknowledgement {
 	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		serde_json::from_str(s)
-			.map_err(|_e| Error::implementation_specific("could not parse acknowledgement".into()))
+		Self::from_bytes(s.as_bytes())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+	use sha2::{Digest, Sha256};
+
+	/// Acknowledgements, their encoding by ibc-go and the hex encoded sha256 of the encoding,
+	/// which is the acknowledgement commitment stored by ibc-go.
+	const FIXTURES: &[(&str, &str, &str)] = &[
+		(
+			"AQ==",
+			r#"{"result":"AQ=="}"#,
+			"08f7557ed51826fe18d84512bf24ec75001edbaf2123a477df72a0a9f3640a7c",
+		),
+		(
+			"ABCI code: 1: error handling packet: see events for details",
+			r#"{"error":"ABCI code: 1: error handling packet: see events for details"}"#,
+			"439dd0ea54f168850977fce615993ac34f8e2d238c76c0c9328a744e89a2230d",
+		),
+		(
+			ACK_ERR_STR,
+			r#"{"error":"error handling packet on destination chain: see events for details"}"#,
+			"84ba0a2ab55b15a58bb6481f6d5066edf3e22d84d672a67fe7b9da39bde30b33",
+		),
+		(
+			"<nil> & \"quoted\"",
+			r#"{"error":"\u003cnil\u003e \u0026 \"quoted\""}"#,
+			"0978cd51f8c2ed359c9f5f76604153ec2baaf6fd50dcd8e7c56f07e63bcbd54d",
+		),
+		(
+			"line\nbreak\ttab \u{1b} é \u{2028}",
+			r#"{"error":"line\nbreak\ttab \u001b é \u2028"}"#,
+			"df088b5137723170ec02b24b036867ff39a5ab3ffea175b78951798d63316140",
+		),
+	];
+
+	fn fixture_ack(i: usize, value: &str) -> Acknowledgement {
+		if i == 0 {
+			Acknowledgement::Result(value.to_string())
+		} else {
+			Acknowledgement::Error(value.to_string())
+		}
+	}
+
+	#[test]
+	fn canonical_encoding_matches_ibc_go() {
+		for (i, (value, encoded, commitment)) in FIXTURES.iter().enumerate() {
+			let ack = fixture_ack(i, value);
+			let bytes = ack.to_canonical_bytes();
+			assert_eq!(bytes, encoded.as_bytes());
+			assert_eq!(hex::encode(Sha256::digest(&bytes)), *commitment);
+			assert_eq!(Acknowledgement::from_bytes(&bytes).unwrap(), ack);
+			assert!(Acknowledgement::is_canonical(&bytes));
+		}
+		assert_eq!(Acknowledgement::success().to_canonical_bytes(), FIXTURES[0].1.as_bytes());
+	}
+
+	#[test]
+	fn non_canonical_encodings_are_detected() {
+		assert!(!Acknowledgement::is_canonical(br#"{ "result": "AQ==" }"#));
+		assert!(!Acknowledgement::is_canonical(br#"{"error":"<nil>"}"#));
+		assert!(!Acknowledgement::is_canonical(b"not an acknowledgement"));
+	}
+
+	proptest! {
+		#[test]
+		fn canonical_encoding_round_trips(value in any::<String>(), is_error in any::<bool>()) {
+			let ack = if is_error {
+				Acknowledgement::Error(value)
+			} else {
+				Acknowledgement::Result(value)
+			};
+			let bytes = ack.to_canonical_bytes();
+			prop_assert_eq!(Acknowledgement::from_bytes(&bytes).unwrap(), ack.clone());
+			prop_assert!(Acknowledgement::is_canonical(&bytes));
+			// the canonical encoding is the same JSON value as the `serde_json` encoding
+			prop_assert_eq!(
+				serde_json::from_slice::<serde_json::Value>(&bytes).unwrap(),
+				serde_json::to_value(&ack).unwrap()
+			);
+			prop_assert!(!bytes.iter().any(|b| matches!(b, b'<' | b'>' | b'&')));
+		}
 	}
 }
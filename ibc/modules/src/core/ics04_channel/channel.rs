@@ -331,6 +331,10 @@ pub enum State {
 	TryOpen = 2,
 	Open = 3,
 	Closed = 4,
+	/// The channel accepted an upgrade and is flushing its in-flight packets.
+	Flushing = 5,
+	/// The channel flushed its in-flight packets and waits for the upgrade to be opened.
+	FlushComplete = 6,
 }
 
 impl State {
@@ -341,6 +345,8 @@ impl State {
 			Self::TryOpen => "TRYOPEN",
 			Self::Open => "OPEN",
 			Self::Closed => "CLOSED",
+			Self::Flushing => "FLUSHING",
+			Self::FlushComplete => "FLUSHCOMPLETE",
 		}
 	}
 
@@ -351,6 +357,8 @@ impl State {
 			2 => Ok(Self::TryOpen),
 			3 => Ok(Self::Open),
 			4 => Ok(Self::Closed),
+			5 => Ok(Self::Flushing),
+			6 => Ok(Self::FlushComplete),
 			_ => Err(Error::unknown_state(s)),
 		}
 	}
@@ -38,6 +38,9 @@ pub const PORT_ID_ATTRIBUTE_KEY: &str = "port_id";
 pub const COUNTERPARTY_CHANNEL_ID_ATTRIBUTE_KEY: &str = "counterparty_channel_id";
 pub const COUNTERPARTY_PORT_ID_ATTRIBUTE_KEY: &str = "counterparty_port_id";
 
+/// Channel upgrade event attribute keys
+pub const UPGRADE_SEQUENCE_ATTRIBUTE_KEY: &str = "upgrade_sequence";
+
 /// Packet event attribute keys
 pub const PKT_SEQ_ATTRIBUTE_KEY: &str = "packet_sequence";
 pub const PKT_DATA_ATTRIBUTE_KEY: &str = "packet_data";
@@ -81,6 +84,21 @@ pub fn try_from_tx(event: &tendermint::abci::Event) -> Option<IbcEvent> {
 			.map(|res| res.ok().map(IbcEvent::CloseConfirmChannel))
 			.ok()
 			.flatten(),
+		Ok(IbcEventType::UpgradeInitChannel) => extract_upgrade_attributes_from_tx(event)
+			.map(|attrs| IbcEvent::UpgradeInitChannel(UpgradeInit(attrs)))
+			.ok(),
+		Ok(IbcEventType::UpgradeTryChannel) => extract_upgrade_attributes_from_tx(event)
+			.map(|attrs| IbcEvent::UpgradeTryChannel(UpgradeTry(attrs)))
+			.ok(),
+		Ok(IbcEventType::UpgradeAckChannel) => extract_upgrade_attributes_from_tx(event)
+			.map(|attrs| IbcEvent::UpgradeAckChannel(UpgradeAck(attrs)))
+			.ok(),
+		Ok(IbcEventType::UpgradeConfirmChannel) => extract_upgrade_attributes_from_tx(event)
+			.map(|attrs| IbcEvent::UpgradeConfirmChannel(UpgradeConfirm(attrs)))
+			.ok(),
+		Ok(IbcEventType::UpgradeOpenChannel) => extract_upgrade_attributes_from_tx(event)
+			.map(|attrs| IbcEvent::UpgradeOpenChannel(UpgradeOpen(attrs)))
+			.ok(),
 		Ok(IbcEventType::SendPacket) => {
 			extract_packet_and_write_ack_from_tx(event)
 				.map(|(packet, write_ack)| {
@@ -151,6 +169,36 @@ fn extract_attributes_from_tx(event: &tendermint::abci::Event) -> Result<Attribu
 	Ok(attr)
 }
 
+pub fn extract_upgrade_attributes_from_tx(
+	event: &tendermint::abci::Event,
+) -> Result<UpgradeAttributes, Error> {
+	let mut attr = UpgradeAttributes::default();
+
+	for tag in &event.attributes {
+		let key = tag.key.as_str();
+		let value = tag.value.as_str();
+		match key {
+			PORT_ID_ATTRIBUTE_KEY => attr.port_id = value.parse().map_err(Error::identifier)?,
+			CHANNEL_ID_ATTRIBUTE_KEY =>
+				attr.channel_id = value.parse().map_err(Error::identifier)?,
+			COUNTERPARTY_PORT_ID_ATTRIBUTE_KEY => {
+				attr.counterparty_port_id = value.parse().map_err(Error::identifier)?;
+			},
+			COUNTERPARTY_CHANNEL_ID_ATTRIBUTE_KEY => {
+				attr.counterparty_channel_id = value.parse().ok();
+			},
+			UPGRADE_SEQUENCE_ATTRIBUTE_KEY => {
+				attr.upgrade_sequence = value.parse().map_err(|_| {
+					Error::implementation_specific("parse upgrade_sequence error".to_string())
+				})?;
+			},
+			_ => {},
+		}
+	}
+
+	Ok(attr)
+}
+
 fn extract_packet_and_write_ack_from_tx(
 	event: &tendermint::abci::Event,
 ) -> Result<(Packet, Vec<u8>), Error> {
@@ -750,6 +798,114 @@ macro_rules! impl_try_from_raw_obj_for_event {
 
 impl_try_from_raw_obj_for_event!(OpenInit, OpenTry, OpenAck, OpenConfirm, CloseInit, CloseConfirm);
 
+/// Attributes of the channel upgrade events, emitted on the chain of the upgraded channel.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UpgradeAttributes {
+	pub height: Height,
+	pub port_id: PortId,
+	pub channel_id: ChannelId,
+	pub counterparty_port_id: PortId,
+	pub counterparty_channel_id: Option<ChannelId>,
+	pub upgrade_sequence: u64,
+}
+
+/// Convert upgrade attributes to Tendermint ABCI tags
+impl From<UpgradeAttributes> for Vec<EventAttribute> {
+	fn from(a: UpgradeAttributes) -> Self {
+		let mut attributes = vec![
+			EventAttribute {
+				key: HEIGHT_ATTRIBUTE_KEY.to_string(),
+				value: a.height.to_string(),
+				index: false,
+			},
+			EventAttribute {
+				key: PORT_ID_ATTRIBUTE_KEY.to_string(),
+				value: a.port_id.to_string(),
+				index: false,
+			},
+			EventAttribute {
+				key: CHANNEL_ID_ATTRIBUTE_KEY.to_string(),
+				value: a.channel_id.to_string(),
+				index: false,
+			},
+			EventAttribute {
+				key: COUNTERPARTY_PORT_ID_ATTRIBUTE_KEY.to_string(),
+				value: a.counterparty_port_id.to_string(),
+				index: false,
+			},
+		];
+		if let Some(channel_id) = a.counterparty_channel_id {
+			attributes.push(EventAttribute {
+				key: COUNTERPARTY_CHANNEL_ID_ATTRIBUTE_KEY.to_string(),
+				value: channel_id.to_string(),
+				index: false,
+			});
+		}
+		attributes.push(EventAttribute {
+			key: UPGRADE_SEQUENCE_ATTRIBUTE_KEY.to_string(),
+			value: a.upgrade_sequence.to_string(),
+			index: false,
+		});
+		attributes
+	}
+}
+
+macro_rules! impl_upgrade_event {
+    ($($event:ident => $variant:ident),+) => {
+        $(#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+        pub struct $event(pub UpgradeAttributes);
+
+        impl $event {
+            pub fn attributes(&self) -> &UpgradeAttributes {
+                &self.0
+            }
+            pub fn channel_id(&self) -> &ChannelId {
+                &self.0.channel_id
+            }
+            pub fn port_id(&self) -> &PortId {
+                &self.0.port_id
+            }
+            pub fn height(&self) -> Height {
+                self.0.height
+            }
+            pub fn set_height(&mut self, height: Height) {
+                self.0.height = height;
+            }
+        }
+
+        impl From<$event> for IbcEvent {
+            fn from(v: $event) -> Self {
+                IbcEvent::$variant(v)
+            }
+        }
+
+        impl EventType for $event {
+            fn event_type() -> IbcEventType {
+                IbcEventType::$variant
+            }
+        }
+
+        impl From<$event> for AbciEvent {
+            fn from(v: $event) -> Self {
+                let attributes = Vec::<EventAttribute>::from(v.0);
+                let kind = <$event>::event_type().as_str().to_string();
+                AbciEvent {
+                    kind,
+                    attributes,
+                }
+            }
+        })+
+    };
+}
+
+impl_upgrade_event!(
+	UpgradeInit => UpgradeInitChannel,
+	UpgradeTry => UpgradeTryChannel,
+	UpgradeAck => UpgradeAckChannel,
+	UpgradeConfirm => UpgradeConfirmChannel,
+	UpgradeOpen => UpgradeOpenChannel
+);
+
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct SendPacket {
 	pub height: Height,
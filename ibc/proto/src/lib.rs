@@ -69,6 +69,11 @@ pub mod cosmos {
 			include_proto!("cosmos.staking.v1beta1.rs");
 		}
 	}
+	pub mod authz {
+		pub mod v1beta1 {
+			include_proto!("cosmos.authz.v1beta1.rs");
+		}
+	}
 	pub mod bank {
 		pub mod v1beta1 {
 			include_proto!("cosmos.bank.v1beta1.rs");
@@ -149,6 +154,11 @@ pub mod ibc {
 		pub use super::applications::*;
 	}
 	pub mod applications {
+		pub mod fee {
+			pub mod v1 {
+				include_proto!("ibc.applications.fee.v1.rs");
+			}
+		}
 		pub mod transfer {
 			pub mod v1 {
 				include_proto!("ibc.applications.transfer.v1.rs");
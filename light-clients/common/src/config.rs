@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::extrinsic_params::SignedExtensionsConfig;
 use alloc::borrow::Cow;
 use async_trait::async_trait;
 use codec::{Decode, Encode};
@@ -288,9 +289,10 @@ pub trait IbcEventsT {
 }
 
 /// This allows end users of this crate return the correct extrinsic metadata required by their
-/// runtimes into the transactions signed by this crate.
+/// runtimes into the transactions signed by this crate. The nonces are `u32`s on all the
+/// supported runtimes, which the relayer relies on to reserve them.
 #[async_trait]
-pub trait Config: subxt::Config + Sized {
+pub trait Config: subxt::Config<Index = u32> + Sized {
 	/// Asset Id type used by the parachain runtime
 	type AssetId: codec::Codec + serde::Serialize + Send + Sync + 'static;
 	/// the signature type of the runtime
@@ -318,6 +320,16 @@ pub trait Config: subxt::Config + Sized {
 	async fn custom_extrinsic_params(
 		client: &OnlineClient<Self>,
 	) -> Result<CustomExtrinsicParams<Self>, Error>;
+
+	/// The extrinsic params for the signed extensions of the chain, as configured by the relayer.
+	/// Defaults to [`Config::custom_extrinsic_params`], for the configs whose extrinsic params
+	/// encode a fixed set of signed extensions.
+	async fn extrinsic_params(
+		client: &OnlineClient<Self>,
+		_signed_extensions: &SignedExtensionsConfig,
+	) -> Result<CustomExtrinsicParams<Self>, Error> {
+		Self::custom_extrinsic_params(client).await
+	}
 }
 
 pub type CustomExtrinsicParams<T> = <<T as subxt::Config>::ExtrinsicParams as ExtrinsicParams<
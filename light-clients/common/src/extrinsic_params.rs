@@ -0,0 +1,236 @@
+// Copyright (C) 2022 ComposableFi.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Extrinsic params encoding the signed extensions the metadata of the chain lists.
+//!
+//! The extrinsic params of `subxt` encode a fixed set of signed extensions, which must match the
+//! `SignedExtra` of the runtime. [`MetadataExtrinsicParams`] instead encode the signed extensions
+//! in the order the metadata lists them: the extensions of the FRAME pallets are filled in by the
+//! relayer, the ones with no data are skipped, and the data of the others is configured with
+//! [`SignedExtensionsConfig`].
+
+use alloc::collections::BTreeMap;
+use codec::{Compact, Encode};
+use core::marker::PhantomData;
+use serde::{Deserialize, Serialize};
+use sp_core::Bytes;
+use subxt::{
+	config::{extrinsic_params::Era, ExtrinsicParams},
+	ext::scale_encode::EncodeAsType,
+	metadata::Metadata,
+	Error,
+};
+
+/// Configuration of the signed extensions of a chain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignedExtensionsConfig {
+	/// SCALE-encoded id of the asset `ChargeAssetTxPayment` pays the fees in, the native asset if
+	/// unset
+	#[serde(default)]
+	pub fee_asset_id: Option<Bytes>,
+	/// SCALE-encoded data of the signed extensions the relayer doesn't know, by identifier
+	#[serde(default)]
+	pub custom: BTreeMap<String, CustomSignedExtension>,
+}
+
+/// SCALE-encoded data of a signed extension.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomSignedExtension {
+	/// Data included in the extrinsic
+	#[serde(default)]
+	pub extra: Bytes,
+	/// Data only included in the signed payload
+	#[serde(default)]
+	pub additional: Bytes,
+}
+
+/// A signed extension, and the data the relayer encodes for it.
+#[derive(Debug, Clone)]
+enum SignedExtension {
+	/// An extension with no data, e.g. `CheckNonZeroSender` or `CheckWeight`
+	Empty,
+	SpecVersion,
+	TxVersion,
+	Genesis,
+	Mortality,
+	Nonce,
+	Tip,
+	AssetTip {
+		asset_id: Option<Vec<u8>>,
+	},
+	/// `CheckMetadataHash`, always disabled
+	MetadataHash,
+	Custom(CustomSignedExtension),
+}
+
+impl SignedExtension {
+	/// The extensions of the Polkadot runtimes, which the `subxt` extrinsic params encode.
+	fn polkadot() -> Vec<Self> {
+		vec![
+			Self::Empty,
+			Self::SpecVersion,
+			Self::TxVersion,
+			Self::Genesis,
+			Self::Mortality,
+			Self::Nonce,
+			Self::Empty,
+			Self::Tip,
+		]
+	}
+}
+
+/// Values of the signed extensions [`MetadataExtrinsicParams`] encode.
+#[derive(Debug, Clone)]
+pub struct MetadataExtrinsicParamsBuilder<Hash> {
+	/// The extensions of the chain, in the order of its metadata. The ones of the Polkadot
+	/// runtimes until resolved from the metadata
+	extensions: Option<Vec<SignedExtension>>,
+	era: Era,
+	mortality_checkpoint: Option<Hash>,
+	tip: u128,
+}
+
+impl<Hash> Default for MetadataExtrinsicParamsBuilder<Hash> {
+	fn default() -> Self {
+		Self { extensions: None, era: Era::Immortal, mortality_checkpoint: None, tip: 0 }
+	}
+}
+
+impl<Hash> MetadataExtrinsicParamsBuilder<Hash> {
+	/// Resolves the signed extensions listed in the metadata. Fails if the metadata lists an
+	/// extension with data that the relayer doesn't know and that isn't configured.
+	pub fn from_metadata(
+		metadata: &Metadata,
+		config: &SignedExtensionsConfig,
+	) -> Result<Self, Error> {
+		let extensions = metadata
+			.extrinsic()
+			.signed_extensions()
+			.iter()
+			.map(|extension| {
+				let identifier = extension.identifier();
+				if let Some(custom) = config.custom.get(identifier) {
+					return Ok(SignedExtension::Custom(custom.clone()))
+				}
+				Ok(match identifier {
+					"CheckSpecVersion" => SignedExtension::SpecVersion,
+					"CheckTxVersion" => SignedExtension::TxVersion,
+					"CheckGenesis" => SignedExtension::Genesis,
+					"CheckMortality" | "CheckEra" => SignedExtension::Mortality,
+					"CheckNonce" => SignedExtension::Nonce,
+					"ChargeTransactionPayment" => SignedExtension::Tip,
+					"ChargeAssetTxPayment" => SignedExtension::AssetTip {
+						asset_id: config.fee_asset_id.clone().map(|asset_id| asset_id.0),
+					},
+					"CheckMetadataHash" => SignedExtension::MetadataHash,
+					_ if is_empty(metadata, extension.extra_ty()) &&
+						is_empty(metadata, extension.additional_ty()) =>
+						SignedExtension::Empty,
+					_ =>
+						return Err(Error::Other(format!(
+							"Unknown signed extension {identifier}, its data must be configured in `signed_extensions.custom`"
+						))),
+				})
+			})
+			.collect::<Result<_, Error>>()?;
+		Ok(Self { extensions: Some(extensions), ..Default::default() })
+	}
+
+	/// Sets the mortality of the extrinsic, with the hash of the block it starts at.
+	pub fn era(mut self, era: Era, checkpoint: Hash) -> Self {
+		self.era = era;
+		self.mortality_checkpoint = Some(checkpoint);
+		self
+	}
+
+	/// Sets the tip of the block author.
+	pub fn tip(mut self, tip: u128) -> Self {
+		self.tip = tip;
+		self
+	}
+}
+
+/// Returns `true` if the type has no data, i.e. the unit value encodes as it.
+fn is_empty(metadata: &Metadata, type_id: u32) -> bool {
+	().encode_as_type(type_id, metadata.types())
+		.map_or(false, |encoded| encoded.is_empty())
+}
+
+/// Extrinsic params encoding the signed extensions of the chain, see the [module
+/// documentation](self).
+#[derive(Debug)]
+pub struct MetadataExtrinsicParams<T: subxt::Config> {
+	extra: Vec<u8>,
+	additional: Vec<u8>,
+	_marker: PhantomData<T>,
+}
+
+impl<T: subxt::Config> ExtrinsicParams<T::Index, T::Hash> for MetadataExtrinsicParams<T> {
+	type OtherParams = MetadataExtrinsicParamsBuilder<T::Hash>;
+
+	fn new(
+		spec_version: u32,
+		tx_version: u32,
+		nonce: T::Index,
+		genesis_hash: T::Hash,
+		other_params: Self::OtherParams,
+	) -> Self {
+		let MetadataExtrinsicParamsBuilder { extensions, era, mortality_checkpoint, tip } =
+			other_params;
+		let (mut extra, mut additional) = (vec![], vec![]);
+		for extension in extensions.unwrap_or_else(SignedExtension::polkadot) {
+			match extension {
+				SignedExtension::Empty => (),
+				SignedExtension::SpecVersion => spec_version.encode_to(&mut additional),
+				SignedExtension::TxVersion => tx_version.encode_to(&mut additional),
+				SignedExtension::Genesis => genesis_hash.encode_to(&mut additional),
+				SignedExtension::Mortality => {
+					era.encode_to(&mut extra);
+					mortality_checkpoint.unwrap_or(genesis_hash).encode_to(&mut additional);
+				},
+				SignedExtension::Nonce => Compact(nonce.into()).encode_to(&mut extra),
+				SignedExtension::Tip => Compact(tip).encode_to(&mut extra),
+				SignedExtension::AssetTip { asset_id } => {
+					Compact(tip).encode_to(&mut extra);
+					match asset_id {
+						Some(asset_id) => {
+							extra.push(1);
+							extra.extend(asset_id);
+						},
+						None => extra.push(0),
+					}
+				},
+				SignedExtension::MetadataHash => {
+					// `Mode::Disabled`, and no metadata hash in the signed payload
+					extra.push(0);
+					None::<[u8; 32]>.encode_to(&mut additional);
+				},
+				SignedExtension::Custom(custom) => {
+					extra.extend(custom.extra.0);
+					additional.extend(custom.additional.0);
+				},
+			}
+		}
+		Self { extra, additional, _marker: PhantomData }
+	}
+
+	fn encode_extra_to(&self, v: &mut Vec<u8>) {
+		v.extend_from_slice(&self.extra);
+	}
+
+	fn encode_additional_to(&self, v: &mut Vec<u8>) {
+		v.extend_from_slice(&self.additional);
+	}
+}
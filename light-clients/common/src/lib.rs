@@ -21,7 +21,7 @@
 extern crate alloc;
 extern crate core;
 
-use alloc::{string::ToString, vec, vec::Vec};
+use alloc::{collections::BTreeSet, string::ToString, vec, vec::Vec};
 use anyhow::anyhow;
 use codec::Compact;
 use core::{
@@ -46,6 +46,8 @@ use sp_trie::StorageProof;
 
 #[cfg(feature = "enable-subxt")]
 pub mod config;
+#[cfg(feature = "enable-subxt")]
+pub mod extrinsic_params;
 pub mod state_machine;
 
 /// Host functions that allow the light client perform cryptographic operations in native.
@@ -199,6 +201,24 @@ pub fn decode_timestamp_extrinsic(ext: &Vec<u8>) -> Result<u64, anyhow::Error> {
 	Ok(timestamp.into())
 }
 
+/// Returns the parachain headers to prove for the requested header numbers, among the headers
+/// that were the head of the parachain in some relay chain block.
+///
+/// With asynchronous backing, the parachain blocks are no longer included one per relay chain
+/// block, and the relay chain state only records the head of the parachain as of the latest
+/// included candidate, so a requested header may never be a head the light client can verify.
+/// Such a header is proven through the first included header at or above it, which commits to
+/// its state.
+pub fn included_headers_to_prove(
+	included: &BTreeSet<u32>,
+	requested: impl IntoIterator<Item = u32>,
+) -> BTreeSet<u32> {
+	requested
+		.into_iter()
+		.filter_map(|number| included.range(number..).next().copied())
+		.collect()
+}
+
 /// This will verify that the connection delay has elapsed for a given [`ibc::Height`]
 pub fn verify_delay_passed<H, C>(
 	ctx: &C,
@@ -24,6 +24,7 @@ use codec::Decode;
 use core::marker::PhantomData;
 use finality_grandpa::Chain;
 use grandpa_client_primitives::{
+	is_warp_fragment,
 	justification::{
 		find_forced_change, find_scheduled_change, AncestryChain, GrandpaJustification,
 	},
@@ -224,9 +225,14 @@ where
 
 		let from = client_state.latest_relay_hash;
 
-		let finalized = ancestry
-			.ancestry(from, header.finality_proof.block)
-			.map_err(|_| Error::Custom(format!("[update_state] Invalid ancestry!")))?;
+		let finalized = if is_warp_fragment(&header.finality_proof, &header.parachain_headers) {
+			// the headers between the latest relay block and the fragment aren't known
+			vec![header.finality_proof.block]
+		} else {
+			ancestry
+				.ancestry(from, header.finality_proof.block)
+				.map_err(|_| Error::Custom(format!("[update_state] Invalid ancestry!")))?
+		};
 		let mut finalized_sorted = finalized.clone();
 		finalized_sorted.sort();
 
@@ -241,10 +247,10 @@ where
 				Error::Custom(format!("No relay chain header found for hash: {relay_hash:?}"))
 			})?;
 
-			let (height, consensus_state) = ConsensusState::from_header::<H>(
+			let (height, consensus_state) = ConsensusState::from_finalized_header::<H>(
 				parachain_header_proof,
 				client_state.para_id,
-				header.state_root.clone(),
+				header,
 			)?;
 
 			// Skip duplicate consensus states
@@ -272,7 +278,7 @@ where
 		let mut heights = consensus_states
 			.iter()
 			.map(|(h, ..)| {
-				// this cast is safe, see [`ConsensusState::from_header`]
+				// this cast is safe, see [`ConsensusState::from_finalized_header`]
 				h.revision_height as u32
 			})
 			.collect::<Vec<_>>();
@@ -343,10 +349,10 @@ where
 				return Ok(true)
 			}
 
-			let (height, consensus_state) = ConsensusState::from_header::<H>(
+			let (height, consensus_state) = ConsensusState::from_finalized_header::<H>(
 				parachain_header_proof,
 				client_state.para_id,
-				header.state_root.clone(),
+				header,
 			)?;
 
 			match ctx.maybe_consensus_state(&client_id, height)? {
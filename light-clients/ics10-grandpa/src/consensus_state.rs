@@ -23,8 +23,10 @@ use tendermint_proto::{google::protobuf as tpb, Protobuf};
 
 use crate::proto::ConsensusState as RawConsensusState;
 
-use crate::{alloc::string::ToString, error::Error};
-use grandpa_client_primitives::{parachain_header_storage_key, ParachainHeaderProofs};
+use crate::{alloc::string::ToString, client_message::RelayChainHeader, error::Error};
+use grandpa_client_primitives::{
+	parachain_header_storage_key, ParachainHeaderProofs, STANDALONE_CHAIN_PARA_ID,
+};
 use ibc::{core::ics23_commitment::commitment::CommitmentRoot, timestamp::Timestamp, Height};
 use ibc_proto::google::protobuf::Any;
 use light_client_common::{decode_timestamp_extrinsic, state_machine};
@@ -75,9 +77,33 @@ impl ConsensusState {
 
 		let parachain_header =
 			generic::Header::<u32, BlakeTwo256>::decode(&mut &parachain_header_bytes[..])?;
+		Self::from_parachain_header(&parachain_header, &parachain_header_proof.extrinsic, para_id)
+	}
+
+	/// Consensus state of the header proven in the finalized relay chain block: the head of the
+	/// parachain in its state, or the block itself for the clients of standalone chains.
+	pub fn from_finalized_header<H>(
+		parachain_header_proof: ParachainHeaderProofs,
+		para_id: u32,
+		relay_header: &RelayChainHeader,
+	) -> Result<(Height, Self), Error>
+	where
+		H: grandpa_client_primitives::HostFunctions,
+	{
+		if para_id != STANDALONE_CHAIN_PARA_ID {
+			return Self::from_header::<H>(parachain_header_proof, para_id, relay_header.state_root)
+		}
+		Self::from_parachain_header(relay_header, &parachain_header_proof.extrinsic, para_id)
+	}
+
+	fn from_parachain_header(
+		parachain_header: &generic::Header<u32, BlakeTwo256>,
+		timestamp_extrinsic: &Vec<u8>,
+		para_id: u32,
+	) -> Result<(Height, Self), Error> {
 		let root = parachain_header.state_root.0.to_vec();
 
-		let timestamp = decode_timestamp_extrinsic(&parachain_header_proof.extrinsic)?;
+		let timestamp = decode_timestamp_extrinsic(timestamp_extrinsic)?;
 		let duration = core::time::Duration::from_millis(timestamp);
 		let timestamp = Timestamp::from_nanoseconds(duration.as_nanos().saturated_into::<u64>())?
 			.into_tm_time()
@@ -14,6 +14,7 @@
 // limitations under the License.
 
 use crate::{
+	client_def::GrandpaClient,
 	client_message::{ClientMessage, Header, RelayChainHeader},
 	client_state::ClientState,
 	consensus_state::ConsensusState,
@@ -23,17 +24,19 @@ use crate::{
 };
 use beefy_prover::helpers::{fetch_timestamp_extrinsic_with_proof, TimeStampExtWithProof};
 use codec::{Decode, Encode};
+use finality_grandpa::{Commit, Message, Precommit, SignedPrecommit};
 use finality_grandpa_rpc::GrandpaApiClient;
 use futures::stream::StreamExt;
 use grandpa_client_primitives::{
 	justification::GrandpaJustification, parachain_header_storage_key, FinalityProof,
-	ParachainHeaderProofs, ParachainHeadersWithFinalityProof,
+	HostFunctions as _, ParachainHeaderProofs, ParachainHeadersWithFinalityProof,
 };
 use grandpa_prover::{GrandpaProver, JustificationNotification};
 use hyperspace_core::substrate::DefaultConfig as PolkadotConfig;
 use ibc::{
 	core::{
 		ics02_client::{
+			client_def::{ClientDef, ConsensusUpdateResult},
 			client_state::ClientState as _,
 			context::{ClientKeeper, ClientReader},
 			handler::{dispatch, ClientResult::Update},
@@ -49,8 +52,10 @@ use ibc::{
 	test_utils::get_dummy_account_id,
 	Height,
 };
-use light_client_common::config::RuntimeStorage;
-use sp_core::{hexdisplay::AsBytesRef, H256};
+use light_client_common::{config::RuntimeStorage, RelayChain};
+use sp_consensus_grandpa::{AuthorityList, ConsensusLog, ScheduledChange, GRANDPA_ENGINE_ID};
+use sp_core::{ed25519, hexdisplay::AsBytesRef, Pair, H256};
+use sp_runtime::{generic::DigestItem, traits::Header as _};
 use std::time::Duration;
 use subxt::config::substrate::{BlakeTwo256, SubstrateHeader};
 
@@ -306,3 +311,149 @@ async fn test_continuous_update_of_grandpa_client() {
 		}
 	}
 }
+
+/// The three voters of an authority set.
+fn voters(set: u8) -> Vec<ed25519::Pair> {
+	(0..3).map(|i| ed25519::Pair::from_seed(&[set * 3 + i + 1; 32])).collect()
+}
+
+fn authority_list(voters: &[ed25519::Pair]) -> AuthorityList {
+	voters.iter().map(|pair| (pair.public().into(), 1)).collect()
+}
+
+fn warp_client_state() -> ClientState<HostFunctionsManager> {
+	ClientState {
+		relay_chain: RelayChain::Rococo,
+		latest_relay_height: 100,
+		latest_relay_hash: H256::repeat_byte(2),
+		frozen_height: None,
+		latest_para_height: 10,
+		para_id: 2000,
+		current_set_id: 5,
+		current_authorities: authority_list(&voters(0)),
+		_phantom: Default::default(),
+	}
+}
+
+/// A warp sync fragment of the relay block `number`, enacting the authority set of `next`,
+/// justified by `voters` as the authority set `set_id`.
+fn warp_fragment(
+	number: u32,
+	voters: &[ed25519::Pair],
+	set_id: u64,
+	next: &[ed25519::Pair],
+) -> Header {
+	// the parent isn't known to the light client
+	let mut header = RelayChainHeader::new(
+		number,
+		Default::default(),
+		Default::default(),
+		H256::repeat_byte(1),
+		Default::default(),
+	);
+	let change = ScheduledChange { next_authorities: authority_list(next), delay: 0 };
+	header.digest.push(DigestItem::Consensus(
+		GRANDPA_ENGINE_ID,
+		ConsensusLog::ScheduledChange(change).encode(),
+	));
+
+	let round = 1u64;
+	let precommit = Precommit { target_hash: header.hash(), target_number: number };
+	let precommits = voters
+		.iter()
+		.map(|pair| {
+			let message = Message::<H256, u32>::Precommit(precommit.clone());
+			SignedPrecommit {
+				precommit: precommit.clone(),
+				signature: pair.sign(&(message, round, set_id).encode()).into(),
+				id: pair.public().into(),
+			}
+		})
+		.collect();
+	let justification = GrandpaJustification::<RelayChainHeader> {
+		round,
+		commit: Commit { target_hash: header.hash(), target_number: number, precommits },
+		votes_ancestries: vec![],
+	};
+
+	Header {
+		finality_proof: FinalityProof {
+			block: header.hash(),
+			justification: justification.encode(),
+			unknown_headers: vec![header],
+		},
+		parachain_headers: Default::default(),
+		height: Height::new(2000, 10),
+	}
+}
+
+fn mock_context() -> MockContext<MockClientTypes> {
+	MockContext::<MockClientTypes>::new(
+		ChainId::new("mockgaiaA".to_string(), 1),
+		MockHostType::Mock,
+		5,
+		Height::new(1, 11),
+	)
+}
+
+/// Verifies the header and updates the client state with it.
+fn update(
+	client_state: ClientState<HostFunctionsManager>,
+	header: Header,
+) -> Result<ClientState<HostFunctionsManager>, ibc::core::ics02_client::error::Error> {
+	let ctx = mock_context();
+	let client_id = ClientId::new(&ClientState::<HostFunctionsManager>::client_type(), 0).unwrap();
+	let client = GrandpaClient::<HostFunctionsManager>::default();
+	let message = ClientMessage::Header(header);
+	client.verify_client_message(&ctx, client_id.clone(), client_state.clone(), message.clone())?;
+	let (client_state, consensus_states) =
+		client.update_state(&ctx, client_id, client_state, message)?;
+	// a fragment doesn't finalize any parachain header
+	assert!(matches!(consensus_states, ConsensusUpdateResult::Batch(states) if states.is_empty()));
+	Ok(client_state)
+}
+
+#[test]
+fn updates_the_client_with_a_warp_sync_fragment() {
+	let header = warp_fragment(150, &voters(0), 5, &voters(1));
+	let block = header.finality_proof.block;
+
+	let client_state = update(warp_client_state(), header).expect("the fragment is valid");
+	assert_eq!(client_state.latest_relay_height, 150);
+	assert_eq!(client_state.latest_relay_hash, block);
+	assert_eq!(client_state.latest_para_height, 10);
+	assert_eq!(client_state.current_set_id, 6);
+	assert_eq!(client_state.current_authorities, authority_list(&voters(1)));
+	assert!(HostFunctionsManager::contains_relay_header_hash(block));
+
+	// the client follows the next authority set with the next fragment
+	let header = warp_fragment(200, &voters(1), 6, &voters(2));
+	let client_state = update(client_state, header).expect("the fragment is valid");
+	assert_eq!(client_state.current_set_id, 7);
+	assert_eq!(client_state.current_authorities, authority_list(&voters(2)));
+}
+
+#[test]
+fn rejects_a_warp_sync_fragment_at_or_below_the_latest_relay_block() {
+	for number in [99, 100] {
+		let header = warp_fragment(number, &voters(0), 5, &voters(1));
+		assert!(update(warp_client_state(), header).is_err());
+	}
+}
+
+#[test]
+fn rejects_a_warp_sync_fragment_with_parachain_headers() {
+	let mut header = warp_fragment(150, &voters(0), 5, &voters(1));
+	let proofs =
+		ParachainHeaderProofs { state_proof: vec![], extrinsic: vec![], extrinsic_proof: vec![] };
+	header.parachain_headers.insert(header.finality_proof.block, proofs);
+	assert!(update(warp_client_state(), header).is_err());
+}
+
+#[test]
+fn rejects_a_warp_sync_fragment_justified_by_another_authority_set() {
+	let header = warp_fragment(150, &voters(0), 4, &voters(1));
+	assert!(update(warp_client_state(), header).is_err());
+	let header = warp_fragment(150, &voters(1), 6, &voters(2));
+	assert!(update(warp_client_state(), header).is_err());
+}
@@ -815,6 +815,13 @@ impl pallet_ibc::Config for Runtime {
 	type FlatFeeAssetId = AssetIdUSDT;
 	type FlatFeeAmount = FlatFeeUSDTAmount;
 	type SubstrateMultihopXcmHandler = SubstrateMultihopXcmHandlerNone<Runtime>;
+	type OnClientUpdate = IbcTelemetry;
+}
+
+impl pallet_ibc_telemetry::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type TimeProvider = Timestamp;
+	type WeightInfo = ();
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -851,6 +858,7 @@ construct_runtime!(
 		IbcPing: pallet_ibc_ping = 36,
 		Assets: pallet_assets = 37,
 		AssetRegistry: asset_registry = 38,
+		IbcTelemetry: pallet_ibc_telemetry = 39,
 		// pallet-ibc, should be the last module in your runtime
 		Ibc: pallet_ibc = 255,
 	}
@@ -995,6 +1003,16 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_ibc_telemetry::runtime_api::IbcTelemetryRuntimeApi<Block, AccountId, BlockNumber> for Runtime {
+		fn client_update(client_id: Vec<u8>) -> Option<pallet_ibc_telemetry::ClientUpdateRecord<AccountId, BlockNumber>> {
+			IbcTelemetry::client_update(client_id)
+		}
+
+		fn client_updates() -> Vec<(Vec<u8>, pallet_ibc_telemetry::ClientUpdateRecord<AccountId, BlockNumber>)> {
+			IbcTelemetry::client_updates()
+		}
+	}
+
 	impl ibc_runtime_api::IbcRuntimeApi<Block, AssetId> for Runtime {
 		fn para_id() -> u32 {
 			<Runtime as cumulus_pallet_parachain_system::Config>::SelfParaId::get().into()